@@ -0,0 +1,25 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 随机动作序列的游戏规则模糊测试
+//!
+//! 输入的第一个字节决定玩家数，随后8个字节为随机种子，其余字节按 `RuleAction::decode_all` 解码为动作。
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use nautilus_server::test_support::rules::{RuleAction, RulesHarness};
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&players, rest)) = data.split_first() else {
+        return;
+    };
+    let Some((seed, actions)) = rest.split_first_chunk::<8>() else {
+        return;
+    };
+    let players = 2 + players as usize % 3;
+    let actions = RuleAction::decode_all(actions);
+    if let Err(e) = RulesHarness::run(players, u64::from_le_bytes(*seed), &actions) {
+        panic!("{}", e);
+    }
+});
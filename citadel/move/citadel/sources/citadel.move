@@ -17,6 +17,8 @@ module citadel::citadel {
     use std::bcs;
     use sui::display;
     use sui::package;
+    use sui::dynamic_field;
+    use sui::ed25519;
     
     // 导入 nexus 模块
     use nexus::passport::{Self, Passport};
@@ -36,6 +38,7 @@ module citadel::citadel {
     const ENotLobbyLeader: u64 = 11;
     const ENotAuthorized: u64 = 12;
     const EGameEntryInvalid: u64 = 13;
+    const EInvalidAttestation: u64 = 14;
     
     /// 常量定义
     const INIT_RATING: u64 = 1000;
@@ -87,6 +90,15 @@ module citadel::citadel {
         profile_id: address,
         avatar: String,
     }
+    /// Profile上名称的动态字段键
+    public struct NameKey has copy, drop, store {}
+
+    /// 用户改名事件
+    public struct ProfileRenamed has copy, drop {
+        profile_id: address,
+        name: String,
+        timestamp: u64,
+    }
     public struct ProfileStateUpdated has copy, drop {
         profile_id: address,
         rating: u64,
@@ -285,6 +297,64 @@ module citadel::citadel {
         timestamp: u64,
     }
 
+    // ============= 成就相关数据结构 =============
+
+    /// 成就对象，铸造后转移给对应的Profile
+    public struct Achievement has key, store {
+        id: UID,
+        profile_id: address,
+        achievement_id: String,
+        name: String,
+        unlocked_at: u64,
+    }
+
+    /// 成就铸造事件
+    public struct AchievementMinted has copy, drop {
+        achievement: address,
+        profile_id: address,
+        achievement_id: String,
+        timestamp: u64,
+    }
+
+    // ============= 对局存证相关数据结构 =============
+
+    /// 对局结果存证，创建后冻结，任何人都可以读取并校验签名
+    public struct MatchAttestation has key {
+        id: UID,
+        match_id: String,
+        /// 所有玩家，胜利者在前，其余按出局顺序
+        players: vector<String>,
+        winner: String,
+        /// 完整动作历史的哈希
+        history_hash: vector<u8>,
+        /// 服务器签名的BCS编码消息，包含评分变化
+        message: vector<u8>,
+        signature: vector<u8>,
+        /// 签名使用的服务器Ed25519公钥
+        public_key: vector<u8>,
+        attested_at: u64,
+    }
+
+    /// 对局结果存证事件
+    public struct MatchResultAttested has copy, drop {
+        attestation: address,
+        match_id: String,
+        winner: String,
+        timestamp: u64,
+    }
+
+    // ============= 经济相关数据结构 =============
+
+    /// Profile上游戏币余额的动态字段键
+    public struct BalanceKey has copy, drop, store {}
+
+    /// 余额结算事件
+    public struct BalanceSettled has copy, drop {
+        profile_id: address,
+        balance: u64,
+        timestamp: u64,
+    }
+
     // ============= 管理员相关数据结构 =============
     
     /// 管理员权限凭证
@@ -603,6 +673,40 @@ module citadel::citadel {
         modify_profile_internal(manager, profile, passport_id, avatar);
     }
 
+    /// 管理员修改某个Profile的名称，名称由服务端按名称策略校验
+    public entry fun rename_profile_for_passport(
+        manager: &ManagerStore,
+        profile: &mut Profile,
+        passport_id: address,
+        name: String,
+        _: &AdminCap,
+        clock: &Clock,
+    ) {
+        let profile_id = table::borrow(&manager.profiles, passport_id);
+        assert!(profile_id == profile.id.to_address(), EInvalidProfile);
+        let key = NameKey {};
+        if (dynamic_field::exists_(&profile.id, key)) {
+            *dynamic_field::borrow_mut<NameKey, String>(&mut profile.id, key) = name;
+        } else {
+            dynamic_field::add(&mut profile.id, key, name);
+        };
+        event::emit(ProfileRenamed {
+            profile_id: profile.id.to_address(),
+            name,
+            timestamp: clock::timestamp_ms(clock),
+        });
+    }
+
+    /// 获取Profile的名称，未设置时返回空字符串
+    public fun get_profile_name(profile: &Profile): String {
+        let key = NameKey {};
+        if (dynamic_field::exists_(&profile.id, key)) {
+            *dynamic_field::borrow<NameKey, String>(&profile.id, key)
+        } else {
+            string::utf8(b"")
+        }
+    }
+
     /// 用户使用护照发送好友请求
     public entry fun send_friend_request_with_passport(
         manager: &mut ManagerStore,
@@ -703,6 +807,98 @@ module citadel::citadel {
         });
     }
 
+    /// 为Profile铸造成就
+    public entry fun mint_achievement(
+        profile: &Profile,
+        achievement_id: String,
+        name: String,
+        _: &AdminCap,
+        clock: &Clock,
+        ctx: &mut TxContext
+    ) {
+        let profile_id = profile.id.to_address();
+        let now = clock::timestamp_ms(clock);
+        let achievement = Achievement {
+            id: object::new(ctx),
+            profile_id,
+            achievement_id,
+            name,
+            unlocked_at: now,
+        };
+        event::emit(AchievementMinted {
+            achievement: achievement.id.to_address(),
+            profile_id,
+            achievement_id: achievement.achievement_id,
+            timestamp: now,
+        });
+        transfer::public_transfer(achievement, profile_id);
+    }
+
+    /// 结算Profile的游戏币余额
+    public entry fun settle_profile_balance(
+        profile: &mut Profile,
+        balance: u64,
+        _: &AdminCap,
+        clock: &Clock,
+    ) {
+        let key = BalanceKey {};
+        if (dynamic_field::exists_(&profile.id, key)) {
+            *dynamic_field::borrow_mut<BalanceKey, u64>(&mut profile.id, key) = balance;
+        } else {
+            dynamic_field::add(&mut profile.id, key, balance);
+        };
+        event::emit(BalanceSettled {
+            profile_id: profile.id.to_address(),
+            balance,
+            timestamp: clock::timestamp_ms(clock),
+        });
+    }
+
+    /// 记录服务器签名的对局结果，签名无效时中止
+    public entry fun attest_match_result(
+        match_id: String,
+        players: vector<String>,
+        winner: String,
+        history_hash: vector<u8>,
+        message: vector<u8>,
+        signature: vector<u8>,
+        public_key: vector<u8>,
+        _: &AdminCap,
+        clock: &Clock,
+        ctx: &mut TxContext
+    ) {
+        assert!(ed25519::ed25519_verify(&signature, &public_key, &message), EInvalidAttestation);
+        let now = clock::timestamp_ms(clock);
+        let attestation = MatchAttestation {
+            id: object::new(ctx),
+            match_id,
+            players,
+            winner,
+            history_hash,
+            message,
+            signature,
+            public_key,
+            attested_at: now,
+        };
+        event::emit(MatchResultAttested {
+            attestation: attestation.id.to_address(),
+            match_id: attestation.match_id,
+            winner: attestation.winner,
+            timestamp: now,
+        });
+        transfer::freeze_object(attestation);
+    }
+
+    /// 获取Profile已结算的游戏币余额
+    public fun get_profile_balance(profile: &Profile): u64 {
+        let key = BalanceKey {};
+        if (dynamic_field::exists_(&profile.id, key)) {
+            *dynamic_field::borrow<BalanceKey, u64>(&profile.id, key)
+        } else {
+            0
+        }
+    }
+
     /// 获取用户分数
     public fun get_user_rating(user: &Profile): u64 {
         user.rating
@@ -740,7 +936,29 @@ module citadel::citadel {
         assert!(id == passport_id, ENoAccess);
         assert!(verify_nexus_passport(passport, game_entry), ENoAccess);
     }
-    
+
+    /// 对局手牌密钥的ID格式为 [bcs::to_bytes(玩家地址)][对局ID]，只有该玩家可以获取
+    public fun is_match_hand_owner(id: &vector<u8>, player: address): bool {
+        let prefix = bcs::to_bytes(&player);
+        let prefix_len = vector::length(&prefix);
+        if (vector::length(id) <= prefix_len) {
+            return false
+        };
+        let mut i = 0;
+        while (i < prefix_len) {
+            if (*vector::borrow(id, i) != *vector::borrow(&prefix, i)) {
+                return false
+            };
+            i = i + 1;
+        };
+        true
+    }
+
+    /// 检查调用者是否可以获取对局中自己手牌消息的解密密钥
+    entry fun seal_approve_match_hand(id: vector<u8>, ctx: &TxContext) {
+        assert!(is_match_hand_owner(&id, tx_context::sender(ctx)), ENoAccess);
+    }
+
     // ============= 游戏大厅函数 =============
     
     /// 创建游戏大厅
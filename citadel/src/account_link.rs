@@ -0,0 +1,603 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 账户关联模块
+//!
+//! # 概述
+//! 用户可能更换Sui地址，而对局记录、余额、成就和好友关系都以Profile ID作为用户ID。
+//! 本模块允许把新的钱包地址关联到已有的Profile：
+//! - 已登录的用户为新钱包申请关联挑战，服务器生成包含钱包地址、Profile ID、随机数和过期时间的消息
+//! - 新钱包对消息进行个人消息签名，服务器验证签名后建立关联
+//! - 关联的钱包登录时直接使用关联的Profile，不会为其创建新的Profile
+//! - 游戏和好友相关的接口收到已关联的地址时，统一解析为Profile ID
+//!
+//! 关联保存在游戏缓存中，不过期。一个钱包只能关联到一个Profile，登录用户自己的地址不能被关联。
+//!
+//! # 接口
+//! - `POST /v1/passport/links/challenge`：申请关联挑战
+//! - `POST /v1/passport/links`：提交新钱包的签名，建立关联
+//! - `GET /v1/passport/links`：列出当前Profile关联的钱包
+//! - `DELETE /v1/passport/links/:address`：解除关联
+//!
+//! # 配置
+//! - `ACCOUNT_LINK_MAX_WALLETS`：每个Profile最多关联的钱包数，默认5
+//! - `ACCOUNT_LINK_CHALLENGE_TTL_SECS`：关联挑战的有效期（秒），默认300
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Result};
+use axum::{
+    extract::{Path, State},
+    routing::{delete, get, post},
+    Extension, Json, Router,
+};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sui_sdk::types::base_types::SuiAddress;
+use sui_sdk::types::signature::GenericSignature;
+use sui_sdk::verify_personal_message_signature::verify_personal_message_signature;
+use tower_sessions::Session;
+use tracing::{debug, info, warn};
+use utoipa::ToSchema;
+
+use crate::errors::{ErrorResponse, InternalError};
+use crate::externals::current_epoch_time;
+use crate::game::{GameCachePrefix, GameService};
+use crate::session_login::{SessionUser, SESSION_USER_KEY};
+use crate::AppState;
+
+/// 默认每个Profile最多关联的钱包数
+pub const DEFAULT_MAX_WALLETS: usize = 5;
+/// 默认关联挑战的有效期（秒）
+pub const DEFAULT_CHALLENGE_TTL_SECS: u64 = 300;
+
+/// 账户关联配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountLinkConfig {
+    /// 每个Profile最多关联的钱包数
+    pub max_wallets: usize,
+    /// 关联挑战的有效期（毫秒）
+    pub challenge_ttl_ms: u64,
+}
+
+impl Default for AccountLinkConfig {
+    fn default() -> Self {
+        Self {
+            max_wallets: DEFAULT_MAX_WALLETS,
+            challenge_ttl_ms: DEFAULT_CHALLENGE_TTL_SECS * 1000,
+        }
+    }
+}
+
+impl AccountLinkConfig {
+    /// 从环境变量读取关联配置，未设置的项使用默认值
+    pub fn from_env() -> Self {
+        let parse = |key: &str| std::env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        let defaults = Self::default();
+        Self {
+            max_wallets: parse("ACCOUNT_LINK_MAX_WALLETS")
+                .map(|v| v as usize)
+                .unwrap_or(defaults.max_wallets),
+            challenge_ttl_ms: parse("ACCOUNT_LINK_CHALLENGE_TTL_SECS")
+                .filter(|v| *v > 0)
+                .map(|v| v * 1000)
+                .unwrap_or(defaults.challenge_ttl_ms),
+        }
+    }
+}
+
+/// 已关联的钱包
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkedWallet {
+    /// 钱包地址
+    pub address: String,
+    /// 关联时间（毫秒）
+    pub linked_at: u64,
+}
+
+/// 等待签名的关联挑战
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkChallenge {
+    /// 发起关联的用户ID（Profile ID）
+    pub user_id: String,
+    /// 需要新钱包签名的消息
+    pub message: String,
+    /// 过期时间（毫秒）
+    pub expires_at: u64,
+}
+
+/**
+ * 生成关联挑战的签名消息
+ *
+ * 参数:
+ * @param address - 要关联的钱包地址
+ * @param user_id - 关联到的Profile ID
+ * @param nonce - 随机数
+ * @param expires_at - 过期时间（毫秒）
+ *
+ * 返回:
+ * 展示给用户签名的消息
+ */
+pub fn link_message(address: &str, user_id: &str, nonce: &str, expires_at: u64) -> String {
+    format!(
+        "Link wallet {} to Catastrophe Genesis profile {}\nNonce: {}\nExpires: {}",
+        address, user_id, nonce, expires_at
+    )
+}
+
+/// 规范化钱包地址，格式无效时返回None
+pub fn normalize_address(address: &str) -> Option<String> {
+    SuiAddress::from_str(address.trim()).ok().map(|a| a.to_string())
+}
+
+fn address_key(address: &str) -> String {
+    format!("address:{}", address)
+}
+
+fn wallets_key(user_id: &str) -> String {
+    format!("user:{}", user_id)
+}
+
+/// 账户关联服务
+pub struct AccountLinkService {
+    game_service: Arc<GameService>,
+    config: AccountLinkConfig,
+    /// 等待签名的挑战，键为规范化的钱包地址
+    challenges: Mutex<HashMap<String, LinkChallenge>>,
+}
+
+impl AccountLinkService {
+    /// 创建账户关联服务
+    pub fn new(game_service: Arc<GameService>, config: AccountLinkConfig) -> Self {
+        Self {
+            game_service,
+            config,
+            challenges: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 钱包关联到的用户ID
+    pub fn linked_user(&self, address: &str) -> Option<String> {
+        let address = normalize_address(address)?;
+        self.game_service.get::<String>(GameCachePrefix::ACCOUNT_LINK, &address_key(&address))
+    }
+
+    /// 用户关联的钱包，按关联时间排列
+    pub fn linked_wallets(&self, user_id: &str) -> Vec<LinkedWallet> {
+        self.game_service
+            .get::<Vec<LinkedWallet>>(GameCachePrefix::ACCOUNT_LINK, &wallets_key(user_id))
+            .unwrap_or_default()
+    }
+
+    /**
+     * 为新钱包生成关联挑战
+     *
+     * 同一个钱包的新挑战会替换之前未完成的挑战
+     *
+     * 参数:
+     * @param user_id - 发起关联的Profile ID
+     * @param address - 要关联的钱包地址
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 生成的挑战；钱包已关联到其他Profile或关联数已达上限时返回错误
+     */
+    pub fn issue_challenge(&self, user_id: &str, address: &str, now: u64) -> Result<LinkChallenge> {
+        let address = normalize_address(address).ok_or_else(|| anyhow!("钱包地址格式无效"))?;
+        self.check_linkable(user_id, &address)?;
+
+        let nonce = format!("{:016x}", rand::thread_rng().gen::<u64>());
+        let expires_at = now + self.config.challenge_ttl_ms;
+        let challenge = LinkChallenge {
+            user_id: user_id.to_string(),
+            message: link_message(&address, user_id, &nonce, expires_at),
+            expires_at,
+        };
+
+        let mut challenges = self.challenges.lock();
+        challenges.retain(|_, c| c.expires_at > now);
+        challenges.insert(address, challenge.clone());
+        Ok(challenge)
+    }
+
+    /**
+     * 取出等待签名的挑战，每个挑战只能使用一次
+     *
+     * 参数:
+     * @param user_id - 提交签名的Profile ID，必须与发起挑战的用户一致
+     * @param address - 钱包地址
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 挑战；不存在、已过期或不属于当前用户时返回错误
+     */
+    pub fn take_challenge(&self, user_id: &str, address: &str, now: u64) -> Result<LinkChallenge> {
+        let address = normalize_address(address).ok_or_else(|| anyhow!("钱包地址格式无效"))?;
+        let mut challenges = self.challenges.lock();
+        match challenges.get(&address) {
+            None => bail!("没有等待签名的关联挑战"),
+            Some(challenge) if challenge.user_id != user_id => bail!("关联挑战不属于当前用户"),
+            Some(_) => {}
+        }
+        let challenge = challenges.remove(&address).expect("checked above");
+        if challenge.expires_at <= now {
+            bail!("关联挑战已过期");
+        }
+        Ok(challenge)
+    }
+
+    /// 钱包能否关联到用户
+    fn check_linkable(&self, user_id: &str, address: &str) -> Result<()> {
+        if let Some(owner) = self.linked_user(address) {
+            if owner != user_id {
+                bail!("钱包已关联到其他档案");
+            }
+            return Ok(());
+        }
+        if self.linked_wallets(user_id).len() >= self.config.max_wallets {
+            bail!("关联的钱包数已达上限 {}", self.config.max_wallets);
+        }
+        Ok(())
+    }
+
+    /**
+     * 建立关联，调用前必须已验证钱包的签名
+     *
+     * 参数:
+     * @param user_id - Profile ID
+     * @param address - 钱包地址
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 关联的钱包，已关联到同一用户时返回已有的记录
+     */
+    pub fn link(&self, user_id: &str, address: &str, now: u64) -> Result<LinkedWallet> {
+        let address = normalize_address(address).ok_or_else(|| anyhow!("钱包地址格式无效"))?;
+        self.check_linkable(user_id, &address)?;
+
+        let mut wallets = self.linked_wallets(user_id);
+        if let Some(existing) = wallets.iter().find(|w| w.address == address) {
+            return Ok(existing.clone());
+        }
+        let wallet = LinkedWallet { address: address.clone(), linked_at: now };
+        wallets.push(wallet.clone());
+
+        if !self.game_service.set(GameCachePrefix::ACCOUNT_LINK, &address_key(&address), &user_id.to_string())
+            || !self.game_service.set(GameCachePrefix::ACCOUNT_LINK, &wallets_key(user_id), &wallets)
+        {
+            bail!("保存钱包关联失败");
+        }
+        info!("钱包 {} 已关联到档案 {}", address, user_id);
+        Ok(wallet)
+    }
+
+    /**
+     * 解除关联
+     *
+     * 参数:
+     * @param user_id - Profile ID
+     * @param address - 钱包地址
+     *
+     * 返回:
+     * 解除了关联返回true，钱包未关联到该用户返回false
+     */
+    pub fn unlink(&self, user_id: &str, address: &str) -> bool {
+        let Some(address) = normalize_address(address) else {
+            return false;
+        };
+        if self.linked_user(&address).as_deref() != Some(user_id) {
+            return false;
+        }
+        let mut wallets = self.linked_wallets(user_id);
+        wallets.retain(|w| w.address != address);
+        self.game_service.set(GameCachePrefix::ACCOUNT_LINK, &wallets_key(user_id), &wallets);
+        self.game_service.delete(GameCachePrefix::ACCOUNT_LINK, &address_key(&address));
+        info!("钱包 {} 已解除与档案 {} 的关联", address, user_id);
+        true
+    }
+
+    /// 把已关联的钱包地址解析为用户ID，其他ID原样返回
+    pub fn resolve(&self, id: &str) -> String {
+        self.linked_user(id).unwrap_or_else(|| id.to_string())
+    }
+}
+
+// 用于存储全局AccountLinkService实例的静态变量
+static GLOBAL_ACCOUNT_LINK_SERVICE: OnceCell<Arc<AccountLinkService>> = OnceCell::new();
+
+/// 初始化账户关联服务并设置为全局实例
+pub fn init_account_link_service(game_service: Arc<GameService>) -> Arc<AccountLinkService> {
+    let service = Arc::new(AccountLinkService::new(game_service, AccountLinkConfig::from_env()));
+    let _ = GLOBAL_ACCOUNT_LINK_SERVICE.set(service.clone());
+    service
+}
+
+/// 获取全局账户关联服务
+pub fn global_account_link_service() -> Option<Arc<AccountLinkService>> {
+    GLOBAL_ACCOUNT_LINK_SERVICE.get().cloned()
+}
+
+/// 把已关联的钱包地址解析为用户ID，服务未初始化或不是已关联的地址时原样返回
+pub fn resolve_user_id(id: &str) -> String {
+    match global_account_link_service() {
+        Some(service) => service.resolve(id),
+        None => id.to_string(),
+    }
+}
+
+/// 申请关联挑战的请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LinkChallengeRequest {
+    /// 要关联的钱包地址
+    pub address: String,
+}
+
+/// 关联挑战响应
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkChallengeResponse {
+    pub success: bool,
+    /// 需要新钱包进行个人消息签名的消息
+    pub message: Option<String>,
+    /// 挑战的过期时间（毫秒）
+    pub expires_at: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// 建立关联的请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LinkWalletRequest {
+    /// 要关联的钱包地址
+    pub address: String,
+    /// 新钱包对挑战消息的个人消息签名
+    #[schema(value_type = String)]
+    pub signature: GenericSignature,
+}
+
+/// 建立关联的响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LinkWalletResponse {
+    pub success: bool,
+    pub wallet: Option<LinkedWallet>,
+    pub error: Option<String>,
+}
+
+/// 关联钱包列表和解除关联的响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LinkedWalletsResponse {
+    pub success: bool,
+    pub wallets: Vec<LinkedWallet>,
+    pub error: Option<String>,
+}
+
+/// 从session中获取当前用户的Profile ID和登录地址
+async fn session_user(session: &Session) -> Result<(String, SuiAddress), InternalError> {
+    let user = session
+        .get::<SessionUser>(SESSION_USER_KEY)
+        .await?
+        .ok_or(InternalError::Unauthorized)?;
+    let profile = user.profile.ok_or(InternalError::Unauthorized)?;
+    Ok((profile.id.to_string(), user.user_address))
+}
+
+/// 申请关联挑战
+#[utoipa::path(
+    post,
+    path = "/v1/passport/links/challenge",
+    tag = "profile",
+    request_body = LinkChallengeRequest,
+    responses(
+        (status = 200, description = "需要新钱包签名的消息", body = LinkChallengeResponse),
+        (status = 403, description = "未登录或没有档案", body = ErrorResponse),
+    )
+)]
+pub async fn handle_link_challenge(
+    State(app_state): State<Arc<AppState>>,
+    Extension(session): Extension<Session>,
+    Json(request): Json<LinkChallengeRequest>,
+) -> Result<Json<LinkChallengeResponse>, InternalError> {
+    app_state.metrics.observe_request("link_challenge");
+    let (user_id, user_address) = session_user(&session).await?;
+    let service = global_account_link_service().ok_or(InternalError::Failure)?;
+
+    let result = if normalize_address(&request.address) == Some(user_address.to_string()) {
+        Err(anyhow!("不能关联当前登录的钱包"))
+    } else {
+        service.issue_challenge(&user_id, &request.address, current_epoch_time())
+    };
+    Ok(Json(match result {
+        Ok(challenge) => LinkChallengeResponse {
+            success: true,
+            message: Some(challenge.message),
+            expires_at: Some(challenge.expires_at),
+            error: None,
+        },
+        Err(e) => LinkChallengeResponse {
+            success: false,
+            message: None,
+            expires_at: None,
+            error: Some(e.to_string()),
+        },
+    }))
+}
+
+/// 提交新钱包的签名，建立关联
+#[utoipa::path(
+    post,
+    path = "/v1/passport/links",
+    tag = "profile",
+    request_body = LinkWalletRequest,
+    responses(
+        (status = 200, description = "关联结果", body = LinkWalletResponse),
+        (status = 403, description = "未登录、地址无效或签名验证失败", body = ErrorResponse),
+    )
+)]
+pub async fn handle_link_wallet(
+    State(app_state): State<Arc<AppState>>,
+    Extension(session): Extension<Session>,
+    Json(request): Json<LinkWalletRequest>,
+) -> Result<Json<LinkWalletResponse>, InternalError> {
+    app_state.metrics.observe_request("link_wallet");
+    let (user_id, _) = session_user(&session).await?;
+    let service = global_account_link_service().ok_or(InternalError::Failure)?;
+    let address = SuiAddress::from_str(request.address.trim()).map_err(|_| InternalError::InvalidInput)?;
+
+    let now = current_epoch_time();
+    let challenge = match service.take_challenge(&user_id, &request.address, now) {
+        Ok(challenge) => challenge,
+        Err(e) => {
+            return Ok(Json(LinkWalletResponse {
+                success: false,
+                wallet: None,
+                error: Some(e.to_string()),
+            }))
+        }
+    };
+
+    verify_personal_message_signature(
+        request.signature,
+        challenge.message.as_bytes(),
+        address,
+        Some(app_state.sui_pool.client()),
+    )
+    .await
+    .map_err(|e| {
+        debug!("钱包关联签名验证失败: {:?}", e);
+        InternalError::InvalidSignature
+    })?;
+
+    Ok(Json(match service.link(&user_id, &request.address, now) {
+        Ok(wallet) => LinkWalletResponse {
+            success: true,
+            wallet: Some(wallet),
+            error: None,
+        },
+        Err(e) => {
+            warn!("钱包 {} 关联到档案 {} 失败: {}", address, user_id, e);
+            LinkWalletResponse {
+                success: false,
+                wallet: None,
+                error: Some(e.to_string()),
+            }
+        }
+    }))
+}
+
+/// 列出当前Profile关联的钱包
+#[utoipa::path(
+    get,
+    path = "/v1/passport/links",
+    tag = "profile",
+    responses(
+        (status = 200, description = "关联的钱包", body = LinkedWalletsResponse),
+        (status = 403, description = "未登录或没有档案", body = ErrorResponse),
+    )
+)]
+pub async fn handle_list_links(
+    State(app_state): State<Arc<AppState>>,
+    Extension(session): Extension<Session>,
+) -> Result<Json<LinkedWalletsResponse>, InternalError> {
+    app_state.metrics.observe_request("list_links");
+    let (user_id, _) = session_user(&session).await?;
+    let service = global_account_link_service().ok_or(InternalError::Failure)?;
+    Ok(Json(LinkedWalletsResponse {
+        success: true,
+        wallets: service.linked_wallets(&user_id),
+        error: None,
+    }))
+}
+
+/// 解除钱包关联
+#[utoipa::path(
+    delete,
+    path = "/v1/passport/links/{address}",
+    tag = "profile",
+    params(("address" = String, Path, description = "钱包地址")),
+    responses(
+        (status = 200, description = "解除后剩余的关联钱包", body = LinkedWalletsResponse),
+        (status = 403, description = "未登录或没有档案", body = ErrorResponse),
+    )
+)]
+pub async fn handle_unlink_wallet(
+    State(app_state): State<Arc<AppState>>,
+    Extension(session): Extension<Session>,
+    Path(address): Path<String>,
+) -> Result<Json<LinkedWalletsResponse>, InternalError> {
+    app_state.metrics.observe_request("unlink_wallet");
+    let (user_id, _) = session_user(&session).await?;
+    let service = global_account_link_service().ok_or(InternalError::Failure)?;
+    let unlinked = service.unlink(&user_id, &address);
+    Ok(Json(LinkedWalletsResponse {
+        success: unlinked,
+        wallets: service.linked_wallets(&user_id),
+        error: (!unlinked).then(|| "钱包未关联到当前档案".to_string()),
+    }))
+}
+
+/// 注册账户关联路由
+pub fn register_account_link_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router
+        .route("/v1/passport/links", get(handle_list_links).post(handle_link_wallet))
+        .route("/v1/passport/links/challenge", post(handle_link_challenge))
+        .route("/v1/passport/links/:address", delete(handle_unlink_wallet))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE: &str = "0x00000000000000000000000000000000000000000000000000000000000000aa";
+    const OTHER: &str = "0x00000000000000000000000000000000000000000000000000000000000000bb";
+
+    fn service(max_wallets: usize) -> AccountLinkService {
+        AccountLinkService::new(
+            Arc::new(GameService::new()),
+            AccountLinkConfig { max_wallets, challenge_ttl_ms: 1000 },
+        )
+    }
+
+    #[test]
+    fn test_link_resolve_unlink() {
+        let service = service(5);
+        let wallet = SuiAddress::random_for_testing_only().to_string();
+
+        let challenge = service.issue_challenge(PROFILE, &wallet, 0).unwrap();
+        assert!(challenge.message.contains(&wallet) && challenge.message.contains(PROFILE));
+        // 挑战只属于发起的用户，且只能使用一次
+        assert!(service.take_challenge(OTHER, &wallet, 10).is_err());
+        assert_eq!(service.take_challenge(PROFILE, &wallet, 10).unwrap(), challenge);
+        assert!(service.take_challenge(PROFILE, &wallet, 10).is_err());
+
+        service.issue_challenge(PROFILE, &wallet, 0).unwrap();
+        assert!(service.take_challenge(PROFILE, &wallet, 1000).is_err());
+
+        service.link(PROFILE, &wallet, 20).unwrap();
+        assert_eq!(service.resolve(&wallet), PROFILE);
+        assert_eq!(service.resolve("guest-1"), "guest-1");
+        assert!(service.issue_challenge(OTHER, &wallet, 30).is_err());
+
+        assert!(!service.unlink(OTHER, &wallet));
+        assert!(service.unlink(PROFILE, &wallet));
+        assert_eq!(service.resolve(&wallet), wallet);
+        assert!(service.linked_wallets(PROFILE).is_empty());
+    }
+
+    #[test]
+    fn test_wallet_limit() {
+        let service = service(1);
+        let first = SuiAddress::random_for_testing_only().to_string();
+        let second = SuiAddress::random_for_testing_only().to_string();
+
+        service.link(PROFILE, &first, 0).unwrap();
+        // 重复关联同一钱包不占用名额
+        assert_eq!(service.link(PROFILE, &first, 10).unwrap().linked_at, 0);
+        assert!(service.issue_challenge(PROFILE, &second, 0).is_err());
+        assert!(service.link(PROFILE, &second, 0).is_err());
+        assert_eq!(service.linked_wallets(PROFILE).len(), 1);
+    }
+}
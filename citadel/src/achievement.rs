@@ -0,0 +1,657 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 成就与每日任务模块
+//!
+//! # 概述
+//! 本模块根据对局中产生的事件（获胜、拆除爆炸猫、打出烦人卡）统计每个用户的进度，
+//! 当进度达到目标时解锁对应的成就或每日任务，并通过WebSocket通知用户。
+//!
+//! # 核心功能
+//! - 成就定义：静态成就表，分为终身成就和每日任务两种范围
+//! - 进度统计：按用户缓存计数器，每日任务在日期变化时自动重置
+//! - 解锁通知：解锁时向用户推送 `achievement:unlocked` 事件
+//! - REST接口：查询成就列表与用户进度，可选地将已解锁成就铸造为链上对象
+//!
+//! # 使用示例
+//! ```ignore
+//! let service = achievement::init_achievement_service(game_service, connection_manager);
+//! service.record(&user_id, AchievementMetric::NopesPlayed, 1).await?;
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use sui_types::base_types::ObjectID;
+use tower_sessions::Session;
+use tracing::{error, info};
+
+use crate::errors::InternalError;
+use crate::game::{GameCachePrefix, GameService};
+use crate::i18n::{self, codes};
+use crate::sdk::executor;
+use crate::session_login::{SessionUser, SESSION_USER_KEY};
+use crate::ws::{ConnectionManager, WsResponse};
+use crate::AppState;
+
+/// 成就相关WebSocket事件
+pub mod events {
+    /// 成就解锁通知
+    pub const UNLOCKED: &str = "achievement:unlocked";
+}
+
+/// 成就统计指标
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AchievementMetric {
+    /// 获胜场数
+    GamesWon,
+    /// 拆除爆炸猫次数
+    KittensDefused,
+    /// 打出烦人卡次数
+    NopesPlayed,
+}
+
+/// 成就范围
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AchievementScope {
+    /// 终身成就，解锁后永久保留
+    Lifetime,
+    /// 每日任务，每天零点（UTC）重置
+    Daily,
+}
+
+/// 成就定义
+#[derive(Debug, Clone, Serialize)]
+pub struct AchievementDefinition {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub metric: AchievementMetric,
+    pub target: u64,
+    pub scope: AchievementScope,
+}
+
+/// 所有成就与每日任务定义
+pub const ACHIEVEMENTS: &[AchievementDefinition] = &[
+    AchievementDefinition {
+        id: "first_win",
+        name: "初尝胜果",
+        description: "赢得第一场对局",
+        metric: AchievementMetric::GamesWon,
+        target: 1,
+        scope: AchievementScope::Lifetime,
+    },
+    AchievementDefinition {
+        id: "win_10",
+        name: "常胜将军",
+        description: "累计赢得10场对局",
+        metric: AchievementMetric::GamesWon,
+        target: 10,
+        scope: AchievementScope::Lifetime,
+    },
+    AchievementDefinition {
+        id: "defuse_10",
+        name: "拆弹专家",
+        description: "累计拆除10只爆炸猫",
+        metric: AchievementMetric::KittensDefused,
+        target: 10,
+        scope: AchievementScope::Lifetime,
+    },
+    AchievementDefinition {
+        id: "nope_25",
+        name: "就是不行",
+        description: "累计打出25张烦人卡",
+        metric: AchievementMetric::NopesPlayed,
+        target: 25,
+        scope: AchievementScope::Lifetime,
+    },
+    AchievementDefinition {
+        id: "daily_win_3",
+        name: "每日任务：连胜",
+        description: "今天赢得3场对局",
+        metric: AchievementMetric::GamesWon,
+        target: 3,
+        scope: AchievementScope::Daily,
+    },
+    AchievementDefinition {
+        id: "daily_defuse_2",
+        name: "每日任务：拆弹",
+        description: "今天拆除2只爆炸猫",
+        metric: AchievementMetric::KittensDefused,
+        target: 2,
+        scope: AchievementScope::Daily,
+    },
+    AchievementDefinition {
+        id: "daily_nope_5",
+        name: "每日任务：唱反调",
+        description: "今天打出5张烦人卡",
+        metric: AchievementMetric::NopesPlayed,
+        target: 5,
+        scope: AchievementScope::Daily,
+    },
+];
+
+/// 根据ID查找成就定义
+pub fn find_definition(id: &str) -> Option<&'static AchievementDefinition> {
+    ACHIEVEMENTS.iter().find(|a| a.id == id)
+}
+
+/// 用户成就数据（缓存中保存的结构）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserAchievements {
+    /// 终身计数器
+    #[serde(default)]
+    pub counters: HashMap<AchievementMetric, u64>,
+    /// 当日计数器
+    #[serde(default)]
+    pub daily_counters: HashMap<AchievementMetric, u64>,
+    /// 当日计数器对应的日期（YYYY-MM-DD）
+    #[serde(default)]
+    pub daily_date: String,
+    /// 已解锁的终身成就及解锁时间
+    #[serde(default)]
+    pub unlocked: HashMap<String, u64>,
+    /// 当日已完成的每日任务
+    #[serde(default)]
+    pub daily_completed: HashSet<String>,
+    /// 已铸造为链上对象的成就
+    #[serde(default)]
+    pub minted: HashSet<String>,
+}
+
+impl UserAchievements {
+    /// 若日期变化则重置每日数据
+    fn roll_day(&mut self, today: &str) {
+        if self.daily_date != today {
+            self.daily_date = today.to_string();
+            self.daily_counters.clear();
+            self.daily_completed.clear();
+        }
+    }
+
+    /**
+     * 记录一次指标进度
+     *
+     * 参数:
+     * @param metric - 统计指标
+     * @param amount - 增加的数量
+     * @param today - 当前日期（YYYY-MM-DD）
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 本次新解锁的成就定义列表
+     */
+    pub fn record(
+        &mut self,
+        metric: AchievementMetric,
+        amount: u64,
+        today: &str,
+        now: u64,
+    ) -> Vec<&'static AchievementDefinition> {
+        self.roll_day(today);
+        *self.counters.entry(metric).or_insert(0) += amount;
+        *self.daily_counters.entry(metric).or_insert(0) += amount;
+
+        let mut newly_unlocked = Vec::new();
+        for definition in ACHIEVEMENTS.iter().filter(|a| a.metric == metric) {
+            match definition.scope {
+                AchievementScope::Lifetime => {
+                    if !self.unlocked.contains_key(definition.id)
+                        && self.counters[&metric] >= definition.target
+                    {
+                        self.unlocked.insert(definition.id.to_string(), now);
+                        newly_unlocked.push(definition);
+                    }
+                }
+                AchievementScope::Daily => {
+                    if !self.daily_completed.contains(definition.id)
+                        && self.daily_counters[&metric] >= definition.target
+                    {
+                        self.daily_completed.insert(definition.id.to_string());
+                        newly_unlocked.push(definition);
+                    }
+                }
+            }
+        }
+        newly_unlocked
+    }
+
+    /// 生成所有成就的进度视图
+    pub fn progress(&self, today: &str) -> Vec<AchievementProgress> {
+        let same_day = self.daily_date == today;
+        ACHIEVEMENTS
+            .iter()
+            .map(|definition| {
+                let (progress, unlocked, unlocked_at) = match definition.scope {
+                    AchievementScope::Lifetime => (
+                        self.counters.get(&definition.metric).copied().unwrap_or(0),
+                        self.unlocked.contains_key(definition.id),
+                        self.unlocked.get(definition.id).copied(),
+                    ),
+                    AchievementScope::Daily if same_day => (
+                        self.daily_counters.get(&definition.metric).copied().unwrap_or(0),
+                        self.daily_completed.contains(definition.id),
+                        None,
+                    ),
+                    AchievementScope::Daily => (0, false, None),
+                };
+                AchievementProgress {
+                    achievement: definition.clone(),
+                    progress: progress.min(definition.target),
+                    unlocked,
+                    unlocked_at,
+                    minted: self.minted.contains(definition.id),
+                }
+            })
+            .collect()
+    }
+}
+
+/// 单个成就的进度
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AchievementProgress {
+    pub achievement: AchievementDefinition,
+    pub progress: u64,
+    pub unlocked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unlocked_at: Option<u64>,
+    pub minted: bool,
+}
+
+/// 当前UTC日期字符串
+fn today() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+/// 成就服务
+pub struct AchievementService {
+    /// 游戏服务，处理缓存
+    game_service: Arc<GameService>,
+    /// WebSocket连接管理器
+    connection_manager: Arc<ConnectionManager>,
+    /// 正在铸造的成就（用户ID，成就ID），防止并发请求重复铸造
+    minting: parking_lot::Mutex<HashSet<(String, String)>>,
+    /// 串行化成就数据的读取-修改-保存，避免并发更新互相覆盖
+    update_lock: parking_lot::Mutex<()>,
+}
+
+/// 铸造中标记，释放时移除，铸造失败后可以重试
+pub struct MintGuard<'a> {
+    minting: &'a parking_lot::Mutex<HashSet<(String, String)>>,
+    key: (String, String),
+}
+
+impl MintGuard<'_> {
+    /// 保留铸造中标记直到服务重启，用于已经上链但无法记录的铸造，阻止再次铸造
+    pub fn keep(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for MintGuard<'_> {
+    fn drop(&mut self) {
+        self.minting.lock().remove(&self.key);
+    }
+}
+
+impl AchievementService {
+    /// 创建新的成就服务
+    pub fn new(game_service: Arc<GameService>, connection_manager: Arc<ConnectionManager>) -> Self {
+        Self {
+            game_service,
+            connection_manager,
+            minting: parking_lot::Mutex::new(HashSet::new()),
+            update_lock: parking_lot::Mutex::new(()),
+        }
+    }
+
+    /// 获取用户成就数据
+    pub fn get_user_achievements(&self, user_id: &str) -> UserAchievements {
+        self.game_service
+            .get(GameCachePrefix::ACHIEVEMENT, user_id)
+            .unwrap_or_default()
+    }
+
+    /// 保存用户成就数据
+    fn save_user_achievements(&self, user_id: &str, data: &UserAchievements) -> bool {
+        self.game_service.set(GameCachePrefix::ACHIEVEMENT, user_id, data)
+    }
+
+    /**
+     * 在锁内读取、修改并保存用户成就数据
+     *
+     * 参数:
+     * @param user_id - 用户ID
+     * @param f - 修改函数
+     *
+     * 返回:
+     * 修改函数的返回值，保存失败时返回错误
+     */
+    fn update<R>(&self, user_id: &str, f: impl FnOnce(&mut UserAchievements) -> R) -> Result<R> {
+        let _guard = self.update_lock.lock();
+        let mut data = self.get_user_achievements(user_id);
+        let result = f(&mut data);
+        if !self.save_user_achievements(user_id, &data) {
+            return Err(anyhow::anyhow!("保存成就数据失败"));
+        }
+        Ok(result)
+    }
+
+    /// 获取用户所有成就进度
+    pub fn get_progress(&self, user_id: &str) -> Vec<AchievementProgress> {
+        self.get_user_achievements(user_id).progress(&today())
+    }
+
+    /**
+     * 记录用户进度并推送解锁通知
+     *
+     * 参数:
+     * @param user_id - 用户ID
+     * @param metric - 统计指标
+     * @param amount - 增加的数量
+     *
+     * 返回:
+     * 本次新解锁的成就定义列表
+     */
+    pub async fn record(
+        &self,
+        user_id: &str,
+        metric: AchievementMetric,
+        amount: u64,
+    ) -> Result<Vec<&'static AchievementDefinition>> {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let unlocked = self.update(user_id, |data| data.record(metric, amount, &today(), now))?;
+
+        for definition in &unlocked {
+            info!("用户 {} 解锁成就: {}", user_id, definition.id);
+            let response = WsResponse {
+                ok: true,
+                payload: Some(serde_json::json!({
+                    "achievement": definition,
+                    "unlockedAt": now
+                })),
+                ..WsResponse::from_text(i18n::text(codes::ACHIEVEMENT_UNLOCKED, &[("name", definition.name.to_string())]))
+            };
+            self.connection_manager
+                .send_to_client(user_id, events::UNLOCKED, Some(serde_json::to_value(response)?))
+                .await?;
+        }
+
+        Ok(unlocked)
+    }
+
+    /**
+     * 标记成就正在铸造
+     *
+     * 参数:
+     * @param user_id - 用户ID
+     * @param achievement_id - 成就ID
+     *
+     * 返回:
+     * 铸造中标记，同一成就已在铸造时返回None
+     */
+    pub fn begin_mint(&self, user_id: &str, achievement_id: &str) -> Option<MintGuard<'_>> {
+        let key = (user_id.to_string(), achievement_id.to_string());
+        if !self.minting.lock().insert(key.clone()) {
+            return None;
+        }
+        Some(MintGuard {
+            minting: &self.minting,
+            key,
+        })
+    }
+
+    /// 标记成就已铸造
+    pub fn mark_minted(&self, user_id: &str, achievement_id: &str) -> Result<()> {
+        self.update(user_id, |data| {
+            data.minted.insert(achievement_id.to_string());
+        })
+    }
+}
+
+// 用于存储全局AchievementService实例的静态变量
+static GLOBAL_ACHIEVEMENT_SERVICE: OnceCell<Arc<AchievementService>> = OnceCell::new();
+
+/// 初始化成就服务并设置为全局实例
+pub fn init_achievement_service(
+    game_service: Arc<GameService>,
+    connection_manager: Arc<ConnectionManager>,
+) -> Arc<AchievementService> {
+    let service = Arc::new(AchievementService::new(game_service, connection_manager));
+    let _ = GLOBAL_ACHIEVEMENT_SERVICE.set(service.clone());
+    service
+}
+
+/// 获取全局成就服务
+pub fn global_achievement_service() -> Option<Arc<AchievementService>> {
+    GLOBAL_ACHIEVEMENT_SERVICE.get().cloned()
+}
+
+/// 成就列表响应
+#[derive(Debug, Serialize)]
+pub struct AchievementListResponse {
+    pub success: bool,
+    pub achievements: Vec<AchievementDefinition>,
+}
+
+/// 成就进度响应
+#[derive(Debug, Serialize)]
+pub struct AchievementProgressResponse {
+    pub success: bool,
+    pub progress: Option<Vec<AchievementProgress>>,
+    pub error: Option<String>,
+}
+
+/// 成就铸造响应
+#[derive(Debug, Serialize)]
+pub struct MintAchievementResponse {
+    pub success: bool,
+    pub digest: Option<String>,
+    pub error: Option<String>,
+}
+
+/// 获取所有成就定义
+pub async fn handle_list_achievements(
+    State(app_state): State<Arc<AppState>>,
+) -> Json<AchievementListResponse> {
+    app_state.metrics.observe_request("list_achievements");
+    Json(AchievementListResponse {
+        success: true,
+        achievements: ACHIEVEMENTS.to_vec(),
+    })
+}
+
+/// 从session中获取当前用户的Profile ID
+async fn session_profile_id(session: &Session) -> Result<ObjectID, InternalError> {
+    let user = session
+        .get::<SessionUser>(SESSION_USER_KEY)
+        .await?
+        .ok_or(InternalError::Unauthorized)?;
+    user.profile.map(|p| p.id).ok_or(InternalError::Unauthorized)
+}
+
+/// 构建进度响应
+fn progress_response(user_id: &str) -> AchievementProgressResponse {
+    match global_achievement_service() {
+        Some(service) => AchievementProgressResponse {
+            success: true,
+            progress: Some(service.get_progress(user_id)),
+            error: None,
+        },
+        None => AchievementProgressResponse {
+            success: false,
+            progress: None,
+            error: Some("成就服务未初始化".to_string()),
+        },
+    }
+}
+
+/// 获取当前用户成就进度
+pub async fn handle_get_my_achievements(
+    State(app_state): State<Arc<AppState>>,
+    Extension(session): Extension<Session>,
+) -> Result<Json<AchievementProgressResponse>, InternalError> {
+    app_state.metrics.observe_request("get_my_achievements");
+    let profile_id = session_profile_id(&session).await?;
+    Ok(Json(progress_response(&profile_id.to_string())))
+}
+
+/// 获取指定用户成就进度
+pub async fn handle_get_user_achievements(
+    State(app_state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+) -> Json<AchievementProgressResponse> {
+    app_state.metrics.observe_request("get_user_achievements");
+    Json(progress_response(&user_id))
+}
+
+/// 记录铸造结果的尝试次数
+const MARK_MINTED_ATTEMPTS: usize = 3;
+
+/// 将当前用户已解锁的成就铸造为链上对象
+pub async fn handle_mint_achievement(
+    State(app_state): State<Arc<AppState>>,
+    Extension(session): Extension<Session>,
+    Path(achievement_id): Path<String>,
+) -> Result<Json<MintAchievementResponse>, InternalError> {
+    app_state.metrics.observe_request("mint_achievement");
+    let profile_id = session_profile_id(&session).await?;
+    let user_id = profile_id.to_string();
+    // 游客没有链上Profile，升级后才能铸造
+    if crate::guest::is_guest(&user_id) {
+        return Err(InternalError::NoAccess);
+    }
+
+    let definition = find_definition(&achievement_id).ok_or(InternalError::InvalidInput)?;
+    if definition.scope != AchievementScope::Lifetime {
+        return Err(InternalError::InvalidInput);
+    }
+
+    let service = global_achievement_service().ok_or(InternalError::Failure)?;
+    // 在检查和等待交易之前标记为铸造中，并发请求只有一个能继续
+    let Some(guard) = service.begin_mint(&user_id, definition.id) else {
+        return Ok(Json(MintAchievementResponse {
+            success: false,
+            digest: None,
+            error: Some("成就正在铸造".to_string()),
+        }));
+    };
+    let data = service.get_user_achievements(&user_id);
+    if !data.unlocked.contains_key(definition.id) {
+        return Err(InternalError::NoAccess);
+    }
+    if data.minted.contains(definition.id) {
+        return Ok(Json(MintAchievementResponse {
+            success: false,
+            digest: None,
+            error: Some("成就已铸造".to_string()),
+        }));
+    }
+
+    match executor::mint_achievement(&app_state, &profile_id, definition.id, definition.name).await {
+        Ok(response) => {
+            // 已经上链，记录失败时重试，仍然失败则保留铸造中标记，阻止再次铸造
+            let marked = (0..MARK_MINTED_ATTEMPTS).any(|_| service.mark_minted(&user_id, definition.id).is_ok());
+            if !marked {
+                error!("成就 {} 已铸造但无法记录, 用户 {}, 交易 {}", definition.id, user_id, response.digest);
+                guard.keep();
+                return Err(InternalError::Failure);
+            }
+            Ok(Json(MintAchievementResponse {
+                success: true,
+                digest: Some(response.digest.to_string()),
+                error: None,
+            }))
+        }
+        Err(e) => {
+            error!("铸造成就失败: {}", e);
+            Ok(Json(MintAchievementResponse {
+                success: false,
+                digest: None,
+                error: Some(format!("铸造成就失败: {}", e)),
+            }))
+        }
+    }
+}
+
+/// 注册成就路由
+pub fn register_achievement_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router
+        .route("/achievements", get(handle_list_achievements))
+        .route("/achievements/me", get(handle_get_my_achievements))
+        .route("/achievements/me/:achievement_id/mint", post(handle_mint_achievement))
+        .route("/achievements/:user_id", get(handle_get_user_achievements))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lifetime_unlock_once() {
+        let mut data = UserAchievements::default();
+        let unlocked = data.record(AchievementMetric::GamesWon, 1, "2024-01-01", 1);
+        assert!(unlocked.iter().any(|a| a.id == "first_win"));
+
+        let unlocked = data.record(AchievementMetric::GamesWon, 1, "2024-01-01", 2);
+        assert!(!unlocked.iter().any(|a| a.id == "first_win"));
+        assert_eq!(data.unlocked.get("first_win"), Some(&1));
+    }
+
+    #[test]
+    fn test_daily_reset() {
+        let mut data = UserAchievements::default();
+        data.record(AchievementMetric::NopesPlayed, 4, "2024-01-01", 1);
+        let unlocked = data.record(AchievementMetric::NopesPlayed, 1, "2024-01-01", 2);
+        assert!(unlocked.iter().any(|a| a.id == "daily_nope_5"));
+
+        // 第二天每日进度清零，但终身计数保留
+        let unlocked = data.record(AchievementMetric::NopesPlayed, 1, "2024-01-02", 3);
+        assert!(unlocked.is_empty());
+        assert_eq!(data.counters[&AchievementMetric::NopesPlayed], 6);
+        assert_eq!(data.daily_counters[&AchievementMetric::NopesPlayed], 1);
+
+        let progress = data.progress("2024-01-02");
+        let daily = progress.iter().find(|p| p.achievement.id == "daily_nope_5").unwrap();
+        assert_eq!(daily.progress, 1);
+        assert!(!daily.unlocked);
+    }
+
+    #[test]
+    fn test_concurrent_mint_rejected() {
+        let service = AchievementService::new(Arc::new(GameService::new()), Arc::new(ConnectionManager::new()));
+        let guard = service.begin_mint("u1", "first_win");
+        assert!(guard.is_some());
+        assert!(service.begin_mint("u1", "first_win").is_none());
+        assert!(service.begin_mint("u1", "win_10").is_some());
+
+        // 铸造失败释放标记后可以重试
+        drop(guard);
+        assert!(service.begin_mint("u1", "first_win").is_some());
+    }
+
+    #[test]
+    fn test_record_keeps_minted() {
+        let service = AchievementService::new(Arc::new(GameService::new()), Arc::new(ConnectionManager::new()));
+        service.update("u1", |data| data.record(AchievementMetric::GamesWon, 1, "2024-01-01", 1)).unwrap();
+        service.mark_minted("u1", "first_win").unwrap();
+
+        // 之后的进度更新基于最新数据，不会覆盖铸造记录
+        service.update("u1", |data| data.record(AchievementMetric::GamesWon, 1, "2024-01-01", 2)).unwrap();
+        let data = service.get_user_achievements("u1");
+        assert!(data.minted.contains("first_win"));
+        assert_eq!(data.counters[&AchievementMetric::GamesWon], 2);
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 对局动作耗时分析
+//!
+//! # 概述
+//! 一次出牌或抽牌要依次读取缓存、校验、写回缓存并广播，中间有多次await，
+//! 只看整体耗时无法判断慢在哪一步。`ActionProfile` 为每次动作建立一个tracing span（`match_action`），
+//! 每个步骤在其下建立子span（`match_action_step`），并按步骤累计耗时：
+//! - `cache_read`：读取对局数据
+//! - `validation`：校验动作
+//! - `cache_write`：保存对局数据
+//! - `broadcast`：向房间或玩家发送消息
+//! - `effect`：卡牌效果、连锁、回合切换等后续处理
+//!
+//! 动作结束（包括出错提前返回）时，各步骤和整体（`total`）的耗时记录到 `match_action_duration` 直方图；
+//! 整体耗时超过阈值时记录一条带各步骤耗时的警告，并计入 `match_slow_actions_total`。
+//!
+//! # 配置
+//! - `MATCH_SLOW_ACTION_MS`：慢动作警告阈值（毫秒），默认250，0表示不警告
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::{Lazy, OnceCell};
+use tracing::{debug_span, info_span, warn, Instrument, Span};
+
+use crate::metrics::Metrics;
+
+/// 默认慢动作阈值（毫秒）
+const DEFAULT_SLOW_ACTION_MS: u64 = 250;
+
+static SLOW_ACTION_THRESHOLD: Lazy<Option<Duration>> = Lazy::new(|| {
+    let ms = std::env::var("MATCH_SLOW_ACTION_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_SLOW_ACTION_MS);
+    (ms > 0).then(|| Duration::from_millis(ms))
+});
+
+static METRICS: OnceCell<Metrics> = OnceCell::new();
+
+/// 关联指标，之后每次动作的耗时都会记录到指标中
+pub fn attach_metrics(metrics: Metrics) {
+    let _ = METRICS.set(metrics);
+}
+
+/// 动作的步骤
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActionStep {
+    CacheRead,
+    Validation,
+    CacheWrite,
+    Broadcast,
+    Effect,
+}
+
+impl ActionStep {
+    /// 所有步骤
+    pub const ALL: [ActionStep; 5] = [
+        ActionStep::CacheRead,
+        ActionStep::Validation,
+        ActionStep::CacheWrite,
+        ActionStep::Broadcast,
+        ActionStep::Effect,
+    ];
+
+    /// 指标和日志中使用的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActionStep::CacheRead => "cache_read",
+            ActionStep::Validation => "validation",
+            ActionStep::CacheWrite => "cache_write",
+            ActionStep::Broadcast => "broadcast",
+            ActionStep::Effect => "effect",
+        }
+    }
+}
+
+/// 一次对局动作的耗时记录，离开作用域时记录指标
+pub struct ActionProfile {
+    action: &'static str,
+    span: Span,
+    started: Instant,
+    /// 按 `ActionStep::ALL` 的顺序累计的耗时，未执行的步骤为None
+    steps: [Option<Duration>; 5],
+    threshold: Option<Duration>,
+}
+
+impl ActionProfile {
+    /**
+     * 开始记录一次动作
+     *
+     * 参数:
+     * @param action - 动作名称，如 `play_card`
+     * @param match_id - 游戏ID
+     *
+     * 返回:
+     * 耗时记录
+     */
+    pub fn start(action: &'static str, match_id: &str) -> Self {
+        Self {
+            action,
+            span: info_span!("match_action", action, match_id),
+            started: Instant::now(),
+            steps: [None; 5],
+            threshold: *SLOW_ACTION_THRESHOLD,
+        }
+    }
+
+    /**
+     * 在子span中执行一个异步步骤并累计耗时
+     *
+     * 参数:
+     * @param step - 步骤
+     * @param future - 步骤的执行
+     *
+     * 返回:
+     * 步骤的结果
+     */
+    pub async fn step<F: Future>(&mut self, step: ActionStep, future: F) -> F::Output {
+        let span = debug_span!(parent: &self.span, "match_action_step", step = step.as_str());
+        let started = Instant::now();
+        let output = future.instrument(span).await;
+        self.record(step, started.elapsed());
+        output
+    }
+
+    /// 在子span中执行一个同步步骤并累计耗时
+    pub fn step_sync<T>(&mut self, step: ActionStep, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let output = debug_span!(parent: &self.span, "match_action_step", step = step.as_str()).in_scope(f);
+        self.record(step, started.elapsed());
+        output
+    }
+
+    fn record(&mut self, step: ActionStep, elapsed: Duration) {
+        let index = ActionStep::ALL.iter().position(|s| *s == step).expect("步骤已登记");
+        *self.steps[index].get_or_insert(Duration::ZERO) += elapsed;
+    }
+
+    /// 已执行步骤的累计耗时
+    pub fn steps(&self) -> impl Iterator<Item = (ActionStep, Duration)> + '_ {
+        ActionStep::ALL.iter().zip(self.steps.iter()).filter_map(|(step, elapsed)| elapsed.map(|e| (*step, e)))
+    }
+
+    /// 各步骤耗时的摘要，如 `cache_read=1.2ms broadcast=30.0ms`
+    fn summary(&self) -> String {
+        self.steps()
+            .map(|(step, elapsed)| format!("{}={:.1}ms", step.as_str(), elapsed.as_secs_f64() * 1000.0))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Drop for ActionProfile {
+    fn drop(&mut self) {
+        let total = self.started.elapsed();
+        let slow = self.threshold.is_some_and(|threshold| total >= threshold);
+        if let Some(metrics) = METRICS.get() {
+            for (step, elapsed) in self.steps() {
+                metrics.observe_match_action(self.action, step.as_str(), elapsed);
+            }
+            metrics.observe_match_action(self.action, "total", total);
+            if slow {
+                metrics.observe_slow_match_action(self.action);
+            }
+        }
+        if slow {
+            let _guard = self.span.enter();
+            warn!("慢动作 {} 耗时 {}ms: {}", self.action, total.as_millis(), self.summary());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_steps_accumulate() {
+        let mut profile = ActionProfile::start("play_card", "m1");
+        profile.threshold = None;
+        let value = profile.step(ActionStep::CacheRead, async { 1 }).await;
+        assert_eq!(value, 1);
+        profile.step_sync(ActionStep::Validation, || ());
+        profile.step(ActionStep::CacheRead, tokio::time::sleep(Duration::from_millis(5))).await;
+
+        let steps = profile.steps().collect::<Vec<_>>();
+        assert_eq!(steps.iter().map(|(s, _)| *s).collect::<Vec<_>>(), vec![ActionStep::CacheRead, ActionStep::Validation]);
+        assert!(steps[0].1 >= Duration::from_millis(5));
+        assert!(profile.summary().starts_with("cache_read="));
+    }
+}
@@ -0,0 +1,297 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 系统公告模块
+//!
+//! # 概述
+//! 运营需要向玩家发送停机维护、活动开始等通知。本模块提供管理接口 `POST /admin/announce`，
+//! 向所有在线客户端或指定房间广播系统公告：
+//! - 公告以独立的 `system:announcement` WebSocket事件下发，客户端可以单独展示
+//! - 支持定时发送（`sendAt`）和重复发送（`repeat` 次，间隔 `intervalSecs` 秒），由后台任务调度
+//! - 需要在 `X-Admin-Key` 请求头中提供管理密钥，与其他管理接口相同
+//!
+//! 定时公告只保存在内存中，服务重启后未发送的公告会丢失。
+//!
+//! # 消息格式
+//! ```json
+//! // POST /admin/announce
+//! { "message": "服务器将在10分钟后维护", "level": "maintenance", "roomId": null, "sendAt": 1700000000000, "repeat": 3, "intervalSecs": 300 }
+//! // 服务器 -> 客户端
+//! { "event": "system:announcement", "data": { "ok": true, "msg": "服务器将在10分钟后维护", "payload": { "id": "...", "level": "maintenance", "sequence": 1, "total": 3 } } }
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::http::HeaderMap;
+use axum::routing::post;
+use axum::{Json, Router};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::errors::InternalError;
+use crate::key_audit::check_admin_key;
+use crate::ws::{ConnectionManager, WsResponse};
+
+/// 公告事件
+pub const ANNOUNCEMENT_EVENT: &str = "system:announcement";
+/// 公告内容的最大长度（字符）
+pub const MAX_MESSAGE_LEN: usize = 1000;
+/// 最大重复次数
+pub const MAX_REPEAT: u32 = 100;
+/// 重复发送的最小间隔
+pub const MIN_REPEAT_INTERVAL: Duration = Duration::from_secs(10);
+/// 默认重复间隔
+pub const DEFAULT_REPEAT_INTERVAL: Duration = Duration::from_secs(60);
+/// 定时发送最远可以安排的时间
+pub const MAX_SCHEDULE_AHEAD: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// 公告级别
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnouncementLevel {
+    /// 普通通知
+    #[default]
+    Info,
+    /// 警告
+    Warning,
+    /// 停机维护
+    Maintenance,
+}
+
+/// `POST /admin/announce` 请求
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnounceRequest {
+    /// 公告内容
+    pub message: String,
+    /// 公告级别，默认info
+    #[serde(default)]
+    pub level: AnnouncementLevel,
+    /// 目标房间，为空时发给所有在线客户端
+    pub room_id: Option<String>,
+    /// 发送时间（毫秒时间戳），为空或已过去时立即发送
+    pub send_at: Option<u64>,
+    /// 发送次数，默认1
+    pub repeat: Option<u32>,
+    /// 重复发送的间隔（秒），默认60
+    pub interval_secs: Option<u64>,
+}
+
+/// 已安排的公告
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Announcement {
+    pub id: String,
+    pub message: String,
+    pub level: AnnouncementLevel,
+    pub room_id: Option<String>,
+    /// 第一次发送的时间
+    pub send_at: u64,
+    /// 发送次数
+    pub repeat: u32,
+    /// 重复发送的间隔（毫秒）
+    pub interval_ms: u64,
+    pub created_at: u64,
+}
+
+impl Announcement {
+    /**
+     * 校验请求并生成公告
+     *
+     * 参数:
+     * @param request - 公告请求
+     * @param now - 当前时间
+     *
+     * 返回:
+     * 公告，参数无效时返回错误说明
+     */
+    pub fn from_request(request: AnnounceRequest, now: u64) -> Result<Self, String> {
+        let message = request.message.trim().to_string();
+        if message.is_empty() {
+            return Err("公告内容不能为空".to_string());
+        }
+        if message.chars().count() > MAX_MESSAGE_LEN {
+            return Err(format!("公告内容不能超过 {} 个字符", MAX_MESSAGE_LEN));
+        }
+        let repeat = request.repeat.unwrap_or(1);
+        if !(1..=MAX_REPEAT).contains(&repeat) {
+            return Err(format!("发送次数必须在 1 到 {} 之间", MAX_REPEAT));
+        }
+        let interval = request
+            .interval_secs
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_REPEAT_INTERVAL);
+        if repeat > 1 && interval < MIN_REPEAT_INTERVAL {
+            return Err(format!("重复发送的间隔不能小于 {} 秒", MIN_REPEAT_INTERVAL.as_secs()));
+        }
+        let send_at = request.send_at.unwrap_or(now).max(now);
+        if send_at - now > MAX_SCHEDULE_AHEAD.as_millis() as u64 {
+            return Err(format!("最多只能提前 {} 天安排公告", MAX_SCHEDULE_AHEAD.as_secs() / 86400));
+        }
+        Ok(Self {
+            id: Uuid::new_v4().to_string(),
+            message,
+            level: request.level,
+            room_id: request.room_id.filter(|room| !room.is_empty()),
+            send_at,
+            repeat,
+            interval_ms: interval.as_millis() as u64,
+            created_at: now,
+        })
+    }
+
+    /// 第 `sequence` 次（从1开始）发送的时间
+    pub fn occurrence_at(&self, sequence: u32) -> u64 {
+        self.send_at + (sequence.saturating_sub(1) as u64) * self.interval_ms
+    }
+
+    /// 第 `sequence` 次发送的WebSocket响应
+    fn response(&self, sequence: u32) -> WsResponse {
+        WsResponse {
+            ok: true,
+            msg: Some(self.message.clone()),
+            payload: Some(serde_json::json!({
+                "id": self.id,
+                "level": self.level,
+                "roomId": self.room_id,
+                "sequence": sequence,
+                "total": self.repeat,
+            })),
+            ..Default::default()
+        }
+    }
+}
+
+/// 公告服务
+pub struct AnnouncementService {
+    /// WebSocket连接管理器
+    connection_manager: Arc<ConnectionManager>,
+}
+
+impl AnnouncementService {
+    /// 创建新的公告服务
+    pub fn new(connection_manager: Arc<ConnectionManager>) -> Self {
+        Self { connection_manager }
+    }
+
+    /// 发送一次公告，返回收到公告的客户端数量
+    async fn send(&self, announcement: &Announcement, sequence: u32) -> anyhow::Result<usize> {
+        let data = Some(serde_json::to_value(announcement.response(sequence))?);
+        match &announcement.room_id {
+            Some(room_id) => self.connection_manager.broadcast_to_room(room_id, ANNOUNCEMENT_EVENT, data).await,
+            None => self.connection_manager.broadcast_to_all(ANNOUNCEMENT_EVENT, data).await,
+        }
+    }
+
+    /// 安排公告，由后台任务按时发送
+    pub fn schedule(self: &Arc<Self>, announcement: Announcement) {
+        info!(
+            "安排系统公告 {}: 目标={}, 次数={}",
+            announcement.id,
+            announcement.room_id.as_deref().unwrap_or("全部"),
+            announcement.repeat
+        );
+        let service = self.clone();
+        tokio::spawn(async move {
+            for sequence in 1..=announcement.repeat {
+                let now = chrono::Utc::now().timestamp_millis() as u64;
+                let delay = announcement.occurrence_at(sequence).saturating_sub(now);
+                if delay > 0 {
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                }
+                match service.send(&announcement, sequence).await {
+                    Ok(count) => info!("系统公告 {} 第 {} 次发送给 {} 个客户端", announcement.id, sequence, count),
+                    Err(e) => error!("发送系统公告 {} 失败: {}", announcement.id, e),
+                }
+            }
+        });
+    }
+}
+
+// 用于存储全局AnnouncementService实例的静态变量
+static GLOBAL_ANNOUNCEMENT_SERVICE: OnceCell<Arc<AnnouncementService>> = OnceCell::new();
+
+/// 获取全局公告服务
+pub fn global_announcement_service() -> Option<Arc<AnnouncementService>> {
+    GLOBAL_ANNOUNCEMENT_SERVICE.get().cloned()
+}
+
+/// 发送系统公告
+pub async fn handle_announce(
+    headers: HeaderMap,
+    Json(request): Json<AnnounceRequest>,
+) -> Result<Json<Announcement>, InternalError> {
+    check_admin_key(&headers)?;
+    let service = global_announcement_service().ok_or(InternalError::Failure)?;
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let announcement = Announcement::from_request(request, now).map_err(|reason| {
+        info!("拒绝系统公告请求: {}", reason);
+        InternalError::InvalidInput
+    })?;
+    service.schedule(announcement.clone());
+    Ok(Json(announcement))
+}
+
+/// 注册系统公告路由
+pub fn register_announcement_routes(app: Router, connection_manager: Arc<ConnectionManager>) -> Router {
+    let _ = GLOBAL_ANNOUNCEMENT_SERVICE.set(Arc::new(AnnouncementService::new(connection_manager)));
+    app.route("/admin/announce", post(handle_announce))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(message: &str) -> AnnounceRequest {
+        AnnounceRequest {
+            message: message.to_string(),
+            level: AnnouncementLevel::Info,
+            room_id: None,
+            send_at: None,
+            repeat: None,
+            interval_secs: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_announcement() {
+        assert!(Announcement::from_request(request("  "), 0).is_err());
+        assert!(Announcement::from_request(request(&"a".repeat(MAX_MESSAGE_LEN + 1)), 0).is_err());
+        assert!(Announcement::from_request(AnnounceRequest { repeat: Some(0), ..request("hi") }, 0).is_err());
+        assert!(Announcement::from_request(
+            AnnounceRequest { repeat: Some(2), interval_secs: Some(1), ..request("hi") },
+            0
+        )
+        .is_err());
+        assert!(Announcement::from_request(
+            AnnounceRequest { send_at: Some(MAX_SCHEDULE_AHEAD.as_millis() as u64 + 1), ..request("hi") },
+            0
+        )
+        .is_err());
+
+        let announcement = Announcement::from_request(
+            AnnounceRequest { room_id: Some(String::new()), ..request(" hi ") },
+            100,
+        )
+        .unwrap();
+        assert_eq!(announcement.message, "hi");
+        assert_eq!(announcement.room_id, None);
+        assert_eq!((announcement.send_at, announcement.repeat), (100, 1));
+    }
+
+    #[test]
+    fn test_schedule_occurrences() {
+        // 已经过去的发送时间按当前时间处理
+        let announcement = Announcement::from_request(
+            AnnounceRequest { send_at: Some(50), repeat: Some(3), interval_secs: Some(60), ..request("hi") },
+            1_000,
+        )
+        .unwrap();
+        assert_eq!(announcement.occurrence_at(1), 1_000);
+        assert_eq!(announcement.occurrence_at(3), 1_000 + 120_000);
+    }
+}
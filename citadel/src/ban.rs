@@ -0,0 +1,444 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 封禁系统
+//!
+//! # 概述
+//! 管理员可以封禁用户（Profile ID）或钱包地址，封禁可以设置时长，也可以是永久的，并附带原因：
+//! - 钱包登录、zkLogin登录和游客升级时检查登录地址和档案，被封禁时拒绝建立会话
+//! - WebSocket连接时检查会话中的用户和地址，被封禁时拒绝升级连接
+//! - 加入匹配队列时检查队伍中的每个成员，任一成员被封禁时整个队伍都不能匹配
+//!
+//! HTTP接口被拒绝时返回 `Banned` 错误，错误体中包含封禁对象、原因和到期时间；
+//! 匹配被拒绝时 `queue:status` 的响应中返回同样的封禁状态。
+//!
+//! 封禁写入持久化存储（见 `storage`），服务启动时恢复到内存，登录、连接和匹配时只检查内存中的封禁。
+//! 写入存储失败时封禁和解封都返回错误，内存中的封禁保持不变；启动时读取失败则拒绝启动。
+//! 使用默认的 `memory` 存储后端时封禁在重启后丢失，生产部署应配置 `sqlite` 等持久化后端。
+//! 临时封禁到期后自动失效，在列出封禁时从存储中清除。
+//!
+//! # 接口
+//! 都需要 `X-Admin-Key` 请求头：
+//! - `POST /admin/bans`：封禁用户或地址
+//! - `GET /admin/bans`：列出生效中的封禁
+//! - `DELETE /admin/bans/:target/:id`：解除封禁
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Result};
+use axum::{
+    extract::Path,
+    http::{HeaderMap, StatusCode},
+    routing::{delete, get},
+    Json, Router,
+};
+use once_cell::sync::OnceCell;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sui_sdk::types::base_types::SuiAddress;
+use tower_sessions::Session;
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+use crate::account_link::normalize_address;
+use crate::errors::InternalError;
+use crate::externals::current_epoch_time;
+use crate::key_audit::check_admin_key;
+use crate::session_login::{SessionUser, SESSION_USER_KEY};
+use crate::storage;
+use crate::AppState;
+
+/// 封禁原因的最大长度（字符）
+pub const MAX_REASON_LEN: usize = 200;
+/// 封禁列表在持久化存储中的设置键
+const SETTING_KEY: &str = "bans";
+
+/// 封禁对象的类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum BanTarget {
+    /// 用户，按Profile ID封禁
+    User,
+    /// 钱包地址
+    Address,
+}
+
+impl BanTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BanTarget::User => "user",
+            BanTarget::Address => "address",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "user" => Some(BanTarget::User),
+            "address" => Some(BanTarget::Address),
+            _ => None,
+        }
+    }
+
+    /// 规范化封禁对象的ID，地址格式无效时返回None
+    pub fn normalize(&self, id: &str) -> Option<String> {
+        match self {
+            BanTarget::User => Some(id.trim().to_string()).filter(|id| !id.is_empty()),
+            BanTarget::Address => normalize_address(id),
+        }
+    }
+}
+
+/// 封禁状态
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BanState {
+    /// 封禁对象的类型
+    pub target: BanTarget,
+    /// 被封禁的Profile ID或钱包地址
+    pub id: String,
+    /// 封禁原因
+    pub reason: String,
+    /// 封禁时间（毫秒）
+    pub banned_at: u64,
+    /// 到期时间（毫秒），永久封禁为None
+    pub expires_at: Option<u64>,
+}
+
+impl BanState {
+    /// 封禁在指定时间是否仍然生效
+    pub fn is_active(&self, now: u64) -> bool {
+        self.expires_at.map_or(true, |expires_at| expires_at > now)
+    }
+
+    pub fn is_permanent(&self) -> bool {
+        self.expires_at.is_none()
+    }
+}
+
+/// 被封禁的错误，用于匹配等返回anyhow错误的流程，调用方可以取回封禁状态
+#[derive(Debug, Clone)]
+pub struct BanError(pub BanState);
+
+impl Display for BanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} 已被封禁: {}", self.0.target.as_str(), self.0.id, self.0.reason)
+    }
+}
+
+impl std::error::Error for BanError {}
+
+impl From<BanError> for InternalError {
+    fn from(e: BanError) -> Self {
+        InternalError::Banned(e.0)
+    }
+}
+
+fn ban_key(target: BanTarget, id: &str) -> String {
+    format!("{}:{}", target.as_str(), id)
+}
+
+/// 封禁服务
+#[derive(Default)]
+pub struct BanService {
+    /// 按 `类型:ID` 索引的封禁
+    bans: RwLock<HashMap<String, BanState>>,
+    /// 写锁，保证存储和内存按相同顺序更新
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl BanService {
+    /// 创建封禁服务
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从持久化存储恢复封禁，返回恢复的数量
+    pub async fn load(&self) -> Result<usize> {
+        let Some(storage) = storage::global_storage() else {
+            return Ok(0);
+        };
+        let Some(value) = storage.get_setting(SETTING_KEY).await? else {
+            return Ok(0);
+        };
+        let bans = serde_json::from_str::<Vec<BanState>>(&value)?;
+        let count = bans.len();
+        *self.bans.write() = bans.into_iter().map(|state| (ban_key(state.target, &state.id), state)).collect();
+        Ok(count)
+    }
+
+    /// 把封禁写入持久化存储，成功后替换内存中的封禁
+    async fn commit(&self, bans: HashMap<String, BanState>) -> Result<()> {
+        if let Some(storage) = storage::global_storage() {
+            let value = serde_json::to_string(&bans.values().collect::<Vec<_>>())?;
+            storage
+                .put_setting(SETTING_KEY, &value)
+                .await
+                .map_err(|e| anyhow!("保存封禁失败: {}", e))?;
+        }
+        *self.bans.write() = bans;
+        Ok(())
+    }
+
+    /**
+     * 封禁用户或地址，已有封禁时覆盖
+     *
+     * 参数:
+     * @param target - 封禁对象的类型
+     * @param id - Profile ID或钱包地址
+     * @param reason - 封禁原因
+     * @param duration_ms - 封禁时长（毫秒），None表示永久
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 新的封禁状态，ID或原因无效、写入存储失败时返回错误
+     */
+    pub async fn ban(
+        &self,
+        target: BanTarget,
+        id: &str,
+        reason: &str,
+        duration_ms: Option<u64>,
+        now: u64,
+    ) -> Result<BanState> {
+        let Some(id) = target.normalize(id) else {
+            bail!("无效的封禁对象: {}", id);
+        };
+        let reason = reason.trim();
+        if reason.is_empty() || reason.chars().count() > MAX_REASON_LEN {
+            bail!("封禁原因不能为空，且不能超过 {} 个字符", MAX_REASON_LEN);
+        }
+        if duration_ms == Some(0) {
+            bail!("封禁时长必须大于0");
+        }
+        let state = BanState {
+            target,
+            id,
+            reason: reason.to_string(),
+            banned_at: now,
+            expires_at: duration_ms.map(|duration| now.saturating_add(duration)),
+        };
+
+        let _guard = self.lock.lock().await;
+        let mut bans = self.bans.read().clone();
+        bans.insert(ban_key(target, &state.id), state.clone());
+        self.commit(bans).await?;
+        info!("封禁 {} {}，原因: {}，到期时间: {:?}", target.as_str(), state.id, state.reason, state.expires_at);
+        Ok(state)
+    }
+
+    /// 解除封禁，没有封禁时返回false，写入存储失败时返回错误
+    pub async fn unban(&self, target: BanTarget, id: &str) -> Result<bool> {
+        let Some(id) = target.normalize(id) else {
+            return Ok(false);
+        };
+        let _guard = self.lock.lock().await;
+        let mut bans = self.bans.read().clone();
+        if bans.remove(&ban_key(target, &id)).is_none() {
+            return Ok(false);
+        }
+        self.commit(bans).await?;
+        info!("解除封禁 {} {}", target.as_str(), id);
+        Ok(true)
+    }
+
+    /// 获取生效中的封禁
+    pub fn active_ban(&self, target: BanTarget, id: &str, now: u64) -> Option<BanState> {
+        let id = target.normalize(id)?;
+        self.bans
+            .read()
+            .get(&ban_key(target, &id))
+            .filter(|state| state.is_active(now))
+            .cloned()
+    }
+
+    /// 列出生效中的封禁，同时清除已到期的封禁
+    pub async fn list(&self, now: u64) -> Vec<BanState> {
+        let _guard = self.lock.lock().await;
+        let mut bans = self.bans.read().clone();
+        let before = bans.len();
+        bans.retain(|_, state| state.is_active(now));
+        let mut active = bans.values().cloned().collect::<Vec<_>>();
+        active.sort_by_key(|state| state.banned_at);
+        if bans.len() != before {
+            // 清除失败时到期的封禁已经不再生效，下次列出时重试
+            if let Err(e) = self.commit(bans).await {
+                error!("清除到期封禁失败: {}", e);
+            }
+        }
+        active
+    }
+}
+
+// 用于存储全局BanService实例的静态变量
+static GLOBAL_BAN_SERVICE: OnceCell<Arc<BanService>> = OnceCell::new();
+
+/// 初始化封禁服务并从持久化存储恢复封禁，读取失败时返回错误
+pub async fn init_ban_service() -> Result<Arc<BanService>> {
+    if let Some(service) = GLOBAL_BAN_SERVICE.get() {
+        return Ok(service.clone());
+    }
+    let service = Arc::new(BanService::new());
+    let restored = service.load().await.map_err(|e| anyhow!("恢复封禁失败: {}", e))?;
+    info!("从持久化存储恢复了 {} 条封禁", restored);
+    let _ = GLOBAL_BAN_SERVICE.set(service.clone());
+    Ok(service)
+}
+
+/// 获取全局封禁服务
+pub fn global_ban_service() -> Option<Arc<BanService>> {
+    GLOBAL_BAN_SERVICE.get().cloned()
+}
+
+/// 检查用户是否被封禁，服务未初始化时不封禁
+pub fn check_user(user_id: &str) -> Result<(), BanError> {
+    match global_ban_service().and_then(|service| service.active_ban(BanTarget::User, user_id, current_epoch_time())) {
+        Some(state) => Err(BanError(state)),
+        None => Ok(()),
+    }
+}
+
+/**
+ * 检查登录地址和用户是否被封禁
+ *
+ * 参数:
+ * @param address - 登录的钱包地址
+ * @param user_id - 用户的Profile ID，没有档案时为None
+ */
+pub fn check_login(address: &SuiAddress, user_id: Option<&str>) -> Result<(), BanError> {
+    let Some(service) = global_ban_service() else {
+        return Ok(());
+    };
+    let now = current_epoch_time();
+    if let Some(state) = service.active_ban(BanTarget::Address, &address.to_string(), now) {
+        return Err(BanError(state));
+    }
+    if let Some(state) = user_id.and_then(|id| service.active_ban(BanTarget::User, id, now)) {
+        return Err(BanError(state));
+    }
+    Ok(())
+}
+
+/**
+ * 检查WebSocket连接的用户是否被封禁
+ *
+ * 参数:
+ * @param session - 连接请求的会话，已登录时检查会话中的地址和档案
+ * @param client_id - 重连时提供的客户端ID，同样作为用户ID检查
+ */
+pub async fn check_ws_connect(session: &Session, client_id: Option<&str>) -> Result<(), BanError> {
+    // 会话读取失败时按未登录处理，只检查客户端ID
+    if let Ok(Some(user)) = session.get::<SessionUser>(SESSION_USER_KEY).await {
+        let profile_id = user.profile.as_ref().map(|p| p.id.to_string());
+        check_login(&user.user_address, profile_id.as_deref())?;
+    }
+    match client_id {
+        Some(client_id) => check_user(client_id),
+        None => Ok(()),
+    }
+}
+
+/// 封禁请求
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BanRequest {
+    /// 封禁对象的类型
+    pub target: BanTarget,
+    /// Profile ID或钱包地址
+    pub id: String,
+    /// 封禁原因
+    pub reason: String,
+    /// 封禁时长（秒），不填表示永久封禁
+    #[serde(default)]
+    pub duration_secs: Option<u64>,
+}
+
+/// 封禁用户或地址
+pub async fn handle_ban(
+    headers: HeaderMap,
+    Json(request): Json<BanRequest>,
+) -> Result<Json<BanState>, InternalError> {
+    check_admin_key(&headers)?;
+    let service = global_ban_service().ok_or(InternalError::Failure)?;
+    let duration_ms = request.duration_secs.map(|secs| secs.saturating_mul(1000));
+    let state = service
+        .ban(request.target, &request.id, &request.reason, duration_ms, current_epoch_time())
+        .await
+        .map_err(|e| {
+            info!("拒绝封禁请求: {}", e);
+            InternalError::InvalidInput
+        })?;
+    Ok(Json(state))
+}
+
+/// 列出生效中的封禁
+pub async fn handle_list_bans(headers: HeaderMap) -> Result<Json<Vec<BanState>>, InternalError> {
+    check_admin_key(&headers)?;
+    let service = global_ban_service().ok_or(InternalError::Failure)?;
+    Ok(Json(service.list(current_epoch_time()).await))
+}
+
+/// 解除封禁
+pub async fn handle_unban(
+    headers: HeaderMap,
+    Path((target, id)): Path<(String, String)>,
+) -> Result<StatusCode, InternalError> {
+    check_admin_key(&headers)?;
+    let service = global_ban_service().ok_or(InternalError::Failure)?;
+    let target = BanTarget::from_str(&target).ok_or(InternalError::InvalidInput)?;
+    let removed = service.unban(target, &id).await.map_err(|e| {
+        error!("解除封禁失败: {}", e);
+        InternalError::Failure
+    })?;
+    Ok(if removed {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    })
+}
+
+/// 注册封禁管理路由
+pub fn register_ban_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router
+        .route("/admin/bans", get(handle_list_bans).post(handle_ban))
+        .route("/admin/bans/:target/:id", delete(handle_unban))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "0x00000000000000000000000000000000000000000000000000000000000000aa";
+
+    #[tokio::test]
+    async fn test_expiring_and_permanent_bans() {
+        let service = BanService::new();
+        let temporary = service.ban(BanTarget::User, "alice", "cheating", Some(1000), 10).await.unwrap();
+        assert_eq!(temporary.expires_at, Some(1010));
+        let permanent = service.ban(BanTarget::Address, "0xaa", "spam", None, 10).await.unwrap();
+        assert!(permanent.is_permanent());
+        assert_eq!(permanent.id, ADDRESS);
+
+        assert!(service.active_ban(BanTarget::User, "alice", 1009).is_some());
+        assert!(service.active_ban(BanTarget::User, "alice", 1010).is_none());
+        assert!(service.active_ban(BanTarget::Address, ADDRESS, u64::MAX).is_some());
+
+        // 列出时清除已到期的封禁
+        assert_eq!(service.list(2000).await, vec![permanent]);
+        assert!(!service.unban(BanTarget::User, "alice").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_bans_and_unban() {
+        let service = BanService::new();
+        assert!(service.ban(BanTarget::Address, "not-an-address", "spam", None, 0).await.is_err());
+        assert!(service.ban(BanTarget::User, "bob", " ", None, 0).await.is_err());
+        assert!(service.ban(BanTarget::User, "bob", "spam", Some(0), 0).await.is_err());
+
+        service.ban(BanTarget::User, "bob", "spam", None, 0).await.unwrap();
+        assert!(service.unban(BanTarget::User, " bob ").await.unwrap());
+        assert!(service.active_ban(BanTarget::User, "bob", 1).is_none());
+        assert!(service.list(1).await.is_empty());
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 机器人玩家策略模块
+//!
+//! # 概述
+//! 机器人根据自己的手牌和公开信息决定抽牌还是出牌，分为三个难度：
+//! - `random`：在抽牌和所有可打出的卡牌中随机选择
+//! - `heuristic`：按固定规则出牌，没有拆除卡时优先跳过抽牌
+//! - `probability`：根据牌堆中爆炸猫的比例和已知的牌堆顶部决定是否冒险抽牌
+//!
+//! 机器人只会在自己的回合行动，不会打出拆除卡和烦人卡（拆除卡在抽到爆炸猫时自动使用）。
+//! `simulate` 子模块在本地运行机器人对战，用于统计各难度的胜率和对局长度。
+
+pub mod simulate;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::gaming::{Card, CardType};
+
+/// 没有拆除卡时，概率型机器人可以接受的爆炸概率
+const RISK_WITHOUT_DEFUSE: f64 = 0.1;
+
+/// 有拆除卡时，概率型机器人可以接受的爆炸概率
+const RISK_WITH_DEFUSE: f64 = 0.5;
+
+/// 机器人难度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum BotDifficulty {
+    /// 随机行动
+    Random,
+    /// 固定规则
+    Heuristic,
+    /// 根据爆炸概率行动
+    Probability,
+}
+
+impl BotDifficulty {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BotDifficulty::Random => "random",
+            BotDifficulty::Heuristic => "heuristic",
+            BotDifficulty::Probability => "probability",
+        }
+    }
+}
+
+/// 机器人行动时可以知道的信息
+#[derive(Debug, Clone, Copy)]
+pub struct BotView<'a> {
+    /// 自己的手牌
+    pub hand: &'a [Card],
+    /// 牌堆剩余张数
+    pub deck_count: usize,
+    /// 牌堆中爆炸猫的估计张数
+    pub kittens_in_deck: usize,
+    /// 已知的牌堆顶部卡牌，第一张为下一张要抽的牌
+    pub known_top: &'a [CardType],
+}
+
+impl BotView<'_> {
+    /// 手牌中是否有拆除卡
+    fn has_defuse(&self) -> bool {
+        self.hand.iter().any(|c| c.card_type == CardType::Defuse)
+    }
+
+    /// 手牌中第一张指定类型的卡牌
+    fn find(&self, card_type: CardType) -> Option<&Card> {
+        self.hand.iter().find(|c| c.card_type == card_type)
+    }
+
+    /// 下一张牌的爆炸概率
+    fn explode_chance(&self) -> f64 {
+        match self.known_top.first() {
+            Some(CardType::ExplodingKitten) => 1.0,
+            Some(_) => 0.0,
+            None if self.deck_count == 0 => 0.0,
+            None => self.kittens_in_deck.min(self.deck_count) as f64 / self.deck_count as f64,
+        }
+    }
+}
+
+/// 机器人的行动
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BotDecision {
+    /// 抽牌，结束回合
+    Draw,
+    /// 出牌
+    Play { card_id: String },
+}
+
+impl BotDecision {
+    fn play(card: &Card) -> Self {
+        BotDecision::Play { card_id: card.id.clone() }
+    }
+}
+
+/// 机器人可以主动打出的卡牌
+fn is_playable(card: &Card) -> bool {
+    !matches!(
+        card.card_type,
+        CardType::Defuse | CardType::Nope | CardType::ExplodingKitten
+    )
+}
+
+/**
+ * 决定机器人在自己回合的下一个行动
+ *
+ * 参数:
+ * @param difficulty - 机器人难度
+ * @param view - 机器人可以知道的信息
+ * @param rng - 随机数生成器
+ *
+ * 返回:
+ * 抽牌或打出手牌中的一张卡牌
+ */
+pub fn decide<R: Rng + ?Sized>(difficulty: BotDifficulty, view: &BotView, rng: &mut R) -> BotDecision {
+    match difficulty {
+        BotDifficulty::Random => decide_random(view, rng),
+        BotDifficulty::Heuristic => decide_heuristic(view),
+        BotDifficulty::Probability => decide_probability(view),
+    }
+}
+
+/// 随机机器人：抽牌和每张可打出的卡牌机会相同
+fn decide_random<R: Rng + ?Sized>(view: &BotView, rng: &mut R) -> BotDecision {
+    let playable = view.hand.iter().filter(|c| is_playable(c)).collect::<Vec<_>>();
+    match rng.gen_range(0..=playable.len()) {
+        0 => BotDecision::Draw,
+        i => BotDecision::play(playable[i - 1]),
+    }
+}
+
+/// 规则机器人：先抢牌，知道下一张是爆炸猫或没有拆除卡时尽量不抽牌
+fn decide_heuristic(view: &BotView) -> BotDecision {
+    if let Some(card) = view.find(CardType::Favor) {
+        return BotDecision::play(card);
+    }
+    let kitten_on_top = view.known_top.first() == Some(&CardType::ExplodingKitten);
+    if kitten_on_top || !view.has_defuse() {
+        for card_type in [CardType::Skip, CardType::Attack] {
+            if let Some(card) = view.find(card_type) {
+                return BotDecision::play(card);
+            }
+        }
+        if kitten_on_top {
+            if let Some(card) = view.find(CardType::Shuffle) {
+                return BotDecision::play(card);
+            }
+        }
+    }
+    BotDecision::Draw
+}
+
+/// 概率机器人：爆炸概率超过可接受范围时先偷看，再跳过或洗牌
+fn decide_probability(view: &BotView) -> BotDecision {
+    if let Some(card) = view.find(CardType::Favor) {
+        return BotDecision::play(card);
+    }
+    let chance = view.explode_chance();
+    let tolerance = if view.has_defuse() { RISK_WITH_DEFUSE } else { RISK_WITHOUT_DEFUSE };
+    if chance <= tolerance {
+        return BotDecision::Draw;
+    }
+    if view.known_top.is_empty() {
+        if let Some(card) = view.find(CardType::SeeTheFuture) {
+            return BotDecision::play(card);
+        }
+    }
+    // 已知下一张是爆炸猫时洗牌比跳过更好，爆炸猫不会留给下家
+    let preference = if chance >= 1.0 {
+        [CardType::Shuffle, CardType::Attack, CardType::Skip]
+    } else {
+        [CardType::Attack, CardType::Skip, CardType::Shuffle]
+    };
+    preference
+        .into_iter()
+        .find_map(|card_type| view.find(card_type))
+        .map(BotDecision::play)
+        .unwrap_or(BotDecision::Draw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+
+    fn card(id: &str, card_type: CardType) -> Card {
+        Card { id: id.to_string(), card_type, variant: None }
+    }
+
+    #[test]
+    fn test_heuristic_avoids_known_kitten() {
+        let hand = vec![card("d", CardType::Defuse), card("s", CardType::Skip)];
+        let mut view = BotView { hand: &hand, deck_count: 10, kittens_in_deck: 1, known_top: &[] };
+        assert_eq!(decide(BotDifficulty::Heuristic, &view, &mut thread_rng()), BotDecision::Draw);
+
+        view.known_top = &[CardType::ExplodingKitten];
+        assert_eq!(
+            decide(BotDifficulty::Heuristic, &view, &mut thread_rng()),
+            BotDecision::Play { card_id: "s".to_string() }
+        );
+
+        // 随机机器人不会主动打出拆除卡
+        for _ in 0..20 {
+            assert_ne!(
+                decide(BotDifficulty::Random, &view, &mut thread_rng()),
+                BotDecision::Play { card_id: "d".to_string() }
+            );
+        }
+    }
+
+    #[test]
+    fn test_probability_uses_risk() {
+        let hand = vec![card("f", CardType::SeeTheFuture), card("sh", CardType::Shuffle), card("a", CardType::Attack)];
+        let mut view = BotView { hand: &hand, deck_count: 20, kittens_in_deck: 1, known_top: &[] };
+        assert_eq!(decide(BotDifficulty::Probability, &view, &mut thread_rng()), BotDecision::Draw);
+
+        // 没有拆除卡且风险较高时先偷看
+        view.deck_count = 4;
+        assert_eq!(
+            decide(BotDifficulty::Probability, &view, &mut thread_rng()),
+            BotDecision::Play { card_id: "f".to_string() }
+        );
+
+        view.known_top = &[CardType::Skip];
+        assert_eq!(decide(BotDifficulty::Probability, &view, &mut thread_rng()), BotDecision::Draw);
+
+        view.known_top = &[CardType::ExplodingKitten];
+        assert_eq!(
+            decide(BotDifficulty::Probability, &view, &mut thread_rng()),
+            BotDecision::Play { card_id: "sh".to_string() }
+        );
+    }
+}
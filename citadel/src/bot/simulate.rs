@@ -0,0 +1,371 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 机器人对局模拟
+//!
+//! # 概述
+//! 在本地运行机器人对战，不读写游戏缓存，也不发送WebSocket消息。
+//! 牌组、发牌、动作校验和胜负判定使用 `GameMode` 的实现，卡牌效果与MatchService一致：
+//! - 跳过和攻击结束回合，不需要抽牌
+//! - 洗牌后所有玩家已知的牌堆顶部失效
+//! - 偷看未来可以看到牌堆顶部三张牌
+//! - 抢夺从随机一名其他玩家手中随机拿走一张牌
+//! - 抽到爆炸猫时自动使用拆除卡，爆炸猫放回牌堆顶部；没有拆除卡则出局
+//! - 其他卡牌打出后没有效果
+//!
+//! 每局按局数轮换座位，减少先手对胜率的影响。
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use anyhow::{anyhow, bail, Result};
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+
+use super::{decide, BotDecision, BotDifficulty, BotView};
+use crate::game_mode::{self, GameMode, PlayerAction};
+use crate::gaming::{Card, CardType, MatchData, MatchState, MatchType, UserInfo};
+
+/// 单局最多回合数，超过后按平局处理
+const MAX_TURNS: usize = 1000;
+
+/// 单个回合最多出牌次数
+const MAX_PLAYS_PER_TURN: usize = 50;
+
+/// 模拟配置
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    /// 游戏模式ID
+    pub mode: String,
+    /// 模拟局数
+    pub matches: usize,
+    /// 每局玩家数
+    pub players: usize,
+    /// 各座位机器人的难度，按座位顺序循环使用
+    pub bots: Vec<BotDifficulty>,
+}
+
+/// 单局结果
+#[derive(Debug, Clone)]
+pub struct MatchOutcome {
+    /// 胜利者的难度，未分出胜负时为None
+    pub winner: Option<BotDifficulty>,
+    /// 回合数
+    pub turns: usize,
+}
+
+/// 单个难度的统计
+#[derive(Debug, Clone, Default)]
+pub struct DifficultyStats {
+    /// 参与的座位次数
+    pub seats: usize,
+    /// 获胜局数
+    pub wins: usize,
+}
+
+/// 模拟结果
+#[derive(Debug, Clone, Default)]
+pub struct SimulationReport {
+    /// 模拟局数
+    pub matches: usize,
+    /// 未分出胜负的局数
+    pub unfinished: usize,
+    /// 分出胜负的对局总回合数
+    pub total_turns: usize,
+    /// 各难度的统计
+    pub by_difficulty: HashMap<BotDifficulty, DifficultyStats>,
+}
+
+impl SimulationReport {
+    /// 分出胜负的对局平均回合数
+    pub fn average_turns(&self) -> f64 {
+        let finished = self.matches - self.unfinished;
+        if finished == 0 {
+            return 0.0;
+        }
+        self.total_turns as f64 / finished as f64
+    }
+}
+
+impl Display for SimulationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "共模拟 {} 局，未分出胜负 {} 局", self.matches, self.unfinished)?;
+        writeln!(f, "平均对局长度: {:.1} 回合", self.average_turns())?;
+        let mut difficulties = self.by_difficulty.iter().collect::<Vec<_>>();
+        difficulties.sort_by_key(|(difficulty, _)| difficulty.as_str());
+        for (difficulty, stats) in difficulties {
+            // 按座位计算胜率，同一难度占多个座位时可以与其他难度直接比较
+            let win_rate = if stats.seats == 0 {
+                0.0
+            } else {
+                stats.wins as f64 * 100.0 / stats.seats as f64
+            };
+            writeln!(
+                f,
+                "  {}: 座位 {}，获胜 {}，每座胜率 {:.1}%",
+                difficulty.as_str(),
+                stats.seats,
+                stats.wins,
+                win_rate
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * 运行机器人对局模拟
+ *
+ * 参数:
+ * @param config - 模拟配置
+ *
+ * 返回:
+ * 各难度的胜率和平均对局长度
+ */
+pub fn run_simulation(config: &SimulationConfig) -> Result<SimulationReport> {
+    let mode = game_mode::global_game_modes()
+        .get(&config.mode)
+        .ok_or_else(|| anyhow!("不支持的游戏模式: {}", config.mode))?;
+    let (min_players, max_players) = mode.player_range();
+    if config.players < min_players || config.players > max_players {
+        bail!("玩家数必须在 {} 到 {} 之间", min_players, max_players);
+    }
+    if config.bots.is_empty() {
+        bail!("至少需要指定一种机器人难度");
+    }
+
+    let mut report = SimulationReport {
+        matches: config.matches,
+        ..Default::default()
+    };
+    for round in 0..config.matches {
+        // 按局数轮换座位
+        let seats = (0..config.players)
+            .map(|i| config.bots[(i + round) % config.bots.len()])
+            .collect::<Vec<_>>();
+        for difficulty in &seats {
+            report.by_difficulty.entry(*difficulty).or_default().seats += 1;
+        }
+
+        let outcome = play_match(mode.as_ref(), &seats)?;
+        match outcome.winner {
+            Some(difficulty) => {
+                report.by_difficulty.entry(difficulty).or_default().wins += 1;
+                report.total_turns += outcome.turns;
+            }
+            None => report.unfinished += 1,
+        }
+    }
+    Ok(report)
+}
+
+/// 单局模拟中的牌局状态
+struct Simulation<'a> {
+    mode: &'a dyn GameMode,
+    match_data: MatchData,
+    /// 玩家ID对应的机器人难度
+    difficulties: HashMap<String, BotDifficulty>,
+    /// 各玩家已知的牌堆顶部，第一张为下一张要抽的牌
+    known_top: HashMap<String, Vec<CardType>>,
+}
+
+/**
+ * 模拟一局机器人对战
+ *
+ * 参数:
+ * @param mode - 游戏模式
+ * @param seats - 按座位顺序排列的机器人难度
+ *
+ * 返回:
+ * 胜利者的难度和回合数
+ */
+pub fn play_match(mode: &dyn GameMode, seats: &[BotDifficulty]) -> Result<MatchOutcome> {
+    let players = seats
+        .iter()
+        .enumerate()
+        .map(|(i, difficulty)| UserInfo {
+            id: format!("bot-{}", i),
+            name: format!("{}-{}", difficulty.as_str(), i),
+            rating: 0,
+            avatar_url: None,
+            provisional: false,
+        })
+        .collect::<Vec<_>>();
+    let mut match_data = MatchData::new("simulation".to_string(), MatchType::Private, &players, 0);
+    match_data.mode = mode.id().to_string();
+    match_data.deck = mode.build_deck(players.len(), &mut thread_rng());
+    mode.deal(&mut match_data);
+    match_data.state = MatchState::InProgress;
+    match_data.players[0].is_turn = true;
+
+    let mut simulation = Simulation {
+        mode,
+        match_data,
+        difficulties: players.iter().map(|p| p.id.clone()).zip(seats.iter().copied()).collect(),
+        known_top: HashMap::new(),
+    };
+    let mut rng = thread_rng();
+    for turn in 1..=MAX_TURNS {
+        // 爆炸猫在开局被发到手牌中时牌堆可能抽完，按未分出胜负处理
+        if simulation.match_data.deck.is_empty() {
+            return Ok(MatchOutcome { winner: None, turns: turn - 1 });
+        }
+        simulation.play_turn(&mut rng)?;
+        if let Some(winner_id) = simulation.mode.winner(&simulation.match_data) {
+            return Ok(MatchOutcome {
+                winner: simulation.difficulties.get(&winner_id).copied(),
+                turns: turn,
+            });
+        }
+    }
+    Ok(MatchOutcome { winner: None, turns: MAX_TURNS })
+}
+
+impl Simulation<'_> {
+    /// 当前回合玩家的ID
+    fn current_player(&self) -> String {
+        self.match_data.players[self.match_data.turn_index].user.id.clone()
+    }
+
+    /// 当前回合玩家的机器人视角
+    fn view_for<'b>(&'b self, user_id: &str, hand: &'b [Card]) -> BotView<'b> {
+        // 牌组中的爆炸猫比玩家数少一张，出局的玩家带走一张，因此按存活玩家数估算
+        let kittens_in_hand = hand.iter().filter(|c| c.card_type == CardType::ExplodingKitten).count();
+        BotView {
+            hand,
+            deck_count: self.match_data.deck.len(),
+            kittens_in_deck: (self.match_data.players.len() - 1).saturating_sub(kittens_in_hand),
+            known_top: self.known_top.get(user_id).map(Vec::as_slice).unwrap_or(&[]),
+        }
+    }
+
+    /// 当前回合玩家行动，直到抽牌或跳过回合
+    fn play_turn<R: Rng>(&mut self, rng: &mut R) -> Result<()> {
+        let user_id = self.current_player();
+        let difficulty = self.difficulties[&user_id];
+        for _ in 0..MAX_PLAYS_PER_TURN {
+            let player_index = self.match_data.turn_index;
+            let hand = self.match_data.players[player_index].hand.clone();
+            let decision = decide(difficulty, &self.view_for(&user_id, &hand), rng);
+            let card_id = match decision {
+                BotDecision::Play { card_id } => card_id,
+                BotDecision::Draw => return self.draw(&user_id),
+            };
+
+            self.mode.validate_action(&self.match_data, &user_id, PlayerAction::Play { card_id: &card_id })?;
+            let card_index = hand.iter().position(|c| c.id == card_id).unwrap();
+            let card = self.match_data.players[player_index].hand.remove(card_index);
+            let card_type = card.card_type.clone();
+            self.match_data.discard_pile.push(card);
+
+            match card_type {
+                CardType::Skip | CardType::Attack => {
+                    self.change_turn();
+                    return Ok(());
+                }
+                CardType::Shuffle => {
+                    self.match_data.deck.shuffle(rng);
+                    self.known_top.clear();
+                }
+                CardType::SeeTheFuture => {
+                    let top = self.match_data.deck.iter().rev().take(3).map(|c| c.card_type.clone()).collect();
+                    self.known_top.insert(user_id.clone(), top);
+                }
+                CardType::Favor => self.steal_random_card(player_index, rng),
+                _ => {}
+            }
+        }
+        // 出牌次数达到上限时强制抽牌，避免对局无法结束
+        self.draw(&user_id)
+    }
+
+    /// 抽牌并结束回合
+    fn draw(&mut self, user_id: &str) -> Result<()> {
+        self.mode.validate_action(&self.match_data, user_id, PlayerAction::Draw)?;
+        let card = self.match_data.deck.pop().unwrap();
+        self.match_data.draw_count += 1;
+        for known in self.known_top.values_mut() {
+            if !known.is_empty() {
+                known.remove(0);
+            }
+        }
+
+        let player_index = self.match_data.turn_index;
+        if card.card_type != CardType::ExplodingKitten {
+            self.match_data.players[player_index].hand.push(card);
+            self.change_turn();
+            return Ok(());
+        }
+
+        let player = &mut self.match_data.players[player_index];
+        match player.hand.iter().position(|c| c.card_type == CardType::Defuse) {
+            Some(defuse_index) => {
+                let defuse = player.hand.remove(defuse_index);
+                self.match_data.discard_pile.push(defuse);
+                // 爆炸猫放回牌堆顶部，所有玩家都知道
+                self.match_data.deck.push(card);
+                for player in &self.match_data.players {
+                    self.known_top.entry(player.user.id.clone()).or_default().insert(0, CardType::ExplodingKitten);
+                }
+                self.change_turn();
+            }
+            None => {
+                let mut player = self.match_data.players.remove(player_index);
+                player.is_active = false;
+                player.is_turn = false;
+                self.known_top.remove(&player.user.id);
+                self.match_data.out.push(player);
+                if !self.match_data.players.is_empty() {
+                    // 出局玩家的下一位接着行动
+                    self.match_data.turn_index = player_index % self.match_data.players.len();
+                    self.match_data.players[self.match_data.turn_index].is_turn = true;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 切换到下一名玩家
+    fn change_turn(&mut self) {
+        let players = &mut self.match_data.players;
+        players[self.match_data.turn_index].is_turn = false;
+        self.match_data.turn_index = (self.match_data.turn_index + 1) % players.len();
+        players[self.match_data.turn_index].is_turn = true;
+    }
+
+    /// 从随机一名有手牌的其他玩家手中随机拿走一张牌
+    fn steal_random_card<R: Rng>(&mut self, player_index: usize, rng: &mut R) {
+        let targets = (0..self.match_data.players.len())
+            .filter(|i| *i != player_index && !self.match_data.players[*i].hand.is_empty())
+            .collect::<Vec<_>>();
+        let Some(&target_index) = targets.choose(rng) else {
+            return;
+        };
+        let target_hand = &mut self.match_data.players[target_index].hand;
+        let card = target_hand.remove(rng.gen_range(0..target_hand.len()));
+        self.match_data.players[player_index].hand.push(card);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_simulation() {
+        let config = SimulationConfig {
+            mode: game_mode::DEFAULT_MODE.to_string(),
+            matches: 20,
+            players: 3,
+            bots: vec![BotDifficulty::Random, BotDifficulty::Probability],
+        };
+        let report = run_simulation(&config).unwrap();
+        let seats = report.by_difficulty.values().map(|s| s.seats).sum::<usize>();
+        let wins = report.by_difficulty.values().map(|s| s.wins).sum::<usize>();
+        assert_eq!(seats, 60);
+        assert_eq!(wins + report.unfinished, 20);
+
+        let invalid = SimulationConfig { players: 1, ..config };
+        assert!(run_simulation(&invalid).is_err());
+    }
+}
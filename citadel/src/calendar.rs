@@ -0,0 +1,828 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 定期活动日历
+//!
+//! # 概述
+//! 运营需要按固定时间举办活动，如每天18:00（UTC）的锦标赛、周末的双倍奖励。本模块定义定期活动，
+//! 由后台调度任务按时开放报名、开始锦标赛，并为客户端提供活动日历：
+//! - 活动按每天或每周的固定时间（UTC）重复，每次重复称为一场，场次ID为 `活动ID@开始时间`
+//! - 锦标赛：开始前 `registrationLeadMins` 分钟开放报名，向所有在线客户端广播 `calendar:registration-open`；
+//!   开始时把报名的玩家平均分成每局不超过 `playersPerMatch` 人的公开对局，
+//!   通过 `calendar:tournament-match` 通知每位玩家自己的对局ID，分组后只剩一人的玩家不参赛
+//! - 奖励加成：活动期间 `active_multiplier` 返回加成倍率，多个加成同时进行时取最大值，供发放奖励的模块使用
+//!
+//! 活动定义写入持久化存储的设置（`calendar.events`），重启后恢复；报名名单只保存在内存中，重启后丢失。
+//!
+//! # 接口
+//! - `GET /v1/events?days=7`：未来若干天（默认7，最多31）的场次，包含进行中的场次
+//! - `POST /v1/events/:occurrence_id/register`：报名开放中的锦标赛，需要登录
+//! - `POST /admin/events`、`DELETE /admin/events/:id`：创建和删除活动，需要 `X-Admin-Key` 请求头
+//!
+//! # 消息格式
+//! ```json
+//! // POST /admin/events
+//! { "name": "每日锦标赛", "kind": { "type": "tournament", "playersPerMatch": 4, "maxPlayers": 64 },
+//!   "recurrence": { "type": "daily", "hour": 18, "minute": 0 }, "durationMins": 60, "registrationLeadMins": 30 }
+//! { "name": "双倍奖励周末", "kind": { "type": "boost", "multiplier": 2 },
+//!   "recurrence": { "type": "weekly", "weekday": 5, "hour": 0, "minute": 0 }, "durationMins": 2880 }
+//! // 服务器 -> 客户端
+//! { "event": "calendar:registration-open", "data": { "id": "...@1700000000000", "name": "每日锦标赛", "startsAt": 1700000000000, ... } }
+//! { "event": "calendar:tournament-match", "data": { "occurrenceId": "...@1700000000000", "matchId": "..." } }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, Query},
+    http::{HeaderMap, StatusCode},
+    routing::{delete, get, post},
+    Extension, Json, Router,
+};
+use chrono::{DateTime, Datelike, Days};
+use once_cell::sync::OnceCell;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::errors::{ErrorResponse, InternalError};
+use crate::externals::current_epoch_time;
+use crate::gaming::{self as match_game, MatchType, UserInfo, MAX_MATCH_PLAYERS};
+use crate::key_audit::check_admin_key;
+use crate::session_login::{SessionUser, SESSION_USER_KEY};
+use crate::storage;
+use crate::ws::ConnectionManager;
+use crate::AppState;
+
+/// 事件名称
+pub mod events {
+    /// 锦标赛开放报名，广播给所有在线客户端
+    pub const REGISTRATION_OPEN: &str = "calendar:registration-open";
+    /// 锦标赛开始，通知玩家自己的对局
+    pub const TOURNAMENT_MATCH: &str = "calendar:tournament-match";
+}
+
+/// 活动定义在存储设置中的键
+pub const SETTING_KEY: &str = "calendar.events";
+/// 调度任务的检查间隔
+pub const TICK_INTERVAL: Duration = Duration::from_secs(15);
+/// 日历默认查询的天数
+pub const DEFAULT_DAYS: u64 = 7;
+/// 日历最多查询的天数
+pub const MAX_DAYS: u64 = 31;
+/// 活动名称的最大长度（字符）
+pub const MAX_NAME_LEN: usize = 64;
+/// 锦标赛的报名人数上限
+pub const MAX_TOURNAMENT_PLAYERS: usize = 1024;
+/// 奖励加成的最大倍率
+pub const MAX_MULTIPLIER: u32 = 10;
+
+const MINUTE_MS: u64 = 60 * 1000;
+const DAY_MS: u64 = 24 * 60 * MINUTE_MS;
+
+/// 重复规则，时间均为UTC
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Recurrence {
+    /// 每天
+    Daily { hour: u32, minute: u32 },
+    /// 每周，weekday为0（周一）到6（周日）
+    Weekly { weekday: u32, hour: u32, minute: u32 },
+}
+
+impl Recurrence {
+    /// 重复周期（毫秒）
+    pub fn period_ms(&self) -> u64 {
+        match self {
+            Self::Daily { .. } => DAY_MS,
+            Self::Weekly { .. } => 7 * DAY_MS,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let (weekday, hour, minute) = match *self {
+            Self::Daily { hour, minute } => (0, hour, minute),
+            Self::Weekly { weekday, hour, minute } => (weekday, hour, minute),
+        };
+        if weekday > 6 || hour > 23 || minute > 59 {
+            return Err("重复时间无效".to_string());
+        }
+        Ok(())
+    }
+
+    /// 不早于 `after` 的第一场的开始时间
+    pub fn next_start(&self, after: u64) -> u64 {
+        let Some(at) = DateTime::from_timestamp_millis(after as i64) else {
+            return after;
+        };
+        let (offset_days, hour, minute) = match *self {
+            Self::Daily { hour, minute } => (0, hour, minute),
+            Self::Weekly { weekday, hour, minute } => {
+                let today = at.weekday().num_days_from_monday();
+                ((weekday + 7 - today) % 7, hour, minute)
+            }
+        };
+        let start = (at.date_naive() + Days::new(offset_days as u64))
+            .and_hms_opt(hour, minute, 0)
+            .map(|start| start.and_utc().timestamp_millis() as u64)
+            .unwrap_or(after);
+        if start < after {
+            start + self.period_ms()
+        } else {
+            start
+        }
+    }
+}
+
+/// 活动类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum EventKind {
+    /// 锦标赛
+    Tournament {
+        #[serde(rename = "playersPerMatch")]
+        players_per_match: usize,
+        #[serde(rename = "maxPlayers")]
+        max_players: usize,
+    },
+    /// 奖励加成
+    Boost { multiplier: u32 },
+}
+
+/// 定期活动
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecurringEvent {
+    pub id: String,
+    pub name: String,
+    pub kind: EventKind,
+    pub recurrence: Recurrence,
+    /// 每场持续的分钟数
+    pub duration_mins: u64,
+    /// 锦标赛开始前开放报名的分钟数
+    pub registration_lead_mins: u64,
+    pub created_at: u64,
+}
+
+impl RecurringEvent {
+    fn duration_ms(&self) -> u64 {
+        self.duration_mins * MINUTE_MS
+    }
+
+    /// 开放报名的时间，只有锦标赛需要报名
+    fn registration_opens_at(&self, starts_at: u64) -> Option<u64> {
+        match self.kind {
+            EventKind::Tournament { .. } => Some(starts_at.saturating_sub(self.registration_lead_mins * MINUTE_MS)),
+            EventKind::Boost { .. } => None,
+        }
+    }
+
+    /// 未结束的第一场的开始时间，可能已经开始
+    fn current_start(&self, now: u64) -> u64 {
+        self.recurrence.next_start((now + 1).saturating_sub(self.duration_ms()))
+    }
+}
+
+/// `POST /admin/events` 请求
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateEventRequest {
+    pub name: String,
+    pub kind: EventKind,
+    pub recurrence: Recurrence,
+    pub duration_mins: u64,
+    /// 锦标赛开始前开放报名的分钟数，默认30
+    #[serde(default)]
+    pub registration_lead_mins: Option<u64>,
+}
+
+impl CreateEventRequest {
+    /**
+     * 校验请求并生成活动
+     *
+     * 参数:
+     * @param now - 当前时间
+     *
+     * 返回:
+     * 活动，参数无效时返回错误说明
+     */
+    pub fn into_event(self, now: u64) -> Result<RecurringEvent, String> {
+        let name = self.name.trim().to_string();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+            return Err(format!("活动名称不能为空且不能超过 {} 个字符", MAX_NAME_LEN));
+        }
+        self.recurrence.validate()?;
+        match self.kind {
+            EventKind::Tournament { players_per_match, max_players } => {
+                if !(2..=MAX_MATCH_PLAYERS).contains(&players_per_match) {
+                    return Err(format!("每局人数必须在 2 到 {} 之间", MAX_MATCH_PLAYERS));
+                }
+                if !(2..=MAX_TOURNAMENT_PLAYERS).contains(&max_players) {
+                    return Err(format!("报名人数上限必须在 2 到 {} 之间", MAX_TOURNAMENT_PLAYERS));
+                }
+            }
+            EventKind::Boost { multiplier } => {
+                if !(2..=MAX_MULTIPLIER).contains(&multiplier) {
+                    return Err(format!("加成倍率必须在 2 到 {} 之间", MAX_MULTIPLIER));
+                }
+            }
+        }
+        let registration_lead_mins = match self.kind {
+            EventKind::Tournament { .. } => self.registration_lead_mins.unwrap_or(30),
+            EventKind::Boost { .. } => 0,
+        };
+        // 报名和进行时间不能跨到下一场
+        let period_mins = self.recurrence.period_ms() / MINUTE_MS;
+        if self.duration_mins == 0 || self.duration_mins + registration_lead_mins > period_mins {
+            return Err("持续时间和报名时间之和不能超过重复周期".to_string());
+        }
+        Ok(RecurringEvent {
+            id: Uuid::new_v4().to_string(),
+            name,
+            kind: self.kind,
+            recurrence: self.recurrence,
+            duration_mins: self.duration_mins,
+            registration_lead_mins,
+            created_at: now,
+        })
+    }
+}
+
+/// 场次阶段
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventPhase {
+    /// 尚未开始，也未开放报名
+    Upcoming,
+    /// 开放报名中
+    Registration,
+    /// 进行中
+    Running,
+    /// 已结束
+    Ended,
+}
+
+/// 一场活动
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EventOccurrence {
+    /// 场次ID，`活动ID@开始时间`
+    pub id: String,
+    pub event_id: String,
+    pub name: String,
+    pub kind: EventKind,
+    pub starts_at: u64,
+    pub ends_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registration_opens_at: Option<u64>,
+    pub phase: EventPhase,
+    /// 已报名人数
+    pub registered: usize,
+    /// 锦标赛开始后创建的对局
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub matches: Vec<String>,
+}
+
+/// 场次的运行状态
+#[derive(Debug, Default)]
+struct OccurrenceState {
+    registered: Vec<UserInfo>,
+    /// 已广播开放报名
+    announced: bool,
+    /// 锦标赛已开始
+    started: bool,
+    matches: Vec<String>,
+}
+
+/// 调度任务需要执行的操作
+#[derive(Debug)]
+enum DueAction {
+    /// 广播开放报名
+    OpenRegistration(EventOccurrence),
+    /// 开始锦标赛，按分组创建对局
+    StartTournament { occurrence_id: String, groups: Vec<Vec<UserInfo>> },
+}
+
+/**
+ * 把报名的玩家平均分组
+ *
+ * 参数:
+ * @param players - 按报名顺序排列的玩家
+ * @param per_match - 每局人数上限
+ *
+ * 返回:
+ * 每局的玩家，人数相差不超过一人；只有一人的分组被丢弃
+ */
+fn group_players(players: &[UserInfo], per_match: usize) -> Vec<Vec<UserInfo>> {
+    let count = players.len().div_ceil(per_match.max(1));
+    let mut groups = vec![Vec::new(); count];
+    for (index, player) in players.iter().enumerate() {
+        groups[index % count].push(player.clone());
+    }
+    groups.retain(|group| group.len() >= 2);
+    groups
+}
+
+/// 活动日历服务
+pub struct CalendarService {
+    connection_manager: Arc<ConnectionManager>,
+    events: RwLock<HashMap<String, RecurringEvent>>,
+    occurrences: Mutex<HashMap<String, OccurrenceState>>,
+}
+
+impl CalendarService {
+    pub fn new(connection_manager: Arc<ConnectionManager>) -> Self {
+        Self {
+            connection_manager,
+            events: RwLock::new(HashMap::new()),
+            occurrences: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 从持久化存储恢复活动定义
+    pub async fn load(&self) {
+        let Some(storage) = storage::global_storage() else {
+            return;
+        };
+        match storage.get_setting(SETTING_KEY).await {
+            Ok(Some(value)) => match serde_json::from_str::<Vec<RecurringEvent>>(&value) {
+                Ok(events) => {
+                    info!("恢复定期活动 {} 个", events.len());
+                    *self.events.write() = events.into_iter().map(|e| (e.id.clone(), e)).collect();
+                }
+                Err(e) => error!("解析定期活动失败: {}", e),
+            },
+            Ok(None) => {}
+            Err(e) => error!("读取定期活动失败: {}", e),
+        }
+    }
+
+    /// 把活动定义写入持久化存储
+    async fn persist(&self) {
+        let Some(storage) = storage::global_storage() else {
+            return;
+        };
+        let events = self.events.read().values().cloned().collect::<Vec<_>>();
+        let result = match serde_json::to_string(&events) {
+            Ok(value) => storage.put_setting(SETTING_KEY, &value).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            error!("保存定期活动失败: {}", e);
+        }
+    }
+
+    /// 添加活动
+    pub async fn add(&self, event: RecurringEvent) {
+        info!("添加定期活动 {}: {}", event.id, event.name);
+        self.events.write().insert(event.id.clone(), event);
+        self.persist().await;
+    }
+
+    /// 删除活动，已开始的锦标赛对局不受影响
+    pub async fn remove(&self, event_id: &str) -> bool {
+        if self.events.write().remove(event_id).is_none() {
+            return false;
+        }
+        let prefix = format!("{}@", event_id);
+        self.occurrences.lock().retain(|id, _| !id.starts_with(&prefix));
+        self.persist().await;
+        true
+    }
+
+    fn occurrence(&self, event: &RecurringEvent, starts_at: u64, now: u64) -> EventOccurrence {
+        let id = format!("{}@{}", event.id, starts_at);
+        let ends_at = starts_at + event.duration_ms();
+        let registration_opens_at = event.registration_opens_at(starts_at);
+        let phase = if now >= ends_at {
+            EventPhase::Ended
+        } else if now >= starts_at {
+            EventPhase::Running
+        } else if registration_opens_at.is_some_and(|opens_at| now >= opens_at) {
+            EventPhase::Registration
+        } else {
+            EventPhase::Upcoming
+        };
+        let occurrences = self.occurrences.lock();
+        let state = occurrences.get(&id);
+        EventOccurrence {
+            event_id: event.id.clone(),
+            name: event.name.clone(),
+            kind: event.kind,
+            starts_at,
+            ends_at,
+            registration_opens_at,
+            phase,
+            registered: state.map_or(0, |s| s.registered.len()),
+            matches: state.map(|s| s.matches.clone()).unwrap_or_default(),
+            id,
+        }
+    }
+
+    /**
+     * 列出未来若干天的场次
+     *
+     * 参数:
+     * @param now - 当前时间
+     * @param days - 查询的天数
+     *
+     * 返回:
+     * 进行中和在查询范围内开始的场次，按开始时间排列
+     */
+    pub fn list(&self, now: u64, days: u64) -> Vec<EventOccurrence> {
+        let until = now + days.clamp(1, MAX_DAYS) * DAY_MS;
+        let events = self.events.read().values().cloned().collect::<Vec<_>>();
+        let mut occurrences = Vec::new();
+        for event in events {
+            let mut starts_at = event.current_start(now);
+            while starts_at < until {
+                occurrences.push(self.occurrence(&event, starts_at, now));
+                starts_at = event.recurrence.next_start(starts_at + 1);
+            }
+        }
+        occurrences.sort_by(|a, b| a.starts_at.cmp(&b.starts_at).then_with(|| a.id.cmp(&b.id)));
+        occurrences
+    }
+
+    /// 当前的奖励加成倍率，没有进行中的加成时为1
+    pub fn active_multiplier(&self, now: u64) -> u32 {
+        self.events
+            .read()
+            .values()
+            .filter_map(|event| match event.kind {
+                EventKind::Boost { multiplier } if event.current_start(now) <= now => Some(multiplier),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(1)
+    }
+
+    /**
+     * 报名锦标赛
+     *
+     * 参数:
+     * @param occurrence_id - 场次ID
+     * @param user - 报名的玩家，重复报名时不变
+     * @param now - 当前时间
+     *
+     * 返回:
+     * 报名后的场次，场次不存在、未开放报名或人数已满时返回错误说明
+     */
+    pub fn register(&self, occurrence_id: &str, user: UserInfo, now: u64) -> Result<EventOccurrence, String> {
+        let (event_id, starts_at) = occurrence_id
+            .rsplit_once('@')
+            .and_then(|(event_id, starts_at)| Some((event_id, starts_at.parse::<u64>().ok()?)))
+            .ok_or_else(|| "场次ID无效".to_string())?;
+        let event = self.events.read().get(event_id).cloned().ok_or_else(|| "活动不存在".to_string())?;
+        let EventKind::Tournament { max_players, .. } = event.kind else {
+            return Err("该活动不需要报名".to_string());
+        };
+        if event.recurrence.next_start(starts_at) != starts_at {
+            return Err("场次不存在".to_string());
+        }
+        if self.occurrence(&event, starts_at, now).phase != EventPhase::Registration {
+            return Err("该场次未开放报名".to_string());
+        }
+        {
+            let mut occurrences = self.occurrences.lock();
+            let state = occurrences.entry(occurrence_id.to_string()).or_default();
+            if !state.registered.iter().any(|p| p.id == user.id) {
+                if state.registered.len() >= max_players {
+                    return Err("报名人数已满".to_string());
+                }
+                info!("用户 {} 报名锦标赛 {}", user.id, occurrence_id);
+                state.registered.push(user);
+            }
+        }
+        Ok(self.occurrence(&event, starts_at, now))
+    }
+
+    /// 找出需要开放报名和开始的锦标赛，并清理已结束的场次
+    fn due(&self, now: u64) -> Vec<DueAction> {
+        let events = self.events.read().values().cloned().collect::<Vec<_>>();
+        let mut actions = Vec::new();
+        for event in events {
+            let EventKind::Tournament { players_per_match, .. } = event.kind else {
+                continue;
+            };
+            let occurrence = self.occurrence(&event, event.current_start(now), now);
+            let mut occurrences = self.occurrences.lock();
+            match occurrence.phase {
+                EventPhase::Registration => {
+                    let state = occurrences.entry(occurrence.id.clone()).or_default();
+                    if !state.announced {
+                        state.announced = true;
+                        actions.push(DueAction::OpenRegistration(occurrence));
+                    }
+                }
+                EventPhase::Running => {
+                    let state = occurrences.entry(occurrence.id.clone()).or_default();
+                    if !state.started {
+                        state.started = true;
+                        actions.push(DueAction::StartTournament {
+                            groups: group_players(&state.registered, players_per_match),
+                            occurrence_id: occurrence.id,
+                        });
+                    }
+                }
+                EventPhase::Upcoming | EventPhase::Ended => {}
+            }
+        }
+        // 场次ID中的开始时间加上最长的周期后一定已经结束
+        self.occurrences.lock().retain(|id, _| {
+            id.rsplit_once('@')
+                .and_then(|(_, starts_at)| starts_at.parse::<u64>().ok())
+                .is_some_and(|starts_at| starts_at + 7 * DAY_MS > now)
+        });
+        actions
+    }
+
+    /// 执行到期的操作
+    async fn run_due(&self, now: u64) {
+        for action in self.due(now) {
+            match action {
+                DueAction::OpenRegistration(occurrence) => {
+                    info!("锦标赛 {} 开放报名", occurrence.id);
+                    let data = serde_json::to_value(&occurrence).ok();
+                    if let Err(e) = self.connection_manager.broadcast_to_all(events::REGISTRATION_OPEN, data).await {
+                        error!("广播锦标赛 {} 开放报名失败: {}", occurrence.id, e);
+                    }
+                }
+                DueAction::StartTournament { occurrence_id, groups } => {
+                    self.start_tournament(&occurrence_id, groups).await;
+                }
+            }
+        }
+    }
+
+    /// 为每个分组创建对局并通知玩家
+    async fn start_tournament(&self, occurrence_id: &str, groups: Vec<Vec<UserInfo>>) {
+        let Some(match_service) = match_game::global_match_service() else {
+            warn!("对局服务未初始化，锦标赛 {} 无法开始", occurrence_id);
+            return;
+        };
+        info!("锦标赛 {} 开始，共 {} 局", occurrence_id, groups.len());
+        for players in groups {
+            let match_data = match match_service.create_match(MatchType::Public, players.clone()).await {
+                Ok(match_data) => match_data,
+                Err(e) => {
+                    error!("创建锦标赛 {} 的对局失败: {}", occurrence_id, e);
+                    continue;
+                }
+            };
+            if let Some(state) = self.occurrences.lock().get_mut(occurrence_id) {
+                state.matches.push(match_data.id.clone());
+            }
+            let Some(passport) = crate::ws::global_passport_state() else {
+                continue;
+            };
+            let data = serde_json::json!({
+                "occurrenceId": occurrence_id,
+                "matchId": match_data.id,
+            });
+            for player in &players {
+                if let Err(e) = passport.send_event_to_user(&player.id, events::TOURNAMENT_MATCH, Some(data.clone())).await {
+                    warn!("通知玩家 {} 锦标赛对局失败: {}", player.id, e);
+                }
+            }
+        }
+    }
+
+    /// 启动调度任务，先恢复活动定义
+    pub fn spawn_scheduler(self: &Arc<Self>) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            service.load().await;
+            let mut ticker = tokio::time::interval(TICK_INTERVAL);
+            loop {
+                ticker.tick().await;
+                service.run_due(current_epoch_time()).await;
+            }
+        });
+    }
+}
+
+// 用于存储全局CalendarService实例的静态变量
+static GLOBAL_CALENDAR_SERVICE: OnceCell<Arc<CalendarService>> = OnceCell::new();
+
+/// 初始化活动日历服务并启动调度任务
+pub fn init_calendar_service(connection_manager: Arc<ConnectionManager>) -> Arc<CalendarService> {
+    GLOBAL_CALENDAR_SERVICE
+        .get_or_init(|| {
+            let service = Arc::new(CalendarService::new(connection_manager));
+            service.spawn_scheduler();
+            service
+        })
+        .clone()
+}
+
+/// 获取全局活动日历服务
+pub fn global_calendar_service() -> Option<Arc<CalendarService>> {
+    GLOBAL_CALENDAR_SERVICE.get().cloned()
+}
+
+/// 查询活动日历
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CalendarQuery {
+    /// 查询的天数，默认7，最多31
+    pub days: Option<u64>,
+}
+
+/// 活动日历响应
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarResponse {
+    pub events: Vec<EventOccurrence>,
+    /// 当前的奖励加成倍率
+    pub active_multiplier: u32,
+}
+
+/// 获取活动日历
+#[utoipa::path(
+    get,
+    path = "/v1/events",
+    tag = "events",
+    params(CalendarQuery),
+    responses(
+        (status = 200, description = "进行中和即将开始的场次", body = CalendarResponse),
+        (status = 500, description = "日历服务未初始化", body = ErrorResponse),
+    )
+)]
+pub async fn handle_get_calendar(Query(query): Query<CalendarQuery>) -> Result<Json<CalendarResponse>, InternalError> {
+    let service = global_calendar_service().ok_or(InternalError::Failure)?;
+    let now = current_epoch_time();
+    Ok(Json(CalendarResponse {
+        events: service.list(now, query.days.unwrap_or(DEFAULT_DAYS)),
+        active_multiplier: service.active_multiplier(now),
+    }))
+}
+
+/// 报名锦标赛
+#[utoipa::path(
+    post,
+    path = "/v1/events/{occurrence_id}/register",
+    tag = "events",
+    params(("occurrence_id" = String, Path, description = "场次ID")),
+    responses(
+        (status = 200, description = "报名后的场次", body = EventOccurrence),
+        (status = 400, description = "场次不存在、未开放报名或人数已满", body = ErrorResponse),
+        (status = 403, description = "未登录或没有游戏档案", body = ErrorResponse),
+    )
+)]
+pub async fn handle_register(
+    Extension(session): Extension<Session>,
+    Path(occurrence_id): Path<String>,
+) -> Result<Json<EventOccurrence>, InternalError> {
+    let user = session
+        .get::<SessionUser>(SESSION_USER_KEY)
+        .await?
+        .ok_or(InternalError::Unauthorized)?;
+    let profile = user.profile.ok_or(InternalError::Unauthorized)?;
+    let service = global_calendar_service().ok_or(InternalError::Failure)?;
+    let user_id = profile.id.to_string();
+    let name = format!("User-{}", user_id.trim_start_matches("0x").chars().take(8).collect::<String>());
+    let player = UserInfo::with_rating(user_id, name, Some(profile.avatar));
+    let occurrence = service
+        .register(&occurrence_id, player, current_epoch_time())
+        .map_err(|reason| {
+            info!("拒绝锦标赛报名 {}: {}", occurrence_id, reason);
+            InternalError::InvalidInput
+        })?;
+    Ok(Json(occurrence))
+}
+
+/// 创建定期活动
+pub async fn handle_create_event(
+    headers: HeaderMap,
+    Json(request): Json<CreateEventRequest>,
+) -> Result<Json<RecurringEvent>, InternalError> {
+    check_admin_key(&headers)?;
+    let service = global_calendar_service().ok_or(InternalError::Failure)?;
+    let event = request.into_event(current_epoch_time()).map_err(|reason| {
+        info!("拒绝创建定期活动: {}", reason);
+        InternalError::InvalidInput
+    })?;
+    service.add(event.clone()).await;
+    Ok(Json(event))
+}
+
+/// 删除定期活动
+pub async fn handle_delete_event(
+    headers: HeaderMap,
+    Path(event_id): Path<String>,
+) -> Result<StatusCode, InternalError> {
+    check_admin_key(&headers)?;
+    let service = global_calendar_service().ok_or(InternalError::Failure)?;
+    Ok(if service.remove(&event_id).await {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    })
+}
+
+/// 注册活动日历路由
+pub fn register_calendar_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router
+        .route("/v1/events", get(handle_get_calendar))
+        .route("/v1/events/:occurrence_id/register", post(handle_register))
+        .route("/admin/events", post(handle_create_event))
+        .route("/admin/events/:id", delete(handle_delete_event))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-01-01 00:00 UTC，周一
+    const MONDAY: u64 = 1_704_067_200_000;
+    const HOUR_MS: u64 = 60 * MINUTE_MS;
+
+    fn tournament(service: &CalendarService) -> RecurringEvent {
+        let event = CreateEventRequest {
+            name: "每日锦标赛".to_string(),
+            kind: EventKind::Tournament { players_per_match: 2, max_players: 3 },
+            recurrence: Recurrence::Daily { hour: 18, minute: 0 },
+            duration_mins: 60,
+            registration_lead_mins: Some(30),
+        }
+        .into_event(0)
+        .unwrap();
+        service.events.write().insert(event.id.clone(), event.clone());
+        event
+    }
+
+    fn player(id: &str) -> UserInfo {
+        UserInfo { id: id.to_string(), name: id.to_string(), rating: 1000, avatar_url: None, provisional: false }
+    }
+
+    #[test]
+    fn test_recurrence() {
+        let daily = Recurrence::Daily { hour: 18, minute: 0 };
+        assert_eq!(daily.next_start(MONDAY), MONDAY + 18 * HOUR_MS);
+        assert_eq!(daily.next_start(MONDAY + 18 * HOUR_MS), MONDAY + 18 * HOUR_MS);
+        assert_eq!(daily.next_start(MONDAY + 18 * HOUR_MS + 1), MONDAY + DAY_MS + 18 * HOUR_MS);
+
+        // 周六 00:00
+        let weekend = Recurrence::Weekly { weekday: 5, hour: 0, minute: 0 };
+        assert_eq!(weekend.next_start(MONDAY), MONDAY + 5 * DAY_MS);
+        assert_eq!(weekend.next_start(MONDAY + 5 * DAY_MS + 1), MONDAY + 12 * DAY_MS);
+        assert!(Recurrence::Weekly { weekday: 7, hour: 0, minute: 0 }.validate().is_err());
+    }
+
+    #[test]
+    fn test_tournament_lifecycle() {
+        let service = CalendarService::new(Arc::new(ConnectionManager::new()));
+        let event = tournament(&service);
+        let starts_at = MONDAY + 18 * HOUR_MS;
+        let occurrence_id = format!("{}@{}", event.id, starts_at);
+
+        // 开放报名前不能报名
+        let before = starts_at - HOUR_MS;
+        assert_eq!(service.list(before, 1)[0].phase, EventPhase::Upcoming);
+        assert!(service.register(&occurrence_id, player("a"), before).is_err());
+        assert!(service.due(before).is_empty());
+
+        let open = starts_at - 10 * MINUTE_MS;
+        assert!(matches!(service.due(open).as_slice(), [DueAction::OpenRegistration(_)]));
+        assert!(service.due(open).is_empty());
+        for id in ["a", "b", "a", "c"] {
+            service.register(&occurrence_id, player(id), open).unwrap();
+        }
+        assert!(service.register(&occurrence_id, player("d"), open).is_err());
+        assert_eq!(service.list(open, 1)[0].registered, 3);
+
+        // 三人按每局两人分组，只剩一人的分组不参赛
+        match service.due(starts_at).as_slice() {
+            [DueAction::StartTournament { groups, .. }] => {
+                assert_eq!(groups.len(), 1);
+                assert_eq!(groups[0].len(), 2);
+            }
+            other => panic!("unexpected actions: {:?}", other),
+        }
+        assert!(service.due(starts_at + MINUTE_MS).is_empty());
+        assert!(service.register(&occurrence_id, player("d"), starts_at).is_err());
+    }
+
+    #[test]
+    fn test_active_multiplier() {
+        let service = CalendarService::new(Arc::new(ConnectionManager::new()));
+        let boost = CreateEventRequest {
+            name: "双倍奖励周末".to_string(),
+            kind: EventKind::Boost { multiplier: 2 },
+            recurrence: Recurrence::Weekly { weekday: 5, hour: 0, minute: 0 },
+            duration_mins: 2 * 24 * 60,
+            registration_lead_mins: None,
+        }
+        .into_event(0)
+        .unwrap();
+        service.events.write().insert(boost.id.clone(), boost);
+        assert_eq!(service.active_multiplier(MONDAY), 1);
+        assert_eq!(service.active_multiplier(MONDAY + 6 * DAY_MS), 2);
+        assert_eq!(service.active_multiplier(MONDAY + 7 * DAY_MS), 1);
+    }
+}
@@ -0,0 +1,343 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 卡牌元数据与本地化
+//!
+//! # 概述
+//! 卡牌在对局数据中只有 `CardType` 枚举名，客户端原先需要自行维护卡牌名称和说明。
+//! 本模块登记每种卡牌的展示名称、效果说明、卡面资源键和所属扩展包，名称和说明按 `i18n::Locale` 提供多语言版本；
+//! 各游戏模式的牌组预设（`GameMode::deck_preset`）也随接口下发，预设中的每种卡牌都附带元数据，
+//! 并附带服务器默认的手牌上限（见 `hand_limit`）。
+//!
+//! 新增卡牌类型时需要在 `CardType::ALL` 和 `metadata` 中同时登记，`metadata` 按枚举穷尽匹配，
+//! 遗漏时无法编译；新卡牌随即出现在接口中，客户端不需要更新即可展示。
+//!
+//! # 接口
+//! - `GET /v1/cards?locale=en`：所有卡牌的元数据和各游戏模式的牌组预设。
+//!   `name` 和 `description` 使用 `locale` 参数指定的语言，未指定时按 `Accept-Language` 请求头，默认中文；
+//!   `localized` 包含所有语言的版本
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::{extract::Query, http::HeaderMap, routing::get, Json, Router};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::game_mode::{self, DeckExhaustion, DeckPresetEntry};
+use crate::gaming::CardType;
+use crate::hand_limit;
+use crate::i18n::Locale;
+use crate::AppState;
+
+/// 基础包
+pub const BASE_EXPANSION: &str = "base";
+/// 内爆猫扩展包
+pub const IMPLODING_EXPANSION: &str = "imploding_kittens";
+
+/// 单种卡牌的元数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CardMeta {
+    /// 稳定的卡牌键，snake_case
+    pub key: &'static str,
+    /// 卡面资源键
+    pub artwork_key: &'static str,
+    /// 所属扩展包
+    pub expansion: &'static str,
+    /// 名称，按 `Locale::ALL` 的顺序
+    pub names: [&'static str; 2],
+    /// 效果说明，按 `Locale::ALL` 的顺序
+    pub descriptions: [&'static str; 2],
+}
+
+impl CardMeta {
+    /// 指定语言的名称
+    pub fn name(&self, locale: Locale) -> &'static str {
+        self.names[locale.index()]
+    }
+
+    /// 指定语言的效果说明
+    pub fn description(&self, locale: Locale) -> &'static str {
+        self.descriptions[locale.index()]
+    }
+}
+
+const fn meta(
+    key: &'static str,
+    expansion: &'static str,
+    names: [&'static str; 2],
+    descriptions: [&'static str; 2],
+) -> CardMeta {
+    CardMeta { key, artwork_key: key, expansion, names, descriptions }
+}
+
+/// 获取卡牌的元数据
+pub fn metadata(card_type: &CardType) -> CardMeta {
+    match card_type {
+        CardType::ExplodingKitten => meta(
+            "exploding_kitten",
+            BASE_EXPANSION,
+            ["爆炸猫", "Exploding Kitten"],
+            ["抽到时如果没有拆除卡，立即出局", "If you draw this without a Defuse, you are out"],
+        ),
+        CardType::Defuse => meta(
+            "defuse",
+            BASE_EXPANSION,
+            ["拆除", "Defuse"],
+            ["抽到爆炸猫时自动使用，拆除后把爆炸猫放回牌堆", "Used automatically on an Exploding Kitten, which goes back into the deck"],
+        ),
+        CardType::Skip => meta(
+            "skip",
+            BASE_EXPANSION,
+            ["跳过", "Skip"],
+            ["结束回合，不抽牌", "End your turn without drawing"],
+        ),
+        CardType::SeeTheFuture => meta(
+            "see_the_future",
+            BASE_EXPANSION,
+            ["偷看未来", "See the Future"],
+            ["私下查看牌堆顶部的三张牌", "Privately view the top three cards of the deck"],
+        ),
+        CardType::Shuffle => meta(
+            "shuffle",
+            BASE_EXPANSION,
+            ["打乱", "Shuffle"],
+            ["洗乱牌堆", "Shuffle the deck"],
+        ),
+        CardType::Attack => meta(
+            "attack",
+            BASE_EXPANSION,
+            ["攻击", "Attack"],
+            ["结束回合，不抽牌，轮到下一名玩家", "End your turn without drawing and pass to the next player"],
+        ),
+        CardType::Favor => meta(
+            "favor",
+            BASE_EXPANSION,
+            ["抢夺", "Favor"],
+            ["从另一名玩家手中拿走一张牌", "Take a card from another player's hand"],
+        ),
+        CardType::Cat => meta(
+            "cat",
+            BASE_EXPANSION,
+            ["猫咪卡", "Cat Card"],
+            ["没有单独的效果", "Has no effect on its own"],
+        ),
+        CardType::Nope => meta(
+            "nope",
+            BASE_EXPANSION,
+            ["烦人卡", "Nope"],
+            ["在任何人的回合打出，取消正在连锁中的动作", "Play at any time to cancel the action in the chain"],
+        ),
+        CardType::ImplodingKitten => meta(
+            "imploding_kitten",
+            IMPLODING_EXPANSION,
+            ["内爆猫", "Imploding Kitten"],
+            ["在牌堆中间加入一张爆炸猫", "Insert an Exploding Kitten into the middle of the deck"],
+        ),
+        CardType::AlterTheFuture => meta(
+            "alter_the_future",
+            IMPLODING_EXPANSION,
+            ["替换未来", "Alter the Future"],
+            ["查看并重新排列牌堆顶部的三张牌", "View and rearrange the top three cards of the deck"],
+        ),
+        CardType::ShareTheFuture => meta(
+            "share_the_future",
+            IMPLODING_EXPANSION,
+            ["分享未来", "Share the Future"],
+            ["选择一名玩家，与其分享牌堆顶部的三张牌", "Choose a player to share the top three cards of the deck with"],
+        ),
+        CardType::BuryCard => meta(
+            "bury_card",
+            IMPLODING_EXPANSION,
+            ["掩埋", "Bury"],
+            ["把牌堆顶部的一张牌埋入牌堆中间", "Bury the top card of the deck in the middle of the deck"],
+        ),
+        CardType::SpeedUpExplosion => meta(
+            "speed_up_explosion",
+            IMPLODING_EXPANSION,
+            ["加速爆炸", "Speed Up Explosion"],
+            ["把牌堆中的一张爆炸猫移到牌堆顶部附近", "Move an Exploding Kitten in the deck near the top"],
+        ),
+    }
+}
+
+/// 单一语言的卡牌文本
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CardText {
+    pub name: String,
+    pub description: String,
+}
+
+/// 卡牌信息
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CardInfo {
+    /// 对局数据中的卡牌类型
+    #[schema(value_type = String)]
+    pub card_type: CardType,
+    pub key: String,
+    pub name: String,
+    pub description: String,
+    pub artwork_key: String,
+    pub expansion: String,
+    /// 语言代码到该语言的名称和说明
+    pub localized: BTreeMap<String, CardText>,
+}
+
+impl CardInfo {
+    /**
+     * 生成卡牌信息
+     *
+     * 参数:
+     * @param card_type - 卡牌类型
+     * @param locale - `name` 和 `description` 使用的语言
+     *
+     * 返回:
+     * 卡牌信息
+     */
+    pub fn new(card_type: &CardType, locale: Locale) -> Self {
+        let meta = metadata(card_type);
+        let localized = Locale::ALL
+            .iter()
+            .map(|l| {
+                let text = CardText { name: meta.name(*l).to_string(), description: meta.description(*l).to_string() };
+                (l.code().to_string(), text)
+            })
+            .collect();
+        CardInfo {
+            card_type: card_type.clone(),
+            key: meta.key.to_string(),
+            name: meta.name(locale).to_string(),
+            description: meta.description(locale).to_string(),
+            artwork_key: meta.artwork_key.to_string(),
+            expansion: meta.expansion.to_string(),
+            localized,
+        }
+    }
+}
+
+/// 牌组预设中的一种卡牌
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeckPresetCard {
+    pub card: CardInfo,
+    /// 张数为 `base + perPlayer * 玩家数`
+    pub base: i32,
+    pub per_player: u32,
+}
+
+impl DeckPresetCard {
+    fn new(entry: &DeckPresetEntry, locale: Locale) -> Self {
+        DeckPresetCard {
+            card: CardInfo::new(&entry.card_type, locale),
+            base: entry.base,
+            per_player: entry.per_player,
+        }
+    }
+}
+
+/// 游戏模式的牌组预设
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeckPreset {
+    pub mode: String,
+    pub min_players: usize,
+    pub max_players: usize,
+    /// 服务器默认的手牌上限，0表示不限制，私人对局可以在创建时另行指定
+    pub max_hand_size: usize,
+    /// 牌堆抽完时的规则，reshuffle或end_game
+    #[schema(value_type = String)]
+    pub deck_exhaustion: DeckExhaustion,
+    pub cards: Vec<DeckPresetCard>,
+}
+
+/// 卡牌目录响应
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CardCatalogResponse {
+    pub locale: String,
+    pub cards: Vec<CardInfo>,
+    pub presets: Vec<DeckPreset>,
+}
+
+/// 生成卡牌目录
+pub fn catalog(locale: Locale) -> CardCatalogResponse {
+    let registry = game_mode::global_game_modes();
+    let presets = registry
+        .ids()
+        .into_iter()
+        .filter_map(|id| registry.get(id))
+        .map(|mode| {
+            let (min_players, max_players) = mode.player_range();
+            DeckPreset {
+                mode: mode.id().to_string(),
+                min_players,
+                max_players,
+                max_hand_size: hand_limit::default_max_hand_size(),
+                deck_exhaustion: mode.deck_exhaustion(),
+                cards: mode.deck_preset().iter().map(|e| DeckPresetCard::new(e, locale)).collect(),
+            }
+        })
+        .collect();
+    CardCatalogResponse {
+        locale: locale.code().to_string(),
+        cards: CardType::ALL.iter().map(|card_type| CardInfo::new(card_type, locale)).collect(),
+        presets,
+    }
+}
+
+/// 查询卡牌目录
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CardCatalogQuery {
+    /// 语言代码（zh或en），未指定时按Accept-Language请求头
+    pub locale: Option<String>,
+}
+
+/// 获取卡牌目录
+#[utoipa::path(
+    get,
+    path = "/v1/cards",
+    tag = "cards",
+    params(CardCatalogQuery),
+    responses((status = 200, description = "所有卡牌的元数据和各游戏模式的牌组预设", body = CardCatalogResponse))
+)]
+pub async fn handle_get_cards(headers: HeaderMap, Query(query): Query<CardCatalogQuery>) -> Json<CardCatalogResponse> {
+    let locale = query
+        .locale
+        .as_deref()
+        .and_then(Locale::from_tag)
+        .or_else(|| {
+            headers
+                .get(axum::http::header::ACCEPT_LANGUAGE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(Locale::from_accept_language)
+        })
+        .unwrap_or_default();
+    Json(catalog(locale))
+}
+
+/// 注册卡牌目录路由
+pub fn register_card_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router.route("/v1/cards", get(handle_get_cards))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_catalog() {
+        let keys = CardType::ALL.iter().map(|t| metadata(t).key).collect::<HashSet<_>>();
+        assert_eq!(keys.len(), CardType::ALL.len());
+
+        let catalog = catalog(Locale::En);
+        assert_eq!(catalog.locale, "en");
+        let skip = catalog.cards.iter().find(|c| c.card_type == CardType::Skip).unwrap();
+        assert_eq!(skip.name, "Skip");
+        assert_eq!(skip.localized["zh"].name, "跳过");
+
+        let preset = catalog.presets.iter().find(|p| p.mode == game_mode::DEFAULT_MODE).unwrap();
+        assert!(preset.cards.iter().any(|c| c.card.key == "defuse" && c.per_player == 1));
+    }
+}
@@ -35,12 +35,18 @@ use sui_sdk::verify_personal_message_signature::verify_personal_message_signatur
 use tap::TapFallible;
 use tracing::{debug, info, warn,error};
 
-use crate::errors::InternalError;
+use crate::errors::{ErrorResponse, InternalError};
 use crate::externals::{current_epoch_time, fetch_first_and_last_pkg_id};
 use crate::keys::{check_request, Certificate};
 use crate::metrics::call_with_duration;
 use crate::metrics::Metrics;
-use crate::session_login::{SessionUser, SESSION_USER_KEY};
+use crate::networks::SuiNetwork;
+use crate::session_login::{
+    resolve_session_profile, start_session, verify_session_request, SessionTokenRequest, SessionTokenResponse,
+    SessionUser, SESSION_USER_KEY,
+};
+use crate::account_link;
+use crate::guest::{self, GuestMigration};
 use crate::types::{ElGamalPublicKey, ElgamalVerificationKey, ElgamalEncryption, MasterKeyPOP, GAS_BUDGET};
 use crate::AppState;
 use axum::{
@@ -56,6 +62,7 @@ use crate::sdk::create_profile_for_passport;
 use crate::sdk::Profile;  // 从sdk模块直接导入Profile类型
 use hex;
 use tower_sessions::{Session, Expiry};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 use axum::extract::Extension;
 use crate::txb;
@@ -64,10 +71,15 @@ use axum::{
     Router,
 };
 use crate::sdk::executor;
+use crate::profile_guard::profile_guard;
+use crate::name_policy::{name_policy, NameViolation};
+use axum::extract::ConnectInfo;
+use std::net::SocketAddr;
 
 
 /// 头像请求参数
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct AvatarParams {
     /// 用于生成头像的种子字符串
     pub address: Option<String>,
@@ -76,6 +88,13 @@ pub struct AvatarParams {
 }
 
 /// 处理头像生成请求
+#[utoipa::path(
+    get,
+    path = "/test/avatar",
+    tag = "catastrophe",
+    params(AvatarParams),
+    responses((status = 200, description = "SVG头像", content_type = "image/svg+xml", body = String))
+)]
 pub async fn generate_avatar(
     State(_state): State<Arc<AppState>>,
     Query(params): Query<AvatarParams>,
@@ -113,9 +132,15 @@ pub async fn generate_avatar(
  * 
  * 用于测试SDK中的create_profile_for_passport函数
  */
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateProfileRequest {
     pub passport_id: String,  // 护照ID (SuiAddress格式)
+    #[serde(default)]
+    pub pow_nonce: Option<String>,      // 工作量证明随机数（启用时必填）
+    #[serde(default)]
+    pub captcha_token: Option<String>,  // 验证码令牌（启用时必填）
+    #[serde(default)]
+    pub name: Option<String>,           // 名称（可选，需通过名称策略）
 }
 
 /**
@@ -123,11 +148,13 @@ pub struct CreateProfileRequest {
  * 
  * 包含交易结果信息
  */
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateProfileResponse {
     pub success: bool,              // 是否成功
     pub digest: Option<String>,     // 交易摘要
     pub error: Option<String>,      // 错误信息(如果有)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub violation: Option<NameViolation>, // 名称违反的规则(如果有)
 }
 
 /**
@@ -135,13 +162,73 @@ pub struct CreateProfileResponse {
  * 
  * 用于测试SDK中的create_profile_for_passport函数
  * 注意：此端点仅用于测试目的，生产环境应该使用适当的认证机制
+ * 提交交易前经过限流、工作量证明/验证码和重复检测（见 `profile_guard` 模块）
+ * 提交了名称时先按名称策略校验（见 `name_policy` 模块），不通过返回400和违反的规则
  */
+#[utoipa::path(
+    post,
+    path = "/test/create_profile",
+    tag = "catastrophe",
+    request_body = CreateProfileRequest,
+    responses(
+        (status = 200, description = "交易结果", body = CreateProfileResponse),
+        (status = 400, description = "护照ID或名称无效", body = CreateProfileResponse),
+        (status = 403, description = "工作量证明或验证码无效", body = CreateProfileResponse),
+        (status = 409, description = "护照已有Profile", body = CreateProfileResponse),
+        (status = 429, description = "创建过于频繁", body = CreateProfileResponse)
+    )
+)]
 pub async fn handle_create_profile(
     State(app_state): State<Arc<AppState>>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
     Json(payload): Json<CreateProfileRequest>,
-) -> Result<Json<CreateProfileResponse>, StatusCode> {
+) -> Result<Json<CreateProfileResponse>, (StatusCode, Json<CreateProfileResponse>)> {
     info!("收到创建用户档案请求: {:?}", payload);
     app_state.metrics.observe_request("test_create_profile");
+
+    // 名称校验不消耗限流额度
+    let name = match payload.name.as_deref() {
+        Some(raw) => Some(name_policy().check(raw).map_err(|violation| {
+            warn!("拒绝创建用户档案 {}: 名称 {:?} {}", payload.passport_id, raw, violation);
+            (
+                StatusCode::BAD_REQUEST,
+                Json(CreateProfileResponse {
+                    success: false,
+                    digest: None,
+                    error: Some(violation.message.clone()),
+                    violation: Some(violation),
+                }),
+            )
+        })?),
+        None => None,
+    };
+
+    // 防女巫检查，通过后到交易完成前同一护照不能再次创建
+    let guard = profile_guard();
+    let client_ip = guard.client_ip(&headers, peer.map(|ConnectInfo(addr)| addr));
+    let pending = guard
+        .check(
+            &app_state.game_manager,
+            &payload.passport_id,
+            client_ip,
+            payload.pow_nonce.as_deref(),
+            payload.captcha_token.as_deref(),
+        )
+        .await
+        .map_err(|e| {
+            warn!("拒绝创建用户档案 {}: {:?}", payload.passport_id, e);
+            (
+                e.status_code(),
+                Json(CreateProfileResponse {
+                    success: false,
+                    digest: None,
+                    error: Some(e.message().to_string()),
+                    violation: None,
+                }),
+            )
+        })?;
+
     // 生成头像
     let svg = make_avatar(&payload.passport_id);
 
@@ -161,10 +248,14 @@ pub async fn handle_create_profile(
             // 使用Network方法生成浏览器URL
             let tx_url = app_state.network.explorer_tx_url(&digest);
             info!("成功创建用户档案，交易摘要: {}", tx_url);
+            if let Some(name) = name {
+                save_profile_name(&app_state, pending.passport_id(), &name).await;
+            }
             Ok(Json(CreateProfileResponse {
                 success: true,
                 digest: Some(digest),
                 error: None,
+                violation: None,
             }))
         },
         Err(err) => {
@@ -174,22 +265,38 @@ pub async fn handle_create_profile(
                 success: false,
                 digest: None,
                 error: Some(err.to_string()),
+                violation: None,
             }))
         }
     }
 }
 
+/// 记录新Profile的名称并同步到用户信息缓存和用户搜索索引，失败只记录日志
+async fn save_profile_name(app_state: &AppState, passport_id: ObjectID, name: &str) {
+    match app_state.game_manager.get_profile_id_by_passport(&passport_id).await {
+        Ok(profile_id) => {
+            let user_id = profile_id.to_string();
+            if let Some(service) = crate::profile_edit::global_profile_edit_service() {
+                service.record_name(&user_id, name, None);
+            }
+            crate::profile_edit::propagate(&user_id, Some(name), None).await;
+        }
+        Err(e) => warn!("查询护照 {} 的Profile失败，名称未保存: {}", passport_id, e),
+    }
+}
+
 
 /// 获取用户档案请求结构
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct GetProfileRequest {
     pub passport_id: String,  // 护照ID (SuiAddress格式)
 }
 
 /// 获取用户档案响应结构
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct GetProfileResponse {
     pub success: bool,
+    #[schema(value_type = Option<Object>)]
     pub profile: Option<Profile>,  // 用户档案信息
     pub error: Option<String>,    // 错误信息(如果有)
 }
@@ -198,12 +305,20 @@ pub struct GetProfileResponse {
 /// 
 /// 用于测试从GameManager获取用户档案信息
 /// 注意：此端点仅用于测试目的
+#[utoipa::path(
+    post,
+    path = "/test/get_profile",
+    tag = "catastrophe",
+    request_body = GetProfileRequest,
+    responses((status = 200, description = "用户档案", body = GetProfileResponse))
+)]
 pub async fn handle_get_profile(
     State(app_state): State<Arc<AppState>>,
+    SuiNetwork(network): SuiNetwork,
     Json(payload): Json<GetProfileRequest>,
 ) -> Result<Json<GetProfileResponse>, StatusCode> {
     info!("收到获取用户档案请求: {:?}", payload);
-    app_state.metrics.observe_request("test_get_profile");
+    app_state.metrics.observe_network_request(&network.name, "test_get_profile");
 
     // 将护照ID转换为ObjectID
     let passport_id = match ObjectID::from_hex_literal(&payload.passport_id) {
@@ -219,9 +334,9 @@ pub async fn handle_get_profile(
     info!("passport_id: {:?}", passport_id);
 
     // 使用GameManager获取用户档案
-    match app_state.game_manager.get_profile_id_by_passport(&passport_id).await {
+    match network.game_manager.get_profile_id_by_passport(&passport_id).await {
         Ok(profile_id) => {
-            match app_state.game_manager.get_profile(&profile_id).await {
+            match network.game_manager.get_profile(&profile_id).await {
                 Ok(profile) => {
                     info!("成功获取用户档案: {:?}", profile);
                     Ok(Json(GetProfileResponse {
@@ -252,9 +367,10 @@ pub async fn handle_get_profile(
 }
 
 /// 获取用户Profile响应结构
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct GetUserProfileResponse {
     pub success: bool,
+    #[schema(value_type = Option<Object>)]
     pub profile: Option<Profile>,
     pub error: Option<String>,
 }
@@ -262,13 +378,23 @@ pub struct GetUserProfileResponse {
 /// 处理获取用户Profile请求
 /// 
 /// 从 session 中获取用户地址，并返回对应的Profile信息
+#[utoipa::path(
+    get,
+    path = "/user/profile",
+    tag = "catastrophe",
+    responses(
+        (status = 200, description = "当前登录用户的档案", body = GetUserProfileResponse),
+        (status = 403, description = "未登录", body = ErrorResponse),
+    )
+)]
 #[axum::debug_handler]
 pub async fn handle_get_user_profile(
     State(app_state): State<Arc<AppState>>,
+    SuiNetwork(network): SuiNetwork,
     Extension(session): Extension<Session>,
 ) -> Result<Json<GetUserProfileResponse>, InternalError> {
     info!("收到获取用户Profile请求");
-    app_state.metrics.observe_request("get_user_profile");
+    app_state.metrics.observe_network_request(&network.name, "get_user_profile");
 
     // 从 session 中获取用户信息
     let user = session.get::<SessionUser>(SESSION_USER_KEY).await?
@@ -288,9 +414,9 @@ pub async fn handle_get_user_profile(
     let passport_id = ObjectID::from(user.user_address);
     
     // 使用GameManager获取用户档案
-    match app_state.game_manager.get_profile_id_by_passport(&passport_id).await {
+    match network.game_manager.get_profile_id_by_passport(&passport_id).await {
         Ok(profile_id) => {
-            match app_state.game_manager.get_profile(&profile_id).await {
+            match network.game_manager.get_profile(&profile_id).await {
                 Ok(profile) => {
                     info!("成功获取用户档案: {:?}", profile);
                     Ok(Json(GetUserProfileResponse {
@@ -321,14 +447,14 @@ pub async fn handle_get_user_profile(
 }
 
 /// 管理员发送好友请求的请求结构
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct AdminSendFriendRequestRequest {
     pub from_profile_id: String,  // 发送者的 Profile ID
     pub to_profile_id: String,    // 接收者的 Profile ID
 }
 
 /// 管理员发送好友请求的响应结构
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct AdminSendFriendRequestResponse {
     pub success: bool,
     pub digest: Option<String>,
@@ -336,6 +462,16 @@ pub struct AdminSendFriendRequestResponse {
 }
 
 /// 处理管理员发送好友请求
+#[utoipa::path(
+    post,
+    path = "/test/send_friend_request",
+    tag = "catastrophe",
+    request_body = AdminSendFriendRequestRequest,
+    responses(
+        (status = 200, description = "交易结果", body = AdminSendFriendRequestResponse),
+        (status = 400, description = "Profile ID格式无效"),
+    )
+)]
 pub async fn handle_admin_send_friend_request(
     State(app_state): State<Arc<AppState>>,
     Json(payload): Json<AdminSendFriendRequestRequest>,
@@ -380,27 +516,36 @@ pub async fn handle_admin_send_friend_request(
 }
 
 /// 获取好友关系请求结构
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct GetRelationshipRequest {
     pub user_id: String,     // 用户的 Profile ID
     pub profile_id: String,  // 目标用户的 Profile ID
 }
 
 /// 获取好友关系响应结构
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct GetRelationshipResponse {
     pub success: bool,
+    #[schema(value_type = Option<Object>)]
     pub relationship: Option<crate::sdk::manager::Relationship>,  // 好友关系信息
     pub error: Option<String>,
 }
 
 /// 处理获取好友关系请求
+#[utoipa::path(
+    post,
+    path = "/test/get_relationship",
+    tag = "catastrophe",
+    request_body = GetRelationshipRequest,
+    responses((status = 200, description = "好友关系", body = GetRelationshipResponse))
+)]
 pub async fn handle_get_relationship(
     State(app_state): State<Arc<AppState>>,
+    SuiNetwork(network): SuiNetwork,
     Json(payload): Json<GetRelationshipRequest>,
 ) -> Result<Json<GetRelationshipResponse>, StatusCode> {
     info!("收到获取好友关系请求: {:?}", payload);
-    app_state.metrics.observe_request("test_get_relationship");
+    app_state.metrics.observe_network_request(&network.name, "test_get_relationship");
 
     // 将 ID 转换为 ObjectID
     let user_id = match ObjectID::from_hex_literal(&payload.user_id) {
@@ -426,7 +571,7 @@ pub async fn handle_get_relationship(
     };
 
     // 使用 GameManager 获取好友关系
-    match app_state.game_manager.get_relationship(&user_id, &profile_id).await {
+    match network.game_manager.get_relationship(&user_id, &profile_id).await {
         Ok(relationship) => {
             info!("成功获取好友关系: {:?}", relationship);
             Ok(Json(GetRelationshipResponse {
@@ -446,12 +591,244 @@ pub async fn handle_get_relationship(
     }
 }
 
+/// 创建游客请求结构
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct CreateGuestRequest {
+    #[serde(default)]
+    pub captcha_token: Option<String>,  // 验证码令牌（启用时必填）
+}
+
+/// 创建游客响应结构
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateGuestResponse {
+    pub success: bool,
+    #[schema(value_type = Option<Object>)]
+    pub profile: Option<Profile>,   // 游客的缓存档案
+    pub expires_at: Option<u64>,    // 游客会话过期时间（毫秒）
+    pub error: Option<String>,      // 错误信息(如果有)
+}
+
+/**
+ * 处理创建游客请求
+ *
+ * 生成游客身份和缓存档案并写入会话，不进行任何链上写入
+ * 创建前经过验证码和IP限流检查（见 `profile_guard` 模块）
+ */
+#[utoipa::path(
+    post,
+    path = "/user/guest",
+    tag = "catastrophe",
+    request_body = CreateGuestRequest,
+    responses(
+        (status = 200, description = "游客档案", body = CreateGuestResponse),
+        (status = 403, description = "游客模式未开启或验证码无效", body = CreateGuestResponse),
+        (status = 429, description = "创建过于频繁", body = CreateGuestResponse)
+    )
+)]
+pub async fn handle_create_guest(
+    State(app_state): State<Arc<AppState>>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Extension(session): Extension<Session>,
+    Json(payload): Json<CreateGuestRequest>,
+) -> Result<Json<CreateGuestResponse>, Response> {
+    app_state.metrics.observe_request("create_guest");
+    let failure = |status: StatusCode, error: &str| {
+        (
+            status,
+            Json(CreateGuestResponse {
+                success: false,
+                profile: None,
+                expires_at: None,
+                error: Some(error.to_string()),
+            }),
+        )
+            .into_response()
+    };
+
+    let service = guest::global_guest_service()
+        .filter(|service| service.config().enabled)
+        .ok_or_else(|| failure(StatusCode::FORBIDDEN, "游客模式未开启"))?;
+
+    let guard = profile_guard();
+    let client_ip = guard.client_ip(&headers, peer.map(|ConnectInfo(addr)| addr));
+    guard
+        .check_guest(client_ip, payload.captcha_token.as_deref())
+        .await
+        .map_err(|e| {
+            warn!("拒绝创建游客: {:?}", e);
+            failure(e.status_code(), e.message())
+        })?;
+
+    let now = current_epoch_time();
+    let account = service.create(now).map_err(|e| {
+        error!("创建游客失败: {}", e);
+        failure(StatusCode::SERVICE_UNAVAILABLE, &e.to_string())
+    })?;
+    let profile = account.profile();
+    let expires_at = now + service.config().session_ttl_ms;
+    let session_user = SessionUser {
+        user_address: account.address,
+        session_vk: String::new(),
+        exp: expires_at / 1000, // 转换为秒
+        profile: Some(profile.clone()),
+    };
+    session
+        .insert(SESSION_USER_KEY, session_user)
+        .await
+        .map_err(|e| InternalError::from(e).into_response())?;
+
+    Ok(Json(CreateGuestResponse {
+        success: true,
+        profile: Some(profile),
+        expires_at: Some(expires_at),
+        error: None,
+    }))
+}
+
+/**
+ * 游客升级请求结构
+ *
+ * 包含与 `/auth/session_token` 相同的钱包登录请求，证明用户拥有护照
+ */
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct UpgradeGuestRequest {
+    #[serde(flatten)]
+    pub login: SessionTokenRequest,     // 钱包登录请求
+    #[serde(default)]
+    pub pow_nonce: Option<String>,      // 工作量证明随机数（启用时必填）
+    #[serde(default)]
+    pub captcha_token: Option<String>,  // 验证码令牌（启用时必填）
+}
+
+/// 游客升级响应结构
+#[derive(Serialize, ToSchema)]
+pub struct UpgradeGuestResponse {
+    pub success: bool,
+    pub session: Option<SessionTokenResponse>,  // 新的登录令牌和链上档案
+    pub migration: Option<GuestMigration>,      // 迁移的数据
+    pub error: Option<String>,                  // 错误信息(如果有)
+}
+
+/**
+ * 处理游客升级请求
+ *
+ * 为护照创建链上Profile，把游客的评分、统计数据和好友关系迁移到新的Profile，
+ * 并把会话切换为钱包登录
+ * 只能升级为新的Profile：护照已有Profile或钱包已关联到其他Profile时拒绝
+ */
+#[utoipa::path(
+    post,
+    path = "/user/guest/upgrade",
+    tag = "catastrophe",
+    request_body = UpgradeGuestRequest,
+    responses(
+        (status = 200, description = "升级结果", body = UpgradeGuestResponse),
+        (status = 403, description = "当前会话不是游客，游客或钱包已被封禁，或登录请求、工作量证明、验证码无效", body = UpgradeGuestResponse),
+        (status = 409, description = "对局进行中、护照已有Profile或钱包已关联", body = UpgradeGuestResponse),
+        (status = 429, description = "创建过于频繁", body = UpgradeGuestResponse)
+    )
+)]
+pub async fn handle_upgrade_guest(
+    State(app_state): State<Arc<AppState>>,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Extension(session): Extension<Session>,
+    Json(payload): Json<UpgradeGuestRequest>,
+) -> Result<Json<UpgradeGuestResponse>, Response> {
+    app_state.metrics.observe_request("upgrade_guest");
+    let failure = |status: StatusCode, error: &str| {
+        (
+            status,
+            Json(UpgradeGuestResponse {
+                success: false,
+                session: None,
+                migration: None,
+                error: Some(error.to_string()),
+            }),
+        )
+            .into_response()
+    };
+    let internal = |e: InternalError| {
+        app_state.metrics.observe_error(e.as_str());
+        e.into_response()
+    };
+
+    // 当前会话必须是未升级的游客
+    let user = session
+        .get::<SessionUser>(SESSION_USER_KEY)
+        .await
+        .map_err(|e| internal(e.into()))?
+        .ok_or_else(|| internal(InternalError::Unauthorized))?;
+    let guest_id = user.profile.map(|p| p.id.to_string()).unwrap_or_default();
+    if !guest::is_guest(&guest_id) {
+        return Err(failure(StatusCode::FORBIDDEN, "当前用户不是游客"));
+    }
+    guest::check_upgradable(&guest_id)
+        .await
+        .map_err(|e| failure(StatusCode::CONFLICT, &e.to_string()))?;
+
+    // 验证钱包登录请求，取得护照ID
+    let passport_id = verify_session_request(&app_state, &headers, &payload.login)
+        .await
+        .map_err(internal)?;
+    let certificate = payload.login.certificate();
+    // 创建链上档案之前检查封禁，被封禁的游客或钱包不能升级
+    crate::ban::check_login(&certificate.user, Some(&guest_id)).map_err(|e| internal(e.into()))?;
+    let linked = account_link::global_account_link_service()
+        .and_then(|service| service.linked_user(&certificate.user.to_string()));
+    if let Some(profile_id) = linked {
+        warn!("钱包 {} 已关联到档案 {}，拒绝游客 {} 升级", certificate.user, profile_id, guest_id);
+        return Err(failure(StatusCode::CONFLICT, "钱包已关联到其他档案"));
+    }
+
+    // 防女巫检查，通过后到交易完成前同一护照不能再次创建
+    let guard = profile_guard();
+    let client_ip = guard.client_ip(&headers, peer.map(|ConnectInfo(addr)| addr));
+    let pending = guard
+        .check(
+            &app_state.game_manager,
+            &passport_id,
+            client_ip,
+            payload.pow_nonce.as_deref(),
+            payload.captcha_token.as_deref(),
+        )
+        .await
+        .map_err(|e| {
+            warn!("拒绝游客 {} 升级到护照 {}: {:?}", guest_id, passport_id, e);
+            failure(e.status_code(), e.message())
+        })?;
+
+    let profile = resolve_session_profile(&app_state, certificate.user, &passport_id)
+        .await
+        .map_err(internal)?
+        .ok_or_else(|| failure(StatusCode::SERVICE_UNAVAILABLE, "创建链上档案失败"))?;
+    let migration = guest::upgrade(&guest_id, &profile.id.to_string())
+        .await
+        .map_err(|e| {
+            error!("游客 {} 升级失败: {}", guest_id, e);
+            failure(StatusCode::CONFLICT, &e.to_string())
+        })?;
+
+    let token = start_session(&app_state, certificate, Some(profile), &session)
+        .await
+        .map_err(internal)?;
+    Ok(Json(UpgradeGuestResponse {
+        success: true,
+        session: Some(token),
+        migration: Some(migration),
+        error: None,
+    }))
+}
+
 /// 注册 Catastrophe 相关路由
 pub fn register_catastrophe_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
     router
         .route("/test/create_profile", post(handle_create_profile))
         .route("/test/get_profile", post(handle_get_profile))
         .route("/user/profile", get(handle_get_user_profile))
+        .route("/user/guest", post(handle_create_guest))
+        .route("/user/guest/upgrade", post(handle_upgrade_guest))
         .route("/test/avatar", get(generate_avatar))
         .route("/test/send_friend_request", post(handle_admin_send_friend_request))
         .route("/test/get_relationship", post(handle_get_relationship))
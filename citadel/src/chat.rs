@@ -13,6 +13,8 @@
 //! - **消息发送**: 支持 `chat:send-message` 事件
 //! - **聊天室加入**: 支持 `chat:join-chat` 事件
 //! - **消息广播**: 通过 `chat:new-message` 事件推送新消息
+//! - **好友私信**: 支持 `chat:send-dm`、`chat:dm-history` 和 `chat:dm-unread` 事件，详见 `direct` 子模块
+//! - **输入状态与已读回执**: 支持 `chat:typing` 和 `chat:read` 事件，详见 `indicators` 子模块
 //! 
 //! ## 事件定义
 //! 
@@ -66,11 +68,17 @@ use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 use tracing::{debug, error, info};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::i18n::codes;
 use crate::ws::{ConnectionManager, WsMessage};
+use crate::ws_acl::{JoinPolicy, RoomOwner};
 use crate::AppState;
 
+pub mod direct;
+pub mod indicators;
+
 /// 聊天室前缀标识
 const ROOM_PREFIX: &str = "chat";
 
@@ -84,10 +92,24 @@ impl ChatEvents {
     pub const JOIN_CHAT: &'static str = "chat:join-chat";
     /// 服务端事件: 新消息广播
     pub const NEW_MESSAGE: &'static str = "chat:new-message";
+    /// 客户端事件: 发送私信
+    pub const SEND_DM: &'static str = "chat:send-dm";
+    /// 客户端事件: 查询私信历史，服务端以同名事件返回
+    pub const DM_HISTORY: &'static str = "chat:dm-history";
+    /// 客户端事件: 查询各对话的未读数，服务端以同名事件返回
+    pub const DM_UNREAD: &'static str = "chat:dm-unread";
+    /// 服务端事件: 收到私信
+    pub const NEW_DM: &'static str = "chat:new-dm";
+    /// 服务端事件: 私信发送结果
+    pub const DM_SENT: &'static str = "chat:dm-sent";
+    /// 双向事件: 正在输入
+    pub const TYPING: &'static str = "chat:typing";
+    /// 双向事件: 已读回执
+    pub const READ: &'static str = "chat:read";
 }
 
 /// 聊天消息结构
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ChatMessage {
     /// 消息ID
     pub id: String,
@@ -100,7 +122,8 @@ pub struct ChatMessage {
 }
 
 /// 用户信息结构
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(as = ChatUserInfo)]
 pub struct UserInfo {
     /// 用户ID
     pub id: String,
@@ -112,14 +135,14 @@ pub struct UserInfo {
 }
 
 /// 加入聊天室请求
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct JoinChatRequest {
     /// 聊天室ID
     pub chat_id: String,
 }
 
 /// 发送消息请求
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SendMessageRequest {
     /// 聊天室ID
     pub chat_id: String,
@@ -152,6 +175,9 @@ async fn handle_join_chat(
     
     info!("用户 {} 加入聊天室: {}", user_id, room_id);
     
+    // 加入房间才能收到聊天室的广播
+    connection_manager.join_room(client_id, &room_id).await?;
+    
     // 设置断开连接处理器
     connection_manager.setup_disconnect_handler(
         client_id,
@@ -168,7 +194,8 @@ async fn handle_join_chat(
     // 返回成功响应
     let response = serde_json::json!({
         "ok": true,
-        "msg": "已成功加入聊天室"
+        "msg": "已成功加入聊天室",
+        "code": codes::CHAT_JOINED
     });
     
     // 发送响应给客户端
@@ -192,6 +219,17 @@ async fn handle_send_message(
     // 格式化聊天室ID
     let room_id = format!("{}:{}", ROOM_PREFIX, chat_id);
     
+    // 只有聊天室内的客户端可以发言
+    if !connection_manager.can_speak_in(client_id, &room_id).await {
+        let response = serde_json::json!({
+            "ok": false,
+            "msg": "请先加入聊天室",
+            "code": codes::CHAT_NOT_JOINED
+        });
+        connection_manager.send_to_client(client_id, "chat:message-sent", Some(response)).await?;
+        return Ok(());
+    }
+    
     info!("用户 {} 在聊天室 {} 发送消息", user_info.id, room_id);
     
     // 创建消息对象
@@ -216,7 +254,8 @@ async fn handle_send_message(
     // 发送确认消息给发送者
     let response = serde_json::json!({
         "ok": true,
-        "msg": "消息已发送"
+        "msg": "消息已发送",
+        "code": codes::CHAT_SENT
     });
     
     connection_manager.send_to_client(
@@ -274,6 +313,58 @@ pub async fn handle_ws_message(
                 }
             }
         },
+        ChatEvents::TYPING => {
+            let (Some(user), Some(data)) = (&user_info, &message.data) else {
+                return Ok(false);
+            };
+            let Ok(req) = serde_json::from_value::<indicators::TypingRequest>(data.clone()) else {
+                return Ok(false);
+            };
+            indicators::handle_typing(client_id, &user.id, req, connection_manager).await?;
+            return Ok(true);
+        },
+        ChatEvents::READ => {
+            let (Some(user), Some(data)) = (&user_info, &message.data) else {
+                return Ok(false);
+            };
+            let Ok(req) = serde_json::from_value::<indicators::ReadRequest>(data.clone()) else {
+                return Ok(false);
+            };
+            indicators::handle_read(client_id, &user.id, req, connection_manager).await?;
+            return Ok(true);
+        },
+        ChatEvents::SEND_DM | ChatEvents::DM_HISTORY | ChatEvents::DM_UNREAD => {
+            let Some(user) = user_info else {
+                error!("用户未认证，无法使用私信");
+                return Ok(false);
+            };
+            let Some(passport_state) = crate::ws::global_passport_state() else {
+                error!("用户护照模块未初始化，无法使用私信");
+                return Ok(false);
+            };
+            let data = message.data.clone().unwrap_or_default();
+            let (event, response) = match message.event.as_str() {
+                ChatEvents::SEND_DM => {
+                    let Ok(req) = serde_json::from_value::<direct::SendDirectMessageRequest>(data) else {
+                        return Ok(false);
+                    };
+                    let response = direct::send_direct_message(&passport_state, user, &req.to_id, &req.text).await?;
+                    (ChatEvents::DM_SENT, response)
+                }
+                ChatEvents::DM_HISTORY => {
+                    let Ok(req) = serde_json::from_value::<direct::DirectHistoryRequest>(data) else {
+                        return Ok(false);
+                    };
+                    (ChatEvents::DM_HISTORY, direct::history(&passport_state, &user.id, &req).await)
+                }
+                _ => {
+                    let unread = direct::unread_counts(&passport_state, &user.id);
+                    (ChatEvents::DM_UNREAD, serde_json::json!({ "ok": true, "unread": unread }))
+                }
+            };
+            connection_manager.send_to_client(client_id, event, Some(response)).await?;
+            return Ok(true);
+        },
         _ => return Ok(false), // 非聊天相关事件
     }
     
@@ -282,6 +373,13 @@ pub async fn handle_ws_message(
 
 /// 注册聊天模块路由
 pub fn register_chat_routes(app: Router, connection_manager: Arc<ConnectionManager>) -> Router {
+    // 聊天室只能通过加入聊天室事件加入
+    if let Err(e) = connection_manager.room_acls().reserve_prefix(&format!("{}:", ROOM_PREFIX), RoomOwner::Chat, JoinPolicy::Server) {
+        error!("保留聊天室房间失败: {}", e);
+    }
+    connection_manager.register_handler("chat:*", |event| async move {
+        handle_ws_message(&event.client_id, event.message, &event.connection_manager, event.user).await
+    });
     let chat_state = Arc::new(ChatState::new(connection_manager));
     
     // 返回路由
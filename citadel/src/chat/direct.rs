@@ -0,0 +1,326 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 好友私信
+//!
+//! # 概述
+//! 好友之间一对一发送私信：
+//! - 只有好友之间可以发送，任何一方阻止对方后不能再发送
+//! - 每个对话保留最近 `MAX_HISTORY` 条消息，通过 `chat:dm-history` 分页查询
+//! - 接收方离线时消息进入离线队列，上线后推送
+//! - 每个用户按对话记录未读数，查询该对话的历史后清零
+//!
+//! # 消息格式
+//! ```json
+//! // chat:send-dm
+//! { "to_id": "user-2", "text": "你好" }
+//! // chat:new-dm，offline为true表示离线期间收到的消息
+//! { "message": { "id": "...", "content": "你好", "sender": { "id": "user-1", "name": "..." }, "created_at": 0 }, "offline": false }
+//! // chat:dm-history，返回created_at早于before的消息，按时间先后排列
+//! { "peer_id": "user-2", "before": 1700000000000, "limit": 50 }
+//! ```
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::Deserialize;
+use tracing::{debug, info};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::{ChatEvents, ChatMessage, UserInfo};
+use crate::event_bus::{self, DomainEvent};
+use crate::game::GameCachePrefix;
+use crate::i18n::codes;
+use crate::passport::{PassportState, RelationshipStatus};
+
+/// 每个对话保留的消息数
+pub const MAX_HISTORY: usize = 200;
+/// 每个用户离线队列的最大长度，超出时丢弃最早的消息
+pub const MAX_PENDING: usize = 100;
+/// 历史查询默认条数
+pub const DEFAULT_HISTORY_LIMIT: usize = 50;
+
+/// 发送私信请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SendDirectMessageRequest {
+    /// 接收方用户ID
+    pub to_id: String,
+    /// 消息内容
+    pub text: String,
+}
+
+/// 查询私信历史请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DirectHistoryRequest {
+    /// 对方用户ID
+    pub peer_id: String,
+    /// 只返回早于该时间（毫秒）的消息，为空时从最新的消息开始
+    pub before: Option<i64>,
+    /// 返回条数，默认50，最多MAX_HISTORY
+    pub limit: Option<usize>,
+}
+
+/// 对话的缓存键，与用户顺序无关
+fn conversation_key(user_id1: &str, user_id2: &str) -> String {
+    if user_id1 < user_id2 {
+        format!("{}:{}", user_id1, user_id2)
+    } else {
+        format!("{}:{}", user_id2, user_id1)
+    }
+}
+
+fn unread_key(user_id: &str) -> String {
+    format!("{}:unread", user_id)
+}
+
+fn pending_key(user_id: &str) -> String {
+    format!("{}:pending", user_id)
+}
+
+/// 错误响应
+fn failure(msg: &str, code: &str) -> serde_json::Value {
+    serde_json::json!({
+        "ok": false,
+        "msg": msg,
+        "code": code
+    })
+}
+
+/**
+ * 检查发送方是否可以给接收方发送私信
+ *
+ * 参数:
+ * @param passport - 用户护照状态
+ * @param sender_id - 发送方ID
+ * @param to_id - 接收方ID
+ *
+ * 返回:
+ * 可以发送时返回None，否则返回错误响应
+ */
+pub(super) async fn check_permission(passport: &PassportState, sender_id: &str, to_id: &str) -> Option<serde_json::Value> {
+    if sender_id == to_id {
+        return Some(failure("你们不是好友关系", codes::NOT_FRIENDS));
+    }
+    let Some(rel) = passport.get_relationship(sender_id, to_id).await else {
+        return Some(failure("你们不是好友关系", codes::NOT_FRIENDS));
+    };
+
+    // 接收方阻止了发送方
+    let blocked_by_receiver = rel.status == RelationshipStatus::Blocked
+        || (rel.status == RelationshipStatus::Blocked1To2 && rel.user2_id == sender_id)
+        || (rel.status == RelationshipStatus::Blocked2To1 && rel.user1_id == sender_id);
+    // 发送方阻止了接收方
+    let blocked_by_sender = (rel.status == RelationshipStatus::Blocked1To2 && rel.user1_id == sender_id)
+        || (rel.status == RelationshipStatus::Blocked2To1 && rel.user2_id == sender_id);
+
+    if blocked_by_receiver {
+        Some(failure("你已被该用户阻止", codes::BLOCKED_BY_USER))
+    } else if blocked_by_sender {
+        Some(failure("你已阻止该用户，解除阻止后才能发送私信", codes::DM_RECIPIENT_BLOCKED))
+    } else if rel.status != RelationshipStatus::Friends {
+        Some(failure("你们不是好友关系", codes::NOT_FRIENDS))
+    } else {
+        None
+    }
+}
+
+/**
+ * 发送私信
+ *
+ * 保存到对话历史并增加接收方的未读数，接收方在线时立即推送，离线时进入离线队列
+ *
+ * 参数:
+ * @param passport - 用户护照状态
+ * @param sender - 发送方信息
+ * @param to_id - 接收方ID
+ * @param text - 消息内容
+ *
+ * 返回:
+ * 发送结果，成功时包含消息
+ */
+pub async fn send_direct_message(
+    passport: &PassportState,
+    sender: UserInfo,
+    to_id: &str,
+    text: &str,
+) -> Result<serde_json::Value> {
+    if let Some(response) = check_permission(passport, &sender.id, to_id).await {
+        return Ok(response);
+    }
+
+    let sender_id = sender.id.clone();
+    let message = ChatMessage {
+        id: Uuid::new_v4().to_string(),
+        content: text.to_string(),
+        sender,
+        created_at: Utc::now().timestamp_millis(),
+    };
+
+    // 保存对话历史
+    let game_service = &passport.game_service;
+    let key = conversation_key(&sender_id, to_id);
+    let mut history = game_service
+        .get::<Vec<ChatMessage>>(GameCachePrefix::DIRECT_MESSAGE, &key)
+        .unwrap_or_default();
+    history.push(message.clone());
+    if history.len() > MAX_HISTORY {
+        history.drain(..history.len() - MAX_HISTORY);
+    }
+    game_service.set(GameCachePrefix::DIRECT_MESSAGE, &key, &history);
+
+    // 增加接收方的未读数
+    let mut unread = unread_counts(passport, to_id);
+    *unread.entry(sender_id.clone()).or_insert(0) += 1;
+    game_service.set(GameCachePrefix::DIRECT_MESSAGE, &unread_key(to_id), &unread);
+
+    let sessions = passport.get_user_sessions(to_id).await;
+    if sessions.is_empty() {
+        // 接收方离线，进入离线队列
+        let mut pending = game_service
+            .get::<Vec<ChatMessage>>(GameCachePrefix::DIRECT_MESSAGE, &pending_key(to_id))
+            .unwrap_or_default();
+        pending.push(message.clone());
+        if pending.len() > MAX_PENDING {
+            pending.drain(..pending.len() - MAX_PENDING);
+        }
+        game_service.set(GameCachePrefix::DIRECT_MESSAGE, &pending_key(to_id), &pending);
+        debug!("用户 {} 离线，私信进入离线队列", to_id);
+    } else {
+        event_bus::publish(DomainEvent::DirectMessage {
+            to_id: to_id.to_string(),
+            message: message.clone(),
+        }).await;
+    }
+
+    info!("用户 {} 向 {} 发送私信", sender_id, to_id);
+    Ok(serde_json::json!({
+        "ok": true,
+        "msg": "消息已发送",
+        "code": codes::CHAT_SENT,
+        "message": message
+    }))
+}
+
+/**
+ * 推送离线期间收到的私信
+ *
+ * 用户上线（会话数从0变为1）时调用，推送后清空离线队列
+ *
+ * 参数:
+ * @param passport - 用户护照状态
+ * @param user_id - 用户ID
+ * @param client_id - 新连接的客户端ID
+ *
+ * 返回:
+ * 推送的消息数
+ */
+pub async fn deliver_pending(passport: &PassportState, user_id: &str, client_id: &str) -> Result<usize> {
+    let game_service = &passport.game_service;
+    let Some(pending) = game_service.get::<Vec<ChatMessage>>(GameCachePrefix::DIRECT_MESSAGE, &pending_key(user_id)) else {
+        return Ok(0);
+    };
+    game_service.delete(GameCachePrefix::DIRECT_MESSAGE, &pending_key(user_id));
+
+    let count = pending.len();
+    for message in pending {
+        let payload = serde_json::json!({
+            "message": message,
+            "offline": true
+        });
+        passport.connection_manager.send_to_client(client_id, ChatEvents::NEW_DM, Some(payload)).await?;
+    }
+    if count > 0 {
+        info!("向用户 {} 推送 {} 条离线私信", user_id, count);
+    }
+    Ok(count)
+}
+
+/**
+ * 查询与某个用户的私信历史，并清零该对话的未读数
+ *
+ * 参数:
+ * @param passport - 用户护照状态
+ * @param user_id - 查询的用户ID
+ * @param request - 查询条件
+ *
+ * 返回:
+ * 按时间先后排列的消息和是否还有更早的消息
+ */
+pub async fn history(passport: &PassportState, user_id: &str, request: &DirectHistoryRequest) -> serde_json::Value {
+    let game_service = &passport.game_service;
+    let history = game_service
+        .get::<Vec<ChatMessage>>(GameCachePrefix::DIRECT_MESSAGE, &conversation_key(user_id, &request.peer_id))
+        .unwrap_or_default();
+    let limit = request.limit.unwrap_or(DEFAULT_HISTORY_LIMIT).clamp(1, MAX_HISTORY);
+    let (messages, has_more) = page(&history, request.before, limit);
+
+    mark_read(passport, user_id, &request.peer_id);
+
+    serde_json::json!({
+        "ok": true,
+        "peer_id": request.peer_id,
+        "messages": messages,
+        "has_more": has_more
+    })
+}
+
+/// 用户各对话的未读数，键为对方用户ID
+pub fn unread_counts(passport: &PassportState, user_id: &str) -> HashMap<String, usize> {
+    passport
+        .game_service
+        .get::<HashMap<String, usize>>(GameCachePrefix::DIRECT_MESSAGE, &unread_key(user_id))
+        .unwrap_or_default()
+}
+
+/// 清零与某个用户对话的未读数
+pub fn mark_read(passport: &PassportState, user_id: &str, peer_id: &str) {
+    let mut unread = unread_counts(passport, user_id);
+    if unread.remove(peer_id).is_some() {
+        passport.game_service.set(GameCachePrefix::DIRECT_MESSAGE, &unread_key(user_id), &unread);
+    }
+}
+
+/// 取出早于 `before` 的最近 `limit` 条消息，返回消息和是否还有更早的消息
+fn page(history: &[ChatMessage], before: Option<i64>, limit: usize) -> (&[ChatMessage], bool) {
+    let end = match before {
+        Some(before) => history.partition_point(|m| m.created_at < before),
+        None => history.len(),
+    };
+    let start = end.saturating_sub(limit);
+    (&history[start..end], start > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(created_at: i64) -> ChatMessage {
+        ChatMessage {
+            id: created_at.to_string(),
+            content: String::new(),
+            sender: UserInfo { id: "u1".to_string(), name: "u1".to_string(), avatar_url: None },
+            created_at,
+        }
+    }
+
+    #[test]
+    fn test_conversation_key_is_symmetric() {
+        assert_eq!(conversation_key("a", "b"), conversation_key("b", "a"));
+        assert_ne!(conversation_key("a", "b"), conversation_key("a", "c"));
+    }
+
+    #[test]
+    fn test_page() {
+        let history = (1..=5).map(message).collect::<Vec<_>>();
+
+        let (messages, has_more) = page(&history, None, 2);
+        assert_eq!(messages.iter().map(|m| m.created_at).collect::<Vec<_>>(), vec![4, 5]);
+        assert!(has_more);
+
+        let (messages, has_more) = page(&history, Some(4), 10);
+        assert_eq!(messages.iter().map(|m| m.created_at).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert!(!has_more);
+    }
+}
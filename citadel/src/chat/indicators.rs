@@ -0,0 +1,237 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 输入状态与已读回执
+//!
+//! # 概述
+//! 客户端通过 `chat:typing` 和 `chat:read` 通知对方正在输入或已读到某条消息，
+//! 服务端转发给聊天室的其他成员或私信对象，事件名称不变：
+//! - 聊天室：发送方必须已加入该聊天室
+//! - 私信：双方必须是好友且没有互相阻止，私信的已读回执同时清零该对话的未读数
+//!
+//! 每个用户每秒最多转发一次“正在输入”，超出的事件直接丢弃，不回复错误。
+//! 停止输入（`typing` 为false）和已读回执不受限制，避免对方的输入提示无法消失。
+//!
+//! # 消息格式
+//! ```json
+//! // chat:typing，chat_id和to_id二选一
+//! { "chat_id": "game-123", "typing": true }
+//! { "to_id": "user-2", "typing": false }
+//! // chat:read，chat_id和peer_id二选一
+//! { "peer_id": "user-2", "message_id": "..." }
+//! // 转发给其他成员的数据
+//! { "user_id": "user-1", "chat_id": "game-123", "typing": true }
+//! { "user_id": "user-1", "message_id": "...", "read_at": 1700000000000 }
+//! ```
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use tracing::debug;
+use utoipa::ToSchema;
+
+use super::{direct, ChatEvents, ROOM_PREFIX};
+use crate::passport::PassportState;
+use crate::ws::ConnectionManager;
+
+/// 同一用户两次转发“正在输入”的最小间隔（毫秒）
+pub const TYPING_INTERVAL_MS: i64 = 1000;
+/// 节流记录超过该数量时清理过期的记录
+const THROTTLE_PRUNE_THRESHOLD: usize = 1024;
+
+/// 输入状态请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TypingRequest {
+    /// 聊天室ID
+    pub chat_id: Option<String>,
+    /// 私信对象ID
+    pub to_id: Option<String>,
+    /// 是否正在输入，默认true
+    #[serde(default = "default_typing")]
+    pub typing: bool,
+}
+
+fn default_typing() -> bool {
+    true
+}
+
+/// 已读回执请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReadRequest {
+    /// 聊天室ID
+    pub chat_id: Option<String>,
+    /// 私信对象ID
+    pub peer_id: Option<String>,
+    /// 已读到的消息ID
+    pub message_id: String,
+}
+
+/// 按用户限制转发频率
+pub struct TypingThrottle {
+    interval_ms: i64,
+    last_sent: Mutex<HashMap<String, i64>>,
+}
+
+impl TypingThrottle {
+    pub fn new(interval_ms: i64) -> Self {
+        Self {
+            interval_ms,
+            last_sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 距离上次转发超过间隔时记录本次时间并返回true
+    pub fn allow(&self, user_id: &str, now: i64) -> bool {
+        let mut last_sent = self.last_sent.lock();
+        if let Some(last) = last_sent.get(user_id) {
+            if now - last < self.interval_ms {
+                return false;
+            }
+        }
+        if last_sent.len() >= THROTTLE_PRUNE_THRESHOLD {
+            let interval_ms = self.interval_ms;
+            last_sent.retain(|_, last| now - *last < interval_ms);
+        }
+        last_sent.insert(user_id.to_string(), now);
+        true
+    }
+}
+
+static TYPING_THROTTLE: Lazy<TypingThrottle> = Lazy::new(|| TypingThrottle::new(TYPING_INTERVAL_MS));
+
+/// 转发给私信对象的所有会话
+async fn relay_to_user(passport: &PassportState, user_id: &str, event: &str, payload: serde_json::Value) -> Result<()> {
+    for client_id in passport.get_user_sessions(user_id).await {
+        passport.connection_manager.send_to_client(&client_id, event, Some(payload.clone())).await?;
+    }
+    Ok(())
+}
+
+/// 转发给发送方所在的聊天室，发送方不在聊天室时忽略
+async fn relay_to_room(
+    client_id: &str,
+    chat_id: &str,
+    event: &str,
+    payload: serde_json::Value,
+    connection_manager: &ConnectionManager,
+) -> Result<()> {
+    let room_id = format!("{}:{}", ROOM_PREFIX, chat_id);
+    if !connection_manager.can_speak_in(client_id, &room_id).await {
+        debug!("客户端 {} 不在聊天室 {} 中，忽略 {}", client_id, room_id, event);
+        return Ok(());
+    }
+    connection_manager.broadcast_to_room(&room_id, event, Some(payload)).await?;
+    Ok(())
+}
+
+/**
+ * 处理输入状态事件
+ *
+ * 参数:
+ * @param client_id - 客户端ID
+ * @param user_id - 发送方用户ID
+ * @param request - 输入状态请求
+ * @param connection_manager - WebSocket连接管理器
+ */
+pub async fn handle_typing(
+    client_id: &str,
+    user_id: &str,
+    request: TypingRequest,
+    connection_manager: &ConnectionManager,
+) -> Result<()> {
+    if request.typing && !TYPING_THROTTLE.allow(user_id, Utc::now().timestamp_millis()) {
+        return Ok(());
+    }
+
+    if let Some(chat_id) = &request.chat_id {
+        let payload = serde_json::json!({
+            "user_id": user_id,
+            "chat_id": chat_id,
+            "typing": request.typing
+        });
+        return relay_to_room(client_id, chat_id, ChatEvents::TYPING, payload, connection_manager).await;
+    }
+
+    let (Some(to_id), Some(passport)) = (&request.to_id, crate::ws::global_passport_state()) else {
+        return Ok(());
+    };
+    if direct::check_permission(&passport, user_id, to_id).await.is_some() {
+        return Ok(());
+    }
+    let payload = serde_json::json!({
+        "user_id": user_id,
+        "typing": request.typing
+    });
+    relay_to_user(&passport, to_id, ChatEvents::TYPING, payload).await
+}
+
+/**
+ * 处理已读回执事件
+ *
+ * 参数:
+ * @param client_id - 客户端ID
+ * @param user_id - 已读的用户ID
+ * @param request - 已读回执请求
+ * @param connection_manager - WebSocket连接管理器
+ */
+pub async fn handle_read(
+    client_id: &str,
+    user_id: &str,
+    request: ReadRequest,
+    connection_manager: &ConnectionManager,
+) -> Result<()> {
+    let read_at = Utc::now().timestamp_millis();
+
+    if let Some(chat_id) = &request.chat_id {
+        let payload = serde_json::json!({
+            "user_id": user_id,
+            "chat_id": chat_id,
+            "message_id": request.message_id,
+            "read_at": read_at
+        });
+        return relay_to_room(client_id, chat_id, ChatEvents::READ, payload, connection_manager).await;
+    }
+
+    let (Some(peer_id), Some(passport)) = (&request.peer_id, crate::ws::global_passport_state()) else {
+        return Ok(());
+    };
+    direct::mark_read(&passport, user_id, peer_id);
+    if direct::check_permission(&passport, user_id, peer_id).await.is_some() {
+        return Ok(());
+    }
+    let payload = serde_json::json!({
+        "user_id": user_id,
+        "message_id": request.message_id,
+        "read_at": read_at
+    });
+    relay_to_user(&passport, peer_id, ChatEvents::READ, payload).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typing_throttle() {
+        let throttle = TypingThrottle::new(1000);
+        assert!(throttle.allow("u1", 0));
+        assert!(!throttle.allow("u1", 999));
+        assert!(throttle.allow("u2", 999));
+        assert!(throttle.allow("u1", 1000));
+        assert!(!throttle.allow("u1", 1500));
+    }
+
+    #[test]
+    fn test_throttle_prunes_expired_entries() {
+        let throttle = TypingThrottle::new(1000);
+        for i in 0..THROTTLE_PRUNE_THRESHOLD {
+            assert!(throttle.allow(&format!("u{}", i), 0));
+        }
+        assert!(throttle.allow("late", 5000));
+        assert_eq!(throttle.last_sent.lock().len(), 1);
+    }
+}
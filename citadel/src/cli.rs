@@ -11,9 +11,15 @@
  * - 提取用户私钥
  * - 验证用户私钥
  * - 使用Seal进行加密和解密操作
+ * - 通过密钥服务器获取密钥并解密，端到端验证部署
  * - 解析和查看加密对象的结构
  * - 发布Move模块
  * - 注册密钥服务器
+ * - 诊断密钥服务器配置
+ * - 从清单文件批量提取私钥和加密消息
+ * - 管理签名钱包
+ * - 运行机器人对局模拟
+ * - 重放对局验证最终状态
  */
 
 use clap::{Parser, Subcommand};
@@ -60,12 +66,17 @@ pub use fastcrypto::traits::{
 use sui_keys::keystore::{AccountKeystore,Keystore,InMemKeystore};
 use shared_crypto::intent::{Intent, IntentMessage};
 use crate::AppState;
+use crate::node_pool::{PoolConfig, SuiClientPool};
 use serde_json::json;
 use sui_sdk::json::SuiJsonValue;
 
 // 导入txb模块
 use crate::txb;
 
+pub mod batch;
+pub mod fetch;
+pub mod wallet;
+
 /// 密钥长度常量（字节）
 const KEY_LENGTH: usize = 32;
 
@@ -139,6 +150,31 @@ pub enum Command {
         public_key: G2Element,
     },
     
+    /// 从清单文件批量提取用户私钥
+    /// 
+    /// 清单为JSON或CSV文件，每个条目包含Hex编码的`id`。并发提取并将结果写入输出文件
+    ExtractBatch {
+        /// Sui网络上处理此密钥的KMS包的地址
+        #[arg(long)]
+        package_id: ObjectID,
+        
+        /// 主密钥。BLS12-381标量的Hex编码
+        #[arg(long, value_parser = parse_serializable::<Scalar, DefaultEncoding>)]
+        master_key: Scalar,
+        
+        /// 清单文件路径（.json或.csv）
+        #[arg(long)]
+        manifest: PathBuf,
+        
+        /// 结果输出文件路径（.json或.csv）
+        #[arg(long)]
+        output: PathBuf,
+        
+        /// 最大并发数
+        #[arg(long, default_value_t = batch::DEFAULT_CONCURRENCY)]
+        concurrency: usize,
+    },
+    
     /// 使用Seal派生密钥（明文模式）
     /// 
     /// 使用基于身份的密钥封装机制(IBKEM)派生密钥，具体使用BLS12381上的Boneh-Franklin方案。
@@ -233,6 +269,44 @@ pub enum Command {
         object_ids: Vec<ObjectID>,
     },
     
+    /// 从清单文件批量加密消息
+    /// 
+    /// 清单为JSON或CSV文件，每个条目包含Hex编码的`id`、`message`和可选的`aad`。
+    /// 并发加密并将加密对象和对称密钥写入输出文件
+    EncryptBatch {
+        /// Sui网络上处理此加密的KMS包的地址
+        #[arg(long)]
+        package_id: ObjectID,
+        
+        /// 加密模式
+        #[arg(long, value_enum, default_value_t = batch::EncryptMode::Aes)]
+        mode: batch::EncryptMode,
+        
+        /// 解密所需的密钥服务器最小数量（阈值）
+        #[arg(long)]
+        threshold: u8,
+        
+        /// 清单文件路径（.json或.csv）
+        #[arg(long)]
+        manifest: PathBuf,
+        
+        /// 结果输出文件路径（.json或.csv）
+        #[arg(long)]
+        output: PathBuf,
+        
+        /// 最大并发数
+        #[arg(long, default_value_t = batch::DEFAULT_CONCURRENCY)]
+        concurrency: usize,
+        
+        /// 密钥服务器的Hex编码公钥列表
+        #[arg(value_parser = parse_serializable::<G2Element, DefaultEncoding>, num_args = 1..)]
+        public_keys: Vec<G2Element>,
+        
+        /// 表示密钥服务器的Move对象地址列表
+        #[arg(num_args = 1.., last = true)]
+        object_ids: Vec<ObjectID>,
+    },
+    
     /// 解密Seal加密对象
     /// 
     /// 使用提供的密钥服务器私钥解密加密对象。如果加密对象包含消息，则返回该消息。
@@ -251,6 +325,40 @@ pub enum Command {
         object_ids: Vec<ObjectID>,
     },
     
+    /// 通过密钥服务器获取密钥并解密
+    /// 
+    /// 构建签名的获取密钥请求，调用运行中的密钥服务器的 `/v1/fetch_key`，
+    /// 合并返回的密钥份额后解密加密对象，用于端到端验证部署
+    FetchAndDecrypt {
+        /// 加密对象（Hex编码字节）
+        #[arg(value_parser = parse_serializable::<EncryptedObject, DefaultEncoding>)]
+        encrypted_object: EncryptedObject,
+        
+        /// 密钥服务器地址，可以指定多个
+        #[arg(long, short = 's', required = true, num_args = 1..)]
+        server: Vec<String>,
+        
+        /// 审批函数，格式为 `module::function`，以加密对象的id作为参数
+        #[arg(long, short = 'a', required_unless_present = "ptb")]
+        approve: Option<String>,
+        
+        /// Base64编码的审批交易，提供时忽略--approve
+        #[arg(long)]
+        ptb: Option<String>,
+        
+        /// 审批函数所在的包ID（最新版本），默认使用加密对象中的包ID
+        #[arg(long, short = 'p')]
+        package_id: Option<ObjectID>,
+        
+        /// 会话证书的有效期（分钟），不能超过密钥服务器允许的上限
+        #[arg(long, default_value_t = crate::keys::SESSION_KEY_TTL_MAX)]
+        ttl_min: u16,
+        
+        /// 签名钱包的别名或地址，未指定时使用WALLET_SK
+        #[arg(long, short = 'w')]
+        wallet: Option<String>,
+    },
+    
     /// 解析Seal加密对象
     /// 
     /// 解析并显示加密对象的各个组成部分，包括版本、包ID、加密份额等详细信息
@@ -279,7 +387,11 @@ pub enum Command {
     Publish {
         /// 要发布的模块路径
         #[arg(short = 'm')]
-        module: String
+        module: String,
+        
+        /// 签名钱包的别名或地址，未指定时使用WALLET_SK
+        #[arg(long, short = 'w')]
+        wallet: Option<String>,
     },
     
     /// 注册密钥服务器
@@ -301,6 +413,22 @@ pub enum Command {
         /// 服务器IBE公钥
         #[arg(long, short = 'k', value_parser = parse_serializable::<G2Element, DefaultEncoding>)]
         public_key: G2Element,
+        
+        /// 签名钱包的别名或地址，未指定时使用WALLET_SK
+        #[arg(long, short = 'w')]
+        wallet: Option<String>,
+    },
+    
+    /// 管理签名钱包
+    /// 
+    /// 基于密钥库文件生成、导入、列出和加密导出私钥
+    Wallet {
+        /// 密钥库文件路径，默认读取WALLET_KEYSTORE环境变量
+        #[arg(long, global = true)]
+        keystore: Option<PathBuf>,
+        
+        #[command(subcommand)]
+        command: wallet::WalletCommand,
     },
 
     /// 解码为十六进制 (Decode from Base64)
@@ -328,6 +456,65 @@ pub enum Command {
         #[arg(long, short = 's', group = "input")]
         string: Option<String>,
     },
+
+    /// 诊断密钥服务器配置
+    /// 
+    /// 检查Sui节点连通性、MASTER_KEY与链上KeyServer对象公钥是否匹配、
+    /// 注册的服务器URL是否有效以及全节点检查点是否过时。建议在节点上线前运行
+    Diagnose {
+        /// 密钥服务器对象ID，默认读取KEY_SERVER_OBJECT_ID环境变量
+        #[arg(long, short = 'o')]
+        key_server_object_id: Option<ObjectID>,
+        
+        /// 允许的检查点过时时间（秒），默认读取ALLOWED_STALENESS_SECS环境变量
+        #[arg(long)]
+        max_staleness_secs: Option<u64>,
+        
+        /// 跳过对注册URL的访问检查
+        #[arg(long)]
+        skip_url_probe: bool,
+    },
+
+    /// 运行机器人对局模拟
+    /// 
+    /// 在本地运行多局机器人对战，不连接缓存和WebSocket，
+    /// 统计各难度的胜率和平均对局长度，用于调整牌组配置
+    Simulate {
+        /// 模拟局数
+        #[arg(long, short = 'n', default_value_t = 1000)]
+        matches: usize,
+        
+        /// 每局玩家数
+        #[arg(long, short = 'p', default_value_t = 4)]
+        players: usize,
+        
+        /// 各座位机器人的难度，逗号分隔，按座位顺序循环使用
+        #[arg(long, value_enum, value_delimiter = ',', default_values = ["random", "heuristic", "probability"])]
+        bots: Vec<crate::bot::BotDifficulty>,
+        
+        /// 游戏模式
+        #[arg(long, default_value = crate::game_mode::DEFAULT_MODE)]
+        mode: String,
+    },
+
+    /// 重放对局验证最终状态
+    /// 
+    /// 按对局记录的随机种子和动作历史重新执行对局，核对最终状态与服务器记录是否一致。
+    /// 指定--file时在本地重放导出的对局数据（需包含完整动作历史），对局未公开种子时
+    /// 需要设置与服务器相同的MATCH_SEED_SECRET；否则调用服务器的管理接口，管理密钥读取ADMIN_API_KEY环境变量
+    VerifyMatch {
+        /// 对局ID
+        #[arg(required_unless_present = "file")]
+        match_id: Option<String>,
+        
+        /// 导出的对局数据文件（JSON）
+        #[arg(long, short = 'f')]
+        file: Option<PathBuf>,
+        
+        /// 服务器地址
+        #[arg(long, default_value = "http://localhost:3000")]
+        server: String,
+    },
 }
 
 /// 生成密钥命令的输出结构
@@ -525,9 +712,376 @@ impl Display for Base64Output {
     }
 }
 
+/// 批量命令的输出结构
+struct BatchOutput {
+    total: usize,
+    failed: usize,
+    output: PathBuf,
+}
+
+impl BatchOutput {
+    fn new(results: &[batch::BatchResult], output: PathBuf) -> Self {
+        Self {
+            total: results.len(),
+            failed: results.iter().filter(|r| !r.ok).count(),
+            output,
+        }
+    }
+}
+
+impl Display for BatchOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "共处理 {} 项，成功 {} 项，失败 {} 项\n结果已写入: {}",
+            self.total,
+            self.total - self.failed,
+            self.failed,
+            self.output.display()
+        )
+    }
+}
+
+/// 诊断检查状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// 单项诊断检查结果
+struct DiagnosticCheck {
+    name: &'static str,
+    status: CheckStatus,
+    detail: String,
+}
+
+/// 诊断命令的输出结构
+#[derive(Default)]
+struct DiagnoseOutput(Vec<DiagnosticCheck>);
+
+impl DiagnoseOutput {
+    fn push(&mut self, name: &'static str, status: CheckStatus, detail: impl Into<String>) {
+        self.0.push(DiagnosticCheck {
+            name,
+            status,
+            detail: detail.into(),
+        });
+    }
+
+    /// 失败的检查项数量
+    fn failures(&self) -> usize {
+        self.0.iter().filter(|c| c.status == CheckStatus::Fail).count()
+    }
+}
+
+impl Display for DiagnoseOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for check in &self.0 {
+            let status = match check.status {
+                CheckStatus::Pass => "通过",
+                CheckStatus::Warn => "警告",
+                CheckStatus::Fail => "失败",
+            };
+            writeln!(f, "[{}] {}: {}", status, check.name, check.detail)?;
+        }
+        write!(f, "共 {} 项检查，{} 项失败", self.0.len(), self.failures())
+    }
+}
+
+/**
+ * 诊断密钥服务器配置
+ *
+ * 依次检查节点连通性、主密钥、链上KeyServer对象、注册URL和检查点新鲜度，
+ * 前置检查失败时跳过依赖它的检查
+ *
+ * 参数:
+ * @param key_server_object_id - 密钥服务器对象ID，为空时读取环境变量
+ * @param max_staleness - 允许的检查点过时时间
+ * @param skip_url_probe - 是否跳过对注册URL的访问
+ *
+ * 返回:
+ * 各项检查结果
+ */
+async fn run_diagnostics(
+    key_server_object_id: Option<ObjectID>,
+    max_staleness: std::time::Duration,
+    skip_url_probe: bool,
+) -> DiagnoseOutput {
+    let mut output = DiagnoseOutput::default();
+    let network = AppState::init_network();
+    
+    // 节点连通性，配置了多个地址时逐个检查，备用地址不可用只给出警告
+    let mut sui_client = None;
+    for (i, url) in network.node_urls().into_iter().enumerate() {
+        let unreachable = if i == 0 { CheckStatus::Fail } else { CheckStatus::Warn };
+        let client = match SuiClientBuilder::default().build(&url).await {
+            Ok(client) => client,
+            Err(e) => {
+                output.push("Sui节点连通性", unreachable, format!("{}: {}", url, e));
+                continue;
+            }
+        };
+        match client.read_api().get_chain_identifier().await {
+            Ok(chain_id) => {
+                output.push(
+                    "Sui节点连通性",
+                    CheckStatus::Pass,
+                    format!("{} (链ID: {}, API版本: {})", url, chain_id, client.api_version()),
+                );
+                sui_client.get_or_insert(client);
+            }
+            Err(e) => output.push("Sui节点连通性", unreachable, format!("{}: {}", url, e)),
+        }
+    }
+    let Some(sui_client) = sui_client else {
+        return output;
+    };
+    
+    // 检查点新鲜度
+    match crate::externals::get_latest_checkpoint_timestamp(sui_client.clone()).await {
+        Ok(timestamp) => {
+            let staleness = crate::externals::duration_since(timestamp);
+            let status = if staleness > max_staleness.as_millis() as i64 {
+                CheckStatus::Fail
+            } else {
+                CheckStatus::Pass
+            };
+            output.push(
+                "检查点新鲜度",
+                status,
+                format!("最新检查点距今 {} ms，允许 {} ms", staleness, max_staleness.as_millis()),
+            );
+        }
+        Err(e) => output.push("检查点新鲜度", CheckStatus::Fail, format!("获取最新检查点失败: {}", e)),
+    }
+    
+    // 主密钥
+    let master_key = match env::var("MASTER_KEY") {
+        Err(_) => {
+            output.push("MASTER_KEY", CheckStatus::Fail, "未设置MASTER_KEY环境变量");
+            None
+        }
+        Ok(value) => {
+            let parsed = Base64::decode(&value)
+                .map_err(|e| format!("不是有效的Base64: {}", e))
+                .and_then(|bytes| {
+                    bytes
+                        .try_into()
+                        .map_err(|_| "长度无效".to_string())
+                })
+                .and_then(|bytes| {
+                    crate::types::IbeMasterKey::from_byte_array(&bytes).map_err(|e| format!("{}", e))
+                });
+            match parsed {
+                Ok(master_key) => {
+                    output.push("MASTER_KEY", CheckStatus::Pass, "格式有效");
+                    Some(master_key)
+                }
+                Err(e) => {
+                    output.push("MASTER_KEY", CheckStatus::Fail, e);
+                    None
+                }
+            }
+        }
+    };
+    
+    // 链上KeyServer对象
+    let object_id = match key_server_object_id {
+        Some(id) => Some(id),
+        None => match env::var("KEY_SERVER_OBJECT_ID").map(|v| ObjectID::from_hex_literal(&v)) {
+            Ok(Ok(id)) => Some(id),
+            Ok(Err(e)) => {
+                output.push("KeyServer对象", CheckStatus::Fail, format!("KEY_SERVER_OBJECT_ID无效: {}", e));
+                None
+            }
+            Err(_) => {
+                output.push("KeyServer对象", CheckStatus::Fail, "未设置KEY_SERVER_OBJECT_ID环境变量");
+                None
+            }
+        },
+    };
+    let Some(object_id) = object_id else {
+        return output;
+    };
+    let fields = match sui_client
+        .read_api()
+        .get_object_with_options(object_id, SuiObjectDataOptions::full_content())
+        .await
+    {
+        Ok(response) => match response.data.and_then(|d| d.content).and_then(|c| c.try_as_move().cloned()) {
+            Some(object) if object.type_.name.as_str() == "KeyServer" => {
+                output.push("KeyServer对象", CheckStatus::Pass, format!("{} ({})", object_id, object.type_));
+                object.fields
+            }
+            Some(object) => {
+                output.push("KeyServer对象", CheckStatus::Fail, format!("对象类型不是KeyServer: {}", object.type_));
+                return output;
+            }
+            None => {
+                output.push("KeyServer对象", CheckStatus::Fail, format!("对象 {} 不存在或不是Move对象", object_id));
+                return output;
+            }
+        },
+        Err(e) => {
+            output.push("KeyServer对象", CheckStatus::Fail, format!("获取对象 {} 失败: {}", object_id, e));
+            return output;
+        }
+    };
+    
+    // 公钥匹配
+    let registered_pk = fields
+        .field_value("pk")
+        .map(|v| v.to_json_value())
+        .and_then(|v| v.as_array().cloned())
+        .map(|v| v.iter().filter_map(|b| b.as_u64().map(|b| b as u8)).collect::<Vec<_>>());
+    match (&master_key, registered_pk) {
+        (Some(master_key), Some(registered_pk)) => {
+            let public_key = ibe::public_key_from_master_key(master_key);
+            if public_key.to_byte_array().as_slice() == registered_pk.as_slice() {
+                output.push("公钥匹配", CheckStatus::Pass, "MASTER_KEY与链上注册的公钥一致");
+            } else {
+                output.push(
+                    "公钥匹配",
+                    CheckStatus::Fail,
+                    format!(
+                        "MASTER_KEY对应公钥 {} 与链上公钥 {} 不一致",
+                        Hex::encode(public_key.to_byte_array()),
+                        Hex::encode(&registered_pk)
+                    ),
+                );
+            }
+        }
+        (None, _) => output.push("公钥匹配", CheckStatus::Fail, "MASTER_KEY无效，无法比较公钥"),
+        (_, None) => output.push("公钥匹配", CheckStatus::Fail, "KeyServer对象缺少pk字段"),
+    }
+    
+    // 注册URL
+    let url = fields
+        .field_value("url")
+        .and_then(|v| v.to_json_value().as_str().map(|s| s.to_string()));
+    let Some(url) = url else {
+        output.push("注册URL", CheckStatus::Fail, "KeyServer对象缺少url字段");
+        return output;
+    };
+    let parsed_url = match reqwest::Url::parse(&url) {
+        Ok(parsed) if parsed.scheme() == "https" => {
+            output.push("注册URL", CheckStatus::Pass, url.clone());
+            parsed
+        }
+        Ok(parsed) if parsed.scheme() == "http" => {
+            let local = matches!(parsed.host_str(), Some("localhost") | Some("127.0.0.1"));
+            let status = if local { CheckStatus::Warn } else { CheckStatus::Fail };
+            output.push("注册URL", status, format!("{} 未使用https", url));
+            parsed
+        }
+        Ok(parsed) => {
+            output.push("注册URL", CheckStatus::Fail, format!("不支持的协议: {}", parsed.scheme()));
+            return output;
+        }
+        Err(e) => {
+            output.push("注册URL", CheckStatus::Fail, format!("{} 不是有效的URL: {}", url, e));
+            return output;
+        }
+    };
+    if skip_url_probe {
+        return output;
+    }
+    
+    // 通过注册URL访问服务信息，确认其指向本服务器
+    let service_url = match parsed_url.join("v1/service") {
+        Ok(service_url) => service_url,
+        Err(e) => {
+            output.push("服务信息", CheckStatus::Fail, format!("无法构建服务地址: {}", e));
+            return output;
+        }
+    };
+    let response = reqwest::Client::new()
+        .get(service_url.clone())
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status());
+    let service = match response {
+        Ok(response) => response.json::<serde_json::Value>().await,
+        Err(e) => {
+            output.push("服务信息", CheckStatus::Fail, format!("访问 {} 失败: {}", service_url, e));
+            return output;
+        }
+    };
+    match service {
+        Ok(service) => {
+            let service_id = service.get("service_id").and_then(|v| v.as_str()).map(ObjectID::from_hex_literal);
+            let expected_pop = master_key.as_ref().map(|master_key| {
+                serde_json::to_value(crypto::ibe::create_proof_of_possession(master_key, &object_id.into_bytes()))
+                    .unwrap_or_default()
+            });
+            if !matches!(service_id, Some(Ok(id)) if id == object_id) {
+                output.push(
+                    "服务信息",
+                    CheckStatus::Fail,
+                    format!("{} 返回的服务ID与 {} 不一致", service_url, object_id),
+                );
+            } else if expected_pop.is_some() && service.get("pop") != expected_pop.as_ref() {
+                output.push(
+                    "服务信息",
+                    CheckStatus::Fail,
+                    format!("{} 返回的持有证明与MASTER_KEY不匹配", service_url),
+                );
+            } else {
+                output.push("服务信息", CheckStatus::Pass, format!("{} 返回的服务ID一致", service_url));
+            }
+        }
+        Err(e) => output.push("服务信息", CheckStatus::Fail, format!("解析 {} 的响应失败: {}", service_url, e)),
+    }
+    
+    output
+}
+
 /// 运行CLI命令
 /// 
 /// 处理来自主程序的CLI命令，执行相应的操作并返回结果
+/**
+ * 重放对局验证最终状态
+ *
+ * 参数:
+ * @param match_id - 对局ID，指定文件时忽略
+ * @param file - 导出的对局数据文件
+ * @param server - 服务器地址
+ *
+ * 返回:
+ * 重放验证报告
+ */
+async fn verify_match(
+    match_id: Option<String>,
+    file: Option<PathBuf>,
+    server: &str,
+) -> anyhow::Result<crate::match_replay::ReplayReport> {
+    if let Some(file) = file {
+        let content = std::fs::read_to_string(&file)
+            .with_context(|| format!("读取对局数据文件 {} 失败", file.display()))?;
+        let match_data: crate::gaming::MatchData = serde_json::from_str(&content)
+            .with_context(|| format!("解析对局数据文件 {} 失败", file.display()))?;
+        return crate::match_replay::replay_match(&match_data);
+    }
+
+    let match_id = match_id.ok_or_else(|| anyhow::anyhow!("必须提供对局ID或--file参数"))?;
+    let admin_key = env::var("ADMIN_API_KEY").context("未设置ADMIN_API_KEY环境变量")?;
+    let url = format!("{}/admin/matches/{}/verify", server.trim_end_matches('/'), match_id);
+    let report = reqwest::Client::new()
+        .get(&url)
+        .header("X-Admin-Key", admin_key)
+        .timeout(std::time::Duration::from_secs(30))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("请求 {} 失败", url))?
+        .json::<crate::match_replay::ReplayReport>()
+        .await?;
+    Ok(report)
+}
+
 pub async fn run_cli_command(command: Command) -> anyhow::Result<()> {
     // 初始化环境变量
     dotenv().ok();
@@ -560,6 +1114,29 @@ pub async fn run_cli_command(command: Command) -> anyhow::Result<()> {
         ))
         .to_string(),
         
+        // 从清单文件批量提取用户私钥
+        Command::ExtractBatch {
+            package_id,
+            master_key,
+            manifest,
+            output,
+            concurrency,
+        } => {
+            let entries: Vec<batch::ExtractEntry> = batch::read_manifest(&manifest)?;
+            let results = batch::run_batch(entries, concurrency, move |entry| {
+                let outcome = DefaultEncoding::decode(&entry.id)
+                    .map_err(|e| format!("无效的ID: {}", e))
+                    .map(|id| {
+                        let key = ibe::extract(&master_key, &create_full_id(&package_id, &id));
+                        (serializable_to_string(&key), None)
+                    });
+                batch::BatchResult::from_outcome(entry.id, outcome)
+            })
+            .await;
+            batch::write_results(&output, &results)?;
+            BatchOutput::new(&results, output).to_string()
+        },
+        
         // 使用Seal派生密钥（明文模式）
         Command::Plain {
             package_id,
@@ -621,6 +1198,47 @@ pub async fn run_cli_command(command: Command) -> anyhow::Result<()> {
         )?)
         .to_string(),
         
+        // 从清单文件批量加密消息
+        Command::EncryptBatch {
+            package_id,
+            mode,
+            threshold,
+            manifest,
+            output,
+            concurrency,
+            public_keys,
+            object_ids,
+        } => {
+            let entries: Vec<batch::EncryptEntry> = batch::read_manifest(&manifest)?;
+            let public_keys = IBEPublicKeys::BonehFranklinBLS12381(public_keys);
+            let results = batch::run_batch(entries, concurrency, move |entry| {
+                let outcome = (|| {
+                    let id = DefaultEncoding::decode(&entry.id).map_err(|e| format!("无效的ID: {}", e))?;
+                    let data = DefaultEncoding::decode(&entry.message).map_err(|e| format!("无效的消息: {}", e))?;
+                    let aad = entry
+                        .aad
+                        .as_deref()
+                        .filter(|a| !a.is_empty())
+                        .map(DefaultEncoding::decode)
+                        .transpose()
+                        .map_err(|e| format!("无效的额外认证数据: {}", e))?;
+                    let input = match mode {
+                        batch::EncryptMode::Aes => EncryptionInput::Aes256Gcm { data, aad },
+                        batch::EncryptMode::Hmac => EncryptionInput::Hmac256Ctr { data, aad },
+                        batch::EncryptMode::Plain => Plain,
+                    };
+                    let (encrypted_object, key) =
+                        seal_encrypt(package_id, id, object_ids.clone(), &public_keys, threshold, input)
+                            .map_err(|e| format!("加密失败: {}", e))?;
+                    Ok((serializable_to_string(&encrypted_object), Some(Hex::encode(key))))
+                })();
+                batch::BatchResult::from_outcome(entry.id, outcome)
+            })
+            .await;
+            batch::write_results(&output, &results)?;
+            BatchOutput::new(&results, output).to_string()
+        },
+        
         // 解密Seal加密对象
         Command::Decrypt {
             encrypted_object,
@@ -635,6 +1253,27 @@ pub async fn run_cli_command(command: Command) -> anyhow::Result<()> {
         )?)
         .to_string(),
         
+        // 通过密钥服务器获取密钥并解密
+        Command::FetchAndDecrypt {
+            encrypted_object,
+            server,
+            approve,
+            ptb,
+            package_id,
+            ttl_min,
+            wallet,
+        } => {
+            let options = fetch::FetchOptions {
+                servers: server,
+                approve,
+                ptb,
+                package_id,
+                ttl_min,
+                wallet,
+            };
+            DecryptionOutput(fetch::fetch_and_decrypt(&encrypted_object, &options).await?).to_string()
+        },
+        
         // 解析Seal加密对象
         Command::Parse { encrypted_object } => ParseOutput(encrypted_object).to_string(),
         
@@ -667,6 +1306,7 @@ pub async fn run_cli_command(command: Command) -> anyhow::Result<()> {
         // 发布Move模块
         Command::Publish {
             module,
+            wallet,
         } => {
             // 读取发布的环境
             let network = AppState::init_network();
@@ -679,18 +1319,17 @@ pub async fn run_cli_command(command: Command) -> anyhow::Result<()> {
                 .build(&path)
                 .context("Compile Move package failed")?;
             
-            // 初始化SUI客户端
-            let sui_client = SuiClientBuilder::default()
-                .build(network.node_url())
+            // 初始化SUI客户端，使用第一个可用的全节点地址
+            let sui_client = SuiClientPool::connect(network.name(), &network.node_urls(), PoolConfig::default())
                 .await
-                .expect("Sui client build failed");
+                .expect("Sui client build failed")
+                .client();
             
             // 创建事务构建器
             let tx_builder = sui_client.transaction_builder();
             
-            // 从环境变量获取密钥对并创建密钥库
-            let sk = env::var("WALLET_SK").context("未设置WALLET_SK环境变量")?;
-            let (keystore, _, sender) = txb::create_keystore_from_sk(&sk, Some("EnvKeyPair".to_string()))?;
+            // 从密钥库或环境变量加载签名者
+            let (keystore, sender) = wallet::load_signer(wallet.as_deref())?;
             
             // 创建发布事务
             let tx_data = tx_builder
@@ -796,21 +1435,21 @@ pub async fn run_cli_command(command: Command) -> anyhow::Result<()> {
             description,
             url,
             public_key,
+            wallet,
         } => {
             // 初始化环境变量
             dotenv().ok();
             // 读取网络配置
             let network = AppState::init_network();
             
-            // 初始化SUI客户端
-            let sui_client = SuiClientBuilder::default()
-                .build(network.node_url())
+            // 初始化SUI客户端，使用第一个可用的全节点地址
+            let sui_client = SuiClientPool::connect(network.name(), &network.node_urls(), PoolConfig::default())
                 .await
-                .expect("Sui client build failed");
+                .expect("Sui client build failed")
+                .client();
             
-            // 从环境变量获取密钥对并创建密钥库
-            let sk = env::var("WALLET_SK").context("未设置WALLET_SK环境变量")?;
-            let (keystore, _, sender) = txb::create_keystore_from_sk(&sk, Some("EnvKeyPair".to_string()))?;
+            // 从密钥库或环境变量加载签名者
+            let (keystore, sender) = wallet::load_signer(wallet.as_deref())?;
             
             // 构建注册事务
             let tx_builder = sui_client.transaction_builder();
@@ -923,6 +1562,46 @@ pub async fn run_cli_command(command: Command) -> anyhow::Result<()> {
                 anyhow::bail!("必须提供-x或-s参数");
             }
         },
+        
+        // 管理签名钱包
+        Command::Wallet { keystore, command } => wallet::run_wallet_command(command, keystore)?,
+        
+        // 诊断密钥服务器配置
+        Command::Diagnose {
+            key_server_object_id,
+            max_staleness_secs,
+            skip_url_probe,
+        } => {
+            let report = run_diagnostics(
+                key_server_object_id,
+                max_staleness_secs
+                    .map(std::time::Duration::from_secs)
+                    .unwrap_or_else(|| crate::node_health::NodeHealth::from_env().allowed_staleness()),
+                skip_url_probe,
+            )
+            .await;
+            if report.failures() > 0 {
+                println!("{}", report);
+                anyhow::bail!("诊断发现 {} 项失败", report.failures());
+            }
+            report.to_string()
+        },
+        
+        // 运行机器人对局模拟
+        Command::Simulate { matches, players, bots, mode } => {
+            let config = crate::bot::simulate::SimulationConfig { mode, matches, players, bots };
+            crate::bot::simulate::run_simulation(&config)?.to_string()
+        },
+        
+        // 重放对局验证最终状态
+        Command::VerifyMatch { match_id, file, server } => {
+            let report = verify_match(match_id, file, &server).await?;
+            if !report.verified {
+                println!("{}", report);
+                anyhow::bail!("对局 {} 重放验证失败", report.match_id);
+            }
+            report.to_string()
+        },
     };
     
     // 输出结果
@@ -0,0 +1,248 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! CLI批量提取和加密
+//!
+//! # 概述
+//! 从清单文件读取待处理的ID或消息，并发处理并显示进度条，最后将结果写入输出文件。
+//! 清单和输出文件的格式由扩展名决定，支持 `.json` 和 `.csv`。
+//!
+//! # 清单格式
+//! ```json
+//! // extract-batch
+//! [{ "id": "0a0b" }, { "id": "0c0d" }]
+//! // encrypt-batch，aad可选
+//! [{ "id": "0a0b", "message": "68656c6c6f", "aad": "01" }]
+//! ```
+//! CSV清单的表头与JSON字段名一致，如 `id,message,aad`。
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use futures::StreamExt;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// 默认并发数
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// 清单和输出文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Json,
+    Csv,
+}
+
+impl FileFormat {
+    /// 根据文件扩展名确定格式
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+            Some("json") => Ok(FileFormat::Json),
+            Some("csv") => Ok(FileFormat::Csv),
+            _ => bail!("不支持的文件格式: {}，请使用.json或.csv", path.display()),
+        }
+    }
+}
+
+/// 批量加密使用的模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EncryptMode {
+    /// AES-256-GCM
+    Aes,
+    /// HMAC-256-CTR
+    Hmac,
+    /// 只派生密钥
+    Plain,
+}
+
+/// 清单条目
+pub trait ManifestEntry: DeserializeOwned + Send + 'static {
+    /// 条目的ID（Hex编码）
+    fn id(&self) -> &str;
+}
+
+/// 批量提取的清单条目
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct ExtractEntry {
+    pub id: String,
+}
+
+impl ManifestEntry for ExtractEntry {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+/// 批量加密的清单条目
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct EncryptEntry {
+    pub id: String,
+    /// 要加密的消息（Hex编码），plain模式下忽略
+    #[serde(default)]
+    pub message: String,
+    /// 可选的额外认证数据（Hex编码）
+    #[serde(default)]
+    pub aad: Option<String>,
+}
+
+impl ManifestEntry for EncryptEntry {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+/// 单个条目的处理结果
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchResult {
+    pub id: String,
+    pub ok: bool,
+    /// 提取时为用户私钥，加密时为加密对象（Hex编码）
+    pub value: Option<String>,
+    /// 加密时派生的对称密钥（Hex编码）
+    pub symmetric_key: Option<String>,
+    pub error: Option<String>,
+}
+
+impl BatchResult {
+    /// 根据处理结果创建
+    pub fn from_outcome(id: String, outcome: Result<(String, Option<String>), String>) -> Self {
+        match outcome {
+            Ok((value, symmetric_key)) => Self {
+                id,
+                ok: true,
+                value: Some(value),
+                symmetric_key,
+                error: None,
+            },
+            Err(error) => Self {
+                id,
+                ok: false,
+                value: None,
+                symmetric_key: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+/// 解析清单内容
+pub fn parse_manifest<T: ManifestEntry>(content: &str, format: FileFormat) -> Result<Vec<T>> {
+    match format {
+        FileFormat::Json => Ok(serde_json::from_str(content)?),
+        FileFormat::Csv => csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(content.as_bytes())
+            .deserialize()
+            .collect::<Result<Vec<T>, _>>()
+            .map_err(Into::into),
+    }
+}
+
+/// 读取清单文件
+pub fn read_manifest<T: ManifestEntry>(path: &Path) -> Result<Vec<T>> {
+    let format = FileFormat::from_path(path)?;
+    let content = fs::read_to_string(path).with_context(|| format!("读取清单文件失败: {}", path.display()))?;
+    parse_manifest(&content, format).with_context(|| format!("解析清单文件失败: {}", path.display()))
+}
+
+/// 将结果写入输出文件
+pub fn write_results(path: &Path, results: &[BatchResult]) -> Result<()> {
+    let content = match FileFormat::from_path(path)? {
+        FileFormat::Json => serde_json::to_string_pretty(results)?,
+        FileFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for result in results {
+                writer.serialize(result)?;
+            }
+            String::from_utf8(writer.into_inner()?)?
+        }
+    };
+    fs::write(path, content).with_context(|| format!("写入结果文件失败: {}", path.display()))
+}
+
+/**
+ * 并发处理清单条目
+ *
+ * 每个条目在阻塞线程池中处理，结果顺序与清单顺序一致
+ *
+ * 参数:
+ * @param entries - 清单条目
+ * @param concurrency - 最大并发数
+ * @param job - 处理单个条目的函数
+ *
+ * 返回:
+ * 每个条目的处理结果
+ */
+pub async fn run_batch<T, F>(entries: Vec<T>, concurrency: usize, job: F) -> Vec<BatchResult>
+where
+    T: ManifestEntry,
+    F: Fn(T) -> BatchResult + Send + Sync + 'static,
+{
+    let progress = ProgressBar::new(entries.len() as u64);
+    progress.set_style(
+        ProgressStyle::with_template("[{elapsed_precise}] {bar:40} {pos}/{len} {msg}")
+            .unwrap_or_else(|_| ProgressStyle::default_bar()),
+    );
+
+    let job = Arc::new(job);
+    let results = futures::stream::iter(entries)
+        .map(|entry| {
+            let job = job.clone();
+            let progress = progress.clone();
+            async move {
+                let id = entry.id().to_string();
+                let result = tokio::task::spawn_blocking(move || job(entry))
+                    .await
+                    .unwrap_or_else(|e| BatchResult::from_outcome(id, Err(format!("处理任务异常: {}", e))));
+                progress.inc(1);
+                result
+            }
+        })
+        .buffered(concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await;
+
+    progress.finish_with_message("完成");
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let entries: Vec<EncryptEntry> =
+            parse_manifest(r#"[{ "id": "0a", "message": "68" }]"#, FileFormat::Json).unwrap();
+        assert_eq!(entries[0].aad, None);
+
+        let entries: Vec<EncryptEntry> =
+            parse_manifest("id,message,aad\n0a, 68 ,01\n0b,69,\n", FileFormat::Csv).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].message, "68");
+        assert_eq!(entries[0].aad.as_deref(), Some("01"));
+
+        assert!(FileFormat::from_path(Path::new("ids.txt")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_batch_keeps_order() {
+        let entries = (0..20).map(|i| ExtractEntry { id: format!("{:02x}", i) }).collect::<Vec<_>>();
+        let results = run_batch(entries, 4, |entry| {
+            if entry.id == "03" {
+                BatchResult::from_outcome(entry.id, Err("bad".to_string()))
+            } else {
+                BatchResult::from_outcome(entry.id.clone(), Ok((entry.id, None)))
+            }
+        })
+        .await;
+        assert_eq!(results.len(), 20);
+        assert_eq!(results[5].value.as_deref(), Some("05"));
+        assert!(!results[3].ok);
+        assert_eq!(results.iter().filter(|r| r.ok).count(), 19);
+    }
+}
@@ -0,0 +1,273 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 通过密钥服务器获取密钥并解密
+//!
+//! # 概述
+//! 按客户端SDK的流程完成一次端到端解密，运维人员部署密钥服务器后可以直接验证整条链路，
+//! 不需要编写客户端代码：
+//!
+//! 1. 生成临时会话密钥，由钱包对会话证书（个人消息）签名
+//! 2. 生成ElGamal密钥对，使用会话密钥对PTB、加密公钥、时间戳和nonce签名
+//! 3. 依次访问每个密钥服务器的 `/v1/service` 确认服务ID属于加密对象，再调用 `/v1/fetch_key`
+//! 4. 使用ElGamal私钥解密返回的用户私钥，达到加密对象的阈值后合并份额解密
+//!
+//! PTB可以通过 `--approve` 指定审批函数自动构建（以加密对象的id作为唯一参数），
+//! 也可以通过 `--ptb` 直接提供Base64编码的BCS序列化 `ProgrammableTransaction`。
+//! 审批函数需要使用包的最新版本，可以通过 `--package-id` 指定，会话证书始终使用加密对象中的原始包ID。
+//!
+//! 单个服务器请求失败时打印警告并继续，获得的密钥数不足阈值时返回错误。
+//!
+//! # 配置
+//! - `WALLET_SK`：未通过 `--wallet` 指定钱包时用于签名会话证书的私钥
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use crypto::{create_full_id, elgamal, seal_decrypt, EncryptedObject, IBEUserSecretKeys, ObjectID};
+use fastcrypto::ed25519::Ed25519KeyPair;
+use fastcrypto::encoding::{Base64, Encoding, Hex};
+use fastcrypto::traits::{KeyPair, Signer};
+use rand::{thread_rng, RngCore};
+use serde::Deserialize;
+use shared_crypto::intent::{Intent, PersonalMessage};
+use sui_keys::keystore::AccountKeystore;
+use sui_types::programmable_transaction_builder::ProgrammableTransactionBuilder;
+use sui_types::signature::GenericSignature;
+use sui_types::transaction::ProgrammableTransaction;
+use sui_types::Identifier;
+use tracing::warn;
+
+use crate::externals::current_epoch_time;
+use crate::keys::{Certificate, FetchKeyRequest, FetchKeyResponse};
+use crate::signed_message::{signed_message, signed_request_with_freshness};
+
+use super::wallet;
+
+/// 请求超时时间
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// nonce的随机字节数
+const NONCE_LENGTH: usize = 16;
+
+/// 获取密钥的参数
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    /// 密钥服务器地址
+    pub servers: Vec<String>,
+    /// 审批函数，格式为 `module::function`
+    pub approve: Option<String>,
+    /// Base64编码的PTB，提供时忽略approve
+    pub ptb: Option<String>,
+    /// 审批函数所在的包ID（最新版本），默认使用加密对象中的包ID
+    pub package_id: Option<ObjectID>,
+    /// 会话证书的有效期（分钟）
+    pub ttl_min: u16,
+    /// 签名钱包的别名或地址
+    pub wallet: Option<String>,
+}
+
+/// `/v1/service` 响应中用到的字段
+#[derive(Deserialize)]
+struct ServiceInfo {
+    service_id: ObjectID,
+}
+
+/**
+ * 构建调用审批函数的PTB
+ *
+ * 参数:
+ * @param package_id - 审批函数所在的包ID
+ * @param target - 审批函数，格式为 `module::function`
+ * @param id - 加密对象的id
+ *
+ * 返回:
+ * 只包含一次审批函数调用的PTB
+ */
+pub fn approve_ptb(package_id: ObjectID, target: &str, id: &[u8]) -> Result<ProgrammableTransaction> {
+    let (module, function) = target
+        .split_once("::")
+        .ok_or_else(|| anyhow!("审批函数 {} 格式无效，应为 module::function", target))?;
+    if !function.starts_with("seal_approve") {
+        bail!("审批函数 {} 必须以seal_approve开头", function);
+    }
+    let mut builder = ProgrammableTransactionBuilder::new();
+    let id_arg = builder.pure(id.to_vec())?;
+    builder.programmable_move_call(
+        package_id,
+        Identifier::from_str(module)?,
+        Identifier::from_str(function)?,
+        vec![],
+        vec![id_arg],
+    );
+    Ok(builder.finish())
+}
+
+/**
+ * 从密钥服务器获取密钥并解密加密对象
+ *
+ * 参数:
+ * @param encrypted_object - 加密对象
+ * @param options - 获取密钥的参数
+ *
+ * 返回:
+ * 解密后的明文
+ */
+pub async fn fetch_and_decrypt(encrypted_object: &EncryptedObject, options: &FetchOptions) -> Result<Vec<u8>> {
+    if options.servers.is_empty() {
+        bail!("至少需要指定一个密钥服务器");
+    }
+    let ptb = match (&options.ptb, &options.approve) {
+        (Some(ptb), _) => bcs::from_bytes::<ProgrammableTransaction>(
+            &Base64::decode(ptb).map_err(|e| anyhow!("PTB不是有效的Base64: {}", e))?,
+        )
+        .context("解析PTB失败")?,
+        (None, Some(target)) => approve_ptb(
+            options.package_id.unwrap_or(encrypted_object.package_id),
+            target,
+            &encrypted_object.id,
+        )?,
+        (None, None) => bail!("必须通过--approve或--ptb指定审批交易"),
+    };
+
+    // 钱包对临时会话密钥签发证书
+    let (keystore, user) = wallet::load_signer(options.wallet.as_deref())?;
+    let session = Ed25519KeyPair::generate(&mut thread_rng());
+    let creation_time = current_epoch_time();
+    let message = signed_message(&encrypted_object.package_id, session.public(), creation_time, options.ttl_min);
+    let signature = keystore
+        .sign_secure(&user, &PersonalMessage { message: message.into_bytes() }, Intent::personal_message())
+        .context("签名会话证书失败")?;
+    let certificate = Certificate {
+        user,
+        session_vk: session.public().clone(),
+        creation_time,
+        ttl_min: options.ttl_min,
+        signature: GenericSignature::Signature(signature),
+    };
+
+    // 会话密钥对请求签名，每个服务器使用不同的nonce
+    let (enc_secret, enc_key, enc_verification_key) = elgamal::genkey(&mut thread_rng());
+    let full_id = create_full_id(&encrypted_object.package_id.into_bytes(), &encrypted_object.id);
+    let client = reqwest::Client::new();
+    let mut user_secret_keys = std::collections::HashMap::new();
+    for server in &options.servers {
+        let mut nonce = [0u8; NONCE_LENGTH];
+        thread_rng().fill_bytes(&mut nonce);
+        let (timestamp, nonce) = (current_epoch_time(), Hex::encode(nonce));
+        let request = FetchKeyRequest::new(
+            Base64::encode(bcs::to_bytes(&ptb)?),
+            enc_key.clone(),
+            enc_verification_key.clone(),
+            session.sign(&signed_request_with_freshness(
+                &ptb,
+                &enc_key,
+                &enc_verification_key,
+                timestamp,
+                &nonce,
+            )),
+            certificate.clone(),
+            Some((timestamp, nonce)),
+        );
+        match fetch_key(&client, server, encrypted_object, &request).await {
+            Ok((service_id, response)) => {
+                let key = response
+                    .decryption_keys
+                    .iter()
+                    .find(|key| key.id() == full_id.as_slice())
+                    .ok_or_else(|| anyhow!("{} 未返回该加密对象的密钥", server))
+                    .map(|key| elgamal::decrypt(&enc_secret, &key.encrypted_key));
+                match key {
+                    Ok(key) => {
+                        user_secret_keys.insert(service_id, key);
+                    }
+                    Err(e) => warn!("{}", e),
+                }
+            }
+            Err(e) => warn!("从 {} 获取密钥失败: {:#}", server, e),
+        }
+    }
+
+    if user_secret_keys.len() < encrypted_object.threshold as usize {
+        bail!(
+            "只获得 {} 个密钥，解密需要 {} 个",
+            user_secret_keys.len(),
+            encrypted_object.threshold
+        );
+    }
+    seal_decrypt(
+        encrypted_object,
+        &IBEUserSecretKeys::BonehFranklinBLS12381(user_secret_keys),
+        None,
+    )
+    .map_err(|e| anyhow!("解密失败: {}", e))
+}
+
+/**
+ * 向单个密钥服务器请求密钥
+ *
+ * 参数:
+ * @param client - HTTP客户端
+ * @param server - 密钥服务器地址
+ * @param encrypted_object - 加密对象，用于确认服务器属于该对象的服务器列表
+ * @param request - 已签名的请求
+ *
+ * 返回:
+ * 服务器的服务ID和响应
+ */
+async fn fetch_key(
+    client: &reqwest::Client,
+    server: &str,
+    encrypted_object: &EncryptedObject,
+    request: &FetchKeyRequest,
+) -> Result<(ObjectID, FetchKeyResponse)> {
+    let server = server.trim_end_matches('/');
+    let service = client
+        .get(format!("{}/v1/service", server))
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .context("获取服务信息失败")?
+        .json::<ServiceInfo>()
+        .await
+        .context("解析服务信息失败")?;
+    if !encrypted_object.services.iter().any(|(id, _)| *id == service.service_id) {
+        bail!("服务 {} 不在加密对象的服务器列表中", service.service_id);
+    }
+
+    let response = client
+        .post(format!("{}/v1/fetch_key", server))
+        .header("Client-Sdk-Type", "cli")
+        .header("Client-Sdk-Version", env!("CARGO_PKG_VERSION"))
+        .header("Request-Id", Hex::encode(current_epoch_time().to_be_bytes()))
+        .timeout(REQUEST_TIMEOUT)
+        .json(request)
+        .send()
+        .await
+        .context("请求密钥失败")?;
+    let status = response.status();
+    if !status.is_success() {
+        bail!("服务器返回 {}: {}", status, response.text().await.unwrap_or_default());
+    }
+    Ok((service.service_id, response.json::<FetchKeyResponse>().await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::valid_ptb::ValidPtb;
+
+    #[test]
+    fn test_approve_ptb() {
+        let package_id = ObjectID::random();
+        let id = vec![1u8, 2, 3];
+        let ptb = approve_ptb(package_id, "citadel::seal_approve_match_hand", &id).unwrap();
+        let valid_ptb = ValidPtb::try_from(ptb).unwrap();
+        assert_eq!(valid_ptb.pkg_id(), package_id);
+        assert_eq!(valid_ptb.inner_ids(), vec![id.clone()]);
+
+        assert!(approve_ptb(package_id, "seal_approve", &id).is_err());
+        assert!(approve_ptb(package_id, "citadel::join", &id).is_err());
+    }
+}
@@ -0,0 +1,365 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! CLI钱包管理
+//!
+//! # 概述
+//! 基于文件的密钥库管理签名地址，发布和注册密钥服务器时可以通过别名或地址选择签名者，
+//! 不必在环境变量中保存原始私钥。
+//!
+//! - `generate`：生成新的密钥对并保存到密钥库
+//! - `import`：导入私钥或加密导出文件
+//! - `list`：列出密钥库中的地址和别名
+//! - `export`：使用口令加密导出私钥
+//!
+//! # 配置
+//! - `WALLET_KEYSTORE`：密钥库文件路径，默认为当前目录下的 `citadel.keystore`
+//! - `WALLET_PASSPHRASE`：导入导出时使用的口令，未设置时交互输入
+//! - `WALLET_SK`：未指定钱包时使用的私钥（兼容旧配置）
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use crypto::dem::Aes256Gcm;
+use fastcrypto::encoding::{Encoding, Hex};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sui_keys::keystore::{AccountKeystore, FileBasedKeystore, Keystore};
+use sui_types::base_types::SuiAddress;
+use sui_types::crypto::{SignatureScheme, SuiKeyPair};
+
+use crate::txb;
+
+/// 默认密钥库文件
+const DEFAULT_KEYSTORE: &str = "citadel.keystore";
+/// 加密导出格式版本
+const EXPORT_VERSION: u8 = 1;
+/// 口令派生密钥使用的盐长度
+const SALT_LENGTH: usize = 16;
+
+/// 钱包子命令
+#[derive(clap::Subcommand, Debug)]
+pub enum WalletCommand {
+    /// 生成新的密钥对并保存到密钥库
+    Generate {
+        /// 地址别名
+        #[arg(long, short = 'a')]
+        alias: Option<String>,
+
+        /// 签名算法
+        #[arg(long, value_enum, default_value_t = KeyScheme::Ed25519)]
+        scheme: KeyScheme,
+    },
+
+    /// 导入私钥到密钥库
+    ///
+    /// 支持 `suiprivkey` 格式私钥、WALLET_SK格式（Base64编码）私钥和 `export` 生成的加密文件。
+    /// 未提供私钥和文件时交互输入私钥
+    Import {
+        /// 地址别名
+        #[arg(long, short = 'a')]
+        alias: Option<String>,
+
+        /// 私钥
+        #[arg(long, short = 'k', group = "source")]
+        key: Option<String>,
+
+        /// 加密导出文件路径
+        #[arg(long, short = 'f', group = "source")]
+        file: Option<PathBuf>,
+    },
+
+    /// 列出密钥库中的地址
+    List,
+
+    /// 使用口令加密导出私钥
+    Export {
+        /// 地址别名或地址
+        wallet: String,
+
+        /// 输出文件路径，未指定时输出到标准输出
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
+    },
+}
+
+/// 支持的签名算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum KeyScheme {
+    Ed25519,
+    Secp256k1,
+    Secp256r1,
+}
+
+impl From<KeyScheme> for SignatureScheme {
+    fn from(scheme: KeyScheme) -> Self {
+        match scheme {
+            KeyScheme::Ed25519 => SignatureScheme::ED25519,
+            KeyScheme::Secp256k1 => SignatureScheme::Secp256k1,
+            KeyScheme::Secp256r1 => SignatureScheme::Secp256r1,
+        }
+    }
+}
+
+/// 加密导出的私钥
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EncryptedKey {
+    pub version: u8,
+    pub address: String,
+    #[serde(default)]
+    pub alias: Option<String>,
+    /// 口令派生密钥的盐（Hex编码）
+    pub salt: String,
+    /// AES-256-GCM加密的私钥（Hex编码）
+    pub ciphertext: String,
+}
+
+/// 从口令派生加密密钥
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("口令派生密钥失败: {}", e))?;
+    Ok(key)
+}
+
+/**
+ * 使用口令加密私钥
+ *
+ * 每次导出使用随机盐派生一次性密钥，地址作为额外认证数据
+ *
+ * 参数:
+ * @param keypair - 要导出的密钥对
+ * @param alias - 地址别名
+ * @param passphrase - 加密口令
+ *
+ * 返回:
+ * 加密后的私钥
+ */
+pub fn encrypt_key(keypair: &SuiKeyPair, alias: Option<String>, passphrase: &str) -> Result<EncryptedKey> {
+    let address = SuiAddress::from(&keypair.public()).to_string();
+    let secret = keypair.encode().map_err(|e| anyhow!("编码私钥失败: {}", e))?;
+
+    let mut salt = [0u8; SALT_LENGTH];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt)?;
+
+    Ok(EncryptedKey {
+        version: EXPORT_VERSION,
+        ciphertext: Hex::encode(Aes256Gcm::encrypt(secret.as_bytes(), address.as_bytes(), &key)),
+        address,
+        alias,
+        salt: Hex::encode(salt),
+    })
+}
+
+/// 使用口令解密私钥
+pub fn decrypt_key(encrypted: &EncryptedKey, passphrase: &str) -> Result<SuiKeyPair> {
+    if encrypted.version != EXPORT_VERSION {
+        bail!("不支持的导出格式版本: {}", encrypted.version);
+    }
+    let salt = Hex::decode(&encrypted.salt).map_err(|e| anyhow!("无效的盐: {}", e))?;
+    let ciphertext = Hex::decode(&encrypted.ciphertext).map_err(|e| anyhow!("无效的密文: {}", e))?;
+    let key = derive_key(passphrase, &salt)?;
+
+    let secret = Aes256Gcm::decrypt(&ciphertext, encrypted.address.as_bytes(), &key)
+        .map_err(|_| anyhow!("口令错误或文件已损坏"))?;
+    let keypair = parse_secret_key(&String::from_utf8(secret)?)?;
+    if SuiAddress::from(&keypair.public()).to_string() != encrypted.address {
+        bail!("解密后的私钥与地址 {} 不匹配", encrypted.address);
+    }
+    Ok(keypair)
+}
+
+/// 解析 `suiprivkey` 格式或WALLET_SK格式的私钥
+pub fn parse_secret_key(value: &str) -> Result<SuiKeyPair> {
+    let value = value.trim();
+    if let Ok(keypair) = SuiKeyPair::decode(value) {
+        return Ok(keypair);
+    }
+    let (_, keypair, _) = txb::create_keystore_from_sk(value, None)?;
+    Ok(keypair)
+}
+
+/// 密钥库文件路径
+pub fn keystore_path(path: Option<PathBuf>) -> PathBuf {
+    path.or_else(|| env::var("WALLET_KEYSTORE").ok().filter(|v| !v.is_empty()).map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_KEYSTORE))
+}
+
+/// 打开密钥库文件，不存在时创建
+pub fn open_keystore(path: &PathBuf) -> Result<Keystore> {
+    let keystore = FileBasedKeystore::new(path).with_context(|| format!("打开密钥库失败: {}", path.display()))?;
+    Ok(Keystore::File(keystore))
+}
+
+/// 按别名或地址查找密钥库中的地址
+pub fn resolve_address(keystore: &Keystore, wallet: &str) -> Result<SuiAddress> {
+    if let Ok(address) = keystore.get_address_by_alias(wallet.to_string()) {
+        return Ok(*address);
+    }
+    let address = SuiAddress::from_str(wallet).map_err(|_| anyhow!("密钥库中没有别名或地址: {}", wallet))?;
+    if !keystore.addresses().contains(&address) {
+        bail!("密钥库中没有地址: {}", address);
+    }
+    Ok(address)
+}
+
+/**
+ * 加载签名者
+ *
+ * 指定钱包时从密钥库中按别名或地址选择，否则使用WALLET_SK环境变量中的私钥
+ *
+ * 参数:
+ * @param wallet - 地址别名或地址
+ *
+ * 返回:
+ * 密钥库和签名地址
+ */
+pub fn load_signer(wallet: Option<&str>) -> Result<(Keystore, SuiAddress)> {
+    match wallet {
+        Some(wallet) => {
+            let keystore = open_keystore(&keystore_path(None))?;
+            let address = resolve_address(&keystore, wallet)?;
+            Ok((keystore, address))
+        }
+        None => {
+            let sk = env::var("WALLET_SK").context("未设置WALLET_SK环境变量，也未通过--wallet指定钱包")?;
+            let (keystore, _, sender) = txb::create_keystore_from_sk(&sk, Some("EnvKeyPair".to_string()))?;
+            Ok((Keystore::InMem(keystore), sender))
+        }
+    }
+}
+
+/// 读取口令，优先使用WALLET_PASSPHRASE环境变量
+fn read_passphrase(confirm: bool) -> Result<String> {
+    if let Ok(passphrase) = env::var("WALLET_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    let passphrase = rpassword::prompt_password("请输入口令: ")?;
+    if passphrase.is_empty() {
+        bail!("口令不能为空");
+    }
+    if confirm && rpassword::prompt_password("请再次输入口令: ")? != passphrase {
+        bail!("两次输入的口令不一致");
+    }
+    Ok(passphrase)
+}
+
+/**
+ * 执行钱包子命令
+ *
+ * 参数:
+ * @param command - 钱包子命令
+ * @param keystore - 密钥库文件路径
+ *
+ * 返回:
+ * 命令输出
+ */
+pub fn run_wallet_command(command: WalletCommand, keystore: Option<PathBuf>) -> Result<String> {
+    let path = keystore_path(keystore);
+    let mut keystore = open_keystore(&path)?;
+
+    match command {
+        WalletCommand::Generate { alias, scheme } => {
+            let (address, phrase, scheme) = keystore
+                .generate_and_add_new_key(scheme.into(), alias, None, None)
+                .context("生成密钥对失败")?;
+            let alias = keystore.get_alias_by_address(&address)?;
+            Ok(format!(
+                "已生成新地址: {}\n别名: {}\n算法: {:?}\n助记词: {}\n密钥库: {}",
+                address,
+                alias,
+                scheme,
+                phrase,
+                path.display()
+            ))
+        }
+        WalletCommand::Import { alias, key, file } => {
+            let (keypair, alias) = match (key, file) {
+                (_, Some(file)) => {
+                    let content = fs::read_to_string(&file).with_context(|| format!("读取文件失败: {}", file.display()))?;
+                    let encrypted: EncryptedKey = serde_json::from_str(&content).context("解析导出文件失败")?;
+                    let keypair = decrypt_key(&encrypted, &read_passphrase(false)?)?;
+                    (keypair, alias.or(encrypted.alias))
+                }
+                (Some(key), None) => (parse_secret_key(&key)?, alias),
+                (None, None) => (parse_secret_key(&rpassword::prompt_password("请输入私钥: ")?)?, alias),
+            };
+            let address = SuiAddress::from(&keypair.public());
+            if keystore.addresses().contains(&address) {
+                bail!("地址 {} 已在密钥库中", address);
+            }
+            keystore.add_key(alias, keypair).context("保存密钥失败")?;
+            Ok(format!(
+                "已导入地址: {}\n别名: {}",
+                address,
+                keystore.get_alias_by_address(&address)?
+            ))
+        }
+        WalletCommand::List => {
+            let mut lines = vec![format!("密钥库: {}", path.display())];
+            for (address, alias) in keystore.addresses_with_alias() {
+                lines.push(format!("{}  {}", address, alias.alias));
+            }
+            if lines.len() == 1 {
+                lines.push("（空）".to_string());
+            }
+            Ok(lines.join("\n"))
+        }
+        WalletCommand::Export { wallet, output } => {
+            let address = resolve_address(&keystore, &wallet)?;
+            let alias = keystore.get_alias_by_address(&address).ok();
+            let keypair = keystore.get_key(&address)?;
+            let encrypted = encrypt_key(keypair, alias, &read_passphrase(true)?)?;
+            let content = serde_json::to_string_pretty(&encrypted)?;
+            match output {
+                Some(output) => {
+                    fs::write(&output, content).with_context(|| format!("写入文件失败: {}", output.display()))?;
+                    Ok(format!("已导出地址 {} 到 {}", address, output.display()))
+                }
+                None => Ok(content),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastcrypto::encoding::Base64;
+    use sui_types::crypto::get_key_pair_from_rng;
+
+    #[test]
+    fn test_encrypt_roundtrip() {
+        let (_, keypair): (_, fastcrypto::ed25519::Ed25519KeyPair) = get_key_pair_from_rng(&mut rand::thread_rng());
+        let keypair = SuiKeyPair::Ed25519(keypair);
+        let encrypted = encrypt_key(&keypair, Some("main".to_string()), "secret").unwrap();
+        assert_eq!(encrypted.address, SuiAddress::from(&keypair.public()).to_string());
+
+        let decrypted = decrypt_key(&encrypted, "secret").unwrap();
+        assert_eq!(decrypted.public(), keypair.public());
+        assert!(decrypt_key(&encrypted, "wrong").is_err());
+
+        // 篡改地址会导致认证失败
+        let mut tampered = encrypted.clone();
+        tampered.address = SuiAddress::ZERO.to_string();
+        assert!(decrypt_key(&tampered, "secret").is_err());
+    }
+
+    #[test]
+    fn test_parse_secret_key_formats() {
+        let (_, keypair): (_, fastcrypto::ed25519::Ed25519KeyPair) = get_key_pair_from_rng(&mut rand::thread_rng());
+        let keypair = SuiKeyPair::Ed25519(keypair);
+        let bech32 = keypair.encode().unwrap();
+        assert_eq!(parse_secret_key(&bech32).unwrap().public(), keypair.public());
+        assert_eq!(
+            parse_secret_key(&Base64::encode(bech32.as_bytes())).unwrap().public(),
+            keypair.public()
+        );
+        assert!(parse_secret_key("not a key").is_err());
+    }
+}
@@ -0,0 +1,436 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 实例间内部RPC模块
+//!
+//! # 概述
+//! 多实例部署时，对局和WebSocket连接分散在不同的citadel实例上。本模块提供实例之间互相调用的内部接口，
+//! 作为后续基于发布/订阅横向扩展的基础：
+//! - `GET /internal/v1/instance`：本实例ID和已配置的对等实例
+//! - `POST /internal/v1/match-owner`：查询对局是否由本实例持有
+//! - `POST /internal/v1/forward`：将事件转发给连接在本实例上的客户端
+//!
+//! `ClusterService` 封装对等实例的调用，例如依次询问所有对等实例以找到对局的持有者。
+//!
+//! # 认证
+//! 所有实例共享同一个密钥。调用方在请求头中携带实例ID、毫秒时间戳和签名，
+//! 签名为 `HMAC-SHA3-256(密钥, "{instance}\n{timestamp}\n{method}\n{path}\n{body}")` 的Hex编码，
+//! 时间戳与接收方时间相差超过30秒的请求被拒绝。未配置密钥时内部接口不可用。
+//! 需要双向TLS时由前置的反向代理或服务网格终止TLS，再转发到内部接口。
+//!
+//! # 配置
+//! - `CITADEL_INSTANCE_ID`：本实例ID，默认启动时随机生成
+//! - `CITADEL_CLUSTER_SECRET`：实例间共享密钥，为空时内部接口不可用
+//! - `CITADEL_PEERS`：对等实例列表，格式为 `id=url`，逗号分隔，如 `a=http://10.0.0.1:3000,b=http://10.0.0.2:3000`
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use axum::body::Bytes;
+use axum::http::{HeaderMap, Method, Uri};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::hmac::{hmac_sha3_256, HmacKey};
+use fastcrypto::traits::ToFromBytes;
+use once_cell::sync::OnceCell;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tap::TapFallible;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::common::constant_time_eq;
+use crate::errors::InternalError;
+use crate::externals::current_epoch_time;
+use crate::ws::ConnectionManager;
+
+/// 调用方实例ID请求头
+pub const INSTANCE_HEADER: &str = "x-citadel-instance";
+/// 时间戳请求头
+pub const TIMESTAMP_HEADER: &str = "x-citadel-timestamp";
+/// 签名请求头
+pub const SIGNATURE_HEADER: &str = "x-citadel-signature";
+/// 签名时间戳允许的偏差（毫秒）
+pub const SIGNATURE_WINDOW_MS: u64 = 30_000;
+/// 调用对等实例的超时时间
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 内部接口路径
+pub mod paths {
+    pub const INSTANCE: &str = "/internal/v1/instance";
+    pub const MATCH_OWNER: &str = "/internal/v1/match-owner";
+    pub const FORWARD: &str = "/internal/v1/forward";
+}
+
+/// 集群配置
+#[derive(Debug, Clone)]
+pub struct ClusterConfig {
+    /// 本实例ID
+    pub instance_id: String,
+    /// 共享密钥
+    secret: Option<Vec<u8>>,
+    /// 对等实例ID -> 基础URL
+    pub peers: BTreeMap<String, String>,
+}
+
+impl ClusterConfig {
+    /// 从环境变量读取配置
+    pub fn from_env() -> Self {
+        let instance_id = std::env::var("CITADEL_INSTANCE_ID")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let secret = std::env::var("CITADEL_CLUSTER_SECRET")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(String::into_bytes);
+        let peers = std::env::var("CITADEL_PEERS")
+            .map(|list| parse_peers(&list, &instance_id))
+            .unwrap_or_default();
+        Self { instance_id, secret, peers }
+    }
+
+    /// 内部接口是否可用
+    pub fn is_enabled(&self) -> bool {
+        self.secret.is_some()
+    }
+}
+
+/// 解析对等实例列表，忽略格式错误的项和本实例
+pub fn parse_peers(list: &str, self_id: &str) -> BTreeMap<String, String> {
+    list.split(',')
+        .filter_map(|item| {
+            let (id, url) = item.trim().split_once('=')?;
+            let (id, url) = (id.trim(), url.trim().trim_end_matches('/'));
+            if id.is_empty() || url.is_empty() {
+                warn!("忽略格式错误的对等实例配置: {}", item);
+                return None;
+            }
+            Some((id.to_string(), url.to_string()))
+        })
+        .filter(|(id, _)| id != self_id)
+        .collect()
+}
+
+/**
+ * 计算请求签名
+ *
+ * 参数:
+ * @param secret - 共享密钥
+ * @param instance - 调用方实例ID
+ * @param timestamp - 毫秒时间戳
+ * @param method - HTTP方法
+ * @param path - 请求路径
+ * @param body - 请求体
+ *
+ * 返回:
+ * Hex编码的签名
+ */
+pub fn sign(secret: &[u8], instance: &str, timestamp: u64, method: &str, path: &str, body: &[u8]) -> String {
+    let key = HmacKey::from_bytes(secret).expect("hmac key accepts any length");
+    let mut message = format!("{}\n{}\n{}\n{}\n", instance, timestamp, method, path).into_bytes();
+    message.extend_from_slice(body);
+    Hex::encode(hmac_sha3_256(&key, &message).digest)
+}
+
+/**
+ * 校验内部请求
+ *
+ * 参数:
+ * @param config - 集群配置
+ * @param headers - 请求头
+ * @param method - HTTP方法
+ * @param path - 请求路径
+ * @param body - 请求体
+ * @param now - 当前时间（毫秒）
+ *
+ * 返回:
+ * 校验通过时返回调用方实例ID
+ */
+pub fn verify_request(
+    config: &ClusterConfig,
+    headers: &HeaderMap,
+    method: &str,
+    path: &str,
+    body: &[u8],
+    now: u64,
+) -> Result<String, InternalError> {
+    let secret = config.secret.as_deref().ok_or(InternalError::Unauthorized)?;
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let (Some(instance), Some(timestamp), Some(signature)) =
+        (header(INSTANCE_HEADER), header(TIMESTAMP_HEADER), header(SIGNATURE_HEADER))
+    else {
+        return Err(InternalError::MissingAuthToken);
+    };
+    let timestamp: u64 = timestamp.parse().map_err(|_| InternalError::InvalidAuthHeader)?;
+    if timestamp.abs_diff(now) > SIGNATURE_WINDOW_MS {
+        return Err(InternalError::ExpiredToken);
+    }
+    let expected = sign(secret, instance, timestamp, method, path, body);
+    if !constant_time_eq(expected.as_bytes(), signature.to_ascii_lowercase().as_bytes()) {
+        return Err(InternalError::InvalidToken);
+    }
+    Ok(instance.to_string())
+}
+
+/// 实例信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceInfo {
+    pub instance_id: String,
+    pub peers: Vec<String>,
+}
+
+/// 对局持有者查询请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchOwnerRequest {
+    pub match_id: String,
+}
+
+/// 对局持有者查询响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchOwnerResponse {
+    pub instance_id: String,
+    /// 对局是否由响应的实例持有
+    pub owned: bool,
+}
+
+/// 事件转发请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForwardRequest {
+    pub client_id: String,
+    pub event: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+/// 事件转发响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForwardResponse {
+    /// 客户端连接在本实例上且已发送
+    pub delivered: bool,
+}
+
+/// 集群服务
+pub struct ClusterService {
+    config: ClusterConfig,
+    connection_manager: Arc<ConnectionManager>,
+    http: Client,
+}
+
+impl ClusterService {
+    pub fn new(config: ClusterConfig, connection_manager: Arc<ConnectionManager>) -> Self {
+        let http = Client::builder().timeout(PEER_TIMEOUT).build().unwrap_or_default();
+        Self { config, connection_manager, http }
+    }
+
+    /// 本实例ID
+    pub fn instance_id(&self) -> &str {
+        &self.config.instance_id
+    }
+
+    /// 本实例是否持有对局
+    pub async fn owns_match(&self, match_id: &str) -> bool {
+        match crate::gaming::global_match_service() {
+            Some(match_service) => match_service.get_match(match_id).await.is_some(),
+            None => false,
+        }
+    }
+
+    /// 校验请求并解析请求体
+    fn authorize<T: for<'de> Deserialize<'de>>(&self, headers: &HeaderMap, method: &Method, uri: &Uri, body: &[u8]) -> Result<(String, T), InternalError> {
+        let caller = verify_request(&self.config, headers, method.as_str(), uri.path(), body, current_epoch_time())
+            .tap_err(|e| warn!("拒绝内部请求 {} {}: {:?}", method, uri.path(), e))?;
+        let request = if body.is_empty() {
+            serde_json::from_value(serde_json::Value::Null)?
+        } else {
+            serde_json::from_slice(body)?
+        };
+        Ok((caller, request))
+    }
+
+    /// 向对等实例发送已签名的请求
+    async fn call_peer<Req: Serialize, Resp: for<'de> Deserialize<'de>>(&self, peer_id: &str, path: &str, request: &Req) -> Result<Resp> {
+        let secret = self.config.secret.as_deref().ok_or_else(|| anyhow!("未配置实例间共享密钥"))?;
+        let base_url = self.config.peers.get(peer_id).ok_or_else(|| anyhow!("未知的对等实例: {}", peer_id))?;
+        let body = serde_json::to_vec(request)?;
+        let timestamp = current_epoch_time();
+        let signature = sign(secret, &self.config.instance_id, timestamp, "POST", path, &body);
+        let response = self
+            .http
+            .post(format!("{}{}", base_url, path))
+            .header(INSTANCE_HEADER, &self.config.instance_id)
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, signature)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json().await?)
+    }
+
+    /**
+     * 查找对局的持有实例
+     *
+     * 参数:
+     * @param match_id - 对局ID
+     *
+     * 返回:
+     * 持有对局的实例ID，本实例持有时返回本实例ID，所有实例都不持有时返回None
+     */
+    pub async fn find_match_owner(&self, match_id: &str) -> Option<String> {
+        if self.owns_match(match_id).await {
+            return Some(self.config.instance_id.clone());
+        }
+        let request = MatchOwnerRequest { match_id: match_id.to_string() };
+        for peer_id in self.config.peers.keys() {
+            match self.call_peer::<_, MatchOwnerResponse>(peer_id, paths::MATCH_OWNER, &request).await {
+                Ok(response) if response.owned => return Some(response.instance_id),
+                Ok(_) => {}
+                Err(e) => warn!("查询对等实例 {} 的对局持有状态失败: {}", peer_id, e),
+            }
+        }
+        None
+    }
+
+    /**
+     * 将事件转发给连接在其他实例上的客户端
+     *
+     * 参数:
+     * @param peer_id - 目标实例ID
+     * @param client_id - 客户端ID
+     * @param event - 事件名称
+     * @param data - 事件数据
+     *
+     * 返回:
+     * 目标实例是否已发送给客户端
+     */
+    pub async fn forward_event(&self, peer_id: &str, client_id: &str, event: &str, data: Option<serde_json::Value>) -> Result<bool> {
+        let request = ForwardRequest {
+            client_id: client_id.to_string(),
+            event: event.to_string(),
+            data,
+        };
+        let response: ForwardResponse = self.call_peer(peer_id, paths::FORWARD, &request).await?;
+        Ok(response.delivered)
+    }
+}
+
+static GLOBAL_CLUSTER_SERVICE: OnceCell<Arc<ClusterService>> = OnceCell::new();
+
+/// 获取全局集群服务
+pub fn global_cluster_service() -> Option<Arc<ClusterService>> {
+    GLOBAL_CLUSTER_SERVICE.get().cloned()
+}
+
+/// 查询本实例信息
+async fn handle_instance(headers: HeaderMap, method: Method, uri: Uri) -> Result<Json<InstanceInfo>, InternalError> {
+    let service = global_cluster_service().ok_or(InternalError::Failure)?;
+    let (_, ()) = service.authorize(&headers, &method, &uri, &[])?;
+    Ok(Json(InstanceInfo {
+        instance_id: service.config.instance_id.clone(),
+        peers: service.config.peers.keys().cloned().collect(),
+    }))
+}
+
+/// 查询对局是否由本实例持有
+async fn handle_match_owner(headers: HeaderMap, method: Method, uri: Uri, body: Bytes) -> Result<Json<MatchOwnerResponse>, InternalError> {
+    let service = global_cluster_service().ok_or(InternalError::Failure)?;
+    let (caller, request): (_, MatchOwnerRequest) = service.authorize(&headers, &method, &uri, &body)?;
+    debug!("实例 {} 查询对局 {} 的持有者", caller, request.match_id);
+    Ok(Json(MatchOwnerResponse {
+        instance_id: service.config.instance_id.clone(),
+        owned: service.owns_match(&request.match_id).await,
+    }))
+}
+
+/// 将事件转发给本实例上的客户端
+async fn handle_forward(headers: HeaderMap, method: Method, uri: Uri, body: Bytes) -> Result<Json<ForwardResponse>, InternalError> {
+    let service = global_cluster_service().ok_or(InternalError::Failure)?;
+    let (caller, request): (_, ForwardRequest) = service.authorize(&headers, &method, &uri, &body)?;
+    debug!("实例 {} 转发事件 {} 到客户端 {}", caller, request.event, request.client_id);
+    let delivered = service
+        .connection_manager
+        .send_to_client(&request.client_id, &request.event, request.data)
+        .await
+        .map_err(|_| InternalError::Failure)?;
+    Ok(Json(ForwardResponse { delivered }))
+}
+
+/// 注册实例间内部路由
+pub fn register_cluster_routes(app: Router, connection_manager: Arc<ConnectionManager>) -> Router {
+    let config = ClusterConfig::from_env();
+    if config.is_enabled() {
+        info!("实例间内部接口已启用: instance={}, peers={:?}", config.instance_id, config.peers.keys());
+    }
+    let _ = GLOBAL_CLUSTER_SERVICE.set(Arc::new(ClusterService::new(config, connection_manager)));
+    app.route(paths::INSTANCE, get(handle_instance))
+        .route(paths::MATCH_OWNER, post(handle_match_owner))
+        .route(paths::FORWARD, post(handle_forward))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cluster_config(secret: Option<&str>) -> ClusterConfig {
+        ClusterConfig {
+            instance_id: "a".to_string(),
+            secret: secret.map(|s| s.as_bytes().to_vec()),
+            peers: BTreeMap::new(),
+        }
+    }
+
+    fn headers(instance: &str, timestamp: u64, signature: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(INSTANCE_HEADER, instance.parse().unwrap());
+        headers.insert(TIMESTAMP_HEADER, timestamp.to_string().parse().unwrap());
+        headers.insert(SIGNATURE_HEADER, signature.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_verify_request() {
+        let config = cluster_config(Some("secret"));
+        let now = 1_700_000_000_000;
+        let body = br#"{"matchId":"m1"}"#;
+        let signature = sign(b"secret", "b", now, "POST", paths::MATCH_OWNER, body);
+
+        let ok = verify_request(&config, &headers("b", now, &signature), "POST", paths::MATCH_OWNER, body, now + 1000);
+        assert_eq!(ok, Ok("b".to_string()));
+
+        // 篡改请求体、路径或调用方都会导致签名不匹配
+        let tampered = verify_request(&config, &headers("b", now, &signature), "POST", paths::MATCH_OWNER, b"{}", now);
+        assert_eq!(tampered, Err(InternalError::InvalidToken));
+        let wrong_path = verify_request(&config, &headers("b", now, &signature), "POST", paths::FORWARD, body, now);
+        assert_eq!(wrong_path, Err(InternalError::InvalidToken));
+        let wrong_caller = verify_request(&config, &headers("c", now, &signature), "POST", paths::MATCH_OWNER, body, now);
+        assert_eq!(wrong_caller, Err(InternalError::InvalidToken));
+
+        let stale = verify_request(&config, &headers("b", now, &signature), "POST", paths::MATCH_OWNER, body, now + SIGNATURE_WINDOW_MS + 1);
+        assert_eq!(stale, Err(InternalError::ExpiredToken));
+        assert_eq!(
+            verify_request(&config, &HeaderMap::new(), "POST", paths::MATCH_OWNER, body, now),
+            Err(InternalError::MissingAuthToken)
+        );
+
+        // 未配置密钥时内部接口不可用
+        let disabled = verify_request(&cluster_config(None), &headers("b", now, &signature), "POST", paths::MATCH_OWNER, body, now);
+        assert_eq!(disabled, Err(InternalError::Unauthorized));
+    }
+
+    #[test]
+    fn test_parse_peers() {
+        let peers = parse_peers(" a=http://a:3000 , b=http://b:3000/, bad, c=", "a");
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers.get("b").map(String::as_str), Some("http://b:3000"));
+    }
+}
@@ -38,6 +38,10 @@ pub struct IntentMessage<T: Serialize> {
 #[repr(u8)]
 pub enum IntentScope {
     Tweet = 0,
+    MatchResult = 1,
+    SessionFrame = 2,
+    KeyResponse = 3,
+    MatchAttestation = 4,
 }
 
 impl<T: Serialize + Debug> IntentMessage<T> {
@@ -84,6 +88,12 @@ pub fn to_signed_response<T: Serialize + Clone>(
     }
 }
 
+/// Compare two byte strings in constant time, so the response time does not
+/// reveal how much of a signature or secret matched.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 /// ==== HEALTHCHECK, GET ATTESTASTION ENDPOINT IMPL ====
 
 /// Response for get attestation.
@@ -0,0 +1,293 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 关键事件送达确认模块
+//!
+//! # 概述
+//! 服务器发给客户端的消息通过有界通道转发，通道已满或客户端不在线时消息会被丢弃。
+//! 对局开始、好友请求等关键事件丢失后客户端无法自行恢复，本模块为这些事件提供可选的确认协议：
+//! - 客户端在 `hello` 中声明 `ack` 能力后，服务器为发给它的关键事件分配递增的消息ID（`msgId`）
+//! - 客户端处理后发送 `ack` 事件确认，可以一次确认多条消息
+//! - 超时未确认的消息以相同的 `msgId` 重发，客户端应按 `msgId` 去重
+//! - 超过最大重试次数仍未确认的消息被放弃并计入统计
+//!
+//! 断线期间待确认的消息继续保留，客户端使用原客户端ID重连后会收到重发。
+//! 未声明 `ack` 能力的客户端不受影响。
+//!
+//! # 配置
+//! - `WS_ACK_TIMEOUT_MS`：等待确认的超时时间（毫秒），默认5000
+//! - `WS_ACK_MAX_RETRIES`：最大重发次数，默认3
+//! - `WS_ACK_MAX_PENDING`：每个客户端待确认消息的上限，默认256，超出时放弃最早的消息
+//!
+//! # 消息格式
+//! ```json
+//! // 服务器 -> 客户端
+//! { "event": "match:start", "msgId": 42, "data": { ... } }
+//! // 客户端 -> 服务器
+//! { "event": "ack", "data": { "msgIds": [42, 43] } }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::ws::{ClientId, WsMessage};
+
+/// 事件名称
+pub mod events {
+    /// 客户端确认收到消息
+    pub const ACK: &str = "ack";
+}
+
+/// 需要确认的关键事件
+pub const CRITICAL_EVENTS: &[&str] = &[
+    "match:start",
+    "match:end",
+    "match:victory",
+    "match:defeat",
+    "user:friend-request-received",
+    "user:friend-request-accepted",
+    "user:match-invite",
+    "party:invited",
+];
+
+/// 是否为需要确认的关键事件
+pub fn is_critical(event: &str) -> bool {
+    CRITICAL_EVENTS.contains(&event)
+}
+
+/// 确认请求
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AckRequest {
+    /// 已处理的消息ID
+    pub msg_ids: Vec<u64>,
+}
+
+/// 确认协议配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AckConfig {
+    pub timeout_ms: u64,
+    pub max_retries: u32,
+    pub max_pending: usize,
+}
+
+impl Default for AckConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 5000,
+            max_retries: 3,
+            max_pending: 256,
+        }
+    }
+}
+
+impl AckConfig {
+    /// 从环境变量读取，未配置或无效的项使用默认值
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            timeout_ms: std::env::var("WS_ACK_TIMEOUT_MS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(defaults.timeout_ms),
+            max_retries: std::env::var("WS_ACK_MAX_RETRIES")
+                .ok()
+                .and_then(|v| v.parse::<u32>().ok())
+                .unwrap_or(defaults.max_retries),
+            max_pending: std::env::var("WS_ACK_MAX_PENDING")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(defaults.max_pending),
+        }
+    }
+}
+
+/// 待确认的消息
+#[derive(Debug, Clone)]
+struct PendingMessage {
+    /// 已带有msgId、按客户端语言渲染的消息
+    message: WsMessage,
+    /// 已重发次数
+    retries: u32,
+    /// 下次重发时间（毫秒）
+    next_retry_at: u64,
+}
+
+/// 一轮重发检查的结果
+#[derive(Debug, Default)]
+pub struct RedeliveryBatch {
+    /// 需要重发的消息
+    pub resend: Vec<(ClientId, WsMessage)>,
+    /// 重试用完被放弃的消息数
+    pub failed: usize,
+}
+
+/// 待确认消息跟踪
+#[derive(Debug)]
+pub struct DeliveryTracker {
+    config: AckConfig,
+    next_id: AtomicU64,
+    /// 客户端ID -> (msgId -> 待确认消息)
+    pending: Mutex<HashMap<ClientId, BTreeMap<u64, PendingMessage>>>,
+}
+
+impl DeliveryTracker {
+    pub fn new(config: AckConfig) -> Self {
+        Self {
+            config,
+            next_id: AtomicU64::new(1),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 检查重发的间隔
+    pub fn tick_interval_ms(&self) -> u64 {
+        (self.config.timeout_ms / 2).max(100)
+    }
+
+    /**
+     * 为消息分配msgId并记录为待确认
+     *
+     * 参数:
+     * @param client_id - 接收消息的客户端ID
+     * @param message - 已按客户端语言渲染的消息
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 带有msgId的消息和因超出上限被放弃的消息数
+     */
+    pub fn track(&self, client_id: &str, mut message: WsMessage, now: u64) -> (WsMessage, usize) {
+        let msg_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        message.msg_id = Some(msg_id);
+
+        let mut pending = self.pending.lock();
+        let queue = pending.entry(client_id.to_string()).or_default();
+        let mut evicted = 0;
+        while queue.len() >= self.config.max_pending {
+            if let Some((oldest, dropped)) = queue.pop_first() {
+                warn!("客户端 {} 待确认消息过多，放弃消息 {} ({})", client_id, oldest, dropped.message.event);
+                evicted += 1;
+            }
+        }
+        queue.insert(
+            msg_id,
+            PendingMessage {
+                message: message.clone(),
+                retries: 0,
+                next_retry_at: now + self.config.timeout_ms,
+            },
+        );
+        (message, evicted)
+    }
+
+    /// 确认消息，返回实际移除的数量
+    pub fn ack(&self, client_id: &str, msg_ids: &[u64]) -> usize {
+        let mut pending = self.pending.lock();
+        let Some(queue) = pending.get_mut(client_id) else {
+            return 0;
+        };
+        let acked = msg_ids.iter().filter(|id| queue.remove(id).is_some()).count();
+        if queue.is_empty() {
+            pending.remove(client_id);
+        }
+        acked
+    }
+
+    /**
+     * 取出到期需要重发的消息
+     *
+     * 参数:
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 需要重发的消息，以及重试用完被放弃的消息数
+     */
+    pub fn due(&self, now: u64) -> RedeliveryBatch {
+        let mut batch = RedeliveryBatch::default();
+        let mut pending = self.pending.lock();
+        pending.retain(|client_id, queue| {
+            queue.retain(|msg_id, entry| {
+                if entry.next_retry_at > now {
+                    return true;
+                }
+                if entry.retries >= self.config.max_retries {
+                    warn!("客户端 {} 未确认消息 {} ({})，放弃重发", client_id, msg_id, entry.message.event);
+                    batch.failed += 1;
+                    return false;
+                }
+                entry.retries += 1;
+                entry.next_retry_at = now + self.config.timeout_ms;
+                batch.resend.push((client_id.clone(), entry.message.clone()));
+                true
+            });
+            !queue.is_empty()
+        });
+        batch
+    }
+
+    /// 待确认的消息总数
+    pub fn pending_count(&self) -> usize {
+        self.pending.lock().values().map(|queue| queue.len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(event: &str) -> WsMessage {
+        WsMessage {
+            event: event.to_string(),
+            data: None,
+            msg_id: None,
+        }
+    }
+
+    #[test]
+    fn test_track_and_ack() {
+        let tracker = DeliveryTracker::new(AckConfig::default());
+        let (first, _) = tracker.track("c1", message("match:start"), 0);
+        let (second, _) = tracker.track("c1", message("match:end"), 0);
+        assert_eq!((first.msg_id, second.msg_id), (Some(1), Some(2)));
+        assert_eq!(tracker.pending_count(), 2);
+
+        // 其他客户端不能确认别人的消息
+        assert_eq!(tracker.ack("c2", &[1]), 0);
+        assert_eq!(tracker.ack("c1", &[1, 1, 99]), 1);
+        assert_eq!(tracker.pending_count(), 1);
+
+        assert!(is_critical("match:start"));
+        assert!(!is_critical("chat:message"));
+    }
+
+    #[test]
+    fn test_redelivery_is_bounded() {
+        let config = AckConfig { timeout_ms: 1000, max_retries: 2, max_pending: 2 };
+        let tracker = DeliveryTracker::new(config);
+        tracker.track("c1", message("match:start"), 0);
+
+        assert!(tracker.due(999).resend.is_empty());
+        let batch = tracker.due(1000);
+        assert_eq!(batch.resend.len(), 1);
+        assert_eq!(batch.resend[0].1.msg_id, Some(1));
+        assert_eq!(tracker.due(2000).resend.len(), 1);
+        let batch = tracker.due(3000);
+        assert!(batch.resend.is_empty());
+        assert_eq!(batch.failed, 1);
+        assert_eq!(tracker.pending_count(), 0);
+
+        // 超出上限时放弃最早的消息
+        tracker.track("c1", message("a"), 0);
+        tracker.track("c1", message("b"), 0);
+        let (_, evicted) = tracker.track("c1", message("c"), 0);
+        assert_eq!(evicted, 1);
+        assert_eq!(tracker.pending_count(), 2);
+    }
+}
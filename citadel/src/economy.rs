@@ -0,0 +1,538 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 游戏经济模块
+//!
+//! # 概述
+//! 本模块管理玩家的游戏币（软货币）余额。钱包写入持久化存储（见 `storage`），缓存只用于加速读取，
+//! 通过Citadel合约定期结算到链上Profile。对局可以设置入场押注，
+//! 所有押注进入奖池，游戏结束后由胜利者获得。
+//!
+//! 只有首次使用经济系统（查询余额或押注）且存储中没有记录的用户会获得初始余额。
+//! 退还押注、发放奖池和记录结算针对已有的钱包，钱包不存在时返回错误；
+//! 读取存储失败时同样返回错误，不会为已有用户重新发放初始余额。
+//!
+//! # 核心功能
+//! - 余额管理：入账、扣款，扣款前检查余额，余额永远不会为负
+//! - 押注托管：玩家加入押注对局时扣除押注，等待阶段离开时退还
+//! - 奖池发放：游戏结束时将奖池发放给胜利者
+//! - 交易记录：每个钱包保留最近的交易流水，可通过REST接口查询
+//! - 链上结算：将当前余额写入链上Profile
+//!
+//! # 使用示例
+//! ```ignore
+//! let economy = economy::init_economy_service(game_service);
+//! economy.escrow_wager(&user_id, &match_id, 100).await?;
+//! economy.payout_pot(&winner_id, &match_id, 200).await?;
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use axum::{
+    extract::{Path, State},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use sui_types::base_types::ObjectID;
+use tower_sessions::Session;
+use tracing::{error, info};
+
+use crate::account_link;
+use crate::errors::InternalError;
+use crate::game::{GameCachePrefix, GameService};
+use crate::sdk::executor;
+use crate::session_login::{SessionUser, SESSION_USER_KEY};
+use crate::storage;
+use crate::AppState;
+
+/// 新钱包的初始余额
+pub const STARTING_BALANCE: u64 = 1000;
+/// 每个钱包保留的最大交易记录数
+pub const MAX_TRANSACTION_HISTORY: usize = 100;
+/// 钱包在持久化存储中的设置键前缀
+const STORAGE_KEY_PREFIX: &str = "wallet:";
+
+/// 交易类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionKind {
+    /// 初始发放
+    Grant,
+    /// 押注托管
+    WagerEscrow,
+    /// 押注退还
+    WagerRefund,
+    /// 奖池发放
+    PotPayout,
+}
+
+/// 交易记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrencyTransaction {
+    pub id: String,
+    pub kind: TransactionKind,
+    /// 余额变化（正数为入账，负数为扣款）
+    pub amount: i64,
+    /// 交易后余额
+    pub balance_after: u64,
+    /// 关联的对局ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub match_id: Option<String>,
+    pub created_at: u64,
+}
+
+/// 用户钱包
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Wallet {
+    pub balance: u64,
+    /// 最近的交易记录（按时间顺序）
+    #[serde(default)]
+    pub history: VecDeque<CurrencyTransaction>,
+    /// 最近一次链上结算的余额
+    #[serde(default)]
+    pub settled_balance: Option<u64>,
+    /// 最近一次链上结算的时间
+    #[serde(default)]
+    pub settled_at: Option<u64>,
+}
+
+impl Wallet {
+    /// 创建带有初始余额的钱包
+    pub fn new(now: u64) -> Self {
+        let mut wallet = Self {
+            balance: STARTING_BALANCE,
+            history: VecDeque::new(),
+            settled_balance: None,
+            settled_at: None,
+        };
+        wallet.push_history(TransactionKind::Grant, STARTING_BALANCE as i64, None, now);
+        wallet
+    }
+
+    /// 入账
+    pub fn credit(
+        &mut self,
+        amount: u64,
+        kind: TransactionKind,
+        match_id: Option<&str>,
+        now: u64,
+    ) -> Result<()> {
+        self.balance = self
+            .balance
+            .checked_add(amount)
+            .ok_or_else(|| anyhow::anyhow!("余额溢出"))?;
+        self.push_history(kind, amount as i64, match_id, now);
+        Ok(())
+    }
+
+    /// 扣款，余额不足时返回错误且不修改余额
+    pub fn debit(
+        &mut self,
+        amount: u64,
+        kind: TransactionKind,
+        match_id: Option<&str>,
+        now: u64,
+    ) -> Result<()> {
+        self.balance = self
+            .balance
+            .checked_sub(amount)
+            .ok_or_else(|| anyhow::anyhow!("余额不足: 当前余额 {}, 需要 {}", self.balance, amount))?;
+        self.push_history(kind, -(amount as i64), match_id, now);
+        Ok(())
+    }
+
+    fn push_history(&mut self, kind: TransactionKind, amount: i64, match_id: Option<&str>, now: u64) {
+        self.history.push_back(CurrencyTransaction {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            amount,
+            balance_after: self.balance,
+            match_id: match_id.map(|s| s.to_string()),
+            created_at: now,
+        });
+        while self.history.len() > MAX_TRANSACTION_HISTORY {
+            self.history.pop_front();
+        }
+    }
+}
+
+/// 钱包不存在时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Missing {
+    /// 从未创建过钱包，创建并发放初始余额
+    Create,
+    /// 操作针对已有的钱包，返回错误
+    Fail,
+}
+
+/// 经济服务
+pub struct EconomyService {
+    /// 游戏服务，处理缓存
+    game_service: Arc<GameService>,
+    /// 钱包写锁，保证读-改-写的原子性
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl EconomyService {
+    /// 创建新的经济服务
+    pub fn new(game_service: Arc<GameService>) -> Self {
+        Self {
+            game_service,
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn now() -> u64 {
+        chrono::Utc::now().timestamp_millis() as u64
+    }
+
+    /// 获取用户钱包，从未创建过时创建并发放初始余额
+    pub async fn get_wallet(&self, user_id: &str) -> Result<Wallet> {
+        let _guard = self.lock.lock().await;
+        self.load_wallet(user_id, Missing::Create).await
+    }
+
+    /**
+     * 读取钱包，先查缓存再查持久化存储
+     *
+     * 参数:
+     * @param user_id - 用户ID
+     * @param missing - 缓存和存储中都没有钱包时的处理方式
+     *
+     * 返回:
+     * 钱包，读取存储失败或不允许创建时返回错误
+     */
+    async fn load_wallet(&self, user_id: &str, missing: Missing) -> Result<Wallet> {
+        if let Some(wallet) = self.game_service.get(GameCachePrefix::WALLET, user_id) {
+            return Ok(wallet);
+        }
+        if let Some(storage) = storage::global_storage() {
+            let stored = storage
+                .get_setting(&storage_key(user_id))
+                .await
+                .map_err(|e| anyhow!("读取用户 {} 的钱包失败: {}", user_id, e))?;
+            if let Some(json) = stored {
+                let wallet: Wallet = serde_json::from_str(&json)
+                    .map_err(|e| anyhow!("解析用户 {} 的钱包失败: {}", user_id, e))?;
+                self.game_service.set(GameCachePrefix::WALLET, user_id, &wallet);
+                return Ok(wallet);
+            }
+        }
+        match missing {
+            Missing::Create => {
+                let wallet = Wallet::new(Self::now());
+                self.save_wallet(user_id, &wallet).await?;
+                info!("为用户 {} 创建钱包，初始余额 {}", user_id, STARTING_BALANCE);
+                Ok(wallet)
+            }
+            Missing::Fail => Err(anyhow!("用户 {} 的钱包不存在", user_id)),
+        }
+    }
+
+    /// 先写入持久化存储再更新缓存，存储写入失败时缓存保持不变
+    async fn save_wallet(&self, user_id: &str, wallet: &Wallet) -> Result<()> {
+        if let Some(storage) = storage::global_storage() {
+            storage
+                .put_setting(&storage_key(user_id), &serde_json::to_string(wallet)?)
+                .await
+                .map_err(|e| anyhow!("保存用户 {} 的钱包失败: {}", user_id, e))?;
+        }
+        if !self.game_service.set(GameCachePrefix::WALLET, user_id, wallet) {
+            return Err(anyhow!("保存钱包数据失败"));
+        }
+        Ok(())
+    }
+
+    /// 在锁内修改钱包，修改失败时不保存
+    async fn modify_wallet<F>(&self, user_id: &str, missing: Missing, f: F) -> Result<Wallet>
+    where
+        F: FnOnce(&mut Wallet) -> Result<()>,
+    {
+        let _guard = self.lock.lock().await;
+        let mut wallet = self.load_wallet(user_id, missing).await?;
+        f(&mut wallet)?;
+        self.save_wallet(user_id, &wallet).await?;
+        Ok(wallet)
+    }
+
+    /// 托管押注
+    pub async fn escrow_wager(&self, user_id: &str, match_id: &str, amount: u64) -> Result<Wallet> {
+        let wallet = self
+            .modify_wallet(user_id, Missing::Create, |w| {
+                w.debit(amount, TransactionKind::WagerEscrow, Some(match_id), Self::now())
+            })
+            .await?;
+        info!("用户 {} 为对局 {} 押注 {}", user_id, match_id, amount);
+        Ok(wallet)
+    }
+
+    /// 退还押注，押注时已创建钱包
+    pub async fn refund_wager(&self, user_id: &str, match_id: &str, amount: u64) -> Result<Wallet> {
+        let wallet = self
+            .modify_wallet(user_id, Missing::Fail, |w| {
+                w.credit(amount, TransactionKind::WagerRefund, Some(match_id), Self::now())
+            })
+            .await?;
+        info!("退还用户 {} 在对局 {} 的押注 {}", user_id, match_id, amount);
+        Ok(wallet)
+    }
+
+    /// 向胜利者发放奖池，胜利者押注时已创建钱包
+    pub async fn payout_pot(&self, user_id: &str, match_id: &str, amount: u64) -> Result<Wallet> {
+        let wallet = self
+            .modify_wallet(user_id, Missing::Fail, |w| {
+                w.credit(amount, TransactionKind::PotPayout, Some(match_id), Self::now())
+            })
+            .await?;
+        info!("用户 {} 赢得对局 {} 的奖池 {}", user_id, match_id, amount);
+        Ok(wallet)
+    }
+
+    /// 记录链上结算结果
+    pub async fn mark_settled(&self, user_id: &str, balance: u64) -> Result<Wallet> {
+        self.modify_wallet(user_id, Missing::Fail, |w| {
+            w.settled_balance = Some(balance);
+            w.settled_at = Some(Self::now());
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// 钱包在持久化存储中的设置键
+fn storage_key(user_id: &str) -> String {
+    format!("{}{}", STORAGE_KEY_PREFIX, user_id)
+}
+
+// 用于存储全局EconomyService实例的静态变量
+static GLOBAL_ECONOMY_SERVICE: OnceCell<Arc<EconomyService>> = OnceCell::new();
+
+/// 初始化经济服务并设置为全局实例
+pub fn init_economy_service(game_service: Arc<GameService>) -> Arc<EconomyService> {
+    let service = Arc::new(EconomyService::new(game_service));
+    let _ = GLOBAL_ECONOMY_SERVICE.set(service.clone());
+    service
+}
+
+/// 获取全局经济服务
+pub fn global_economy_service() -> Option<Arc<EconomyService>> {
+    GLOBAL_ECONOMY_SERVICE.get().cloned()
+}
+
+/// 余额响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceResponse {
+    pub success: bool,
+    pub balance: Option<u64>,
+    pub settled_balance: Option<u64>,
+    pub settled_at: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// 交易记录响应
+#[derive(Debug, Serialize)]
+pub struct TransactionHistoryResponse {
+    pub success: bool,
+    pub transactions: Option<Vec<CurrencyTransaction>>,
+    pub error: Option<String>,
+}
+
+/// 链上结算响应
+#[derive(Debug, Serialize)]
+pub struct SettleResponse {
+    pub success: bool,
+    pub balance: Option<u64>,
+    pub digest: Option<String>,
+    pub error: Option<String>,
+}
+
+/// 从session中获取当前用户的Profile ID
+async fn session_profile_id(session: &Session) -> Result<ObjectID, InternalError> {
+    let user = session
+        .get::<SessionUser>(SESSION_USER_KEY)
+        .await?
+        .ok_or(InternalError::Unauthorized)?;
+    user.profile.map(|p| p.id).ok_or(InternalError::Unauthorized)
+}
+
+async fn balance_response(user_id: &str) -> BalanceResponse {
+    let wallet = match global_economy_service() {
+        Some(service) => service.get_wallet(user_id).await,
+        None => Err(anyhow!("经济服务未初始化")),
+    };
+    match wallet {
+        Ok(wallet) => BalanceResponse {
+            success: true,
+            balance: Some(wallet.balance),
+            settled_balance: wallet.settled_balance,
+            settled_at: wallet.settled_at,
+            error: None,
+        },
+        Err(e) => BalanceResponse {
+            success: false,
+            balance: None,
+            settled_balance: None,
+            settled_at: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+async fn history_response(user_id: &str) -> TransactionHistoryResponse {
+    let wallet = match global_economy_service() {
+        Some(service) => service.get_wallet(user_id).await,
+        None => Err(anyhow!("经济服务未初始化")),
+    };
+    match wallet {
+        // 最新的交易排在前面
+        Ok(wallet) => TransactionHistoryResponse {
+            success: true,
+            transactions: Some(wallet.history.into_iter().rev().collect()),
+            error: None,
+        },
+        Err(e) => TransactionHistoryResponse {
+            success: false,
+            transactions: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// 获取当前用户余额
+pub async fn handle_get_my_balance(
+    State(app_state): State<Arc<AppState>>,
+    Extension(session): Extension<Session>,
+) -> Result<Json<BalanceResponse>, InternalError> {
+    app_state.metrics.observe_request("get_my_balance");
+    let profile_id = session_profile_id(&session).await?;
+    Ok(Json(balance_response(&profile_id.to_string()).await))
+}
+
+/// 获取当前用户交易记录
+pub async fn handle_get_my_transactions(
+    State(app_state): State<Arc<AppState>>,
+    Extension(session): Extension<Session>,
+) -> Result<Json<TransactionHistoryResponse>, InternalError> {
+    app_state.metrics.observe_request("get_my_transactions");
+    let profile_id = session_profile_id(&session).await?;
+    Ok(Json(history_response(&profile_id.to_string()).await))
+}
+
+/// 获取指定用户余额
+pub async fn handle_get_user_balance(
+    State(app_state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+) -> Json<BalanceResponse> {
+    app_state.metrics.observe_request("get_user_balance");
+    Json(balance_response(&account_link::resolve_user_id(&user_id)).await)
+}
+
+/// 将当前用户余额结算到链上
+pub async fn handle_settle_balance(
+    State(app_state): State<Arc<AppState>>,
+    Extension(session): Extension<Session>,
+) -> Result<Json<SettleResponse>, InternalError> {
+    app_state.metrics.observe_request("settle_balance");
+    let profile_id = session_profile_id(&session).await?;
+    let user_id = profile_id.to_string();
+    // 游客没有链上Profile，升级后才能结算
+    if crate::guest::is_guest(&user_id) {
+        return Err(InternalError::NoAccess);
+    }
+    let service = global_economy_service().ok_or(InternalError::Failure)?;
+    let balance = service
+        .get_wallet(&user_id)
+        .await
+        .map_err(|e| {
+            error!("读取钱包失败: {}", e);
+            InternalError::Failure
+        })?
+        .balance;
+
+    match executor::settle_profile_balance(&app_state, &profile_id, balance).await {
+        Ok(response) => {
+            if let Err(e) = service.mark_settled(&user_id, balance).await {
+                error!("记录结算结果失败: {}", e);
+            }
+            Ok(Json(SettleResponse {
+                success: true,
+                balance: Some(balance),
+                digest: Some(response.digest.to_string()),
+                error: None,
+            }))
+        }
+        Err(e) => {
+            error!("余额结算失败: {}", e);
+            Ok(Json(SettleResponse {
+                success: false,
+                balance: Some(balance),
+                digest: None,
+                error: Some(format!("余额结算失败: {}", e)),
+            }))
+        }
+    }
+}
+
+/// 注册经济系统路由
+pub fn register_economy_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router
+        .route("/economy/balance", get(handle_get_my_balance))
+        .route("/economy/balance/:user_id", get(handle_get_user_balance))
+        .route("/economy/transactions", get(handle_get_my_transactions))
+        .route("/economy/settle", post(handle_settle_balance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debit_never_negative() {
+        let mut wallet = Wallet::new(0);
+        assert_eq!(wallet.balance, STARTING_BALANCE);
+
+        assert!(wallet
+            .debit(STARTING_BALANCE + 1, TransactionKind::WagerEscrow, Some("m1"), 1)
+            .is_err());
+        assert_eq!(wallet.balance, STARTING_BALANCE);
+        assert_eq!(wallet.history.len(), 1);
+
+        wallet
+            .debit(STARTING_BALANCE, TransactionKind::WagerEscrow, Some("m1"), 2)
+            .unwrap();
+        assert_eq!(wallet.balance, 0);
+        assert_eq!(wallet.history.back().unwrap().amount, -(STARTING_BALANCE as i64));
+    }
+
+    #[test]
+    fn test_history_capped() {
+        let mut wallet = Wallet::new(0);
+        for i in 0..(MAX_TRANSACTION_HISTORY as u64 + 10) {
+            wallet.credit(1, TransactionKind::PotPayout, None, i).unwrap();
+        }
+        assert_eq!(wallet.history.len(), MAX_TRANSACTION_HISTORY);
+        assert_eq!(wallet.history.back().unwrap().balance_after, wallet.balance);
+    }
+
+    #[tokio::test]
+    async fn test_missing_wallet_not_recreated() {
+        let service = EconomyService::new(Arc::new(GameService::new()));
+        let user_id = format!("economy_test_{}", uuid::Uuid::new_v4());
+
+        // 从未创建过钱包的用户不能直接入账
+        assert!(service.refund_wager(&user_id, "m1", 10).await.is_err());
+        assert!(service.payout_pot(&user_id, "m1", 10).await.is_err());
+
+        service.escrow_wager(&user_id, "m1", 100).await.unwrap();
+        let wallet = service.payout_pot(&user_id, "m1", 300).await.unwrap();
+        assert_eq!(wallet.balance, STARTING_BALANCE + 200);
+        assert_eq!(service.get_wallet(&user_id).await.unwrap().balance, STARTING_BALANCE + 200);
+    }
+}
@@ -9,19 +9,32 @@
  * 2. 访问控制错误 - 如无权访问特定密钥
  * 3. 服务器内部错误 - 如系统故障
  * 4. JWT令牌验证错误 - 如无效或过期的令牌
+ * 5. 封禁错误 - 错误体中包含封禁对象、原因和到期时间
  *
  * 每种错误类型都映射到特定的HTTP状态码和错误消息，以提供清晰的客户端反馈。
  */
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::ban::BanState;
+
+/// 降级模式下建议客户端重试的间隔（秒）
+pub const DEGRADED_RETRY_AFTER_SECS: u64 = 30;
+
+/// 全节点数据过时时建议客户端重试的间隔（秒），与检查点时间戳的更新间隔一致
+pub const STALE_FULL_NODE_RETRY_AFTER_SECS: u64 = 10;
+
+/// 密钥请求队列已满时建议客户端重试的间隔（秒）
+pub const OVERLOADED_RETRY_AFTER_SECS: u64 = 1;
 
 /**
  * 内部错误枚举
  * 定义了密钥服务器可能遇到的各种错误情况
  */
-#[derive(Debug, Serialize, PartialEq)]
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
 pub enum InternalError {
     /// 无效的可编程交易块(PTB)格式
     InvalidPTB,
@@ -47,6 +60,28 @@ pub enum InternalError {
     DecryptionError,
     /// 序列化错误
     SerializationError,
+    /// 请求过于频繁
+    RateLimited,
+    /// 密钥请求队列已满或排队超过截止时间，稍后重试
+    Overloaded,
+    /// 全节点不可用，服务处于降级模式，稍后重试
+    Degraded,
+    /// 全节点最新检查点超过允许的延迟，稍后重试
+    StaleFullNode,
+    /// 请求指定的Sui网络未配置
+    UnknownNetwork,
+    /// 请求缺少时间戳和nonce，或时间戳超出新鲜度窗口
+    StaleRequest,
+    /// 请求的nonce已被使用
+    ReplayedRequest,
+    /// 对局不存在或已过期
+    MatchNotFound,
+    /// 对局尚未结束
+    MatchInProgress,
+    /// 实例为只读镜像，不接受修改状态的请求
+    ReadOnly,
+    /// 用户或地址已被封禁，附带封禁状态
+    Banned(BanState),
     // ===== JWT令牌验证错误 =====
     /// JWT令牌无效（签名验证失败、格式错误等）
     InvalidToken,
@@ -64,7 +99,7 @@ pub enum InternalError {
  * 错误响应结构
  * 包含错误类型和详细错误消息，用于HTTP响应
  */
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ErrorResponse {
     error: InternalError,
     message: String,
@@ -119,6 +154,35 @@ impl IntoResponse for InternalError {
                 "User is not authorized to access this resource",
             ),
             InternalError::SerializationError => (StatusCode::FORBIDDEN, "Serialization error"),
+            InternalError::RateLimited => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many requests, please try again later",
+            ),
+            InternalError::Overloaded => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Key server is overloaded, please retry later",
+            ),
+            InternalError::Degraded => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Sui full node is unreachable, key service is degraded, please retry later",
+            ),
+            InternalError::StaleFullNode => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Sui full node data is stale, please retry later",
+            ),
+            InternalError::UnknownNetwork => (
+                StatusCode::BAD_REQUEST,
+                "Requested Sui network is not configured on this server",
+            ),
+            InternalError::StaleRequest => (
+                StatusCode::FORBIDDEN,
+                "Request timestamp is missing or outside the allowed freshness window",
+            ),
+            InternalError::ReplayedRequest => (StatusCode::FORBIDDEN, "Request nonce has already been used"),
+            InternalError::MatchNotFound => (StatusCode::NOT_FOUND, "Match not found or expired"),
+            InternalError::MatchInProgress => (StatusCode::CONFLICT, "Match is still in progress"),
+            InternalError::ReadOnly => (StatusCode::FORBIDDEN, "This server is a read-only mirror"),
+            InternalError::Banned(_) => (StatusCode::FORBIDDEN, "User or address is banned"),
         };
 
         let error_response = ErrorResponse {
@@ -126,6 +190,22 @@ impl IntoResponse for InternalError {
             message: message.to_string(),
         };
 
+        // 降级模式、数据过时和过载都是暂时的，提示客户端稍后重试
+        let retry_after = match error_response.error {
+            InternalError::Overloaded => Some(OVERLOADED_RETRY_AFTER_SECS),
+            InternalError::Degraded => Some(DEGRADED_RETRY_AFTER_SECS),
+            InternalError::StaleFullNode => Some(STALE_FULL_NODE_RETRY_AFTER_SECS),
+            _ => None,
+        };
+        if let Some(retry_after) = retry_after {
+            return (
+                status,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                Json(error_response),
+            )
+                .into_response();
+        }
+
         (status, Json(error_response)).into_response()
     }
 }
@@ -154,6 +234,17 @@ impl InternalError {
             InternalError::InvalidAuthHeader => "InvalidAuthHeader",
             InternalError::Unauthorized => "Unauthorized",
             InternalError::SerializationError => "SerializationError",
+            InternalError::RateLimited => "RateLimited",
+            InternalError::Overloaded => "Overloaded",
+            InternalError::Degraded => "Degraded",
+            InternalError::StaleFullNode => "StaleFullNode",
+            InternalError::UnknownNetwork => "UnknownNetwork",
+            InternalError::StaleRequest => "StaleRequest",
+            InternalError::ReplayedRequest => "ReplayedRequest",
+            InternalError::MatchNotFound => "MatchNotFound",
+            InternalError::MatchInProgress => "MatchInProgress",
+            InternalError::ReadOnly => "ReadOnly",
+            InternalError::Banned(_) => "Banned",
         }
     }
 }
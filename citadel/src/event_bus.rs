@@ -0,0 +1,274 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 模块间的类型化事件总线
+//!
+//! # 概述
+//! 对局、护照和聊天模块原先直接调用 `ConnectionManager` 并使用字符串事件名推送消息，
+//! 模块之间的联动（如对局保存后更新参与者的在线状态）也是直接互相调用。本模块把这些交互改为领域事件：
+//! - 模块通过 `publish` 发布 `DomainEvent`，不关心有哪些订阅者
+//! - 订阅者实现 `EventSubscriber`，在 `init_event_bus` 中按模块注册，按注册顺序依次处理事件，
+//!   某个订阅者处理失败只记录日志，不影响其他订阅者
+//! - `WsEgress` 是唯一的WebSocket出口，通过 `DomainEvent::egress` 把事件转换为WS事件名和数据，
+//!   推送给用户的所有会话或房间
+//!
+//! 当前的联动：
+//! - 对局保存或删除 → 护照模块更新参与者的丰富在线状态
+//! - 待处理好友请求数量变化 → 推送 `user:friend-request-counts`
+//! - 在线用户收到私信 → 推送 `chat:new-dm`
+//!
+//! 总线在注册WebSocket路由时初始化，未初始化时发布的事件被忽略。测试可以用 `EventBus::new` 创建独立的总线
+//! 并注册记录事件的订阅者。
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use once_cell::sync::OnceCell;
+use parking_lot::RwLock;
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::chat::{ChatEvents, ChatMessage};
+use crate::gaming::MatchData;
+use crate::passport::inbox::FriendRequestCounts;
+use crate::passport::{PassportState, ServerEvent};
+
+/// 领域事件
+#[derive(Debug, Clone)]
+pub enum DomainEvent {
+    /// 对局数据已保存
+    MatchSaved(Arc<MatchData>),
+    /// 对局已删除
+    MatchRemoved { match_id: String },
+    /// 用户的待处理好友请求数量变化
+    FriendRequestCountsChanged { user_id: String, counts: FriendRequestCounts },
+    /// 在线用户收到私信
+    DirectMessage { to_id: String, message: ChatMessage },
+}
+
+/// 推送目标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EgressTarget {
+    /// 用户的所有会话
+    User(String),
+    /// 房间内的所有客户端
+    Room(String),
+}
+
+/// 推送给客户端的WebSocket消息
+#[derive(Debug, Clone, PartialEq)]
+pub struct Egress {
+    pub target: EgressTarget,
+    pub event: &'static str,
+    pub data: Value,
+}
+
+impl DomainEvent {
+    /// 事件名称，用于日志
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::MatchSaved(_) => "match_saved",
+            Self::MatchRemoved { .. } => "match_removed",
+            Self::FriendRequestCountsChanged { .. } => "friend_request_counts_changed",
+            Self::DirectMessage { .. } => "direct_message",
+        }
+    }
+
+    /// 需要推送给客户端的消息，只在服务端内部使用的事件返回None
+    pub fn egress(&self) -> Result<Option<Egress>> {
+        let egress = match self {
+            Self::MatchSaved(_) | Self::MatchRemoved { .. } => return Ok(None),
+            Self::FriendRequestCountsChanged { user_id, counts } => Egress {
+                target: EgressTarget::User(user_id.clone()),
+                event: ServerEvent::FriendRequestCounts.as_str(),
+                data: serde_json::to_value(counts)?,
+            },
+            Self::DirectMessage { to_id, message } => Egress {
+                target: EgressTarget::User(to_id.clone()),
+                event: ChatEvents::NEW_DM,
+                data: serde_json::json!({
+                    "message": message,
+                    "offline": false
+                }),
+            },
+        };
+        Ok(Some(egress))
+    }
+}
+
+/// 事件订阅者
+#[async_trait]
+pub trait EventSubscriber: Send + Sync {
+    /// 订阅者名称，用于日志
+    fn name(&self) -> &'static str;
+
+    /// 处理事件，不关心的事件直接返回
+    async fn handle(&self, event: &DomainEvent) -> Result<()>;
+}
+
+/// 事件总线
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: RwLock<Vec<Arc<dyn EventSubscriber>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册订阅者
+    pub fn subscribe(&self, subscriber: Arc<dyn EventSubscriber>) {
+        debug!("事件总线注册订阅者: {}", subscriber.name());
+        self.subscribers.write().push(subscriber);
+    }
+
+    /// 按注册顺序把事件交给所有订阅者处理
+    pub async fn publish(&self, event: DomainEvent) {
+        let subscribers = self.subscribers.read().clone();
+        for subscriber in subscribers {
+            if let Err(e) = subscriber.handle(&event).await {
+                warn!("订阅者 {} 处理事件 {} 失败: {}", subscriber.name(), event.name(), e);
+            }
+        }
+    }
+}
+
+/// WebSocket出口，把事件推送给客户端
+pub struct WsEgress {
+    passport: Arc<PassportState>,
+}
+
+impl WsEgress {
+    pub fn new(passport: Arc<PassportState>) -> Self {
+        Self { passport }
+    }
+}
+
+#[async_trait]
+impl EventSubscriber for WsEgress {
+    fn name(&self) -> &'static str {
+        "ws_egress"
+    }
+
+    async fn handle(&self, event: &DomainEvent) -> Result<()> {
+        let Some(egress) = event.egress()? else {
+            return Ok(());
+        };
+        match egress.target {
+            EgressTarget::User(user_id) => {
+                self.passport.send_event_to_user(&user_id, egress.event, Some(egress.data)).await?;
+            }
+            EgressTarget::Room(room_id) => {
+                self.passport
+                    .connection_manager
+                    .broadcast_to_room(&room_id, egress.event, Some(egress.data))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+static GLOBAL_EVENT_BUS: OnceCell<Arc<EventBus>> = OnceCell::new();
+
+/**
+ * 初始化全局事件总线并注册各模块的订阅者
+ *
+ * 参数:
+ * @param passport - 用户护照状态，既是在线状态的订阅者，也提供WebSocket出口需要的用户会话
+ *
+ * 返回:
+ * 全局事件总线
+ */
+pub fn init_event_bus(passport: Arc<PassportState>) -> Arc<EventBus> {
+    GLOBAL_EVENT_BUS
+        .get_or_init(|| {
+            let bus = EventBus::new();
+            bus.subscribe(passport.clone());
+            bus.subscribe(Arc::new(WsEgress::new(passport)));
+            Arc::new(bus)
+        })
+        .clone()
+}
+
+/// 获取全局事件总线
+pub fn global_event_bus() -> Option<Arc<EventBus>> {
+    GLOBAL_EVENT_BUS.get().cloned()
+}
+
+/// 向全局事件总线发布事件，未初始化时忽略
+pub async fn publish(event: DomainEvent) {
+    if let Some(bus) = global_event_bus() {
+        bus.publish(event).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::bail;
+    use parking_lot::Mutex;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Mutex<Vec<&'static str>>,
+    }
+
+    #[async_trait]
+    impl EventSubscriber for Recorder {
+        fn name(&self) -> &'static str {
+            "recorder"
+        }
+
+        async fn handle(&self, event: &DomainEvent) -> Result<()> {
+            self.events.lock().push(event.name());
+            Ok(())
+        }
+    }
+
+    struct Failing;
+
+    #[async_trait]
+    impl EventSubscriber for Failing {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        async fn handle(&self, _event: &DomainEvent) -> Result<()> {
+            bail!("总是失败")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish_reaches_all_subscribers() {
+        let bus = EventBus::new();
+        let recorder = Arc::new(Recorder::default());
+        bus.subscribe(Arc::new(Failing));
+        bus.subscribe(recorder.clone());
+
+        bus.publish(DomainEvent::MatchRemoved { match_id: "m1".to_string() }).await;
+        bus.publish(DomainEvent::FriendRequestCountsChanged {
+            user_id: "u1".to_string(),
+            counts: FriendRequestCounts::default(),
+        })
+        .await;
+        assert_eq!(*recorder.events.lock(), vec!["match_removed", "friend_request_counts_changed"]);
+    }
+
+    #[test]
+    fn test_egress_mapping() {
+        let removed = DomainEvent::MatchRemoved { match_id: "m1".to_string() };
+        assert_eq!(removed.egress().unwrap(), None);
+
+        let counts = DomainEvent::FriendRequestCountsChanged {
+            user_id: "u1".to_string(),
+            counts: FriendRequestCounts { incoming: 3, outgoing: 1 },
+        };
+        let egress = counts.egress().unwrap().unwrap();
+        assert_eq!(egress.target, EgressTarget::User("u1".to_string()));
+        assert_eq!(egress.event, "user:friend-request-counts");
+        assert_eq!(egress.data, serde_json::json!({ "incoming": 3, "outgoing": 1 }));
+    }
+}
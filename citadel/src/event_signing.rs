@@ -0,0 +1,197 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 权威游戏事件签名模块
+//!
+//! # 概述
+//! 对局结果和评分变化由服务器权威计算。启用签名后，服务器使用启动时生成的临时密钥对（`eph_kp`）
+//! 对这些事件签名，客户端和第三方可以验证结果在传输中没有被篡改：
+//! - 签名方式与 `common::to_signed_response` 一致：对 `IntentMessage` 的BCS字节做Ed25519签名，签名以Hex编码
+//! - 对局结果使用 `IntentScope::MatchResult`，评分变化包含在对局结果中一起签名
+//! - 公钥通过 `GET /v1/service` 的 `eph_pk` 字段公开
+//!
+//! 临时密钥对在每次启动时重新生成（除非设置了 `SEED`），验证方需要使用签名时的公钥。
+//!
+//! # 配置
+//! - `EVENT_SIGNING_ENABLED`：是否对权威游戏事件签名，默认false
+
+use std::sync::Arc;
+
+use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519PublicKey, Ed25519Signature};
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::traits::{ToFromBytes, VerifyingKey};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::common::{to_signed_response, IntentMessage, IntentScope, ProcessedDataResponse};
+use crate::externals::current_epoch_time;
+use crate::gaming::MatchData;
+use crate::stats::RatingChange;
+use crate::AppState;
+
+/// 签名的对局结果
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchResultData {
+    /// 对局ID
+    pub match_id: String,
+    /// 胜利者ID
+    pub winner_id: String,
+    /// 按出局顺序排列的其他玩家ID
+    pub out_ids: Vec<String>,
+    /// 本局的评分变化
+    pub rating_changes: Vec<SignedRatingChange>,
+    /// 对局结束时间
+    pub completed_at: u64,
+}
+
+/// 签名的评分变化
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedRatingChange {
+    pub user_id: String,
+    pub rating_before: i32,
+    pub rating_after: i32,
+}
+
+impl From<&RatingChange> for SignedRatingChange {
+    fn from(change: &RatingChange) -> Self {
+        Self {
+            user_id: change.user_id.clone(),
+            rating_before: change.rating_before,
+            rating_after: change.rating_after,
+        }
+    }
+}
+
+impl MatchResultData {
+    /// 从已结束的对局和评分变化构建对局结果
+    pub fn from_match(match_data: &MatchData, winner_id: &str, rating_changes: &[RatingChange]) -> Self {
+        Self {
+            match_id: match_data.id.clone(),
+            winner_id: winner_id.to_string(),
+            out_ids: match_data.out.iter().map(|p| p.user.id.clone()).collect(),
+            rating_changes: rating_changes.iter().map(SignedRatingChange::from).collect(),
+            completed_at: match_data.updated_at,
+        }
+    }
+}
+
+/// 对局结果签名
+pub fn sign_match_result(
+    kp: &Ed25519KeyPair,
+    result: MatchResultData,
+    timestamp_ms: u64,
+) -> ProcessedDataResponse<IntentMessage<MatchResultData>> {
+    to_signed_response(kp, result, timestamp_ms, IntentScope::MatchResult)
+}
+
+/**
+ * 验证签名的事件
+ *
+ * 参数:
+ * @param pk - 签名时使用的临时公钥
+ * @param signed - 签名的事件
+ *
+ * 返回:
+ * 签名是否有效
+ */
+pub fn verify_signed_event<T: Serialize>(
+    pk: &Ed25519PublicKey,
+    signed: &ProcessedDataResponse<IntentMessage<T>>,
+) -> bool {
+    let Ok(bytes) = bcs::to_bytes(&signed.response) else {
+        return false;
+    };
+    let Ok(sig) = Hex::decode(&signed.signature) else {
+        return false;
+    };
+    let Ok(sig) = Ed25519Signature::from_bytes(&sig) else {
+        return false;
+    };
+    pk.verify(&bytes, &sig).is_ok()
+}
+
+/// 全局事件签名器，持有应用状态以使用其中的临时密钥对
+static GLOBAL_EVENT_SIGNER: OnceCell<Arc<AppState>> = OnceCell::new();
+
+/// 是否启用了事件签名
+fn signing_enabled() -> bool {
+    std::env::var("EVENT_SIGNING_ENABLED")
+        .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+/**
+ * 初始化全局事件签名器
+ *
+ * 未设置 `EVENT_SIGNING_ENABLED` 时不启用签名
+ *
+ * 参数:
+ * @param state - 应用状态
+ *
+ * 返回:
+ * 是否启用了事件签名
+ */
+pub fn init_event_signer(state: Arc<AppState>) -> bool {
+    if !signing_enabled() {
+        return false;
+    }
+    let _ = GLOBAL_EVENT_SIGNER.set(state);
+    info!("已启用权威游戏事件签名");
+    true
+}
+
+/**
+ * 使用全局临时密钥对签名对局结果
+ *
+ * 参数:
+ * @param result - 对局结果
+ *
+ * 返回:
+ * 签名的对局结果JSON，未启用签名时返回None
+ */
+pub fn signed_match_result(result: MatchResultData) -> Option<serde_json::Value> {
+    let state = GLOBAL_EVENT_SIGNER.get()?;
+    let signed = sign_match_result(&state.eph_kp, result, current_epoch_time());
+    serde_json::to_value(signed)
+        .map_err(|e| error!("序列化签名的对局结果失败: {}", e))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastcrypto::traits::KeyPair;
+
+    fn result() -> MatchResultData {
+        MatchResultData {
+            match_id: "m1".to_string(),
+            winner_id: "u1".to_string(),
+            out_ids: vec!["u2".to_string()],
+            rating_changes: vec![SignedRatingChange {
+                user_id: "u1".to_string(),
+                rating_before: 1000,
+                rating_after: 1016,
+            }],
+            completed_at: 1,
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify_match_result() {
+        let kp = AppState::generate_keypair(Some(7));
+        let signed = sign_match_result(&kp, result(), 2);
+        assert!(verify_signed_event(kp.public(), &signed));
+
+        // 其他密钥对无法验证
+        let other = AppState::generate_keypair(Some(8));
+        assert!(!verify_signed_event(other.public(), &signed));
+
+        // 篡改结果后签名失效
+        let mut tampered = signed;
+        tampered.response.data.rating_changes[0].rating_after = 2000;
+        assert!(!verify_signed_event(kp.public(), &tampered));
+    }
+}
@@ -15,14 +15,13 @@
 
  use crate::cache::{Cache, CACHE_SIZE, CACHE_TTL};
  use crate::errors::InternalError;
+ use crate::sui_api::SuiApi;
  use crate::types::Network;
  use once_cell::sync::Lazy;
  use reqwest::Client;
  use serde_json::Value;
  use std::str::FromStr;
  use sui_sdk::error::SuiRpcResult;
- use sui_sdk::rpc_types::CheckpointId;
- use sui_sdk::SuiClient;
  use sui_types::base_types::ObjectID;
  use tap::TapFallible;
  use tracing::{debug, warn};
@@ -144,23 +143,28 @@
   * 这对于验证请求的新鲜度至关重要
   * 
   * 参数:
-  * @param client - Sui客户端实例
+  * @param client - 全节点接口，通常为Sui客户端
   * 
   * 返回:
   * 最新检查点的时间戳(毫秒)
   */
- pub async fn get_latest_checkpoint_timestamp(client: SuiClient) -> SuiRpcResult<u64> {
-     let latest_checkpoint_sequence_number = client
-         .read_api()
-         .get_latest_checkpoint_sequence_number()
-         .await?;
-     let checkpoint = client
-         .read_api()
-         .get_checkpoint(CheckpointId::SequenceNumber(
-             latest_checkpoint_sequence_number,
-         ))
-         .await?;
-     Ok(checkpoint.timestamp_ms)
+ pub async fn get_latest_checkpoint_timestamp(client: impl SuiApi) -> SuiRpcResult<u64> {
+     client.latest_checkpoint_timestamp().await
+ }
+ 
+ /**
+  * 获取当前纪元
+  * 
+  * 从最新检查点读取纪元编号，用于检查zkLogin临时密钥是否过期
+  * 
+  * 参数:
+  * @param client - 全节点接口，通常为Sui客户端
+  * 
+  * 返回:
+  * 当前纪元编号
+  */
+ pub async fn get_current_epoch(client: impl SuiApi) -> SuiRpcResult<u64> {
+     client.current_epoch().await
  }
  
  /**
@@ -170,15 +174,14 @@
   * 用于验证交易Gas价格是否合理
   * 
   * 参数:
-  * @param client - Sui客户端实例
+  * @param client - 全节点接口，通常为Sui客户端
   * 
   * 返回:
   * 当前参考Gas价格
   */
- pub async fn get_reference_gas_price(client: SuiClient) -> SuiRpcResult<u64> {
+ pub async fn get_reference_gas_price(client: impl SuiApi) -> SuiRpcResult<u64> {
      let rgp = client
-         .read_api()
-         .get_reference_gas_price()
+         .reference_gas_price()
          .await
          .tap_err(|e| {
              warn!("Failed retrieving RGP ({:?})", e);
@@ -0,0 +1,363 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 链上好友关系与护照好友缓存的同步
+//!
+//! # 概述
+//! 护照模块的好友系统只保存在缓存中，而 `CITADEL_FRIENDSHIP_ADDRESS` 指向的链上 `FriendshipStore`
+//! 由 `GameManager` 定期全量拉取。本模块在两者之间做双向同步：
+//! - 链上到缓存：定期对比 `GameManager` 最近一次拉取的好友关系与上一次同步的结果，
+//!   新增或变化的关系写入护照关系缓存和好友列表，从链上消失的关系从缓存中删除
+//! - 缓存到链上：开启 `FRIEND_SYNC_COMMIT_ON_CHAIN` 后，接受好友请求时通过管理员交易把好友关系提交到链上，
+//!   链上还没有请求时先代为发送请求再接受
+//!
+//! 链上的Profile通过PassportID映射回护照用户ID，没有Profile的用户不参与同步。
+//! 本地的封禁关系优先于链上数据，不会被同步覆盖；只存在于本地、从未出现在链上的关系也不会被删除。
+//!
+//! # 配置
+//! - `FRIEND_SYNC_INTERVAL_SECS`：链上到缓存的同步间隔，默认60秒，为0时关闭
+//! - `FRIEND_SYNC_COMMIT_ON_CHAIN`：是否把接受的好友请求提交到链上，默认关闭
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use once_cell::sync::OnceCell;
+use sui_types::base_types::ObjectID;
+use tracing::{debug, error, info};
+
+use crate::passport::{PassportState, RelationshipStatus};
+use crate::sdk::manager::{Relationship as ChainRelationship, RelationshipStatus as ChainStatus};
+use crate::AppState;
+
+/// 好友同步配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FriendSyncConfig {
+    /// 链上到缓存的同步间隔，为None时关闭
+    pub interval: Option<Duration>,
+    /// 是否把接受的好友请求提交到链上
+    pub commit_on_chain: bool,
+}
+
+impl Default for FriendSyncConfig {
+    fn default() -> Self {
+        Self {
+            interval: Some(Duration::from_secs(60)),
+            commit_on_chain: false,
+        }
+    }
+}
+
+impl FriendSyncConfig {
+    /// 从环境变量读取，未配置或无效的项使用默认值
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let interval = match std::env::var("FRIEND_SYNC_INTERVAL_SECS").ok().and_then(|v| v.trim().parse::<u64>().ok()) {
+            Some(0) => None,
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => defaults.interval,
+        };
+        let commit_on_chain = std::env::var("FRIEND_SYNC_COMMIT_ON_CHAIN")
+            .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(defaults.commit_on_chain);
+        Self { interval, commit_on_chain }
+    }
+}
+
+/// 护照关系键，较小的用户ID在前，与护照关系缓存的键顺序一致
+type PairKey = (String, String);
+
+/// 链上好友关系在护照缓存中对应的状态，按护照关系键索引
+type ChainSnapshot = HashMap<PairKey, RelationshipStatus>;
+
+/// 一次同步需要执行的操作
+#[derive(Debug, Clone, PartialEq)]
+enum SyncAction {
+    /// 链上新增或变化的关系
+    Set { key: PairKey, status: RelationshipStatus },
+    /// 链上已删除的关系，附带上一次同步时的状态
+    Remove { key: PairKey, previous: RelationshipStatus },
+}
+
+/**
+ * 将链上关系转换为护照关系键和状态
+ *
+ * 参数:
+ * @param initiator - 发起请求的护照用户ID
+ * @param receiver - 接收请求的护照用户ID
+ * @param status - 链上关系状态
+ *
+ * 返回:
+ * 护照关系键和对应的护照关系状态
+ */
+fn passport_relationship(initiator: &str, receiver: &str, status: &ChainStatus) -> (PairKey, RelationshipStatus) {
+    let initiator_first = initiator < receiver;
+    let key = if initiator_first {
+        (initiator.to_string(), receiver.to_string())
+    } else {
+        (receiver.to_string(), initiator.to_string())
+    };
+    let status = match status {
+        ChainStatus::Friends => RelationshipStatus::Friends,
+        ChainStatus::Pending if initiator_first => RelationshipStatus::FriendRequest1To2,
+        ChainStatus::Pending => RelationshipStatus::FriendRequest2To1,
+    };
+    (key, status)
+}
+
+/// 对比两次链上快照，得到需要同步到缓存的操作
+fn diff(previous: &ChainSnapshot, current: &ChainSnapshot) -> Vec<SyncAction> {
+    let mut actions: Vec<SyncAction> = current
+        .iter()
+        .filter(|(key, status)| previous.get(*key) != Some(*status))
+        .map(|(key, status)| SyncAction::Set {
+            key: key.clone(),
+            status: status.clone(),
+        })
+        .collect();
+    actions.extend(
+        previous
+            .iter()
+            .filter(|(key, _)| !current.contains_key(*key))
+            .map(|(key, previous)| SyncAction::Remove {
+                key: key.clone(),
+                previous: previous.clone(),
+            }),
+    );
+    actions
+}
+
+/// 本地封禁关系不被链上数据覆盖
+fn is_blocked(status: &RelationshipStatus) -> bool {
+    matches!(
+        status,
+        RelationshipStatus::Blocked | RelationshipStatus::Blocked1To2 | RelationshipStatus::Blocked2To1
+    )
+}
+
+/// 好友同步服务
+pub struct FriendSync {
+    app_state: Arc<AppState>,
+    config: FriendSyncConfig,
+    /// 上一次同步到缓存的链上快照
+    synced: tokio::sync::Mutex<ChainSnapshot>,
+}
+
+static GLOBAL_FRIEND_SYNC: OnceCell<Arc<FriendSync>> = OnceCell::new();
+
+/**
+ * 初始化好友同步并启动链上到缓存的同步任务
+ *
+ * 参数:
+ * @param state - 应用状态
+ */
+pub fn init_friend_sync(state: Arc<AppState>) {
+    let mut config = FriendSyncConfig::from_env();
+    // 只读镜像不提交链上交易
+    if crate::read_only::is_read_only() {
+        config.commit_on_chain = false;
+    }
+    let sync = Arc::new(FriendSync {
+        app_state: state.clone(),
+        config: config.clone(),
+        synced: tokio::sync::Mutex::new(HashMap::new()),
+    });
+    if GLOBAL_FRIEND_SYNC.set(sync.clone()).is_err() {
+        return;
+    }
+    info!(
+        "好友同步已启用，同步间隔: {:?}，提交到链上: {}",
+        config.interval, config.commit_on_chain
+    );
+
+    let Some(interval) = config.interval else {
+        return;
+    };
+    state.tasks.spawn("friend_sync", move |token| async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut last_update = 0;
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = ticker.tick() => {}
+            }
+            // 链上数据没有更新时跳过
+            let update = sync.app_state.game_manager.get_last_relationship_update();
+            if update == last_update {
+                continue;
+            }
+            // 护照模块在WebSocket路由注册后才可用
+            let Some(passport) = crate::ws::global_passport_state() else {
+                continue;
+            };
+            match sync.sync_from_chain(&passport).await {
+                Ok(()) => last_update = update,
+                Err(e) => error!("同步链上好友关系失败: {}", e),
+            }
+        }
+    });
+}
+
+impl FriendSync {
+    /// 读取GameManager最近一次拉取的链上关系，转换为护照快照
+    async fn chain_snapshot(&self) -> ChainSnapshot {
+        let game_manager = &self.app_state.game_manager;
+        let passports = game_manager.profile_passport_map().await;
+        let passport_of = |profile: &ObjectID| passports.get(profile).map(|p| p.to_string());
+        game_manager
+            .all_relationships()
+            .await
+            .iter()
+            .filter_map(|rel: &ChainRelationship| {
+                let initiator = passport_of(&rel.initiator)?;
+                let receiver = passport_of(&rel.receiver)?;
+                Some(passport_relationship(&initiator, &receiver, &rel.status))
+            })
+            .collect()
+    }
+
+    /// 将链上变化同步到护照缓存
+    async fn sync_from_chain(&self, passport: &PassportState) -> Result<()> {
+        let current = self.chain_snapshot().await;
+        let mut synced = self.synced.lock().await;
+        let actions = diff(&synced, &current);
+        if !actions.is_empty() {
+            debug!("链上好友关系有 {} 处变化", actions.len());
+        }
+        for action in actions {
+            match action {
+                SyncAction::Set { key: (user1, user2), status } => {
+                    let local = passport.get_relationship(&user1, &user2).await.map(|rel| rel.status);
+                    if local.as_ref().is_some_and(|s| is_blocked(s) || *s == status) {
+                        continue;
+                    }
+                    passport.set_relationship(&user1, &user2, status.clone()).await?;
+                    if status == RelationshipStatus::Friends {
+                        passport.add_to_friends_list(&user1, &user2).await?;
+                        passport.add_to_friends_list(&user2, &user1).await?;
+                    } else {
+                        passport.remove_from_friends_list(&user1, &user2).await?;
+                        passport.remove_from_friends_list(&user2, &user1).await?;
+                    }
+                }
+                SyncAction::Remove { key: (user1, user2), previous } => {
+                    // 只删除同步后本地没有再修改过的关系
+                    let local = passport.get_relationship(&user1, &user2).await.map(|rel| rel.status);
+                    if local != Some(previous) {
+                        continue;
+                    }
+                    passport.delete_relationship(&user1, &user2).await?;
+                    passport.remove_from_friends_list(&user1, &user2).await?;
+                    passport.remove_from_friends_list(&user2, &user1).await?;
+                }
+            }
+        }
+        *synced = current;
+        Ok(())
+    }
+
+    /**
+     * 将已接受的好友关系提交到链上
+     *
+     * 参数:
+     * @param requester - 发送好友请求的护照用户ID
+     * @param accepter - 接受好友请求的护照用户ID
+     */
+    async fn commit_friendship(&self, requester: &str, accepter: &str) -> Result<()> {
+        let game_manager = &self.app_state.game_manager;
+        let requester_passport = ObjectID::from_hex_literal(requester).context("无效的护照ID格式")?;
+        let accepter_passport = ObjectID::from_hex_literal(accepter).context("无效的护照ID格式")?;
+        let requester_profile = game_manager.get_profile_id_by_passport(&requester_passport).await?;
+        let accepter_profile = game_manager.get_profile_id_by_passport(&accepter_passport).await?;
+
+        // 链上请求的方向以链上记录为准
+        let (from, to) = match game_manager.get_relationship(&requester_profile, &accepter_profile).await? {
+            Some(rel) if rel.status == ChainStatus::Friends => return Ok(()),
+            Some(rel) => (rel.initiator, rel.receiver),
+            None => {
+                crate::sdk::executor::admin_send_friend_request(&self.app_state, &requester_profile, &accepter_profile)
+                    .await?;
+                (requester_profile, accepter_profile)
+            }
+        };
+        crate::sdk::executor::admin_accept_friend_request(&self.app_state, &from, &to).await?;
+        info!("好友关系已提交到链上: {} <-> {}", requester, accepter);
+        Ok(())
+    }
+}
+
+/**
+ * 接受好友请求后调用，开启提交到链上时在后台提交
+ *
+ * 参数:
+ * @param requester - 发送好友请求的护照用户ID
+ * @param accepter - 接受好友请求的护照用户ID
+ */
+pub fn on_friend_request_accepted(requester: &str, accepter: &str) {
+    let Some(sync) = GLOBAL_FRIEND_SYNC.get().cloned() else {
+        return;
+    };
+    if !sync.config.commit_on_chain {
+        return;
+    }
+    // 游客没有链上Profile，升级时好友关系随游客迁移
+    if crate::guest::is_guest(requester) || crate::guest::is_guest(accepter) {
+        return;
+    }
+    let (requester, accepter) = (requester.to_string(), accepter.to_string());
+    tokio::spawn(async move {
+        if let Err(e) = sync.commit_friendship(&requester, &accepter).await {
+            error!("提交好友关系 {} <-> {} 到链上失败: {}", requester, accepter, e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passport_relationship() {
+        let (key, status) = passport_relationship("0xb", "0xa", &ChainStatus::Pending);
+        assert_eq!(key, ("0xa".to_string(), "0xb".to_string()));
+        assert_eq!(status, RelationshipStatus::FriendRequest2To1);
+
+        let (_, status) = passport_relationship("0xa", "0xb", &ChainStatus::Pending);
+        assert_eq!(status, RelationshipStatus::FriendRequest1To2);
+        let (_, status) = passport_relationship("0xb", "0xa", &ChainStatus::Friends);
+        assert_eq!(status, RelationshipStatus::Friends);
+    }
+
+    #[test]
+    fn test_diff() {
+        let key = |a: &str, b: &str| (a.to_string(), b.to_string());
+        let previous: ChainSnapshot = [
+            (key("a", "b"), RelationshipStatus::FriendRequest1To2),
+            (key("a", "c"), RelationshipStatus::Friends),
+            (key("b", "c"), RelationshipStatus::Friends),
+        ]
+        .into_iter()
+        .collect();
+        let current: ChainSnapshot = [
+            (key("a", "b"), RelationshipStatus::Friends),
+            (key("a", "c"), RelationshipStatus::Friends),
+            (key("c", "d"), RelationshipStatus::FriendRequest2To1),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut actions = diff(&previous, &current);
+        actions.sort_by_key(|action| format!("{:?}", action));
+        assert_eq!(
+            actions,
+            vec![
+                SyncAction::Remove { key: key("b", "c"), previous: RelationshipStatus::Friends },
+                SyncAction::Set { key: key("a", "b"), status: RelationshipStatus::Friends },
+                SyncAction::Set { key: key("c", "d"), status: RelationshipStatus::FriendRequest2To1 },
+            ]
+        );
+        assert!(diff(&current, &current).is_empty());
+    }
+}
@@ -6,9 +6,18 @@
  *
  * 本模块实现了一个专门用于游戏数据的缓存系统，基于LRU缓存策略，具有以下特点：
  * 1. 提供游戏数据的快速存取
- * 2. 支持基于时间的自动过期机制（TTL）
+ * 2. 支持基于时间的自动过期机制（TTL），每种数据前缀可以有不同的过期时间，也可以不过期，
+ *    不过期的前缀写入时直接固定，不会因容量限制被淘汰
  * 3. 线程安全实现，支持并发访问
  * 4. 支持游戏会话、用户数据和游戏状态的缓存
+ * 5. 支持固定条目（如进行中的游戏），固定的条目不会过期，也不会因容量限制被淘汰
+ * 6. 后台定期清理过期条目，并统计命中、过期和淘汰次数
+ *
+ * 配置（环境变量）:
+ * - GAME_CACHE_CAPACITY：未固定条目的最大数量，默认10000，不过期前缀的数据不计入
+ * - GAME_CACHE_SWEEP_SECS：后台清理间隔（秒），默认60
+ * - GAME_CACHE_TTL_<PREFIX>_SECS：指定前缀的过期时间（秒），0表示不过期，
+ *   例如 GAME_CACHE_TTL_MATCH_SECS=3600
  *
  * 基于cache.rs模块重新实现，专为游戏数据优化
  */
@@ -16,9 +25,14 @@ use crate::externals::current_epoch_time;
 use lru::LruCache;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::hash::Hash;
 use std::num::NonZero;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tracing::{debug, info};
+use utoipa::ToSchema;
 
 /// 游戏缓存前缀常量，用于区分不同类型的游戏数据
 pub enum GameCachePrefix {
@@ -27,6 +41,17 @@ pub enum GameCachePrefix {
     USER,    // 用户数据
     SESSION, // 会话数据
     STATE,   // 游戏状态数据
+    ACHIEVEMENT, // 用户成就数据
+    WALLET,  // 用户钱包数据
+    STATS,   // 玩家统计数据
+    RATING_HISTORY, // 玩家评分历史
+    PENALTY, // 玩家退出记录与匹配禁令
+    QUEUE,   // 匹配队列
+    DIRECT_MESSAGE, // 好友私信
+    ACCOUNT_LINK, // 钱包与Profile的关联
+    GUEST,   // 游客账户
+    PROFILE, // Profile名称与修改记录
+    SERIES,  // 多局制系列赛
 }
 
 impl GameCachePrefix {
@@ -37,6 +62,65 @@ impl GameCachePrefix {
             GameCachePrefix::USER => "user",
             GameCachePrefix::SESSION => "session",
             GameCachePrefix::STATE => "state",
+            GameCachePrefix::ACHIEVEMENT => "achievement",
+            GameCachePrefix::WALLET => "wallet",
+            GameCachePrefix::STATS => "stats",
+            GameCachePrefix::RATING_HISTORY => "rating_history",
+            GameCachePrefix::PENALTY => "penalty",
+            GameCachePrefix::QUEUE => "queue",
+            GameCachePrefix::DIRECT_MESSAGE => "dm",
+            GameCachePrefix::ACCOUNT_LINK => "account_link",
+            GameCachePrefix::GUEST => "guest",
+            GameCachePrefix::PROFILE => "profile",
+            GameCachePrefix::SERIES => "series",
+        }
+    }
+
+    /// 所有前缀
+    pub const ALL: [GameCachePrefix; 16] = [
+        GameCachePrefix::MATCH,
+        GameCachePrefix::LOBBY,
+        GameCachePrefix::USER,
+        GameCachePrefix::SESSION,
+        GameCachePrefix::STATE,
+        GameCachePrefix::ACHIEVEMENT,
+        GameCachePrefix::WALLET,
+        GameCachePrefix::STATS,
+        GameCachePrefix::RATING_HISTORY,
+        GameCachePrefix::PENALTY,
+        GameCachePrefix::QUEUE,
+        GameCachePrefix::DIRECT_MESSAGE,
+        GameCachePrefix::ACCOUNT_LINK,
+        GameCachePrefix::GUEST,
+        GameCachePrefix::PROFILE,
+        GameCachePrefix::SERIES,
+    ];
+
+    /// 默认过期时间（毫秒），None表示不过期
+    pub fn default_ttl(&self) -> Option<u64> {
+        match self {
+            // 进行中的游戏会被固定，这里只影响等待中和已结束的游戏
+            GameCachePrefix::MATCH | GameCachePrefix::STATE => Some(2 * HOUR_MS),
+            // 系列赛跨越多局，每局结束时更新
+            GameCachePrefix::SERIES => Some(6 * HOUR_MS),
+            GameCachePrefix::LOBBY | GameCachePrefix::SESSION => Some(GAME_CACHE_TTL),
+            // 用户信息、好友关系，长期未更新的离线用户会被清理
+            GameCachePrefix::USER => Some(7 * 24 * HOUR_MS),
+            // 成就、钱包、统计数据、退出记录、钱包关联和Profile名称没有其他存储，不过期，
+            // 写入时固定，不会被LRU淘汰，重启后依靠缓存快照恢复
+            GameCachePrefix::ACHIEVEMENT
+            | GameCachePrefix::WALLET
+            | GameCachePrefix::STATS
+            | GameCachePrefix::RATING_HISTORY
+            | GameCachePrefix::PENALTY
+            | GameCachePrefix::ACCOUNT_LINK
+            | GameCachePrefix::PROFILE => None,
+            // 每次队列变化时整体重写，能否恢复由快照的最大年龄决定
+            GameCachePrefix::QUEUE => None,
+            // 私信记录、未读数和离线队列，长期没有新消息的对话会被清理
+            GameCachePrefix::DIRECT_MESSAGE => Some(30 * 24 * HOUR_MS),
+            // 游客账户只保存有限时间，需要长期保留数据的游客应升级为链上Profile
+            GameCachePrefix::GUEST => Some(30 * 24 * HOUR_MS),
         }
     }
 }
@@ -44,6 +128,109 @@ impl GameCachePrefix {
 /// 游戏缓存默认设置
 pub(crate) const GAME_CACHE_SIZE: usize = 10000; // 默认缓存大小
 pub(crate) const GAME_CACHE_TTL: u64 = 30 * 60 * 1000; // 30分钟默认过期时间
+pub(crate) const GAME_CACHE_SWEEP_INTERVAL: Duration = Duration::from_secs(60); // 默认清理间隔
+const HOUR_MS: u64 = 60 * 60 * 1000;
+
+/**
+ * 游戏缓存配置
+ *
+ * 字段:
+ * @field capacity - 未固定条目的最大数量
+ * @field sweep_interval - 后台清理间隔
+ * @field ttls - 各前缀的过期时间（毫秒），None表示不过期
+ */
+#[derive(Debug, Clone)]
+pub struct GameCacheConfig {
+    pub capacity: usize,
+    pub sweep_interval: Duration,
+    pub ttls: HashMap<&'static str, Option<u64>>,
+}
+
+impl GameCacheConfig {
+    /// 使用默认值创建配置
+    pub fn default() -> Self {
+        Self {
+            capacity: GAME_CACHE_SIZE,
+            sweep_interval: GAME_CACHE_SWEEP_INTERVAL,
+            ttls: GameCachePrefix::ALL
+                .iter()
+                .map(|prefix| (prefix.as_str(), prefix.default_ttl()))
+                .collect(),
+        }
+    }
+
+    /// 从环境变量读取配置，未设置的项使用默认值
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(capacity) = env_u64("GAME_CACHE_CAPACITY").filter(|c| *c > 0) {
+            config.capacity = capacity as usize;
+        }
+        if let Some(secs) = env_u64("GAME_CACHE_SWEEP_SECS").filter(|s| *s > 0) {
+            config.sweep_interval = Duration::from_secs(secs);
+        }
+        for prefix in GameCachePrefix::ALL.iter() {
+            let key = format!("GAME_CACHE_TTL_{}_SECS", prefix.as_str().to_uppercase());
+            if let Some(secs) = env_u64(&key) {
+                config.ttls.insert(prefix.as_str(), (secs > 0).then_some(secs * 1000));
+            }
+        }
+        config
+    }
+}
+
+fn env_u64(key: &str) -> Option<u64> {
+    std::env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok())
+}
+
+/// 缓存统计计数器
+#[derive(Default)]
+struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    expired: AtomicU64,
+    evicted: AtomicU64,
+}
+
+/**
+ * 缓存统计快照
+ *
+ * 字段:
+ * @field hits - 命中次数
+ * @field misses - 未命中次数（包括已过期）
+ * @field expired - 因过期被移除的条目数
+ * @field evicted - 因容量限制被淘汰的条目数
+ * @field entries - 当前未固定的条目数
+ * @field pinned - 当前固定的条目数
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheMetricsSnapshot {
+    pub hits: u64,
+    pub misses: u64,
+    pub expired: u64,
+    pub evicted: u64,
+    pub entries: usize,
+    pub pinned: usize,
+}
+
+/**
+ * 缓存快照条目
+ *
+ * 字段:
+ * @field key - 键
+ * @field value - 值
+ * @field expiry - 过期时间（毫秒时间戳），None表示不过期
+ * @field ttl - 条目的生存时间，None表示不过期
+ * @field pinned - 是否固定
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheSnapshotEntry<K, V> {
+    pub key: K,
+    pub value: V,
+    pub expiry: Option<u64>,
+    pub ttl: Option<u64>,
+    pub pinned: bool,
+}
 
 /**
  * 游戏缓存条目结构
@@ -53,25 +240,47 @@ pub(crate) const GAME_CACHE_TTL: u64 = 30 * 60 * 1000; // 30分钟默认过期
  * 字段:
  * @field value - 缓存的实际数据
  * @field expiry - 条目过期时间（毫秒时间戳）
+ * @field ttl - 条目的生存时间，更新时据此重新计算过期时间，None表示不过期
  */
 #[derive(Clone)]
 struct GameCacheEntry<V> {
     pub value: V,    // 缓存数据
     pub expiry: u64, // 过期时间戳
+    pub ttl: Option<u64>,
+}
+
+impl<V> GameCacheEntry<V> {
+    fn new(value: V, ttl: Option<u64>) -> Self {
+        let expiry = match ttl {
+            Some(ttl) => current_epoch_time().saturating_add(ttl),
+            None => u64::MAX,
+        };
+        Self { value, expiry, ttl }
+    }
+
+    fn is_expired(&self, now: u64) -> bool {
+        self.expiry < now
+    }
 }
 
 /**
  * 游戏缓存结构
  *
  * 实现线程安全的LRU缓存，专门用于游戏数据
+ * 固定的条目单独保存，不计入容量，也不会过期
+ * 需要同时访问两个锁时，总是先锁pinned再锁cache
  *
  * 字段:
- * @field ttl - 缓存条目的生存时间（毫秒）
+ * @field ttl - 缓存条目的默认生存时间（毫秒）
  * @field cache - 底层LRU缓存，使用互斥锁保护
+ * @field pinned - 固定的条目
+ * @field counters - 统计计数器
  */
 pub struct GameCache<K, V> {
     ttl: u64,
     cache: Mutex<LruCache<K, GameCacheEntry<V>>>,
+    pinned: Mutex<HashMap<K, V>>,
+    counters: CacheCounters,
 }
 
 impl<K: Hash + Eq + Clone, V: Clone> GameCache<K, V> {
@@ -94,6 +303,8 @@ impl<K: Hash + Eq + Clone, V: Clone> GameCache<K, V> {
             cache: Mutex::new(LruCache::new(
                 NonZero::new(size).expect("缓存大小必须大于0"),
             )),
+            pinned: Mutex::new(HashMap::new()),
+            counters: CacheCounters::default(),
         }
     }
 
@@ -122,18 +333,27 @@ impl<K: Hash + Eq + Clone, V: Clone> GameCache<K, V> {
      * 如果键存在且未过期，则返回关联的游戏数据，否则返回None
      */
     pub fn get(&self, key: &K) -> Option<V> {
+        if let Some(value) = self.pinned.lock().get(key) {
+            self.counters.hits.fetch_add(1, Ordering::Relaxed);
+            return Some(value.clone());
+        }
+
         let mut cache = self.cache.lock();
-        match cache.get(key) {
+        let result = match cache.get(key) {
             Some(entry) => {
-                if entry.expiry < current_epoch_time() {
+                if entry.is_expired(current_epoch_time()) {
                     cache.pop(key);
+                    self.counters.expired.fetch_add(1, Ordering::Relaxed);
                     None
                 } else {
                     Some(entry.value.clone())
                 }
             }
             None => None,
-        }
+        };
+        let counter = if result.is_some() { &self.counters.hits } else { &self.counters.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        result
     }
 
     /**
@@ -147,14 +367,33 @@ impl<K: Hash + Eq + Clone, V: Clone> GameCache<K, V> {
      * @param value - 要存储的游戏数据
      */
     pub fn set(&self, key: K, value: V) {
+        self.set_with_ttl(key, value, Some(self.ttl));
+    }
+
+    /**
+     * 使用指定的生存时间插入或更新缓存条目
+     *
+     * 如果键已固定，只更新固定的值
+     * 插入导致容量超限时，淘汰最久未使用的条目
+     *
+     * 参数:
+     * @param key - 要插入的键
+     * @param value - 要存储的游戏数据
+     * @param ttl - 生存时间（毫秒），None表示不过期
+     */
+    pub fn set_with_ttl(&self, key: K, value: V, ttl: Option<u64>) {
+        let mut pinned = self.pinned.lock();
+        if let Some(pinned_value) = pinned.get_mut(&key) {
+            *pinned_value = value;
+            return;
+        }
+
         let mut cache = self.cache.lock();
-        cache.put(
-            key,
-            GameCacheEntry {
-                value,
-                expiry: current_epoch_time() + self.ttl,
-            },
-        );
+        if let Some((evicted_key, _)) = cache.push(key.clone(), GameCacheEntry::new(value, ttl)) {
+            if evicted_key != key {
+                self.counters.evicted.fetch_add(1, Ordering::Relaxed);
+            }
+        }
     }
 
     /**
@@ -174,21 +413,23 @@ impl<K: Hash + Eq + Clone, V: Clone> GameCache<K, V> {
     where
         F: FnOnce(V) -> V,
     {
+        let mut pinned = self.pinned.lock();
+        if let Some(pinned_value) = pinned.get_mut(key) {
+            *pinned_value = update_fn(pinned_value.clone());
+            return true;
+        }
+
         let mut cache = self.cache.lock();
         if let Some(entry) = cache.get(key) {
-            if entry.expiry < current_epoch_time() {
+            if entry.is_expired(current_epoch_time()) {
                 cache.pop(key);
+                self.counters.expired.fetch_add(1, Ordering::Relaxed);
                 return false;
             }
 
+            let ttl = entry.ttl;
             let updated_value = update_fn(entry.value.clone());
-            cache.put(
-                key.clone(),
-                GameCacheEntry {
-                    value: updated_value,
-                    expiry: current_epoch_time() + self.ttl,
-                },
-            );
+            cache.put(key.clone(), GameCacheEntry::new(updated_value, ttl));
             true
         } else {
             false
@@ -207,8 +448,220 @@ impl<K: Hash + Eq + Clone, V: Clone> GameCache<K, V> {
      * 如果键存在并被删除返回true，否则返回false
      */
     pub fn delete(&self, key: &K) -> bool {
+        let mut pinned = self.pinned.lock();
+        let was_pinned = pinned.remove(key).is_some();
+        let mut cache = self.cache.lock();
+        cache.pop(key).is_some() || was_pinned
+    }
+
+    /**
+     * 删除所有满足条件的条目，包括已固定的条目
+     *
+     * 在同一次加锁中完成，删除过程中不会有其他线程看到部分删除的结果
+     *
+     * 参数:
+     * @param predicate - 判断键是否需要删除
+     *
+     * 返回:
+     * 删除的条目数
+     */
+    pub fn delete_where<F>(&self, predicate: F) -> usize
+    where
+        F: Fn(&K) -> bool,
+    {
+        let mut pinned = self.pinned.lock();
         let mut cache = self.cache.lock();
-        cache.pop(key).is_some()
+        let before = pinned.len();
+        pinned.retain(|key, _| !predicate(key));
+        let removed = before - pinned.len();
+        let keys = cache
+            .iter()
+            .filter(|(key, _)| predicate(key))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in &keys {
+            cache.pop(key);
+        }
+        removed + keys.len()
+    }
+
+    /**
+     * 插入或更新固定的缓存条目
+     *
+     * 固定的条目不计入容量，不会过期，也不会被淘汰
+     *
+     * 参数:
+     * @param key - 要插入的键
+     * @param value - 要存储的游戏数据
+     */
+    pub fn set_pinned(&self, key: K, value: V) {
+        let mut pinned = self.pinned.lock();
+        self.cache.lock().pop(&key);
+        pinned.insert(key, value);
+    }
+
+    /**
+     * 固定缓存条目
+     *
+     * 固定的条目不会过期，也不会因容量限制被淘汰
+     *
+     * 参数:
+     * @param key - 要固定的键
+     *
+     * 返回:
+     * 键存在（或已固定）返回true，否则返回false
+     */
+    pub fn pin(&self, key: &K) -> bool {
+        let mut pinned = self.pinned.lock();
+        if pinned.contains_key(key) {
+            return true;
+        }
+        let mut cache = self.cache.lock();
+        match cache.pop(key) {
+            Some(entry) if !entry.is_expired(current_epoch_time()) => {
+                pinned.insert(key.clone(), entry.value);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /**
+     * 取消固定缓存条目
+     *
+     * 条目放回LRU缓存，并从当前时间开始重新计算过期时间
+     *
+     * 参数:
+     * @param key - 要取消固定的键
+     * @param ttl - 放回后的生存时间（毫秒），None表示不过期
+     *
+     * 返回:
+     * 键已固定返回true，否则返回false
+     */
+    pub fn unpin(&self, key: &K, ttl: Option<u64>) -> bool {
+        let mut pinned = self.pinned.lock();
+        let Some(value) = pinned.remove(key) else {
+            return false;
+        };
+        let mut cache = self.cache.lock();
+        if let Some((evicted_key, _)) = cache.push(key.clone(), GameCacheEntry::new(value, ttl)) {
+            if evicted_key != *key {
+                self.counters.evicted.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        true
+    }
+
+    /**
+     * 清理所有过期条目
+     *
+     * 返回:
+     * 清理的条目数
+     */
+    pub fn sweep(&self) -> usize {
+        let now = current_epoch_time();
+        let mut cache = self.cache.lock();
+        let expired = cache
+            .iter()
+            .filter(|(_, entry)| entry.is_expired(now))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in &expired {
+            cache.pop(key);
+        }
+        self.counters.expired.fetch_add(expired.len() as u64, Ordering::Relaxed);
+        expired.len()
+    }
+
+    /**
+     * 导出所有未过期的条目
+     *
+     * 未固定的条目按最久未使用到最近使用的顺序排列，导入时可以保持LRU顺序
+     *
+     * 返回:
+     * 快照条目列表
+     */
+    pub fn export(&self) -> Vec<CacheSnapshotEntry<K, V>> {
+        let now = current_epoch_time();
+        let pinned = self.pinned.lock();
+        let cache = self.cache.lock();
+        let mut entries = cache
+            .iter()
+            .rev()
+            .filter(|(_, entry)| !entry.is_expired(now))
+            .map(|(key, entry)| CacheSnapshotEntry {
+                key: key.clone(),
+                value: entry.value.clone(),
+                expiry: (entry.expiry != u64::MAX).then_some(entry.expiry),
+                ttl: entry.ttl,
+                pinned: false,
+            })
+            .collect::<Vec<_>>();
+        entries.extend(pinned.iter().map(|(key, value)| CacheSnapshotEntry {
+            key: key.clone(),
+            value: value.clone(),
+            expiry: None,
+            ttl: None,
+            pinned: true,
+        }));
+        entries
+    }
+
+    /**
+     * 导入快照条目
+     *
+     * 保留条目原有的过期时间，跳过已过期的条目和缓存中已存在的键（已存在的数据更新）
+     *
+     * 参数:
+     * @param entries - 快照条目列表
+     *
+     * 返回:
+     * 导入的条目数
+     */
+    pub fn import(&self, entries: Vec<CacheSnapshotEntry<K, V>>) -> usize {
+        let now = current_epoch_time();
+        let mut pinned = self.pinned.lock();
+        let mut cache = self.cache.lock();
+        let mut imported = 0;
+        for entry in entries {
+            if pinned.contains_key(&entry.key) || cache.contains(&entry.key) {
+                continue;
+            }
+            if entry.pinned {
+                pinned.insert(entry.key, entry.value);
+            } else {
+                let expiry = entry.expiry.unwrap_or(u64::MAX);
+                if expiry < now {
+                    continue;
+                }
+                let value = GameCacheEntry {
+                    value: entry.value,
+                    expiry,
+                    ttl: entry.ttl,
+                };
+                if let Some((evicted_key, _)) = cache.push(entry.key.clone(), value) {
+                    if evicted_key != entry.key {
+                        self.counters.evicted.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            }
+            imported += 1;
+        }
+        imported
+    }
+
+    /// 获取缓存统计快照
+    pub fn metrics(&self) -> CacheMetricsSnapshot {
+        let pinned = self.pinned.lock().len();
+        let entries = self.cache.lock().len();
+        CacheMetricsSnapshot {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            expired: self.counters.expired.load(Ordering::Relaxed),
+            evicted: self.counters.evicted.load(Ordering::Relaxed),
+            entries,
+            pinned,
+        }
     }
 }
 
@@ -216,24 +669,67 @@ impl<K: Hash + Eq + Clone, V: Clone> GameCache<K, V> {
  * 游戏服务结构
  *
  * 提供游戏相关操作的高级接口，内部使用GameCache进行数据缓存
+ * 每种数据前缀使用GameCacheConfig中配置的过期时间，不过期的前缀写入时固定
  */
 pub struct GameService {
     cache: Arc<GameCache<String, String>>,
+    ttls: HashMap<&'static str, Option<u64>>,
+    sweep_interval: Duration,
 }
 
 impl GameService {
     /**
      * 创建新的游戏服务实例
      *
+     * 使用环境变量中的缓存配置
+     *
      * 返回:
      * 新创建的游戏服务实例
      */
     pub fn new() -> Self {
+        Self::with_config(GameCacheConfig::from_env())
+    }
+
+    /**
+     * 使用指定配置创建游戏服务实例
+     *
+     * 参数:
+     * @param config - 缓存配置
+     *
+     * 返回:
+     * 新创建的游戏服务实例
+     */
+    pub fn with_config(config: GameCacheConfig) -> Self {
         Self {
-            cache: Arc::new(GameCache::default()),
+            cache: Arc::new(GameCache::new(GAME_CACHE_TTL, config.capacity)),
+            ttls: config.ttls,
+            sweep_interval: config.sweep_interval,
+        }
+    }
+
+    /// 前缀对应的过期时间
+    fn ttl_for(&self, prefix: &GameCachePrefix) -> Option<u64> {
+        self.ttls
+            .get(prefix.as_str())
+            .copied()
+            .unwrap_or_else(|| prefix.default_ttl())
+    }
+
+    /// 按过期时间写入缓存，不过期的数据固定，避免被LRU淘汰后丢失
+    fn store(&self, key: String, json: String, ttl: Option<u64>) {
+        match ttl {
+            Some(ttl) => self.cache.set_with_ttl(key, json, Some(ttl)),
+            None => self.cache.set_pinned(key, json),
         }
     }
 
+    /// 缓存键所属前缀是否不过期
+    fn is_durable_key(&self, key: &str) -> bool {
+        key.split_once(':')
+            .and_then(|(prefix, _)| self.ttls.get(prefix))
+            .is_some_and(|ttl| ttl.is_none())
+    }
+
     /**
      * 获取游戏数据
      *
@@ -274,7 +770,7 @@ impl GameService {
         let prefixed_key = format!("{}:{}", prefix.as_str(), key);
         match serde_json::to_string(value) {
             Ok(json) => {
-                self.cache.set(prefixed_key, json);
+                self.store(prefixed_key, json, self.ttl_for(&prefix));
                 true
             }
             Err(_) => false,
@@ -335,6 +831,181 @@ impl GameService {
         let prefixed_key = format!("{}:{}", prefix.as_str(), key);
         self.cache.delete(&prefixed_key)
     }
+
+    /**
+     * 固定游戏数据，使其不会过期或被淘汰
+     *
+     * 参数:
+     * @param prefix - 数据类型前缀
+     * @param key - 数据键
+     *
+     * 返回:
+     * 数据存在返回true，否则返回false
+     */
+    pub fn pin(&self, prefix: GameCachePrefix, key: &str) -> bool {
+        let prefixed_key = format!("{}:{}", prefix.as_str(), key);
+        self.cache.pin(&prefixed_key)
+    }
+
+    /**
+     * 取消固定游戏数据，从当前时间开始按前缀的过期时间计算
+     *
+     * 不过期前缀的数据始终固定，不能取消
+     *
+     * 参数:
+     * @param prefix - 数据类型前缀
+     * @param key - 数据键
+     *
+     * 返回:
+     * 数据已固定且已取消返回true，否则返回false
+     */
+    pub fn unpin(&self, prefix: GameCachePrefix, key: &str) -> bool {
+        let prefixed_key = format!("{}:{}", prefix.as_str(), key);
+        match self.ttl_for(&prefix) {
+            Some(ttl) => self.cache.unpin(&prefixed_key, Some(ttl)),
+            None => false,
+        }
+    }
+
+    /// 作用域内的缓存键，格式为 `前缀:@作用域:键`
+    fn scoped_key(prefix: &GameCachePrefix, scope: &str, key: &str) -> String {
+        format!("{}:@{}:{}", prefix.as_str(), scope, key)
+    }
+
+    /**
+     * 获取作用域内的游戏数据
+     *
+     * 参数:
+     * @param prefix - 数据类型前缀
+     * @param scope - 作用域，通常为游戏ID
+     * @param key - 数据键
+     *
+     * 返回:
+     * 成功返回解析后的数据，否则返回None
+     */
+    pub fn get_scoped<T: for<'de> Deserialize<'de>>(
+        &self,
+        prefix: GameCachePrefix,
+        scope: &str,
+        key: &str,
+    ) -> Option<T> {
+        self.cache
+            .get(&Self::scoped_key(&prefix, scope, key))
+            .and_then(|json| serde_json::from_str(&json).ok())
+    }
+
+    /**
+     * 设置作用域内的游戏数据
+     *
+     * 同一作用域下各前缀的数据可以通过 `delete_scope` 一次删除
+     *
+     * 参数:
+     * @param prefix - 数据类型前缀
+     * @param scope - 作用域，通常为游戏ID
+     * @param key - 数据键
+     * @param value - 要存储的数据
+     *
+     * 返回:
+     * 成功返回true，失败返回false
+     */
+    pub fn set_scoped<T: Serialize>(
+        &self,
+        prefix: GameCachePrefix,
+        scope: &str,
+        key: &str,
+        value: &T,
+    ) -> bool {
+        match serde_json::to_string(value) {
+            Ok(json) => {
+                let ttl = self.ttl_for(&prefix);
+                self.store(Self::scoped_key(&prefix, scope, key), json, ttl);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// 删除作用域内的单条数据
+    pub fn delete_scoped(&self, prefix: GameCachePrefix, scope: &str, key: &str) -> bool {
+        self.cache.delete(&Self::scoped_key(&prefix, scope, key))
+    }
+
+    /**
+     * 删除作用域内所有前缀的数据
+     *
+     * 游戏结束时用于清理手牌、计时器、连锁状态等对局数据，
+     * 不在作用域内的数据（如 `match:游戏ID` 本身）不受影响
+     *
+     * 参数:
+     * @param scope - 作用域，通常为游戏ID
+     *
+     * 返回:
+     * 删除的条目数
+     */
+    pub fn delete_scope(&self, scope: &str) -> usize {
+        let marker = format!("@{}:", scope);
+        self.cache.delete_where(|key| {
+            key.split_once(':')
+                .map(|(_, rest)| rest.starts_with(&marker))
+                .unwrap_or(false)
+        })
+    }
+
+    /// 清理所有过期数据，返回清理的条目数
+    pub fn sweep(&self) -> usize {
+        self.cache.sweep()
+    }
+
+    /// 导出所有未过期的数据，用于写入快照
+    pub fn export_entries(&self) -> Vec<CacheSnapshotEntry<String, String>> {
+        self.cache.export()
+    }
+
+    /// 导入快照数据，已存在的键不会被覆盖，不过期前缀的数据导入后固定，返回导入的条目数
+    pub fn import_entries(&self, mut entries: Vec<CacheSnapshotEntry<String, String>>) -> usize {
+        for entry in entries.iter_mut() {
+            entry.pinned |= self.is_durable_key(&entry.key);
+        }
+        self.cache.import(entries)
+    }
+
+    /// 获取缓存统计快照
+    pub fn cache_metrics(&self) -> CacheMetricsSnapshot {
+        self.cache.metrics()
+    }
+
+    /**
+     * 启动后台清理任务
+     *
+     * 按配置的间隔清理过期数据，游戏服务被释放后任务自动退出
+     *
+     * 返回:
+     * 后台任务句柄
+     */
+    pub fn spawn_sweeper(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let service: Weak<Self> = Arc::downgrade(self);
+        let interval = self.sweep_interval;
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // 第一次tick立即完成，跳过
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(service) = service.upgrade() else {
+                    debug!("游戏服务已释放，停止缓存清理");
+                    break;
+                };
+                let removed = service.sweep();
+                if removed > 0 {
+                    let metrics = service.cache_metrics();
+                    info!(
+                        "清理过期缓存 {} 条，当前 {} 条（固定 {} 条），累计过期 {} 条、淘汰 {} 条",
+                        removed, metrics.entries, metrics.pinned, metrics.expired, metrics.evicted
+                    );
+                }
+            }
+        })
+    }
 }
 
 #[cfg(test)]
@@ -409,4 +1080,101 @@ mod tests {
         let deleted: Option<TestUser> = service.get(GameCachePrefix::USER, "1");
         assert_eq!(deleted, None);
     }
+
+    #[test]
+    fn test_game_cache_eviction_and_pinning() {
+        let cache = GameCache::<String, String>::new(100, 2);
+        cache.set("a".to_string(), "1".to_string());
+        assert!(cache.pin(&"a".to_string()));
+        cache.set("b".to_string(), "2".to_string());
+        cache.set("c".to_string(), "3".to_string());
+        cache.set("d".to_string(), "4".to_string());
+
+        // 固定的条目不计入容量，也不会过期
+        assert_eq!(cache.get(&"b".to_string()), None);
+        sleep(Duration::from_millis(200));
+        assert_eq!(cache.get(&"a".to_string()), Some("1".to_string()));
+        assert_eq!(cache.sweep(), 2);
+
+        let metrics = cache.metrics();
+        assert_eq!(metrics.evicted, 1);
+        assert_eq!(metrics.expired, 2);
+        assert_eq!(metrics.pinned, 1);
+        assert_eq!(metrics.entries, 0);
+
+        // 取消固定后重新计算过期时间
+        assert!(cache.unpin(&"a".to_string(), Some(100)));
+        sleep(Duration::from_millis(200));
+        assert_eq!(cache.get(&"a".to_string()), None);
+    }
+
+    #[test]
+    fn test_game_service_delete_scope() {
+        let service = GameService::new();
+        assert!(service.set_scoped(GameCachePrefix::STATE, "m1", "hand:u1", &vec![1, 2]));
+        assert!(service.set_scoped(GameCachePrefix::STATE, "m1", "chain", &3));
+        assert!(service.set_scoped(GameCachePrefix::SESSION, "m1", "timer", &4));
+        assert!(service.pin(GameCachePrefix::STATE, "@m1:chain"));
+        assert!(service.set_scoped(GameCachePrefix::STATE, "m10", "chain", &5));
+        assert!(service.set(GameCachePrefix::MATCH, "m1", &6));
+
+        assert_eq!(
+            service.get_scoped::<Vec<i32>>(GameCachePrefix::STATE, "m1", "hand:u1"),
+            Some(vec![1, 2])
+        );
+        assert_eq!(service.delete_scope("m1"), 3);
+        assert_eq!(service.get_scoped::<i32>(GameCachePrefix::STATE, "m1", "chain"), None);
+        assert_eq!(service.get_scoped::<i32>(GameCachePrefix::SESSION, "m1", "timer"), None);
+
+        // 其他作用域和未加作用域的数据保留
+        assert_eq!(service.get_scoped::<i32>(GameCachePrefix::STATE, "m10", "chain"), Some(5));
+        assert_eq!(service.get::<i32>(GameCachePrefix::MATCH, "m1"), Some(6));
+        assert_eq!(service.delete_scope("m1"), 0);
+    }
+
+    #[test]
+    fn test_game_service_prefix_ttl() {
+        let mut config = GameCacheConfig::default();
+        config.ttls.insert(GameCachePrefix::LOBBY.as_str(), Some(100));
+        let service = GameService::with_config(config);
+
+        assert!(service.set(GameCachePrefix::LOBBY, "1", &1));
+        assert!(service.set(GameCachePrefix::STATS, "1", &1));
+        sleep(Duration::from_millis(200));
+        assert_eq!(service.get::<i32>(GameCachePrefix::LOBBY, "1"), None);
+        assert_eq!(service.get::<i32>(GameCachePrefix::STATS, "1"), Some(1));
+    }
+
+    #[test]
+    fn test_game_service_durable_prefix_not_evicted() {
+        let mut config = GameCacheConfig::default();
+        config.capacity = 1;
+        let service = GameService::with_config(config);
+
+        for user in ["u1", "u2", "u3"] {
+            assert!(service.set(GameCachePrefix::WALLET, user, &100));
+        }
+        assert!(service.set(GameCachePrefix::MATCH, "m1", &1));
+        assert!(service.set(GameCachePrefix::MATCH, "m2", &2));
+
+        // 不过期的数据不占用容量，也不会被淘汰
+        for user in ["u1", "u2", "u3"] {
+            assert_eq!(service.get::<i32>(GameCachePrefix::WALLET, user), Some(100));
+        }
+        assert_eq!(service.get::<i32>(GameCachePrefix::MATCH, "m1"), None);
+        assert!(!service.unpin(GameCachePrefix::WALLET, "u1"));
+        assert_eq!(service.cache_metrics().pinned, 3);
+
+        // 旧快照中未固定的数据导入后固定
+        let restored = GameService::with_config(GameCacheConfig::default());
+        let entries = vec![CacheSnapshotEntry {
+            key: "stats:u1".to_string(),
+            value: "1".to_string(),
+            expiry: None,
+            ttl: None,
+            pinned: false,
+        }];
+        assert_eq!(restored.import_entries(entries), 1);
+        assert_eq!(restored.cache_metrics().pinned, 1);
+    }
 }
@@ -0,0 +1,266 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 游戏模式注册模块
+//!
+//! # 概述
+//! MatchService负责房间、回合、计时、押注和结算等与具体玩法无关的流程，
+//! 与玩法相关的部分由 `GameMode` 实现：
+//! - 生成牌组和发牌，描述牌组构成（牌组预设）
+//! - 校验玩家的抽牌和出牌动作
+//! - 牌堆抽完时的处理规则（洗回弃牌或结束游戏），以及判断胜负
+//! - 生成公开视角和玩家视角的对局数据
+//!
+//! 每局游戏在 `MatchData.mode` 中记录模式ID，MatchService通过 `mode_for` 取得对应的实现。
+//! 新增玩法时在 `game_mode/` 下添加模块实现 `GameMode`，并在 `GameModeRegistry::with_builtin` 中注册。
+//!
+//! # 内置模式
+//! - `exploding_kittens`：爆炸猫，默认模式
+
+pub mod exploding_kittens;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use rand::seq::SliceRandom;
+use rand::RngCore;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::gaming::{Card, CardType, MatchData};
+use crate::match_replay;
+
+/// 默认游戏模式
+pub const DEFAULT_MODE: &str = exploding_kittens::MODE_ID;
+
+/// 需要游戏模式校验的玩家动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerAction<'a> {
+    /// 抽牌
+    Draw,
+    /// 出牌
+    Play { card_id: &'a str },
+}
+
+/// 牌组预设中的一种卡牌，张数为 `base + per_player * 玩家数`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeckPresetEntry {
+    pub card_type: CardType,
+    pub base: i32,
+    pub per_player: u32,
+}
+
+impl DeckPresetEntry {
+    /// 指定玩家数时的张数
+    pub fn count(&self, player_count: usize) -> usize {
+        (self.base + (self.per_player as usize * player_count) as i32).max(0) as usize
+    }
+}
+
+/// 牌堆抽完时的处理规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeckExhaustion {
+    /// 把弃牌堆中爆炸猫以外的牌洗回牌堆，没有可以洗回的牌时结束游戏
+    Reshuffle,
+    /// 结束游戏，由 `GameMode::winner` 计分决定胜利者
+    EndGame,
+}
+
+impl DeckExhaustion {
+    /// 从配置值解析，只接受 `reshuffle` 和 `end_game`
+    pub fn from_str(value: &str) -> Option<Self> {
+        match value.trim() {
+            "reshuffle" => Some(DeckExhaustion::Reshuffle),
+            "end_game" => Some(DeckExhaustion::EndGame),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * 游戏模式
+ *
+ * 实现只描述玩法规则，不直接修改缓存或发送消息
+ */
+pub trait GameMode: Send + Sync {
+    /// 模式ID，保存在 `MatchData.mode` 中
+    fn id(&self) -> &'static str;
+
+    /// 一局游戏的玩家数范围
+    fn player_range(&self) -> (usize, usize);
+
+    /// 生成洗好的牌组，使用传入的随机数生成器洗牌，相同的随机数序列生成相同的牌组
+    fn build_deck(&self, player_count: usize, rng: &mut dyn RngCore) -> Vec<Card>;
+
+    /// 牌组构成，与 `build_deck` 生成的牌组一致，供客户端展示
+    fn deck_preset(&self) -> Vec<DeckPresetEntry>;
+
+    /// 开局发牌，牌组已由 `build_deck` 生成
+    fn deal(&self, match_data: &mut MatchData);
+
+    /// 校验玩家动作，不合法时返回错误
+    fn validate_action(&self, match_data: &MatchData, user_id: &str, action: PlayerAction) -> Result<()>;
+
+    /// 牌堆抽完时的处理规则
+    fn deck_exhaustion(&self) -> DeckExhaustion;
+
+    /// 游戏结束时返回胜利者ID，否则返回None；`MatchData.deck_exhausted` 为true时必须计分选出胜利者
+    fn winner(&self, match_data: &MatchData) -> Option<String>;
+
+    /// 观众视角的对局数据，不包含任何玩家的手牌和牌堆内容
+    fn public_view(&self, match_data: &MatchData) -> Result<Value>;
+
+    /// 玩家视角的对局数据，只包含该玩家自己的手牌
+    fn private_view(&self, match_data: &MatchData, user_id: &str) -> Result<Value>;
+}
+
+/// 游戏模式注册表
+#[derive(Default)]
+pub struct GameModeRegistry {
+    modes: HashMap<&'static str, Arc<dyn GameMode>>,
+}
+
+impl GameModeRegistry {
+    /// 注册所有内置模式
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::default();
+        registry.register(Arc::new(exploding_kittens::ExplodingKittens));
+        registry
+    }
+
+    /// 注册游戏模式，相同ID的模式会被替换
+    pub fn register(&mut self, mode: Arc<dyn GameMode>) {
+        self.modes.insert(mode.id(), mode);
+    }
+
+    /// 按ID获取游戏模式
+    pub fn get(&self, id: &str) -> Option<Arc<dyn GameMode>> {
+        self.modes.get(id).cloned()
+    }
+
+    /// 已注册的模式ID，按字母顺序排列
+    pub fn ids(&self) -> Vec<&'static str> {
+        let mut ids = self.modes.keys().copied().collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids
+    }
+}
+
+static GAME_MODES: OnceCell<GameModeRegistry> = OnceCell::new();
+
+/// 获取全局游戏模式注册表
+pub fn global_game_modes() -> &'static GameModeRegistry {
+    GAME_MODES.get_or_init(GameModeRegistry::with_builtin)
+}
+
+/// 获取对局使用的游戏模式，未注册的模式按默认模式处理
+pub fn mode_for(match_data: &MatchData) -> Arc<dyn GameMode> {
+    let registry = global_game_modes();
+    registry
+        .get(&match_data.mode)
+        .or_else(|| registry.get(DEFAULT_MODE))
+        .expect("默认游戏模式未注册")
+}
+
+/// 默认模式ID，用于反序列化没有模式字段的旧对局数据
+pub fn default_mode_id() -> String {
+    DEFAULT_MODE.to_string()
+}
+
+/**
+ * 裁剪对局数据中的隐藏信息
+ *
+ * 牌堆只保留张数，除 `viewer` 以外的玩家手牌只保留张数，字段结构保持不变
+ *
+ * 参数:
+ * @param match_data - 对局数据
+ * @param viewer - 可以看到自己手牌的玩家ID，观众为None
+ *
+ * 返回:
+ * 裁剪后的对局数据
+ */
+pub fn redacted_view(match_data: &MatchData, viewer: Option<&str>) -> Result<Value> {
+    let mut value = serde_json::to_value(match_data)?;
+    if let Some(deck) = value.get_mut("deck") {
+        let count = deck.as_array().map(|cards| cards.len()).unwrap_or(0);
+        *deck = Value::Array(Vec::new());
+        value["deck_count"] = count.into();
+    }
+    for list in ["players", "out"] {
+        let Some(players) = value.get_mut(list).and_then(|v| v.as_array_mut()) else {
+            continue;
+        };
+        for player in players {
+            let own = viewer.is_some() && player["user"]["id"].as_str() == viewer;
+            if own {
+                continue;
+            }
+            let count = player["hand"].as_array().map(|cards| cards.len()).unwrap_or(0);
+            player["hand"] = Value::Array(Vec::new());
+            player["hand_count"] = count.into();
+        }
+    }
+    Ok(value)
+}
+
+/**
+ * 可以洗回牌堆的牌
+ *
+ * 弃牌堆可能被压缩（见 `match_history`），因此从开局时的所有卡牌中找出不在牌堆、也不在任何玩家手中的牌，
+ * 对局中和重放时得到的结果相同。爆炸猫不会洗回牌堆。对局没有种子时只使用弃牌堆中保留的牌。
+ */
+fn reshufflable_cards(match_data: &MatchData) -> Vec<Card> {
+    let held = match_data.deck.iter()
+        .chain(match_data.players.iter().chain(match_data.out.iter()).flat_map(|p| p.hand.iter()))
+        .map(|c| c.id.as_str())
+        .collect::<HashSet<_>>();
+    match_replay::initial_cards(match_data)
+        .unwrap_or_else(|| match_data.discard_pile.clone())
+        .into_iter()
+        .filter(|c| c.card_type != CardType::ExplodingKitten && !held.contains(c.id.as_str()))
+        .collect()
+}
+
+/**
+ * 牌堆是否已经抽完且无法补充
+ *
+ * 参数:
+ * @param mode - 对局的游戏模式
+ * @param match_data - 对局数据
+ *
+ * 返回:
+ * 牌堆为空，且模式规则为结束游戏或没有可以洗回的牌时返回true
+ */
+pub fn deck_exhausted(mode: &dyn GameMode, match_data: &MatchData) -> bool {
+    match_data.deck.is_empty()
+        && (mode.deck_exhaustion() == DeckExhaustion::EndGame || reshufflable_cards(match_data).is_empty())
+}
+
+/**
+ * 牌堆抽完时按游戏模式的规则补充牌堆
+ *
+ * 规则为洗回弃牌时，把可以洗回的牌从弃牌堆移除并洗成新的牌堆，弃牌堆的压缩计数随之清零。
+ *
+ * 参数:
+ * @param mode - 对局的游戏模式
+ * @param match_data - 牌堆为空的对局数据
+ * @param rng - 洗牌使用的随机数生成器
+ *
+ * 返回:
+ * 洗回牌堆的张数，为0时游戏应当结束
+ */
+pub fn refill_deck(mode: &dyn GameMode, match_data: &mut MatchData, rng: &mut dyn RngCore) -> usize {
+    if !match_data.deck.is_empty() || mode.deck_exhaustion() != DeckExhaustion::Reshuffle {
+        return 0;
+    }
+    let mut cards = reshufflable_cards(match_data);
+    let ids = cards.iter().map(|c| c.id.clone()).collect::<HashSet<_>>();
+    match_data.discard_pile.retain(|c| !ids.contains(&c.id));
+    match_data.history_summary.trimmed_discards = 0;
+    cards.shuffle(rng);
+    match_data.deck = cards;
+    match_data.deck.len()
+}
@@ -0,0 +1,303 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 爆炸猫模式
+//!
+//! 当前唯一的内置玩法：抽到爆炸猫且没有拆除卡的玩家出局，最后留在场上的玩家获胜。
+//! 烦人卡（Nope）可以在任何人的回合打出。
+//!
+//! 牌堆抽完时默认把弃牌洗回牌堆；规则为结束游戏或没有可以洗回的牌时，
+//! 拆除卡最多的玩家获胜，相同时比较手牌数，仍相同时座位靠前的玩家获胜。
+//!
+//! # 配置
+//! - `EXPLODING_KITTENS_DECK_EXHAUSTION`：牌堆抽完时的规则，`reshuffle`（默认）或 `end_game`
+
+use std::cmp::Reverse;
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use rand::seq::SliceRandom;
+use rand::RngCore;
+use serde_json::Value;
+
+use super::{redacted_view, DeckExhaustion, DeckPresetEntry, GameMode, PlayerAction};
+use crate::gaming::{Card, CardType, MatchData, MAX_MATCH_PLAYERS};
+
+/// 模式ID
+pub const MODE_ID: &str = "exploding_kittens";
+
+static DECK_EXHAUSTION: Lazy<DeckExhaustion> = Lazy::new(|| {
+    std::env::var("EXPLODING_KITTENS_DECK_EXHAUSTION")
+        .ok()
+        .and_then(|v| DeckExhaustion::from_str(&v))
+        .unwrap_or(DeckExhaustion::Reshuffle)
+});
+
+/// 爆炸猫
+pub struct ExplodingKittens;
+
+impl GameMode for ExplodingKittens {
+    fn id(&self) -> &'static str {
+        MODE_ID
+    }
+
+    fn player_range(&self) -> (usize, usize) {
+        (2, MAX_MATCH_PLAYERS)
+    }
+
+    fn build_deck(&self, player_count: usize, rng: &mut dyn RngCore) -> Vec<Card> {
+        generate_deck(player_count, rng)
+    }
+
+    fn deck_preset(&self) -> Vec<DeckPresetEntry> {
+        // 爆炸猫比玩家数少1张，每名玩家1张拆除卡，标准卡牌各4张
+        let mut preset = vec![
+            DeckPresetEntry { card_type: CardType::ExplodingKitten, base: -1, per_player: 1 },
+            DeckPresetEntry { card_type: CardType::Defuse, base: 0, per_player: 1 },
+        ];
+        preset.extend(STANDARD_CARDS.iter().map(|card_type| DeckPresetEntry {
+            card_type: card_type.clone(),
+            base: STANDARD_CARD_COUNT as i32,
+            per_player: 0,
+        }));
+        preset
+    }
+
+    fn deal(&self, match_data: &mut MatchData) {
+        distribute_cards(match_data)
+    }
+
+    fn validate_action(&self, match_data: &MatchData, user_id: &str, action: PlayerAction) -> Result<()> {
+        let player = match_data.players.iter()
+            .find(|p| p.user.id == user_id)
+            .ok_or_else(|| anyhow!("玩家不在游戏中"))?;
+
+        match action {
+            PlayerAction::Draw => {
+                if !player.is_turn {
+                    return Err(anyhow!("不是该玩家的回合"));
+                }
+                if match_data.deck.is_empty() {
+                    return Err(anyhow!("牌堆已空"));
+                }
+            }
+            PlayerAction::Play { card_id } => {
+                let card = player.hand.iter()
+                    .find(|c| c.id == card_id)
+                    .ok_or_else(|| anyhow!("卡牌不存在"))?;
+                // 烦人卡例外，任何人可以打出
+                if !player.is_turn && !matches!(card.card_type, CardType::Nope) {
+                    return Err(anyhow!("不是该玩家的回合"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn deck_exhaustion(&self) -> DeckExhaustion {
+        *DECK_EXHAUSTION
+    }
+
+    fn winner(&self, match_data: &MatchData) -> Option<String> {
+        match match_data.players.as_slice() {
+            [last] => Some(last.user.id.clone()),
+            players if match_data.deck_exhausted => {
+                // 拆除卡最多的玩家获胜，相同时比较手牌数，仍相同时座位靠前的玩家获胜
+                players.iter()
+                    .enumerate()
+                    .max_by_key(|(seat, p)| {
+                        let defuses = p.hand.iter().filter(|c| matches!(c.card_type, CardType::Defuse)).count();
+                        (defuses, p.hand.len(), Reverse(*seat))
+                    })
+                    .map(|(_, p)| p.user.id.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn public_view(&self, match_data: &MatchData) -> Result<Value> {
+        redacted_view(match_data, None)
+    }
+
+    fn private_view(&self, match_data: &MatchData, user_id: &str) -> Result<Value> {
+        redacted_view(match_data, Some(user_id))
+    }
+}
+
+/// 标准卡牌
+const STANDARD_CARDS: [CardType; 7] = [
+    CardType::Skip,
+    CardType::SeeTheFuture,
+    CardType::Shuffle,
+    CardType::Attack,
+    CardType::Favor,
+    CardType::Cat,
+    CardType::Nope,
+];
+
+/// 每种标准卡牌的张数
+const STANDARD_CARD_COUNT: usize = 4;
+
+/// 生成牌组
+fn generate_deck(player_count: usize, rng: &mut dyn RngCore) -> Vec<Card> {
+    let mut deck = Vec::new();
+    
+    // 添加爆炸猫卡（玩家数量-1）
+    for i in 0..player_count - 1 {
+        deck.push(Card {
+            id: format!("exploding-{}", i),
+            card_type: CardType::ExplodingKitten,
+            variant: None,
+        });
+    }
+    
+    // 每个玩家添加1张拆除卡
+    for i in 0..player_count {
+        deck.push(Card {
+            id: format!("defuse-{}", i),
+            card_type: CardType::Defuse,
+            variant: None,
+        });
+    }
+    
+    // 添加标准卡牌，每种4张
+    for (type_index, card_type) in STANDARD_CARDS.iter().enumerate() {
+        for i in 0..STANDARD_CARD_COUNT {
+            deck.push(Card {
+                id: format!("{}-{}", type_index, i),
+                card_type: card_type.clone(),
+                variant: None,
+            });
+        }
+    }
+    
+    // 洗牌
+    deck.shuffle(rng);
+    
+    deck
+}
+
+/// 发牌
+fn distribute_cards(match_data: &mut MatchData) {
+    const INITIAL_CARD_COUNT: usize = 4; // 每个玩家初始卡牌数
+    
+    for player in &mut match_data.players {
+        for _ in 0..INITIAL_CARD_COUNT {
+            if let Some(card) = match_data.deck.pop() {
+                player.hand.push(card);
+            }
+        }
+        
+        // 确保每个玩家有一张拆除卡
+        // 检查玩家是否已经有拆除卡
+        let has_defuse = player.hand.iter().any(|card| matches!(card.card_type, CardType::Defuse));
+        
+        if !has_defuse {
+            // 从牌堆找一张拆除卡
+            if let Some(pos) = match_data.deck.iter().position(|card| matches!(card.card_type, CardType::Defuse)) {
+                let defuse_card = match_data.deck.remove(pos);
+                player.hand.push(defuse_card);
+            } else {
+                // 如果牌堆中没有拆除卡，创建一张新的
+                player.hand.push(Card {
+                    id: format!("defuse-extra-{}", player.user.id),
+                    card_type: CardType::Defuse,
+                    variant: None,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample_match() -> MatchData {
+        let player = |id: &str, is_turn: bool, hand: Value| json!({
+            "user": { "id": id, "name": id, "rating": 1000 },
+            "hand": hand,
+            "is_active": true,
+            "is_winner": false,
+            "is_turn": is_turn,
+        });
+        serde_json::from_value(json!({
+            "id": "m1",
+            "type": "Private",
+            "state": "InProgress",
+            "players": [
+                player("alice", true, json!([{ "id": "a1", "type": "Skip", "variant": null }])),
+                player("bob", false, json!([
+                    { "id": "b1", "type": "Nope", "variant": null },
+                    { "id": "b2", "type": "Attack", "variant": null },
+                ])),
+            ],
+            "out": [],
+            "spectators": [],
+            "deck": [{ "id": "d1", "type": "Defuse", "variant": null }],
+            "discard_pile": [],
+            "turn_index": 0,
+            "created_at": 0,
+            "updated_at": 0,
+            "draw_count": 0,
+            "skip_votes": {},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validate_action() {
+        let mode = ExplodingKittens;
+        let mut match_data = sample_match();
+        assert_eq!(match_data.mode, MODE_ID);
+
+        assert!(mode.validate_action(&match_data, "alice", PlayerAction::Draw).is_ok());
+        assert!(mode.validate_action(&match_data, "alice", PlayerAction::Play { card_id: "a1" }).is_ok());
+        assert!(mode.validate_action(&match_data, "alice", PlayerAction::Play { card_id: "b1" }).is_err());
+        assert!(mode.validate_action(&match_data, "bob", PlayerAction::Draw).is_err());
+        // 烦人卡可以在别人的回合打出
+        assert!(mode.validate_action(&match_data, "bob", PlayerAction::Play { card_id: "b1" }).is_ok());
+        assert!(mode.validate_action(&match_data, "bob", PlayerAction::Play { card_id: "b2" }).is_err());
+        assert!(mode.validate_action(&match_data, "carol", PlayerAction::Draw).is_err());
+
+        match_data.deck.clear();
+        assert!(mode.validate_action(&match_data, "alice", PlayerAction::Draw).is_err());
+
+        assert_eq!(mode.winner(&match_data), None);
+        match_data.players.remove(0);
+        assert_eq!(mode.winner(&match_data), Some("bob".to_string()));
+    }
+
+    #[test]
+    fn test_views_hide_hidden_cards() {
+        let mode = ExplodingKittens;
+        let match_data = sample_match();
+
+        let public = mode.public_view(&match_data).unwrap();
+        assert_eq!(public["deck"], json!([]));
+        assert_eq!(public["deck_count"], 1);
+        assert_eq!(public["players"][1]["hand"], json!([]));
+        assert_eq!(public["players"][1]["hand_count"], 2);
+
+        let private = mode.private_view(&match_data, "bob").unwrap();
+        assert_eq!(private["players"][0]["hand"], json!([]));
+        assert_eq!(private["players"][0]["hand_count"], 1);
+        assert_eq!(private["players"][1]["hand"].as_array().unwrap().len(), 2);
+        assert_eq!(private["deck"], json!([]));
+    }
+
+    #[test]
+    fn test_deck_preset_matches_deck() {
+        let mode = ExplodingKittens;
+        for player_count in 2..=MAX_MATCH_PLAYERS {
+            let deck = mode.build_deck(player_count, &mut rand::thread_rng());
+            let preset = mode.deck_preset();
+            assert_eq!(preset.iter().map(|e| e.count(player_count)).sum::<usize>(), deck.len());
+            for entry in preset {
+                let count = deck.iter().filter(|c| c.card_type == entry.card_type).count();
+                assert_eq!(count, entry.count(player_count), "{:?}", entry.card_type);
+            }
+        }
+    }
+}
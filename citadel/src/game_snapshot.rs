@@ -0,0 +1,253 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 游戏数据快照模块
+//!
+//! # 概述
+//! 单实例部署且没有Redis时，GameService中的数据（进行中的游戏、好友关系、钱包等）只保存在内存中，
+//! 快速重启也会全部丢失。本模块定期将GameService的内容用bincode序列化写入快照文件，启动时从快照恢复：
+//! - 先写临时文件再重命名，进程中途退出不会留下不完整的快照
+//! - 快照带有格式版本和SHA-256校验和，校验失败的快照会被忽略
+//! - 超过最大年龄的快照不会被恢复，避免长时间停机后恢复过时的数据
+//! - 恢复时跳过已经过期的条目，固定的条目（进行中的游戏）恢复后仍然固定
+//!
+//! # 配置
+//! - `GAME_SNAPSHOT_DIR`：快照目录，未设置时不启用快照
+//! - `GAME_SNAPSHOT_INTERVAL_SECS`：快照间隔（秒），默认60
+//! - `GAME_SNAPSHOT_MAX_AGE_SECS`：可恢复快照的最大年龄（秒），默认900
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use crypto_hash::{hex_digest, Algorithm};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::externals::current_epoch_time;
+use crate::game::{CacheSnapshotEntry, GameService};
+
+/// 快照格式版本，格式不兼容时递增
+pub const SNAPSHOT_VERSION: u32 = 1;
+/// 默认快照间隔
+pub const DEFAULT_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+/// 默认可恢复快照的最大年龄
+pub const DEFAULT_SNAPSHOT_MAX_AGE: Duration = Duration::from_secs(15 * 60);
+
+/// 快照配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotConfig {
+    /// 快照目录
+    pub dir: PathBuf,
+    /// 快照间隔
+    pub interval: Duration,
+    /// 可恢复快照的最大年龄
+    pub max_age: Duration,
+}
+
+impl SnapshotConfig {
+    /// 从环境变量读取快照配置，未设置快照目录时返回None
+    pub fn from_env() -> Option<Self> {
+        let dir = std::env::var("GAME_SNAPSHOT_DIR").ok().filter(|v| !v.is_empty())?;
+        let secs = |key: &str| {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .filter(|v| *v > 0)
+                .map(Duration::from_secs)
+        };
+        Some(Self {
+            dir: PathBuf::from(dir),
+            interval: secs("GAME_SNAPSHOT_INTERVAL_SECS").unwrap_or(DEFAULT_SNAPSHOT_INTERVAL),
+            max_age: secs("GAME_SNAPSHOT_MAX_AGE_SECS").unwrap_or(DEFAULT_SNAPSHOT_MAX_AGE),
+        })
+    }
+
+    /// 指定名称的快照文件路径
+    pub fn path_for(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.snapshot", name))
+    }
+}
+
+/// 快照文件内容
+#[derive(Debug, Serialize, Deserialize)]
+struct SnapshotFile {
+    version: u32,
+    created_at: u64,
+    /// payload的SHA-256
+    checksum: String,
+    /// bincode序列化的快照条目
+    payload: Vec<u8>,
+}
+
+/**
+ * 将GameService的内容写入快照文件
+ *
+ * 参数:
+ * @param service - 游戏服务
+ * @param path - 快照文件路径
+ *
+ * 返回:
+ * 写入的条目数
+ */
+pub fn write_snapshot(service: &GameService, path: &Path) -> Result<usize> {
+    let entries = service.export_entries();
+    let payload = bincode::serialize(&entries).context("序列化快照条目失败")?;
+    let file = SnapshotFile {
+        version: SNAPSHOT_VERSION,
+        created_at: current_epoch_time(),
+        checksum: hex_digest(Algorithm::SHA256, &payload),
+        payload,
+    };
+    let bytes = bincode::serialize(&file).context("序列化快照文件失败")?;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("创建快照目录 {} 失败", dir.display()))?;
+    }
+    let tmp_path = path.with_extension("snapshot.tmp");
+    std::fs::write(&tmp_path, bytes).with_context(|| format!("写入快照 {} 失败", tmp_path.display()))?;
+    std::fs::rename(&tmp_path, path).with_context(|| format!("替换快照 {} 失败", path.display()))?;
+    Ok(entries.len())
+}
+
+/**
+ * 读取并校验快照文件
+ *
+ * 参数:
+ * @param path - 快照文件路径
+ * @param max_age - 可恢复快照的最大年龄
+ *
+ * 返回:
+ * 快照条目；文件不存在返回None，版本、校验和或年龄不符合要求返回错误
+ */
+pub fn read_snapshot(path: &Path, max_age: Duration) -> Result<Option<Vec<CacheSnapshotEntry<String, String>>>> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(anyhow!("读取快照 {} 失败: {}", path.display(), e)),
+    };
+    let file: SnapshotFile = bincode::deserialize(&bytes).context("快照文件格式错误")?;
+    if file.version != SNAPSHOT_VERSION {
+        return Err(anyhow!("快照版本 {} 不受支持，当前版本 {}", file.version, SNAPSHOT_VERSION));
+    }
+    if hex_digest(Algorithm::SHA256, &file.payload) != file.checksum {
+        return Err(anyhow!("快照校验和不匹配"));
+    }
+    let age = current_epoch_time().saturating_sub(file.created_at);
+    if age > max_age.as_millis() as u64 {
+        return Err(anyhow!("快照已过时（{} 秒前创建）", age / 1000));
+    }
+    let entries = bincode::deserialize(&file.payload).context("快照条目格式错误")?;
+    Ok(Some(entries))
+}
+
+/**
+ * 从快照恢复GameService的内容
+ *
+ * 参数:
+ * @param service - 游戏服务
+ * @param path - 快照文件路径
+ * @param max_age - 可恢复快照的最大年龄
+ *
+ * 返回:
+ * 恢复的条目数
+ */
+pub fn restore_snapshot(service: &GameService, path: &Path, max_age: Duration) -> Result<usize> {
+    match read_snapshot(path, max_age)? {
+        Some(entries) => Ok(service.import_entries(entries)),
+        None => Ok(0),
+    }
+}
+
+/**
+ * 为GameService启用快照：立即从快照恢复，然后定期写入快照
+ *
+ * 快照无效时只记录警告，从空数据启动
+ *
+ * 参数:
+ * @param service - 游戏服务
+ * @param name - 快照名称，同一目录下的不同服务需要使用不同名称
+ * @param config - 快照配置
+ */
+pub fn enable_snapshots(service: Arc<GameService>, name: &'static str, config: &SnapshotConfig) {
+    let path = config.path_for(name);
+    match restore_snapshot(&service, &path, config.max_age) {
+        Ok(0) => info!("没有可恢复的 {} 快照", name),
+        Ok(count) => info!("从快照 {} 恢复了 {} 条数据", path.display(), count),
+        Err(e) => warn!("忽略无效的 {} 快照: {}", name, e),
+    }
+
+    let interval = config.interval;
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // 第一次tick立即完成，跳过
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let service = service.clone();
+            let path = path.clone();
+            match tokio::task::spawn_blocking(move || write_snapshot(&service, &path)).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => error!("写入 {} 快照失败: {}", name, e),
+                Err(e) => error!("快照任务异常: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameCachePrefix;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("game_snapshot_{}", std::process::id()))
+            .join(format!("{}.snapshot", name))
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let path = temp_path("roundtrip");
+        let service = GameService::new();
+        service.set(GameCachePrefix::MATCH, "m1", &"active");
+        service.set(GameCachePrefix::STATS, "u1", &3);
+        assert!(service.pin(GameCachePrefix::MATCH, "m1"));
+        assert_eq!(write_snapshot(&service, &path).unwrap(), 2);
+
+        let restored = GameService::new();
+        assert_eq!(restore_snapshot(&restored, &path, DEFAULT_SNAPSHOT_MAX_AGE).unwrap(), 2);
+        assert_eq!(restored.get::<String>(GameCachePrefix::MATCH, "m1"), Some("active".to_string()));
+        assert_eq!(restored.get::<i32>(GameCachePrefix::STATS, "u1"), Some(3));
+        // 进行中的对局和不过期的统计数据都是固定的
+        assert_eq!(restored.cache_metrics().pinned, 2);
+
+        // 已存在的数据不会被快照覆盖
+        assert_eq!(restore_snapshot(&restored, &path, DEFAULT_SNAPSHOT_MAX_AGE).unwrap(), 0);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_snapshot_integrity_and_age() {
+        let path = temp_path("integrity");
+        let service = GameService::new();
+        service.set(GameCachePrefix::USER, "u1", &"friend");
+        write_snapshot(&service, &path).unwrap();
+
+        // 超过最大年龄的快照不会被恢复
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(read_snapshot(&path, Duration::ZERO).is_err());
+        assert!(read_snapshot(&path, DEFAULT_SNAPSHOT_MAX_AGE).unwrap().is_some());
+
+        // 校验和不匹配
+
+        let mut file: SnapshotFile = bincode::deserialize(&std::fs::read(&path).unwrap()).unwrap();
+        file.payload[0] ^= 0xff;
+        std::fs::write(&path, bincode::serialize(&file).unwrap()).unwrap();
+        assert!(read_snapshot(&path, DEFAULT_SNAPSHOT_MAX_AGE).is_err());
+
+        let _ = std::fs::remove_file(&path);
+        assert!(read_snapshot(&path, DEFAULT_SNAPSHOT_MAX_AGE).unwrap().is_none());
+    }
+}
@@ -1,19 +1,48 @@
 // Copyright (c), Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::account_link;
+use crate::action_profile::{ActionProfile, ActionStep};
+use crate::ban::{self, BanError};
+use crate::achievement::{AchievementMetric, AchievementService};
+use crate::economy::EconomyService;
+use crate::event_bus::{self, DomainEvent};
+use crate::event_signing;
 use crate::game::{GameCache, GameCachePrefix, GameService};
+use crate::game_mode::{self, PlayerAction};
+use crate::geo;
+use crate::hand_limit;
+use crate::hand_seal;
+use crate::i18n::{self, codes, Locale, LocalizedText};
+use crate::latency::LatencyBucket;
+use crate::match_attestation;
+use crate::match_delta::MatchDeltaTracker;
+use crate::match_history::{self, HistorySummary};
+use crate::match_log;
+use crate::match_replay::{self, ReplaySeed};
+use crate::shuffle_proof::{self, ShuffleProof};
+use crate::time_bank::{self, TimeBanks};
+use crate::match_timers::{MatchTimerManager, TimerKind};
+use crate::party::{self, QueueEntry};
+use crate::penalty::PenaltyService;
+use crate::protocol::capabilities;
+use crate::rating::{self, RatingContext};
+use crate::series::{self, SeriesData, SeriesState};
+use crate::stats::{RatingChange, StatsService};
+use crate::storage;
+use crate::tasks::{self, RestartPolicy};
 use crate::ws::{ClientId, ConnectionManager, RoomId, WsMessage, WsResponse};
+use crate::ws_acl::{JoinPolicy, RoomOwner};
 use crate::tool::elo::{self, MatchOutcome}; // 导入 ELO 评分系统
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio::time::{sleep, Duration};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
 
 /// 匹配类型枚举
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -31,6 +60,8 @@ pub enum MatchState {
     Waiting,
     /// 进行中
     InProgress,
+    /// 已暂停，计时器冻结，玩家动作排队等待恢复
+    Paused,
     /// 已完成
     Completed,
 }
@@ -84,10 +115,34 @@ pub struct UserInfo {
     pub rating: i32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub avatar_url: Option<String>,
+    /// 评分是否为临时评分（未完成定级赛）
+    #[serde(default)]
+    pub provisional: bool,
+}
+
+impl UserInfo {
+    /// 创建玩家信息，评分取最近一局结束后的评分
+    pub fn with_rating(id: String, name: String, avatar_url: Option<String>) -> Self {
+        let stats_service = crate::stats::global_stats_service();
+        let rating = stats_service.as_ref()
+            .and_then(|stats| stats.get_rating_history(&id).last().map(|e| e.rating_after))
+            .unwrap_or(INITIAL_RATING);
+        let games_played = stats_service.as_ref()
+            .and_then(|stats| stats.get_stats(&id))
+            .map(|s| s.played)
+            .unwrap_or(0);
+        Self {
+            id,
+            name,
+            rating,
+            avatar_url,
+            provisional: elo::is_provisional(games_played),
+        }
+    }
 }
 
 /// 卡牌类型
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum CardType {
     /// 爆炸猫
     ExplodingKitten,
@@ -119,6 +174,26 @@ pub enum CardType {
     SpeedUpExplosion,
 }
 
+impl CardType {
+    /// 所有卡牌类型
+    pub const ALL: [CardType; 14] = [
+        CardType::ExplodingKitten,
+        CardType::Defuse,
+        CardType::Skip,
+        CardType::SeeTheFuture,
+        CardType::Shuffle,
+        CardType::Attack,
+        CardType::Favor,
+        CardType::Cat,
+        CardType::Nope,
+        CardType::ImplodingKitten,
+        CardType::AlterTheFuture,
+        CardType::ShareTheFuture,
+        CardType::BuryCard,
+        CardType::SpeedUpExplosion,
+    ];
+}
+
 /// 卡牌信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Card {
@@ -149,6 +224,10 @@ pub enum CardActionType {
     Nope,
     /// 使用拆除卡
     Defuse,
+    /// 因离开或超时出局（抽到爆炸猫出局由抽卡动作体现）
+    Eliminate,
+    /// 手牌超过上限时弃牌，每张牌一个动作
+    Discard,
 }
 
 /// 卡牌动作
@@ -169,6 +248,236 @@ pub struct CardAction {
     pub created_at: u64,
 }
 
+impl CardAction {
+    /// 玩家因离开或超时出局的动作
+    pub fn eliminate(user_id: &str) -> Self {
+        Self {
+            action_type: CardActionType::Eliminate,
+            user_id: user_id.to_string(),
+            card_id: None,
+            card_type: None,
+            is_canceled: false,
+            created_at: chrono::Utc::now().timestamp_millis() as u64,
+        }
+    }
+
+    /// 玩家因手牌超过上限弃掉一张牌的动作
+    pub fn discard(user_id: &str, card: &Card) -> Self {
+        Self {
+            action_type: CardActionType::Discard,
+            user_id: user_id.to_string(),
+            card_id: Some(card.id.clone()),
+            card_type: Some(card.card_type.clone()),
+            is_canceled: false,
+            created_at: chrono::Utc::now().timestamp_millis() as u64,
+        }
+    }
+}
+
+/// 每局游戏的最大玩家数
+pub const MAX_MATCH_PLAYERS: usize = 4;
+/// 新玩家的初始评分，与链上Profile的初始评分一致
+pub const INITIAL_RATING: i32 = 1000;
+/// 游戏邀请有效期（毫秒）
+pub const MATCH_INVITE_TTL_MS: u64 = 5 * 60 * 1000;
+/// 暂停投票有效期（毫秒）
+pub const PAUSE_VOTE_TTL_MS: u64 = 30 * 1000;
+/// 单次暂停的最长时间（毫秒），到期后自动恢复
+pub const MAX_PAUSE_DURATION_MS: u64 = 5 * 60 * 1000;
+/// 当前回合玩家至少未操作这么久（毫秒）后，其他玩家才可以投票跳过
+pub const SKIP_VOTE_MIN_IDLE_MS: u64 = queue_constants::inactivity::COMMON / 2;
+/// 出牌玩家选择目标的等待时间（毫秒），超时后随机选择
+pub const SELECT_TARGET_TIMEOUT_MS: u64 = 15 * 1000;
+/// 匹配队列在缓存中的键
+const QUEUE_CACHE_KEY: &str = "entries";
+
+/// 默认观战人数上限
+pub const DEFAULT_MAX_SPECTATORS: usize = 20;
+/// 可配置的观战人数上限
+pub const MAX_SPECTATORS_LIMIT: usize = 100;
+
+/// 观战权限
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SpectatorPolicy {
+    /// 任何人都可以观战
+    #[default]
+    Open,
+    /// 只有玩家的好友可以观战
+    FriendsOnly,
+    /// 禁止观战
+    Disabled,
+}
+
+impl SpectatorPolicy {
+    /// 不同类型游戏的默认观战权限
+    pub fn default_for(match_type: &MatchType) -> Self {
+        match match_type {
+            MatchType::Public => SpectatorPolicy::Open,
+            MatchType::Private => SpectatorPolicy::FriendsOnly,
+        }
+    }
+    
+    /// 从字符串解析观战权限
+    pub fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "open" => Some(SpectatorPolicy::Open),
+            "friends_only" | "friends" => Some(SpectatorPolicy::FriendsOnly),
+            "disabled" => Some(SpectatorPolicy::Disabled),
+            _ => None,
+        }
+    }
+}
+
+/// 拒绝观战的原因
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SpectateDenial {
+    /// 游戏禁止观战
+    Disabled,
+    /// 只有玩家的好友可以观战
+    FriendsOnly,
+    /// 观战人数已满
+    Full,
+    /// 用户是本局玩家
+    AlreadyPlaying,
+    /// 用户已经在观战
+    AlreadySpectating,
+}
+
+impl SpectateDenial {
+    /// 消息代码
+    pub fn code(&self) -> &'static str {
+        match self {
+            SpectateDenial::Disabled => codes::SPECTATE_DISABLED,
+            SpectateDenial::FriendsOnly => codes::SPECTATE_FRIENDS_ONLY,
+            SpectateDenial::Full => codes::SPECTATE_FULL,
+            SpectateDenial::AlreadyPlaying => codes::SPECTATE_ALREADY_PLAYING,
+            SpectateDenial::AlreadySpectating => codes::SPECTATE_ALREADY_SPECTATING,
+        }
+    }
+}
+
+impl std::fmt::Display for SpectateDenial {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", i18n::text(self.code(), &[]).render(Locale::default()))
+    }
+}
+
+impl std::error::Error for SpectateDenial {}
+
+/// 暂停状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PauseState {
+    /// 发起暂停的玩家ID
+    pub requested_by: String,
+    /// 发起时间
+    pub requested_at: u64,
+    /// 玩家投票，true表示同意
+    pub votes: HashMap<String, bool>,
+    /// 暂停开始时间，投票通过前为空
+    pub paused_at: Option<u64>,
+    /// 自动恢复时间
+    pub resume_deadline: Option<u64>,
+}
+
+impl PauseState {
+    /// 暂停是否已生效
+    pub fn is_active(&self) -> bool {
+        self.paused_at.is_some()
+    }
+}
+
+/// 等待出牌玩家选择目标的卡牌效果
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PendingActionKind {
+    /// 与选择的玩家分享未来三张牌
+    ShareTheFuture,
+    /// 回合结束时手牌超过上限，弃掉多出的牌
+    DiscardDown,
+}
+
+/// 等待出牌玩家选择目标的动作
+///
+/// 出牌玩家通过 `match:select_target` 从候选玩家中选择目标，超时后从仍在游戏中的候选玩家里随机选择。
+/// 手牌超过上限时玩家通过 `match:discard` 从候选手牌中选择弃掉的牌，超时后随机弃牌，见 `hand_limit`。
+/// 等待期间回合计时暂停，所有玩家不能抽牌或出牌。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingAction {
+    pub id: String,
+    pub kind: PendingActionKind,
+    /// 出牌玩家ID
+    pub user_id: String,
+    /// 打出的卡牌ID，弃牌时为空
+    pub card_id: String,
+    /// 可以选择的玩家ID，弃牌时为空（从当前手牌中选择，避免通过对局数据泄露手牌）
+    pub candidates: Vec<String>,
+    /// 需要弃掉的张数，只用于弃牌
+    #[serde(default)]
+    pub count: usize,
+    pub created_at: u64,
+    /// 超时时间，暂停期间顺延
+    pub deadline: u64,
+}
+
+/// 对局计时器记录
+///
+/// 计时任务启动时记录到期时间，到期后只有记录未变化时才执行。
+/// 暂停时清除到期时间并保存剩余时间，旧的计时任务因此失效，恢复时按剩余时间重新计时。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchTimers {
+    /// 当前回合开始时间，不包括暂停的时间
+    pub turn_started_at: Option<u64>,
+    /// 回合超时的玩家ID
+    pub turn_user_id: Option<String>,
+    /// 回合超时时间
+    pub turn_deadline: Option<u64>,
+    /// 连锁结束时间
+    pub chain_deadline: Option<u64>,
+    /// 暂停时回合超时的剩余时间（毫秒）
+    pub frozen_turn_ms: Option<u64>,
+    /// 暂停时连锁等待的剩余时间（毫秒）
+    pub frozen_chain_ms: Option<u64>,
+    /// 连锁等待期间暂停的回合剩余时间（毫秒），连锁结束后继续计时
+    pub suspended_turn_ms: Option<u64>,
+    /// 本回合给予高延迟玩家的宽限时间（毫秒），每回合只给一次
+    #[serde(default)]
+    pub turn_grace_ms: Option<u64>,
+}
+
+impl MatchTimers {
+    /// 冻结所有计时器，记录剩余时间
+    pub fn freeze(&mut self, now: u64) {
+        self.frozen_turn_ms = self.turn_deadline.take().map(|d| d.saturating_sub(now));
+        self.frozen_chain_ms = self.chain_deadline.take().map(|d| d.saturating_sub(now));
+    }
+}
+
+/// 已结束的回合记录，用于统计平均回合时间
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TurnRecord {
+    pub user_id: String,
+    pub started_at: u64,
+    /// 回合时长（毫秒），不包括暂停的时间
+    pub duration_ms: u64,
+}
+
+/// 暂停期间排队的玩家动作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedAction {
+    pub user_id: String,
+    /// 动作类型，只会是Draw或Play
+    pub action_type: CardActionType,
+    pub card_id: Option<String>,
+    pub queued_at: u64,
+}
+
 /// 游戏房间数据
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchData {
@@ -185,16 +494,264 @@ pub struct MatchData {
     pub created_at: u64,
     pub updated_at: u64,
     pub draw_count: usize,
+    /// 跳过当前回合玩家的投票，键为投票玩家ID，每次切换回合时清空
     pub skip_votes: HashMap<String, bool>,
-    /// 动作历史记录
+    /// 动作历史记录，较早的已结算连锁会被归档，见 `match_history`
     #[serde(default)]
     pub action_history: Vec<CardAction>,
+    /// 已归档动作和已移除弃牌的汇总
+    #[serde(default)]
+    pub history_summary: HistorySummary,
     /// 当前连锁状态（如果非空，表示有连锁效果在等待反应）
     #[serde(default)]
     pub chain_state: Option<CardAction>,
     /// 连锁响应等待时间（毫秒）
     #[serde(default = "default_chain_wait_time")]
     pub chain_wait_time: u64,
+    /// 入场押注金额（0表示无押注）
+    #[serde(default)]
+    pub wager: u64,
+    /// 奖池，已托管的押注总额
+    #[serde(default)]
+    pub pot: u64,
+    /// 已托管押注的玩家ID
+    #[serde(default)]
+    pub staked: Vec<String>,
+    /// 观战权限
+    #[serde(default)]
+    pub spectator_policy: SpectatorPolicy,
+    /// 观战人数上限
+    #[serde(default = "default_max_spectators")]
+    pub max_spectators: usize,
+    /// 暂停投票或暂停状态
+    #[serde(default)]
+    pub pause: Option<PauseState>,
+    /// 计时器记录
+    #[serde(default)]
+    pub timers: MatchTimers,
+    /// 暂停期间排队的动作，恢复后按顺序执行
+    #[serde(default)]
+    pub queued_actions: Vec<QueuedAction>,
+    /// 已结束的回合
+    #[serde(default)]
+    pub turn_log: Vec<TurnRecord>,
+    /// 中途退出的玩家
+    #[serde(default)]
+    pub abandoned: Vec<String>,
+    /// 等待出牌玩家选择目标的动作
+    #[serde(default)]
+    pub pending_action: Option<PendingAction>,
+    /// 游戏模式ID
+    #[serde(default = "game_mode::default_mode_id")]
+    pub mode: String,
+    /// 随机种子和初始座位，用于重放验证
+    #[serde(default)]
+    pub replay_seed: Option<ReplaySeed>,
+    /// 开局牌堆顺序的承诺，对局结束后公开
+    #[serde(default)]
+    pub shuffle_proof: Option<ShuffleProof>,
+    /// 玩家的回合时间储备
+    #[serde(default)]
+    pub time_banks: TimeBanks,
+    /// 所属的系列赛ID，单局游戏为None
+    #[serde(default)]
+    pub series_id: Option<String>,
+    /// 手牌上限，0表示不限制
+    #[serde(default)]
+    pub max_hand_size: usize,
+    /// 牌堆抽完且无法补充，游戏按模式规则计分结束
+    #[serde(default)]
+    pub deck_exhausted: bool,
+}
+
+impl MatchData {
+    /**
+     * 创建等待开始的游戏数据
+     *
+     * 参数:
+     * @param id - 游戏ID
+     * @param match_type - 游戏类型
+     * @param players - 初始玩家
+     * @param now - 创建时间（毫秒）
+     *
+     * 返回:
+     * 使用默认游戏模式、尚未发牌的游戏数据
+     */
+    pub fn new(id: String, match_type: MatchType, players: &[UserInfo], now: u64) -> Self {
+        let players = players.iter().map(|user| {
+            MatchPlayer {
+                user: user.clone(),
+                hand: Vec::new(),
+                is_active: true,
+                is_winner: false,
+                is_turn: false,
+            }
+        }).collect::<Vec<_>>();
+        let spectator_policy = SpectatorPolicy::default_for(&match_type);
+        
+        MatchData {
+            id,
+            match_type,
+            state: MatchState::Waiting,
+            players,
+            out: Vec::new(),
+            spectators: Vec::new(),
+            deck: Vec::new(), // 初始化空牌组，实际游戏开始前会生成
+            discard_pile: Vec::new(),
+            turn_index: 0,
+            created_at: now,
+            updated_at: now,
+            draw_count: 0,
+            skip_votes: HashMap::new(),
+            action_history: Vec::new(),
+            history_summary: HistorySummary::default(),
+            chain_state: None,
+            chain_wait_time: default_chain_wait_time(),
+            wager: 0,
+            pot: 0,
+            staked: Vec::new(),
+            spectator_policy,
+            max_spectators: DEFAULT_MAX_SPECTATORS,
+            pause: None,
+            timers: MatchTimers::default(),
+            queued_actions: Vec::new(),
+            turn_log: Vec::new(),
+            abandoned: Vec::new(),
+            pending_action: None,
+            mode: game_mode::default_mode_id(),
+            replay_seed: None,
+            shuffle_proof: None,
+            time_banks: TimeBanks::default(),
+            series_id: None,
+            max_hand_size: hand_limit::default_max_hand_size(),
+            deck_exhausted: false,
+        }
+    }
+    
+    /**
+     * 检查玩家当前能否打出指定卡牌，不修改游戏数据
+     *
+     * 出牌和 `match:can_play` 查询使用同一套校验：游戏状态、连锁和目标选择、回合和手牌。
+     *
+     * 参数:
+     * @param user_id - 玩家ID
+     * @param card_id - 卡牌ID
+     *
+     * 返回:
+     * 可以出牌返回Ok，否则返回原因
+     */
+    pub fn validate_play(&self, user_id: &str, card_id: &str) -> Result<()> {
+        // 检查游戏状态
+        if self.state != MatchState::InProgress {
+            return Err(anyhow::anyhow!("游戏未开始或已结束"));
+        }
+        
+        // 检查是否有连锁状态正在处理
+        if self.chain_state.is_some() {
+            return Err(anyhow::anyhow!("有连锁效果正在处理中，请稍后再试"));
+        }
+        
+        // 检查是否有玩家正在选择目标
+        if self.pending_action.is_some() {
+            return Err(anyhow::anyhow!("有玩家正在选择目标，请稍后再试"));
+        }
+        
+        // 按游戏模式规则校验出牌（玩家、回合和手牌）
+        game_mode::mode_for(self).validate_action(self, user_id, PlayerAction::Play { card_id })?;
+        
+        // 烦人卡只能取消连锁中的动作
+        let is_nope = self.players.iter()
+            .find(|p| p.user.id == user_id)
+            .and_then(|p| p.hand.iter().find(|c| c.id == card_id))
+            .is_some_and(|c| matches!(c.card_type, CardType::Nope));
+        if is_nope && self.chain_state.is_none() {
+            return Err(anyhow::anyhow!("没有可以取消的操作"));
+        }
+        
+        Ok(())
+    }
+    
+    /**
+     * 检查用户是否可以观战
+     *
+     * 参数:
+     * @param user_id - 用户ID
+     * @param is_friend - 用户是否为任一玩家的好友
+     *
+     * 返回:
+     * 可以观战返回Ok，否则返回拒绝原因
+     */
+    pub fn check_spectate(&self, user_id: &str, is_friend: bool) -> Result<(), SpectateDenial> {
+        if self.players.iter().chain(self.out.iter()).any(|p| p.user.id == user_id) {
+            return Err(SpectateDenial::AlreadyPlaying);
+        }
+        if self.spectators.iter().any(|s| s.id == user_id) {
+            return Err(SpectateDenial::AlreadySpectating);
+        }
+        match self.spectator_policy {
+            SpectatorPolicy::Disabled => return Err(SpectateDenial::Disabled),
+            SpectatorPolicy::FriendsOnly if !is_friend => return Err(SpectateDenial::FriendsOnly),
+            _ => {}
+        }
+        if self.spectators.len() >= self.max_spectators {
+            return Err(SpectateDenial::Full);
+        }
+        Ok(())
+    }
+    
+    /**
+     * 统计暂停投票结果
+     *
+     * 返回:
+     * 有玩家反对返回Some(false)，所有仍在游戏中的玩家都同意返回Some(true)，
+     * 没有暂停投票或投票未结束返回None
+     */
+    pub fn pause_vote_result(&self) -> Option<bool> {
+        let pause = self.pause.as_ref()?;
+        if pause.votes.values().any(|accept| !accept) {
+            return Some(false);
+        }
+        let all_accepted = self.players.iter()
+            .all(|p| pause.votes.get(&p.user.id).copied().unwrap_or(false));
+        if all_accepted {
+            Some(true)
+        } else {
+            None
+        }
+    }
+    
+    /**
+     * 统计跳过当前回合玩家的投票进度
+     *
+     * 只统计仍在游戏中的其他玩家的同意票，需要其他玩家中的多数同意。
+     *
+     * 返回:
+     * (同意票数, 需要的票数)
+     */
+    pub fn skip_vote_progress(&self) -> (usize, usize) {
+        let target_id = self.players.get(self.turn_index).map(|p| p.user.id.as_str());
+        let voters = self.players.iter()
+            .filter(|p| Some(p.user.id.as_str()) != target_id)
+            .collect::<Vec<_>>();
+        let votes = voters.iter()
+            .filter(|p| self.skip_votes.get(&p.user.id).copied().unwrap_or(false))
+            .count();
+        (votes, voters.len() / 2 + 1)
+    }
+}
+
+/// 游戏邀请
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchInvite {
+    pub id: String,
+    pub match_id: String,
+    pub inviter: UserInfo,
+    pub invitee_id: String,
+    /// 入场押注金额
+    pub wager: u64,
+    pub created_at: u64,
+    pub expires_at: u64,
 }
 
 /// 卡牌动作队列载荷
@@ -223,12 +780,15 @@ pub mod events {
     
     /// 匹配事件
     pub mod match_events {
+        pub const CREATE: &str = "match:create";
+        pub const INVITE: &str = "match:invite";
         pub const JOIN: &str = "match:join";
         pub const LEAVE: &str = "match:leave";
         pub const START: &str = "match:start";
         pub const END: &str = "match:end";
         pub const DRAW_CARD: &str = "match:draw_card";
         pub const PLAY_CARD: &str = "match:play_card";
+        pub const CAN_PLAY: &str = "match:can_play";
         pub const TURN_CHANGE: &str = "match:turn_change";
         pub const DEFUSE: &str = "match:defuse";
         pub const INSERT_EXPLODING_KITTEN: &str = "match:insert_exploding_kitten";
@@ -241,6 +801,15 @@ pub mod events {
         pub const INSERT_IMPLODING_KITTEN: &str = "match:insert_imploding_kitten";
         pub const JOIN_SPECTATORS: &str = "match:join_spectators";
         pub const LEAVE_SPECTATORS: &str = "match:leave_spectators";
+        pub const STATE: &str = "match:state";
+        pub const STATE_SYNC: &str = "match:state_sync";
+        pub const PAUSE: &str = "match:pause";
+        pub const RESUME: &str = "match:resume";
+        pub const VOTE_SKIP: &str = "match:vote_skip";
+        pub const SELECT_TARGET: &str = "match:select_target";
+        pub const DISCARD: &str = "match:discard";
+        pub const DECK_EXHAUSTED: &str = "match:deck_exhausted";
+        pub const HAND: &str = "match:hand";
     }
 }
 
@@ -250,10 +819,26 @@ pub struct MatchService {
     game_service: Arc<GameService>,
     /// WebSocket连接管理器
     connection_manager: Arc<ConnectionManager>,
+    /// 成就服务
+    achievement_service: Arc<AchievementService>,
+    /// 经济服务
+    economy_service: Arc<EconomyService>,
+    /// 玩家统计服务
+    stats_service: Arc<StatsService>,
+    /// 中途退出惩罚服务
+    penalty_service: Arc<PenaltyService>,
     /// 活跃的游戏匹配
     active_matches: Arc<RwLock<HashMap<String, String>>>,
-    /// 游戏队列
-    queue: Arc<RwLock<Vec<UserInfo>>>,
+    /// 游戏队列，队伍作为一项整体排队
+    queue: Arc<RwLock<Vec<QueueEntry>>>,
+    /// 重启后恢复排队、尚未收到通知的玩家
+    restored_queue: Arc<parking_lot::Mutex<HashSet<String>>>,
+    /// 对局状态增量跟踪
+    state_tracker: Arc<parking_lot::Mutex<MatchDeltaTracker>>,
+    /// 待处理的游戏邀请
+    invites: Arc<RwLock<HashMap<String, MatchInvite>>>,
+    /// 回合与连锁计时器
+    timer_manager: Arc<MatchTimerManager>,
 }
 
 impl MatchService {
@@ -261,22 +846,122 @@ impl MatchService {
     pub fn new(
         game_service: Arc<GameService>,
         connection_manager: Arc<ConnectionManager>,
+        achievement_service: Arc<AchievementService>,
+        economy_service: Arc<EconomyService>,
+        stats_service: Arc<StatsService>,
+        penalty_service: Arc<PenaltyService>,
     ) -> Self {
         Self {
             game_service,
             connection_manager,
+            achievement_service,
+            economy_service,
+            stats_service,
+            penalty_service,
             active_matches: Arc::new(RwLock::new(HashMap::new())),
             queue: Arc::new(RwLock::new(Vec::new())),
+            restored_queue: Arc::new(parking_lot::Mutex::new(HashSet::new())),
+            state_tracker: Arc::new(parking_lot::Mutex::new(MatchDeltaTracker::new())),
+            invites: Arc::new(RwLock::new(HashMap::new())),
+            timer_manager: Arc::new(MatchTimerManager::new()),
         }
     }
     
     /// 获取游戏
     pub async fn get_match(&self, match_id: &str) -> Option<MatchData> {
-        self.game_service.get(GameCachePrefix::MATCH, match_id)
+        if let Some(match_data) = self.game_service.get(GameCachePrefix::MATCH, match_id) {
+            return Some(match_data);
+        }
+        // 缓存中丢失的进行中对局从事件日志重建
+        match self.rebuild_match(match_id).await {
+            Ok(rebuilt) => rebuilt,
+            Err(e) => {
+                error!("重建游戏 {} 失败: {}", match_id, e);
+                None
+            }
+        }
+    }
+    
+    /**
+     * 从事件日志重建对局并重新写入缓存
+     *
+     * 参数:
+     * @param match_id - 对局ID
+     *
+     * 返回:
+     * 重建的对局数据，没有事件日志时返回None
+     */
+    pub async fn rebuild_match(&self, match_id: &str) -> Result<Option<MatchData>> {
+        let Some(storage) = storage::global_storage() else {
+            return Ok(None);
+        };
+        let Some(rebuilt) = match_log::rebuild(storage.as_ref(), match_id).await? else {
+            return Ok(None);
+        };
+        if !self.save_match(&rebuilt).await {
+            return Err(anyhow::anyhow!("保存重建的游戏数据失败"));
+        }
+        info!("游戏 {} 的缓存已丢失，从事件日志重建，动作 {} 个", match_id, rebuilt.action_history.len());
+        Ok(Some(rebuilt))
+    }
+    
+    /**
+     * 获取带完整动作历史的游戏
+     *
+     * 对局数据中较早的动作会被归档，需要完整历史时（时间线、统计）使用本方法
+     */
+    pub async fn get_match_with_history(&self, match_id: &str) -> Option<MatchData> {
+        match self.get_match(match_id).await {
+            Some(match_data) => Some(self.with_full_history(match_data)),
+            // 已结束的对局从缓存过期后，从持久化存储读取
+            None => storage::load_match(match_id).await,
+        }
+    }
+    
+    /// 拼接已归档的动作，恢复完整的动作历史
+    fn with_full_history(&self, mut match_data: MatchData) -> MatchData {
+        if match_data.history_summary.archived_actions > 0 {
+            let archive = match_history::load_archive(&self.game_service, &match_data.id);
+            match_history::restore(&mut match_data, archive);
+        }
+        match_data
+    }
+    
+    /**
+     * 压缩超过上限的动作历史和弃牌堆，移出的动作追加到归档
+     *
+     * 返回:
+     * 压缩后的游戏数据，不需要压缩时返回None
+     */
+    fn compact_match(&self, match_data: &MatchData) -> Option<MatchData> {
+        let config = match_history::config();
+        if !config.needs_compaction(match_data) {
+            return None;
+        }
+        
+        let mut compacted = match_data.clone();
+        let start = compacted.history_summary.archived_actions;
+        let archived = match_history::compact(&mut compacted, config);
+        if !archived.is_empty() {
+            if !match_history::append_archive(&self.game_service, &compacted.id, start, &archived) {
+                warn!("游戏 {} 的动作历史归档失败，跳过压缩", compacted.id);
+                return None;
+            }
+            debug!("游戏 {} 归档动作 {} 条", compacted.id, archived.len());
+        }
+        Some(compacted)
     }
     
     /// 保存游戏
     pub async fn save_match(&self, match_data: &MatchData) -> bool {
+        // 压缩前把新增的动作追加到事件日志，缓存丢失时可以重建
+        if match_data.state != MatchState::Completed {
+            match_log::record_match(match_data).await;
+        }
+        
+        // 动作历史和弃牌堆超过上限时先压缩，避免缓存条目和广播载荷无限增长
+        let compacted = self.compact_match(match_data);
+        let match_data = compacted.as_ref().unwrap_or(match_data);
         let result = self.game_service.set(GameCachePrefix::MATCH, &match_data.id, match_data);
         
         // 更新活跃游戏列表
@@ -285,66 +970,395 @@ impl MatchService {
             active_matches.insert(match_data.id.clone(), match_data.id.clone());
         }
         
+        // 同步对局房间的成员
+        if result {
+            self.sync_room_acl(match_data);
+        }
+        
+        // 进行中的游戏固定在缓存中，结束后按过期时间清理
+        if result {
+            match match_data.state {
+                MatchState::InProgress | MatchState::Paused => {
+                    self.game_service.pin(GameCachePrefix::MATCH, &match_data.id);
+                }
+                MatchState::Waiting | MatchState::Completed => {
+                    self.game_service.unpin(GameCachePrefix::MATCH, &match_data.id);
+                }
+            }
+            if match_data.history_summary.archived_actions > 0 {
+                let pinned = matches!(match_data.state, MatchState::InProgress | MatchState::Paused);
+                match_history::pin_archive(&self.game_service, &match_data.id, pinned);
+            }
+            // 结束的对局连同完整动作历史写入持久化存储
+            if match_data.state == MatchState::Completed {
+                storage::persist_match(&self.with_full_history(match_data.clone())).await;
+                match_log::discard_match(&match_data.id).await;
+            }
+        }
+        
+        // 同步状态增量
+        if result {
+            if let Err(e) = self.publish_match_state(match_data).await {
+                error!("广播游戏 {} 状态更新失败: {}", match_data.id, e);
+            }
+        }
+        
+        // 通知其他模块对局已更新（如同步参与者的丰富在线状态）
+        if result {
+            event_bus::publish(DomainEvent::MatchSaved(Arc::new(match_data.clone()))).await;
+        }
+        
         result
     }
     
+    /// 声明对局房间，只有玩家和观众可以加入和接收广播
+    fn sync_room_acl(&self, match_data: &MatchData) {
+        let acls = self.connection_manager.room_acls();
+        let members = match_data.players.iter()
+            .map(|p| p.user.id.clone())
+            .chain(match_data.spectators.iter().map(|s| s.id.clone()));
+        let synced = acls.declare(&match_data.id, RoomOwner::Match, JoinPolicy::Members)
+            .and_then(|_| acls.set_members(&match_data.id, RoomOwner::Match, members));
+        if let Err(e) = synced {
+            warn!("同步游戏 {} 的房间权限失败: {}", match_data.id, e);
+        }
+    }
+    
+    /// 清理对局作用域内的所有缓存数据，并取消对局的计时器
+    fn clear_match_scope(&self, match_id: &str) {
+        self.timer_manager.clear(match_id);
+        let removed = self.game_service.delete_scope(match_id);
+        if removed > 0 {
+            debug!("清理游戏 {} 的对局缓存 {} 条", match_id, removed);
+        }
+    }
+    
     /// 删除游戏
     pub async fn delete_match(&self, match_id: &str) -> bool {
         let result = self.game_service.delete(GameCachePrefix::MATCH, match_id);
+        match_history::delete_archive(&self.game_service, match_id);
+        match_log::discard_match(match_id).await;
+        self.clear_match_scope(match_id);
+        let _ = self.connection_manager.room_acls().release(match_id, RoomOwner::Match);
         
         // 从活跃游戏列表中移除
         if result {
             let mut active_matches = self.active_matches.write().await;
             active_matches.remove(match_id);
         }
-        
+        self.state_tracker.lock().remove(match_id);
+        event_bus::publish(DomainEvent::MatchRemoved { match_id: match_id.to_string() }).await;
+
         result
     }
-    
-    /// 创建新游戏
-    pub async fn create_match(&self, match_type: MatchType, players: Vec<UserInfo>) -> Result<MatchData> {
-        let match_id = Uuid::new_v4().to_string();
-        let now = chrono::Utc::now().timestamp_millis() as u64;
-        
-        // 创建游戏玩家
-        let match_players = players.iter().map(|user| {
-            MatchPlayer {
-                user: user.clone(),
-                hand: Vec::new(),
-                is_active: true,
-                is_winner: false,
-                is_turn: false,
+
+    /**
+     * 更新用户在活跃对局和匹配队列中的名称和头像
+     *
+     * 参数:
+     * @param user_id - 用户ID
+     * @param name - 新名称，None表示不变
+     * @param avatar_url - 新头像，None表示不变
+     *
+     * 返回:
+     * 更新的对局数
+     */
+    pub async fn refresh_user_info(&self, user_id: &str, name: Option<&str>, avatar_url: Option<&str>) -> usize {
+        let apply = |user: &mut UserInfo| {
+            if let Some(name) = name {
+                user.name = name.to_string();
+            }
+            if let Some(avatar_url) = avatar_url {
+                user.avatar_url = Some(avatar_url.to_string());
             }
-        }).collect::<Vec<_>>();
-        
-        // 创建游戏数据
-        let match_data = MatchData {
-            id: match_id.clone(),
-            match_type,
-            state: MatchState::Waiting,
-            players: match_players,
-            out: Vec::new(),
-            spectators: Vec::new(),
-            deck: Vec::new(), // 初始化空牌组，实际游戏开始前会生成
-            discard_pile: Vec::new(),
-            turn_index: 0,
-            created_at: now,
-            updated_at: now,
-            draw_count: 0,
-            skip_votes: HashMap::new(),
-            action_history: Vec::new(),
-            chain_state: None,
-            chain_wait_time: default_chain_wait_time(),
         };
-        
-        // 保存游戏数据
-        if !self.save_match(&match_data).await {
-            return Err(anyhow::anyhow!("保存游戏数据失败"));
+
+        let match_ids: Vec<String> = self.active_matches.read().await.keys().cloned().collect();
+        let mut updated = 0;
+        for match_id in match_ids {
+            let Some(mut match_data) = self.get_match(&match_id).await else {
+                continue;
+            };
+            let users = match_data.players.iter_mut()
+                .chain(match_data.out.iter_mut())
+                .map(|player| &mut player.user)
+                .chain(match_data.spectators.iter_mut())
+                .filter(|user| user.id == user_id);
+            let mut changed = false;
+            for user in users {
+                apply(user);
+                changed = true;
+            }
+            if changed && self.save_match(&match_data).await {
+                updated += 1;
+            }
+        }
+
+        let mut queue = self.queue.write().await;
+        let mut queued = false;
+        for member in queue.iter_mut().flat_map(|entry| entry.members.iter_mut()) {
+            if member.id == user_id {
+                apply(member);
+                queued = true;
+            }
+        }
+        if queued {
+            self.persist_queue(&queue);
+        }
+
+        updated
+    }
+
+    /**
+     * 向支持增量同步的客户端发送对局状态的增量或快照
+     *
+     * 观众收到按公开视图计算的更新，每名玩家收到按自己的私有视图计算的更新，
+     * 玩家的更新通过 `send_private` 发送
+     */
+    async fn publish_match_state(&self, match_data: &MatchData) -> Result<()> {
+        let players = match_data.players.iter()
+            .chain(match_data.out.iter())
+            .map(|p| p.user.id.clone())
+            .collect::<Vec<_>>();
+        let (public, private) = {
+            let mut tracker = self.state_tracker.lock();
+            let public = tracker.next_update(match_data, None)?;
+            let mut private = Vec::with_capacity(players.len());
+            for user_id in &players {
+                if let Some(update) = tracker.next_update(match_data, Some(user_id))? {
+                    private.push((user_id.clone(), update));
+                }
+            }
+            // 已结束的对局不会再更新
+            if match_data.state == MatchState::Completed {
+                tracker.remove(&match_data.id);
+            }
+            (public, private)
+        };
+        
+        if self.connection_manager.get_room_size(&match_data.id).await == 0 {
+            return Ok(());
+        }
+        if let Some(update) = public {
+            self.connection_manager.broadcast_to_room_except(
+                &match_data.id,
+                events::match_events::STATE,
+                Some(serde_json::to_value(update)?),
+                &players,
+            ).await?;
+        }
+        for (user_id, update) in private {
+            if let Err(e) = self.send_private(
+                &match_data.id,
+                &user_id,
+                events::match_events::STATE,
+                serde_json::to_value(update)?,
+            ).await {
+                warn!("向玩家 {} 发送对局状态失败: {}", user_id, e);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// 向单个客户端发送该客户端视角的对局状态快照，用于观战加入或客户端发现序号不连续时重新同步
+    pub async fn send_match_state(&self, match_id: &str, client_id: &str) -> Result<bool> {
+        let match_data = self.get_match(match_id).await
+            .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
+        let viewer = match_data.players.iter()
+            .chain(match_data.out.iter())
+            .any(|p| p.user.id == client_id)
+            .then_some(client_id);
+        let current = self.state_tracker.lock().current(match_id, viewer);
+        let update = match current {
+            Some(update) => update,
+            None => self.state_tracker.lock().next_update(&match_data, viewer)?
+                .ok_or_else(|| anyhow::anyhow!("无法生成游戏状态快照"))?,
+        };
+        
+        match viewer {
+            Some(user_id) => self.send_private(
+                match_id,
+                user_id,
+                events::match_events::STATE,
+                serde_json::to_value(update)?,
+            ).await,
+            None => self.connection_manager.send_to_client(
+                client_id,
+                events::match_events::STATE,
+                Some(serde_json::to_value(update)?),
+            ).await,
+        }
+    }
+    
+    /**
+     * 向玩家发送只有该玩家可以看到的消息，如抽到的牌、偷到的牌、预见的牌堆顶
+     *
+     * 协商了 `sealed_hands` 能力的客户端收到按对局和登录钱包地址加密的消息，见 `hand_seal` 模块。
+     * 无法加密时丢弃消息并返回错误，不会退回明文
+     *
+     * 参数:
+     * @param match_id - 游戏ID
+     * @param user_id - 接收消息的玩家
+     * @param event - 事件名
+     * @param data - 消息数据
+     *
+     * 返回:
+     * 是否已发送
+     */
+    async fn send_private(&self, match_id: &str, user_id: &str, event: &str, data: serde_json::Value) -> Result<bool> {
+        let protocol = self.connection_manager.get_client_protocol(user_id).await;
+        let wallet = self.connection_manager.client_wallet(user_id).await;
+        let data = hand_seal::private_message(
+            hand_seal::hand_sealer(),
+            &protocol,
+            wallet.as_ref(),
+            match_id,
+            user_id,
+            event,
+            data,
+        )?;
+        self.connection_manager.send_to_client(user_id, event, Some(data)).await
+    }
+    
+    /**
+     * 广播携带对局数据的事件，支持增量同步的客户端只收到对局ID，状态通过 match:state 同步
+     *
+     * 房间内只广播公开视图，每名玩家的手牌通过 `send_private` 单独发送 match:hand
+     */
+    async fn broadcast_match_event(
+        &self,
+        match_id: &str,
+        event: &str,
+        text: LocalizedText,
+        match_data: &MatchData,
+    ) -> Result<usize> {
+        let response = WsResponse {
+            ok: true,
+            payload: Some(game_mode::mode_for(match_data).public_view(match_data)?),
+            ..WsResponse::from_text(text.clone())
+        };
+        let lite_response = WsResponse {
+            ok: true,
+            payload: Some(serde_json::json!({
+                "matchId": match_data.id,
+                "shuffleProof": match_data.shuffle_proof,
+            })),
+            ..WsResponse::from_text(text)
+        };
+        
+        let sent = self.connection_manager.broadcast_to_room_with_lite(
+            match_id,
+            event,
+            Some(serde_json::to_value(response)?),
+            capabilities::MATCH_DELTA,
+            Some(serde_json::to_value(lite_response)?),
+        ).await?;
+        
+        for player in match_data.players.iter().filter(|p| !p.hand.is_empty()) {
+            let hand = serde_json::json!({
+                "matchId": match_data.id,
+                "hand": player.hand,
+            });
+            if let Err(e) = self.send_private(match_id, &player.user.id, events::match_events::HAND, hand).await {
+                warn!("向玩家 {} 发送手牌失败: {}", player.user.id, e);
+            }
+        }
+        Ok(sent)
+    }
+    
+    /// 创建新游戏
+    pub async fn create_match(&self, match_type: MatchType, players: Vec<UserInfo>) -> Result<MatchData> {
+        let match_id = Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        
+        // 创建游戏数据
+        let match_data = MatchData::new(match_id, match_type, &players, now);
+        
+        // 保存游戏数据
+        if !self.save_match(&match_data).await {
+            return Err(anyhow::anyhow!("保存游戏数据失败"));
         }
         
         Ok(match_data)
     }
     
+    /// 创建带入场押注的游戏，为所有初始玩家托管押注
+    pub async fn create_wagered_match(&self, match_type: MatchType, players: Vec<UserInfo>, wager: u64) -> Result<MatchData> {
+        let mut match_data = self.create_match(match_type, players).await?;
+        if wager == 0 {
+            return Ok(match_data);
+        }
+        
+        match_data.wager = wager;
+        let user_ids = match_data.players.iter().map(|p| p.user.id.clone()).collect::<Vec<_>>();
+        for user_id in user_ids {
+            if let Err(e) = self.stake_wager(&mut match_data, &user_id).await {
+                // 押注失败，退还已托管的押注并删除游戏
+                self.refund_all_wagers(&mut match_data).await;
+                self.delete_match(&match_data.id).await;
+                return Err(e);
+            }
+        }
+        
+        if !self.save_match(&match_data).await {
+            self.refund_all_wagers(&mut match_data).await;
+            return Err(anyhow::anyhow!("保存游戏数据失败"));
+        }
+        
+        Ok(match_data)
+    }
+    
+    /// 为玩家托管押注，已托管的玩家不会重复扣款
+    async fn stake_wager(&self, match_data: &mut MatchData, user_id: &str) -> Result<()> {
+        if match_data.wager == 0 || match_data.staked.iter().any(|id| id == user_id) {
+            return Ok(());
+        }
+        self.economy_service.escrow_wager(user_id, &match_data.id, match_data.wager).await?;
+        match_data.pot += match_data.wager;
+        match_data.staked.push(user_id.to_string());
+        Ok(())
+    }
+    
+    /// 退还玩家的押注
+    async fn refund_wager(&self, match_data: &mut MatchData, user_id: &str) {
+        let Some(index) = match_data.staked.iter().position(|id| id == user_id) else {
+            return;
+        };
+        match self.economy_service.refund_wager(user_id, &match_data.id, match_data.wager).await {
+            Ok(_) => {
+                match_data.staked.remove(index);
+                match_data.pot = match_data.pot.saturating_sub(match_data.wager);
+            }
+            Err(e) => error!("退还押注失败: {}", e),
+        }
+    }
+    
+    /// 退还所有玩家的押注
+    async fn refund_all_wagers(&self, match_data: &mut MatchData) {
+        for user_id in match_data.staked.clone() {
+            self.refund_wager(match_data, &user_id).await;
+        }
+    }
+    
+    /// 将奖池发放给胜利者，失败时奖池保留在对局数据中
+    async fn award_pot(&self, match_data: &mut MatchData, winner_id: &str) -> Result<()> {
+        if match_data.pot == 0 {
+            return Ok(());
+        }
+        match self.economy_service.payout_pot(winner_id, &match_data.id, match_data.pot).await {
+            Ok(_) => {
+                match_data.pot = 0;
+                self.save_match(match_data).await;
+                Ok(())
+            }
+            Err(e) => {
+                error!("发放奖池失败: {}", e);
+                Err(anyhow::anyhow!("发放奖池失败: {}", e))
+            }
+        }
+    }
+    
     /// 加入游戏
     pub async fn join_match(&self, match_id: &str, user_id: &str, client_id: &str) -> Result<()> {
         // 获取游戏数据
@@ -356,35 +1370,173 @@ impl MatchService {
             return Err(anyhow::anyhow!("游戏已经开始或结束，无法加入"));
         }
         
+        // 押注在玩家加入对局时托管（创建游戏或接受邀请），不在对局中的用户不能通过加入押注游戏付款
+        if match_data.wager > 0 && !match_data.players.iter().any(|p| p.user.id == user_id) {
+            return Err(anyhow::anyhow!("押注游戏只能通过邀请加入"));
+        }
+        
         // 加入WebSocket房间 - 使用手动实现加入房间
         self.connection_manager.broadcast_to_room(match_id, "system:join", Some(serde_json::json!({
             "client_id": client_id
         }))).await?;
         
         // 广播加入事件
-        let response = WsResponse {
-            ok: true,
-            msg: Some(format!("玩家 {} 加入了游戏", user_id)),
-            payload: Some(serde_json::to_value(&match_data)?),
-        };
-        
-        self.connection_manager.broadcast_to_room(
+        self.broadcast_match_event(
             match_id,
             events::match_events::JOIN,
-            Some(serde_json::to_value(response)?),
+            i18n::text(codes::PLAYER_JOINED, &[("user", user_id.to_string())]),
+            &match_data,
         ).await?;
         
         Ok(())
     }
     
+    /**
+     * 邀请好友加入等待中的游戏
+     *
+     * 参数:
+     * @param match_id - 游戏ID
+     * @param inviter - 邀请者
+     * @param friend_id - 被邀请的好友ID
+     *
+     * 返回:
+     * 创建的邀请，邀请通过用户护照模块推送到好友的所有会话
+     */
+    pub async fn invite_friend(&self, match_id: &str, inviter: &UserInfo, friend_id: &str) -> Result<MatchInvite> {
+        let match_data = self.get_match(match_id).await
+            .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
+        
+        if match_data.state != MatchState::Waiting {
+            return Err(anyhow::anyhow!("只能邀请好友加入等待中的游戏"));
+        }
+        if !match_data.players.iter().any(|p| p.user.id == inviter.id) {
+            return Err(anyhow::anyhow!("只有游戏中的玩家可以邀请好友"));
+        }
+        if match_data.players.iter().any(|p| p.user.id == friend_id) {
+            return Err(anyhow::anyhow!("好友已经在游戏中"));
+        }
+        if match_data.players.len() >= MAX_MATCH_PLAYERS {
+            return Err(anyhow::anyhow!("游戏人数已满"));
+        }
+        
+        let passport_state = crate::ws::global_passport_state()
+            .ok_or_else(|| anyhow::anyhow!("用户护照服务未初始化"))?;
+        let friends = passport_state.get_user_friends(&inviter.id).await?;
+        if !friends.iter().any(|id| id == friend_id) {
+            return Err(anyhow::anyhow!("只能邀请好友"));
+        }
+        
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let invite = MatchInvite {
+            id: Uuid::new_v4().to_string(),
+            match_id: match_id.to_string(),
+            inviter: inviter.clone(),
+            invitee_id: friend_id.to_string(),
+            wager: match_data.wager,
+            created_at: now,
+            expires_at: now + MATCH_INVITE_TTL_MS,
+        };
+        
+        {
+            let mut invites = self.invites.write().await;
+            // 清理过期邀请，同一好友在同一游戏中只保留最新的邀请
+            invites.retain(|_, i| {
+                i.expires_at > now && !(i.match_id == invite.match_id && i.invitee_id == invite.invitee_id)
+            });
+            invites.insert(invite.id.clone(), invite.clone());
+        }
+        
+        if !passport_state.notify_match_invite(&invite).await? {
+            debug!("好友 {} 当前不在线，邀请 {} 未能送达", friend_id, invite.id);
+        }
+        
+        Ok(invite)
+    }
+    
+    /// 取出有效的邀请，邀请不存在、已过期或不属于该用户时返回错误
+    async fn take_invite(&self, invite_id: &str, user_id: &str) -> Result<MatchInvite> {
+        let mut invites = self.invites.write().await;
+        match invites.get(invite_id) {
+            Some(invite) if invite.invitee_id != user_id => Err(anyhow::anyhow!("邀请不属于当前用户")),
+            Some(_) => {
+                let invite = invites.remove(invite_id).unwrap();
+                if invite.expires_at <= chrono::Utc::now().timestamp_millis() as u64 {
+                    return Err(anyhow::anyhow!("邀请已过期"));
+                }
+                Ok(invite)
+            }
+            None => Err(anyhow::anyhow!("邀请不存在")),
+        }
+    }
+    
+    /**
+     * 接受游戏邀请，直接加入游戏并进入游戏房间
+     *
+     * 参数:
+     * @param invite_id - 邀请ID
+     * @param user - 接受邀请的用户
+     * @param client_id - 接受邀请的客户端ID
+     *
+     * 返回:
+     * 加入后的游戏数据
+     */
+    pub async fn accept_invite(&self, invite_id: &str, user: &UserInfo, client_id: &str) -> Result<MatchData> {
+        let invite = self.take_invite(invite_id, &user.id).await?;
+        let match_id = invite.match_id.as_str();
+        
+        let mut match_data = self.get_match(match_id).await
+            .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
+        if match_data.state != MatchState::Waiting {
+            return Err(anyhow::anyhow!("游戏已经开始或结束，无法加入"));
+        }
+        
+        if !match_data.players.iter().any(|p| p.user.id == user.id) {
+            if match_data.players.len() >= MAX_MATCH_PLAYERS {
+                return Err(anyhow::anyhow!("游戏人数已满"));
+            }
+            // 先托管押注，失败时不加入游戏
+            self.stake_wager(&mut match_data, &user.id).await?;
+            match_data.spectators.retain(|s| s.id != user.id);
+            match_data.players.push(MatchPlayer {
+                user: user.clone(),
+                hand: Vec::new(),
+                is_active: true,
+                is_winner: false,
+                is_turn: false,
+            });
+            match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
+            if !self.save_match(&match_data).await {
+                self.refund_wager(&mut match_data, &user.id).await;
+                return Err(anyhow::anyhow!("保存游戏数据失败"));
+            }
+        }
+        
+        // 将客户端加入游戏房间
+        if !self.connection_manager.join_room(client_id, match_id).await? {
+            warn!("客户端 {} 不在任何房间中，无法自动加入游戏房间 {}", client_id, match_id);
+        }
+        
+        self.join_match(match_id, &user.id, client_id).await?;
+        Ok(match_data)
+    }
+    
+    /// 拒绝游戏邀请，并通知邀请者
+    pub async fn decline_invite(&self, invite_id: &str, user_id: &str) -> Result<MatchInvite> {
+        let invite = self.take_invite(invite_id, user_id).await?;
+        if let Some(passport_state) = crate::ws::global_passport_state() {
+            passport_state.notify_match_invite_declined(&invite).await?;
+        }
+        Ok(invite)
+    }
+    
     /// 离开游戏
     pub async fn leave_match(&self, match_id: &str, user_id: &str, client_id: &str) -> Result<()> {
         // 获取游戏数据
         let mut match_data = self.get_match(match_id).await
             .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
         
-        // 如果游戏已经开始，将玩家标记为离开
-        if match_data.state == MatchState::InProgress {
+        // 如果游戏已经开始（包括暂停中），将玩家标记为离开
+        if matches!(match_data.state, MatchState::InProgress | MatchState::Paused) {
             // 查找玩家
             let player_index = match_data.players.iter().position(|p| p.user.id == user_id);
             
@@ -393,38 +1545,66 @@ impl MatchService {
                 let mut player = match_data.players.remove(index);
                 player.is_active = false;
                 match_data.out.push(player);
+                match_data.action_history.push(CardAction::eliminate(user_id));
+                
+                // 记录中途退出，频繁退出的玩家会被临时禁止匹配
+                match_data.abandoned.push(user_id.to_string());
+                self.penalty_service.record_abandon(user_id);
+                
+                // 离开的玩家不再参与暂停投票，其排队的动作也不再执行
+                if let Some(pause) = match_data.pause.as_mut() {
+                    pause.votes.remove(user_id);
+                }
+                match_data.queued_actions.retain(|a| a.user_id != user_id);
                 
                 // 更新游戏数据
                 match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
                 self.save_match(&match_data).await;
                 
                 // 检查游戏是否结束
-                if match_data.players.len() <= 1 {
+                if let Some(winner_id) = game_mode::mode_for(&match_data).winner(&match_data) {
                     {
                         // 使用代码块来限制可变引用的作用域
-                        let last_player = match_data.players.first_mut().unwrap();
                         // 标记为胜利者
-                        last_player.is_winner = true;
+                        if let Some(winner) = match_data.players.iter_mut().find(|p| p.user.id == winner_id) {
+                            winner.is_winner = true;
+                        }
                         
                         // 更新游戏状态
                         match_data.state = MatchState::Completed;
+                        match_replay::reveal_seed(&mut match_data);
+                        shuffle_proof::reveal(&mut match_data);
+                        match_data.pause = None;
+                        match_data.queued_actions.clear();
                         match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
-                    } // last_player的可变引用在这里结束
+                    } // winner的可变引用在这里结束
                     
                     // 克隆数据供后续使用
                     let match_data_clone = match_data.clone();
                     self.save_match(&match_data).await;
                     
-                    // 获取胜利者的用户ID用于响应
-                    let winner_id = match_data.players.first().unwrap().user.id.clone();
+                    // 先发放奖池，后续的广播失败不会让押注留在托管中
+                    let payout = self.award_pot(&mut match_data, &winner_id).await;
+                    
+                    // 系列赛未结束时只记录比分并创建下一局，评分、统计和成就在系列赛结束时更新
+                    let series = self.finish_series_round(&match_data_clone, &winner_id).await;
+                    let series_over = !matches!(&series, Some(s) if s.state == SeriesState::InProgress);
                     
-                    // 广播胜利事件
+                    // 更新玩家评分
+                    let rating_changes = if series_over {
+                        self.update_player_ratings(match_id).await.unwrap_or_else(|e| {
+                            error!("更新玩家评分失败: {}", e);
+                            Vec::new()
+                        })
+                    } else {
+                        Vec::new()
+                    };
+                    
+                    // 广播胜利事件，启用签名时附带签名的对局结果
                     let victory_response = WsResponse {
                         ok: true,
-                        msg: Some(format!("玩家 {} 获胜", winner_id)),
-                        payload: Some(serde_json::json!({
-                            "userId": winner_id
-                        })),
+                        payload: Some(victory_payload(&match_data_clone, &winner_id, &rating_changes)),
+                        ..WsResponse::from_text(i18n::text(codes::PLAYER_WON, &[("user", winner_id.to_string())]))
                     };
                     
                     self.connection_manager.broadcast_to_room(
@@ -434,29 +1614,44 @@ impl MatchService {
                     ).await?;
                     
                     // 广播游戏结束事件
-                    let end_response = WsResponse {
-                        ok: true,
-                        msg: Some("游戏结束".to_string()),
-                        payload: Some(serde_json::to_value(&match_data_clone)?),
-                    };
-                    
-                    self.connection_manager.broadcast_to_room(
+                    self.broadcast_match_event(
                         match_id,
                         events::match_events::END,
-                        Some(serde_json::to_value(end_response)?),
+                        i18n::text(codes::MATCH_ENDED, &[]),
+                        &match_data_clone,
                     ).await?;
                     
-                    // 更新玩家评分
-                    if let Err(e) = self.update_player_ratings(match_id).await {
-                        error!("更新玩家评分失败: {}", e);
+                    // 广播系列赛的比分或结果
+                    if let Some(series) = &series {
+                        self.broadcast_series_result(match_id, series, &rating_changes).await?;
+                    }
+                    
+                    // 更新玩家统计与评分历史
+                    if series_over {
+                        let full_match = self.with_full_history(match_data_clone.clone());
+                        self.stats_service.record_match(&full_match, &rating_changes);
                     }
+                    self.penalty_service.record_match(&match_data_clone);
+                    record_search_interactions(&match_data_clone);
                     
-                    return Ok(());
+                    // 记录胜利成就进度
+                    if series_over {
+                        self.record_achievement(&winner_id, AchievementMetric::GamesWon).await;
+                    }
+                    
+                    // 清理对局作用域内的缓存数据，游戏记录本身按过期时间清理
+                    self.clear_match_scope(match_id);
+                    
+                    return payout;
                 }
             }
         } else if match_data.state == MatchState::Waiting {
-            // 如果游戏还在等待中，直接移除玩家
+            // 如果游戏还在等待中，直接移除玩家并退还押注
             match_data.players.retain(|p| p.user.id != user_id);
+            self.refund_wager(&mut match_data, user_id).await;
+            if match_data.players.is_empty() {
+                self.refund_all_wagers(&mut match_data).await;
+            }
             
             // 如果没有玩家了，删除游戏
             if match_data.players.is_empty() {
@@ -474,16 +1669,11 @@ impl MatchService {
         }))).await?;
         
         // 广播离开事件
-        let response = WsResponse {
-            ok: true,
-            msg: Some(format!("玩家 {} 离开了游戏", user_id)),
-            payload: Some(serde_json::to_value(&match_data)?),
-        };
-        
-        self.connection_manager.broadcast_to_room(
+        self.broadcast_match_event(
             match_id,
             events::match_events::LEAVE,
-            Some(serde_json::to_value(response)?),
+            i18n::text(codes::PLAYER_LEFT, &[("user", user_id.to_string())]),
+            &match_data,
         ).await?;
         
         Ok(())
@@ -493,147 +1683,287 @@ impl MatchService {
     pub async fn start_matchmaking(&self) {
         let match_service = self.clone();
         
-        tokio::spawn(async move {
-            loop {
-                // 检查队列中的玩家数量，如果达到设定人数则创建游戏
-                match_service.process_queue().await;
-                
-                // 每5秒检查一次
-                sleep(Duration::from_secs(5)).await;
+        // 匹配队列处理异常退出时按退避重新启动
+        tasks::supervise_global("matchmaking", RestartPolicy::default(), move |token| {
+            let match_service = match_service.clone();
+            async move {
+                loop {
+                    // 检查队列中的玩家数量，如果达到设定人数则创建游戏
+                    match_service.process_queue().await;
+                    
+                    // 每5秒检查一次
+                    tokio::select! {
+                        _ = token.cancelled() => break,
+                        _ = sleep(Duration::from_secs(5)) => {}
+                    }
+                }
             }
         });
     }
     
     /// 处理匹配队列
     async fn process_queue(&self) {
-        // 获取队列中的玩家
-        let players = {
+        // 选出一局游戏的玩家，队伍成员必须进入同一局游戏，优先同地区匹配
+        let entries = {
             let queue = self.queue.read().await;
-            if queue.len() < 2 {
-                return; // 至少需要2名玩家才能开始游戏
-            }
-            
-            // 复制前4名玩家（或者全部，如果少于4名）
-            let player_count = queue.len().min(4);
-            queue[0..player_count].to_vec()
+            let now = chrono::Utc::now().timestamp_millis() as u64;
+            let fallback_ms = geo::geo_service().config().fallback_ms;
+            let Some(selected) = geo::plan_regional_match(&queue, MAX_MATCH_PLAYERS, now, fallback_ms) else {
+                return; // 至少需要2名玩家才能开始游戏，不同地区的玩家等待超过回退时间后才能匹配
+            };
+            selected.into_iter().map(|i| queue[i].clone()).collect::<Vec<_>>()
         };
+        let players: Vec<UserInfo> = entries.iter().flat_map(|e| e.members.clone()).collect();
         
-        if players.len() >= 2 {
-            // 创建新游戏
-            match self.create_match(MatchType::Public, players.clone()).await {
-                Ok(match_data) => {
-                    // 从队列中移除这些玩家
-                    {
-                        let mut queue = self.queue.write().await;
-                        for player in &players {
-                            if let Some(pos) = queue.iter().position(|p| p.id == player.id) {
-                                queue.remove(pos);
-                            }
-                        }
+        // 创建新游戏
+        match self.create_match(MatchType::Public, players.clone()).await {
+            Ok(match_data) => {
+                // 从队列中移除这些玩家
+                {
+                    let mut queue = self.queue.write().await;
+                    queue.retain(|e| !players.iter().any(|p| e.contains(&p.id)));
+                    self.persist_queue(&queue);
+                }
+                self.restored_queue.lock().retain(|id| !players.iter().any(|p| &p.id == id));
+                if let Some(party_service) = party::global_party_service() {
+                    for party_id in entries.iter().filter_map(|e| e.party_id.as_ref()) {
+                        party_service.set_queued(party_id, false);
                     }
+                }
+                
+                // 通知所有玩家游戏创建成功
+                for player in &players {
+                    // 在实际应用中，这里需要查找玩家的WebSocket连接并发送消息
+                    // 这里使用连接管理器向玩家发送消息
                     
-                    // 通知所有玩家游戏创建成功
-                    for player in &players {
-                        // 在实际应用中，这里需要查找玩家的WebSocket连接并发送消息
-                        // 这里使用连接管理器向玩家发送消息
-                        
-                        // 模拟向玩家发送消息，实际应用需要获取玩家的连接ID
-                        let response = WsResponse {
-                            ok: true,
-                            msg: Some(format!("游戏已创建，ID: {}", match_data.id)),
-                            payload: Some(serde_json::to_value(&match_data).unwrap_or_default()),
-                        };
-                        
-                        // 这里需要获取玩家的连接ID，这个示例中我们使用玩家ID作为连接ID
-                        if let Err(e) = self.connection_manager.send_to_client(
-                            &player.id,
-                            events::match_events::START,
-                            Some(serde_json::to_value(response).unwrap_or_default()),
-                        ).await {
-                            error!("向玩家 {} 发送游戏创建消息失败: {}", player.id, e);
-                        }
-                    }
+                    // 模拟向玩家发送消息，实际应用需要获取玩家的连接ID
+                    let response = WsResponse {
+                        ok: true,
+                        payload: Some(serde_json::to_value(&match_data).unwrap_or_default()),
+                        ..WsResponse::from_text(i18n::text(codes::MATCH_CREATED, &[("match", match_data.id.to_string())]))
+                    };
                     
-                    info!("已创建新游戏: {}", match_data.id);
-                }
-                Err(e) => {
-                    error!("创建游戏失败: {}", e);
+                    // 这里需要获取玩家的连接ID，这个示例中我们使用玩家ID作为连接ID
+                    if let Err(e) = self.connection_manager.send_to_client(
+                        &player.id,
+                        events::match_events::START,
+                        Some(serde_json::to_value(response).unwrap_or_default()),
+                    ).await {
+                        error!("向玩家 {} 发送游戏创建消息失败: {}", player.id, e);
+                    }
                 }
+                
+                info!("已创建新游戏: {}", match_data.id);
+            }
+            Err(e) => {
+                error!("创建游戏失败: {}", e);
             }
         }
     }
     
-    /// 加入匹配队列
-    pub async fn join_queue(&self, user: UserInfo) -> Result<()> {
-        // 检查玩家是否已在队列中
-        {
-            let queue = self.queue.read().await;
-            if queue.iter().any(|p| p.id == user.id) {
-                return Err(anyhow::anyhow!("玩家已在队列中"));
+    /// 加入匹配队列，玩家在队伍中时由队长带领整个队伍加入，队伍使用队长的匹配地区
+    pub async fn join_queue(&self, user: UserInfo, region: Option<String>) -> Result<()> {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let party_service = party::global_party_service();
+        let entry = match party_service.as_ref().and_then(|s| s.party_of(&user.id)) {
+            Some(party) if party.leader_id != user.id => {
+                return Err(anyhow::anyhow!("只有队长可以带领队伍匹配"));
+            }
+            Some(party) => QueueEntry::party(&party, now),
+            None => QueueEntry::solo(user.clone(), now),
+        }
+        .with_region(region);
+        
+        // 频繁中途退出的玩家在禁令期间不能匹配，队伍中任一成员受罚时整个队伍都不能匹配
+        for member in &entry.members {
+            if let Some(remaining) = self.penalty_service.ban_remaining(&member.id) {
+                return Err(anyhow::anyhow!("玩家 {} 频繁中途退出对局，{} 秒内不能匹配", member.id, remaining.div_ceil(1000)));
             }
+            // 被封禁的玩家不能匹配，返回封禁状态供客户端展示
+            ban::check_user(&member.id)?;
         }
         
-        // 将玩家添加到队列
+        // 检查玩家是否已在队列中，并将玩家添加到队列
         {
             let mut queue = self.queue.write().await;
-            queue.push(user.clone());
+            if queue.iter().any(|e| entry.members.iter().any(|m| e.contains(&m.id))) {
+                return Err(anyhow::anyhow!("玩家已在队列中"));
+            }
+            queue.push(entry.clone());
+            self.persist_queue(&queue);
+        }
+        
+        if let (Some(party_service), Some(party_id)) = (party_service, &entry.party_id) {
+            party_service.set_queued(party_id, true);
+            if let Some(party) = party_service.party_of(&user.id) {
+                party_service.broadcast_party(&party).await?;
+            }
         }
         
-        info!("玩家 {} 加入匹配队列", user.id);
+        info!("玩家 {} 加入匹配队列，同队 {} 人，地区 {:?}", user.id, entry.members.len(), entry.region);
         Ok(())
     }
     
-    /// 离开匹配队列
+    /// 离开匹配队列，玩家在队伍中时整个队伍一起离开
     pub async fn leave_queue(&self, user_id: &str) -> Result<()> {
-        let mut queue = self.queue.write().await;
-        let original_len = queue.len();
+        let removed = {
+            let mut queue = self.queue.write().await;
+            let Some(pos) = queue.iter().position(|e| e.contains(user_id)) else {
+                return Err(anyhow::anyhow!("玩家不在队列中"));
+            };
+            let removed = queue.remove(pos);
+            self.persist_queue(&queue);
+            removed
+        };
+        self.restored_queue.lock().retain(|id| !removed.contains(id));
         
-        queue.retain(|p| p.id != user_id);
+        if let (Some(party_service), Some(party_id)) = (party::global_party_service(), &removed.party_id) {
+            party_service.set_queued(party_id, false);
+            if let Some(party) = party_service.party_of(user_id) {
+                party_service.broadcast_party(&party).await?;
+            }
+        }
         
+        info!("玩家 {} 离开匹配队列", user_id);
+        Ok(())
+    }
+    
+    /// 队伍成员变化时将队伍移出匹配队列
+    pub async fn dequeue_party(&self, party_id: &str) {
+        let mut queue = self.queue.write().await;
+        let original_len = queue.len();
+        queue.retain(|e| e.party_id.as_deref() != Some(party_id));
         if queue.len() < original_len {
-            info!("玩家 {} 离开匹配队列", user_id);
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("玩家不在队列中"))
+            self.persist_queue(&queue);
+            info!("队伍 {} 成员变化，已移出匹配队列", party_id);
         }
     }
     
-    /// 获取队列状态
-    pub async fn get_queue_status(&self, user_id: &str) -> Option<u64> {
-        let queue = self.queue.read().await;
-        
-        // 查找玩家在队列中的位置
-        for (i, player) in queue.iter().enumerate() {
-            if player.id == user_id {
-                // 实际应用中，可能需要返回更多信息，如等待时间、队列位置等
-                return Some(chrono::Utc::now().timestamp_millis() as u64);
-            }
+    /// 将匹配队列写入缓存，重启后由 `restore_queue` 恢复
+    fn persist_queue(&self, queue: &[QueueEntry]) {
+        if !self.game_service.set(GameCachePrefix::QUEUE, QUEUE_CACHE_KEY, &queue) {
+            warn!("保存匹配队列失败");
         }
-        
-        None
     }
     
-    /// 开始游戏
-    pub async fn start_game(&self, match_id: &str) -> Result<()> {
-        // 获取游戏数据
-        let mut match_data = self.get_match(match_id).await
-            .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
+    /**
+     * 从缓存恢复重启前的匹配队列
+     *
+     * 重启期间受到匹配禁令或被封禁的玩家不再恢复。恢复的玩家在下次上线、重连或查询队列状态时
+     * 收到带有 `restored` 标记的 `queue:status`。
+     *
+     * 返回:
+     * 恢复的玩家数
+     */
+    pub async fn restore_queue(&self) -> usize {
+        let Some(entries) = self.game_service.get::<Vec<QueueEntry>>(GameCachePrefix::QUEUE, QUEUE_CACHE_KEY) else {
+            return 0;
+        };
         
-        // 检查游戏状态
-        if match_data.state != MatchState::Waiting {
-            return Err(anyhow::anyhow!("游戏已经开始或结束"));
+        let mut queue = self.queue.write().await;
+        let mut restored = self.restored_queue.lock();
+        for entry in entries {
+            if entry.members.iter().any(|m| {
+                self.penalty_service.ban_remaining(&m.id).is_some() || ban::check_user(&m.id).is_err()
+            }) {
+                continue;
+            }
+            if queue.iter().any(|e| entry.members.iter().any(|m| e.contains(&m.id))) {
+                continue;
+            }
+            restored.extend(entry.members.iter().map(|m| m.id.clone()));
+            queue.push(entry);
+        }
+        self.persist_queue(&queue);
+        
+        if !restored.is_empty() {
+            info!("从缓存恢复匹配队列，共 {} 名玩家", restored.len());
+        }
+        restored.len()
+    }
+    
+    /// 向重启后恢复排队的玩家发送一次队列状态，玩家不是恢复的玩家时返回false
+    pub async fn notify_queue_restored(&self, user_id: &str, client_id: &str) -> Result<bool> {
+        if !self.restored_queue.lock().remove(user_id) {
+            return Ok(false);
+        }
+        self.send_queue_status(user_id, client_id, true).await
+    }
+    
+    /// 发送队列状态、惩罚状态和封禁状态
+    async fn send_queue_status(&self, user_id: &str, client_id: &str, restored: bool) -> Result<bool> {
+        let enqueued_at = self.get_queue_status(user_id).await;
+        let penalty = self.penalty_service.status(user_id);
+        let ban = ban::check_user(user_id).err().map(|e| e.0);
+        
+        let response = WsResponse {
+            ok: true,
+            msg: None,
+            payload: Some(serde_json::json!({
+                "isEnqueued": enqueued_at.is_some(),
+                "enqueuedAt": enqueued_at,
+                "penalty": penalty,
+                "ban": ban,
+                "restored": restored
+            })),
+            ..Default::default()
+        };
+        
+        self.connection_manager.send_to_client(
+            client_id,
+            "queue:status",
+            Some(serde_json::to_value(response)?),
+        ).await
+    }
+    
+    /// 获取队列状态，返回进入队列的时间
+    pub async fn get_queue_status(&self, user_id: &str) -> Option<u64> {
+        let queue = self.queue.read().await;
+        queue.iter().find(|e| e.contains(user_id)).map(|e| e.enqueued_at)
+    }
+    
+    /// 开始游戏
+    pub async fn start_game(&self, match_id: &str) -> Result<()> {
+        // 获取游戏数据
+        let mut match_data = self.get_match(match_id).await
+            .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
+        
+        // 检查游戏状态
+        if match_data.state != MatchState::Waiting {
+            return Err(anyhow::anyhow!("游戏已经开始或结束"));
         }
         
         // 检查玩家数量
-        if match_data.players.len() < 2 {
+        let mode = game_mode::mode_for(&match_data);
+        let (min_players, max_players) = mode.player_range();
+        if match_data.players.len() < min_players {
             return Err(anyhow::anyhow!("玩家数量不足，无法开始游戏"));
         }
+        if match_data.players.len() > max_players {
+            return Err(anyhow::anyhow!("玩家数量超过上限，无法开始游戏"));
+        }
+        
+        // 系列赛按座位轮换排列玩家，先手玩家每局交替
+        if let Some(series_id) = match_data.series_id.clone() {
+            if let Some(mut series) = series::load(&self.game_service, &series_id) {
+                series.seat(&mut match_data.players);
+                if !series::save(&self.game_service, &series) {
+                    warn!("保存系列赛 {} 失败", series_id);
+                }
+            }
+        }
+        
+        // 记录随机种子和座位，牌组和卡牌效果的随机数都由种子决定，便于重放验证
+        match_data.replay_seed = Some(ReplaySeed::new(&match_data.id, &match_data.players));
         
         // 生成牌组
-        match_data.deck = generate_deck(match_data.players.len());
+        match_data.deck = mode.build_deck(match_data.players.len(), &mut match_replay::deck_rng(&match_data));
         
         // 发牌
-        distribute_cards(&mut match_data);
+        mode.deal(&mut match_data);
+        
+        // 承诺开局牌堆顺序，随开始事件下发，结束时公开
+        shuffle_proof::commit(&mut match_data);
         
         // 更新游戏状态
         match_data.state = MatchState::InProgress;
@@ -642,6 +1972,13 @@ impl MatchService {
         // 设置第一个玩家为当前回合
         match_data.turn_index = 0;
         match_data.players[0].is_turn = true;
+        match_data.timers.turn_started_at = Some(match_data.updated_at);
+        
+        // 分配回合时间储备
+        match_data.time_banks = TimeBanks::new(
+            match_data.players.iter().map(|p| p.user.id.as_str()),
+            time_bank::initial_bank(),
+        );
         
         // 保存游戏数据
         if !self.save_match(&match_data).await {
@@ -649,77 +1986,109 @@ impl MatchService {
         }
         
         // 广播游戏开始事件
-        let response = WsResponse {
-            ok: true,
-            msg: Some("游戏开始".to_string()),
-            payload: Some(serde_json::to_value(&match_data)?),
-        };
-        
-        self.connection_manager.broadcast_to_room(
+        self.broadcast_match_event(
             match_id,
             events::match_events::START,
-            Some(serde_json::to_value(response)?),
+            i18n::text(codes::MATCH_STARTED, &[]),
+            &match_data,
         ).await?;
         
+        // 第一个玩家开始计时
+        let first_user_id = match_data.players[0].user.id.clone();
+        self.start_turn_timer(match_id, &first_user_id, &match_data.time_banks).await;
+        
         Ok(())
     }
     
     /// 抽卡
     pub async fn draw_card(&self, match_id: &str, user_id: &str) -> Result<Option<Card>> {
+        let mut profile = ActionProfile::start("draw_card", match_id);
+        
         // 获取游戏数据
-        let mut match_data = self.get_match(match_id).await
+        let mut match_data = profile.step(ActionStep::CacheRead, self.get_match(match_id)).await
             .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
         
+        // 游戏暂停中，动作排队等待恢复
+        if match_data.state == MatchState::Paused {
+            self.queue_action(&mut match_data, user_id, CardActionType::Draw, None).await?;
+            return Ok(None);
+        }
+        
         // 检查游戏状态
         if match_data.state != MatchState::InProgress {
             return Err(anyhow::anyhow!("游戏未开始或已结束"));
         }
         
-        // 查找玩家
-        let player_index = match_data.players.iter().position(|p| p.user.id == user_id)
-            .ok_or_else(|| anyhow::anyhow!("玩家不在游戏中"))?;
-        
-        // 检查是否是玩家的回合
-        if !match_data.players[player_index].is_turn {
-            return Err(anyhow::anyhow!("不是该玩家的回合"));
+        // 检查是否有玩家正在选择目标
+        if match_data.pending_action.is_some() {
+            return Err(anyhow::anyhow!("有玩家正在选择目标，请稍后再试"));
         }
         
-        // 检查牌堆是否为空
+        // 牌堆抽完时按游戏模式的规则洗回弃牌，无法补充时结束游戏
         if match_data.deck.is_empty() {
-            return Err(anyhow::anyhow!("牌堆已空"));
+            if !match_data.players.iter().any(|p| p.user.id == user_id && p.is_turn) {
+                return Err(anyhow::anyhow!("不是该玩家的回合"));
+            }
+            if match_data.chain_state.is_some() {
+                return Err(anyhow::anyhow!("有连锁效果正在处理中，请稍后再试"));
+            }
+            if !profile.step(ActionStep::Effect, self.handle_deck_exhausted(&mut match_data)).await? {
+                profile.step(ActionStep::Effect, self.handle_game_end(match_id)).await?;
+                return Ok(None);
+            }
         }
         
+        // 按游戏模式规则校验抽牌
+        profile.step_sync(ActionStep::Validation, || {
+            game_mode::mode_for(&match_data).validate_action(&match_data, user_id, PlayerAction::Draw)
+        })?;
+        
+        // 查找玩家
+        let player_index = match_data.players.iter().position(|p| p.user.id == user_id)
+            .ok_or_else(|| anyhow::anyhow!("玩家不在游戏中"))?;
+        
         // 抽卡
         let card = match_data.deck.pop().unwrap();
         
         // 更新抽卡计数
         match_data.draw_count += 1;
         
+        // 记录抽卡动作，重放验证时用于核对牌堆
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        match_data.action_history.push(CardAction {
+            action_type: CardActionType::Draw,
+            user_id: user_id.to_string(),
+            card_id: Some(card.id.clone()),
+            card_type: Some(card.card_type.clone()),
+            is_canceled: false,
+            created_at: now,
+        });
+        
         // 广播抽卡事件（不含卡牌信息，只通知有人抽卡）
         let draw_response = WsResponse {
             ok: true,
-            msg: Some(format!("玩家 {} 抽了一张牌", user_id)),
             payload: Some(serde_json::json!({
                 "userId": user_id,
                 "deckCount": match_data.deck.len()
             })),
+            ..WsResponse::from_text(i18n::text(codes::CARD_DRAWN, &[("user", user_id.to_string())]))
         };
         
-        self.connection_manager.broadcast_to_room(
+        profile.step(ActionStep::Broadcast, self.connection_manager.broadcast_to_room(
             match_id,
             events::match_events::DRAW_CARD,
             Some(serde_json::to_value(draw_response)?),
-        ).await?;
+        )).await?;
         
         // 处理爆炸猫
         if matches!(card.card_type, CardType::ExplodingKitten) {
             // 给玩家私下消息通知抽到爆炸猫
             let explode_response = WsResponse {
                 ok: true,
-                msg: Some("你抽到了爆炸猫！".to_string()),
                 payload: Some(serde_json::json!({
                     "card": card
                 })),
+                ..WsResponse::from_text(i18n::text(codes::YOU_DREW_EXPLODING_KITTEN, &[]))
             };
             
             // 检查玩家是否有拆除卡
@@ -727,11 +2096,12 @@ impl MatchService {
                 .any(|c| matches!(c.card_type, CardType::Defuse));
             
             // 私下通知玩家
-            self.connection_manager.send_to_client(
-                &user_id, 
+            profile.step(ActionStep::Broadcast, self.send_private(
+                match_id,
+                &user_id,
                 events::match_events::DRAW_CARD,
-                Some(serde_json::to_value(explode_response)?),
-            ).await?;
+                serde_json::to_value(explode_response)?,
+            )).await?;
             
             if has_defuse {
                 // 玩家有拆除卡，进入拆弹状态
@@ -744,6 +2114,14 @@ impl MatchService {
                     .unwrap();
                 
                 let defuse_card = match_data.players[player_index].hand.remove(defuse_index);
+                match_data.action_history.push(CardAction {
+                    action_type: CardActionType::Defuse,
+                    user_id: user_id.to_string(),
+                    card_id: Some(defuse_card.id.clone()),
+                    card_type: Some(CardType::Defuse),
+                    is_canceled: false,
+                    created_at: now,
+                });
                 
                 // 将拆除卡放入弃牌堆
                 match_data.discard_pile.push(defuse_card);
@@ -754,24 +2132,27 @@ impl MatchService {
                 // 广播拆弹成功事件
                 let defuse_response = WsResponse {
                     ok: true,
-                    msg: Some(format!("玩家 {} 使用拆除卡解除了爆炸猫", user_id)),
                     payload: Some(serde_json::json!({
                         "userId": user_id
                     })),
+                    ..WsResponse::from_text(i18n::text(codes::KITTEN_DEFUSED, &[("user", user_id.to_string())]))
                 };
                 
-                self.connection_manager.broadcast_to_room(
+                profile.step(ActionStep::Broadcast, self.connection_manager.broadcast_to_room(
                     match_id,
                     events::match_events::DEFUSE,
                     Some(serde_json::to_value(defuse_response)?),
-                ).await?;
+                )).await?;
                 
-                // 进入下一回合
-                self.change_turn(match_id).await?;
+                // 记录拆弹成就进度
+                self.record_achievement(user_id, AchievementMetric::KittensDefused).await;
                 
-                // 保存游戏数据
+                // 先保存，切换回合会重新读取游戏数据
                 match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
-                self.save_match(&match_data).await;
+                profile.step(ActionStep::CacheWrite, self.save_match(&match_data)).await;
+                
+                // 进入下一回合
+                profile.step(ActionStep::Effect, self.change_turn(match_id)).await?;
                 
                 // 返回抽到的牌
                 return Ok(Some(card));
@@ -781,35 +2162,41 @@ impl MatchService {
                 // 将玩家移到出局列表
                 let mut player = match_data.players.remove(player_index);
                 player.is_active = false;
+                player.is_turn = false;
                 match_data.out.push(player);
                 
+                // 回合索引指向上一个玩家，切换回合后轮到原本的下一个玩家
+                if !match_data.players.is_empty() {
+                    match_data.turn_index = (player_index + match_data.players.len() - 1) % match_data.players.len();
+                }
+                
+                // 先保存，切换回合和结束游戏都会重新读取游戏数据
+                match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
+                profile.step(ActionStep::CacheWrite, self.save_match(&match_data)).await;
+                
                 // 广播淘汰事件
                 let defeat_response = WsResponse {
                     ok: true,
-                    msg: Some(format!("玩家 {} 被爆炸猫炸死了", user_id)),
                     payload: Some(serde_json::json!({
                         "userId": user_id,
                         "reason": "explosion"
                     })),
+                    ..WsResponse::from_text(i18n::text(codes::PLAYER_EXPLODED, &[("user", user_id.to_string())]))
                 };
                 
-                self.connection_manager.broadcast_to_room(
+                profile.step(ActionStep::Broadcast, self.connection_manager.broadcast_to_room(
                     match_id,
                     events::match_events::DEFEAT,
                     Some(serde_json::to_value(defeat_response)?),
-                ).await?;
+                )).await?;
                 
                 // 检查游戏是否结束
-                if match_data.players.len() <= 1 {
+                if game_mode::mode_for(&match_data).winner(&match_data).is_some() {
                     // 使用新的游戏结束处理方法
-                    self.handle_game_end(match_id).await?;
+                    profile.step(ActionStep::Effect, self.handle_game_end(match_id)).await?;
                 } else {
                     // 游戏继续，切换到下一玩家
-                    self.change_turn(match_id).await?;
-                    
-                    // 保存游戏数据
-                    match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
-                    self.save_match(&match_data).await;
+                    profile.step(ActionStep::Effect, self.change_turn(match_id)).await?;
                 }
                 
                 return Ok(Some(card));
@@ -821,24 +2208,25 @@ impl MatchService {
             // 私下通知玩家抽到的牌
             let card_response = WsResponse {
                 ok: true,
-                msg: Some(format!("你抽到了 {:?}", card.card_type)),
                 payload: Some(serde_json::json!({
                     "card": card
                 })),
+                ..WsResponse::from_text(i18n::text(codes::YOU_DREW, &[("card", format!("{:?}", card.card_type))]))
             };
             
-            self.connection_manager.send_to_client(
+            profile.step(ActionStep::Broadcast, self.send_private(
+                match_id,
                 &user_id,
                 events::match_events::DRAW_CARD,
-                Some(serde_json::to_value(card_response)?),
-            ).await?;
-            
-            // 进入下一回合
-            self.change_turn(match_id).await?;
+                serde_json::to_value(card_response)?,
+            )).await?;
             
-            // 保存游戏数据
+            // 先保存，切换回合会重新读取游戏数据
             match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
-            self.save_match(&match_data).await;
+            profile.step(ActionStep::CacheWrite, self.save_match(&match_data)).await;
+            
+            // 进入下一回合
+            profile.step(ActionStep::Effect, self.change_turn(match_id)).await?;
             
             return Ok(Some(card));
         }
@@ -846,42 +2234,35 @@ impl MatchService {
     
     /// 出牌
     pub async fn play_card(&self, match_id: &str, user_id: &str, card_id: &str) -> Result<()> {
+        let mut profile = ActionProfile::start("play_card", match_id);
+        
         // 获取游戏数据
-        let mut match_data = self.get_match(match_id).await
+        let mut match_data = profile.step(ActionStep::CacheRead, self.get_match(match_id)).await
             .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
         
-        // 检查游戏状态
-        if match_data.state != MatchState::InProgress {
-            return Err(anyhow::anyhow!("游戏未开始或已结束"));
+        // 游戏暂停中，动作排队等待恢复
+        if match_data.state == MatchState::Paused {
+            self.queue_action(&mut match_data, user_id, CardActionType::Play, Some(card_id.to_string())).await?;
+            return Ok(());
         }
         
-        // 检查是否有连锁状态正在处理
-        if match_data.chain_state.is_some() {
-            return Err(anyhow::anyhow!("有连锁效果正在处理中，请稍后再试"));
-        }
+        // 校验出牌（游戏状态、连锁、回合和手牌）
+        profile.step_sync(ActionStep::Validation, || match_data.validate_play(user_id, card_id))?;
         
         // 查找玩家
         let player_index = match_data.players.iter().position(|p| p.user.id == user_id)
             .ok_or_else(|| anyhow::anyhow!("玩家不在游戏中"))?;
         
-        // 检查是否是玩家的回合（烦人卡例外，任何人可以打出）
-        let is_nope_card = match_data.players[player_index].hand.iter()
-            .any(|c| c.id == card_id && matches!(c.card_type, CardType::Nope));
-            
-        if !match_data.players[player_index].is_turn && !is_nope_card {
-            return Err(anyhow::anyhow!("不是该玩家的回合"));
-        }
-        
-        // 处理烦人卡特殊情况
-        if is_nope_card {
-            return self.play_nope(match_id, user_id, card_id).await;
-        }
-        
         // 查找卡牌
         let card_index = match_data.players[player_index].hand.iter()
             .position(|c| c.id == card_id)
             .ok_or_else(|| anyhow::anyhow!("卡牌不存在"))?;
         
+        // 处理烦人卡特殊情况
+        if matches!(match_data.players[player_index].hand[card_index].card_type, CardType::Nope) {
+            return profile.step(ActionStep::Effect, self.play_nope(match_id, user_id, card_id)).await;
+        }
+        
         // 获取卡牌
         let card = match_data.players[player_index].hand[card_index].clone();
         
@@ -903,26 +2284,73 @@ impl MatchService {
         
         // 保存游戏数据（确保卡牌已从手中移除并放入弃牌堆）
         match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
-        self.save_match(&match_data).await;
+        profile.step(ActionStep::CacheWrite, self.save_match(&match_data)).await;
         
         // 广播出牌事件
         let play_response = WsResponse {
             ok: true,
-            msg: Some(format!("玩家 {} 打出了 {:?}", user_id, card.card_type)),
             payload: Some(serde_json::json!({
                 "userId": user_id,
                 "card": card
             })),
+            ..WsResponse::from_text(i18n::text(codes::CARD_PLAYED, &[("user", user_id.to_string()), ("card", format!("{:?}", card.card_type))]))
         };
         
-        self.connection_manager.broadcast_to_room(
+        profile.step(ActionStep::Broadcast, self.connection_manager.broadcast_to_room(
             match_id,
             events::match_events::PLAY_CARD,
             Some(serde_json::to_value(play_response)?),
-        ).await?;
+        )).await?;
         
         // 启动连锁效果系统
-        self.start_card_chain(match_id, card_action).await?;
+        profile.step(ActionStep::Effect, self.start_card_chain(match_id, card_action)).await?;
+        
+        Ok(())
+    }
+    
+    /**
+     * 查询卡牌能否打出，结果只发送给请求的客户端
+     *
+     * 参数:
+     * @param match_id - 游戏ID
+     * @param user_id - 玩家ID
+     * @param card_id - 卡牌ID，为None时检查玩家的所有手牌
+     * @param client_id - 客户端ID
+     */
+    pub async fn can_play(&self, match_id: &str, user_id: &str, card_id: Option<&str>, client_id: &str) -> Result<()> {
+        let match_data = self.get_match(match_id).await
+            .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
+        
+        let card_ids = match card_id {
+            Some(card_id) => vec![card_id.to_string()],
+            None => match_data.players.iter()
+                .find(|p| p.user.id == user_id)
+                .map(|p| p.hand.iter().map(|c| c.id.clone()).collect())
+                .unwrap_or_default(),
+        };
+        let results = card_ids.iter().map(|card_id| {
+            let reason = match_data.validate_play(user_id, card_id).err().map(|e| e.to_string());
+            serde_json::json!({
+                "cardId": card_id,
+                "legal": reason.is_none(),
+                "reason": reason,
+            })
+        }).collect::<Vec<_>>();
+        
+        let response = WsResponse {
+            ok: true,
+            msg: None,
+            payload: Some(serde_json::json!({
+                "matchId": match_id,
+                "results": results,
+            })),
+            ..Default::default()
+        };
+        self.connection_manager.send_to_client(
+            client_id,
+            events::match_events::CAN_PLAY,
+            Some(serde_json::to_value(response)?),
+        ).await?;
         
         Ok(())
     }
@@ -933,26 +2361,78 @@ impl MatchService {
         let mut match_data = self.get_match(match_id).await
             .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
         
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+
+        // 当前玩家手牌超过上限时先弃牌，弃牌后再切换回合
+        if match_data.pending_action.is_none() {
+            let over_limit = match_data.players.get(match_data.turn_index)
+                .filter(|p| p.is_turn)
+                .map(|p| (p.user.id.clone(), hand_limit::excess(p.hand.len(), match_data.max_hand_size)))
+                .filter(|(_, count)| *count > 0);
+            if let Some((user_id, count)) = over_limit {
+                self.request_discard(&mut match_data, &user_id, count, now).await?;
+                match_data.updated_at = now;
+                self.save_match(&match_data).await;
+                return Ok(());
+            }
+        }
+
+        // 记录结束的回合，被淘汰玩家的最后一个回合不计入
+        if let (Some(current_player), Some(started_at)) = (
+            match_data.players.iter().find(|p| p.is_turn),
+            match_data.timers.turn_started_at,
+        ) {
+            match_data.turn_log.push(TurnRecord {
+                user_id: current_player.user.id.clone(),
+                started_at,
+                duration_ms: now.saturating_sub(started_at),
+            });
+        }
+        
         // 重置当前玩家的回合标志
         if let Some(current_player) = match_data.players.get_mut(match_data.turn_index) {
             current_player.is_turn = false;
         }
         
+        // 按回合计时器的剩余时间结算上一回合玩家的时间储备
+        if let Some(turn_user_id) = match_data.timers.turn_user_id.clone() {
+            let remaining = match_data.timers.turn_deadline
+                .map(|deadline| deadline.saturating_sub(now))
+                .or(match_data.timers.suspended_turn_ms)
+                .or(match_data.timers.frozen_turn_ms);
+            if let Some(remaining) = remaining {
+                let bank = match_data.time_banks.settle(&turn_user_id, remaining);
+                debug!("玩家 {} 剩余时间储备 {}ms", turn_user_id, bank);
+            }
+        }
+        
         // 计算下一个玩家的索引
         match_data.turn_index = (match_data.turn_index + 1) % match_data.players.len();
         
+        // 新回合重新计时，上一回合的跳过投票作废
+        match_data.timers.turn_started_at = Some(now);
+        match_data.timers.turn_deadline = None;
+        match_data.timers.suspended_turn_ms = None;
+        match_data.timers.turn_grace_ms = None;
+        self.timer_manager.cancel(match_id, TimerKind::Turn);
+        match_data.skip_votes.clear();
+        
         // 设置下一个玩家的回合标志
+        let mut next_user_id = None;
         if let Some(next_player) = match_data.players.get_mut(match_data.turn_index) {
             next_player.is_turn = true;
+            next_user_id = Some(next_player.user.id.clone());
             
             // 广播回合变更事件
             let turn_response = WsResponse {
                 ok: true,
-                msg: Some(format!("轮到玩家 {} 的回合", next_player.user.id)),
                 payload: Some(serde_json::json!({
                     "userId": next_player.user.id,
-                    "turnIndex": match_data.turn_index
+                    "turnIndex": match_data.turn_index,
+                    "turnTime": queue_constants::inactivity::COMMON,
+                    "timeBank": match_data.time_banks.remaining(&next_player.user.id)
                 })),
+                ..WsResponse::from_text(i18n::text(codes::TURN_CHANGED, &[("user", next_player.user.id.to_string())]))
             };
             
             self.connection_manager.broadcast_to_room(
@@ -966,26 +2446,56 @@ impl MatchService {
         match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
         self.save_match(&match_data).await;
         
+        // 下一个玩家开始计时，保存之后设置，避免计时信息被覆盖
+        if let Some(next_user_id) = next_user_id {
+            self.start_turn_timer(match_id, &next_user_id, &match_data.time_banks).await;
+        }
+        
         Ok(())
     }
     
+    /**
+     * 开始玩家的回合计时
+     *
+     * 超时时间为基础回合时间加剩余时间储备，到期时储备已耗尽，淘汰玩家。
+     *
+     * 参数:
+     * @param match_id - 游戏ID
+     * @param user_id - 当前回合的玩家ID
+     * @param time_banks - 游戏的时间储备
+     */
+    async fn start_turn_timer(&self, match_id: &str, user_id: &str, time_banks: &TimeBanks) {
+        let timeout = time_banks.turn_timeout(user_id, queue_constants::inactivity::COMMON);
+        self.setup_inactivity_timer(match_id, user_id, timeout).await;
+    }
+    
     /// 加入观战
     pub async fn join_spectator(&self, match_id: &str, user_info: UserInfo, client_id: &str) -> Result<()> {
         // 获取游戏数据
         let mut match_data = self.get_match(match_id).await
             .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
         
-        // 检查用户是否已经在游戏中
-        let is_player = match_data.players.iter().any(|p| p.user.id == user_info.id);
-        let is_out = match_data.out.iter().any(|p| p.user.id == user_info.id);
-        
-        if is_player || is_out {
-            return Err(anyhow::anyhow!("玩家已在游戏中，不能观战"));
-        }
-        
-        // 检查用户是否已经是观战者
-        if match_data.spectators.iter().any(|s| s.id == user_info.id) {
-            return Err(anyhow::anyhow!("用户已经在观战"));
+        // 检查观战权限和人数上限
+        let is_friend = match_data.spectator_policy == SpectatorPolicy::FriendsOnly
+            && self.is_friend_of_players(&match_data, &user_info.id).await;
+        if let Err(denial) = match_data.check_spectate(&user_info.id, is_friend) {
+            let response = WsResponse {
+                ok: false,
+                payload: Some(serde_json::json!({
+                    "matchId": match_id,
+                    "reason": denial,
+                    "spectatorPolicy": match_data.spectator_policy,
+                    "maxSpectators": match_data.max_spectators,
+                    "spectatorCount": match_data.spectators.len(),
+                })),
+                ..WsResponse::from_text(i18n::text(denial.code(), &[]))
+            };
+            self.connection_manager.send_to_client(
+                client_id,
+                events::match_events::JOIN_SPECTATORS,
+                Some(serde_json::to_value(response)?),
+            ).await?;
+            return Err(denial.into());
         }
         
         // 添加观战者
@@ -1003,10 +2513,10 @@ impl MatchService {
         // 广播有新观战者加入
         let spectator_response = WsResponse {
             ok: true,
-            msg: Some(format!("{} 加入观战", user_info.name)),
             payload: Some(serde_json::json!({
                 "user": user_info
             })),
+            ..WsResponse::from_text(i18n::text(codes::SPECTATOR_JOINED, &[("user", user_info.name.to_string())]))
         };
         
         self.connection_manager.broadcast_to_room(
@@ -1015,22 +2525,247 @@ impl MatchService {
             Some(serde_json::to_value(spectator_response)?),
         ).await?;
         
-        // 发送当前游戏状态给观战者
-        let game_response = WsResponse {
-            ok: true,
-            msg: Some("游戏状态".to_string()),
-            payload: Some(serde_json::to_value(&match_data)?),
+        // 发送当前游戏状态给观战者，支持增量同步的客户端发送快照
+        if self.connection_manager.get_client_protocol(client_id).await.supports(capabilities::MATCH_DELTA) {
+            self.send_match_state(match_id, client_id).await?;
+        } else {
+            let game_response = WsResponse {
+                ok: true,
+                payload: Some(serde_json::to_value(&match_data)?),
+                ..WsResponse::from_text(i18n::text(codes::MATCH_STATE, &[]))
+            };
+            
+            self.connection_manager.send_to_client(
+                client_id,
+                events::match_events::JOIN,
+                Some(serde_json::to_value(game_response)?),
+            ).await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// 用户是否为任一玩家的好友
+    async fn is_friend_of_players(&self, match_data: &MatchData, user_id: &str) -> bool {
+        let Some(passport_state) = crate::ws::global_passport_state() else {
+            return false;
         };
+        for player in match_data.players.iter().chain(match_data.out.iter()) {
+            match passport_state.get_user_friends(&player.user.id).await {
+                Ok(friends) if friends.iter().any(|id| id == user_id) => return true,
+                Ok(_) => {}
+                Err(e) => warn!("获取玩家 {} 的好友列表失败: {}", player.user.id, e),
+            }
+        }
+        false
+    }
+    
+    /**
+     * 设置游戏的观战权限和人数上限
+     *
+     * 参数:
+     * @param match_data - 游戏数据
+     * @param policy - 观战权限，None表示保持不变
+     * @param max_spectators - 观战人数上限，None表示保持不变，超过MAX_SPECTATORS_LIMIT时截断
+     */
+    pub async fn configure_spectators(
+        &self,
+        match_data: &mut MatchData,
+        policy: Option<SpectatorPolicy>,
+        max_spectators: Option<usize>,
+    ) -> Result<()> {
+        if let Some(policy) = policy {
+            match_data.spectator_policy = policy;
+        }
+        if let Some(max_spectators) = max_spectators {
+            match_data.max_spectators = max_spectators.min(MAX_SPECTATORS_LIMIT);
+        }
         
-        self.connection_manager.send_to_client(
-            client_id,
-            events::match_events::JOIN,
-            Some(serde_json::to_value(game_response)?),
-        ).await?;
+        match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
+        if !self.save_match(match_data).await {
+            return Err(anyhow::anyhow!("保存游戏数据失败"));
+        }
+        Ok(())
+    }
+    
+    /**
+     * 设置游戏模式，只能在游戏开始前设置
+     *
+     * 参数:
+     * @param match_data - 游戏数据
+     * @param mode_id - 已注册的游戏模式ID
+     */
+    pub async fn set_match_mode(&self, match_data: &mut MatchData, mode_id: &str) -> Result<()> {
+        if match_data.state != MatchState::Waiting {
+            return Err(anyhow::anyhow!("游戏已经开始，无法更改游戏模式"));
+        }
+        let mode = game_mode::global_game_modes().get(mode_id)
+            .ok_or_else(|| anyhow::anyhow!("不支持的游戏模式: {}", mode_id))?;
+        
+        match_data.mode = mode.id().to_string();
+        match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
+        if !self.save_match(match_data).await {
+            return Err(anyhow::anyhow!("保存游戏数据失败"));
+        }
+        Ok(())
+    }
+
+    /**
+     * 设置手牌上限，只能在游戏开始前设置
+     *
+     * 参数:
+     * @param match_data - 游戏数据
+     * @param max_hand_size - 手牌上限，0表示不限制
+     */
+    pub async fn set_max_hand_size(&self, match_data: &mut MatchData, max_hand_size: usize) -> Result<()> {
+        if match_data.state != MatchState::Waiting {
+            return Err(anyhow::anyhow!("游戏已经开始，无法更改手牌上限"));
+        }
+        match_data.max_hand_size = hand_limit::validate(max_hand_size)?;
+        match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
+        if !self.save_match(match_data).await {
+            return Err(anyhow::anyhow!("保存游戏数据失败"));
+        }
+        Ok(())
+    }
+    
+    /**
+     * 把等待中的游戏设为系列赛的第一局
+     *
+     * 参数:
+     * @param match_data - 游戏数据
+     * @param best_of - 局数，只支持3和5
+     *
+     * 返回:
+     * 创建的系列赛数据
+     */
+    pub async fn create_series(&self, match_data: &mut MatchData, best_of: u32) -> Result<SeriesData> {
+        if match_data.state != MatchState::Waiting {
+            return Err(anyhow::anyhow!("游戏已经开始，无法设为系列赛"));
+        }
+        if match_data.wager > 0 {
+            return Err(anyhow::anyhow!("系列赛不支持押注"));
+        }
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let series = SeriesData::new(Uuid::new_v4().to_string(), best_of, match_data.id.clone(), now)?;
+        if !series::save(&self.game_service, &series) {
+            return Err(anyhow::anyhow!("保存系列赛数据失败"));
+        }
+        
+        match_data.series_id = Some(series.id.clone());
+        match_data.updated_at = now;
+        if !self.save_match(match_data).await {
+            return Err(anyhow::anyhow!("保存游戏数据失败"));
+        }
+        Ok(series)
+    }
+    
+    /**
+     * 记录系列赛一局的结果，系列赛未结束时创建下一局
+     *
+     * 下一局使用相同的玩家、游戏类型、游戏模式和观战设置，座位在开始时轮换。
+     *
+     * 参数:
+     * @param match_data - 结束的游戏数据
+     * @param winner_id - 本局胜利者ID
+     *
+     * 返回:
+     * 更新后的系列赛数据，游戏不属于系列赛时返回None
+     */
+    async fn finish_series_round(&self, match_data: &MatchData, winner_id: &str) -> Option<SeriesData> {
+        let series_id = match_data.series_id.as_deref()?;
+        let Some(mut series) = series::load(&self.game_service, series_id) else {
+            warn!("游戏 {} 所属的系列赛 {} 不存在", match_data.id, series_id);
+            return None;
+        };
+        
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        if !series.record_round(&match_data.id, winner_id, now) {
+            match self.create_match(match_data.match_type.clone(), series.players.clone()).await {
+                Ok(mut next) => {
+                    next.series_id = Some(series.id.clone());
+                    next.mode = match_data.mode.clone();
+                    next.spectator_policy = match_data.spectator_policy;
+                    next.max_spectators = match_data.max_spectators;
+                    next.max_hand_size = match_data.max_hand_size;
+                    self.save_match(&next).await;
+                    series.current_match_id = next.id;
+                }
+                Err(e) => error!("创建系列赛 {} 的第 {} 局失败: {}", series.id, series.round(), e),
+            }
+        }
+        if !series::save(&self.game_service, &series) {
+            error!("保存系列赛 {} 失败", series.id);
+        }
         
+        info!("系列赛 {} 第 {} 局由 {} 获胜，比分 {:?}", series.id, series.rounds.len(), winner_id, series.scores);
+        Some(series)
+    }
+    
+    /// 向对局房间广播系列赛一局结束或整个系列赛结束
+    async fn broadcast_series_result(&self, match_id: &str, series: &SeriesData, rating_changes: &[RatingChange]) -> Result<()> {
+        let Some(last_round) = series.rounds.last() else {
+            return Ok(());
+        };
+        let (event, payload) = match series.state {
+            SeriesState::InProgress => (series::events::ROUND_END, serde_json::json!({
+                "seriesId": series.id,
+                "bestOf": series.best_of,
+                "round": series.rounds.len(),
+                "matchId": last_round.match_id,
+                "winnerId": last_round.winner_id,
+                "scores": series.scores,
+                "nextMatchId": series.current_match_id,
+            })),
+            SeriesState::Completed => (series::events::END, serde_json::json!({
+                "seriesId": series.id,
+                "bestOf": series.best_of,
+                "winnerId": series.winner_id,
+                "scores": series.scores,
+                "rounds": series.rounds,
+                "ratingChanges": rating_changes,
+            })),
+        };
+        let response = WsResponse {
+            ok: true,
+            msg: None,
+            payload: Some(payload),
+            ..Default::default()
+        };
+        self.connection_manager.broadcast_to_room(
+            match_id,
+            event,
+            Some(serde_json::to_value(response)?),
+        ).await?;
         Ok(())
     }
     
+    /**
+     * 按游戏模式生成对局数据视图，玩家只能看到自己的手牌，观众看不到任何手牌
+     *
+     * 视图的 `latency` 字段按玩家ID给出延迟等级，还没有测量结果的玩家不在其中
+     */
+    pub async fn match_view(&self, match_data: &MatchData, viewer: Option<&str>) -> Result<serde_json::Value> {
+        let mode = game_mode::mode_for(match_data);
+        let mut view = match viewer {
+            Some(user_id) if match_data.players.iter().chain(match_data.out.iter()).any(|p| p.user.id == user_id) => {
+                mode.private_view(match_data, user_id)?
+            }
+            _ => mode.public_view(match_data)?,
+        };
+        
+        let mut latency = serde_json::Map::new();
+        for player in &match_data.players {
+            if let Some(rtt) = self.player_latency(&player.user.id).await {
+                latency.insert(player.user.id.clone(), serde_json::to_value(LatencyBucket::from_rtt(rtt))?);
+            }
+        }
+        if let Some(view) = view.as_object_mut() {
+            view.insert("latency".to_string(), serde_json::Value::Object(latency));
+        }
+        Ok(view)
+    }
+    
     /// 离开观战
     pub async fn leave_spectator(&self, match_id: &str, user_id: &str, client_id: &str) -> Result<()> {
         // 获取游戏数据
@@ -1056,10 +2791,10 @@ impl MatchService {
             // 广播观战者离开
             let spectator_response = WsResponse {
                 ok: true,
-                msg: Some(format!("{} 离开观战", spectator.name)),
                 payload: Some(serde_json::json!({
                     "userId": user_id
                 })),
+                ..WsResponse::from_text(i18n::text(codes::SPECTATOR_LEFT, &[("user", spectator.name.to_string())]))
             };
             
             self.connection_manager.broadcast_to_room(
@@ -1076,6 +2811,19 @@ impl MatchService {
     
     /// 处理玩家超时
     pub async fn handle_player_timeout(&self, match_id: &str, user_id: &str) -> Result<()> {
+        self.eliminate_turn_player(match_id, user_id, "timeout", codes::PLAYER_TIMED_OUT).await
+    }
+    
+    /**
+     * 淘汰当前回合的玩家并切换回合
+     *
+     * 参数:
+     * @param match_id - 游戏ID
+     * @param user_id - 被淘汰的玩家ID，必须是当前回合的玩家
+     * @param reason - 淘汰原因，随失败事件下发
+     * @param code - 失败事件的消息代码
+     */
+    async fn eliminate_turn_player(&self, match_id: &str, user_id: &str, reason: &str, code: &'static str) -> Result<()> {
         // 获取游戏数据
         let mut match_data = self.get_match(match_id).await
             .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
@@ -1091,38 +2839,48 @@ impl MatchService {
         if let Some(index) = player_index {
             // 检查是否是当前玩家的回合
             if match_data.players[index].is_turn {
-                // 玩家超时，移到出局列表
+                // 玩家移到出局列表
                 let mut player = match_data.players.remove(index);
                 player.is_active = false;
+                player.is_turn = false;
                 match_data.out.push(player);
+                match_data.action_history.push(CardAction::eliminate(user_id));
+                match_data.timers.turn_deadline = None;
+                match_data.timers.suspended_turn_ms = None;
+                self.timer_manager.cancel(match_id, TimerKind::Turn);
+                
+                // 回合索引指向上一个玩家，切换回合后轮到原本的下一个玩家
+                if !match_data.players.is_empty() {
+                    match_data.turn_index = (index + match_data.players.len() - 1) % match_data.players.len();
+                }
                 
-                // 广播超时事件
-                let timeout_response = WsResponse {
+                // 先保存，切换回合和结束游戏都会重新读取游戏数据
+                match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
+                self.save_match(&match_data).await;
+                
+                // 广播失败事件
+                let defeat_response = WsResponse {
                     ok: true,
-                    msg: Some(format!("玩家 {} 因超时而出局", user_id)),
                     payload: Some(serde_json::json!({
                         "userId": user_id,
-                        "reason": "timeout"
+                        "reason": reason
                     })),
+                    ..WsResponse::from_text(i18n::text(code, &[("user", user_id.to_string())]))
                 };
                 
                 self.connection_manager.broadcast_to_room(
                     match_id,
                     events::match_events::DEFEAT,
-                    Some(serde_json::to_value(timeout_response)?),
+                    Some(serde_json::to_value(defeat_response)?),
                 ).await?;
                 
                 // 检查游戏是否结束
-                if match_data.players.len() <= 1 {
+                if game_mode::mode_for(&match_data).winner(&match_data).is_some() {
                     // 使用新的游戏结束处理方法
                     self.handle_game_end(match_id).await?;
                 } else {
                     // 游戏继续，切换到下一玩家
                     self.change_turn(match_id).await?;
-                    
-                    // 保存游戏数据
-                    match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
-                    self.save_match(&match_data).await;
                 }
                 
                 Ok(())
@@ -1134,19 +2892,480 @@ impl MatchService {
         }
     }
     
-    /// 设置超时处理
+    /**
+     * 投票跳过长时间未操作的当前回合玩家
+     *
+     * 当前回合玩家未操作超过SKIP_VOTE_MIN_IDLE_MS后，其他玩家可以投票，
+     * 其他玩家中的多数同意后该玩家按超时处理淘汰，不必等待不活跃计时器到期。
+     *
+     * 参数:
+     * @param match_id - 游戏ID
+     * @param voter_id - 投票的玩家ID
+     * @param target_id - 客户端认为的当前回合玩家ID，与实际不符时拒绝投票，避免回合切换后误投
+     * @param accept - 是否同意跳过
+     *
+     * 返回:
+     * 投票是否已通过
+     */
+    pub async fn vote_skip(&self, match_id: &str, voter_id: &str, target_id: Option<&str>, accept: bool) -> Result<bool> {
+        let mut match_data = self.get_match(match_id).await
+            .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
+        
+        if match_data.state != MatchState::InProgress {
+            return Err(anyhow::anyhow!("游戏未开始、已暂停或已结束"));
+        }
+        if !match_data.players.iter().any(|p| p.user.id == voter_id) {
+            return Err(anyhow::anyhow!("玩家不在游戏中"));
+        }
+        
+        let current_id = match_data.players.get(match_data.turn_index)
+            .filter(|p| p.is_turn)
+            .map(|p| p.user.id.clone())
+            .ok_or_else(|| anyhow::anyhow!("当前没有进行中的回合"))?;
+        if target_id.map(|t| t != current_id).unwrap_or(false) {
+            return Err(anyhow::anyhow!("投票对象不是当前回合的玩家"));
+        }
+        if current_id == voter_id {
+            return Err(anyhow::anyhow!("不能投票跳过自己"));
+        }
+        
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let idle = now.saturating_sub(match_data.timers.turn_started_at.unwrap_or(match_data.updated_at));
+        if idle < SKIP_VOTE_MIN_IDLE_MS {
+            return Err(anyhow::anyhow!("玩家未操作的时间不足 {} 秒，暂不能投票跳过", SKIP_VOTE_MIN_IDLE_MS / 1000));
+        }
+        
+        match_data.skip_votes.insert(voter_id.to_string(), accept);
+        let (votes, required) = match_data.skip_vote_progress();
+        let passed = votes >= required;
+        
+        match_data.updated_at = now;
+        self.save_match(&match_data).await;
+        
+        let response = WsResponse {
+            ok: true,
+            payload: Some(serde_json::json!({
+                "matchId": match_id,
+                "targetId": current_id,
+                "votes": votes,
+                "required": required,
+                "passed": passed,
+                "turnDeadline": match_data.timers.turn_deadline
+            })),
+            ..WsResponse::from_text(i18n::text(codes::SKIP_VOTE_PROGRESS, &[
+                ("user", voter_id.to_string()),
+                ("target", current_id.clone()),
+                ("votes", votes.to_string()),
+                ("required", required.to_string()),
+            ]))
+        };
+        self.connection_manager.broadcast_to_room(
+            match_id,
+            events::match_events::VOTE_SKIP,
+            Some(serde_json::to_value(response)?),
+        ).await?;
+        
+        if passed {
+            info!("游戏 {} 中玩家 {} 被投票跳过 ({}/{})", match_id, current_id, votes, required);
+            self.eliminate_turn_player(match_id, &current_id, "vote_skip", codes::PLAYER_SKIPPED).await?;
+        }
+        
+        Ok(passed)
+    }
+    
+    /**
+     * 请求暂停或对暂停请求投票
+     *
+     * 没有进行中的暂停投票时发起投票（发起者视为同意），否则记录投票。
+     * 所有仍在游戏中的玩家都同意后暂停生效，任一玩家反对则投票失败，
+     * 投票在PAUSE_VOTE_TTL_MS内未结束则自动作废。
+     *
+     * 参数:
+     * @param match_id - 游戏ID
+     * @param user_id - 玩家ID
+     * @param accept - 是否同意暂停
+     *
+     * 返回:
+     * 暂停是否已生效
+     */
+    pub async fn pause_match(&self, match_id: &str, user_id: &str, accept: bool) -> Result<bool> {
+        let mut match_data = self.get_match(match_id).await
+            .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
+        
+        if match_data.state != MatchState::InProgress {
+            return Err(anyhow::anyhow!("只有进行中的游戏可以暂停"));
+        }
+        if !match_data.players.iter().any(|p| p.user.id == user_id) {
+            return Err(anyhow::anyhow!("玩家不在游戏中"));
+        }
+        
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let new_request = match match_data.pause.as_mut() {
+            Some(pause) => {
+                pause.votes.insert(user_id.to_string(), accept);
+                false
+            }
+            None => {
+                if !accept {
+                    return Err(anyhow::anyhow!("没有进行中的暂停投票"));
+                }
+                match_data.pause = Some(PauseState {
+                    requested_by: user_id.to_string(),
+                    requested_at: now,
+                    votes: HashMap::from([(user_id.to_string(), true)]),
+                    paused_at: None,
+                    resume_deadline: None,
+                });
+                true
+            }
+        };
+        
+        match match_data.pause_vote_result() {
+            Some(true) => {
+                self.apply_pause(&mut match_data, now).await?;
+                Ok(true)
+            }
+            Some(false) => {
+                match_data.pause = None;
+                match_data.updated_at = now;
+                self.save_match(&match_data).await;
+                
+                let response = WsResponse {
+                    ok: true,
+                    payload: Some(serde_json::json!({
+                        "matchId": match_id,
+                        "status": "rejected",
+                        "userId": user_id
+                    })),
+                    ..WsResponse::from_text(i18n::text(codes::PAUSE_REJECTED, &[("user", user_id.to_string())]))
+                };
+                self.connection_manager.broadcast_to_room(
+                    match_id,
+                    events::match_events::PAUSE,
+                    Some(serde_json::to_value(response)?),
+                ).await?;
+                Ok(false)
+            }
+            None => {
+                match_data.updated_at = now;
+                self.save_match(&match_data).await;
+                
+                let pause = match_data.pause.as_ref().expect("暂停投票已创建");
+                let (status, text) = if new_request {
+                    ("requested", i18n::text(codes::PAUSE_REQUESTED, &[("user", user_id.to_string())]))
+                } else {
+                    ("voting", i18n::text(codes::PAUSE_VOTED, &[("user", user_id.to_string())]))
+                };
+                let response = WsResponse {
+                    ok: true,
+                    payload: Some(serde_json::json!({
+                        "matchId": match_id,
+                        "status": status,
+                        "requestedBy": pause.requested_by,
+                        "votes": pause.votes,
+                        "expiresAt": pause.requested_at + PAUSE_VOTE_TTL_MS
+                    })),
+                    ..WsResponse::from_text(text)
+                };
+                self.connection_manager.broadcast_to_room(
+                    match_id,
+                    events::match_events::PAUSE,
+                    Some(serde_json::to_value(response)?),
+                ).await?;
+                
+                if new_request {
+                    self.spawn_pause_vote_expiry(match_id, now);
+                }
+                Ok(false)
+            }
+        }
+    }
+    
+    /// 投票通过后暂停游戏：冻结计时器并设置自动恢复时间
+    async fn apply_pause(&self, match_data: &mut MatchData, now: u64) -> Result<()> {
+        let resume_deadline = now + MAX_PAUSE_DURATION_MS;
+        if let Some(pause) = match_data.pause.as_mut() {
+            pause.paused_at = Some(now);
+            pause.resume_deadline = Some(resume_deadline);
+        }
+        match_data.state = MatchState::Paused;
+        match_data.timers.freeze(now);
+        self.timer_manager.cancel(&match_data.id, TimerKind::Turn);
+        self.timer_manager.cancel(&match_data.id, TimerKind::Chain);
+        self.timer_manager.cancel(&match_data.id, TimerKind::Selection);
+        match_data.updated_at = now;
+        self.save_match(match_data).await;
+        
+        info!("游戏 {} 已暂停，最晚于 {} 自动恢复", match_data.id, resume_deadline);
+        
+        let response = WsResponse {
+            ok: true,
+            payload: Some(serde_json::json!({
+                "matchId": match_data.id,
+                "status": "paused",
+                "pausedAt": now,
+                "resumeDeadline": resume_deadline
+            })),
+            ..WsResponse::from_text(i18n::text(codes::MATCH_PAUSED, &[]))
+        };
+        self.connection_manager.broadcast_to_room(
+            &match_data.id,
+            events::match_events::PAUSE,
+            Some(serde_json::to_value(response)?),
+        ).await?;
+        
+        // 到达最长暂停时间后自动恢复
+        let match_service = self.clone();
+        let match_id = match_data.id.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(MAX_PAUSE_DURATION_MS)).await;
+            
+            let still_paused = match_service.get_match(&match_id).await
+                .and_then(|m| m.pause)
+                .map(|p| p.paused_at == Some(now))
+                .unwrap_or(false);
+            if still_paused {
+                if let Err(e) = match_service.resume_match(&match_id, None).await {
+                    error!("自动恢复游戏 {} 失败: {}", match_id, e);
+                }
+            }
+        });
+        
+        Ok(())
+    }
+    
+    /// 暂停投票到期后作废
+    fn spawn_pause_vote_expiry(&self, match_id: &str, requested_at: u64) {
+        let match_service = self.clone();
+        let match_id = match_id.to_string();
+        
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(PAUSE_VOTE_TTL_MS)).await;
+            
+            let Some(mut match_data) = match_service.get_match(&match_id).await else {
+                return;
+            };
+            let expired = match_data.pause.as_ref()
+                .map(|p| !p.is_active() && p.requested_at == requested_at)
+                .unwrap_or(false);
+            if !expired {
+                return;
+            }
+            
+            match_data.pause = None;
+            match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
+            match_service.save_match(&match_data).await;
+            
+            let response = WsResponse {
+                ok: true,
+                payload: Some(serde_json::json!({
+                    "matchId": match_id,
+                    "status": "expired"
+                })),
+                ..WsResponse::from_text(i18n::text(codes::PAUSE_EXPIRED, &[]))
+            };
+            if let Ok(value) = serde_json::to_value(response) {
+                if let Err(e) = match_service.connection_manager.broadcast_to_room(
+                    &match_id,
+                    events::match_events::PAUSE,
+                    Some(value),
+                ).await {
+                    error!("广播暂停投票过期失败: {}", e);
+                }
+            }
+        });
+    }
+    
+    /**
+     * 恢复暂停的游戏
+     *
+     * 按暂停时记录的剩余时间重新启动回合和连锁计时器，然后按顺序执行暂停期间排队的动作。
+     *
+     * 参数:
+     * @param match_id - 游戏ID
+     * @param user_id - 发起恢复的玩家ID，到达最长暂停时间自动恢复时为None
+     */
+    pub async fn resume_match(&self, match_id: &str, user_id: Option<&str>) -> Result<()> {
+        let mut match_data = self.get_match(match_id).await
+            .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
+        
+        if match_data.state != MatchState::Paused {
+            return Err(anyhow::anyhow!("游戏没有暂停"));
+        }
+        if let Some(user_id) = user_id {
+            if !match_data.players.iter().any(|p| p.user.id == user_id) {
+                return Err(anyhow::anyhow!("玩家不在游戏中"));
+            }
+        }
+        
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let paused_at = match_data.pause.take().and_then(|p| p.paused_at).unwrap_or(now);
+        match_data.state = MatchState::InProgress;
+        
+        // 暂停的时间不计入回合时间
+        if let Some(started_at) = match_data.timers.turn_started_at.as_mut() {
+            *started_at += now.saturating_sub(paused_at);
+        }
+        let frozen_turn = match_data.timers.frozen_turn_ms.take();
+        let frozen_chain = match_data.timers.frozen_chain_ms.take();
+        let queued = std::mem::take(&mut match_data.queued_actions);
+        
+        // 先记录连锁结束时间再保存，避免保存前连锁被提前结束
+        let chain_deadline = frozen_chain
+            .filter(|_| match_data.chain_state.is_some())
+            .map(|remaining| (now + remaining, remaining));
+        match_data.timers.chain_deadline = chain_deadline.map(|(deadline, _)| deadline);
+        
+        // 目标选择的超时时间顺延暂停的时长
+        if let Some(pending) = match_data.pending_action.as_mut() {
+            pending.deadline = now + pending.deadline.saturating_sub(paused_at);
+        }
+        match_data.updated_at = now;
+        self.save_match(&match_data).await;
+        
+        info!("游戏 {} 已恢复，待执行动作 {} 个", match_id, queued.len());
+        
+        let response = WsResponse {
+            ok: true,
+            payload: Some(serde_json::json!({
+                "matchId": match_id,
+                "resumedBy": user_id,
+                "auto": user_id.is_none(),
+                "queuedActions": queued.len()
+            })),
+            ..WsResponse::from_text(i18n::text(codes::MATCH_RESUMED, &[]))
+        };
+        self.connection_manager.broadcast_to_room(
+            match_id,
+            events::match_events::RESUME,
+            Some(serde_json::to_value(response)?),
+        ).await?;
+        
+        // 按剩余时间重新计时
+        if let Some((deadline, remaining)) = chain_deadline {
+            self.spawn_chain_timer(match_id, deadline, remaining);
+        }
+        if let (Some(remaining), Some(turn_user_id)) = (frozen_turn, match_data.timers.turn_user_id.clone()) {
+            self.setup_inactivity_timer(match_id, &turn_user_id, remaining).await;
+        }
+        if let Some(pending) = &match_data.pending_action {
+            self.spawn_selection_timer(match_id, &pending.id, pending.deadline, pending.deadline.saturating_sub(now));
+        }
+        
+        // 执行排队的动作，单个动作失败不影响后续动作
+        for action in queued {
+            let result = match (&action.action_type, &action.card_id) {
+                (CardActionType::Play, Some(card_id)) => self.play_card(match_id, &action.user_id, card_id).await,
+                _ => self.draw_card(match_id, &action.user_id).await.map(|_| ()),
+            };
+            if let Err(e) = result {
+                warn!("执行玩家 {} 排队的动作失败: {}", action.user_id, e);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// 游戏暂停期间将玩家动作加入队列
+    async fn queue_action(
+        &self,
+        match_data: &mut MatchData,
+        user_id: &str,
+        action_type: CardActionType,
+        card_id: Option<String>,
+    ) -> Result<()> {
+        if !match_data.players.iter().any(|p| p.user.id == user_id) {
+            return Err(anyhow::anyhow!("玩家不在游戏中"));
+        }
+        
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        match_data.queued_actions.push(QueuedAction {
+            user_id: user_id.to_string(),
+            action_type,
+            card_id,
+            queued_at: now,
+        });
+        match_data.updated_at = now;
+        self.save_match(match_data).await;
+        
+        let response = WsResponse {
+            ok: true,
+            payload: Some(serde_json::json!({
+                "matchId": match_data.id,
+                "status": "action_queued",
+                "userId": user_id,
+                "queuedActions": match_data.queued_actions.len()
+            })),
+            ..WsResponse::from_text(i18n::text(codes::ACTION_QUEUED, &[("user", user_id.to_string())]))
+        };
+        self.connection_manager.broadcast_to_room(
+            &match_data.id,
+            events::match_events::PAUSE,
+            Some(serde_json::to_value(response)?),
+        ).await?;
+        
+        Ok(())
+    }
+    
+    /// 玩家的往返延迟（毫秒），玩家有多个连接时取最低的延迟
+    pub async fn player_latency(&self, user_id: &str) -> Option<u64> {
+        let mut client_ids = match crate::ws::global_passport_state() {
+            Some(passport_state) => passport_state.get_user_sessions(user_id).await,
+            None => Vec::new(),
+        };
+        if client_ids.is_empty() {
+            // 目前使用客户端ID作为用户ID
+            client_ids.push(user_id.to_string());
+        }
+        self.connection_manager.latency().best(&client_ids)
+    }
+    
+    /// 设置超时处理，高延迟玩家每回合第一次计时时获得额外的宽限时间
     pub async fn setup_inactivity_timer(&self, match_id: &str, user_id: &str, timeout: u64) {
+        // 记录超时时间，暂停时据此冻结剩余时间
+        let mut timeout = timeout;
+        let mut deadline = chrono::Utc::now().timestamp_millis() as u64 + timeout;
+        if let Some(mut match_data) = self.get_match(match_id).await {
+            match_data.timers.turn_user_id = Some(user_id.to_string());
+            if match_data.timers.turn_grace_ms.is_none() {
+                let rtt = self.player_latency(user_id).await;
+                let grace = self.connection_manager.latency().config().turn_grace(rtt);
+                if grace > 0 {
+                    debug!("玩家 {} 延迟 {:?}ms，回合计时宽限 {}ms", user_id, rtt, grace);
+                }
+                match_data.timers.turn_grace_ms = Some(grace);
+                timeout += grace;
+                deadline += grace;
+            }
+            
+            // 连锁等待和目标选择期间回合计时暂停，结束后再开始计时
+            if match_data.chain_state.is_some() || match_data.pending_action.is_some() {
+                match_data.timers.turn_deadline = None;
+                match_data.timers.suspended_turn_ms = Some(timeout);
+                self.timer_manager.cancel(match_id, TimerKind::Turn);
+                self.save_match(&match_data).await;
+                return;
+            }
+            
+            match_data.timers.turn_deadline = Some(deadline);
+            match_data.timers.suspended_turn_ms = None;
+            self.save_match(&match_data).await;
+        }
+        
         let match_service = self.clone();
         let match_id_clone = match_id.to_string();
         let user_id_clone = user_id.to_string();
         
-        tokio::spawn(async move {
-            // 延迟指定时间
-            sleep(Duration::from_millis(timeout)).await;
-            
+        self.timer_manager.schedule(match_id, TimerKind::Turn, deadline, timeout, move || async move {
             // 检查游戏是否还存在及用户是否还在游戏中
             match match_service.get_match(&match_id_clone).await {
                 Some(match_data) => {
+                    // 计时器已被暂停冻结或重新设置
+                    if match_data.timers.turn_deadline != Some(deadline) {
+                        debug!("游戏 {} 的回合计时器已失效，忽略超时处理", match_id_clone);
+                        return;
+                    }
+                    
                     if match_data.state == MatchState::InProgress {
                         // 找到当前回合的玩家
                         let current_player = match_data.players.get(match_data.turn_index);
@@ -1170,7 +3389,7 @@ impl MatchService {
     
     /// 更新玩家评分
     /// 使用 ELO 评分系统计算并更新所有玩家的评分
-    pub async fn update_player_ratings(&self, match_id: &str) -> Result<()> {
+    pub async fn update_player_ratings(&self, match_id: &str) -> Result<Vec<RatingChange>> {
         // 获取游戏数据
         let match_data = self.get_match(match_id).await
             .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
@@ -1188,6 +3407,13 @@ impl MatchService {
         // 找到胜利者
         let winner = match_data.players.iter().find(|p| p.is_winner);
         
+        // 本局之前已完成的场数和连胜/连败场数，用于调整K因子和评分修正
+        let player_stats = |user_id: &str| self.stats_service.get_stats(user_id).unwrap_or_default();
+        let total_players = all_players.len();
+        // 出局列表按出局顺序排列
+        let first_out = match_data.out.first().map(|p| p.user.id.clone());
+        let config = rating::rating_config();
+        
         if let Some(winner) = winner {
             info!("计算玩家 {} 的新评分（胜利）", winner.user.id);
             
@@ -1197,15 +3423,35 @@ impl MatchService {
                 .map(|p| p.user.rating)
                 .collect();
             
-            // 计算胜利者的新评分
-            let new_rating = elo::if_won(winner.user.rating, &opponent_ratings);
+            // 计算胜利者的新评分，再应用连胜和逆转奖励
+            let stats = player_stats(&winner.user.id);
+            let base_rating = elo::rating_after(
+                winner.user.rating,
+                stats.played,
+                &opponent_ratings,
+                MatchOutcome::Victory,
+            );
+            let (new_rating, modifiers) = config.adjust(winner.user.rating, base_rating, &RatingContext {
+                won: true,
+                streak_before: stats.current_streak,
+                first_out: false,
+                players: total_players,
+            });
             
             // 记录评分变化
-            info!("玩家 {} 的评分从 {} 更新为 {} （+{}）", 
+            info!("玩家 {} 的评分从 {} 更新为 {} （+{}，修正 {:?}）", 
                  winner.user.id, 
                  winner.user.rating, 
                  new_rating,
-                 new_rating - winner.user.rating);
+                 new_rating - winner.user.rating,
+                 modifiers);
+            
+            let mut changes = vec![RatingChange {
+                user_id: winner.user.id.clone(),
+                rating_before: winner.user.rating,
+                rating_after: new_rating,
+                modifiers,
+            }];
             
             // 更新数据库中的玩家评分
             // 注意：在实际实现中，这里应该调用数据库或用户服务来更新永久存储的评分
@@ -1217,26 +3463,53 @@ impl MatchService {
                 // 收集对手评分，包括胜利者
                 let opponent_ratings = vec![winner.user.rating];
                 
-                // 计算新评分
-                let new_rating = elo::if_lost(player.user.rating, &opponent_ratings);
+                // 计算新评分，第一个出局的玩家减少扣分
+                let stats = player_stats(&player.user.id);
+                let base_rating = elo::rating_after(
+                    player.user.rating,
+                    stats.played,
+                    &opponent_ratings,
+                    MatchOutcome::Defeat,
+                );
+                let (new_rating, modifiers) = config.adjust(player.user.rating, base_rating, &RatingContext {
+                    won: false,
+                    streak_before: stats.current_streak,
+                    first_out: first_out.as_deref() == Some(player.user.id.as_str()),
+                    players: total_players,
+                });
                 
                 // 记录评分变化
-                info!("玩家 {} 的评分从 {} 更新为 {} （{}）", 
+                info!("玩家 {} 的评分从 {} 更新为 {} （{}，修正 {:?}）", 
                      player.user.id, 
                      player.user.rating, 
                      new_rating,
-                     new_rating - player.user.rating);
+                     new_rating - player.user.rating,
+                     modifiers);
+                
+                changes.push(RatingChange {
+                    user_id: player.user.id.clone(),
+                    rating_before: player.user.rating,
+                    rating_after: new_rating,
+                    modifiers,
+                });
                 
                 // 更新数据库中的玩家评分
                 // await update_user_rating_in_database(player.user.id, new_rating);
             }
             
-            Ok(())
+            Ok(changes)
         } else {
             Err(anyhow::anyhow!("游戏已结束但未找到胜利者"))
         }
     }
     
+    /// 记录成就进度，失败时仅记录日志，不影响对局流程
+    async fn record_achievement(&self, user_id: &str, metric: AchievementMetric) {
+        if let Err(e) = self.achievement_service.record(user_id, metric, 1).await {
+            error!("记录成就进度失败: {}", e);
+        }
+    }
+
     /// 处理游戏结束
     async fn handle_game_end(&self, match_id: &str) -> Result<()> {
         // 获取游戏数据
@@ -1248,33 +3521,48 @@ impl MatchService {
             return Err(anyhow::anyhow!("游戏未处于进行中状态"));
         }
         
-        // 如果只剩一名玩家，游戏结束
-        if match_data.players.len() <= 1 {
+        // 游戏模式判定出胜利者时，游戏结束
+        if let Some(winner_id) = game_mode::mode_for(&match_data).winner(&match_data) {
             {
                 // 使用代码块来限制可变引用的作用域
-                let last_player = match_data.players.first_mut().unwrap();
                 // 标记为胜利者
-                last_player.is_winner = true;
+                if let Some(winner) = match_data.players.iter_mut().find(|p| p.user.id == winner_id) {
+                    winner.is_winner = true;
+                }
                 
                 // 更新游戏状态
                 match_data.state = MatchState::Completed;
+                match_replay::reveal_seed(&mut match_data);
+                shuffle_proof::reveal(&mut match_data);
                 match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
-            } // last_player的可变引用在这里结束
+            } // winner的可变引用在这里结束
             
             // 克隆数据供后续使用
             let match_data_clone = match_data.clone();
             self.save_match(&match_data).await;
             
-            // 获取胜利者的用户ID用于响应
-            let winner_id = match_data.players.first().unwrap().user.id.clone();
+            // 先发放奖池，后续的广播失败不会让押注留在托管中
+            let payout = self.award_pot(&mut match_data, &winner_id).await;
+            
+            // 系列赛未结束时只记录比分并创建下一局，评分、统计和成就在系列赛结束时更新
+            let series = self.finish_series_round(&match_data_clone, &winner_id).await;
+            let series_over = !matches!(&series, Some(s) if s.state == SeriesState::InProgress);
+            
+            // 更新玩家评分
+            let rating_changes = if series_over {
+                self.update_player_ratings(match_id).await.unwrap_or_else(|e| {
+                    error!("更新玩家评分失败: {}", e);
+                    Vec::new()
+                })
+            } else {
+                Vec::new()
+            };
             
-            // 广播胜利事件
+            // 广播胜利事件，启用签名时附带签名的对局结果
             let victory_response = WsResponse {
                 ok: true,
-                msg: Some(format!("玩家 {} 获胜", winner_id)),
-                payload: Some(serde_json::json!({
-                    "userId": winner_id
-                })),
+                payload: Some(victory_payload(&match_data_clone, &winner_id, &rating_changes)),
+                ..WsResponse::from_text(i18n::text(codes::PLAYER_WON, &[("user", winner_id.to_string())]))
             };
             
             self.connection_manager.broadcast_to_room(
@@ -1283,30 +3571,88 @@ impl MatchService {
                 Some(serde_json::to_value(victory_response)?),
             ).await?;
             
-            // 广播游戏结束事件
-            let end_response = WsResponse {
-                ok: true,
-                msg: Some("游戏结束".to_string()),
-                payload: Some(serde_json::to_value(&match_data_clone)?),
-            };
+            // 启用存证时把签名的对局结果提交到链上
+            if match_attestation::global_match_attestor().is_some() {
+                let full = self.with_full_history(match_data_clone.clone());
+                match_attestation::attest_match(&full, &winner_id, &rating_changes);
+            }
             
-            self.connection_manager.broadcast_to_room(
+            // 广播游戏结束事件
+            self.broadcast_match_event(
                 match_id,
                 events::match_events::END,
-                Some(serde_json::to_value(end_response)?),
+                i18n::text(codes::MATCH_ENDED, &[]),
+                &match_data_clone,
             ).await?;
             
-            // 更新玩家评分
-            if let Err(e) = self.update_player_ratings(match_id).await {
-                error!("更新玩家评分失败: {}", e);
+            // 广播系列赛的比分或结果
+            if let Some(series) = &series {
+                self.broadcast_series_result(match_id, series, &rating_changes).await?;
             }
             
-            return Ok(());
+            // 更新玩家统计与评分历史
+            if series_over {
+                self.stats_service.record_match(&match_data_clone, &rating_changes);
+            }
+            self.penalty_service.record_match(&match_data_clone);
+            record_search_interactions(&match_data_clone);
+            
+            // 记录胜利成就进度
+            if series_over {
+                self.record_achievement(&winner_id, AchievementMetric::GamesWon).await;
+            }
+            
+            // 清理对局作用域内的缓存数据，游戏记录本身按过期时间清理
+            self.clear_match_scope(match_id);
+            
+            return payout;
         }
         
         Err(anyhow::anyhow!("游戏尚未达到结束条件"))
     }
 
+    /**
+     * 处理抽牌时牌堆已空
+     *
+     * 按游戏模式的规则把弃牌洗回牌堆，洗牌的随机数由种子和即将记录的抽牌动作的位置决定，便于重放验证。
+     * 无法补充时标记牌堆已抽完并保存，调用方随后结束游戏。
+     *
+     * 参数:
+     * @param match_data - 牌堆为空的游戏数据
+     *
+     * 返回:
+     * 牌堆已补充时返回true
+     */
+    async fn handle_deck_exhausted(&self, match_data: &mut MatchData) -> Result<bool> {
+        let mode = game_mode::mode_for(match_data);
+        let reshuffled = game_mode::refill_deck(mode.as_ref(), match_data, &mut match_replay::next_action_rng(match_data));
+        if reshuffled == 0 {
+            match_data.deck_exhausted = true;
+            match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
+            self.save_match(match_data).await;
+        }
+        info!("游戏 {} 牌堆已抽完，洗回 {} 张牌", match_data.id, reshuffled);
+        
+        let code = if reshuffled > 0 { codes::DECK_RESHUFFLED } else { codes::DECK_EXHAUSTED };
+        let response = WsResponse {
+            ok: true,
+            payload: Some(serde_json::json!({
+                "matchId": match_data.id,
+                "rule": mode.deck_exhaustion(),
+                "reshuffled": reshuffled,
+                "deckCount": match_data.deck.len()
+            })),
+            ..WsResponse::from_text(i18n::text(code, &[("count", reshuffled.to_string())]))
+        };
+        self.connection_manager.broadcast_to_room(
+            &match_data.id,
+            events::match_events::DECK_EXHAUSTED,
+            Some(serde_json::to_value(response)?),
+        ).await?;
+        
+        Ok(reshuffled > 0)
+    }
+
     /// 使用烦人卡（Nope）取消上一个操作
     pub async fn play_nope(&self, match_id: &str, user_id: &str, card_id: &str) -> Result<()> {
         // 获取游戏数据
@@ -1358,12 +3704,12 @@ impl MatchService {
             // 广播烦人卡使用事件
             let nope_response = WsResponse {
                 ok: true,
-                msg: Some(format!("玩家 {} 使用烦人卡取消了上一个操作", user_id)),
                 payload: Some(serde_json::json!({
                     "userId": user_id,
                     "cardId": card_id,
                     "canceledAction": chain_action
                 })),
+                ..WsResponse::from_text(i18n::text(codes::NOPE_CANCELED, &[("user", user_id.to_string())]))
             };
             
             self.connection_manager.broadcast_to_room(
@@ -1372,13 +3718,21 @@ impl MatchService {
                 Some(serde_json::to_value(nope_response)?),
             ).await?;
             
+            // 记录烦人卡成就进度
+            self.record_achievement(user_id, AchievementMetric::NopesPlayed).await;
+            
             // 清除连锁状态
             match_data.chain_state = None;
+            match_data.timers.chain_deadline = None;
+            self.timer_manager.cancel(match_id, TimerKind::Chain);
             
             // 保存游戏数据
             match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
             self.save_match(&match_data).await;
             
+            // 连锁结束，继续回合计时
+            self.resume_turn_timer(match_id).await;
+            
             Ok(())
         } else {
             Err(anyhow::anyhow!("没有可以取消的操作"))
@@ -1397,18 +3751,29 @@ impl MatchService {
         // 添加到动作历史
         match_data.action_history.push(action.clone());
         
+        // 记录连锁结束时间
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let deadline = now + match_data.chain_wait_time;
+        match_data.timers.chain_deadline = Some(deadline);
+        
+        // 连锁等待期间暂停回合计时
+        if let Some(turn_deadline) = match_data.timers.turn_deadline.take() {
+            match_data.timers.suspended_turn_ms = Some(turn_deadline.saturating_sub(now));
+            self.timer_manager.cancel(match_id, TimerKind::Turn);
+        }
+        
         // 保存游戏数据
-        match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
+        match_data.updated_at = now;
         self.save_match(&match_data).await;
         
         // 广播连锁开始事件
         let chain_response = WsResponse {
             ok: true,
-            msg: Some("开始卡牌连锁效果，可以使用烦人卡取消".to_string()),
             payload: Some(serde_json::json!({
                 "action": action,
                 "waitTime": match_data.chain_wait_time
             })),
+            ..WsResponse::from_text(i18n::text(codes::CHAIN_STARTED, &[]))
         };
         
         self.connection_manager.broadcast_to_room(
@@ -1418,94 +3783,482 @@ impl MatchService {
         ).await?;
         
         // 设置超时处理
+        self.spawn_chain_timer(match_id, deadline, match_data.chain_wait_time);
+        
+        Ok(true)
+    }
+    
+    /// 启动连锁计时任务，到期时连锁结束时间未变化才结束连锁
+    fn spawn_chain_timer(&self, match_id: &str, deadline: u64, delay: u64) {
         let match_service = self.clone();
         let match_id_clone = match_id.to_string();
         
-        tokio::spawn(async move {
-            // 等待指定时间
-            sleep(Duration::from_millis(match_data.chain_wait_time)).await;
+        self.timer_manager.schedule(match_id, TimerKind::Chain, deadline, delay, move || async move {
+            // 计时器已被暂停冻结
+            let still_armed = match_service.get_match(&match_id_clone).await
+                .map(|m| m.timers.chain_deadline == Some(deadline))
+                .unwrap_or(false);
+            if !still_armed {
+                debug!("游戏 {} 的连锁计时器已失效", match_id_clone);
+                return;
+            }
             
             // 尝试结束连锁
             if let Err(e) = match_service.end_card_chain(&match_id_clone).await {
                 error!("结束卡牌连锁失败: {}", e);
             }
         });
+    }
+    
+    /// 连锁结束后按剩余时间继续回合计时
+    async fn resume_turn_timer(&self, match_id: &str) {
+        let Some(match_data) = self.get_match(match_id).await else {
+            return;
+        };
+        if match_data.state != MatchState::InProgress
+            || match_data.chain_state.is_some()
+            || match_data.pending_action.is_some()
+        {
+            return;
+        }
+        if let (Some(remaining), Some(user_id)) = (
+            match_data.timers.suspended_turn_ms,
+            match_data.timers.turn_user_id.clone(),
+        ) {
+            self.setup_inactivity_timer(match_id, &user_id, remaining).await;
+        }
+    }
+    
+    /// 结束卡牌连锁效果
+    async fn end_card_chain(&self, match_id: &str) -> Result<bool> {
+        // 获取游戏数据
+        let mut match_data = self.get_match(match_id).await
+            .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
+        
+        // 检查是否有连锁状态
+        if let Some(chain_action) = match_data.chain_state.take() {
+            // 先清除连锁状态并保存，卡牌效果会重新读取并保存游戏数据
+            match_data.timers.chain_deadline = None;
+            match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
+            self.save_match(&match_data).await;
+            
+            // 如果动作没有被取消，则执行
+            if !chain_action.is_canceled {
+                // 广播连锁结束事件
+                let end_response = WsResponse {
+                    ok: true,
+                    payload: Some(serde_json::json!({
+                        "action": chain_action
+                    })),
+                    ..WsResponse::from_text(i18n::text(codes::CHAIN_RESOLVED, &[]))
+                };
+                
+                self.connection_manager.broadcast_to_room(
+                    match_id,
+                    "match:chain_end",
+                    Some(serde_json::to_value(end_response)?),
+                ).await?;
+                
+                // 根据动作类型继续执行效果
+                match chain_action.action_type {
+                    CardActionType::Play => {
+                        if let (Some(card_id), Some(user_id)) = (chain_action.card_id, Some(chain_action.user_id)) {
+                            // 执行出牌效果，但跳过连锁处理
+                            self.execute_card_effect(match_id, &user_id, &card_id).await?;
+                        }
+                    },
+                    CardActionType::Draw => {
+                        // 抽卡动作通常不会被放入连锁
+                    },
+                    CardActionType::Nope => {
+                        // Nope只是取消效果，不需要额外执行
+                    },
+                    CardActionType::Defuse | CardActionType::Eliminate | CardActionType::Discard => {
+                        // 拆除卡、出局和弃牌动作不会被放入连锁
+                    },
+                }
+            } else {
+                // 动作被取消
+                let cancel_response = WsResponse {
+                    ok: true,
+                    payload: Some(serde_json::json!({
+                        "action": chain_action
+                    })),
+                    ..WsResponse::from_text(i18n::text(codes::CHAIN_CANCELED, &[]))
+                };
+                
+                self.connection_manager.broadcast_to_room(
+                    match_id,
+                    "match:chain_end",
+                    Some(serde_json::to_value(cancel_response)?),
+                ).await?;
+            }
+            
+            // 连锁结束，继续回合计时
+            self.resume_turn_timer(match_id).await;
+            
+            Ok(true)
+        } else {
+            // 没有连锁状态，不需要处理
+            Ok(false)
+        }
+    }
+    
+    /**
+     * 请求出牌玩家选择目标
+     *
+     * 记录等待选择的动作，暂停回合计时，并向出牌玩家发送候选玩家列表。调用方负责保存游戏数据。
+     *
+     * 参数:
+     * @param match_data - 游戏数据
+     * @param user_id - 出牌玩家ID
+     * @param card_id - 打出的卡牌ID
+     * @param kind - 等待选择目标的卡牌效果
+     *
+     * 返回:
+     * 没有其他玩家可以选择时返回false
+     */
+    async fn request_target(
+        &self,
+        match_data: &mut MatchData,
+        user_id: &str,
+        card_id: &str,
+        kind: PendingActionKind,
+    ) -> Result<bool> {
+        let (candidates, candidate_info): (Vec<_>, Vec<_>) = match_data.players.iter()
+            .filter(|p| p.user.id != user_id)
+            .map(|p| (p.user.id.clone(), serde_json::json!({ "userId": p.user.id, "name": p.user.name })))
+            .unzip();
+        if candidates.is_empty() {
+            return Ok(false);
+        }
+        
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let pending = PendingAction {
+            id: Uuid::new_v4().to_string(),
+            kind,
+            user_id: user_id.to_string(),
+            card_id: card_id.to_string(),
+            candidates,
+            count: 0,
+            created_at: now,
+            deadline: now + SELECT_TARGET_TIMEOUT_MS,
+        };
+        
+        // 选择期间暂停回合计时
+        if let Some(turn_deadline) = match_data.timers.turn_deadline.take() {
+            match_data.timers.suspended_turn_ms = Some(turn_deadline.saturating_sub(now));
+            self.timer_manager.cancel(&match_data.id, TimerKind::Turn);
+        }
+        match_data.pending_action = Some(pending.clone());
+        
+        let response = WsResponse {
+            ok: true,
+            payload: Some(serde_json::json!({
+                "matchId": match_data.id,
+                "pendingId": pending.id,
+                "kind": pending.kind,
+                "candidates": candidate_info,
+                "deadline": pending.deadline
+            })),
+            ..WsResponse::from_text(i18n::text(codes::SELECT_TARGET_PROMPT, &[]))
+        };
+        self.connection_manager.send_to_client(
+            user_id,
+            events::match_events::SELECT_TARGET,
+            Some(serde_json::to_value(response)?),
+        ).await?;
+        
+        self.spawn_selection_timer(&match_data.id, &pending.id, pending.deadline, SELECT_TARGET_TIMEOUT_MS);
+        Ok(true)
+    }
+    
+    /// 启动目标选择的计时任务，到期时仍未选择则随机选择
+    fn spawn_selection_timer(&self, match_id: &str, pending_id: &str, deadline: u64, delay: u64) {
+        let match_service = self.clone();
+        let match_id_clone = match_id.to_string();
+        let pending_id = pending_id.to_string();
+        
+        self.timer_manager.schedule(match_id, TimerKind::Selection, deadline, delay, move || async move {
+            if let Err(e) = match_service.expire_pending_action(&match_id_clone, &pending_id).await {
+                error!("处理目标选择超时失败: {}", e);
+            }
+        });
+    }
+    
+    /**
+     * 出牌玩家选择目标
+     *
+     * 参数:
+     * @param match_id - 游戏ID
+     * @param user_id - 出牌玩家ID
+     * @param pending_id - 等待选择的动作ID
+     * @param target_id - 选择的玩家ID，必须在候选列表中且仍在游戏中
+     */
+    pub async fn select_target(&self, match_id: &str, user_id: &str, pending_id: &str, target_id: &str) -> Result<()> {
+        let match_data = self.get_match(match_id).await
+            .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
+        
+        if match_data.state != MatchState::InProgress {
+            return Err(anyhow::anyhow!("游戏未开始、已暂停或已结束"));
+        }
+        let pending = match_data.pending_action.clone()
+            .filter(|p| p.id == pending_id && p.kind != PendingActionKind::DiscardDown)
+            .ok_or_else(|| anyhow::anyhow!("没有等待选择的目标"))?;
+        if pending.user_id != user_id {
+            return Err(anyhow::anyhow!("只有出牌玩家可以选择目标"));
+        }
+        if !pending.candidates.iter().any(|c| c == target_id)
+            || !match_data.players.iter().any(|p| p.user.id == target_id)
+        {
+            return Err(anyhow::anyhow!("目标玩家不在游戏中"));
+        }
+        
+        self.timer_manager.cancel(match_id, TimerKind::Selection);
+        self.resolve_pending_action(match_data, pending, Some(target_id)).await
+    }
+    
+    /// 目标选择超时，从仍在游戏中的候选玩家里随机选择
+    async fn expire_pending_action(&self, match_id: &str, pending_id: &str) -> Result<()> {
+        let Some(match_data) = self.get_match(match_id).await else {
+            return Ok(());
+        };
+        let Some(pending) = match_data.pending_action.clone().filter(|p| p.id == pending_id) else {
+            return Ok(());
+        };
+
+        // 弃牌超时，从手牌中随机弃牌，弃掉的牌记录在动作历史中，重放不依赖这里的随机数
+        if pending.kind == PendingActionKind::DiscardDown {
+            if match_data.state != MatchState::InProgress {
+                return Ok(());
+            }
+            let card_ids = match_data.players.iter()
+                .find(|p| p.user.id == pending.user_id)
+                .map(|p| {
+                    p.hand.choose_multiple(&mut rand::thread_rng(), pending.count)
+                        .map(|c| c.id.clone())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            info!("玩家 {} 弃牌超时，随机弃掉 {} 张牌", pending.user_id, card_ids.len());
+            return self.resolve_discard(match_data, pending, &card_ids, true).await;
+        }
+
+        let target_id = {
+            let remaining = pending.candidates.iter()
+                .filter(|c| match_data.players.iter().any(|p| &p.user.id == *c))
+                .collect::<Vec<_>>();
+            remaining.choose(&mut match_replay::effect_rng(&match_data, &pending.card_id)).map(|c| c.to_string())
+        };
+        info!("玩家 {} 选择目标超时，随机选择 {:?}", pending.user_id, target_id);
+        self.resolve_pending_action(match_data, pending, target_id.as_deref()).await
+    }
+    
+    /// 执行等待选择目标的动作，目标为None时放弃效果
+    async fn resolve_pending_action(&self, mut match_data: MatchData, pending: PendingAction, target_id: Option<&str>) -> Result<()> {
+        match_data.pending_action = None;
+        match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
+        self.save_match(&match_data).await;
+        
+        // 出牌玩家已离开时放弃效果
+        let actor_present = match_data.players.iter().any(|p| p.user.id == pending.user_id);
+        if let (true, Some(target_id)) = (actor_present, target_id) {
+            match pending.kind {
+                PendingActionKind::ShareTheFuture => {
+                    self.share_future(&match_data, &pending.user_id, target_id).await?;
+                }
+                PendingActionKind::DiscardDown => {
+                    // 弃牌由resolve_discard处理
+                }
+            }
+        }
+        
+        // 选择结束，继续回合计时
+        self.resume_turn_timer(&match_data.id).await;
+        Ok(())
+    }
+    
+    /// 与目标玩家分享未来三张牌
+    async fn share_future(&self, match_data: &MatchData, user_id: &str, target_id: &str) -> Result<()> {
+        let future_cards = match_data.deck.iter()
+            .take(3)
+            .cloned()
+            .collect::<Vec<_>>();
+        let target_name = match_data.players.iter()
+            .find(|p| p.user.id == target_id)
+            .map(|p| p.user.name.clone())
+            .unwrap_or_else(|| target_id.to_string());
+        
+        // 向目标玩家分享卡牌
+        let share_response = WsResponse {
+            ok: true,
+            payload: Some(serde_json::json!({
+                "cards": future_cards,
+                "fromUserId": user_id
+            })),
+            ..WsResponse::from_text(i18n::text(codes::FUTURE_SHARED_WITH_YOU, &[("user", user_id.to_string())]))
+        };
+        
+        self.send_private(
+            &match_data.id,
+            target_id,
+            events::match_events::SHARE_FUTURE,
+            serde_json::to_value(share_response)?,
+        ).await?;
+        
+        // 通知当前玩家已分享
+        let notify_response = WsResponse {
+            ok: true,
+            payload: Some(serde_json::json!({
+                "cards": future_cards,
+                "toUserId": target_id
+            })),
+            ..WsResponse::from_text(i18n::text(codes::YOU_SHARED_FUTURE, &[("target", target_name)]))
+        };
+        
+        self.connection_manager.send_to_client(
+            user_id,
+            events::match_events::SHARE_FUTURE,
+            Some(serde_json::to_value(notify_response)?),
+        ).await?;
+        
+        Ok(())
+    }
+    
+    /**
+     * 要求回合结束的玩家弃掉超过手牌上限的牌
+     *
+     * 记录等待弃牌的动作，暂停回合计时，并向玩家发送需要弃掉的张数。调用方负责保存游戏数据。
+     *
+     * 参数:
+     * @param match_data - 游戏数据
+     * @param user_id - 当前回合的玩家ID
+     * @param count - 需要弃掉的张数
+     * @param now - 当前时间（毫秒）
+     */
+    async fn request_discard(&self, match_data: &mut MatchData, user_id: &str, count: usize, now: u64) -> Result<()> {
+        let pending = PendingAction {
+            id: Uuid::new_v4().to_string(),
+            kind: PendingActionKind::DiscardDown,
+            user_id: user_id.to_string(),
+            card_id: String::new(),
+            candidates: Vec::new(),
+            count,
+            created_at: now,
+            deadline: now + hand_limit::DISCARD_TIMEOUT_MS,
+        };
+        
+        // 弃牌期间暂停回合计时
+        if let Some(turn_deadline) = match_data.timers.turn_deadline.take() {
+            match_data.timers.suspended_turn_ms = Some(turn_deadline.saturating_sub(now));
+            self.timer_manager.cancel(&match_data.id, TimerKind::Turn);
+        }
+        match_data.pending_action = Some(pending.clone());
+        
+        let response = WsResponse {
+            ok: true,
+            payload: Some(serde_json::json!({
+                "matchId": match_data.id,
+                "pendingId": pending.id,
+                "count": count,
+                "maxHandSize": match_data.max_hand_size,
+                "deadline": pending.deadline
+            })),
+            ..WsResponse::from_text(i18n::text(codes::DISCARD_PROMPT, &[("count", count.to_string())]))
+        };
+        self.connection_manager.send_to_client(
+            user_id,
+            events::match_events::DISCARD,
+            Some(serde_json::to_value(response)?),
+        ).await?;
+        
+        self.spawn_selection_timer(&match_data.id, &pending.id, pending.deadline, hand_limit::DISCARD_TIMEOUT_MS);
+        Ok(())
+    }
+    
+    /**
+     * 玩家选择弃掉的牌
+     *
+     * 参数:
+     * @param match_id - 游戏ID
+     * @param user_id - 弃牌玩家ID
+     * @param pending_id - 等待弃牌的动作ID
+     * @param card_ids - 弃掉的卡牌ID，必须恰好是需要弃掉的张数
+     */
+    pub async fn discard_cards(&self, match_id: &str, user_id: &str, pending_id: &str, card_ids: &[String]) -> Result<()> {
+        let match_data = self.get_match(match_id).await
+            .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
+        
+        if match_data.state != MatchState::InProgress {
+            return Err(anyhow::anyhow!("游戏未开始、已暂停或已结束"));
+        }
+        let pending = match_data.pending_action.clone()
+            .filter(|p| p.id == pending_id && p.kind == PendingActionKind::DiscardDown)
+            .ok_or_else(|| anyhow::anyhow!("没有等待弃牌的动作"))?;
+        if pending.user_id != user_id {
+            return Err(anyhow::anyhow!("只有手牌超过上限的玩家可以弃牌"));
+        }
+        let player = match_data.players.iter()
+            .find(|p| p.user.id == user_id)
+            .ok_or_else(|| anyhow::anyhow!("玩家不在游戏中"))?;
+        hand_limit::validate_selection(&player.hand, card_ids, pending.count)?;
         
-        Ok(true)
+        self.timer_manager.cancel(match_id, TimerKind::Selection);
+        self.resolve_discard(match_data, pending, card_ids, false).await
     }
     
-    /// 结束卡牌连锁效果
-    async fn end_card_chain(&self, match_id: &str) -> Result<bool> {
-        // 获取游戏数据
-        let mut match_data = self.get_match(match_id).await
-            .ok_or_else(|| anyhow::anyhow!("游戏不存在"))?;
+    /// 弃掉选择的牌并切换回合，auto表示超时随机弃牌
+    async fn resolve_discard(&self, mut match_data: MatchData, pending: PendingAction, card_ids: &[String], auto: bool) -> Result<()> {
+        match_data.pending_action = None;
+        match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
         
-        // 检查是否有连锁状态
-        if let Some(chain_action) = match_data.chain_state.clone() {
-            // 如果动作没有被取消，则执行
-            if !chain_action.is_canceled {
-                // 广播连锁结束事件
-                let end_response = WsResponse {
-                    ok: true,
-                    msg: Some("卡牌连锁效果结束，动作有效".to_string()),
-                    payload: Some(serde_json::json!({
-                        "action": chain_action
-                    })),
-                };
-                
-                self.connection_manager.broadcast_to_room(
-                    match_id,
-                    "match:chain_end",
-                    Some(serde_json::to_value(end_response)?),
-                ).await?;
-                
-                // 根据动作类型继续执行效果
-                match chain_action.action_type {
-                    CardActionType::Play => {
-                        if let (Some(card_id), Some(user_id)) = (chain_action.card_id, Some(chain_action.user_id)) {
-                            // 执行出牌效果，但跳过连锁处理
-                            self.execute_card_effect(match_id, &user_id, &card_id).await?;
-                        }
-                    },
-                    CardActionType::Draw => {
-                        // 抽卡动作通常不会被放入连锁
-                    },
-                    CardActionType::Nope => {
-                        // Nope只是取消效果，不需要额外执行
-                    },
-                    CardActionType::Defuse => {
-                        // 拆除卡动作通常不会被放入连锁
-                    },
-                }
-            } else {
-                // 动作被取消
-                let cancel_response = WsResponse {
-                    ok: true,
-                    msg: Some("卡牌连锁效果结束，动作被取消".to_string()),
-                    payload: Some(serde_json::json!({
-                        "action": chain_action
-                    })),
-                };
-                
-                self.connection_manager.broadcast_to_room(
-                    match_id,
-                    "match:chain_end",
-                    Some(serde_json::to_value(cancel_response)?),
-                ).await?;
+        let Some(player_index) = match_data.players.iter().position(|p| p.user.id == pending.user_id) else {
+            // 弃牌玩家已离开，回合索引指向上一个玩家，切换回合后轮到原本的下一个玩家
+            let continue_turn = match_data.state == MatchState::InProgress && !match_data.players.is_empty();
+            if continue_turn {
+                match_data.turn_index = (match_data.turn_index + match_data.players.len() - 1) % match_data.players.len();
             }
-            
-            // 清除连锁状态
-            match_data.chain_state = None;
-            
-            // 保存游戏数据
-            match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
             self.save_match(&match_data).await;
-            
-            Ok(true)
-        } else {
-            // 没有连锁状态，不需要处理
-            Ok(false)
+            if continue_turn {
+                self.change_turn(&match_data.id).await?;
+            }
+            return Ok(());
+        };
+        
+        let mut discarded = Vec::new();
+        for card_id in card_ids {
+            let hand = &mut match_data.players[player_index].hand;
+            if let Some(index) = hand.iter().position(|c| &c.id == card_id) {
+                let card = hand.remove(index);
+                match_data.action_history.push(CardAction::discard(&pending.user_id, &card));
+                match_data.discard_pile.push(card.clone());
+                discarded.push(card);
+            }
         }
+        
+        // 先保存，切换回合会重新读取游戏数据
+        self.save_match(&match_data).await;
+        
+        let response = WsResponse {
+            ok: true,
+            payload: Some(serde_json::json!({
+                "matchId": match_data.id,
+                "userId": pending.user_id,
+                "cards": discarded,
+                "auto": auto
+            })),
+            ..WsResponse::from_text(i18n::text(codes::CARDS_DISCARDED, &[
+                ("user", pending.user_id.clone()),
+                ("count", discarded.len().to_string()),
+            ]))
+        };
+        self.connection_manager.broadcast_to_room(
+            &match_data.id,
+            events::match_events::DISCARD,
+            Some(serde_json::to_value(response)?),
+        ).await?;
+        
+        self.change_turn(&match_data.id).await
     }
     
     /// 执行卡牌效果（不进入连锁系统）
@@ -1526,25 +4279,25 @@ impl MatchService {
         
         // 处理卡牌效果
         match card.card_type {
-            CardType::Skip => {
-                // 跳过当前回合
-                self.change_turn(match_id).await?;
-            },
-            CardType::Attack => {
-                // 攻击：下一玩家连续抽两张牌
-                self.change_turn(match_id).await?;
-                
-                // 标记下一玩家需要抽两张牌
+            CardType::Skip | CardType::Attack => {
+                // 攻击：标记下一玩家需要抽两张牌
                 // 在实际游戏中，需要更复杂的机制来处理
                 // 此示例中简化为记录在游戏状态中
-                match_data.draw_count = 2;
+                if matches!(card.card_type, CardType::Attack) {
+                    match_data.draw_count = 2;
+                }
+                
+                // 先保存，切换回合会重新读取游戏数据
+                match_data.updated_at = chrono::Utc::now().timestamp_millis() as u64;
+                self.save_match(&match_data).await;
+                
+                // 跳过当前回合
+                self.change_turn(match_id).await?;
+                return Ok(());
             },
             CardType::Shuffle => {
-                // 洗牌
-                use rand::seq::SliceRandom;
-                use rand::thread_rng;
-                
-                match_data.deck.shuffle(&mut thread_rng());
+                // 洗牌，随机数由对局种子和出牌位置决定，便于重放验证
+                match_data.deck.shuffle(&mut match_replay::effect_rng(&match_data, card_id));
                 
                 // 不切换回合，玩家可以继续操作
             },
@@ -1558,16 +4311,17 @@ impl MatchService {
                 // 私下通知玩家
                 let future_response = WsResponse {
                     ok: true,
-                    msg: Some("你看到了未来的牌".to_string()),
                     payload: Some(serde_json::json!({
                         "cards": future_cards
                     })),
+                    ..WsResponse::from_text(i18n::text(codes::SAW_FUTURE, &[]))
                 };
                 
-                self.connection_manager.send_to_client(
+                self.send_private(
+                    match_id,
                     &user_id,
                     events::match_events::PLAY_CARD,
-                    Some(serde_json::to_value(future_response)?),
+                    serde_json::to_value(future_response)?,
                 ).await?;
                 
                 // 不切换回合，玩家可以继续操作
@@ -1577,6 +4331,7 @@ impl MatchService {
                 // 在实际游戏中，需要等待玩家选择目标
                 // 此示例中简化为随机选择一名玩家
                 
+                let mut rng = match_replay::effect_rng(&match_data, card_id);
                 let other_players = match_data.players.iter_mut()
                     .enumerate()
                     .filter(|(i, p)| *i != player_index && !p.hand.is_empty())
@@ -1584,11 +4339,11 @@ impl MatchService {
                 
                 if !other_players.is_empty() {
                     use rand::Rng;
-                    let random_index = rand::thread_rng().gen_range(0..other_players.len());
+                    let random_index = rng.gen_range(0..other_players.len());
                     let (target_index, _) = other_players[random_index];
                     
                     // 随机选择一张牌
-                    let random_card_index = rand::thread_rng().gen_range(0..match_data.players[target_index].hand.len());
+                    let random_card_index = rng.gen_range(0..match_data.players[target_index].hand.len());
                     let target_card = match_data.players[target_index].hand.remove(random_card_index);
                     
                     // 获取目标玩家ID（用于消息）
@@ -1600,12 +4355,11 @@ impl MatchService {
                     // 广播抢夺事件
                     let favor_response = WsResponse {
                         ok: true,
-                        msg: Some(format!("玩家 {} 从玩家 {} 那里获得了一张牌", 
-                                        user_id, target_player_id)),
                         payload: Some(serde_json::json!({
                             "userId": user_id,
                             "targetId": target_player_id
                         })),
+                        ..WsResponse::from_text(i18n::text(codes::CARD_STOLEN, &[("user", user_id.to_string()), ("target", target_player_id.to_string())]))
                     };
                     
                     self.connection_manager.broadcast_to_room(
@@ -1617,17 +4371,17 @@ impl MatchService {
                     // 私下通知当前玩家获得的牌
                     let private_response = WsResponse {
                         ok: true,
-                        msg: Some(format!("你从玩家 {} 那里获得了 {:?}", 
-                                        target_player_id, target_card.card_type)),
                         payload: Some(serde_json::json!({
                             "card": target_card
                         })),
+                        ..WsResponse::from_text(i18n::text(codes::YOU_STOLE, &[("target", target_player_id.to_string()), ("card", format!("{:?}", target_card.card_type))]))
                     };
                     
-                    self.connection_manager.send_to_client(
+                    self.send_private(
+                        match_id,
                         &user_id,
                         events::match_events::PLAY_CARD,
-                        Some(serde_json::to_value(private_response)?),
+                        serde_json::to_value(private_response)?,
                     ).await?;
                 }
                 
@@ -1647,22 +4401,21 @@ impl MatchService {
                     // 显示给玩家
                     let future_response = WsResponse {
                         ok: true,
-                        msg: Some("你可以重新排列未来的牌".to_string()),
                         payload: Some(serde_json::json!({
                             "cards": future_cards
                         })),
+                        ..WsResponse::from_text(i18n::text(codes::ALTER_FUTURE_PROMPT, &[]))
                     };
                     
-                    self.connection_manager.send_to_client(
+                    self.send_private(
+                        match_id,
                         &user_id,
                         events::match_events::ALTER_FUTURE,
-                        Some(serde_json::to_value(future_response)?),
+                        serde_json::to_value(future_response)?,
                     ).await?;
                     
                     // 这里简化处理，随机排列这些牌
-                    use rand::seq::SliceRandom;
-                    use rand::thread_rng;
-                    future_cards.shuffle(&mut thread_rng());
+                    future_cards.shuffle(&mut match_replay::effect_rng(&match_data, card_id));
                     
                     // 放回牌堆顶部
                     for card in future_cards.into_iter().rev() {
@@ -1672,8 +4425,8 @@ impl MatchService {
                     // 通知玩家已重新排列
                     let alter_response = WsResponse {
                         ok: true,
-                        msg: Some("已重新排列未来的牌".to_string()),
                         payload: None,
+                        ..WsResponse::from_text(i18n::text(codes::FUTURE_ALTERED, &[]))
                     };
                     
                     self.connection_manager.send_to_client(
@@ -1686,55 +4439,9 @@ impl MatchService {
                 // 不切换回合，玩家可以继续操作
             },
             CardType::ShareTheFuture => {
-                // 与另一位玩家分享未来三张牌
-                let future_cards = match_data.deck.iter()
-                    .take(3)
-                    .cloned()
-                    .collect::<Vec<_>>();
-                
-                if !future_cards.is_empty() {
-                    // 随机选择一名其他玩家
-                    let other_players = match_data.players.iter()
-                        .filter(|p| p.user.id != user_id)
-                        .collect::<Vec<_>>();
-                    
-                    if !other_players.is_empty() {
-                        use rand::Rng;
-                        let random_index = rand::thread_rng().gen_range(0..other_players.len());
-                        let target_player = &other_players[random_index];
-                        
-                        // 向目标玩家分享卡牌
-                        let share_response = WsResponse {
-                            ok: true,
-                            msg: Some(format!("玩家 {} 与你分享了未来的牌", user_id)),
-                            payload: Some(serde_json::json!({
-                                "cards": future_cards,
-                                "fromUserId": user_id
-                            })),
-                        };
-                        
-                        self.connection_manager.send_to_client(
-                            &target_player.user.id,
-                            events::match_events::SHARE_FUTURE,
-                            Some(serde_json::to_value(share_response)?),
-                        ).await?;
-                        
-                        // 通知当前玩家已分享
-                        let notify_response = WsResponse {
-                            ok: true,
-                            msg: Some(format!("你与玩家 {} 分享了未来的牌", target_player.user.name)),
-                            payload: Some(serde_json::json!({
-                                "cards": future_cards,
-                                "toUserId": target_player.user.id
-                            })),
-                        };
-                        
-                        self.connection_manager.send_to_client(
-                            &user_id,
-                            events::match_events::SHARE_FUTURE,
-                            Some(serde_json::to_value(notify_response)?),
-                        ).await?;
-                    }
+                // 由出牌玩家选择分享未来三张牌的对象，超时后随机选择
+                if !match_data.deck.is_empty() {
+                    self.request_target(&mut match_data, user_id, card_id, PendingActionKind::ShareTheFuture).await?;
                 }
                 
                 // 不切换回合，玩家可以继续操作
@@ -1754,23 +4461,24 @@ impl MatchService {
                         // 通知玩家
                         let bury_response = WsResponse {
                             ok: true,
-                            msg: Some("你将一张牌埋入了牌堆中间".to_string()),
                             payload: Some(serde_json::json!({
                                 "buriedCard": card_to_bury
                             })),
+                            ..WsResponse::from_text(i18n::text(codes::YOU_BURIED_CARD, &[]))
                         };
                         
-                        self.connection_manager.send_to_client(
+                        self.send_private(
+                            match_id,
                             &user_id,
                             events::match_events::BURY_CARD,
-                            Some(serde_json::to_value(bury_response)?),
+                            serde_json::to_value(bury_response)?,
                         ).await?;
                         
                         // 广播埋牌事件
                         let public_response = WsResponse {
                             ok: true,
-                            msg: Some(format!("玩家 {} 将一张牌埋入了牌堆中间", user_id)),
                             payload: None,
+                            ..WsResponse::from_text(i18n::text(codes::CARD_BURIED, &[("user", user_id.to_string())]))
                         };
                         
                         self.connection_manager.broadcast_to_room(
@@ -1799,15 +4507,15 @@ impl MatchService {
                     // 放到牌堆顶部附近的随机位置
                     use rand::Rng;
                     let top_range = (match_data.deck.len() / 4).max(1);
-                    let new_pos = rand::thread_rng().gen_range(0..top_range);
+                    let new_pos = match_replay::effect_rng(&match_data, card_id).gen_range(0..top_range);
                     
                     match_data.deck.insert(new_pos, exploding_card);
                     
                     // 广播事件
                     let speed_response = WsResponse {
                         ok: true,
-                        msg: Some(format!("玩家 {} 加速了爆炸猫的爆炸", user_id)),
                         payload: None,
+                        ..WsResponse::from_text(i18n::text(codes::EXPLOSION_SPED_UP, &[("user", user_id.to_string())]))
                     };
                     
                     self.connection_manager.broadcast_to_room(
@@ -1825,7 +4533,7 @@ impl MatchService {
                 
                 // 创建一个新的爆炸猫
                 let imploding_card = Card {
-                    id: format!("imploding-{}", Uuid::new_v4()),
+                    id: format!("imploding-{}", card_id),
                     card_type: CardType::ExplodingKitten,
                     variant: Some("imploding".to_string()),
                 };
@@ -1837,10 +4545,10 @@ impl MatchService {
                 // 广播事件
                 let implode_response = WsResponse {
                     ok: true,
-                    msg: Some(format!("玩家 {} 插入了一只内爆猫", user_id)),
                     payload: Some(serde_json::json!({
                         "position": "middle"
                     })),
+                    ..WsResponse::from_text(i18n::text(codes::IMPLODING_KITTEN_INSERTED, &[("user", user_id.to_string())]))
                 };
                 
                 self.connection_manager.broadcast_to_room(
@@ -1859,8 +4567,8 @@ impl MatchService {
                 // 广播使用猫咪卡
                 let cat_response = WsResponse {
                     ok: true,
-                    msg: Some(format!("玩家 {} 使用了猫咪卡", user_id)),
                     payload: None,
+                    ..WsResponse::from_text(i18n::text(codes::CAT_CARD_PLAYED, &[("user", user_id.to_string())]))
                 };
                 
                 self.connection_manager.broadcast_to_room(
@@ -1878,8 +4586,8 @@ impl MatchService {
                 // 广播使用Nope卡
                 let nope_response = WsResponse {
                     ok: true,
-                    msg: Some(format!("玩家 {} 使用了烦人卡", user_id)),
                     payload: None,
+                    ..WsResponse::from_text(i18n::text(codes::NOPE_PLAYED, &[("user", user_id.to_string())]))
                 };
                 
                 self.connection_manager.broadcast_to_room(
@@ -1898,8 +4606,8 @@ impl MatchService {
                 // 广播一个通用的出牌消息
                 let generic_response = WsResponse {
                     ok: true,
-                    msg: Some(format!("玩家 {} 使用了 {:?} 卡牌", user_id, card.card_type)),
                     payload: None,
+                    ..WsResponse::from_text(i18n::text(codes::CARD_USED, &[("user", user_id.to_string()), ("card", format!("{:?}", card.card_type))]))
                 };
                 
                 self.connection_manager.broadcast_to_room(
@@ -1920,87 +4628,43 @@ impl MatchService {
     }
 }
 
-/// 生成牌组
-fn generate_deck(player_count: usize) -> Vec<Card> {
-    let mut deck = Vec::new();
-    let mut rng = thread_rng();
-    
-    // 添加爆炸猫卡（玩家数量-1）
-    for i in 0..player_count - 1 {
-        deck.push(Card {
-            id: format!("exploding-{}", i),
-            card_type: CardType::ExplodingKitten,
-            variant: None,
-        });
-    }
-    
-    // 每个玩家添加1张拆除卡
-    for i in 0..player_count {
-        deck.push(Card {
-            id: format!("defuse-{}", i),
-            card_type: CardType::Defuse,
-            variant: None,
-        });
-    }
-    
-    // 添加标准卡牌
-    let card_types = [
-        CardType::Skip,
-        CardType::SeeTheFuture,
-        CardType::Shuffle,
-        CardType::Attack,
-        CardType::Favor,
-        CardType::Cat,
-        CardType::Nope,
-    ];
-    
-    // 每种卡牌添加4张
-    for (type_index, card_type) in card_types.iter().enumerate() {
-        for i in 0..4 {
-            deck.push(Card {
-                id: format!("{}-{}", type_index, i),
-                card_type: card_type.clone(),
-                variant: None,
-            });
-        }
-    }
-    
-    // 洗牌
-    deck.shuffle(&mut rng);
-    
-    deck
+/// 将对局玩家写入用户名索引并记录同局互动
+fn record_search_interactions(match_data: &MatchData) {
+    let Some(search) = crate::user_search::global_user_search() else {
+        return;
+    };
+    let players: Vec<_> = match_data
+        .players
+        .iter()
+        .chain(match_data.out.iter())
+        .map(|p| (p.user.id.clone(), p.user.name.clone(), p.user.avatar_url.clone()))
+        .collect();
+    search.record_match(&players, match_data.updated_at);
 }
 
-/// 发牌
-fn distribute_cards(match_data: &mut MatchData) {
-    const INITIAL_CARD_COUNT: usize = 4; // 每个玩家初始卡牌数
-    
-    for player in &mut match_data.players {
-        for _ in 0..INITIAL_CARD_COUNT {
-            if let Some(card) = match_data.deck.pop() {
-                player.hand.push(card);
-            }
-        }
-        
-        // 确保每个玩家有一张拆除卡
-        // 检查玩家是否已经有拆除卡
-        let has_defuse = player.hand.iter().any(|card| matches!(card.card_type, CardType::Defuse));
-        
-        if !has_defuse {
-            // 从牌堆找一张拆除卡
-            if let Some(pos) = match_data.deck.iter().position(|card| matches!(card.card_type, CardType::Defuse)) {
-                let defuse_card = match_data.deck.remove(pos);
-                player.hand.push(defuse_card);
-            } else {
-                // 如果牌堆中没有拆除卡，创建一张新的
-                player.hand.push(Card {
-                    id: format!("defuse-extra-{}", player.user.id),
-                    card_type: CardType::Defuse,
-                    variant: None,
-                });
-            }
-        }
+/**
+ * 构建胜利事件的负载
+ *
+ * 启用事件签名时附带签名的对局结果（包含评分变化），客户端可以用 `/v1/service` 公开的临时公钥验证
+ *
+ * 参数:
+ * @param match_data - 已结束的对局
+ * @param winner_id - 胜利者ID
+ * @param rating_changes - 本局的评分变化
+ *
+ * 返回:
+ * 胜利事件负载
+ */
+fn victory_payload(match_data: &MatchData, winner_id: &str, rating_changes: &[RatingChange]) -> serde_json::Value {
+    let mut payload = serde_json::json!({
+        "userId": winner_id,
+        "ratingChanges": rating_changes,
+    });
+    let result = event_signing::MatchResultData::from_match(match_data, winner_id, rating_changes);
+    if let Some(signed) = event_signing::signed_match_result(result) {
+        payload["signedResult"] = signed;
     }
+    payload
 }
 
 /// 克隆实现
@@ -2009,8 +4673,16 @@ impl Clone for MatchService {
         Self {
             game_service: self.game_service.clone(),
             connection_manager: self.connection_manager.clone(),
+            achievement_service: self.achievement_service.clone(),
+            economy_service: self.economy_service.clone(),
+            stats_service: self.stats_service.clone(),
+            penalty_service: self.penalty_service.clone(),
             active_matches: self.active_matches.clone(),
             queue: self.queue.clone(),
+            restored_queue: self.restored_queue.clone(),
+            state_tracker: self.state_tracker.clone(),
+            invites: self.invites.clone(),
+            timer_manager: self.timer_manager.clone(),
         }
     }
 }
@@ -2027,9 +4699,114 @@ pub async fn handle_ws_message(
         Some(user) => user,
         None => return Ok(false), // 没有用户信息，无法处理
     };
+    // 已关联的钱包地址统一使用所属用户的ID
+    let user = UserInfo {
+        id: account_link::resolve_user_id(&user.id),
+        ..user
+    };
     
     match message.event.as_str() {
         // 匹配相关事件
+        "match:create" => {
+            let wager = message.data.as_ref()
+                .and_then(|data| data.get("wager"))
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0);
+            // 指定游戏模式时先检查是否已注册，避免押注托管后才失败
+            let mode = message.data.as_ref()
+                .and_then(|data| data.get("mode"))
+                .and_then(|v| v.as_str())
+                .map(|v| v.to_string());
+            if let Some(mode) = &mode {
+                if game_mode::global_game_modes().get(mode).is_none() {
+                    return Err(anyhow::anyhow!("不支持的游戏模式: {}", mode));
+                }
+            }
+            // 指定局数时创建系列赛，系列赛不支持押注
+            let best_of = message.data.as_ref()
+                .and_then(|data| data.get("bestOf"))
+                .and_then(|v| v.as_u64())
+                .map(|v| v as u32);
+            if let Some(best_of) = best_of {
+                if !series::SUPPORTED_BEST_OF.contains(&best_of) {
+                    return Err(anyhow::anyhow!("不支持的系列赛局数: {}", best_of));
+                }
+                if wager > 0 {
+                    return Err(anyhow::anyhow!("系列赛不支持押注"));
+                }
+            }
+            // 指定手牌上限时先检查范围，0表示不限制
+            let max_hand_size = message.data.as_ref()
+                .and_then(|data| data.get("maxHandSize"))
+                .and_then(|v| v.as_u64())
+                .map(|v| hand_limit::validate(v as usize))
+                .transpose()?;
+            let mut match_data = match_service.create_wagered_match(MatchType::Private, vec![user.clone()], wager).await?;
+            if let Some(mode) = &mode {
+                match_service.set_match_mode(&mut match_data, mode).await?;
+            }
+            if let Some(best_of) = best_of {
+                match_service.create_series(&mut match_data, best_of).await?;
+            }
+            if let Some(max_hand_size) = max_hand_size {
+                match_service.set_max_hand_size(&mut match_data, max_hand_size).await?;
+            }
+            
+            // 创建时可以指定观战权限和人数上限
+            let spectator_policy = message.data.as_ref()
+                .and_then(|data| data.get("spectatorPolicy"))
+                .and_then(|v| v.as_str())
+                .and_then(SpectatorPolicy::from_str);
+            let max_spectators = message.data.as_ref()
+                .and_then(|data| data.get("maxSpectators"))
+                .and_then(|v| v.as_u64())
+                .map(|v| v as usize);
+            if spectator_policy.is_some() || max_spectators.is_some() {
+                match_service.configure_spectators(&mut match_data, spectator_policy, max_spectators).await?;
+            }
+            
+            let response = WsResponse {
+                ok: true,
+                msg: None,
+                payload: Some(match_service.match_view(&match_data, Some(&user.id)).await?),
+                ..Default::default()
+            };
+            match_service.connection_manager.send_to_client(
+                client_id,
+                events::match_events::CREATE,
+                Some(serde_json::to_value(response)?),
+            ).await?;
+            
+            return Ok(true);
+        }
+        "match:invite" => {
+            if let Some(data) = message.data {
+                let match_id = data.get("matchId").and_then(|v| v.as_str());
+                let friend_id = data.get("userId").and_then(|v| v.as_str()).map(account_link::resolve_user_id);
+                if let (Some(match_id), Some(friend_id)) = (match_id, friend_id) {
+                    let response = match match_service.invite_friend(match_id, &user, &friend_id).await {
+                        Ok(invite) => WsResponse {
+                            ok: true,
+                            msg: None,
+                            payload: Some(serde_json::to_value(&invite)?),
+                            ..Default::default()
+                        },
+                        Err(e) => WsResponse {
+                            ok: false,
+                            msg: Some(e.to_string()),
+                            payload: None,
+                            ..Default::default()
+                        },
+                    };
+                    match_service.connection_manager.send_to_client(
+                        client_id,
+                        events::match_events::INVITE,
+                        Some(serde_json::to_value(response)?),
+                    ).await?;
+                    return Ok(true);
+                }
+            }
+        }
         "match:join" => {
             if let Some(data) = message.data {
                 if let Some(match_id) = data.get("matchId").and_then(|v| v.as_str()) {
@@ -2046,6 +4823,15 @@ pub async fn handle_ws_message(
                 }
             }
         }
+        "match:state_sync" => {
+            // 客户端发现状态序号不连续时请求完整快照
+            if let Some(data) = message.data {
+                if let Some(match_id) = data.get("matchId").and_then(|v| v.as_str()) {
+                    match_service.send_match_state(match_id, client_id).await?;
+                    return Ok(true);
+                }
+            }
+        }
         "match:start" => {
             if let Some(data) = message.data {
                 if let Some(match_id) = data.get("matchId").and_then(|v| v.as_str()) {
@@ -2072,6 +4858,67 @@ pub async fn handle_ws_message(
                 }
             }
         }
+        "match:can_play" => {
+            if let Some(data) = message.data {
+                if let Some(match_id) = data.get("matchId").and_then(|v| v.as_str()) {
+                    let card_id = data.get("cardId").and_then(|v| v.as_str());
+                    match_service.can_play(match_id, &user.id, card_id, client_id).await?;
+                    return Ok(true);
+                }
+            }
+        }
+        "match:pause" => {
+            if let Some(data) = message.data {
+                if let Some(match_id) = data.get("matchId").and_then(|v| v.as_str()) {
+                    // 不带accept字段时视为发起或同意暂停
+                    let accept = data.get("accept").and_then(|v| v.as_bool()).unwrap_or(true);
+                    match_service.pause_match(match_id, &user.id, accept).await?;
+                    return Ok(true);
+                }
+            }
+        }
+        "match:resume" => {
+            if let Some(data) = message.data {
+                if let Some(match_id) = data.get("matchId").and_then(|v| v.as_str()) {
+                    match_service.resume_match(match_id, Some(&user.id)).await?;
+                    return Ok(true);
+                }
+            }
+        }
+        "match:vote_skip" => {
+            if let Some(data) = message.data {
+                if let Some(match_id) = data.get("matchId").and_then(|v| v.as_str()) {
+                    let target_id = data.get("userId").and_then(|v| v.as_str()).map(account_link::resolve_user_id);
+                    let accept = data.get("accept").and_then(|v| v.as_bool()).unwrap_or(true);
+                    match_service.vote_skip(match_id, &user.id, target_id.as_deref(), accept).await?;
+                    return Ok(true);
+                }
+            }
+        }
+        "match:select_target" => {
+            if let Some(data) = message.data {
+                let match_id = data.get("matchId").and_then(|v| v.as_str());
+                let pending_id = data.get("pendingId").and_then(|v| v.as_str());
+                let target_id = data.get("targetId").and_then(|v| v.as_str()).map(account_link::resolve_user_id);
+                if let (Some(match_id), Some(pending_id), Some(target_id)) = (match_id, pending_id, target_id) {
+                    match_service.select_target(match_id, &user.id, pending_id, &target_id).await?;
+                    return Ok(true);
+                }
+            }
+        }
+        "match:discard" => {
+            if let Some(data) = message.data {
+                let match_id = data.get("matchId").and_then(|v| v.as_str());
+                let pending_id = data.get("pendingId").and_then(|v| v.as_str());
+                let card_ids = data.get("cardIds").and_then(|v| v.as_array()).map(|ids| {
+                    ids.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect::<Vec<_>>()
+                });
+                if let (Some(match_id), Some(pending_id), Some(card_ids)) = (match_id, pending_id, card_ids) {
+                    match_service.discard_cards(match_id, &user.id, pending_id, &card_ids).await?;
+                    return Ok(true);
+                }
+            }
+        }
         "match:join_spectators" => {
             if let Some(data) = message.data {
                 if let Some(match_id) = data.get("matchId").and_then(|v| v.as_str()) {
@@ -2089,7 +4936,31 @@ pub async fn handle_ws_message(
             }
         }
         "queue:join" => {
-            match_service.join_queue(user).await?;
+            // 客户端可以声明匹配地区，否则使用连接IP所在的地区
+            let declared = message.data.as_ref().and_then(|d| d.get("region")).and_then(|v| v.as_str());
+            let region = geo::geo_service().resolve(client_id, declared);
+            if let Err(e) = match_service.join_queue(user, region).await {
+                // 被封禁时把封禁状态返回给客户端
+                if let Some(BanError(state)) = e.downcast_ref::<BanError>() {
+                    let response = WsResponse {
+                        ok: false,
+                        payload: Some(serde_json::json!({
+                            "isEnqueued": false,
+                            "ban": state,
+                        })),
+                        ..WsResponse::from_text(i18n::text(codes::QUEUE_BANNED, &[
+                            ("user", state.id.clone()),
+                            ("reason", state.reason.clone()),
+                        ]))
+                    };
+                    match_service.connection_manager.send_to_client(
+                        client_id,
+                        "queue:status",
+                        Some(serde_json::to_value(response)?),
+                    ).await?;
+                }
+                return Err(e);
+            }
             return Ok(true);
         }
         "queue:leave" => {
@@ -2097,25 +4968,9 @@ pub async fn handle_ws_message(
             return Ok(true);
         }
         "queue:status" => {
-            // 获取队列状态
-            let enqueued_at = match_service.get_queue_status(&user.id).await;
-            
-            // 创建响应
-            let response = WsResponse {
-                ok: true,
-                msg: None,
-                payload: Some(serde_json::json!({
-                    "isEnqueued": enqueued_at.is_some(),
-                    "enqueuedAt": enqueued_at
-                })),
-            };
-            
-            // 发送响应
-            match_service.connection_manager.send_to_client(
-                client_id,
-                "queue:status",
-                Some(serde_json::to_value(response)?),
-            ).await?;
+            // 恢复排队的玩家第一次查询时带上restored标记
+            let restored = match_service.restored_queue.lock().remove(&user.id);
+            match_service.send_queue_status(&user.id, client_id, restored).await?;
             
             return Ok(true);
         }
@@ -2128,16 +4983,48 @@ pub async fn handle_ws_message(
     Ok(false)
 }
 
+// 用于存储全局MatchService实例的静态变量
+static GLOBAL_MATCH_SERVICE: once_cell::sync::OnceCell<Arc<MatchService>> = once_cell::sync::OnceCell::new();
+
+/// 获取全局游戏匹配服务
+pub fn global_match_service() -> Option<Arc<MatchService>> {
+    GLOBAL_MATCH_SERVICE.get().cloned()
+}
+
 /// 初始化游戏匹配服务
 pub fn init_match_service(
     game_service: Arc<GameService>,
     connection_manager: Arc<ConnectionManager>,
+    achievement_service: Arc<AchievementService>,
+    economy_service: Arc<EconomyService>,
+    stats_service: Arc<StatsService>,
+    penalty_service: Arc<PenaltyService>,
 ) -> Arc<MatchService> {
-    let match_service = Arc::new(MatchService::new(game_service, connection_manager));
+    let match_service = Arc::new(MatchService::new(
+        game_service,
+        connection_manager,
+        achievement_service,
+        economy_service,
+        stats_service,
+        penalty_service,
+    ));
+    
+    let _ = GLOBAL_MATCH_SERVICE.set(match_service.clone());
+    
+    // 处理所有对局事件
+    let handler_service = match_service.clone();
+    match_service.connection_manager.register_handler("match:*", move |event| {
+        let match_service = handler_service.clone();
+        async move {
+            let user_info = event.user.map(|u| UserInfo::with_rating(u.id, u.name, u.avatar_url));
+            handle_ws_message(&event.client_id, event.message, &match_service, user_info).await
+        }
+    });
     
-    // 启动匹配队列处理
+    // 恢复重启前的匹配队列，然后启动匹配队列处理
     let match_service_clone = match_service.clone();
     tokio::spawn(async move {
+        match_service_clone.restore_queue().await;
         match_service_clone.start_matchmaking().await;
     });
     
@@ -2148,3 +5035,8 @@ pub fn init_match_service(
 fn default_chain_wait_time() -> u64 {
     5000 // 5秒
 }
+
+/// 默认观战人数上限
+fn default_max_spectators() -> usize {
+    DEFAULT_MAX_SPECTATORS
+}
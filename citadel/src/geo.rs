@@ -0,0 +1,357 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 匹配地区提示
+//!
+//! # 概述
+//! 匹配队列中的每一项可以带有一个地区标签，匹配时优先把同一地区的玩家分到同一局，降低对局延迟：
+//! - 客户端可以在 `queue:join` 的数据中声明地区（`{"region": "eu"}`），声明的地区优先
+//! - 没有声明时按连接的客户端IP在配置的GeoIP数据库中查找地区
+//! - 都没有时不带地区标签，没有标签的玩家可以和任何地区的玩家匹配
+//!
+//! 地区只是匹配提示，不做校验也不影响权限。队列中有玩家等待超过回退时间后，
+//! 找不到同地区对局时按进入队列的顺序跨地区匹配，避免小地区的玩家一直等待。
+//!
+//! GeoIP数据库是CSV文本文件，每行一个 `网段,地区`，例如 `203.0.113.0/24,ap`，
+//! 支持IPv4和IPv6，`#` 开头的行是注释。网段之间不应重叠。
+//!
+//! # 配置
+//! - `GEOIP_DATABASE`：GeoIP数据库文件路径，不配置时不按IP查找地区
+//! - `MATCH_CLIENT_REGION_ENABLED`：是否接受客户端声明的地区，默认开启
+//! - `MATCH_REGION_FALLBACK_SECS`：跨地区匹配前的等待时间（秒），默认30
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use tracing::{info, warn};
+
+use crate::party::{self, QueueEntry};
+
+/// 默认跨地区匹配前的等待时间（秒）
+pub const DEFAULT_FALLBACK_SECS: u64 = 30;
+/// 地区标签的最大长度
+pub const MAX_REGION_LEN: usize = 16;
+
+/// 匹配地区配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeoConfig {
+    /// GeoIP数据库文件路径
+    pub database: Option<String>,
+    /// 是否接受客户端声明的地区
+    pub client_region_enabled: bool,
+    /// 跨地区匹配前的等待时间（毫秒）
+    pub fallback_ms: u64,
+}
+
+impl Default for GeoConfig {
+    fn default() -> Self {
+        Self {
+            database: None,
+            client_region_enabled: true,
+            fallback_ms: DEFAULT_FALLBACK_SECS * 1000,
+        }
+    }
+}
+
+impl GeoConfig {
+    /// 从环境变量读取匹配地区配置，未设置的项使用默认值
+    pub fn from_env() -> Self {
+        let parse = |key: &str| std::env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        let defaults = Self::default();
+        Self {
+            database: std::env::var("GEOIP_DATABASE").ok().filter(|v| !v.trim().is_empty()),
+            client_region_enabled: std::env::var("MATCH_CLIENT_REGION_ENABLED")
+                .map(|v| !matches!(v.trim().to_ascii_lowercase().as_str(), "0" | "false" | "no"))
+                .unwrap_or(defaults.client_region_enabled),
+            fallback_ms: parse("MATCH_REGION_FALLBACK_SECS")
+                .map(|v| v * 1000)
+                .unwrap_or(defaults.fallback_ms),
+        }
+    }
+}
+
+/// 规范化地区标签：转为小写，只允许字母、数字和 `-`，无效时返回None
+pub fn normalize_region(region: &str) -> Option<String> {
+    let region = region.trim().to_ascii_lowercase();
+    let valid = !region.is_empty()
+        && region.len() <= MAX_REGION_LEN
+        && region.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    valid.then_some(region)
+}
+
+/// IP地址转换为IPv6整数，IPv4地址使用映射地址，两种地址在同一个数据库中查找
+fn ip_to_u128(ip: IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(ip) => u128::from(ip.to_ipv6_mapped()),
+        IpAddr::V6(ip) => u128::from(ip),
+    }
+}
+
+/// 解析网段，返回起止地址（包含）
+fn parse_cidr(cidr: &str) -> Result<(u128, u128)> {
+    let (ip, prefix) = match cidr.split_once('/') {
+        Some((ip, prefix)) => (ip, Some(prefix)),
+        None => (cidr, None),
+    };
+    let ip: IpAddr = ip.trim().parse().with_context(|| format!("无效的IP地址: {}", ip))?;
+    let bits = if ip.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => prefix.trim().parse::<u32>().with_context(|| format!("无效的前缀长度: {}", prefix))?,
+        None => bits,
+    };
+    if prefix > bits {
+        return Err(anyhow!("前缀长度超过 {}: {}", bits, cidr));
+    }
+    // IPv4映射地址的前96位固定，前缀长度需要加上96
+    let prefix = prefix + (128 - bits);
+    let host_mask = u128::MAX.checked_shr(prefix).unwrap_or(0);
+    let start = ip_to_u128(ip) & !host_mask;
+    Ok((start, start | host_mask))
+}
+
+/// GeoIP数据库，按起始地址排序的网段
+#[derive(Debug, Default)]
+pub struct GeoDatabase {
+    ranges: Vec<(u128, u128, String)>,
+}
+
+impl GeoDatabase {
+    /**
+     * 解析GeoIP数据库
+     *
+     * 参数:
+     * @param text - CSV文本，每行一个 `网段,地区`
+     *
+     * 返回:
+     * 数据库，任一行格式无效时返回错误
+     */
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut ranges = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (cidr, region) = line
+                .split_once(',')
+                .ok_or_else(|| anyhow!("第 {} 行缺少地区", number + 1))?;
+            let (start, end) = parse_cidr(cidr).with_context(|| format!("第 {} 行", number + 1))?;
+            let region = normalize_region(region)
+                .ok_or_else(|| anyhow!("第 {} 行的地区无效: {}", number + 1, region))?;
+            ranges.push((start, end, region));
+        }
+        ranges.sort_by_key(|(start, _, _)| *start);
+        Ok(Self { ranges })
+    }
+
+    /// 从文件加载GeoIP数据库
+    pub fn load(path: &str) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("读取GeoIP数据库 {} 失败", path))?;
+        Self::parse(&text)
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// 查找IP地址所在的地区
+    pub fn lookup(&self, ip: IpAddr) -> Option<&str> {
+        let ip = ip_to_u128(ip);
+        // 起始地址不大于IP的最后一个网段
+        let index = self.ranges.partition_point(|(start, _, _)| *start <= ip).checked_sub(1)?;
+        let (_, end, region) = &self.ranges[index];
+        (ip <= *end).then_some(region.as_str())
+    }
+}
+
+/// 匹配地区服务
+pub struct GeoService {
+    config: GeoConfig,
+    database: Option<GeoDatabase>,
+    /// 连接按IP查找到的地区，按客户端ID索引
+    connections: Mutex<HashMap<String, String>>,
+}
+
+impl GeoService {
+    /// 创建匹配地区服务
+    pub fn new(config: GeoConfig, database: Option<GeoDatabase>) -> Self {
+        Self {
+            config,
+            database,
+            connections: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &GeoConfig {
+        &self.config
+    }
+
+    /// 按IP查找地区，没有配置数据库时返回None
+    pub fn lookup(&self, ip: IpAddr) -> Option<String> {
+        self.database.as_ref()?.lookup(ip).map(str::to_string)
+    }
+
+    /// 记录连接的IP所在的地区，查找不到时清除之前的记录
+    pub fn bind(&self, client_id: &str, ip: Option<IpAddr>) {
+        let mut connections = self.connections.lock();
+        match ip.and_then(|ip| self.lookup(ip)) {
+            Some(region) => {
+                connections.insert(client_id.to_string(), region);
+            }
+            None => {
+                connections.remove(client_id);
+            }
+        }
+    }
+
+    /// 连接断开时清除记录
+    pub fn unbind(&self, client_id: &str) {
+        self.connections.lock().remove(client_id);
+    }
+
+    /**
+     * 确定加入匹配队列时使用的地区
+     *
+     * 参数:
+     * @param client_id - 客户端ID
+     * @param declared - 客户端声明的地区
+     *
+     * 返回:
+     * 客户端声明的有效地区优先，否则使用连接IP所在的地区
+     */
+    pub fn resolve(&self, client_id: &str, declared: Option<&str>) -> Option<String> {
+        let declared = declared
+            .filter(|_| self.config.client_region_enabled)
+            .and_then(normalize_region);
+        declared.or_else(|| self.connections.lock().get(client_id).cloned())
+    }
+}
+
+// 全局匹配地区服务，首次使用时加载GeoIP数据库
+static GLOBAL_GEO_SERVICE: Lazy<GeoService> = Lazy::new(|| {
+    let config = GeoConfig::from_env();
+    let database = config.database.as_deref().and_then(|path| match GeoDatabase::load(path) {
+        Ok(database) => {
+            info!("GeoIP数据库 {} 已加载，共 {} 个网段", path, database.len());
+            Some(database)
+        }
+        Err(e) => {
+            warn!("加载GeoIP数据库失败，不按IP查找地区: {:#}", e);
+            None
+        }
+    });
+    GeoService::new(config, database)
+});
+
+/// 获取全局匹配地区服务
+pub fn geo_service() -> &'static GeoService {
+    &GLOBAL_GEO_SERVICE
+}
+
+/**
+ * 从匹配队列中选出一局游戏的玩家，优先同地区匹配
+ *
+ * 按地区在队列中首次出现的顺序，依次尝试只用该地区和没有地区标签的玩家组成一局；
+ * 都组不成时，如果有玩家等待超过回退时间，按进入队列的顺序跨地区匹配。
+ *
+ * 参数:
+ * @param queue - 匹配队列
+ * @param max_players - 每局游戏的人数上限
+ * @param now - 当前时间（毫秒）
+ * @param fallback_ms - 跨地区匹配前的等待时间（毫秒）
+ *
+ * 返回:
+ * 选中的队列项下标，不能开始游戏时返回None
+ */
+pub fn plan_regional_match(queue: &[QueueEntry], max_players: usize, now: u64, fallback_ms: u64) -> Option<Vec<usize>> {
+    let mut regions: Vec<Option<&str>> = Vec::new();
+    for entry in queue {
+        let region = entry.region.as_deref();
+        if !regions.contains(&region) {
+            regions.push(region);
+        }
+    }
+
+    for region in regions {
+        let candidates: Vec<usize> = (0..queue.len())
+            .filter(|&i| queue[i].region.is_none() || queue[i].region.as_deref() == region)
+            .collect();
+        let subset: Vec<QueueEntry> = candidates.iter().map(|&i| queue[i].clone()).collect();
+        if let Some(selected) = party::plan_match(&subset, max_players) {
+            return Some(selected.into_iter().map(|i| candidates[i]).collect());
+        }
+    }
+
+    // 同地区凑不成一局时，有玩家等待过久才跨地区匹配
+    let waited = queue.iter().any(|e| now.saturating_sub(e.enqueued_at) >= fallback_ms);
+    if waited {
+        return party::plan_match(queue, max_players);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gaming::UserInfo;
+
+    fn entry(id: &str, region: Option<&str>, enqueued_at: u64) -> QueueEntry {
+        let user = UserInfo {
+            id: id.to_string(),
+            name: id.to_string(),
+            rating: 1000,
+            avatar_url: None,
+            provisional: false,
+        };
+        QueueEntry::solo(user, enqueued_at).with_region(region.map(str::to_string))
+    }
+
+    #[test]
+    fn test_database_lookup() {
+        let database = GeoDatabase::parse(
+            "# 测试数据\n203.0.113.0/24,AP\n198.51.100.7,us\n2001:db8::/32,eu\n",
+        )
+        .unwrap();
+        assert_eq!(database.len(), 3);
+        assert_eq!(database.lookup("203.0.113.200".parse().unwrap()), Some("ap"));
+        assert_eq!(database.lookup("203.0.114.1".parse().unwrap()), None);
+        assert_eq!(database.lookup("198.51.100.7".parse().unwrap()), Some("us"));
+        assert_eq!(database.lookup("198.51.100.8".parse().unwrap()), None);
+        assert_eq!(database.lookup("2001:db8::1".parse().unwrap()), Some("eu"));
+
+        assert!(GeoDatabase::parse("10.0.0.0/33,eu").is_err());
+        assert!(GeoDatabase::parse("10.0.0.0/8,bad region").is_err());
+
+        let service = GeoService::new(GeoConfig::default(), Some(database));
+        service.bind("c1", Some("203.0.113.1".parse().unwrap()));
+        assert_eq!(service.resolve("c1", None).as_deref(), Some("ap"));
+        assert_eq!(service.resolve("c1", Some(" EU ")).as_deref(), Some("eu"));
+        service.unbind("c1");
+        assert_eq!(service.resolve("c1", None), None);
+    }
+
+    #[test]
+    fn test_prefer_same_region_then_fallback() {
+        let queue = vec![
+            entry("a", Some("eu"), 0),
+            entry("b", Some("us"), 0),
+            entry("c", Some("us"), 0),
+            entry("d", None, 0),
+        ];
+        // eu只有a和没有地区的d
+        assert_eq!(plan_regional_match(&queue, 2, 0, 1000), Some(vec![0, 3]));
+        assert_eq!(plan_regional_match(&queue[1..], 4, 0, 1000), Some(vec![0, 1, 2]));
+
+        // 不同地区的两名玩家等待超过回退时间后才跨地区匹配
+        let queue = vec![entry("a", Some("eu"), 0), entry("b", Some("us"), 500)];
+        assert_eq!(plan_regional_match(&queue, 4, 999, 1000), None);
+        assert_eq!(plan_regional_match(&queue, 4, 1000, 1000), Some(vec![0, 1]));
+    }
+}
@@ -0,0 +1,316 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 游客模式
+//!
+//! # 概述
+//! 游客不需要钱包和护照即可开始游戏：
+//! - 服务器为游客生成身份：随机的游客ID（格式与Profile ID相同，作为用户ID）和随机的占位地址
+//! - 游客档案只保存在缓存中，不会创建链上Profile；游客不能结算余额、铸造成就，好友关系也不会提交到链上
+//! - 游客之后可以通过钱包登录请求升级：服务器为护照创建链上Profile，把评分历史、统计数据和好友关系
+//!   迁移到新的Profile ID，并把游客标记为已升级，已升级的游客不能再次升级
+//!
+//! 游客身份只保存在会话中，会话失效或缓存过期后无法找回，需要保留数据的游客应尽早升级。
+//! 对局进行中的游客不能升级，避免对局结果记录到已迁移的游客ID上。
+//!
+//! # 接口
+//! 创建和升级的接口在 `catastrophe` 模块中，都经过 `profile_guard` 的验证码和限流检查：
+//! - `POST /user/guest`：创建游客并写入会话
+//! - `POST /user/guest/upgrade`：用钱包登录请求把当前游客升级为链上Profile
+//!
+//! # 配置
+//! - `GUEST_MODE_ENABLED`：是否允许创建游客，默认开启
+//! - `GUEST_SESSION_TTL_HOURS`：游客会话的有效期（小时），默认24
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Result};
+use fastcrypto::encoding::{Base64, Encoding};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sui_sdk::types::base_types::{ObjectID, SuiAddress};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::avatars::make_avatar;
+use crate::game::{GameCachePrefix, GameService};
+use crate::gaming::INITIAL_RATING;
+use crate::sdk::Profile;
+
+/// 默认游客会话的有效期（小时）
+pub const DEFAULT_SESSION_TTL_HOURS: u64 = 24;
+
+/// 游客模式配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuestConfig {
+    /// 是否允许创建游客
+    pub enabled: bool,
+    /// 游客会话的有效期（毫秒）
+    pub session_ttl_ms: u64,
+}
+
+impl Default for GuestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            session_ttl_ms: DEFAULT_SESSION_TTL_HOURS * 3600 * 1000,
+        }
+    }
+}
+
+impl GuestConfig {
+    /// 从环境变量读取游客配置，未设置的项使用默认值
+    pub fn from_env() -> Self {
+        let parse = |key: &str| std::env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        let defaults = Self::default();
+        Self {
+            enabled: std::env::var("GUEST_MODE_ENABLED")
+                .map(|v| !matches!(v.trim().to_ascii_lowercase().as_str(), "0" | "false" | "no"))
+                .unwrap_or(defaults.enabled),
+            session_ttl_ms: parse("GUEST_SESSION_TTL_HOURS")
+                .filter(|v| *v > 0)
+                .map(|v| v * 3600 * 1000)
+                .unwrap_or(defaults.session_ttl_ms),
+        }
+    }
+}
+
+/// 游客账户
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GuestAccount {
+    /// 游客ID，作为用户ID使用
+    pub id: ObjectID,
+    /// 占位地址，写入会话的用户地址
+    pub address: SuiAddress,
+    /// 创建时间（毫秒）
+    pub created_at: u64,
+    /// 升级后的Profile ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upgraded_to: Option<String>,
+}
+
+impl GuestAccount {
+    /// 是否仍是未升级的游客
+    pub fn is_active(&self) -> bool {
+        self.upgraded_to.is_none()
+    }
+
+    /// 游客的缓存档案
+    pub fn profile(&self) -> Profile {
+        let svg = make_avatar(&self.id.to_string());
+        Profile {
+            id: self.id,
+            avatar: format!("data:image/svg+xml;base64,{}", Base64::encode(svg.as_bytes())),
+            rating: INITIAL_RATING as u64,
+            played: 0,
+            won: 0,
+            lost: 0,
+        }
+    }
+}
+
+/// 游客升级时迁移的数据
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GuestMigration {
+    /// 是否迁移了统计数据或评分历史
+    pub stats: bool,
+    /// 迁移的好友数量
+    pub friends: usize,
+}
+
+/// 游客服务
+pub struct GuestService {
+    /// 游戏服务，处理缓存
+    game_service: Arc<GameService>,
+    config: GuestConfig,
+    /// 写锁，保证升级标记的原子性
+    lock: Mutex<()>,
+}
+
+impl GuestService {
+    /// 创建新的游客服务
+    pub fn new(game_service: Arc<GameService>, config: GuestConfig) -> Self {
+        Self {
+            game_service,
+            config,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn config(&self) -> &GuestConfig {
+        &self.config
+    }
+
+    /// 获取游客账户，包括已升级的游客
+    pub fn get(&self, id: &str) -> Option<GuestAccount> {
+        self.game_service.get(GameCachePrefix::GUEST, id)
+    }
+
+    /// 是否是未升级的游客
+    pub fn is_guest(&self, id: &str) -> bool {
+        self.get(id).is_some_and(|account| account.is_active())
+    }
+
+    /**
+     * 创建游客账户
+     *
+     * 参数:
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 新的游客账户
+     */
+    pub fn create(&self, now: u64) -> Result<GuestAccount> {
+        if !self.config.enabled {
+            bail!("游客模式未开启");
+        }
+        let account = GuestAccount {
+            id: ObjectID::random(),
+            address: SuiAddress::from(ObjectID::random()),
+            created_at: now,
+            upgraded_to: None,
+        };
+        if !self.game_service.set(GameCachePrefix::GUEST, &account.id.to_string(), &account) {
+            bail!("保存游客账户失败");
+        }
+        info!("创建游客 {}", account.id);
+        Ok(account)
+    }
+
+    /**
+     * 把游客标记为已升级
+     *
+     * 参数:
+     * @param id - 游客ID
+     * @param profile_id - 升级后的Profile ID
+     *
+     * 返回:
+     * 更新后的游客账户，游客不存在或已升级时返回错误
+     */
+    pub fn mark_upgraded(&self, id: &str, profile_id: &str) -> Result<GuestAccount> {
+        let _guard = self.lock.lock();
+        let mut account = self.get(id).ok_or_else(|| anyhow!("游客不存在"))?;
+        if let Some(upgraded_to) = &account.upgraded_to {
+            bail!("游客已升级为 {}", upgraded_to);
+        }
+        account.upgraded_to = Some(profile_id.to_string());
+        if !self.game_service.set(GameCachePrefix::GUEST, id, &account) {
+            bail!("保存游客账户失败");
+        }
+        Ok(account)
+    }
+}
+
+// 用于存储全局GuestService实例的静态变量
+static GLOBAL_GUEST_SERVICE: OnceCell<Arc<GuestService>> = OnceCell::new();
+
+/// 初始化游客服务并设置为全局实例
+pub fn init_guest_service(game_service: Arc<GameService>) -> Arc<GuestService> {
+    let service = Arc::new(GuestService::new(game_service, GuestConfig::from_env()));
+    let _ = GLOBAL_GUEST_SERVICE.set(service.clone());
+    service
+}
+
+/// 获取全局游客服务
+pub fn global_guest_service() -> Option<Arc<GuestService>> {
+    GLOBAL_GUEST_SERVICE.get().cloned()
+}
+
+/// 用户ID是否属于未升级的游客，服务未初始化时为否
+pub fn is_guest(id: &str) -> bool {
+    global_guest_service().is_some_and(|service| service.is_guest(id))
+}
+
+/**
+ * 检查游客当前能否升级
+ *
+ * 参数:
+ * @param guest_id - 游客ID
+ *
+ * 返回:
+ * 不是未升级的游客或有进行中的对局时返回错误
+ */
+pub async fn check_upgradable(guest_id: &str) -> Result<()> {
+    let service = global_guest_service().ok_or_else(|| anyhow!("游客服务未初始化"))?;
+    if !service.is_guest(guest_id) {
+        bail!("当前用户不是游客或已升级");
+    }
+    if let Some(passport) = crate::ws::global_passport_state() {
+        if !passport.get_user_ongoing_games(guest_id).await.is_empty() {
+            bail!("对局进行中，结束后再升级");
+        }
+    }
+    Ok(())
+}
+
+/**
+ * 把游客升级为已创建的链上Profile，迁移评分历史、统计数据和好友关系
+ *
+ * 参数:
+ * @param guest_id - 游客ID
+ * @param profile_id - 新的Profile ID
+ *
+ * 返回:
+ * 迁移的数据，游客不存在或已升级时返回错误
+ */
+pub async fn upgrade(guest_id: &str, profile_id: &str) -> Result<GuestMigration> {
+    let service = global_guest_service().ok_or_else(|| anyhow!("游客服务未初始化"))?;
+    // 先标记升级，并发的重复升级请求不会重复迁移
+    service.mark_upgraded(guest_id, profile_id)?;
+
+    let mut migration = GuestMigration::default();
+    if let Some(stats) = crate::stats::global_stats_service() {
+        migration.stats = stats.transfer(guest_id, profile_id);
+    }
+    if let Some(passport) = crate::ws::global_passport_state() {
+        match passport.transfer_friends(guest_id, profile_id).await {
+            Ok(friends) => migration.friends = friends,
+            Err(e) => warn!("迁移游客 {} 的好友关系失败: {}", guest_id, e),
+        }
+    }
+    info!(
+        "游客 {} 已升级为 {}，统计数据 {}，好友 {} 个",
+        guest_id, profile_id, migration.stats, migration.friends
+    );
+    Ok(migration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(enabled: bool) -> GuestService {
+        GuestService::new(
+            Arc::new(GameService::new()),
+            GuestConfig { enabled, ..Default::default() },
+        )
+    }
+
+    #[test]
+    fn test_create_and_upgrade_once() {
+        let service = service(true);
+        let account = service.create(1).unwrap();
+        let id = account.id.to_string();
+        assert!(service.is_guest(&id));
+        assert_eq!(account.profile().id, account.id);
+
+        let upgraded = service.mark_upgraded(&id, "0xaa").unwrap();
+        assert_eq!(upgraded.upgraded_to.as_deref(), Some("0xaa"));
+        assert!(!service.is_guest(&id));
+        assert!(service.get(&id).is_some());
+
+        // 已升级的游客不能再次升级
+        assert!(service.mark_upgraded(&id, "0xbb").is_err());
+    }
+
+    #[test]
+    fn test_disabled_and_unknown() {
+        let service = service(false);
+        assert!(service.create(1).is_err());
+        assert!(!service.is_guest("0x1"));
+        assert!(service.mark_upgraded("0x1", "0xaa").is_err());
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 手牌上限
+//!
+//! # 概述
+//! 抢夺等效果可以让玩家不断积攒手牌，每局游戏因此设有手牌上限（`MatchData.max_hand_size`，0表示不限制）。
+//! 回合结束时当前玩家的手牌超过上限，`MatchService` 先要求玩家弃掉多出的牌，弃牌后才轮到下一名玩家：
+//! - 弃牌期间回合计时暂停，所有玩家不能抽牌或出牌，与选择目标相同
+//! - 玩家在 `DISCARD_TIMEOUT_MS` 内没有选择时随机弃牌
+//! - 弃掉的牌公开放入弃牌堆，每张牌在动作历史中记录为一个弃牌动作，重放时按记录执行
+//!
+//! 私人对局创建时可以通过 `maxHandSize` 指定上限，牌组预设中也附带服务器的默认上限。
+//!
+//! # 配置
+//! - `MATCH_MAX_HAND_SIZE`：默认手牌上限，默认10，0表示不限制
+//!
+//! # 消息格式
+//! - `match:discard`（服务器发给弃牌玩家）：payload包含matchId、pendingId、count（需要弃掉的张数）、
+//!   maxHandSize和deadline
+//! - `match:discard`（客户端）：`{matchId, pendingId, cardIds}`，cardIds必须恰好是count张手牌
+//! - `match:discard`（广播）：payload包含matchId、userId、cards（弃掉的牌）和auto（是否超时随机弃牌）
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+
+use crate::gaming::Card;
+
+/// 默认手牌上限
+pub const DEFAULT_MAX_HAND_SIZE: usize = 10;
+/// 可配置的最小手牌上限，不小于开局手牌数
+pub const MIN_HAND_SIZE_LIMIT: usize = 5;
+/// 可配置的最大手牌上限
+pub const MAX_HAND_SIZE_LIMIT: usize = 30;
+/// 弃牌的等待时间（毫秒），超时后随机弃牌
+pub const DISCARD_TIMEOUT_MS: u64 = 15 * 1000;
+
+static MAX_HAND_SIZE: Lazy<usize> = Lazy::new(|| {
+    std::env::var("MATCH_MAX_HAND_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .and_then(|v| validate(v).ok())
+        .unwrap_or(DEFAULT_MAX_HAND_SIZE)
+});
+
+/// 新建游戏的默认手牌上限，0表示不限制
+pub fn default_max_hand_size() -> usize {
+    *MAX_HAND_SIZE
+}
+
+/**
+ * 校验手牌上限
+ *
+ * 参数:
+ * @param size - 手牌上限，0表示不限制
+ *
+ * 返回:
+ * 上限不在可配置范围内时返回错误
+ */
+pub fn validate(size: usize) -> Result<usize> {
+    if size != 0 && !(MIN_HAND_SIZE_LIMIT..=MAX_HAND_SIZE_LIMIT).contains(&size) {
+        return Err(anyhow!(
+            "手牌上限必须在 {} 到 {} 之间，0表示不限制",
+            MIN_HAND_SIZE_LIMIT,
+            MAX_HAND_SIZE_LIMIT
+        ));
+    }
+    Ok(size)
+}
+
+/// 超过手牌上限、需要弃掉的张数
+pub fn excess(hand_size: usize, max_hand_size: usize) -> usize {
+    if max_hand_size == 0 {
+        return 0;
+    }
+    hand_size.saturating_sub(max_hand_size)
+}
+
+/**
+ * 校验玩家选择弃掉的牌
+ *
+ * 参数:
+ * @param hand - 玩家的手牌
+ * @param card_ids - 选择弃掉的卡牌ID
+ * @param count - 需要弃掉的张数
+ *
+ * 返回:
+ * 张数不对、有重复或不在手牌中时返回错误
+ */
+pub fn validate_selection(hand: &[Card], card_ids: &[String], count: usize) -> Result<()> {
+    if card_ids.len() != count {
+        return Err(anyhow!("需要弃掉 {} 张牌", count));
+    }
+    for (index, card_id) in card_ids.iter().enumerate() {
+        if card_ids[..index].contains(card_id) {
+            return Err(anyhow!("重复选择了卡牌 {}", card_id));
+        }
+        if !hand.iter().any(|c| &c.id == card_id) {
+            return Err(anyhow!("卡牌不存在"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gaming::CardType;
+
+    fn card(id: &str) -> Card {
+        Card { id: id.to_string(), card_type: CardType::Cat, variant: None }
+    }
+
+    #[test]
+    fn test_discard_down() {
+        assert!(validate(0).is_ok());
+        assert!(validate(3).is_err());
+        assert!(validate(MAX_HAND_SIZE_LIMIT + 1).is_err());
+
+        assert_eq!(excess(12, 10), 2);
+        assert_eq!(excess(8, 10), 0);
+        assert_eq!(excess(40, 0), 0);
+
+        let hand = vec![card("a"), card("b"), card("c")];
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        assert!(validate_selection(&hand, &ids(&["a", "c"]), 2).is_ok());
+        assert!(validate_selection(&hand, &ids(&["a"]), 2).is_err());
+        assert!(validate_selection(&hand, &ids(&["a", "a"]), 2).is_err());
+        assert!(validate_selection(&hand, &ids(&["a", "x"]), 2).is_err());
+    }
+}
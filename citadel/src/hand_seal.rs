@@ -0,0 +1,272 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 对局私密消息加密
+//!
+//! # 概述
+//! 抽到的牌、偷到的牌、预见的牌堆顶等只发给单个玩家的消息，经过房间发送、重发队列和日志时都是明文，
+//! 服务器的传输或广播环节出错时可能泄露玩家的手牌。协商了 `sealed_hands` 能力的客户端改为收到
+//! 使用本密钥服务器IBE公钥加密的消息，只有该玩家能通过 `/v1/fetch_key` 取得解密密钥。
+//!
+//! 加密身份由对局ID和玩家登录会话的钱包地址派生：`id = bcs(钱包地址) || 对局ID`，完整身份为 `[包ID][id]`。
+//! WebSocket的用户ID是随机的客户端ID，不能作为地址，钱包地址在建立连接时从登录会话中读取。
+//! 包中的 `citadel::seal_approve_match_hand(id)` 只允许地址与id前缀一致的发送者获取密钥，
+//! 客户端在fetch_key请求的PTB中调用该函数，每场对局只需获取一次密钥。
+//!
+//! 整条响应（包括按客户端语言渲染后的提示文本和参数）使用AES-256-GCM加密为Seal的 `EncryptedObject`，
+//! 附加数据为事件名，密文不能被当作其他事件解密。没有登录钱包的连接（如游客）无法获取密钥，
+//! 协商时不会获得 `sealed_hands` 能力；已协商该能力的连接收到的私密消息无法加密时被丢弃，不会退回明文。
+//!
+//! # 配置
+//! - `MATCH_HAND_SEAL_PACKAGE`：提供 `seal_approve_match_hand` 的包ID，必须是包的第一个版本，默认使用 `CITADEL_PACKAGE`
+//!
+//! # 消息格式
+//! 加密后的事件名不变，data为 `{ok, payload: {sealed}}`，sealed包含：
+//! - `matchId`：对局ID
+//! - `id`：Hex编码的身份，即fetch_key时传给 `seal_approve_match_hand` 的参数
+//! - `packageId`、`keyServer`：包ID和密钥服务器对象ID
+//! - `ciphertext`：Base64编码的BCS序列化 `EncryptedObject`，解密后为原来的data JSON
+
+use anyhow::{anyhow, Result};
+use crypto::{ibe, seal_encrypt, EncryptionInput, IBEPublicKeys, ObjectID};
+use fastcrypto::encoding::{Base64, Encoding, Hex};
+use once_cell::sync::OnceCell;
+use serde_json::{json, Value};
+use sui_types::base_types::SuiAddress;
+use tracing::info;
+
+use crate::i18n;
+use crate::protocol::{capabilities, ClientProtocol};
+use crate::ws::WsResponse;
+use crate::AppState;
+
+static HAND_SEALER: OnceCell<HandSealer> = OnceCell::new();
+
+/**
+ * 使用本服务的主密钥初始化全局加密器
+ *
+ * 参数:
+ * @param state - 应用状态
+ *
+ * 返回:
+ * 包ID无效时返回错误
+ */
+pub fn init_hand_sealer(state: &AppState) -> Result<()> {
+    let sealer = HandSealer::from_app_state(state)?;
+    info!("已启用对局私密消息加密，包ID: {}", sealer.package_id);
+    let _ = HAND_SEALER.set(sealer);
+    Ok(())
+}
+
+/// 获取全局加密器，未初始化时返回None
+pub fn hand_sealer() -> Option<&'static HandSealer> {
+    HAND_SEALER.get()
+}
+
+/// 连接能否使用加密的私密消息，需要启用加密器且连接有登录钱包
+pub fn can_seal(wallet: Option<&SuiAddress>) -> bool {
+    hand_sealer().is_some() && wallet.is_some()
+}
+
+/**
+ * 派生玩家在对局中的加密身份
+ *
+ * 参数:
+ * @param match_id - 对局ID
+ * @param address - 玩家登录会话的钱包地址
+ *
+ * 返回:
+ * 身份，即 `seal_approve_match_hand` 的id参数
+ */
+pub fn identity(match_id: &str, address: &SuiAddress) -> Result<Vec<u8>> {
+    let mut id = bcs::to_bytes(address)?;
+    id.extend_from_slice(match_id.as_bytes());
+    Ok(id)
+}
+
+/// 使用密钥服务器公钥加密对局私密消息
+pub struct HandSealer {
+    package_id: ObjectID,
+    key_server: ObjectID,
+    public_keys: IBEPublicKeys,
+}
+
+impl std::fmt::Debug for HandSealer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HandSealer")
+            .field("package_id", &self.package_id)
+            .field("key_server", &self.key_server)
+            .finish()
+    }
+}
+
+impl HandSealer {
+    pub fn new(package_id: ObjectID, key_server: ObjectID, public_key: ibe::PublicKey) -> Self {
+        Self {
+            package_id,
+            key_server,
+            public_keys: IBEPublicKeys::BonehFranklinBLS12381(vec![public_key]),
+        }
+    }
+
+    /// 使用本服务的主密钥和配置的包ID创建
+    pub fn from_app_state(app_state: &AppState) -> Result<Self> {
+        let package = std::env::var("MATCH_HAND_SEAL_PACKAGE")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .or_else(|| app_state.config.get("CITADEL_PACKAGE").cloned())
+            .ok_or_else(|| anyhow!("未配置MATCH_HAND_SEAL_PACKAGE或CITADEL_PACKAGE"))?;
+        let package_id = ObjectID::from_hex_literal(package.trim())
+            .map_err(|e| anyhow!("MATCH_HAND_SEAL_PACKAGE无效: {}", e))?;
+        Ok(Self::new(
+            package_id,
+            ObjectID::new(app_state.key_server_object_id.into_bytes()),
+            ibe::public_key_from_master_key(&app_state.master_key),
+        ))
+    }
+
+    /**
+     * 加密发给玩家的消息
+     *
+     * 参数:
+     * @param match_id - 对局ID
+     * @param address - 接收消息的玩家的钱包地址
+     * @param event - 事件名，作为附加数据
+     * @param data - 已按客户端语言渲染的消息数据
+     *
+     * 返回:
+     * 替代原数据发送的加密响应
+     */
+    pub fn seal(&self, match_id: &str, address: &SuiAddress, event: &str, data: &Value) -> Result<Value> {
+        let id = identity(match_id, address)?;
+        let (encrypted, _) = seal_encrypt(
+            self.package_id,
+            id.clone(),
+            vec![self.key_server],
+            &self.public_keys,
+            1,
+            EncryptionInput::Aes256Gcm {
+                data: serde_json::to_vec(data)?,
+                aad: Some(event.as_bytes().to_vec()),
+            },
+        )
+        .map_err(|e| anyhow!("加密对局消息失败: {}", e))?;
+        let response = WsResponse {
+            ok: true,
+            payload: Some(json!({
+                "sealed": {
+                    "matchId": match_id,
+                    "id": Hex::encode(&id),
+                    "packageId": self.package_id.to_hex_uncompressed(),
+                    "keyServer": self.key_server.to_hex_uncompressed(),
+                    "ciphertext": Base64::encode(bcs::to_bytes(&encrypted)?),
+                }
+            })),
+            ..Default::default()
+        };
+        Ok(serde_json::to_value(response)?)
+    }
+}
+
+/**
+ * 生成发给单个玩家的私密消息
+ *
+ * 客户端没有协商 `sealed_hands` 能力时原样返回；协商了该能力时必须加密，
+ * 没有加密器或钱包地址、加密失败时返回错误，调用方应丢弃消息。
+ *
+ * 参数:
+ * @param sealer - 加密器
+ * @param protocol - 接收者协商的协议
+ * @param wallet - 接收者连接的钱包地址
+ * @param match_id - 对局ID
+ * @param user_id - 接收者的用户ID，即WebSocket客户端ID
+ * @param event - 事件名
+ * @param data - 消息数据
+ *
+ * 返回:
+ * 要发送的消息数据
+ */
+pub fn private_message(
+    sealer: Option<&HandSealer>,
+    protocol: &ClientProtocol,
+    wallet: Option<&SuiAddress>,
+    match_id: &str,
+    user_id: &str,
+    event: &str,
+    mut data: Value,
+) -> Result<Value> {
+    if !protocol.supports(capabilities::SEALED_HANDS) {
+        return Ok(data);
+    }
+    let (Some(sealer), Some(address)) = (sealer, wallet) else {
+        return Err(anyhow!("客户端 {} 协商了加密但没有可用的加密身份，丢弃消息 {}", user_id, event));
+    };
+    // 加密后无法再按客户端语言渲染提示文本，先渲染再加密
+    i18n::localize_value(&mut data, protocol.locale);
+    sealer
+        .seal(match_id, address, event, &data)
+        .map_err(|e| anyhow!("无法加密发给客户端 {} 的消息 {}，已丢弃: {}", user_id, event, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::{create_full_id, seal_decrypt, EncryptedObject, IBEUserSecretKeys};
+    use rand::thread_rng;
+
+    #[test]
+    fn test_seal_roundtrip() {
+        let (master_key, public_key) = ibe::generate_key_pair(&mut thread_rng());
+        let package_id = ObjectID::random();
+        let key_server = ObjectID::random();
+        let sealer = HandSealer::new(package_id, key_server, public_key);
+        let address = SuiAddress::random_for_testing_only();
+        let data = json!({"ok": true, "payload": {"card": "Defuse"}});
+
+        let sealed = sealer.seal("m1", &address, "match:draw_card", &data).unwrap();
+        assert!(sealed.get("msg").is_none());
+        let sealed = &sealed["payload"]["sealed"];
+        let id = Hex::decode(sealed["id"].as_str().unwrap()).unwrap();
+        assert_eq!(id, identity("m1", &address).unwrap());
+        assert!(id.ends_with(b"m1"));
+
+        // 玩家从密钥服务器取得的用户密钥可以解密
+        let encrypted: EncryptedObject =
+            bcs::from_bytes(&Base64::decode(sealed["ciphertext"].as_str().unwrap()).unwrap()).unwrap();
+        let usk = ibe::extract(&master_key, &create_full_id(&package_id.into_bytes(), &id));
+        let keys = IBEUserSecretKeys::BonehFranklinBLS12381([(key_server, usk)].into_iter().collect());
+        let plain = seal_decrypt(&encrypted, &keys, None).unwrap();
+        assert_eq!(serde_json::from_slice::<Value>(&plain).unwrap(), data);
+
+        // 其他对局的密钥不能解密
+        let other = identity("m2", &address).unwrap();
+        let usk = ibe::extract(&master_key, &create_full_id(&package_id.into_bytes(), &other));
+        let keys = IBEUserSecretKeys::BonehFranklinBLS12381([(key_server, usk)].into_iter().collect());
+        assert!(seal_decrypt(&encrypted, &keys, None).is_err());
+    }
+
+    #[test]
+    fn test_private_message_for_ws_client() {
+        let (_, public_key) = ibe::generate_key_pair(&mut thread_rng());
+        let sealer = HandSealer::new(ObjectID::random(), ObjectID::random(), public_key);
+        // WebSocket的用户ID是随机的客户端ID，不是地址
+        let user_id = uuid::Uuid::new_v4().to_string();
+        let address = SuiAddress::random_for_testing_only();
+        let data = json!({"ok": true, "payload": {"card": "Defuse"}});
+        let mut sealed_hands = ClientProtocol::default();
+        sealed_hands.capabilities.insert(capabilities::SEALED_HANDS.to_string());
+
+        // 按钱包地址派生身份加密
+        let sealed = private_message(Some(&sealer), &sealed_hands, Some(&address), "m1", &user_id, "match:draw_card", data.clone()).unwrap();
+        let id = Hex::decode(sealed["payload"]["sealed"]["id"].as_str().unwrap()).unwrap();
+        assert_eq!(id, identity("m1", &address).unwrap());
+
+        // 协商了加密但没有钱包或加密器时丢弃，不会退回明文
+        assert!(private_message(Some(&sealer), &sealed_hands, None, "m1", &user_id, "match:draw_card", data.clone()).is_err());
+        assert!(private_message(None, &sealed_hands, Some(&address), "m1", &user_id, "match:draw_card", data.clone()).is_err());
+
+        // 没有协商加密的客户端收到原始消息
+        let plain = private_message(Some(&sealer), &ClientProtocol::default(), None, "m1", &user_id, "match:draw_card", data.clone()).unwrap();
+        assert_eq!(plain, data);
+    }
+}
@@ -0,0 +1,392 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 服务端消息本地化模块
+//!
+//! # 概述
+//! 服务端下发的提示消息（WsResponse的`msg`、护照模块响应中的`msg`）都带有消息代码`code`
+//! 和参数`params`，客户端既可以直接显示`msg`，也可以根据代码自行本地化。
+//!
+//! 每个连接的语言在建立WebSocket连接时根据`Accept-Language`请求头确定，
+//! 也可以在`hello`握手消息中通过`locale`字段指定。连接管理器在发送消息时
+//! 按客户端语言重新渲染`msg`，默认语言为中文。
+//!
+//! # 消息格式
+//! ```json
+//! { "ok": true, "msg": "Player u1 joined the match", "code": "match.player_joined", "params": { "user": "u1" } }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// 支持的语言
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum Locale {
+    /// 简体中文
+    #[default]
+    #[serde(rename = "zh")]
+    Zh,
+    /// 英文
+    #[serde(rename = "en")]
+    En,
+}
+
+impl Locale {
+    /// 所有支持的语言
+    pub const ALL: [Locale; 2] = [Locale::Zh, Locale::En];
+
+    /// 语言在ALL中的序号
+    pub fn index(&self) -> usize {
+        match self {
+            Locale::Zh => 0,
+            Locale::En => 1,
+        }
+    }
+
+    /// 语言代码，与序列化结果一致
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::Zh => "zh",
+            Locale::En => "en",
+        }
+    }
+
+    /// 从语言标签解析（如 `en-US`、`zh-CN`）
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next()?.to_lowercase();
+        match primary.as_str() {
+            "zh" => Some(Locale::Zh),
+            "en" => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    /// 从Accept-Language请求头中选择权重最高的支持语言
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        let mut best: Option<(Locale, f32)> = None;
+        for item in header.split(',') {
+            let mut parts = item.split(';');
+            let Some(locale) = parts.next().and_then(Locale::from_tag) else {
+                continue;
+            };
+            let quality = parts
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if best.map(|(_, q)| quality > q).unwrap_or(true) {
+                best = Some((locale, quality));
+            }
+        }
+        best.map(|(locale, _)| locale)
+    }
+}
+
+/// 消息代码
+pub mod codes {
+    // 对局
+    pub const PLAYER_JOINED: &str = "match.player_joined";
+    pub const PLAYER_LEFT: &str = "match.player_left";
+    pub const MATCH_CREATED: &str = "match.created";
+    pub const MATCH_STARTED: &str = "match.started";
+    pub const MATCH_ENDED: &str = "match.ended";
+    pub const PLAYER_WON: &str = "match.player_won";
+    pub const CARD_DRAWN: &str = "match.card_drawn";
+    pub const YOU_DREW: &str = "match.you_drew";
+    pub const YOU_DREW_EXPLODING_KITTEN: &str = "match.you_drew_exploding_kitten";
+    pub const KITTEN_DEFUSED: &str = "match.kitten_defused";
+    pub const PLAYER_EXPLODED: &str = "match.player_exploded";
+    pub const CARD_PLAYED: &str = "match.card_played";
+    pub const TURN_CHANGED: &str = "match.turn_changed";
+    pub const PLAYER_TIMED_OUT: &str = "match.player_timed_out";
+    pub const MATCH_STATE: &str = "match.state";
+    pub const CHAIN_STARTED: &str = "match.chain_started";
+    pub const CHAIN_RESOLVED: &str = "match.chain_resolved";
+    pub const CHAIN_CANCELED: &str = "match.chain_canceled";
+    pub const NOPE_CANCELED: &str = "match.nope_canceled";
+    pub const SAW_FUTURE: &str = "match.saw_future";
+    pub const CARD_STOLEN: &str = "match.card_stolen";
+    pub const YOU_STOLE: &str = "match.you_stole";
+    pub const ALTER_FUTURE_PROMPT: &str = "match.alter_future_prompt";
+    pub const FUTURE_ALTERED: &str = "match.future_altered";
+    pub const FUTURE_SHARED_WITH_YOU: &str = "match.future_shared_with_you";
+    pub const YOU_SHARED_FUTURE: &str = "match.you_shared_future";
+    pub const SELECT_TARGET_PROMPT: &str = "match.select_target_prompt";
+    pub const YOU_BURIED_CARD: &str = "match.you_buried_card";
+    pub const CARD_BURIED: &str = "match.card_buried";
+    pub const EXPLOSION_SPED_UP: &str = "match.explosion_sped_up";
+    pub const IMPLODING_KITTEN_INSERTED: &str = "match.imploding_kitten_inserted";
+    pub const CAT_CARD_PLAYED: &str = "match.cat_card_played";
+    pub const NOPE_PLAYED: &str = "match.nope_played";
+    pub const CARD_USED: &str = "match.card_used";
+    pub const PAUSE_REQUESTED: &str = "match.pause_requested";
+    pub const PAUSE_VOTED: &str = "match.pause_voted";
+    pub const PAUSE_REJECTED: &str = "match.pause_rejected";
+    pub const PAUSE_EXPIRED: &str = "match.pause_expired";
+    pub const MATCH_PAUSED: &str = "match.paused";
+    pub const MATCH_RESUMED: &str = "match.resumed";
+    pub const ACTION_QUEUED: &str = "match.action_queued";
+    pub const SKIP_VOTE_PROGRESS: &str = "match.skip_vote_progress";
+    pub const PLAYER_SKIPPED: &str = "match.player_skipped";
+    pub const DISCARD_PROMPT: &str = "match.discard_prompt";
+    pub const CARDS_DISCARDED: &str = "match.cards_discarded";
+    pub const DECK_RESHUFFLED: &str = "match.deck_reshuffled";
+    pub const DECK_EXHAUSTED: &str = "match.deck_exhausted";
+
+    // 观战
+    pub const SPECTATOR_JOINED: &str = "spectate.joined";
+    pub const SPECTATOR_LEFT: &str = "spectate.left";
+    pub const SPECTATE_DISABLED: &str = "spectate.disabled";
+    pub const SPECTATE_FRIENDS_ONLY: &str = "spectate.friends_only";
+    pub const SPECTATE_FULL: &str = "spectate.full";
+    pub const SPECTATE_ALREADY_PLAYING: &str = "spectate.already_playing";
+    pub const SPECTATE_ALREADY_SPECTATING: &str = "spectate.already_spectating";
+
+    // 连接
+    pub const ROOM_JOINED: &str = "room.joined";
+    pub const ROOM_LEFT: &str = "room.left";
+    pub const ROOM_NOT_MEMBER: &str = "room.not_member";
+    pub const ROOM_SERVER_MANAGED: &str = "room.server_managed";
+    pub const RECONNECTED: &str = "room.reconnected";
+    pub const RECONNECTED_NO_ROOMS: &str = "room.reconnected_no_rooms";
+    pub const RESUME_TOKEN_INVALID: &str = "room.resume_token_invalid";
+    pub const RESUME_TOKEN_EXPIRED: &str = "room.resume_token_expired";
+    pub const SESSION_ATTESTED: &str = "session.attested";
+    pub const SESSION_ATTEST_FAILED: &str = "session.attest_failed";
+
+    // 成就
+    pub const ACHIEVEMENT_UNLOCKED: &str = "achievement.unlocked";
+
+    // 用户护照
+    pub const USER_NOT_FOUND: &str = "passport.user_not_found";
+    pub const ALREADY_FRIENDS: &str = "passport.already_friends";
+    pub const BLOCKED_BY_USER: &str = "passport.blocked_by_user";
+    pub const FRIEND_REQUEST_ALREADY_SENT: &str = "passport.friend_request_already_sent";
+    pub const NO_FRIEND_REQUEST_TO_REVOKE: &str = "passport.no_friend_request_to_revoke";
+    pub const NO_FRIEND_REQUEST_TO_ACCEPT: &str = "passport.no_friend_request_to_accept";
+    pub const NO_FRIEND_REQUEST_TO_REJECT: &str = "passport.no_friend_request_to_reject";
+    pub const NO_RELATIONSHIP: &str = "passport.no_relationship";
+    pub const NOT_FRIENDS: &str = "passport.not_friends";
+    pub const MATCH_SERVICE_UNAVAILABLE: &str = "passport.match_service_unavailable";
+    pub const SET_INTERIM_FAILED: &str = "passport.set_interim_failed";
+
+    // 聊天
+    pub const CHAT_JOINED: &str = "chat.joined";
+    pub const CHAT_SENT: &str = "chat.sent";
+    pub const CHAT_NOT_JOINED: &str = "chat.not_joined";
+    pub const DM_RECIPIENT_BLOCKED: &str = "chat.dm_recipient_blocked";
+
+    // 负载校验
+    pub const PAYLOAD_TOO_LARGE: &str = "payload.too_large";
+    pub const PAYLOAD_TOO_DEEP: &str = "payload.too_deep";
+    pub const PAYLOAD_FIELD_TOO_LONG: &str = "payload.field_too_long";
+
+    // 组队
+    pub const PARTY_UPDATED: &str = "party.updated";
+    pub const PARTY_INVITED: &str = "party.invited";
+    pub const PARTY_FAILED: &str = "party.failed";
+
+    // 连接
+    pub const CONNECTION_RESYNC: &str = "connection.resync";
+
+    // 封禁
+    pub const QUEUE_BANNED: &str = "ban.queue";
+}
+
+/// 消息目录：代码、中文、英文
+const CATALOG: &[(&str, &str, &str)] = &[
+    (codes::PLAYER_JOINED, "玩家 {user} 加入了游戏", "Player {user} joined the match"),
+    (codes::PLAYER_LEFT, "玩家 {user} 离开了游戏", "Player {user} left the match"),
+    (codes::MATCH_CREATED, "游戏已创建，ID: {match}", "Match created, ID: {match}"),
+    (codes::MATCH_STARTED, "游戏开始", "The match has started"),
+    (codes::MATCH_ENDED, "游戏结束", "The match has ended"),
+    (codes::PLAYER_WON, "玩家 {user} 获胜", "Player {user} won"),
+    (codes::CARD_DRAWN, "玩家 {user} 抽了一张牌", "Player {user} drew a card"),
+    (codes::YOU_DREW, "你抽到了 {card}", "You drew {card}"),
+    (codes::YOU_DREW_EXPLODING_KITTEN, "你抽到了爆炸猫！", "You drew an Exploding Kitten!"),
+    (codes::KITTEN_DEFUSED, "玩家 {user} 使用拆除卡解除了爆炸猫", "Player {user} defused an Exploding Kitten"),
+    (codes::PLAYER_EXPLODED, "玩家 {user} 被爆炸猫炸死了", "Player {user} exploded"),
+    (codes::CARD_PLAYED, "玩家 {user} 打出了 {card}", "Player {user} played {card}"),
+    (codes::TURN_CHANGED, "轮到玩家 {user} 的回合", "It is now {user}'s turn"),
+    (codes::PLAYER_TIMED_OUT, "玩家 {user} 因超时而出局", "Player {user} was eliminated for inactivity"),
+    (codes::MATCH_STATE, "游戏状态", "Match state"),
+    (codes::CHAIN_STARTED, "开始卡牌连锁效果，可以使用烦人卡取消", "Card chain started, a Nope can cancel it"),
+    (codes::CHAIN_RESOLVED, "卡牌连锁效果结束，动作有效", "Card chain resolved, the action takes effect"),
+    (codes::CHAIN_CANCELED, "卡牌连锁效果结束，动作被取消", "Card chain resolved, the action was canceled"),
+    (codes::NOPE_CANCELED, "玩家 {user} 使用烦人卡取消了上一个操作", "Player {user} played a Nope on the last action"),
+    (codes::SAW_FUTURE, "你看到了未来的牌", "You saw the future"),
+    (codes::CARD_STOLEN, "玩家 {user} 从玩家 {target} 那里获得了一张牌", "Player {user} took a card from {target}"),
+    (codes::YOU_STOLE, "你从玩家 {target} 那里获得了 {card}", "You took {card} from {target}"),
+    (codes::ALTER_FUTURE_PROMPT, "你可以重新排列未来的牌", "You may rearrange the future"),
+    (codes::FUTURE_ALTERED, "已重新排列未来的牌", "The future has been rearranged"),
+    (codes::FUTURE_SHARED_WITH_YOU, "玩家 {user} 与你分享了未来的牌", "Player {user} shared the future with you"),
+    (codes::YOU_SHARED_FUTURE, "你与玩家 {target} 分享了未来的牌", "You shared the future with {target}"),
+    (codes::SELECT_TARGET_PROMPT, "请选择目标玩家", "Choose a target player"),
+    (codes::YOU_BURIED_CARD, "你将一张牌埋入了牌堆中间", "You buried a card in the deck"),
+    (codes::CARD_BURIED, "玩家 {user} 将一张牌埋入了牌堆中间", "Player {user} buried a card in the deck"),
+    (codes::EXPLOSION_SPED_UP, "玩家 {user} 加速了爆炸猫的爆炸", "Player {user} sped up the explosion"),
+    (codes::IMPLODING_KITTEN_INSERTED, "玩家 {user} 插入了一只内爆猫", "Player {user} inserted an Imploding Kitten"),
+    (codes::CAT_CARD_PLAYED, "玩家 {user} 使用了猫咪卡", "Player {user} played a cat card"),
+    (codes::NOPE_PLAYED, "玩家 {user} 使用了烦人卡", "Player {user} played a Nope"),
+    (codes::CARD_USED, "玩家 {user} 使用了 {card} 卡牌", "Player {user} used {card}"),
+    (codes::PAUSE_REQUESTED, "玩家 {user} 请求暂停游戏", "Player {user} requested a pause"),
+    (codes::PAUSE_VOTED, "玩家 {user} 同意暂停", "Player {user} agreed to pause"),
+    (codes::PAUSE_REJECTED, "玩家 {user} 拒绝了暂停请求", "Player {user} rejected the pause request"),
+    (codes::PAUSE_EXPIRED, "暂停请求已过期", "The pause request expired"),
+    (codes::MATCH_PAUSED, "游戏已暂停", "The match is paused"),
+    (codes::MATCH_RESUMED, "游戏已恢复", "The match has resumed"),
+    (codes::ACTION_QUEUED, "游戏暂停中，玩家 {user} 的操作将在恢复后执行", "The match is paused, {user}'s action will run when it resumes"),
+    (codes::SKIP_VOTE_PROGRESS, "玩家 {user} 投票跳过玩家 {target}（{votes}/{required}）", "Player {user} voted to skip {target} ({votes}/{required})"),
+    (codes::PLAYER_SKIPPED, "玩家 {user} 长时间未操作，被投票淘汰", "Player {user} was voted out for inactivity"),
+    (codes::DISCARD_PROMPT, "手牌超过上限，请弃掉 {count} 张牌", "Your hand is over the limit, discard {count} cards"),
+    (codes::CARDS_DISCARDED, "玩家 {user} 弃掉了 {count} 张牌", "Player {user} discarded {count} cards"),
+    (codes::DECK_RESHUFFLED, "牌堆已抽完，{count} 张弃牌洗回了牌堆", "The deck ran out, {count} discarded cards were shuffled back in"),
+    (codes::DECK_EXHAUSTED, "牌堆已抽完，按剩余手牌计分结束游戏", "The deck ran out, the match ends on remaining hands"),
+    (codes::SPECTATOR_JOINED, "{user} 加入观战", "{user} is now spectating"),
+    (codes::SPECTATOR_LEFT, "{user} 离开观战", "{user} stopped spectating"),
+    (codes::SPECTATE_DISABLED, "该游戏禁止观战", "Spectating is disabled for this match"),
+    (codes::SPECTATE_FRIENDS_ONLY, "该游戏只允许玩家的好友观战", "Only friends of the players may spectate this match"),
+    (codes::SPECTATE_FULL, "观战人数已满", "The spectator limit has been reached"),
+    (codes::SPECTATE_ALREADY_PLAYING, "玩家已在游戏中，不能观战", "Players in the match cannot spectate it"),
+    (codes::SPECTATE_ALREADY_SPECTATING, "用户已经在观战", "You are already spectating"),
+    (codes::ROOM_JOINED, "已加入房间: {room}", "Joined room: {room}"),
+    (codes::ROOM_LEFT, "已离开房间: {room}", "Left room: {room}"),
+    (codes::ROOM_NOT_MEMBER, "你不是房间 {room} 的成员", "You are not a member of room {room}"),
+    (codes::ROOM_SERVER_MANAGED, "房间 {room} 不能直接加入", "Room {room} cannot be joined directly"),
+    (codes::RECONNECTED, "重连成功", "Reconnected"),
+    (codes::RECONNECTED_NO_ROOMS, "重连成功，但没有找到以前的房间", "Reconnected, but no previous rooms were found"),
+    (codes::RESUME_TOKEN_INVALID, "恢复令牌无效或已使用", "The resume token is invalid or has already been used"),
+    (codes::RESUME_TOKEN_EXPIRED, "恢复令牌已过期，无法恢复以前的房间", "The resume token has expired, previous rooms cannot be restored"),
+    (codes::SESSION_ATTESTED, "会话已绑定飞地证明，后续消息将被签名", "Session attested, subsequent messages will be signed"),
+    (codes::SESSION_ATTEST_FAILED, "会话证明失败: {reason}", "Session attestation failed: {reason}"),
+    (codes::ACHIEVEMENT_UNLOCKED, "解锁成就: {name}", "Achievement unlocked: {name}"),
+    (codes::USER_NOT_FOUND, "用户不存在", "User not found"),
+    (codes::ALREADY_FRIENDS, "你们已经是好友了", "You are already friends"),
+    (codes::BLOCKED_BY_USER, "你已被该用户阻止", "You have been blocked by this user"),
+    (codes::FRIEND_REQUEST_ALREADY_SENT, "好友请求已经发送过了", "Friend request already sent"),
+    (codes::NO_FRIEND_REQUEST_TO_REVOKE, "没有可撤销的好友请求", "There is no friend request to revoke"),
+    (codes::NO_FRIEND_REQUEST_TO_ACCEPT, "没有待接受的好友请求", "There is no friend request to accept"),
+    (codes::NO_FRIEND_REQUEST_TO_REJECT, "没有待拒绝的好友请求", "There is no friend request to reject"),
+    (codes::NO_RELATIONSHIP, "没有与该用户的关系", "You have no relationship with this user"),
+    (codes::NOT_FRIENDS, "你们不是好友关系", "You are not friends"),
+    (codes::MATCH_SERVICE_UNAVAILABLE, "游戏服务未初始化", "The match service is unavailable"),
+    (codes::SET_INTERIM_FAILED, "设置临时状态失败: {error}", "Failed to set status: {error}"),
+    (codes::CHAT_JOINED, "已成功加入聊天室", "Joined the chat room"),
+    (codes::CHAT_SENT, "消息已发送", "Message sent"),
+    (codes::CHAT_NOT_JOINED, "请先加入聊天室", "Join the chat room first"),
+    (codes::DM_RECIPIENT_BLOCKED, "你已阻止该用户，解除阻止后才能发送私信", "You have blocked this user, unblock them to send direct messages"),
+    (codes::PAYLOAD_TOO_LARGE, "消息过大: {size} 字节，上限 {limit} 字节", "Message too large: {size} bytes, limit is {limit} bytes"),
+    (codes::PAYLOAD_TOO_DEEP, "消息嵌套层数超过上限 {limit}", "Message nesting exceeds the limit of {limit}"),
+    (codes::PAYLOAD_FIELD_TOO_LONG, "字段 {field} 超过长度上限 {limit}", "Field {field} exceeds the length limit of {limit}"),
+    (codes::PARTY_UPDATED, "队伍已更新", "Party updated"),
+    (codes::PARTY_INVITED, "{user} 邀请你加入队伍", "{user} invited you to their party"),
+    (codes::PARTY_FAILED, "组队失败: {reason}", "Party action failed: {reason}"),
+    (codes::CONNECTION_RESYNC, "网络较慢，部分消息未能送达，请重新同步", "Connection is too slow and some messages were dropped, please resync"),
+    (codes::QUEUE_BANNED, "玩家 {user} 已被封禁，不能匹配: {reason}", "Player {user} is banned from matchmaking: {reason}"),
+];
+
+/// 带代码和参数的消息
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalizedText {
+    pub code: &'static str,
+    pub params: Map<String, Value>,
+}
+
+impl LocalizedText {
+    /// 按指定语言渲染
+    pub fn render(&self, locale: Locale) -> String {
+        render(self.code, &self.params, locale).unwrap_or_else(|| self.code.to_string())
+    }
+}
+
+/// 创建带参数的消息
+pub fn text(code: &'static str, params: &[(&str, String)]) -> LocalizedText {
+    LocalizedText {
+        code,
+        params: params
+            .iter()
+            .map(|(key, value)| (key.to_string(), Value::String(value.clone())))
+            .collect(),
+    }
+}
+
+/**
+ * 按语言渲染消息代码
+ *
+ * 参数:
+ * @param code - 消息代码
+ * @param params - 消息参数，替换模板中的 `{name}` 占位符
+ * @param locale - 目标语言
+ *
+ * 返回:
+ * 渲染后的文本，未知代码返回None
+ */
+pub fn render(code: &str, params: &Map<String, Value>, locale: Locale) -> Option<String> {
+    let (_, zh, en) = CATALOG.iter().find(|(c, _, _)| *c == code)?;
+    let mut rendered = match locale {
+        Locale::Zh => zh.to_string(),
+        Locale::En => en.to_string(),
+    };
+    for (key, value) in params {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        rendered = rendered.replace(&format!("{{{}}}", key), &value);
+    }
+    Some(rendered)
+}
+
+/// 按客户端语言重新渲染消息数据中的 `msg` 字段，数据中没有 `code` 时不做修改
+pub fn localize_value(data: &mut Value, locale: Locale) {
+    let Some(object) = data.as_object_mut() else {
+        return;
+    };
+    let Some(code) = object.get("code").and_then(|c| c.as_str()) else {
+        return;
+    };
+    let empty = Map::new();
+    let params = object.get("params").and_then(|p| p.as_object()).unwrap_or(&empty);
+    if let Some(msg) = render(code, params, locale) {
+        object.insert("msg".to_string(), Value::String(msg));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_language() {
+        assert_eq!(Locale::from_accept_language("en-US,en;q=0.9,zh-CN;q=0.8"), Some(Locale::En));
+        assert_eq!(Locale::from_accept_language("fr-FR, zh;q=0.5, en;q=0.4"), Some(Locale::Zh));
+        assert_eq!(Locale::from_accept_language("fr-FR"), None);
+        assert_eq!(Locale::from_tag("zh_TW"), Some(Locale::Zh));
+    }
+
+    #[test]
+    fn test_render_and_localize() {
+        let message = text(codes::CARD_STOLEN, &[("user", "u1".to_string()), ("target", "u2".to_string())]);
+        assert_eq!(message.render(Locale::Zh), "玩家 u1 从玩家 u2 那里获得了一张牌");
+        assert_eq!(message.render(Locale::En), "Player u1 took a card from u2");
+
+        let mut data = serde_json::json!({
+            "ok": true,
+            "msg": "游戏开始",
+            "code": codes::MATCH_STARTED
+        });
+        localize_value(&mut data, Locale::En);
+        assert_eq!(data["msg"], "The match has started");
+
+        let mut plain = serde_json::json!({ "ok": false, "msg": "错误" });
+        localize_value(&mut plain, Locale::En);
+        assert_eq!(plain["msg"], "错误");
+    }
+
+    #[test]
+    fn test_catalog_codes_are_unique() {
+        let mut seen = std::collections::HashSet::new();
+        for (code, _, _) in CATALOG {
+            assert!(seen.insert(*code), "重复的消息代码: {}", code);
+        }
+    }
+}
@@ -0,0 +1,521 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 密钥访问审计与限流模块
+//!
+//! # 概述
+//! 为第三方运行密钥服务器时需要记录每一次密钥请求，本模块提供：
+//! - 按请求者地址的令牌桶限流，超出限制的请求返回429
+//! - 结构化审计日志，记录请求者地址、密钥ID、PTB摘要、处理结果和耗时，
+//!   保存在内存中供管理接口查询，并可追加写入JSON Lines文件；
+//!   配置了持久化存储（见 `storage` 模块）时同时写入存储，启动时恢复最近的记录
+//! - 管理查询接口 `GET /admin/key_audit`，需要在 `X-Admin-Key` 请求头中提供管理密钥
+//!
+//! # 配置
+//! - `FETCH_KEY_RATE_LIMIT`：每个地址每分钟允许的请求数，默认60，0表示不限流
+//! - `FETCH_KEY_BURST`：允许的突发请求数，默认与每分钟请求数相同
+//! - `KEY_AUDIT_LOG_PATH`：审计日志文件路径，为空时只保存在内存中
+//! - `KEY_AUDIT_CAPACITY`：内存中保留的审计记录数量，默认10000
+//! - `ADMIN_API_KEY`：管理接口密钥，为空时管理接口不可用
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::hash::Hash;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::Query;
+use axum::http::HeaderMap;
+use axum::routing::get;
+use axum::{Json, Router};
+use dashmap::DashMap;
+use fastcrypto::encoding::{Base64, Encoding, Hex};
+use fastcrypto::hash::{Blake2b256, HashFunction};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sui_sdk::types::transaction::ProgrammableTransaction;
+use sui_types::base_types::SuiAddress;
+use tracing::{error, info};
+
+use crate::common::constant_time_eq;
+use crate::errors::InternalError;
+use crate::externals::current_epoch_time;
+use crate::valid_ptb::ValidPtb;
+use crate::AppState;
+
+/// 默认每分钟请求数
+const DEFAULT_RATE_PER_MINUTE: u32 = 60;
+/// 默认内存中保留的审计记录数量
+const DEFAULT_AUDIT_CAPACITY: usize = 10_000;
+/// 查询返回的最大记录数
+const MAX_QUERY_LIMIT: usize = 1000;
+/// 空闲桶的清理阈值（毫秒）
+const IDLE_BUCKET_MS: u64 = 10 * 60 * 1000;
+
+/// 令牌桶
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated_at: u64,
+}
+
+/// 令牌桶限流器，默认按地址限流，也可以按IP等其他键限流
+pub struct RateLimiter<K = SuiAddress> {
+    /// 每毫秒补充的令牌数
+    refill_per_ms: f64,
+    /// 桶容量
+    burst: f64,
+    buckets: DashMap<K, Bucket>,
+}
+
+impl RateLimiter {
+    /// 根据环境变量创建限流器
+    pub fn from_env() -> Self {
+        let per_minute = std::env::var("FETCH_KEY_RATE_LIMIT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_RATE_PER_MINUTE);
+        let burst = std::env::var("FETCH_KEY_BURST")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(per_minute);
+        Self::new(per_minute, burst)
+    }
+}
+
+impl<K: Eq + Hash + Clone> RateLimiter<K> {
+    /**
+     * 创建限流器
+     *
+     * 参数:
+     * @param per_minute - 每分钟允许的请求数，0表示不限流
+     * @param burst - 允许的突发请求数
+     */
+    pub fn new(per_minute: u32, burst: u32) -> Self {
+        Self {
+            refill_per_ms: per_minute as f64 / 60_000.0,
+            burst: burst.max(1) as f64,
+            buckets: DashMap::new(),
+        }
+    }
+
+    /**
+     * 创建按时间窗口计数的限流器，窗口内最多允许 `limit` 次请求
+     *
+     * 参数:
+     * @param limit - 窗口内允许的请求数，0表示不限流
+     * @param window - 时间窗口
+     */
+    pub fn per_window(limit: u32, window: Duration) -> Self {
+        Self {
+            refill_per_ms: limit as f64 / window.as_millis().max(1) as f64,
+            burst: limit.max(1) as f64,
+            buckets: DashMap::new(),
+        }
+    }
+
+    /// 是否启用限流
+    pub fn is_enabled(&self) -> bool {
+        self.refill_per_ms > 0.0
+    }
+
+    /// 尝试消耗一个令牌，返回是否允许请求
+    pub fn try_acquire(&self, key: &K, now: u64) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+        let mut bucket = self.buckets.entry(key.clone()).or_insert(Bucket {
+            tokens: self.burst,
+            updated_at: now,
+        });
+        let elapsed = now.saturating_sub(bucket.updated_at) as f64;
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_ms).min(self.burst);
+        bucket.updated_at = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 清理长时间未使用的桶，桶在补满之前不会被清理
+    pub fn prune(&self, now: u64) {
+        let refill_ms = if self.is_enabled() { (self.burst / self.refill_per_ms) as u64 } else { 0 };
+        let idle_ms = IDLE_BUCKET_MS.max(refill_ms);
+        self.buckets
+            .retain(|_, bucket| now.saturating_sub(bucket.updated_at) < idle_ms);
+    }
+}
+
+/// 审计结果
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditDecision {
+    /// 已发放密钥
+    Allowed,
+    /// 请求被拒绝
+    Denied,
+    /// 请求被限流
+    RateLimited,
+}
+
+/// 单条审计记录
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditRecord {
+    pub timestamp: u64,
+    pub req_id: Option<String>,
+    /// 请求者地址
+    pub requester: String,
+    /// 请求的密钥ID（Hex编码）
+    pub key_ids: Vec<String>,
+    /// PTB摘要（Blake2b256，Hex编码）
+    pub ptb_digest: String,
+    pub decision: AuditDecision,
+    /// 拒绝原因
+    pub error: Option<String>,
+    pub latency_ms: u64,
+}
+
+/// 审计日志查询条件
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditQuery {
+    pub requester: Option<String>,
+    pub decision: Option<AuditDecision>,
+    /// 起始时间（毫秒，包含）
+    pub since: Option<u64>,
+    /// 结束时间（毫秒，不包含）
+    pub until: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, record: &AuditRecord) -> bool {
+        self.requester
+            .as_ref()
+            .map(|r| r.eq_ignore_ascii_case(&record.requester))
+            .unwrap_or(true)
+            && self.decision.map(|d| d == record.decision).unwrap_or(true)
+            && self.since.map(|t| record.timestamp >= t).unwrap_or(true)
+            && self.until.map(|t| record.timestamp < t).unwrap_or(true)
+    }
+}
+
+/// 审计日志
+pub struct AuditLog {
+    capacity: usize,
+    records: Mutex<VecDeque<AuditRecord>>,
+    file: Option<Mutex<File>>,
+}
+
+impl AuditLog {
+    /// 创建只保存在内存中的审计日志
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            records: Mutex::new(VecDeque::new()),
+            file: None,
+        }
+    }
+
+    /// 根据环境变量创建审计日志
+    pub fn from_env() -> anyhow::Result<Self> {
+        let capacity = std::env::var("KEY_AUDIT_CAPACITY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_AUDIT_CAPACITY);
+        let mut log = Self::new(capacity);
+        if let Some(path) = std::env::var("KEY_AUDIT_LOG_PATH").ok().filter(|v| !v.is_empty()) {
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            info!("密钥审计日志写入: {}", path);
+            log.file = Some(Mutex::new(file));
+        }
+        Ok(log)
+    }
+
+    /// 记录一条审计记录
+    pub fn record(&self, record: AuditRecord) {
+        info!(
+            target: "key_audit",
+            requester = %record.requester,
+            decision = ?record.decision,
+            ptb_digest = %record.ptb_digest,
+            latency_ms = record.latency_ms,
+            req_id = ?record.req_id,
+            "密钥请求审计"
+        );
+        if let Some(file) = &self.file {
+            let written = serde_json::to_string(&record)
+                .map_err(std::io::Error::from)
+                .and_then(|line| writeln!(file.lock(), "{}", line));
+            if let Err(e) = written {
+                error!("写入密钥审计日志失败: {}", e);
+            }
+        }
+
+        // 持久化存储的写入放到后台，不阻塞密钥请求
+        if let (Some(storage), Ok(handle)) = (
+            crate::storage::global_storage(),
+            tokio::runtime::Handle::try_current(),
+        ) {
+            let persisted = record.clone();
+            handle.spawn(async move {
+                if let Err(e) = storage.append_audit(&persisted).await {
+                    error!("保存密钥审计记录失败: {}", e);
+                }
+            });
+        }
+
+        self.push(record);
+    }
+
+    fn push(&self, record: AuditRecord) {
+        let mut records = self.records.lock();
+        if records.len() >= self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    /// 从持久化存储恢复最近的审计记录，返回恢复的条数
+    pub async fn restore(&self) -> usize {
+        let Some(storage) = crate::storage::global_storage() else {
+            return 0;
+        };
+        match storage.recent_audit(self.capacity).await {
+            Ok(records) => {
+                let count = records.len();
+                for record in records {
+                    self.push(record);
+                }
+                count
+            }
+            Err(e) => {
+                error!("恢复密钥审计记录失败: {}", e);
+                0
+            }
+        }
+    }
+
+    /// 按条件查询审计记录，最新的记录在前
+    pub fn query(&self, query: &AuditQuery) -> Vec<AuditRecord> {
+        let limit = query.limit.unwrap_or(100).min(MAX_QUERY_LIMIT);
+        self.records
+            .lock()
+            .iter()
+            .rev()
+            .filter(|record| query.matches(record))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+/// 密钥访问审计服务
+pub struct KeyAuditService {
+    pub rate_limiter: RateLimiter,
+    pub audit_log: AuditLog,
+}
+
+/// 单次请求的审计上下文
+pub struct AuditContext {
+    started_at: Instant,
+    req_id: Option<String>,
+    requester: SuiAddress,
+    key_ids: Vec<String>,
+    ptb_digest: String,
+}
+
+impl AuditContext {
+    /**
+     * 为密钥请求创建审计上下文
+     *
+     * PTB无法解析时摘要和密钥ID尽量填写，不影响后续的请求校验
+     *
+     * 参数:
+     * @param requester - 请求者地址
+     * @param ptb_str - Base64编码的PTB
+     * @param req_id - 请求ID
+     */
+    pub fn new(requester: SuiAddress, ptb_str: &str, req_id: Option<&str>) -> Self {
+        let ptb_bytes = Base64::decode(ptb_str).unwrap_or_else(|_| ptb_str.as_bytes().to_vec());
+        let key_ids = bcs::from_bytes::<ProgrammableTransaction>(&ptb_bytes)
+            .ok()
+            .and_then(|ptb| ValidPtb::try_from(ptb).ok())
+            .map(|vptb| vptb.inner_ids().iter().map(Hex::encode).collect())
+            .unwrap_or_default();
+        Self {
+            started_at: Instant::now(),
+            req_id: req_id.map(|s| s.to_string()),
+            requester,
+            key_ids,
+            ptb_digest: Hex::encode(Blake2b256::digest(&ptb_bytes).digest),
+        }
+    }
+
+    /// 生成审计记录
+    pub fn finish(self, decision: AuditDecision, error: Option<&InternalError>) -> AuditRecord {
+        AuditRecord {
+            timestamp: current_epoch_time(),
+            req_id: self.req_id,
+            requester: self.requester.to_string(),
+            key_ids: self.key_ids,
+            ptb_digest: self.ptb_digest,
+            decision,
+            error: error.map(|e| e.as_str().to_string()),
+            latency_ms: self.started_at.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+impl KeyAuditService {
+    pub fn new(rate_limiter: RateLimiter, audit_log: AuditLog) -> Self {
+        Self {
+            rate_limiter,
+            audit_log,
+        }
+    }
+
+    /// 检查请求者是否超出限流，超出时记录审计并返回错误
+    pub fn check_rate_limit(&self, context: AuditContext) -> Result<AuditContext, InternalError> {
+        let now = current_epoch_time();
+        if self.rate_limiter.try_acquire(&context.requester, now) {
+            return Ok(context);
+        }
+        let error = InternalError::RateLimited;
+        self.audit_log
+            .record(context.finish(AuditDecision::RateLimited, Some(&error)));
+        Err(error)
+    }
+
+    /// 记录请求的处理结果
+    pub fn record_result<T>(&self, context: AuditContext, result: &Result<T, InternalError>) {
+        let record = match result {
+            Ok(_) => context.finish(AuditDecision::Allowed, None),
+            Err(e) => context.finish(AuditDecision::Denied, Some(e)),
+        };
+        self.audit_log.record(record);
+    }
+
+    /// 启动定期清理空闲限流桶的后台任务
+    pub fn spawn_pruner(self: &Arc<Self>) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(std::time::Duration::from_millis(IDLE_BUCKET_MS));
+            loop {
+                ticker.tick().await;
+                service.rate_limiter.prune(current_epoch_time());
+            }
+        });
+    }
+}
+
+static GLOBAL_KEY_AUDIT_SERVICE: OnceCell<Arc<KeyAuditService>> = OnceCell::new();
+
+/// 初始化全局密钥审计服务
+pub fn init_key_audit_service() -> anyhow::Result<Arc<KeyAuditService>> {
+    let service = GLOBAL_KEY_AUDIT_SERVICE
+        .get_or_try_init(|| -> anyhow::Result<_> {
+            let service = Arc::new(KeyAuditService::new(RateLimiter::from_env(), AuditLog::from_env()?));
+            service.spawn_pruner();
+            Ok(service)
+        })?
+        .clone();
+    Ok(service)
+}
+
+/// 获取全局密钥审计服务
+pub fn global_key_audit_service() -> Option<Arc<KeyAuditService>> {
+    GLOBAL_KEY_AUDIT_SERVICE.get().cloned()
+}
+
+/// 校验管理接口密钥
+pub(crate) fn check_admin_key(headers: &HeaderMap) -> Result<(), InternalError> {
+    let expected = std::env::var("ADMIN_API_KEY")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .ok_or(InternalError::Unauthorized)?;
+    let provided = headers
+        .get("X-Admin-Key")
+        .and_then(|v| v.to_str().ok())
+        .ok_or(InternalError::MissingAuthToken)?;
+    if !constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+        return Err(InternalError::Unauthorized);
+    }
+    Ok(())
+}
+
+/// 查询密钥审计日志
+pub async fn handle_query_key_audit(
+    headers: HeaderMap,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditRecord>>, InternalError> {
+    check_admin_key(&headers)?;
+    let service = global_key_audit_service().ok_or(InternalError::Failure)?;
+    Ok(Json(service.audit_log.query(&query)))
+}
+
+/// 注册密钥审计路由
+pub fn register_key_audit_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router.route("/admin/key_audit", get(handle_query_key_audit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_refill() {
+        let limiter = RateLimiter::new(60, 2);
+        let address = SuiAddress::random_for_testing_only();
+        assert!(limiter.try_acquire(&address, 0));
+        assert!(limiter.try_acquire(&address, 0));
+        assert!(!limiter.try_acquire(&address, 10));
+        // 每秒补充一个令牌
+        assert!(limiter.try_acquire(&address, 1_010));
+        assert!(!limiter.try_acquire(&address, 1_020));
+
+        // 其他地址不受影响
+        assert!(limiter.try_acquire(&SuiAddress::random_for_testing_only(), 1_020));
+
+        limiter.prune(IDLE_BUCKET_MS + 2_000);
+        assert!(limiter.buckets.is_empty());
+        assert!(RateLimiter::new(0, 0).try_acquire(&address, 0));
+    }
+
+    #[test]
+    fn test_audit_query() {
+        let log = AuditLog::new(2);
+        let address = SuiAddress::random_for_testing_only();
+        for (i, decision) in [AuditDecision::Allowed, AuditDecision::Denied, AuditDecision::Allowed]
+            .into_iter()
+            .enumerate()
+        {
+            let mut record = AuditContext::new(address, "not base64", None).finish(decision, None);
+            record.timestamp = i as u64;
+            log.record(record);
+        }
+
+        // 超出容量时丢弃最早的记录
+        let all = log.query(&AuditQuery::default());
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].timestamp, 2);
+
+        let denied = log.query(&AuditQuery {
+            decision: Some(AuditDecision::Denied),
+            requester: Some(address.to_string().to_uppercase()),
+            ..Default::default()
+        });
+        assert_eq!(denied.len(), 1);
+        assert!(log
+            .query(&AuditQuery {
+                since: Some(3),
+                ..Default::default()
+            })
+            .is_empty());
+    }
+}
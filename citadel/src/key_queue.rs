@@ -0,0 +1,312 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 密钥请求排队与过载保护模块
+//!
+//! # 概述
+//! `/v1/fetch_key` 的校验需要多次访问全节点（PTB检查、包ID查询、策略执行），负载高时无限制的并发校验
+//! 会互相拖慢并耗尽全节点连接。本模块把校验放入有界的工作队列：
+//! - 同时执行的校验数不超过并发上限，其余请求排队等待
+//! - 排队请求数达到上限时新请求直接被拒绝（429，带 `Retry-After`）
+//! - 每个请求有截止时间，排队超过截止时间的请求被拒绝（429），执行超过截止时间的校验被中止（503）
+//! - 排队深度、执行中的请求数、排队等待时间和被拒绝的请求数记录到Prometheus指标，
+//!   运维可以据此确定并发上限和实例数量
+//!
+//! # 配置
+//! - `KEY_QUEUE_MAX_CONCURRENT`：同时执行的校验数，默认64
+//! - `KEY_QUEUE_MAX_PENDING`：排队等待的最大请求数，默认256，0表示不排队
+//! - `KEY_QUEUE_DEADLINE_MS`：从进入队列到校验完成的截止时间（毫秒），默认5000
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use once_cell::sync::OnceCell;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::errors::InternalError;
+use crate::metrics::Metrics;
+
+/// 默认并发上限
+const DEFAULT_MAX_CONCURRENT: usize = 64;
+/// 默认排队上限
+const DEFAULT_MAX_PENDING: usize = 256;
+/// 默认截止时间（毫秒）
+const DEFAULT_DEADLINE_MS: u64 = 5000;
+
+/// 工作队列配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyQueueConfig {
+    pub max_concurrent: usize,
+    pub max_pending: usize,
+    pub deadline: Duration,
+}
+
+impl Default for KeyQueueConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: DEFAULT_MAX_CONCURRENT,
+            max_pending: DEFAULT_MAX_PENDING,
+            deadline: Duration::from_millis(DEFAULT_DEADLINE_MS),
+        }
+    }
+}
+
+impl KeyQueueConfig {
+    /// 从环境变量读取配置
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_concurrent: std::env::var("KEY_QUEUE_MAX_CONCURRENT")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(defaults.max_concurrent),
+            max_pending: std::env::var("KEY_QUEUE_MAX_PENDING")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(defaults.max_pending),
+            deadline: std::env::var("KEY_QUEUE_DEADLINE_MS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|v| *v > 0)
+                .map_or(defaults.deadline, Duration::from_millis),
+        }
+    }
+}
+
+/// 有界的密钥请求工作队列
+#[derive(Debug)]
+pub struct KeyRequestQueue {
+    config: KeyQueueConfig,
+    permits: Arc<Semaphore>,
+    /// 排队等待的请求数
+    pending: AtomicUsize,
+}
+
+/// 排队中的请求，离开队列（包括请求被取消）时减少排队数
+struct PendingGuard<'a> {
+    queue: &'a KeyRequestQueue,
+    metrics: Option<&'a Metrics>,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.queue.pending.fetch_sub(1, Ordering::SeqCst);
+        self.queue.report_depth(self.metrics);
+    }
+}
+
+impl KeyRequestQueue {
+    pub fn new(config: KeyQueueConfig) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(config.max_concurrent)),
+            pending: AtomicUsize::new(0),
+            config,
+        }
+    }
+
+    /// 排队等待的请求数
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    /// 正在执行的请求数
+    pub fn in_flight(&self) -> usize {
+        self.config.max_concurrent - self.permits.available_permits()
+    }
+
+    /**
+     * 在队列中执行一个请求的校验
+     *
+     * 参数:
+     * @param metrics - 记录排队指标，为None时不记录
+     * @param work - 校验过程
+     *
+     * 返回:
+     * 校验结果；队列已满或排队超过截止时间时返回Overloaded，执行超过截止时间时返回Failure
+     */
+    pub async fn run<F, T>(&self, metrics: Option<&Metrics>, work: F) -> Result<T, InternalError>
+    where
+        F: Future<Output = Result<T, InternalError>>,
+    {
+        let deadline = Instant::now() + self.config.deadline;
+        let permit = self.acquire(metrics, deadline).await?;
+        self.report_depth(metrics);
+        let result = tokio::time::timeout_at(deadline, work).await;
+        drop(permit);
+        self.report_depth(metrics);
+        result.unwrap_or_else(|_| {
+            warn!("密钥请求校验超过截止时间 {:?}", self.config.deadline);
+            if let Some(metrics) = metrics {
+                metrics.observe_key_queue_shed("timeout");
+            }
+            Err(InternalError::Failure)
+        })
+    }
+
+    /// 获取执行许可，有空闲并发时不排队
+    async fn acquire(&self, metrics: Option<&Metrics>, deadline: Instant) -> Result<OwnedSemaphorePermit, InternalError> {
+        if let Ok(permit) = self.permits.clone().try_acquire_owned() {
+            if let Some(metrics) = metrics {
+                metrics.observe_key_queue_wait(Duration::ZERO);
+            }
+            return Ok(permit);
+        }
+
+        if self.pending.fetch_add(1, Ordering::SeqCst) >= self.config.max_pending {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            if let Some(metrics) = metrics {
+                metrics.observe_key_queue_shed("queue_full");
+            }
+            return Err(InternalError::Overloaded);
+        }
+        let guard = PendingGuard { queue: self, metrics };
+        self.report_depth(metrics);
+
+        let started = Instant::now();
+        let result = tokio::time::timeout_at(deadline, self.permits.clone().acquire_owned()).await;
+        drop(guard);
+        if let Some(metrics) = metrics {
+            metrics.observe_key_queue_wait(started.elapsed());
+        }
+        match result {
+            Ok(Ok(permit)) => Ok(permit),
+            Ok(Err(_)) => Err(InternalError::Failure),
+            Err(_) => {
+                if let Some(metrics) = metrics {
+                    metrics.observe_key_queue_shed("deadline");
+                }
+                Err(InternalError::Overloaded)
+            }
+        }
+    }
+
+    fn report_depth(&self, metrics: Option<&Metrics>) {
+        if let Some(metrics) = metrics {
+            metrics.observe_key_queue_depth(self.pending(), self.in_flight());
+        }
+    }
+}
+
+static GLOBAL_KEY_QUEUE: OnceCell<Arc<KeyRequestQueue>> = OnceCell::new();
+
+/// 初始化全局密钥请求队列
+pub fn init_key_queue() -> Arc<KeyRequestQueue> {
+    GLOBAL_KEY_QUEUE
+        .get_or_init(|| {
+            let config = KeyQueueConfig::from_env();
+            info!(
+                "密钥请求队列: 并发上限 {}, 排队上限 {}, 截止时间 {:?}",
+                config.max_concurrent, config.max_pending, config.deadline
+            );
+            Arc::new(KeyRequestQueue::new(config))
+        })
+        .clone()
+}
+
+/// 获取全局密钥请求队列
+pub fn global_key_queue() -> Option<Arc<KeyRequestQueue>> {
+    GLOBAL_KEY_QUEUE.get().cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::oneshot;
+
+    fn queue(max_concurrent: usize, max_pending: usize, deadline_ms: u64) -> Arc<KeyRequestQueue> {
+        Arc::new(KeyRequestQueue::new(KeyQueueConfig {
+            max_concurrent,
+            max_pending,
+            deadline: Duration::from_millis(deadline_ms),
+        }))
+    }
+
+    /// 占用一个并发许可，直到发送端被丢弃
+    fn occupy(queue: &Arc<KeyRequestQueue>) -> oneshot::Sender<()> {
+        let (tx, rx) = oneshot::channel::<()>();
+        let queue = queue.clone();
+        tokio::spawn(async move {
+            let _ = queue
+                .run(None, async move {
+                    let _ = rx.await;
+                    Ok::<_, InternalError>(())
+                })
+                .await;
+        });
+        tx
+    }
+
+    #[tokio::test]
+    async fn test_run_within_capacity() {
+        let queue = queue(2, 0, 1000);
+        assert_eq!(queue.run(None, async { Ok::<_, InternalError>(7) }).await, Ok(7));
+        assert_eq!(
+            queue.run(None, async { Err::<(), _>(InternalError::NoAccess) }).await,
+            Err(InternalError::NoAccess)
+        );
+        assert_eq!(queue.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_shed_when_queue_full() {
+        let queue = queue(1, 0, 1000);
+        let busy = occupy(&queue);
+        tokio::task::yield_now().await;
+        assert_eq!(queue.in_flight(), 1);
+
+        let result = queue.run(None, async { Ok::<_, InternalError>(()) }).await;
+        assert_eq!(result, Err(InternalError::Overloaded));
+        assert_eq!(queue.pending(), 0);
+
+        drop(busy);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(queue.run(None, async { Ok::<_, InternalError>(()) }).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_deadline_in_queue_and_in_work() {
+        let queue = queue(1, 4, 50);
+        let busy = occupy(&queue);
+        tokio::task::yield_now().await;
+
+        // 排队超过截止时间
+        let result = queue.run(None, async { Ok::<_, InternalError>(()) }).await;
+        assert_eq!(result, Err(InternalError::Overloaded));
+        assert_eq!(queue.pending(), 0);
+        drop(busy);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        // 执行超过截止时间
+        let result = queue
+            .run(None, async {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                Ok::<_, InternalError>(())
+            })
+            .await;
+        assert_eq!(result, Err(InternalError::Failure));
+        assert_eq!(queue.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_queued_request_runs_when_permit_released() {
+        let queue = queue(1, 4, 1000);
+        let busy = occupy(&queue);
+        tokio::task::yield_now().await;
+
+        let waiting = {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.run(None, async { Ok::<_, InternalError>(1) }).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(queue.pending(), 1);
+
+        drop(busy);
+        assert_eq!(waiting.await.unwrap(), Ok(1));
+        assert_eq!(queue.pending(), 0);
+    }
+}
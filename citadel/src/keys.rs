@@ -17,12 +17,12 @@ use serde_json::json;
 
 use crypto::elgamal::encrypt;
 use crypto::ibe;
-use fastcrypto::ed25519::{Ed25519PublicKey, Ed25519Signature};
-use fastcrypto::encoding::{Base64, Encoding};
-use fastcrypto::traits::VerifyingKey;
+use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519PublicKey, Ed25519Signature};
+use fastcrypto::encoding::{Base64, Encoding, Hex};
+use fastcrypto::hash::{Blake2b256, HashFunction};
+use fastcrypto::traits::{KeyPair, ToFromBytes, VerifyingKey};
 use rand::thread_rng;
 use std::sync::Arc;
-use std::time::Duration;
 
 use sui_sdk::rpc_types::SuiTransactionBlockEffectsAPI;
 use sui_sdk::types::base_types::{ObjectID, SuiAddress};
@@ -31,12 +31,20 @@ use sui_sdk::types::transaction::{ProgrammableTransaction, TransactionKind};
 use sui_sdk::verify_personal_message_signature::verify_personal_message_signature;
 use tap::TapFallible;
 use tracing::{debug, info, warn};
+use utoipa::ToSchema;
 
-use crate::errors::InternalError;
+use crate::common::{to_signed_response, IntentMessage, IntentScope, ProcessedDataResponse};
+use crate::errors::{ErrorResponse, InternalError};
 use crate::externals::{current_epoch_time, fetch_first_and_last_pkg_id};
+use crate::key_audit::{self, AuditContext};
+use crate::key_queue;
 use crate::metrics::call_with_duration;
 use crate::metrics::Metrics;
-use crate::signed_message::{signed_message, signed_request};
+use crate::node_health::NodeStatus;
+use crate::ptb_policy;
+use crate::read_only;
+use crate::replay_guard::{self, Freshness, FreshnessPolicy};
+use crate::signed_message::{signed_message, signed_request, signed_request_with_freshness};
 use crate::types::{ElGamalPublicKey, ElgamalEncryption, ElgamalVerificationKey, MasterKeyPOP, GAS_BUDGET};
 use crate::valid_ptb::ValidPtb;
 use crate::AppState;
@@ -44,10 +52,6 @@ use crate::AppState;
 /// 会话密钥的最大生存时间（分钟）
 pub const SESSION_KEY_TTL_MAX: u16 = 10;
 
-/// 允许的全节点数据过时时间
-/// 设置此持续时间时，注意Sui上的时间戳可能比当前时间稍晚，但不应超过一秒。
-pub const ALLOWED_STALENESS: Duration = Duration::from_secs(120);
-
 /**
  * 会话证书，由用户签名
  * 用于验证用户身份和请求合法性
@@ -59,12 +63,15 @@ pub const ALLOWED_STALENESS: Duration = Duration::from_secs(120);
  * - 生存时间
  * - 用户签名
  */
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, ToSchema)]
 pub struct Certificate {
+    #[schema(value_type = String)]
     pub user: SuiAddress,             // 用户的Sui地址
+    #[schema(value_type = String)]
     pub session_vk: Ed25519PublicKey, // 会话验证密钥
     pub creation_time: u64,           // 创建时间（Unix时间戳）
     pub ttl_min: u16,                 // 生存时间（分钟）
+    #[schema(value_type = String)]
     pub signature: GenericSignature,  // 用户签名
 }
 
@@ -74,17 +81,62 @@ pub struct Certificate {
  * 客户端发送此请求以获取解密密钥
  * 包含签名的请求数据和验证信息
  */
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct FetchKeyRequest {
     // 以下字段必须签名，以防止他人代表用户发送请求并能够获取密钥
     ptb: String, // 必须遵循特定结构，参见ValidPtb
     // 我们不想仅依靠HTTPS来限制对此用户的响应，因为在多个服务的情况下，
     // 一个服务可以对另一个服务进行重放攻击以获取其他服务的密钥。
+    #[schema(value_type = String)]
     enc_key: ElGamalPublicKey,                    // ElGamal加密公钥
+    #[schema(value_type = String)]
     enc_verification_key: ElgamalVerificationKey, // ElGamal验证密钥
+    #[schema(value_type = String)]
     request_signature: Ed25519Signature,          // 请求签名
 
     certificate: Certificate, // 用户会话证书
+
+    // 新鲜度字段，提供时必须同时提供并包含在请求签名中，参见replay_guard
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<u64>, // 请求时间戳（毫秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>,  // 一次性随机串
+}
+
+impl FetchKeyRequest {
+    /**
+     * 构建获取密钥请求，供命令行等客户端使用
+     *
+     * 参数:
+     * @param ptb - Base64编码的PTB
+     * @param enc_key - ElGamal加密公钥
+     * @param enc_verification_key - ElGamal验证密钥
+     * @param request_signature - 会话密钥对请求数据的签名
+     * @param certificate - 用户会话证书
+     * @param freshness - 时间戳和nonce，提供时必须包含在请求签名中
+     *
+     * 返回:
+     * 获取密钥请求
+     */
+    pub fn new(
+        ptb: String,
+        enc_key: ElGamalPublicKey,
+        enc_verification_key: ElgamalVerificationKey,
+        request_signature: Ed25519Signature,
+        certificate: Certificate,
+        freshness: Option<(u64, String)>,
+    ) -> Self {
+        let (timestamp, nonce) = freshness.unzip();
+        Self {
+            ptb,
+            enc_key,
+            enc_verification_key,
+            request_signature,
+            certificate,
+            timestamp,
+            nonce,
+        }
+    }
 }
 
 /// 密钥ID类型（字节数组）
@@ -96,31 +148,83 @@ pub type KeyId = Vec<u8>;
  * 包含密钥ID和加密后的密钥
  * 返回给客户端用于解密其数据
  */
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct DecryptionKey {
+    #[schema(value_type = Vec<u8>)]
     id: KeyId,                            // 密钥标识符
+    #[schema(value_type = Object)]
     pub encrypted_key: ElgamalEncryption, // 加密的密钥
 }
 
+impl DecryptionKey {
+    /// 密钥对应的完整身份
+    pub fn id(&self) -> &[u8] {
+        &self.id
+    }
+}
+
+/**
+ * 密钥响应的请求绑定
+ *
+ * 由服务器的临时密钥对签名，客户端可以向第三方证明某份密钥由哪个服务器、
+ * 针对哪个请求、在什么新鲜度下返回，用于多服务器门限解密的审计
+ */
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct KeyResponseBinding {
+    #[schema(value_type = String)]
+    pub service_id: ObjectID,       // 密钥服务器对象ID
+    pub request_digest: Vec<u8>,    // 请求摘要，参见request_digest
+    pub keys_digest: Vec<u8>,       // 响应中密钥材料的摘要，参见keys_digest
+    pub checkpoint_timestamp: u64,  // 处理请求时全节点最新检查点的时间戳（毫秒）
+}
+
+/// 签名的请求绑定
+pub type SignedKeyResponseBinding = ProcessedDataResponse<IntentMessage<KeyResponseBinding>>;
+
 /**
  * 获取密钥响应结构
  *
- * 服务器返回的加密密钥列表
+ * 服务器返回的加密密钥列表，以及对密钥材料和请求的签名绑定
  */
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct FetchKeyResponse {
     pub decryption_keys: Vec<DecryptionKey>, // 解密密钥列表
+    // 使用 `GET /v1/service` 中的 `eph_pk` 验证
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub binding: Option<SignedKeyResponseBinding>,
 }
 
 /**
  * 获取服务信息响应
  *
- * 包含服务ID和主密钥持有证明
+ * 包含服务ID、主密钥持有证明、用于验证签名游戏事件的临时公钥、全节点健康状态和密钥请求的新鲜度要求
  */
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct GetServiceResponse {
+    #[schema(value_type = String)]
     service_id: ObjectID,
+    #[schema(value_type = String)]
     pop: MasterKeyPOP,
+    /// 启动时生成的临时Ed25519公钥（Hex编码），用于验证签名的对局结果和密钥响应的请求绑定
+    eph_pk: String,
+    /// 全节点健康状态，降级模式下密钥请求暂不可用
+    node_status: NodeStatus,
+    /// 密钥请求的新鲜度要求，客户端据此构造带时间戳和nonce的请求
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    freshness: Option<FreshnessPolicy>,
+    /// 是否为只读镜像，只读镜像可以派生密钥，但不接受对局和修改状态的请求
+    #[serde(default)]
+    read_only: bool,
+}
+
+/// 证书的创建时间和生存时间是否有效：TTL不超过上限，创建时间不在未来且尚未过期
+pub(crate) fn certificate_time_is_valid(cert: &Certificate) -> bool {
+    let now = current_epoch_time();
+    cert.ttl_min <= SESSION_KEY_TTL_MAX
+        && cert.creation_time <= now
+        && now >= 60_000 * (cert.ttl_min as u64) // 检查溢出
+        && now - 60_000 * (cert.ttl_min as u64) <= cert.creation_time
 }
 
 /**
@@ -135,6 +239,7 @@ pub struct GetServiceResponse {
  * @param enc_verification_key - ElGamal验证密钥
  * @param session_sig - 会话签名
  * @param cert - 用户证书
+ * @param freshness - 请求携带的时间戳和nonce，提供时按带新鲜度字段的格式验证会话签名
  * @param req_id - 请求ID（用于日志）
  *
  * 返回:
@@ -149,14 +254,11 @@ async fn check_signature(
     enc_verification_key: &ElgamalVerificationKey,
     session_sig: &Ed25519Signature,
     cert: &Certificate,
+    freshness: Option<Freshness<'_>>,
     req_id: Option<&str>,
 ) -> Result<(), InternalError> {
     // 检查证书有效性
-    if cert.ttl_min > SESSION_KEY_TTL_MAX
-        || cert.creation_time > current_epoch_time()
-        || current_epoch_time() < 60_000 * (cert.ttl_min as u64) // 检查溢出
-        || current_epoch_time() - 60_000 * (cert.ttl_min as u64) > cert.creation_time
-    {
+    if !certificate_time_is_valid(cert) {
         debug!(
             "Certificate has invalid expiration time (req_id: {:?})",
             req_id
@@ -174,7 +276,7 @@ async fn check_signature(
         cert.signature.clone(),
         msg.as_bytes(),
         cert.user,
-        Some(app_state.sui_client.clone()),
+        Some(app_state.sui_pool.client()),
     )
     .await
     .tap_err(|e| {
@@ -186,7 +288,10 @@ async fn check_signature(
     .map_err(|_| InternalError::InvalidSignature)?;
 
     // 验证会话签名（请求签名）
-    let signed_msg = signed_request(ptb, enc_key, enc_verification_key);
+    let signed_msg = match freshness {
+        Some(f) => signed_request_with_freshness(ptb, enc_key, enc_verification_key, f.timestamp, f.nonce),
+        None => signed_request(ptb, enc_key, enc_verification_key),
+    };
     cert.session_vk
         .verify(&signed_msg, session_sig)
         .map_err(|_| {
@@ -225,8 +330,8 @@ async fn check_policy(
         req_id
     );
     // 评估`seal_approve*`函数
-    let tx_data = app_state
-        .sui_client
+    let sui_client = app_state.sui_pool.client();
+    let tx_data = sui_client
         .transaction_builder()
         .tx_data_for_dry_run(
             sender,
@@ -237,8 +342,7 @@ async fn check_policy(
             None,
         )
         .await;
-    let dry_run_res = app_state
-        .sui_client
+    let dry_run_res = sui_client
         .read_api()
         .dry_run_transaction_block(tx_data)
         .await
@@ -289,6 +393,40 @@ pub async fn check_request(
     gas_price: u64,
     metrics: Option<&Metrics>,
     req_id: Option<&str>,
+) -> Result<Vec<KeyId>, InternalError> {
+    check_fresh_request(
+        app_state,
+        ptb_str,
+        enc_key,
+        enc_verification_key,
+        request_signature,
+        certificate,
+        None,
+        gas_price,
+        metrics,
+        req_id,
+    )
+    .await
+}
+
+/**
+ * 检查带新鲜度字段的请求的有效性
+ *
+ * 与check_request相同，freshness不为空时会话签名必须覆盖时间戳和nonce。
+ * 时间戳窗口和nonce是否已使用由调用方通过replay_guard检查。
+ */
+#[allow(clippy::too_many_arguments)]
+pub async fn check_fresh_request(
+    app_state: &AppState,
+    ptb_str: &str,
+    enc_key: &ElGamalPublicKey,
+    enc_verification_key: &ElgamalVerificationKey,
+    request_signature: &Ed25519Signature,
+    certificate: &Certificate,
+    freshness: Option<Freshness<'_>>,
+    gas_price: u64,
+    metrics: Option<&Metrics>,
+    req_id: Option<&str>,
 ) -> Result<Vec<KeyId>, InternalError> {
     debug!(
         "Checking request for ptb_str: {:?}, cert {:?} (req_id: {:?})",
@@ -298,6 +436,9 @@ pub async fn check_request(
     let ptb: ProgrammableTransaction =
         bcs::from_bytes(&ptb_b64).map_err(|_| InternalError::InvalidPTB)?;
     let valid_ptb = ValidPtb::try_from(ptb.clone())?;
+    if let Some(policy_engine) = ptb_policy::global_policy_engine() {
+        policy_engine.check(&ptb, req_id)?;
+    }
 
     // 向指标报告请求中的ID数量
     if let Some(m) = metrics {
@@ -331,6 +472,7 @@ pub async fn check_request(
         enc_verification_key,
         request_signature,
         certificate,
+        freshness,
         req_id,
     )
     .await?;
@@ -380,13 +522,98 @@ pub fn create_response(
             }
         })
         .collect();
-    FetchKeyResponse { decryption_keys }
+    FetchKeyResponse {
+        decryption_keys,
+        binding: None,
+    }
+}
+
+/**
+ * 计算请求摘要
+ *
+ * 摘要覆盖客户端签名的请求数据和请求签名，客户端无需保存完整请求即可复现
+ *
+ * 参数:
+ * @param signed_request - 客户端签名的请求数据，即signed_request或signed_request_with_freshness的结果
+ * @param request_signature - 请求签名
+ *
+ * 返回:
+ * Blake2b256(BCS(signed_request, request_signature))
+ */
+pub fn request_digest(signed_request: &[u8], request_signature: &Ed25519Signature) -> Vec<u8> {
+    let bytes = bcs::to_bytes(&(signed_request, request_signature.as_bytes())).expect("should serialize");
+    Blake2b256::digest(&bytes).digest.to_vec()
+}
+
+/// 计算密钥材料摘要：Blake2b256(BCS(decryption_keys))
+pub fn keys_digest(decryption_keys: &[DecryptionKey]) -> Vec<u8> {
+    let bytes = bcs::to_bytes(decryption_keys).expect("should serialize");
+    Blake2b256::digest(&bytes).digest.to_vec()
+}
+
+/**
+ * 签名密钥响应的请求绑定
+ *
+ * 参数:
+ * @param kp - 临时密钥对
+ * @param binding - 请求绑定
+ * @param timestamp_ms - 签名时间
+ *
+ * 返回:
+ * 签名的请求绑定，签名方式与 `common::to_signed_response` 一致
+ */
+pub fn sign_key_response(
+    kp: &Ed25519KeyPair,
+    binding: KeyResponseBinding,
+    timestamp_ms: u64,
+) -> SignedKeyResponseBinding {
+    to_signed_response(kp, binding, timestamp_ms, IntentScope::KeyResponse)
+}
+
+/**
+ * 为已通过校验的请求生成签名绑定
+ *
+ * 参数:
+ * @param app_state - 应用状态
+ * @param payload - 已通过校验的请求
+ * @param decryption_keys - 返回的密钥材料
+ *
+ * 返回:
+ * 签名的请求绑定，PTB无法解码时返回None
+ */
+fn bind_response(
+    app_state: &AppState,
+    payload: &FetchKeyRequest,
+    decryption_keys: &[DecryptionKey],
+) -> Option<SignedKeyResponseBinding> {
+    let ptb: ProgrammableTransaction = Base64::decode(&payload.ptb)
+        .ok()
+        .and_then(|bytes| bcs::from_bytes(&bytes).ok())?;
+    // 与校验签名时使用相同的请求格式
+    let signed = match (payload.timestamp, payload.nonce.as_deref()) {
+        (Some(timestamp), Some(nonce)) => signed_request_with_freshness(
+            &ptb,
+            &payload.enc_key,
+            &payload.enc_verification_key,
+            timestamp,
+            nonce,
+        ),
+        _ => signed_request(&ptb, &payload.enc_key, &payload.enc_verification_key),
+    };
+    let binding = KeyResponseBinding {
+        service_id: app_state.key_server_object_id,
+        request_digest: request_digest(&signed, &payload.request_signature),
+        keys_digest: keys_digest(decryption_keys),
+        checkpoint_timestamp: *app_state.latest_checkpoint_timestamp_receiver.borrow(),
+    };
+    Some(sign_key_response(&app_state.eph_kp, binding, current_epoch_time()))
 }
 
 /**
  * 处理获取密钥请求
  *
- * 处理客户端的密钥请求，验证其有效性并返回加密的密钥
+ * 处理客户端的密钥请求，验证其有效性并返回加密的密钥，
+ * 响应附带临时密钥对对请求摘要、密钥材料和检查点时间戳的签名
  *
  * 参数:
  * @param app_state - 应用状态
@@ -396,6 +623,28 @@ pub fn create_response(
  * 返回:
  * 成功时返回密钥响应，失败时返回错误
  */
+#[utoipa::path(
+    post,
+    path = "/v1/fetch_key",
+    tag = "keys",
+    request_body = FetchKeyRequest,
+    params(
+        ("Request-Id" = Option<String>, Header, description = "请求ID"),
+        ("Client-Sdk-Version" = Option<String>, Header, description = "客户端SDK版本"),
+        ("Client-Sdk-Type" = Option<String>, Header, description = "客户端SDK类型"),
+    ),
+    responses(
+        (status = 200, description = "加密后的解密密钥及签名的请求绑定", body = FetchKeyResponse),
+        (status = 403, description = "请求校验失败或无权访问", body = ErrorResponse),
+        (status = 429, description = "请求过于频繁，或请求队列已满（可按Retry-After重试）", body = ErrorResponse),
+        (status = 503, description = "全节点不可用或数据过时（可按Retry-After重试）、校验超时或服务内部错误", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(
+    name = "fetch_key",
+    skip_all,
+    fields(request_id = tracing::field::Empty, user_id = %payload.certificate.user)
+)]
 pub async fn handle_fetch_key(
     State(app_state): State<Arc<AppState>>,
     headers: HeaderMap,
@@ -404,6 +653,9 @@ pub async fn handle_fetch_key(
     let req_id = headers
         .get("Request-Id")
         .map(|v| v.to_str().unwrap_or_default());
+    if let Some(req_id) = req_id {
+        tracing::Span::current().record("request_id", req_id);
+    }
     let version = headers.get("Client-Sdk-Version");
     let sdk_type = headers.get("Client-Sdk-Type");
     let target_api_version = headers.get("Client-Target-Api-Version");
@@ -413,27 +665,68 @@ pub async fn handle_fetch_key(
     );
 
     app_state.metrics.observe_request("fetch_key");
-    app_state.check_full_node_is_fresh(ALLOWED_STALENESS)?;
-
-    check_request(
-        &app_state,
-        &payload.ptb,
-        &payload.enc_key,
-        &payload.enc_verification_key,
-        &payload.request_signature,
-        &payload.certificate,
-        app_state.reference_gas_price(),
-        Some(&app_state.metrics),
-        req_id,
-    )
-    .await
-    .map(|full_id| Json(create_response(&app_state, &full_id, &payload.enc_key)))
-    .tap_err(|e| app_state.metrics.observe_error(e.as_str()))
+    app_state.check_full_node_is_fresh()?;
+
+    // 按请求者地址限流并记录审计日志
+    let audit = key_audit::global_key_audit_service();
+    let audit_context = match &audit {
+        Some(service) => {
+            let context = AuditContext::new(payload.certificate.user, &payload.ptb, req_id);
+            Some(
+                service
+                    .check_rate_limit(context)
+                    .tap_err(|e| app_state.metrics.observe_error(e.as_str()))?,
+            )
+        }
+        None => None,
+    };
+
+    // 签名校验前先检查时间戳窗口，签名校验通过后再记录nonce
+    let replay_guard = replay_guard::global_replay_guard();
+    let work = async {
+        let freshness = Freshness::from_parts(payload.timestamp, payload.nonce.as_deref())?;
+        if let Some(guard) = &replay_guard {
+            guard.check(freshness, current_epoch_time())?;
+        }
+        let ids = check_fresh_request(
+            &app_state,
+            &payload.ptb,
+            &payload.enc_key,
+            &payload.enc_verification_key,
+            &payload.request_signature,
+            &payload.certificate,
+            freshness,
+            app_state.reference_gas_price(),
+            Some(&app_state.metrics),
+            req_id,
+        )
+        .await?;
+        if let (Some(guard), Some(freshness)) = (&replay_guard, freshness) {
+            let session_key = Base64::encode(payload.certificate.session_vk.as_bytes());
+            guard.consume(&session_key, freshness, current_epoch_time())?;
+        }
+        Ok::<_, InternalError>(ids)
+    };
+    // 校验在有界队列中执行，过载时直接拒绝
+    let result = match key_queue::global_key_queue() {
+        Some(queue) => queue.run(Some(&app_state.metrics), work).await,
+        None => work.await,
+    };
+    if let (Some(service), Some(context)) = (audit, audit_context) {
+        service.record_result(context, &result);
+    }
+    result
+        .map(|full_id| {
+            let mut response = create_response(&app_state, &full_id, &payload.enc_key);
+            response.binding = bind_response(&app_state, &payload, &response.decryption_keys);
+            Json(response)
+        })
+        .tap_err(|e| app_state.metrics.observe_error(e.as_str()))
 }
 /**
  * 处理获取服务信息请求
  *
- * 返回服务器ID和密钥持有证明，用于客户端验证服务器身份
+ * 返回服务器ID、密钥持有证明、临时公钥和全节点健康状态，用于客户端验证服务器身份和签名的游戏事件
  *
  * 参数:
  * @param app_state - 应用状态
@@ -441,6 +734,12 @@ pub async fn handle_fetch_key(
  * 返回:
  * 服务信息响应
  */
+#[utoipa::path(
+    get,
+    path = "/v1/service",
+    tag = "keys",
+    responses((status = 200, description = "密钥服务器对象ID、主密钥持有证明和临时公钥", body = GetServiceResponse))
+)]
 pub async fn handle_get_service(
     State(app_state): State<Arc<AppState>>,
 ) -> Result<Json<GetServiceResponse>, InternalError> {
@@ -448,5 +747,9 @@ pub async fn handle_get_service(
     Ok(Json(GetServiceResponse {
         service_id: app_state.key_server_object_id.clone(),
         pop: app_state.key_server_object_id_sig.clone(),
+        eph_pk: Hex::encode(app_state.eph_kp.public().as_bytes()),
+        node_status: app_state.node_health.status(),
+        freshness: replay_guard::global_replay_guard().map(|g| g.policy()),
+        read_only: read_only::is_read_only(),
     }))
 }
@@ -0,0 +1,204 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 连接延迟测量
+//!
+//! # 概述
+//! 心跳任务定期向每个连接发送WebSocket Ping帧，负载是发送时间（毫秒，大端u64），
+//! 客户端按协议原样返回Pong帧，收到后计算往返延迟（RTT）并做平滑处理，记录在 `ConnectionManager` 中。
+//!
+//! 延迟的用途：
+//! - 对局数据视图的 `latency` 字段按玩家ID给出粗粒度的延迟等级（good / fair / poor），不暴露具体数值
+//! - 高延迟玩家的回合计时额外获得一小段宽限时间，每回合只给一次
+//!
+//! 同一用户有多个连接时取其中最低的延迟。还没有测量结果的连接没有延迟等级，也不获得宽限。
+//!
+//! # 配置
+//! - `LATENCY_PING_INTERVAL_SECS`：心跳Ping的间隔，默认15秒
+//! - `LATENCY_HIGH_MS`：获得回合宽限的延迟阈值（毫秒），默认250
+//! - `LATENCY_TURN_GRACE_SECS`：高延迟玩家每回合的宽限时间（秒），默认3，为0时关闭
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::ws::ClientId;
+
+/// 延迟等级为good的上限（毫秒，不含）
+pub const GOOD_MAX_MS: u64 = 100;
+/// 延迟等级为fair的上限（毫秒，不含）
+pub const FAIR_MAX_MS: u64 = 250;
+
+/// 延迟测量配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyConfig {
+    /// 心跳Ping的间隔
+    pub ping_interval: Duration,
+    /// 获得回合宽限的延迟阈值（毫秒）
+    pub high_latency_ms: u64,
+    /// 高延迟玩家每回合的宽限时间（毫秒），为0时关闭
+    pub turn_grace_ms: u64,
+}
+
+impl Default for LatencyConfig {
+    fn default() -> Self {
+        Self {
+            ping_interval: Duration::from_secs(15),
+            high_latency_ms: FAIR_MAX_MS,
+            turn_grace_ms: 3000,
+        }
+    }
+}
+
+impl LatencyConfig {
+    /// 从环境变量读取，未配置或无效的项使用默认值
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let parse = |key: &str| std::env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        Self {
+            ping_interval: parse("LATENCY_PING_INTERVAL_SECS")
+                .filter(|v| *v > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.ping_interval),
+            high_latency_ms: parse("LATENCY_HIGH_MS").unwrap_or(defaults.high_latency_ms),
+            turn_grace_ms: parse("LATENCY_TURN_GRACE_SECS")
+                .map(|v| v * 1000)
+                .unwrap_or(defaults.turn_grace_ms),
+        }
+    }
+
+    /// 按延迟计算回合宽限时间（毫秒），没有测量结果或延迟未达到阈值时为0
+    pub fn turn_grace(&self, rtt_ms: Option<u64>) -> u64 {
+        match rtt_ms {
+            Some(rtt_ms) if rtt_ms >= self.high_latency_ms => self.turn_grace_ms,
+            _ => 0,
+        }
+    }
+}
+
+/// 粗粒度的延迟等级
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum LatencyBucket {
+    Good,
+    Fair,
+    Poor,
+}
+
+impl LatencyBucket {
+    pub fn from_rtt(rtt_ms: u64) -> Self {
+        if rtt_ms < GOOD_MAX_MS {
+            LatencyBucket::Good
+        } else if rtt_ms < FAIR_MAX_MS {
+            LatencyBucket::Fair
+        } else {
+            LatencyBucket::Poor
+        }
+    }
+}
+
+/// 生成心跳Ping帧的负载
+pub fn ping_payload(now: u64) -> Vec<u8> {
+    now.to_be_bytes().to_vec()
+}
+
+/// 每个连接平滑后的往返延迟
+#[derive(Debug, Default)]
+pub struct LatencyTracker {
+    config: LatencyConfig,
+    rtt: Mutex<HashMap<ClientId, u64>>,
+}
+
+impl LatencyTracker {
+    pub fn new(config: LatencyConfig) -> Self {
+        Self {
+            config,
+            rtt: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &LatencyConfig {
+        &self.config
+    }
+
+    /**
+     * 记录一次Pong
+     *
+     * 参数:
+     * @param client_id - 客户端ID
+     * @param payload - Pong帧的负载，应为服务端Ping帧的原样负载
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 平滑后的往返延迟，负载不是服务端Ping时返回None
+     */
+    pub fn record_pong(&self, client_id: &str, payload: &[u8], now: u64) -> Option<u64> {
+        let sent_at = u64::from_be_bytes(payload.try_into().ok()?);
+        if sent_at > now {
+            return None;
+        }
+        let sample = now - sent_at;
+        let mut rtt = self.rtt.lock();
+        // 新样本占1/4权重，减少单次抖动的影响
+        let smoothed = rtt
+            .get(client_id)
+            .map(|previous| (previous * 3 + sample) / 4)
+            .unwrap_or(sample);
+        rtt.insert(client_id.to_string(), smoothed);
+        Some(smoothed)
+    }
+
+    /// 连接的往返延迟
+    pub fn rtt(&self, client_id: &str) -> Option<u64> {
+        self.rtt.lock().get(client_id).copied()
+    }
+
+    /// 多个连接中最低的往返延迟
+    pub fn best(&self, client_ids: &[ClientId]) -> Option<u64> {
+        let rtt = self.rtt.lock();
+        client_ids.iter().filter_map(|id| rtt.get(id).copied()).min()
+    }
+
+    /// 连接断开时移除记录
+    pub fn remove(&self, client_id: &str) {
+        self.rtt.lock().remove(client_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_pong() {
+        let tracker = LatencyTracker::default();
+        assert_eq!(tracker.record_pong("c1", &ping_payload(1000), 1200), Some(200));
+        assert_eq!(tracker.record_pong("c1", &ping_payload(2000), 2040), Some(160));
+        assert_eq!(tracker.rtt("c1"), Some(160));
+
+        // 客户端自己的Pong或伪造的未来时间不计入
+        assert_eq!(tracker.record_pong("c1", b"hello", 3000), None);
+        assert_eq!(tracker.record_pong("c1", &ping_payload(5000), 3000), None);
+        assert_eq!(tracker.rtt("c1"), Some(160));
+
+        tracker.record_pong("c2", &ping_payload(0), 50);
+        assert_eq!(tracker.best(&["c1".to_string(), "c2".to_string()]), Some(50));
+        tracker.remove("c2");
+        assert_eq!(tracker.best(&["c2".to_string()]), None);
+    }
+
+    #[test]
+    fn test_bucket_and_grace() {
+        assert_eq!(LatencyBucket::from_rtt(99), LatencyBucket::Good);
+        assert_eq!(LatencyBucket::from_rtt(100), LatencyBucket::Fair);
+        assert_eq!(LatencyBucket::from_rtt(250), LatencyBucket::Poor);
+
+        let config = LatencyConfig::default();
+        assert_eq!(config.turn_grace(None), 0);
+        assert_eq!(config.turn_grace(Some(249)), 0);
+        assert_eq!(config.turn_grace(Some(400)), 3000);
+    }
+}
@@ -23,41 +23,112 @@ use std::env;
 use std::sync::Arc;
 use std::time::Duration;
 use sui_sdk::types::base_types::ObjectID;
-use sui_sdk::SuiClient;
-use sui_sdk::SuiClientBuilder;
 use tokio::sync::watch::channel;
 use tokio::sync::watch::Receiver;
 use tracing::{info, Level};
-use tracing_subscriber::{fmt, EnvFilter};
 use crate::sdk::GameManager;
+use crate::networks::{NetworkContext, NetworkRegistry};
+use crate::node_health::NodeHealth;
+use crate::node_pool::{PoolConfig, SuiClientPool};
+use crate::tasks::{RestartPolicy, TaskManager};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+pub mod account_link; // 多钱包关联到同一Profile
+pub mod action_profile; // 对局动作耗时分析与慢动作警告
+pub mod achievement; // 成就与每日任务模块
+pub mod announcement; // 管理员系统公告
 pub mod app;
 pub mod avatars; // 头像模块
+pub mod ban; // 用户和地址封禁
+pub mod bot; // 机器人玩家策略与对局模拟
 pub mod cache; // 缓存系统，优化性能
+pub mod calendar; // 定期活动日历与锦标赛调度
+pub mod cards; // 卡牌元数据与本地化
 pub mod catastrophe; // 游戏模块
 pub mod chat; // 聊天系统
 pub mod cli; // 命令行接口
+pub mod cluster; // 实例间内部RPC
 pub mod common;
+pub mod delivery; // 关键事件送达确认
+pub mod economy; // 游戏经济与押注模块
 pub mod errors; // 错误类型定义
+pub mod event_bus; // 模块间的类型化事件总线
+pub mod event_signing; // 权威游戏事件签名
 pub mod externals; // 外部接口，如时间和gas价格
+pub mod friend_sync; // 链上好友关系与护照缓存的同步
 pub mod game; // 游戏模块
+pub mod game_mode; // 可扩展的游戏模式
+pub mod game_snapshot; // 游戏数据快照与恢复
 pub mod gaming; // 游戏匹配模块
+pub mod geo; // 匹配地区提示与GeoIP查找
+pub mod guest; // 游客模式与账户升级
+pub mod hand_limit; // 手牌上限与回合结束弃牌
+pub mod hand_seal; // 对局私密消息的IBE加密
+pub mod i18n; // 服务端消息本地化
+pub mod key_audit; // 密钥访问审计与限流
+pub mod key_queue; // 密钥请求排队与过载保护
 pub mod keys; // 密钥服务器模块
+pub mod latency; // 连接延迟测量与回合宽限
+pub mod logging; // 日志格式、文件轮转与模块级别
+pub mod match_attestation; // 对局结果链上存证
+pub mod match_delta; // 对局状态增量同步
+pub mod match_history; // 对局动作历史压缩与归档
+pub mod match_log; // 对局事件日志与缓存丢失后的状态重建
+pub mod match_replay; // 按随机种子重放对局的确定性验证
+pub mod match_timers; // 对局计时器统一管理
 pub mod metrics;
+pub mod name_policy; // Profile名称策略
+pub mod networks; // 单进程多网络支持
+pub mod node_health; // 全节点健康状态与降级模式
+pub mod node_pool; // 全节点连接池与故障转移
+pub mod notification; // 离线推送通知网关
+pub mod observer; // 外部转播工具的对局观察接口
+pub mod openapi; // REST接口OpenAPI文档
+pub mod outbound; // 慢客户端的发送队列与背压处理
+pub mod party; // 组队匹配
 pub mod passport; // 用户护照系统
+pub mod penalty; // 中途退出惩罚与匹配禁令
+pub mod presence; // 自定义状态消息与丰富在线状态
+pub mod probes; // 存活与就绪探针
 pub mod profile;
+pub mod profile_edit; // Profile改名与重新生成头像
+pub mod profile_guard; // Profile创建的限流与防女巫保护
+pub mod protocol; // WebSocket协议版本与能力协商
+pub mod ptb_policy; // 可配置的PTB访问策略
+pub mod rating; // 评分修正与配置
+pub mod read_only; // 只读镜像模式
+pub mod replay_guard; // 密钥请求重放保护
+pub mod series; // 多局制系列赛
+pub mod shuffle_proof; // 开局牌堆顺序的承诺与公开
 pub mod signed_message; // 签名消息处理
+pub mod stats; // 玩家统计与评分历史
+pub mod storage; // 可插拔的持久化存储
+pub mod sui_api; // 可替换为模拟实现的全节点接口
+pub mod tasks; // 后台任务管理与优雅关闭
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support; // 集成测试工具：进程内测试服务、模拟客户端和场景运行器
 #[cfg(test)]
 pub mod tests;
+pub mod time_bank; // 回合时间储备
+pub mod timeline; // 对局时间线与赛后复盘
 pub mod tool; // 游戏工具模块
 pub mod txb; // 事务构建模块
 pub mod types; // 数据类型定义
+pub mod user_search; // 用户名前缀搜索
 pub mod valid_ptb; // 可编程交易块验证 // 测试模块
 pub mod ws; // WebSocket 会话管理模块
+pub mod ws_acl; // WebSocket房间权限
+pub mod ws_guard; // WebSocket负载校验与大小限制
+pub mod ws_handlers; // WebSocket事件处理器注册与分发
+pub mod ws_resume; // WebSocket重连恢复令牌
+pub mod ws_schema; // WebSocket事件目录
+pub mod ws_sessions; // WebSocket会话列表与强制断开
+pub mod ws_traffic; // WebSocket按事件和房间的流量统计
 pub mod sdk; // SUI SDK 模块
+pub mod session_attestation; // WebSocket会话的飞地证明绑定
 pub mod session_login; // 会话登录模块
+pub mod session_store; // 可持久化的会话存储
 
 /// 更新最新检查点时间戳的间隔
 const CHECKPOINT_UPDATE_INTERVAL: Duration = Duration::from_secs(10);
@@ -82,8 +153,8 @@ pub struct AppState {
     pub network: Network,
     /// Metrics
     pub metrics: Metrics,
-    /// SUI客户端（可选，为密钥服务器功能）
-    pub sui_client: SuiClient,
+    /// SUI全节点连接池，支持多个地址之间的故障转移
+    pub sui_pool: Arc<SuiClientPool>,
     /// IBE主密钥（可选，为密钥服务器功能）
     pub master_key: types::IbeMasterKey,
     /// 密钥服务器对象ID（可选，为密钥服务器功能）
@@ -98,6 +169,12 @@ pub struct AppState {
     pub citadel_package_id_receiver: Receiver<String>,
     /// 游戏数据管理器
     pub game_manager: Arc<GameManager>,
+    /// 全节点健康状态，全节点不可用时进入降级模式
+    pub node_health: Arc<NodeHealth>,
+    /// 进程内服务的所有网络，主网络即上面的 `network`/`sui_pool`/`game_manager`
+    pub networks: Arc<NetworkRegistry>,
+    /// 定期更新等后台任务，关闭服务时统一停止
+    pub tasks: Arc<TaskManager>,
 }
 
 impl AppState {
@@ -120,12 +197,14 @@ impl AppState {
             "CITADEL_ADMINCAP_ADDRESS",
         ]);
         info!("Load env vars: {:?}", config);
-        // 初始化SUI客户端
-        let sui_client = SuiClientBuilder::default()
-            .build(&network.node_url())
-            .await
-            .expect(format!("Sui client build failed with {:?}", network.node_url()).as_str());
-        info!("Sui client build success, node url: {:?},graphql url: {:?}, network: {:?}, api version: {:?}", network.node_url(), network.graphql_url(), network, sui_client.api_version());
+        // 初始化SUI客户端连接池
+        let sui_pool = Arc::new(
+            SuiClientPool::connect(network.name(), &network.node_urls(), PoolConfig::from_env())
+                .await
+                .expect(format!("Sui client build failed with {:?}", network.node_url()).as_str()),
+        );
+        let sui_client = sui_pool.client();
+        info!("Sui client build success, node url: {:?},graphql url: {:?}, network: {:?}, api version: {:?}", sui_pool.active_url(), network.graphql_url(), network, sui_client.api_version());
         // 初始化主密钥和服务器ID
         let master_key = IbeMasterKey::from_byte_array(
             &Base64::decode(&config["MASTER_KEY"])
@@ -157,6 +236,15 @@ impl AppState {
             manager_store_id,
             friendship_store_id,
         ).await.unwrap());
+        // 初始化SUI_NETWORKS中配置的额外网络
+        let networks = NetworkRegistry::from_env(NetworkContext {
+            name: network.name().to_string(),
+            network: network.clone(),
+            sui_pool: sui_pool.clone(),
+            game_manager: game_manager.clone(),
+        })
+        .await
+        .expect("Failed to initialize SUI_NETWORKS");
         // 启动指标服务器,创建分组的metrics
         let registry_service = start_basic_prometheus_server(None);
         let metrics = create_metrics! {
@@ -179,12 +267,22 @@ impl AppState {
             registry_service.count_registries()
         );
         let citadel_package_receiver = channel(config["CITADEL_PACKAGE"].clone()).1;
+        // 全节点连接池记录每个地址的健康状态，并在后台定期检查
+        let tasks = Arc::new(TaskManager::new());
+        tasks.attach_metrics(metrics.clone());
+        tasks::set_global_task_manager(tasks.clone());
+        for pool in std::iter::once(sui_pool.clone()).chain(networks.secondary().map(|c| c.sui_pool.clone())) {
+            pool.attach_metrics(metrics.clone());
+            SuiClientPool::spawn_health_checker(pool, &tasks);
+        }
+        // 对局动作的各步骤耗时记录到指标中
+        action_profile::attach_metrics(metrics.clone());
         AppState {
             eph_kp,
             config,
             network,
             metrics,
-            sui_client: sui_client.clone(),
+            sui_pool,
             master_key,
             key_server_object_id,
             key_server_object_id_sig,
@@ -192,6 +290,9 @@ impl AppState {
             reference_gas_price: channel(0).1,
             citadel_package_id_receiver: citadel_package_receiver,
             game_manager,
+            node_health: Arc::new(NodeHealth::from_env()),
+            networks: Arc::new(networks),
+            tasks,
         }
     }
 
@@ -248,28 +349,18 @@ impl AppState {
     /**
      * 检查全节点数据是否新鲜
      *
-     * 验证最新检查点时间戳是否在允许的过时时间范围内
-     *
-     * 参数:
-     * @param allowed_staleness - 允许的过时时间
+     * 验证最新检查点时间戳是否在配置的允许过时时间（`ALLOWED_STALENESS_SECS`）范围内，
+     * 所有提供密钥的接口在处理请求前都需要调用
      *
      * 返回:
-     * 成功时返回Ok(())，如果数据过时则返回错误
+     * 成功时返回Ok(())，数据过时返回 `StaleFullNode`，处于降级模式返回 `Degraded`，两者都可重试
      */
-    pub fn check_full_node_is_fresh(
-        &self,
-        allowed_staleness: std::time::Duration,
-    ) -> Result<(), errors::InternalError> {
+    pub fn check_full_node_is_fresh(&self) -> Result<(), errors::InternalError> {
         let staleness =
             externals::duration_since(*self.latest_checkpoint_timestamp_receiver.borrow());
-        if staleness > allowed_staleness.as_millis() as i64 {
-            tracing::warn!(
-                "Full node is stale. Latest checkpoint is {} ms old.",
-                staleness
-            );
-            return Err(errors::InternalError::Failure);
-        }
-        Ok(())
+        self.node_health
+            .ensure_fresh(staleness, externals::current_epoch_time())?;
+        self.node_health.ensure_available()
     }
     /**
      * 获取当前参考gas价格
@@ -284,11 +375,14 @@ impl AppState {
     /**
      * 生成定期更新器
      *
-     * 启动一个线程，定期获取值并将其发送到接收器
+     * 启动一个受管理的后台任务，定期获取值并将其发送到接收器
      * 用于维护服务器状态，如最新检查点时间和gas价格
+     * 任务在取消令牌取消或所有接收器关闭后退出
      *
      * 参数:
-     * @param sui_client - SUI客户端
+     * @param tasks - 后台任务管理器
+     * @param sui_pool - SUI全节点连接池，请求失败时自动切换地址重试
+     * @param node_health - 全节点健康状态，每次请求的结果都会记录到其中
      * @param update_interval - 更新间隔
      * @param fetch_fn - 获取值的函数
      * @param value_name - 值名称（用于日志）
@@ -299,8 +393,11 @@ impl AppState {
      * 返回:
      * 包含更新值的接收器
      */
+    #[allow(clippy::too_many_arguments)]
     async fn spawn_periodic_updater<F, Fut, G, H, I>(
-        sui_client: sui_sdk::SuiClient,
+        tasks: &TaskManager,
+        sui_pool: Arc<SuiClientPool>,
+        node_health: Arc<NodeHealth>,
         update_interval: Duration,
         fetch_fn: F,
         value_name: &'static str,
@@ -309,23 +406,25 @@ impl AppState {
         success_callback: Option<I>,
     ) -> tokio::sync::watch::Receiver<u64>
     where
-        F: Fn(sui_sdk::SuiClient) -> Fut + Send + 'static,
+        F: Fn(sui_sdk::SuiClient) -> Fut + Send + Sync + 'static,
         Fut: std::future::Future<Output = sui_sdk::error::SuiRpcResult<u64>> + Send,
         G: Fn(u64) + Send + 'static,
         H: Fn(Duration) + Send + 'static,
         I: Fn(bool) + Send + 'static,
     {
         let (sender, mut receiver) = channel(0);
-        let local_client = sui_client.clone();
         let mut interval = tokio::time::interval(update_interval);
 
         // 如果由于全节点响应缓慢而错过了一个tick，我们不需要赶上来，而是延迟下一个tick。
         interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
 
-        tokio::task::spawn(async move {
+        tasks.spawn(value_name, move |token| async move {
             loop {
                 let now = std::time::Instant::now();
-                let result = fetch_fn(local_client.clone()).await;
+                let result = tokio::select! {
+                    _ = token.cancelled() => break,
+                    result = sui_pool.call(&fetch_fn) => result,
+                };
                 if let Some(dcb) = &duration_callback {
                     dcb(now.elapsed());
                 }
@@ -334,18 +433,30 @@ impl AppState {
                 }
                 match result {
                     Ok(new_value) => {
-                        sender
-                            .send(new_value)
-                            .expect("Channel closed, this should never happen");
+                        node_health.record_success(externals::current_epoch_time());
+                        if sender.send(new_value).is_err() {
+                            tracing::warn!("All receivers of {} are closed, stopping updater", value_name);
+                            break;
+                        }
                         tracing::debug!("{} updated to: {:?}", value_name, new_value);
                         if let Some(subscriber) = &subscriber {
                             subscriber(new_value);
                         }
                     }
-                    Err(e) => tracing::warn!("Failed to get {}: {:?}", value_name, e),
+                    Err(e) => {
+                        tracing::warn!("Failed to get {}: {:?}", value_name, e);
+                        node_health.record_failure(
+                            externals::current_epoch_time(),
+                            format!("获取{}失败: {}", value_name, e),
+                        );
+                    }
+                }
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    _ = interval.tick() => {}
                 }
-                interval.tick().await;
             }
+            tracing::info!("{} updater stopped", value_name);
         });
 
         // 这会阻塞直到获取到一个值。
@@ -375,7 +486,9 @@ impl AppState {
     ) -> Receiver<Timestamp> {
         // 启动定期更新任务
         app_state.latest_checkpoint_timestamp_receiver = Self::spawn_periodic_updater(
-            app_state.sui_client.clone(),
+            &app_state.tasks,
+            app_state.sui_pool.clone(),
+            app_state.node_health.clone(),
             interval.unwrap_or(CHECKPOINT_UPDATE_INTERVAL),
             get_latest_checkpoint_timestamp,
             "latest checkpoint timestamp",
@@ -411,7 +524,9 @@ impl AppState {
         interval: Option<Duration>,
     ) -> Receiver<u64> {
         app_state.reference_gas_price = Self::spawn_periodic_updater(
-            app_state.sui_client.clone(),
+            &app_state.tasks,
+            app_state.sui_pool.clone(),
+            app_state.node_health.clone(),
             interval.unwrap_or(GAS_PRICE_UPDATE_INTERVAL),
             get_reference_gas_price,
             "RGP",
@@ -460,18 +575,26 @@ impl AppState {
                 let network = app_state.network.clone();
                 
                 // 启动更新任务
-                tokio::task::spawn(async move {
+                app_state.tasks.spawn("package_id_updater", move |token| async move {
                     let mut interval = tokio::time::interval(update_interval);
                     
                     loop {
-                        interval.tick().await;
+                        tokio::select! {
+                            _ = token.cancelled() => break,
+                            _ = interval.tick() => {}
+                        }
                         
                         // 获取最新的包ID
                         if let Ok((_, latest)) = fetch_first_and_last_pkg_id(&pkg_id, &network).await {
                             // 检查是否需要更新
-                            if latest != pkg_id && sender.send(latest.to_string()).is_ok() {
-                                tracing::info!("Citadel package ID updated: {} -> {}", pkg_id, latest);
+                            if latest == pkg_id {
+                                continue;
+                            }
+                            if sender.send(latest.to_string()).is_err() {
+                                tracing::warn!("All receivers of Citadel package ID are closed, stopping updater");
+                                break;
                             }
+                            tracing::info!("Citadel package ID updated: {} -> {}", pkg_id, latest);
                         }
                     }
                 });
@@ -509,38 +632,18 @@ impl AppState {
         let (sender, receiver) = tokio::sync::watch::channel(initial_count);
         
         let update_interval = interval.unwrap_or(PROFILE_UPDATE_INTERVAL);
-        let game_manager = app_state.game_manager.clone();
-
-        // 启动更新任务
-        tokio::task::spawn(async move {
-            loop {
-                // 计算距离上次更新的时间
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
-                let last = game_manager.get_last_profile_update();
-                let elapsed = now - last;
-
-                // 如果距离上次更新时间小于间隔，则等待剩余时间
-                if elapsed < update_interval.as_secs() {
-                    let wait_time = update_interval.as_secs() - elapsed;
-                    tokio::time::sleep(Duration::from_secs(wait_time)).await;
-                }
-
-                // 更新所有profiles
-                if let Err(e) = game_manager.update_all_profiles().await {
-                    tracing::warn!("Failed to update user profiles: {}", e);
-                }
-                
-                // 获取最新的profiles数量
-                if let Ok(count) = game_manager.get_profile_size().await {
-                    if sender.send(count).is_ok() {
-                        tracing::debug!("Profiles count updated: {}", count);
-                    }
-                }
-            }
-        });
+        Self::spawn_profile_loop(
+            &app_state.tasks,
+            "profile_updater".to_string(),
+            app_state.game_manager.clone(),
+            update_interval,
+            Some(sender),
+        );
+        // 额外网络各自独立更新
+        for context in app_state.networks.secondary() {
+            let name = format!("profile_updater:{}", context.name);
+            Self::spawn_profile_loop(&app_state.tasks, name, context.game_manager.clone(), update_interval, None);
+        }
         
         tracing::info!(
             "Profile updater started, initial profiles count: {}, update interval: {} seconds, last update time: {}", 
@@ -575,37 +678,18 @@ impl AppState {
         let (sender, receiver) = channel(initial_count);
         
         let update_interval = interval.unwrap_or(RELATIONSHIP_UPDATE_INTERVAL);
-        let game_manager = app_state.game_manager.clone();
-
-        // 启动更新任务
-        tokio::task::spawn(async move {
-            loop {
-                // 计算距离上次更新的时间
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
-                let last = game_manager.get_last_relationship_update();
-                let elapsed = now - last;
-
-                // 如果距离上次更新时间小于间隔，则等待剩余时间
-                if elapsed < update_interval.as_secs() {
-                    let wait_time = update_interval.as_secs() - elapsed;
-                    tokio::time::sleep(Duration::from_secs(wait_time)).await;
-                }
-                
-                // 更新所有好友关系
-                if let Err(e) = game_manager.update_all_relationships().await {
-                    tracing::warn!("Failed to update relationships: {}", e);
-                }
-                
-                // 获取最新的关系数量
-                let count = game_manager.get_relationship_cache_size().await;
-                if sender.send(count).is_ok() {
-                    tracing::debug!("Relationships count updated: {}", count);
-                }
-            }
-        });
+        Self::spawn_relationship_loop(
+            &app_state.tasks,
+            "relationship_updater".to_string(),
+            app_state.game_manager.clone(),
+            update_interval,
+            Some(sender),
+        );
+        // 额外网络各自独立更新
+        for context in app_state.networks.secondary() {
+            let name = format!("relationship_updater:{}", context.name);
+            Self::spawn_relationship_loop(&app_state.tasks, name, context.game_manager.clone(), update_interval, None);
+        }
         
         info!(
             "Relationship updater started, initial count: {}, update interval: {} seconds, last update time: {}", 
@@ -617,6 +701,123 @@ impl AppState {
         receiver
     }
     
+    /**
+     * 启动单个网络的档案更新任务
+     * 
+     * 参数:
+     * @param tasks - 后台任务管理器
+     * @param name - 任务名称
+     * @param game_manager - 该网络的游戏管理器
+     * @param update_interval - 更新间隔
+     * @param sender - 主网络的profiles数量发送器，额外网络为None
+     */
+    fn spawn_profile_loop(
+        tasks: &TaskManager,
+        name: String,
+        game_manager: Arc<GameManager>,
+        update_interval: Duration,
+        sender: Option<tokio::sync::watch::Sender<u64>>,
+    ) {
+        tasks.supervise(name, RestartPolicy::default(), move |token| {
+            let game_manager = game_manager.clone();
+            let sender = sender.clone();
+            async move {
+                loop {
+                    // 计算距离上次更新的时间
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs();
+                    let last = game_manager.get_last_profile_update();
+                    let elapsed = now - last;
+
+                    // 如果距离上次更新时间小于间隔，则等待剩余时间
+                    if elapsed < update_interval.as_secs() {
+                        let wait_time = update_interval.as_secs() - elapsed;
+                        tokio::select! {
+                            _ = token.cancelled() => break,
+                            _ = tokio::time::sleep(Duration::from_secs(wait_time)) => {}
+                        }
+                    } else if token.is_cancelled() {
+                        break;
+                    }
+
+                    // 更新所有profiles，就绪探针只跟踪主网络
+                    match game_manager.update_all_profiles().await {
+                        Ok(_) if sender.is_some() => probes::record_profile_cycle(),
+                        Ok(_) => {}
+                        Err(e) => tracing::warn!("Failed to update user profiles: {}", e),
+                    }
+                
+                    // 获取最新的profiles数量
+                    let Some(sender) = &sender else { continue };
+                    if let Ok(count) = game_manager.get_profile_size().await {
+                        if sender.send(count).is_ok() {
+                            tracing::debug!("Profiles count updated: {}", count);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /**
+     * 启动单个网络的好友关系更新任务
+     * 
+     * 参数:
+     * @param tasks - 后台任务管理器
+     * @param name - 任务名称
+     * @param game_manager - 该网络的游戏管理器
+     * @param update_interval - 更新间隔
+     * @param sender - 主网络的关系数量发送器，额外网络为None
+     */
+    fn spawn_relationship_loop(
+        tasks: &TaskManager,
+        name: String,
+        game_manager: Arc<GameManager>,
+        update_interval: Duration,
+        sender: Option<tokio::sync::watch::Sender<u64>>,
+    ) {
+        tasks.supervise(name, RestartPolicy::default(), move |token| {
+            let game_manager = game_manager.clone();
+            let sender = sender.clone();
+            async move {
+                loop {
+                    // 计算距离上次更新的时间
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs();
+                    let last = game_manager.get_last_relationship_update();
+                    let elapsed = now - last;
+
+                    // 如果距离上次更新时间小于间隔，则等待剩余时间
+                    if elapsed < update_interval.as_secs() {
+                        let wait_time = update_interval.as_secs() - elapsed;
+                        tokio::select! {
+                            _ = token.cancelled() => break,
+                            _ = tokio::time::sleep(Duration::from_secs(wait_time)) => {}
+                        }
+                    } else if token.is_cancelled() {
+                        break;
+                    }
+                
+                    // 更新所有好友关系
+                    if let Err(e) = game_manager.update_all_relationships().await {
+                        tracing::warn!("Failed to update relationships: {}", e);
+                    }
+                
+                    // 获取最新的关系数量
+                    let Some(sender) = &sender else { continue };
+                    let count = game_manager.get_relationship_cache_size().await;
+                    if sender.send(count).is_ok() {
+                        tracing::debug!("Relationships count updated: {}", count);
+                    }
+                }
+            }
+        });
+    }
+    
     /**
      * 获取当前Citadel包ID
      * 
@@ -683,16 +884,16 @@ macro_rules! create_metrics {
     }};
 }
 
-/// 初始化日志
+/// 初始化日志，格式、日志文件和模块级别见 `logging` 模块
 pub fn init_tracing_logger() {
-    // 配置日志系统
-    let env_filter = EnvFilter::from_default_env()
-        .add_directive(Level::INFO.into())
-        .add_directive("nautilus_server=debug".parse().unwrap());
-
-    fmt::fmt()
-        .with_env_filter(env_filter)
-        .with_target(true)
-        .init();
+    // 日志在读取其他配置前初始化，需要先加载.env
+    dotenv().ok();
+    let config = logging::LogConfig::from_env();
+    if let Err(e) = logging::init(&config) {
+        // 日志文件不可用时退回控制台输出
+        eprintln!("无法打开日志文件 {:?}: {}", config.file, e);
+        logging::init(&logging::LogConfig { file: None, ..config })
+            .expect("console logging should not fail");
+    }
     info!("Log system initialized");
 }
@@ -0,0 +1,420 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 日志输出配置
+//!
+//! # 概述
+//! 默认在控制台输出便于阅读的文本日志。部署到日志聚合系统时可以改为每行一个JSON对象，
+//! 除时间、级别、模块和事件字段外，当前span链上的 `request_id`、`user_id`、`match_id`
+//! 会提升为顶层字段，内层span的值覆盖外层，便于按请求、用户或对局检索。
+//!
+//! 配置日志文件后日志同时写入文件，文件超过大小上限时轮转：`citadel.log` 重命名为 `citadel.log.1`，
+//! 已有的备份依次后移，超出保留数量的最旧备份被删除。
+//!
+//! 日志级别依次叠加 `RUST_LOG`、默认级别（全局info，本crate为debug）和 `LOG_LEVELS`，
+//! 同一模块以后出现的配置为准。
+//!
+//! # 配置
+//! - `LOG_FORMAT`：`pretty`（默认）或 `json`
+//! - `LOG_LEVELS`：按模块覆盖级别，格式与 `RUST_LOG` 相同，如 `nautilus_server::gaming=trace,hyper=warn`
+//! - `LOG_FILE`：日志文件路径，未设置时只输出到控制台
+//! - `LOG_FILE_MAX_BYTES`：单个日志文件的大小上限，默认100MB
+//! - `LOG_FILE_MAX_FILES`：保留的轮转备份数量，默认5
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{self, FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+/// 提升为JSON顶层字段的span字段
+pub const CONTEXT_FIELDS: [&str; 3] = ["request_id", "user_id", "match_id"];
+
+/// 默认的单个日志文件大小上限
+const DEFAULT_MAX_BYTES: u64 = 100 * 1024 * 1024;
+/// 默认保留的轮转备份数量
+const DEFAULT_MAX_FILES: usize = 5;
+
+/// 日志格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+/// 日志配置
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    /// 按模块覆盖的级别指令
+    pub levels: Vec<String>,
+    pub file: Option<PathBuf>,
+    pub max_bytes: u64,
+    pub max_files: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Pretty,
+            levels: Vec::new(),
+            file: None,
+            max_bytes: DEFAULT_MAX_BYTES,
+            max_files: DEFAULT_MAX_FILES,
+        }
+    }
+}
+
+impl LogConfig {
+    pub fn from_env() -> Self {
+        let var = |key: &str| std::env::var(key).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        let format = match var("LOG_FORMAT").map(|v| v.to_lowercase()).as_deref() {
+            Some("json") => LogFormat::Json,
+            _ => LogFormat::Pretty,
+        };
+        Self {
+            format,
+            levels: var("LOG_LEVELS").map(|v| parse_levels(&v)).unwrap_or_default(),
+            file: var("LOG_FILE").map(PathBuf::from),
+            max_bytes: var("LOG_FILE_MAX_BYTES")
+                .and_then(|v| v.parse().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(DEFAULT_MAX_BYTES),
+            max_files: var("LOG_FILE_MAX_FILES")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MAX_FILES),
+        }
+    }
+
+    /// 按配置构建级别过滤器，无效的指令被忽略
+    pub fn env_filter(&self) -> EnvFilter {
+        let mut filter = EnvFilter::from_default_env()
+            .add_directive(Level::INFO.into())
+            .add_directive("nautilus_server=debug".parse().unwrap());
+        for level in &self.levels {
+            match level.parse() {
+                Ok(directive) => filter = filter.add_directive(directive),
+                Err(e) => eprintln!("忽略无效的日志级别配置 {}: {}", level, e),
+            }
+        }
+        filter
+    }
+}
+
+/// 拆分逗号分隔的级别指令
+fn parse_levels(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/**
+ * 按配置初始化全局日志
+ *
+ * 参数:
+ * @param config - 日志配置
+ *
+ * 返回:
+ * 日志文件无法打开时返回错误，此时不会初始化日志
+ */
+pub fn init(config: &LogConfig) -> io::Result<()> {
+    let mut layers: Vec<Box<dyn Layer<Registry> + Send + Sync>> = vec![ContextLayer.boxed()];
+    layers.push(match config.format {
+        LogFormat::Pretty => fmt::layer().with_target(true).boxed(),
+        LogFormat::Json => fmt::layer().event_format(JsonFormat).boxed(),
+    });
+    if let Some(path) = &config.file {
+        let file = Mutex::new(RotatingFile::open(path, config.max_bytes, config.max_files)?);
+        layers.push(match config.format {
+            LogFormat::Pretty => fmt::layer().with_ansi(false).with_target(true).with_writer(file).boxed(),
+            LogFormat::Json => fmt::layer().event_format(JsonFormat).with_writer(file).boxed(),
+        });
+    }
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(config.env_filter())
+        .init();
+    Ok(())
+}
+
+/// span中记录的上下文字段
+#[derive(Debug, Default, Clone)]
+struct ContextFields(Map<String, Value>);
+
+impl Visit for ContextFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if CONTEXT_FIELDS.contains(&field.name()) {
+            self.0.insert(field.name().to_string(), Value::from(value));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if CONTEXT_FIELDS.contains(&field.name()) {
+            self.0.insert(field.name().to_string(), Value::from(format!("{:?}", value)));
+        }
+    }
+}
+
+/// 在span的扩展中保存上下文字段，供JSON格式读取
+struct ContextLayer;
+
+impl<S> Layer<S> for ContextLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = ContextFields::default();
+        attrs.record(&mut fields);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(fields);
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(fields) = span.extensions_mut().get_mut::<ContextFields>() {
+                values.record(fields);
+            }
+        }
+    }
+}
+
+/// 事件字段，`message` 为日志消息
+#[derive(Default)]
+struct EventFields(Map<String, Value>);
+
+impl Visit for EventFields {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name().to_string(), Value::from(format!("{:?}", value)));
+    }
+}
+
+/// 每行一个JSON对象的日志格式
+struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> std::fmt::Result {
+        let metadata = event.metadata();
+        let mut line = Map::new();
+        line.insert(
+            "timestamp".to_string(),
+            Value::from(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)),
+        );
+        line.insert("level".to_string(), Value::from(metadata.level().as_str()));
+        line.insert("target".to_string(), Value::from(metadata.target()));
+
+        // 从外到内合并span链上的上下文字段
+        if let Some(scope) = ctx.event_scope() {
+            let mut spans = Vec::new();
+            for span in scope.from_root() {
+                if let Some(fields) = span.extensions().get::<ContextFields>() {
+                    line.extend(fields.0.clone());
+                }
+                spans.push(Value::from(span.name()));
+            }
+            line.insert("spans".to_string(), Value::Array(spans));
+        }
+
+        let mut fields = EventFields::default();
+        event.record(&mut fields);
+        if let Some(message) = fields.0.remove("message") {
+            line.insert("message".to_string(), message);
+        }
+        if !fields.0.is_empty() {
+            line.insert("fields".to_string(), Value::Object(fields.0));
+        }
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+/// 按大小轮转的日志文件
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    /**
+     * 打开日志文件，已有内容保留并追加
+     *
+     * 参数:
+     * @param path - 日志文件路径
+     * @param max_bytes - 单个文件的大小上限
+     * @param max_files - 保留的轮转备份数量，为0时轮转后直接丢弃旧文件
+     *
+     * 返回:
+     * 日志文件
+     */
+    pub fn open(path: &Path, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    fn backup(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(self.backup(self.max_files));
+            for index in (1..self.max_files).rev() {
+                let from = self.backup(index);
+                if from.exists() {
+                    fs::rename(&from, self.backup(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.backup(1))?;
+        }
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tracing::info_span;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_context_fields() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::registry()
+            .with(ContextLayer)
+            .with(fmt::layer().event_format(JsonFormat).with_writer(move || writer.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            let _request = info_span!("fetch_key", request_id = "r1", user_id = "u1").entered();
+            let _action = info_span!("match_action", match_id = "m1", user_id = "u2").entered();
+            tracing::info!(attempt = 2, "处理完成");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["message"], "处理完成");
+        assert_eq!(line["request_id"], "r1");
+        assert_eq!(line["match_id"], "m1");
+        // 内层span覆盖外层
+        assert_eq!(line["user_id"], "u2");
+        assert_eq!(line["fields"]["attempt"], 2);
+        assert_eq!(line["spans"], serde_json::json!(["fetch_key", "match_action"]));
+    }
+
+    #[test]
+    fn test_rotating_file() {
+        let dir = std::env::temp_dir().join(format!("logging_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("citadel.log");
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+        for line in ["aaaaaaaa\n", "bbbbbbbb\n", "cccccccc\n", "dddddddd\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "dddddddd\n");
+        assert_eq!(fs::read_to_string(dir.join("citadel.log.1")).unwrap(), "cccccccc\n");
+        assert_eq!(fs::read_to_string(dir.join("citadel.log.2")).unwrap(), "bbbbbbbb\n");
+        assert!(!dir.join("citadel.log.3").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_levels() {
+        assert_eq!(
+            parse_levels(" nautilus_server::gaming=trace, ,hyper=warn"),
+            vec!["nautilus_server::gaming=trace", "hyper=warn"]
+        );
+        let config = LogConfig {
+            levels: vec!["hyper=warn".to_string(), "hyper=loud".to_string()],
+            ..Default::default()
+        };
+        assert!(config.env_filter().to_string().contains("hyper=warn"));
+    }
+}
@@ -9,18 +9,25 @@ use axum::{
 };
 use clap::{Parser, Subcommand};
 use fastcrypto::traits::KeyPair;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tower_http::cors::{Any, CorsLayer};
 use tracing::{debug, info};
 use http::Method;
 use http::header;
 use http::HeaderName;
-use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
+use tower_sessions::{Expiry, SessionManagerLayer};
 use time::Duration;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+use nautilus_server::account_link::register_account_link_routes;
+use nautilus_server::profile_edit::register_profile_edit_routes;
+use nautilus_server::achievement::register_achievement_routes;
 use nautilus_server::app::process_data;
+use nautilus_server::ban::register_ban_routes;
+use nautilus_server::calendar::register_calendar_routes;
+use nautilus_server::cards::register_card_routes;
 use nautilus_server::catastrophe::{
     generate_avatar, 
     handle_create_profile,
@@ -29,11 +36,25 @@ use nautilus_server::catastrophe::{
     register_catastrophe_routes
 };
 use nautilus_server::common::{get_attestation, health_check};
+use nautilus_server::economy::register_economy_routes;
+use nautilus_server::key_audit::register_key_audit_routes;
 use nautilus_server::keys::{handle_fetch_key, handle_get_service};
+use nautilus_server::networks::network_prefix_middleware;
+use nautilus_server::observer::register_observer_routes;
+use nautilus_server::openapi::register_openapi_routes;
 use nautilus_server::ws::register_ws_routes;
 use nautilus_server::{init_tracing_logger, AppState};
+use nautilus_server::probes::register_probe_routes;
+use nautilus_server::passport::register_relationship_routes;
 use nautilus_server::profile::register_profile_routes;
+use nautilus_server::read_only::read_only_middleware;
+use nautilus_server::match_attestation::register_attestation_routes;
+use nautilus_server::match_replay::register_replay_routes;
 use nautilus_server::session_login::{auth_middleware, register_auth_routes};
+use nautilus_server::timeline::register_timeline_routes;
+use nautilus_server::user_search::register_user_search_routes;
+use nautilus_server::session_store::init_session_store;
+use nautilus_server::tasks::{TaskManager, DEFAULT_SHUTDOWN_TIMEOUT};
 
 const DEFAULT_PORT: u16 = 3000;
 
@@ -53,6 +74,9 @@ enum Command {
         /// Server listening port
         #[arg(long, default_value_t = DEFAULT_PORT)]
         port: u16,
+        /// Serve reads only and reject state-mutating requests (read mirror)
+        #[arg(long)]
+        read_only: bool,
     },
 
     /// Run CLI tool
@@ -71,9 +95,13 @@ async fn main() -> Result<()> {
     info!("Parsed command line arguments: {:?}", args);
     match args.command {
         // If no command is specified or the Server command is specified, start the server
-        None | Some(Command::Server { port: _ }) => {
+        None => {
             info!("Starting Nautilus server mode");
-            start_server().await
+            start_server(false).await
+        }
+        Some(Command::Server { port: _, read_only }) => {
+            info!("Starting Nautilus server mode");
+            start_server(read_only).await
         }
 
         // If a CLI command is specified, run CLI functionality
@@ -85,15 +113,37 @@ async fn main() -> Result<()> {
 }
 
 /// Start server functionality
-async fn start_server() -> Result<()> {
+async fn start_server(read_only: bool) -> Result<()> {
+    // 只读模式需要在好友同步等后台任务之前确定
+    nautilus_server::read_only::init_read_only(read_only);
+    // 持久化存储需要在Profile更新任务和审计日志之前初始化
+    nautilus_server::storage::init_storage().await?;
+    // 封禁需要在接受登录和连接之前从持久化存储恢复
+    nautilus_server::ban::init_ban_service().await?;
     let mut state = AppState::new().await;
     AppState::spawn_profile_updater(&mut state, None).await;
     AppState::spawn_relationship_updater(&mut state, None).await;
     AppState::spawn_latest_checkpoint_timestamp_updater(&mut state, None).await;
     AppState::spawn_reference_gas_price_updater(&mut state, None).await;
     AppState::spawn_package_id_updater(&mut state, None).await;
+    nautilus_server::ptb_policy::init_policy_engine()?;
+    let key_audit = nautilus_server::key_audit::init_key_audit_service()?;
+    let restored = key_audit.audit_log.restore().await;
+    if restored > 0 {
+        info!("从持久化存储恢复了 {} 条密钥审计记录", restored);
+    }
+    nautilus_server::replay_guard::init_replay_guard();
+    nautilus_server::key_queue::init_key_queue();
+    nautilus_server::user_search::init_user_search();
+    nautilus_server::profile_guard::spawn_pruner();
 
+    let tasks = state.tasks.clone();
     let state_arc = Arc::new(state);
+    nautilus_server::event_signing::init_event_signer(state_arc.clone());
+    nautilus_server::hand_seal::init_hand_sealer(&state_arc)?;
+    nautilus_server::match_attestation::init_match_attestor(state_arc.clone());
+    nautilus_server::session_attestation::init_session_attestation(state_arc.clone());
+    nautilus_server::friend_sync::init_friend_sync(state_arc.clone());
 
     // Define CORS strategy
     let cors = CorsLayer::new()
@@ -105,6 +155,7 @@ async fn start_server() -> Result<()> {
             HeaderName::from_static("request-id"),
             HeaderName::from_static("client-sdk-type"),
             HeaderName::from_static("client-sdk-version"),
+            HeaderName::from_static("x-sui-network"),
         ])
         .allow_origin([
             "http://127.0.0.1:5173".parse().unwrap(),
@@ -112,8 +163,9 @@ async fn start_server() -> Result<()> {
         ])
         .allow_credentials(true);
 
-    // 创建 session store
-    let session_store = MemoryStore::default();
+    // 创建 session store，类型由 SESSION_STORE 环境变量决定
+    let session_store = init_session_store().await?;
+    session_store.spawn_expired_cleanup(None);
     let session_layer = SessionManagerLayer::new(session_store)
         .with_secure(true)
         .with_expiry(Expiry::OnInactivity(Duration::days(1))); // 设置为24小时
@@ -128,6 +180,22 @@ async fn start_server() -> Result<()> {
     let public_routes = register_auth_routes(public_routes);
     let public_routes = register_profile_routes(public_routes);
     let public_routes = register_catastrophe_routes(public_routes);
+    let public_routes = register_achievement_routes(public_routes);
+    let public_routes = register_economy_routes(public_routes);
+    let public_routes = register_timeline_routes(public_routes);
+    let public_routes = register_attestation_routes(public_routes);
+    let public_routes = register_user_search_routes(public_routes);
+    let public_routes = register_relationship_routes(public_routes);
+    let public_routes = register_account_link_routes(public_routes);
+    let public_routes = register_profile_edit_routes(public_routes);
+    let public_routes = register_key_audit_routes(public_routes);
+    let public_routes = register_replay_routes(public_routes);
+    let public_routes = register_ban_routes(public_routes);
+    let public_routes = register_calendar_routes(public_routes);
+    let public_routes = register_card_routes(public_routes);
+    let public_routes = register_observer_routes(public_routes);
+    let public_routes = register_probe_routes(public_routes);
+    let public_routes = register_openapi_routes(public_routes);
 
     // Configure protected routes that require JWT authentication
     let protected_routes = Router::new()
@@ -148,14 +216,51 @@ async fn start_server() -> Result<()> {
     info!("Server started, WebSocket and Profile functionality integrated");
     // integrate cors and session
     let app = app
+        .layer(middleware::from_fn(read_only_middleware)) // 只读镜像拒绝修改状态的请求
         .layer(session_layer) // 添加 session 支持
         .layer(cors) // 添加 CORS 支持
         .layer(TraceLayer::new_for_http());
-    serve(app).await
+    // 在路由之前把 /net/<name>/ 前缀转换为网络请求头
+    let app = Router::new()
+        .fallback_service(app)
+        .layer(middleware::from_fn(network_prefix_middleware));
+    serve(app, tasks).await
+}
+
+/// 等待关闭信号（Ctrl+C或SIGTERM）
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("监听Ctrl+C信号失败: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("监听SIGTERM信号失败: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!("收到关闭信号，开始关闭服务");
 }
 
 /// Start server
-pub async fn serve(app: Router) -> Result<()> {
+pub async fn serve(app: Router, tasks: Arc<TaskManager>) -> Result<()> {
     debug!("listening on http://localhost:{}", DEFAULT_PORT);
     // Start server
     let listener = tokio::net::TcpListener::bind(&format!("0.0.0.0:{}", DEFAULT_PORT))
@@ -172,7 +277,12 @@ pub async fn serve(app: Router) -> Result<()> {
  ░▒▓██████▓▒░░▒▓█▓▒░  ░▒▓█▓▒░  ░▒▓█▓▒░░▒▓█▓▒░▒▓███████▓▒░░▒▓████████▓▒░▒▓████████▓▒░ 
 🚀 Server is ready to launch at http://localhost:{}! 🚀\n", listener.local_addr().unwrap().port()); //端口可能会变!
         // Start server
-    axum::serve(listener, app)
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal())
         .await
-        .map_err(|e| anyhow::anyhow!("Server Launch Error: {}", e))
+        .map_err(|e| anyhow::anyhow!("Server Launch Error: {}", e))?;
+    // 停止定期更新等后台任务
+    tasks.shutdown(DEFAULT_SHUTDOWN_TIMEOUT).await;
+    info!("服务已关闭");
+    Ok(())
 }
@@ -0,0 +1,455 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 对局结果链上存证
+//!
+//! # 概述
+//! 签名的对局结果（见 `event_signing`）只随胜利事件发给房间内的客户端，排行榜和第三方无法事后获取。
+//! 启用存证后，对局结束时服务器构建一份精简的对局结果并签名，通过 `sdk::executor` 调用
+//! Citadel合约的 `attest_match_result` 写到链上：
+//! - 结果包含对局ID、玩家、胜利者、每名玩家的评分变化和完整动作历史的哈希
+//! - 使用临时密钥对（`eph_kp`）签名，意图为 `IntentScope::MatchAttestation`
+//! - 合约用 `ed25519_verify` 校验签名后创建冻结的 `MatchAttestation` 对象，并发出 `MatchResultAttested` 事件
+//!
+//! 验证方按 `IntentMessage { intent: 4, timestamp_ms, data }` 的BCS编码重建签名消息，
+//! 与链上对象的 `message` 字段比较，再用其中的 `public_key` 校验签名。
+//! 动作历史哈希为完整动作历史（包括已归档的动作）JSON编码的Blake2b256，可以与时间线或重放结果对照。
+//!
+//! 提交在后台进行，失败时按固定间隔重试。提交记录只保存在内存中，最多保留最近的
+//! `MAX_RECORDS` 场，重启后无法再通过接口查询，链上对象不受影响。
+//!
+//! # 配置
+//! - `MATCH_ATTESTATION_ENABLED`：是否在对局结束时提交存证，默认false
+//! - `MATCH_ATTESTATION_MAX_ATTEMPTS`：提交的最大尝试次数，默认3
+//! - `MATCH_ATTESTATION_RETRY_SECS`：重试间隔（秒），默认10
+//!
+//! # 接口
+//! - `GET /v1/matches/:match_id/attestation`：签名的对局结果和提交状态
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    routing::get,
+    Json, Router,
+};
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::hash::{Blake2b256, HashFunction};
+use fastcrypto::traits::{KeyPair, ToFromBytes};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+use crate::common::{to_signed_response, IntentScope};
+use crate::errors::{ErrorResponse, InternalError};
+use crate::externals::current_epoch_time;
+use crate::gaming::{CardAction, MatchData};
+use crate::sdk::executor;
+use crate::stats::RatingChange;
+use crate::AppState;
+
+/// 内存中保留的提交记录数
+pub const MAX_RECORDS: usize = 1000;
+/// 默认最大尝试次数
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+/// 默认重试间隔（秒）
+const DEFAULT_RETRY_SECS: u64 = 10;
+
+/// 链上存证的对局结果
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchAttestation {
+    pub match_id: String,
+    /// 所有玩家ID，先是未出局的玩家，再按出局顺序排列
+    pub players: Vec<String>,
+    pub winner_id: String,
+    /// 与 `players` 一一对应的评分变化，没有评分变化的玩家为0
+    pub rating_deltas: Vec<i32>,
+    /// 完整动作历史的Blake2b256哈希
+    pub history_hash: Vec<u8>,
+    /// 对局结束时间（毫秒）
+    pub completed_at: u64,
+}
+
+impl MatchAttestation {
+    /**
+     * 从已结束的对局构建存证内容
+     *
+     * 参数:
+     * @param match_data - 已结束的对局，动作历史需要包括已归档的部分
+     * @param winner_id - 胜利者ID
+     * @param rating_changes - 本局的评分变化
+     *
+     * 返回:
+     * 存证内容
+     */
+    pub fn from_match(match_data: &MatchData, winner_id: &str, rating_changes: &[RatingChange]) -> Self {
+        let players: Vec<String> = match_data
+            .players
+            .iter()
+            .chain(match_data.out.iter())
+            .map(|p| p.user.id.clone())
+            .collect();
+        let rating_deltas = players
+            .iter()
+            .map(|id| {
+                rating_changes
+                    .iter()
+                    .find(|c| &c.user_id == id)
+                    .map(|c| c.rating_after - c.rating_before)
+                    .unwrap_or(0)
+            })
+            .collect();
+        Self {
+            match_id: match_data.id.clone(),
+            players,
+            winner_id: winner_id.to_string(),
+            rating_deltas,
+            history_hash: history_hash(&match_data.action_history),
+            completed_at: match_data.updated_at,
+        }
+    }
+}
+
+/// 动作历史JSON编码的Blake2b256哈希
+pub fn history_hash(actions: &[CardAction]) -> Vec<u8> {
+    let bytes = serde_json::to_vec(actions).unwrap_or_default();
+    Blake2b256::digest(&bytes).to_vec()
+}
+
+/// 存证的提交状态
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AttestationStatus {
+    /// 等待提交或正在重试
+    Pending,
+    /// 已写到链上
+    Submitted,
+    /// 重试次数用尽
+    Failed,
+}
+
+/// 一场对局的存证记录
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationRecord {
+    pub attestation: MatchAttestation,
+    /// 签名消息中的时间戳（毫秒）
+    pub timestamp_ms: u64,
+    /// Hex编码的Ed25519签名
+    pub signature: String,
+    /// Hex编码的签名公钥
+    pub public_key: String,
+    pub status: AttestationStatus,
+    /// 已尝试提交的次数
+    pub attempts: u32,
+    /// 存证交易的摘要
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    /// 最近一次提交失败的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 存证配置
+#[derive(Debug, Clone)]
+pub struct AttestationConfig {
+    pub max_attempts: u32,
+    pub retry_delay: Duration,
+}
+
+impl AttestationConfig {
+    pub fn from_env() -> Self {
+        let parse = |key: &str| std::env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok()).filter(|v| *v > 0);
+        Self {
+            max_attempts: parse("MATCH_ATTESTATION_MAX_ATTEMPTS").map(|v| v as u32).unwrap_or(DEFAULT_MAX_ATTEMPTS),
+            retry_delay: Duration::from_secs(parse("MATCH_ATTESTATION_RETRY_SECS").unwrap_or(DEFAULT_RETRY_SECS)),
+        }
+    }
+}
+
+/// 按提交顺序保留的存证记录
+#[derive(Default)]
+struct Records {
+    by_match: HashMap<String, AttestationRecord>,
+    order: VecDeque<String>,
+}
+
+impl Records {
+    fn insert(&mut self, record: AttestationRecord) {
+        let match_id = record.attestation.match_id.clone();
+        if self.by_match.insert(match_id.clone(), record).is_none() {
+            self.order.push_back(match_id);
+        }
+        while self.order.len() > MAX_RECORDS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.by_match.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// 对局结果存证服务
+pub struct MatchAttestor {
+    state: Arc<AppState>,
+    config: AttestationConfig,
+    records: Mutex<Records>,
+}
+
+static GLOBAL_MATCH_ATTESTOR: OnceCell<Arc<MatchAttestor>> = OnceCell::new();
+
+/// 是否启用了存证
+fn attestation_enabled() -> bool {
+    std::env::var("MATCH_ATTESTATION_ENABLED")
+        .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+/**
+ * 初始化全局存证服务
+ *
+ * 未设置 `MATCH_ATTESTATION_ENABLED` 时不启用存证
+ *
+ * 参数:
+ * @param state - 应用状态
+ *
+ * 返回:
+ * 是否启用了存证
+ */
+pub fn init_match_attestor(state: Arc<AppState>) -> bool {
+    if !attestation_enabled() {
+        return false;
+    }
+    let attestor = MatchAttestor {
+        state,
+        config: AttestationConfig::from_env(),
+        records: Mutex::new(Records::default()),
+    };
+    let _ = GLOBAL_MATCH_ATTESTOR.set(Arc::new(attestor));
+    info!("已启用对局结果链上存证");
+    true
+}
+
+/// 获取全局存证服务，未启用时返回None
+pub fn global_match_attestor() -> Option<Arc<MatchAttestor>> {
+    GLOBAL_MATCH_ATTESTOR.get().cloned()
+}
+
+impl MatchAttestor {
+    /**
+     * 签名对局结果并在后台提交到链上
+     *
+     * 参数:
+     * @param attestation - 存证内容
+     */
+    pub fn submit(self: &Arc<Self>, attestation: MatchAttestation) {
+        let signed = to_signed_response(&self.state.eph_kp, attestation.clone(), current_epoch_time(), IntentScope::MatchAttestation);
+        let message = match bcs::to_bytes(&signed.response) {
+            Ok(message) => message,
+            Err(e) => {
+                error!("序列化对局 {} 的存证消息失败: {}", attestation.match_id, e);
+                return;
+            }
+        };
+        let record = AttestationRecord {
+            timestamp_ms: signed.response.timestamp_ms,
+            signature: signed.signature,
+            public_key: Hex::encode(self.state.eph_kp.public().as_bytes()),
+            attestation,
+            status: AttestationStatus::Pending,
+            attempts: 0,
+            digest: None,
+            error: None,
+        };
+        self.records.lock().insert(record.clone());
+
+        let attestor = self.clone();
+        tokio::spawn(async move {
+            attestor.run_submission(record, message).await;
+        });
+    }
+
+    async fn run_submission(&self, record: AttestationRecord, message: Vec<u8>) {
+        let match_id = record.attestation.match_id.clone();
+        let (Ok(signature), Ok(public_key)) = (Hex::decode(&record.signature), Hex::decode(&record.public_key)) else {
+            self.update(&match_id, |r| {
+                r.status = AttestationStatus::Failed;
+                r.error = Some("签名编码无效".to_string());
+            });
+            return;
+        };
+        for attempt in 1..=self.config.max_attempts {
+            let result =
+                executor::attest_match_result(&self.state, &record.attestation, &message, &signature, &public_key).await;
+            match result {
+                Ok(response) => {
+                    let digest = response.digest.to_string();
+                    info!("对局 {} 的结果已存证，交易: {}", match_id, digest);
+                    self.update(&match_id, |r| {
+                        r.status = AttestationStatus::Submitted;
+                        r.attempts = attempt;
+                        r.digest = Some(digest);
+                        r.error = None;
+                    });
+                    return;
+                }
+                Err(e) => {
+                    warn!("对局 {} 的结果存证失败（第{}次）: {}", match_id, attempt, e);
+                    let last = attempt == self.config.max_attempts;
+                    self.update(&match_id, |r| {
+                        r.attempts = attempt;
+                        r.error = Some(e.to_string());
+                        if last {
+                            r.status = AttestationStatus::Failed;
+                        }
+                    });
+                    if !last {
+                        tokio::time::sleep(self.config.retry_delay).await;
+                    }
+                }
+            }
+        }
+        error!("对局 {} 的结果存证重试次数用尽", match_id);
+    }
+
+    fn update(&self, match_id: &str, f: impl FnOnce(&mut AttestationRecord)) {
+        if let Some(record) = self.records.lock().by_match.get_mut(match_id) {
+            f(record);
+        }
+    }
+
+    /// 获取对局的存证记录
+    pub fn record(&self, match_id: &str) -> Option<AttestationRecord> {
+        self.records.lock().by_match.get(match_id).cloned()
+    }
+}
+
+/**
+ * 对局结束时提交存证，未启用存证时不做任何处理
+ *
+ * 参数:
+ * @param match_data - 已结束的对局，动作历史需要包括已归档的部分
+ * @param winner_id - 胜利者ID
+ * @param rating_changes - 本局的评分变化
+ */
+pub fn attest_match(match_data: &MatchData, winner_id: &str, rating_changes: &[RatingChange]) {
+    if let Some(attestor) = global_match_attestor() {
+        attestor.submit(MatchAttestation::from_match(match_data, winner_id, rating_changes));
+    }
+}
+
+/// 获取对局存证
+#[utoipa::path(
+    get,
+    path = "/v1/matches/{match_id}/attestation",
+    tag = "matches",
+    params(("match_id" = String, Path, description = "对局ID")),
+    responses(
+        (status = 200, description = "签名的对局结果和链上提交状态", body = AttestationRecord),
+        (status = 404, description = "对局没有存证记录", body = ErrorResponse),
+    )
+)]
+pub async fn handle_get_match_attestation(
+    State(app_state): State<Arc<AppState>>,
+    Path(match_id): Path<String>,
+) -> Result<Json<AttestationRecord>, InternalError> {
+    app_state.metrics.observe_request("get_match_attestation");
+    global_match_attestor()
+        .and_then(|attestor| attestor.record(&match_id))
+        .map(Json)
+        .ok_or(InternalError::MatchNotFound)
+}
+
+/// 注册对局存证路由
+pub fn register_attestation_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router.route("/v1/matches/:match_id/attestation", get(handle_get_match_attestation))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::IntentMessage;
+    use crate::event_signing::verify_signed_event;
+    use crate::gaming::{CardActionType, MatchType, UserInfo};
+
+    fn completed_match() -> MatchData {
+        let players = ["alice", "bob", "carol"]
+            .iter()
+            .map(|id| UserInfo {
+                id: id.to_string(),
+                name: id.to_string(),
+                rating: 1000,
+                avatar_url: None,
+                provisional: false,
+            })
+            .collect::<Vec<_>>();
+        let mut match_data = MatchData::new("m1".to_string(), MatchType::Private, &players, 0);
+        let carol = match_data.players.remove(2);
+        match_data.out.push(carol);
+        match_data.action_history.push(CardAction {
+            action_type: CardActionType::Draw,
+            user_id: "alice".to_string(),
+            card_id: None,
+            card_type: None,
+            is_canceled: false,
+            created_at: 1,
+        });
+        match_data.updated_at = 10;
+        match_data
+    }
+
+    #[test]
+    fn test_attestation_from_match() {
+        let match_data = completed_match();
+        let changes = vec![RatingChange {
+            user_id: "alice".to_string(),
+            rating_before: 1000,
+            rating_after: 1016,
+            modifiers: Vec::new(),
+        }];
+        let attestation = MatchAttestation::from_match(&match_data, "alice", &changes);
+        assert_eq!(attestation.players, vec!["alice", "bob", "carol"]);
+        assert_eq!(attestation.rating_deltas, vec![16, 0, 0]);
+        assert_eq!(attestation.history_hash.len(), 32);
+
+        // 动作历史不同时哈希不同
+        let mut other = match_data.clone();
+        other.action_history[0].user_id = "bob".to_string();
+        assert_ne!(history_hash(&other.action_history), attestation.history_hash);
+
+        // 签名可以用公开的临时公钥验证，合约校验的是同一份BCS消息
+        let kp = AppState::generate_keypair(Some(7));
+        let signed = to_signed_response(&kp, attestation.clone(), 20, IntentScope::MatchAttestation);
+        assert!(verify_signed_event(kp.public(), &signed));
+        let rebuilt = IntentMessage::new(attestation, 20, IntentScope::MatchAttestation);
+        assert_eq!(bcs::to_bytes(&rebuilt).unwrap(), bcs::to_bytes(&signed.response).unwrap());
+    }
+
+    #[test]
+    fn test_records_are_bounded() {
+        let attestation = MatchAttestation::from_match(&completed_match(), "alice", &[]);
+        let mut records = Records::default();
+        for i in 0..MAX_RECORDS + 5 {
+            records.insert(AttestationRecord {
+                attestation: MatchAttestation {
+                    match_id: format!("m{}", i),
+                    ..attestation.clone()
+                },
+                timestamp_ms: 0,
+                signature: String::new(),
+                public_key: String::new(),
+                status: AttestationStatus::Pending,
+                attempts: 0,
+                digest: None,
+                error: None,
+            });
+        }
+        assert_eq!(records.by_match.len(), MAX_RECORDS);
+        assert!(!records.by_match.contains_key("m0"));
+        assert!(records.by_match.contains_key(&format!("m{}", MAX_RECORDS + 4)));
+    }
+}
@@ -0,0 +1,523 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 对局状态增量同步模块
+//!
+//! # 概述
+//! 每次对局状态变化时广播完整的MatchData代价很高（牌堆、所有手牌、动作历史），
+//! 四人对局加上观战者时尤为明显。本模块按观看者记录每个对局最近一次下发的视图，
+//! 计算新视图与之相比的增量：
+//! - 普通字段：只下发变化的字段
+//! - 牌堆：只下发剩余张数
+//! - 弃牌堆：只追加时下发新增部分，否则整体替换
+//! - 动作历史：只下发新增部分，较早的动作被归档时客户端根据 `history_summary` 移除对应数量
+//! - 玩家：只下发变化的字段，手牌只下发张数及增减数量
+//!
+//! 视图由游戏模式生成：观众使用 `public_view`，玩家使用自己的 `private_view`，
+//! 快照中只会出现观看者自己的手牌，其他玩家的手牌只有张数。
+//!
+//! 每隔固定数量的增量会下发一次完整快照，客户端也可以随时请求快照来重新同步。
+//! 每条更新都带有递增的序号，增量还带有基准序号，客户端发现序号不连续时应发送
+//! `match:state_sync` 请求快照。
+//!
+//! 只有协商了 `match_delta` 能力的客户端才会收到 `match:state` 事件，
+//! 这些客户端收到的加入/离开/开始/结束事件也不再携带完整的MatchData。
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::game_mode;
+use crate::gaming::MatchData;
+
+/// 每隔多少个增量下发一次完整快照
+pub const SNAPSHOT_INTERVAL: u32 = 20;
+
+/// 单独处理的字段，不参与普通字段比较
+const SPECIAL_FIELDS: &[&str] = &["deck", "deck_count", "discard_pile", "action_history", "players", "out"];
+
+/// 玩家状态增量
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerDelta {
+    pub user_id: String,
+    /// 变化的字段（不包括手牌）
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub changed: Map<String, Value>,
+    /// 当前手牌张数（有变化时）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hand_count: Option<usize>,
+    /// 新增手牌数量
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub cards_added: usize,
+    /// 移除手牌数量
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub cards_removed: usize,
+}
+
+/// 对局状态增量
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchDelta {
+    /// 变化的普通字段
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub changed: Map<String, Value>,
+    /// 牌堆剩余张数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deck_count: Option<usize>,
+    /// 弃牌堆新增的牌
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub discard_added: Vec<Value>,
+    /// 弃牌堆被整体替换时的新内容
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discard_pile: Option<Vec<Value>>,
+    /// 新增的动作记录
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions_added: Vec<Value>,
+    /// 在场玩家顺序（有变化时）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player_order: Option<Vec<String>>,
+    /// 出局玩家顺序（有变化时）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub out_order: Option<Vec<String>>,
+    /// 状态有变化的玩家
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub players: Vec<PlayerDelta>,
+}
+
+impl MatchDelta {
+    /// 是否没有任何变化
+    pub fn is_empty(&self) -> bool {
+        *self == MatchDelta::default()
+    }
+}
+
+/// 状态更新（快照或增量）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum StateUpdate {
+    /// 完整快照
+    #[serde(rename_all = "camelCase")]
+    Snapshot { match_id: String, seq: u64, state: Value },
+    /// 增量
+    #[serde(rename_all = "camelCase")]
+    Delta { match_id: String, seq: u64, base_seq: u64, delta: MatchDelta },
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value.get(key).and_then(|v| v.as_array()).map(|v| v.as_slice()).unwrap_or(&[])
+}
+
+/// 已归档的动作数，见 `match_history::HistorySummary`
+fn archived_actions(value: &Value) -> usize {
+    value
+        .get("history_summary")
+        .and_then(|s| s.get("archived_actions"))
+        .and_then(|n| n.as_u64())
+        .unwrap_or(0) as usize
+}
+
+fn player_id(player: &Value) -> Option<String> {
+    player
+        .get("user")
+        .and_then(|u| u.get("id"))
+        .and_then(|id| id.as_str())
+        .map(|id| id.to_string())
+}
+
+/// 视图中的牌堆张数，裁剪后的视图只有 `deck_count`
+fn deck_count(state: &Value) -> usize {
+    state
+        .get("deck_count")
+        .and_then(|n| n.as_u64())
+        .map(|n| n as usize)
+        .unwrap_or_else(|| array(state, "deck").len())
+}
+
+/// 视图中的手牌张数，其他玩家的手牌被裁剪后只有 `hand_count`
+fn hand_count(player: &Value) -> usize {
+    player
+        .get("hand_count")
+        .and_then(|n| n.as_u64())
+        .map(|n| n as usize)
+        .unwrap_or_else(|| array(player, "hand").len())
+}
+
+fn card_ids(player: &Value) -> HashSet<String> {
+    array(player, "hand")
+        .iter()
+        .filter_map(|card| card.get("id").and_then(|id| id.as_str()).map(|id| id.to_string()))
+        .collect()
+}
+
+/// 计算单个玩家的增量，None表示没有变化
+fn diff_player(user_id: &str, prev: Option<&Value>, next: &Value) -> Option<PlayerDelta> {
+    let mut delta = PlayerDelta {
+        user_id: user_id.to_string(),
+        ..Default::default()
+    };
+
+    if let Some(fields) = next.as_object() {
+        for (key, value) in fields {
+            if key == "hand" || key == "hand_count" {
+                continue;
+            }
+            if prev.and_then(|p| p.get(key)) != Some(value) {
+                delta.changed.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    let prev_count = prev.map(hand_count).unwrap_or(0);
+    let next_count = hand_count(next);
+    if next.get("hand_count").is_some() {
+        // 看不到手牌时只能按张数推算增减
+        delta.cards_added = next_count.saturating_sub(prev_count);
+        delta.cards_removed = prev_count.saturating_sub(next_count);
+    } else {
+        let prev_cards = prev.map(card_ids).unwrap_or_default();
+        let next_cards = card_ids(next);
+        delta.cards_added = next_cards.difference(&prev_cards).count();
+        delta.cards_removed = prev_cards.difference(&next_cards).count();
+    }
+    if prev.is_none() || delta.cards_added > 0 || delta.cards_removed > 0 {
+        delta.hand_count = Some(next_count);
+    }
+
+    if delta.changed.is_empty() && delta.hand_count.is_none() {
+        None
+    } else {
+        Some(delta)
+    }
+}
+
+/// 若next以prev为前缀，返回新增部分
+fn appended<'a>(prev: &[Value], next: &'a [Value]) -> Option<&'a [Value]> {
+    if next.len() >= prev.len() && next[..prev.len()] == *prev {
+        Some(&next[prev.len()..])
+    } else {
+        None
+    }
+}
+
+/**
+ * 计算两个对局状态之间的增量
+ *
+ * 参数:
+ * @param prev - 上一次下发的状态（MatchData的JSON表示或游戏模式生成的视图）
+ * @param next - 当前状态
+ *
+ * 返回:
+ * 状态增量，没有变化时为空
+ */
+pub fn compute_delta(prev: &Value, next: &Value) -> MatchDelta {
+    let mut delta = MatchDelta::default();
+
+    // 普通字段
+    if let Some(fields) = next.as_object() {
+        for (key, value) in fields {
+            if SPECIAL_FIELDS.contains(&key.as_str()) {
+                continue;
+            }
+            if prev.get(key) != Some(value) {
+                delta.changed.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    // 牌堆只同步张数
+    let prev_deck = deck_count(prev);
+    let next_deck = deck_count(next);
+    if prev_deck != next_deck || prev.get("deck").is_none() {
+        delta.deck_count = Some(next_deck);
+    }
+
+    // 弃牌堆
+    let prev_discard = array(prev, "discard_pile");
+    let next_discard = array(next, "discard_pile");
+    match appended(prev_discard, next_discard) {
+        Some(added) => delta.discard_added = added.to_vec(),
+        None => delta.discard_pile = Some(next_discard.to_vec()),
+    }
+
+    // 动作历史只追加，压缩归档后按归档的动作数对齐，客户端根据 `history_summary` 移除已归档的部分
+    let prev_history = array(prev, "action_history");
+    let next_history = array(next, "action_history");
+    let added = appended(prev_history, next_history).or_else(|| {
+        let shift = archived_actions(next).checked_sub(archived_actions(prev))?;
+        appended(prev_history.get(shift..)?, next_history)
+    });
+    if let Some(added) = added {
+        delta.actions_added = added.to_vec();
+    }
+
+    // 玩家
+    let prev_players = array(prev, "players")
+        .iter()
+        .chain(array(prev, "out").iter())
+        .filter_map(|p| player_id(p).map(|id| (id, p)))
+        .collect::<HashMap<_, _>>();
+
+    for key in ["players", "out"] {
+        let prev_order = array(prev, key).iter().filter_map(player_id).collect::<Vec<_>>();
+        let next_order = array(next, key).iter().filter_map(player_id).collect::<Vec<_>>();
+        if prev_order != next_order {
+            if key == "players" {
+                delta.player_order = Some(next_order);
+            } else {
+                delta.out_order = Some(next_order);
+            }
+        }
+
+        for player in array(next, key) {
+            if let Some(user_id) = player_id(player) {
+                if let Some(player_delta) = diff_player(&user_id, prev_players.get(&user_id).copied(), player) {
+                    delta.players.push(player_delta);
+                }
+            }
+        }
+    }
+
+    delta
+}
+
+/// 单个观看者的同步状态
+#[derive(Debug, Clone)]
+struct TrackedMatch {
+    seq: u64,
+    last: Value,
+    deltas_since_snapshot: u32,
+}
+
+/// 对局状态增量跟踪器，每个对局的观众共用一条序列，每名玩家各自一条
+#[derive(Debug, Default)]
+pub struct MatchDeltaTracker {
+    matches: HashMap<String, HashMap<Option<String>, TrackedMatch>>,
+}
+
+/**
+ * 观看者看到的对局视图
+ *
+ * 参数:
+ * @param match_data - 对局数据
+ * @param viewer - 玩家ID，观众为None
+ *
+ * 返回:
+ * 玩家的 `private_view` 或观众的 `public_view`
+ */
+pub fn viewer_state(match_data: &MatchData, viewer: Option<&str>) -> anyhow::Result<Value> {
+    let mode = game_mode::mode_for(match_data);
+    match viewer {
+        Some(user_id) => mode.private_view(match_data, user_id),
+        None => mode.public_view(match_data),
+    }
+}
+
+impl MatchDeltaTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 为观看者生成下一条状态更新，状态没有变化时返回None
+    pub fn next_update(&mut self, match_data: &MatchData, viewer: Option<&str>) -> anyhow::Result<Option<StateUpdate>> {
+        let state = viewer_state(match_data, viewer)?;
+
+        let Some(tracked) = self.tracked_mut(&match_data.id, viewer) else {
+            return Ok(Some(self.snapshot_value(&match_data.id, viewer, state)));
+        };
+
+        if tracked.deltas_since_snapshot >= SNAPSHOT_INTERVAL {
+            return Ok(Some(self.snapshot_value(&match_data.id, viewer, state)));
+        }
+
+        let delta = compute_delta(&tracked.last, &state);
+        if delta.is_empty() {
+            return Ok(None);
+        }
+
+        let base_seq = tracked.seq;
+        tracked.seq += 1;
+        tracked.deltas_since_snapshot += 1;
+        tracked.last = state;
+
+        Ok(Some(StateUpdate::Delta {
+            match_id: match_data.id.clone(),
+            seq: tracked.seq,
+            base_seq,
+            delta,
+        }))
+    }
+
+    /// 获取最近一次下发给观看者的状态快照（不推进序号），用于单个客户端重新同步
+    pub fn current(&self, match_id: &str, viewer: Option<&str>) -> Option<StateUpdate> {
+        self.matches
+            .get(match_id)
+            .and_then(|viewers| viewers.get(&viewer.map(|v| v.to_string())))
+            .map(|tracked| StateUpdate::Snapshot {
+                match_id: match_id.to_string(),
+                seq: tracked.seq,
+                state: tracked.last.clone(),
+            })
+    }
+
+    fn tracked_mut(&mut self, match_id: &str, viewer: Option<&str>) -> Option<&mut TrackedMatch> {
+        self.matches
+            .get_mut(match_id)
+            .and_then(|viewers| viewers.get_mut(&viewer.map(|v| v.to_string())))
+    }
+
+    fn snapshot_value(&mut self, match_id: &str, viewer: Option<&str>, state: Value) -> StateUpdate {
+        let tracked = self
+            .matches
+            .entry(match_id.to_string())
+            .or_default()
+            .entry(viewer.map(|v| v.to_string()))
+            .or_insert(TrackedMatch {
+                seq: 0,
+                last: Value::Null,
+                deltas_since_snapshot: 0,
+            });
+        tracked.seq += 1;
+        tracked.deltas_since_snapshot = 0;
+        tracked.last = state.clone();
+
+        StateUpdate::Snapshot {
+            match_id: match_id.to_string(),
+            seq: tracked.seq,
+            state,
+        }
+    }
+
+    /// 对局结束后移除所有观看者的跟踪状态
+    pub fn remove(&mut self, match_id: &str) {
+        self.matches.remove(match_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn state(deck: usize, hand: &[&str], turn_index: u64) -> Value {
+        json!({
+            "id": "m1",
+            "turn_index": turn_index,
+            "deck": (0..deck).map(|i| json!({ "id": format!("d{}", i) })).collect::<Vec<_>>(),
+            "discard_pile": [],
+            "action_history": [],
+            "players": [
+                { "user": { "id": "u1" }, "hand": hand.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(), "is_turn": true },
+                { "user": { "id": "u2" }, "hand": [], "is_turn": false }
+            ],
+            "out": []
+        })
+    }
+
+    #[test]
+    fn test_draw_card_delta() {
+        let prev = state(10, &["a", "b"], 0);
+        let next = state(9, &["a", "b", "c"], 0);
+        let delta = compute_delta(&prev, &next);
+
+        assert!(delta.changed.is_empty());
+        assert_eq!(delta.deck_count, Some(9));
+        assert!(delta.player_order.is_none());
+        assert_eq!(delta.players.len(), 1);
+        assert_eq!(delta.players[0].user_id, "u1");
+        assert_eq!(delta.players[0].hand_count, Some(3));
+        assert_eq!(delta.players[0].cards_added, 1);
+        assert_eq!(delta.players[0].cards_removed, 0);
+    }
+
+    #[test]
+    fn test_unchanged_state_is_empty() {
+        let prev = state(10, &["a"], 1);
+        assert!(compute_delta(&prev, &prev.clone()).is_empty());
+
+        let next = state(10, &["a"], 2);
+        let delta = compute_delta(&prev, &next);
+        assert_eq!(delta.changed.get("turn_index"), Some(&json!(2)));
+        assert!(delta.players.is_empty());
+    }
+
+    #[test]
+    fn test_player_moved_out() {
+        let prev = state(5, &["a"], 0);
+        let mut next = prev.clone();
+        let player = next["players"].as_array_mut().unwrap().remove(1);
+        next["out"].as_array_mut().unwrap().push(player);
+
+        let delta = compute_delta(&prev, &next);
+        assert_eq!(delta.player_order, Some(vec!["u1".to_string()]));
+        assert_eq!(delta.out_order, Some(vec!["u2".to_string()]));
+        assert!(delta.players.is_empty());
+    }
+
+    #[test]
+    fn test_compacted_history_delta() {
+        let action = |at: u64| json!({ "type": "Play", "created_at": at });
+        let mut prev = state(5, &["a"], 0);
+        prev["action_history"] = json!([action(1), action(2), action(3)]);
+        let mut next = prev.clone();
+        next["action_history"] = json!([action(3), action(4)]);
+        next["history_summary"] = json!({ "archived_actions": 2 });
+
+        let delta = compute_delta(&prev, &next);
+        assert_eq!(delta.actions_added, vec![action(4)]);
+        assert_eq!(delta.changed.get("history_summary"), Some(&next["history_summary"]));
+    }
+
+    #[test]
+    fn test_seq_chain() {
+        let mut tracker = MatchDeltaTracker::new();
+        let first = tracker.snapshot_value("m1", None, state(10, &["a"], 0));
+        assert!(matches!(first, StateUpdate::Snapshot { seq: 1, .. }));
+
+        let second = tracker.snapshot_value("m1", None, state(9, &["a", "b"], 0));
+        assert!(matches!(second, StateUpdate::Snapshot { seq: 2, .. }));
+
+        let Some(StateUpdate::Snapshot { seq, state: current, .. }) = tracker.current("m1", None) else {
+            panic!("应返回当前快照");
+        };
+        assert_eq!(seq, 2);
+        assert_eq!(current, state(9, &["a", "b"], 0));
+        assert!(tracker.current("m2", None).is_none());
+        // 每名玩家的序列相互独立
+        assert!(tracker.current("m1", Some("u1")).is_none());
+        let own = tracker.snapshot_value("m1", Some("u1"), state(9, &["a", "b"], 0));
+        assert!(matches!(own, StateUpdate::Snapshot { seq: 1, .. }));
+
+        tracker.remove("m1");
+        assert!(tracker.current("m1", None).is_none());
+        assert!(tracker.current("m1", Some("u1")).is_none());
+    }
+
+    #[test]
+    fn test_redacted_view_delta() {
+        let redacted = |deck: usize, hand: usize| {
+            json!({
+                "id": "m1",
+                "deck": [],
+                "deck_count": deck,
+                "discard_pile": [],
+                "action_history": [],
+                "players": [{ "user": { "id": "u2" }, "hand": [], "hand_count": hand }],
+                "out": []
+            })
+        };
+        let delta = compute_delta(&redacted(10, 2), &redacted(9, 3));
+
+        assert!(delta.changed.is_empty());
+        assert_eq!(delta.deck_count, Some(9));
+        assert_eq!(delta.players.len(), 1);
+        assert!(delta.players[0].changed.is_empty());
+        assert_eq!(delta.players[0].hand_count, Some(3));
+        assert_eq!(delta.players[0].cards_added, 1);
+    }
+}
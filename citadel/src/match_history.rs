@@ -0,0 +1,291 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 对局动作历史压缩与归档
+//!
+//! # 概述
+//! `action_history` 和 `discard_pile` 随对局进行不断增长，每次保存都会整体序列化到缓存中，
+//! 也会随完整快照下发给客户端，长对局的缓存条目和广播载荷会越来越大。本模块在保存对局时进行压缩：
+//! - 动作历史超过上限时，把较早的已结算连锁（一次出牌及其后的烦人卡）移到归档中，
+//!   对局数据中只保留最近的动作和已归档连锁的汇总
+//! - 弃牌堆超过上限时只保留最近的卡牌，移除的张数计入汇总。连锁等待中的卡牌不会被移除
+//!
+//! 等待反应的连锁不会被归档。归档按对局存放在 `match:{对局ID}:history` 中，与对局数据一起固定和过期，
+//! 时间线和统计等需要完整历史的地方通过 `restore` 拼接归档和对局数据中的动作。
+//!
+//! # 配置
+//! - `MATCH_HISTORY_MAX_ACTIONS`：对局数据中保留的动作数上限，默认200，0表示不压缩
+//! - `MATCH_DISCARD_MAX`：对局数据中保留的弃牌数上限，默认60，0表示不压缩
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::game::{GameCachePrefix, GameService};
+use crate::gaming::{CardAction, CardActionType, MatchData};
+
+/// 默认保留的动作数上限
+pub const DEFAULT_MAX_ACTIONS: usize = 200;
+/// 默认保留的弃牌数上限
+pub const DEFAULT_MAX_DISCARD: usize = 60;
+
+/// 动作历史压缩配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchHistoryConfig {
+    /// 对局数据中保留的动作数上限，0表示不压缩
+    pub max_actions: usize,
+    /// 对局数据中保留的弃牌数上限，0表示不压缩
+    pub max_discard: usize,
+}
+
+impl Default for MatchHistoryConfig {
+    fn default() -> Self {
+        Self {
+            max_actions: DEFAULT_MAX_ACTIONS,
+            max_discard: DEFAULT_MAX_DISCARD,
+        }
+    }
+}
+
+impl MatchHistoryConfig {
+    /// 从环境变量读取压缩配置，未设置的项使用默认值
+    pub fn from_env() -> Self {
+        let parse = |key: &str| std::env::var(key).ok().and_then(|v| v.trim().parse::<usize>().ok());
+        let defaults = Self::default();
+        Self {
+            max_actions: parse("MATCH_HISTORY_MAX_ACTIONS").unwrap_or(defaults.max_actions),
+            max_discard: parse("MATCH_DISCARD_MAX").unwrap_or(defaults.max_discard),
+        }
+    }
+
+    /// 对局数据是否超过上限需要压缩
+    pub fn needs_compaction(&self, match_data: &MatchData) -> bool {
+        (self.max_actions > 0 && match_data.action_history.len() > self.max_actions)
+            || (self.max_discard > 0 && match_data.discard_pile.len() > self.max_discard)
+    }
+}
+
+static CONFIG: Lazy<MatchHistoryConfig> = Lazy::new(|| {
+    let config = MatchHistoryConfig::from_env();
+    info!(
+        "对局历史压缩: 保留动作 {}，保留弃牌 {}",
+        config.max_actions, config.max_discard
+    );
+    config
+});
+
+/// 获取全局压缩配置
+pub fn config() -> &'static MatchHistoryConfig {
+    &CONFIG
+}
+
+/// 已归档历史的汇总，保存在对局数据中
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HistorySummary {
+    /// 已归档的动作数，也是 `action_history` 第一项在完整历史中的位置
+    pub archived_actions: usize,
+    /// 已归档的生效连锁数
+    pub resolved_chains: usize,
+    /// 已归档的被烦人卡取消的连锁数
+    pub canceled_chains: usize,
+    /// 从弃牌堆中移除的卡牌数
+    pub trimmed_discards: usize,
+}
+
+/**
+ * 压缩对局数据中的动作历史和弃牌堆
+ *
+ * 只在出牌动作处切分，保证每个连锁完整地留在归档或对局数据中。
+ * 等待反应的连锁总是最后一次出牌，因此不会被归档
+ *
+ * 参数:
+ * @param match_data - 要压缩的对局数据，汇总会同步更新
+ * @param config - 压缩配置
+ *
+ * 返回:
+ * 移出对局数据、需要追加到归档中的动作
+ */
+pub fn compact(match_data: &mut MatchData, config: &MatchHistoryConfig) -> Vec<CardAction> {
+    let mut archived = Vec::new();
+
+    let history = &match_data.action_history;
+    if config.max_actions > 0 && history.len() > config.max_actions {
+        let excess = history.len() - config.max_actions;
+        let cut = (excess..history.len()).find(|&i| history[i].action_type == CardActionType::Play);
+        if let Some(cut) = cut {
+            archived = match_data.action_history.drain(..cut).collect();
+        }
+    }
+
+    let summary = &mut match_data.history_summary;
+    summary.archived_actions += archived.len();
+    for (index, action) in archived.iter().enumerate() {
+        if action.action_type != CardActionType::Play {
+            continue;
+        }
+        let canceled = archived[index + 1..]
+            .iter()
+            .take_while(|a| a.action_type != CardActionType::Play)
+            .any(|a| a.action_type == CardActionType::Nope);
+        if canceled {
+            summary.canceled_chains += 1;
+        } else {
+            summary.resolved_chains += 1;
+        }
+    }
+
+    if config.max_discard > 0 && match_data.discard_pile.len() > config.max_discard {
+        let mut trim = match_data.discard_pile.len() - config.max_discard;
+        // 连锁结算时还要从弃牌堆中查找等待中的卡牌
+        let chain_card = match_data.chain_state.as_ref().and_then(|c| c.card_id.as_deref());
+        if let Some(index) = chain_card.and_then(|id| match_data.discard_pile.iter().position(|c| c.id == id)) {
+            trim = trim.min(index);
+        }
+        match_data.discard_pile.drain(..trim);
+        match_data.history_summary.trimmed_discards += trim;
+    }
+
+    archived
+}
+
+/// 对局归档的缓存键
+fn archive_key(match_id: &str) -> String {
+    format!("{}:history", match_id)
+}
+
+/**
+ * 读取对局已归档的动作
+ *
+ * 参数:
+ * @param game_service - 游戏缓存服务
+ * @param match_id - 对局ID
+ *
+ * 返回:
+ * 按时间顺序排列的归档动作，没有归档时为空
+ */
+pub fn load_archive(game_service: &GameService, match_id: &str) -> Vec<CardAction> {
+    game_service
+        .get::<Vec<CardAction>>(GameCachePrefix::MATCH, &archive_key(match_id))
+        .unwrap_or_default()
+}
+
+/**
+ * 把压缩移出的动作追加到归档
+ *
+ * 调用方可能用同一份旧数据多次保存，已经归档过的部分按位置跳过
+ *
+ * 参数:
+ * @param game_service - 游戏缓存服务
+ * @param match_id - 对局ID
+ * @param start - 第一个动作在完整历史中的位置
+ * @param actions - 要追加的动作
+ *
+ * 返回:
+ * 成功返回true，失败返回false
+ */
+pub fn append_archive(game_service: &GameService, match_id: &str, start: usize, actions: &[CardAction]) -> bool {
+    let mut archive = load_archive(game_service, match_id);
+    let skip = archive.len().saturating_sub(start);
+    if skip >= actions.len() {
+        return true;
+    }
+    if archive.len() < start {
+        // 中间的归档已经过期，无法拼接
+        return false;
+    }
+    archive.extend_from_slice(&actions[skip..]);
+    game_service.set(GameCachePrefix::MATCH, &archive_key(match_id), &archive)
+}
+
+/// 固定或解除固定对局归档，与对局数据保持一致
+pub fn pin_archive(game_service: &GameService, match_id: &str, pinned: bool) {
+    if pinned {
+        game_service.pin(GameCachePrefix::MATCH, &archive_key(match_id));
+    } else {
+        game_service.unpin(GameCachePrefix::MATCH, &archive_key(match_id));
+    }
+}
+
+/// 删除对局归档
+pub fn delete_archive(game_service: &GameService, match_id: &str) -> bool {
+    game_service.delete(GameCachePrefix::MATCH, &archive_key(match_id))
+}
+
+/**
+ * 拼接归档和对局数据中的动作，恢复完整的动作历史
+ *
+ * 参数:
+ * @param match_data - 对局数据，`action_history` 会被替换为完整历史
+ * @param archive - 已归档的动作
+ */
+pub fn restore(match_data: &mut MatchData, archive: Vec<CardAction>) {
+    let archived = match_data.history_summary.archived_actions.min(archive.len());
+    if archived == 0 {
+        return;
+    }
+    let mut history = archive;
+    history.truncate(archived);
+    history.append(&mut match_data.action_history);
+    match_data.action_history = history;
+    match_data.history_summary.archived_actions -= archived;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gaming::{Card, CardType, MatchType};
+
+    fn action(action_type: CardActionType, at: u64) -> CardAction {
+        CardAction {
+            action_type,
+            user_id: "u1".to_string(),
+            card_id: Some(format!("card-{}", at)),
+            card_type: None,
+            is_canceled: false,
+            created_at: at,
+        }
+    }
+
+    fn match_with_history(kinds: &[CardActionType]) -> MatchData {
+        let mut match_data = MatchData::new("m1".to_string(), MatchType::Public, &[], 0);
+        match_data.action_history = kinds.iter().enumerate().map(|(i, k)| action(k.clone(), i as u64)).collect();
+        match_data
+    }
+
+    #[test]
+    fn test_compact_archives_whole_chains() {
+        use CardActionType::{Nope, Play};
+        let config = MatchHistoryConfig { max_actions: 3, max_discard: 0 };
+        let mut match_data = match_with_history(&[Play, Nope, Play, Play, Nope, Nope, Play]);
+
+        let archived = compact(&mut match_data, &config);
+        // 需要移出4个动作，第4个动作处于连锁中间，切分点后移到下一次出牌
+        assert_eq!(archived.len(), 6);
+        assert_eq!(match_data.action_history.len(), 1);
+        assert_eq!(match_data.history_summary.archived_actions, 6);
+        assert_eq!(match_data.history_summary.canceled_chains, 2);
+        assert_eq!(match_data.history_summary.resolved_chains, 1);
+
+        let mut full = match_data.clone();
+        restore(&mut full, archived);
+        assert_eq!(full.action_history.len(), 7);
+        assert_eq!(full.action_history[0].created_at, 0);
+        assert_eq!(full.history_summary.archived_actions, 0);
+    }
+
+    #[test]
+    fn test_compact_keeps_pending_chain_card() {
+        let config = MatchHistoryConfig { max_actions: 0, max_discard: 2 };
+        let mut match_data = match_with_history(&[]);
+        match_data.discard_pile = (0..5)
+            .map(|i| Card { id: format!("card-{}", i), card_type: CardType::Skip, variant: None })
+            .collect();
+        match_data.chain_state = Some(action(CardActionType::Play, 1));
+
+        assert!(config.needs_compaction(&match_data));
+        assert!(compact(&mut match_data, &config).is_empty());
+        assert_eq!(match_data.discard_pile.len(), 4);
+        assert_eq!(match_data.discard_pile[0].id, "card-1");
+        assert_eq!(match_data.history_summary.trimmed_discards, 1);
+    }
+}
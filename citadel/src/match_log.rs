@@ -0,0 +1,209 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 对局事件日志与状态重建
+//!
+//! # 概述
+//! 进行中的对局只保存在游戏缓存中，缓存条目丢失（被淘汰、节点重启后未恢复快照）时对局无法继续。
+//! 本模块在每次保存对局时把开局快照和新增的动作追加到持久化存储的事件日志中：
+//! - 位置0为开局快照，即发牌后、第一个动作之前的对局数据
+//! - 位置 `n + 1` 为完整动作历史中的第 `n` 个动作，同一位置重复写入时忽略，多次保存同一份数据不会产生重复
+//!
+//! 缓存中找不到对局时，`MatchService::rebuild_match` 读取日志，从开局快照按规则依次重放动作
+//! （与 `match_replay` 的重放规则相同）重建对局数据并重新写入缓存。
+//! 等待烦人卡反应的最后一次出牌按未被取消处理，重建后不再等待反应。
+//!
+//! 对局结束后完整数据已写入对局历史，日志随即删除。开始时间早于本功能的对局没有开局快照，无法重建。
+
+use anyhow::{anyhow, bail, Result};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error};
+
+use crate::gaming::{CardAction, MatchData, MatchState};
+use crate::match_replay;
+use crate::storage::{self, Storage};
+
+/// 事件日志条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "camelCase")]
+pub enum MatchLogEntry {
+    /// 开局快照
+    Started(Box<MatchData>),
+    /// 玩家动作
+    Action(CardAction),
+}
+
+/// 每个对局已写入日志的动作数，只用于跳过已写入的部分
+static LOGGED: Lazy<DashMap<String, usize>> = Lazy::new(DashMap::new);
+
+/**
+ * 把对局的开局快照和新增的动作追加到事件日志
+ *
+ * 参数:
+ * @param storage - 持久化存储
+ * @param match_data - 即将保存的对局数据
+ */
+pub async fn record(storage: &dyn Storage, match_data: &MatchData) -> Result<()> {
+    if match_data.state == MatchState::Waiting {
+        return Ok(());
+    }
+    let archived = match_data.history_summary.archived_actions;
+    let total = archived + match_data.action_history.len();
+    let from = LOGGED.get(&match_data.id).map_or(archived, |logged| *logged).max(archived);
+
+    if total == 0 && !LOGGED.contains_key(&match_data.id) {
+        storage
+            .append_match_log(&match_data.id, 0, &MatchLogEntry::Started(Box::new(match_data.clone())))
+            .await?;
+    }
+    for seq in from..total {
+        let action = match_data.action_history[seq - archived].clone();
+        storage
+            .append_match_log(&match_data.id, seq as u64 + 1, &MatchLogEntry::Action(action))
+            .await?;
+    }
+    LOGGED.insert(match_data.id.clone(), total);
+    Ok(())
+}
+
+/**
+ * 从事件日志重建对局数据
+ *
+ * 参数:
+ * @param storage - 持久化存储
+ * @param match_id - 对局ID
+ *
+ * 返回:
+ * 重建的对局数据，带完整动作历史；没有日志时返回None，日志缺少开局快照或动作无法重放时返回错误
+ */
+pub async fn rebuild(storage: &dyn Storage, match_id: &str) -> Result<Option<MatchData>> {
+    let mut entries = storage.match_log(match_id).await?.into_iter();
+    let start = match entries.next() {
+        None => return Ok(None),
+        Some(MatchLogEntry::Started(start)) => start,
+        Some(MatchLogEntry::Action(_)) => bail!("对局 {} 的事件日志缺少开局快照", match_id),
+    };
+    let actions = entries
+        .map(|entry| match entry {
+            MatchLogEntry::Action(action) => Ok(action),
+            MatchLogEntry::Started(_) => Err(anyhow!("对局 {} 的事件日志中有多个开局快照", match_id)),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let rebuilt = match_replay::rebuild_state(&start, &actions)?;
+    LOGGED.insert(match_id.to_string(), actions.len());
+    debug!("从事件日志重建对局 {}，重放动作 {} 个", match_id, actions.len());
+    Ok(Some(rebuilt))
+}
+
+/// 删除对局的事件日志
+pub async fn discard(storage: &dyn Storage, match_id: &str) -> Result<()> {
+    LOGGED.remove(match_id);
+    storage.delete_match_log(match_id).await
+}
+
+/// 使用全局存储记录事件日志，未初始化存储或写入失败时只记录日志
+pub async fn record_match(match_data: &MatchData) {
+    let Some(storage) = storage::global_storage() else {
+        return;
+    };
+    if let Err(e) = record(storage.as_ref(), match_data).await {
+        error!("写入对局 {} 的事件日志失败: {}", match_data.id, e);
+    }
+}
+
+/// 使用全局存储删除事件日志，未初始化存储或删除失败时只记录日志
+pub async fn discard_match(match_id: &str) {
+    let Some(storage) = storage::global_storage() else {
+        return;
+    };
+    if let Err(e) = discard(storage.as_ref(), match_id).await {
+        error!("删除对局 {} 的事件日志失败: {}", match_id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_mode;
+    use crate::gaming::{CardActionType, CardType, MatchType, UserInfo};
+    use crate::match_replay::ReplaySeed;
+    use crate::storage::memory::MemoryStorage;
+
+    fn started_match(match_id: &str) -> MatchData {
+        let players = ["alice", "bob"]
+            .iter()
+            .map(|id| UserInfo {
+                id: id.to_string(),
+                name: id.to_string(),
+                rating: 1000,
+                avatar_url: None,
+                provisional: false,
+            })
+            .collect::<Vec<_>>();
+        let mut match_data = MatchData::new(match_id.to_string(), MatchType::Private, &players, 0);
+        match_data.replay_seed = Some(ReplaySeed::new(match_id, &match_data.players));
+        let mode = game_mode::mode_for(&match_data);
+        match_data.deck = mode.build_deck(2, &mut match_replay::deck_rng(&match_data));
+        mode.deal(&mut match_data);
+        match_data.state = MatchState::InProgress;
+        match_data.players[0].is_turn = true;
+        match_data
+    }
+
+    /// 当前回合玩家抽一张牌，抽到爆炸猫时不抽
+    fn draw(match_data: &mut MatchData) -> bool {
+        let card = match_data.deck.last().unwrap().clone();
+        if card.card_type == CardType::ExplodingKitten {
+            return false;
+        }
+        let turn = match_data.turn_index;
+        let user_id = match_data.players[turn].user.id.clone();
+        match_data.deck.pop();
+        match_data.players[turn].hand.push(card.clone());
+        match_data.players[turn].is_turn = false;
+        match_data.turn_index = (turn + 1) % match_data.players.len();
+        match_data.players[match_data.turn_index].is_turn = true;
+        match_data.draw_count += 1;
+        match_data.action_history.push(CardAction {
+            action_type: CardActionType::Draw,
+            user_id,
+            card_id: Some(card.id),
+            card_type: Some(card.card_type),
+            is_canceled: false,
+            created_at: 0,
+        });
+        true
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_from_log() {
+        let storage = MemoryStorage::default();
+        assert!(rebuild(&storage, "log-1").await.unwrap().is_none());
+
+        let mut match_data = started_match("log-1");
+        record(&storage, &match_data).await.unwrap();
+        for _ in 0..3 {
+            if draw(&mut match_data) {
+                // 同一份数据保存两次不会重复写入
+                record(&storage, &match_data).await.unwrap();
+                record(&storage, &match_data).await.unwrap();
+            }
+        }
+        assert_eq!(storage.match_log("log-1").await.unwrap().len(), match_data.action_history.len() + 1);
+
+        let rebuilt = rebuild(&storage, "log-1").await.unwrap().unwrap();
+        let ids = |m: &MatchData| m.deck.iter().map(|c| c.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&rebuilt), ids(&match_data));
+        assert_eq!(rebuilt.turn_index, match_data.turn_index);
+        assert_eq!(rebuilt.action_history.len(), match_data.action_history.len());
+        for (rebuilt, recorded) in rebuilt.players.iter().zip(&match_data.players) {
+            assert_eq!(rebuilt.hand.len(), recorded.hand.len());
+        }
+
+        discard(&storage, "log-1").await.unwrap();
+        assert!(rebuild(&storage, "log-1").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,805 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 对局重放验证模块
+//!
+//! # 概述
+//! 每局的随机种子由服务器密钥和对局ID派生：`HMAC-SHA3-256(MATCH_SEED_SECRET, 对局ID)`，
+//! 牌组和所有带随机性的操作（洗牌、抢夺、改变未来、加速爆炸、超时随机选择目标）都使用由种子和
+//! 动作位置决定的随机数。给定种子和完整的动作历史，就可以从开局状态按游戏规则重新执行一遍对局，
+//! 核对最终状态是否与服务器记录的一致，用于发现服务器的状态错误，也可以作为争议处理的依据。
+//!
+//! 种子本身不保存在对局数据中，避免随对局数据下发给客户端后被用来预测牌堆。开局时对局数据中
+//! 只记录种子的承诺 `Blake2b256(种子)` 和座位顺序；对局结束后公开种子（可通过配置关闭），
+//! 任何人都可以核对种子与开局时的承诺一致，并用种子重放对局。
+//!
+//! 重放规则：
+//! - 出牌后到下一次出牌之前有烦人卡的，出牌效果被取消，否则在出牌时立即生效
+//! - 抽到的牌必须与记录的卡牌ID一致，抽到爆炸猫后紧接着的拆除动作把爆炸猫放回牌堆顶部，否则玩家出局
+//! - 离开和超时出局按出局动作处理，出局的是当前回合玩家时轮到下一位玩家
+//! - 手牌超过上限时弃掉的牌按记录从手牌移入弃牌堆，超时随机弃牌同样按记录执行
+//! - 牌堆抽完时，抽牌前按游戏模式的规则把弃牌洗回牌堆，随机数由种子和抽牌动作的位置决定
+//! - 牌堆抽完且无法补充而结束的对局，重放后的牌堆同样无法补充时按模式规则计分判定胜利者
+//!
+//! 动作不合法或抽到的牌与记录不一致时重放中止并报告分歧位置；重放完成后逐项比较牌堆、手牌、弃牌堆、
+//! 玩家顺序和胜利者，报告所有不一致的项。`StdRng` 的算法可能随rand版本变化，升级后旧对局可能无法验证。
+//!
+//! # 接口
+//! - `GET /admin/matches/:match_id/verify`：重放服务器中的对局，需要在 `X-Admin-Key` 请求头中提供管理密钥
+//! - 命令行 `verify-match`：调用上述接口，或直接重放导出的对局数据文件
+//!
+//! # 配置
+//! - `MATCH_SEED_SECRET`：派生对局种子的服务器密钥，未设置时每次启动随机生成，重启后无法重放之前的对局
+//! - `MATCH_SEED_REVEAL`：对局结束后是否公开种子，默认true
+
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Result};
+use axum::{extract::Path, http::HeaderMap, routing::get, Json, Router};
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::hash::{Blake2b256, HashFunction};
+use fastcrypto::hmac::{hmac_sha3_256, HmacKey};
+use once_cell::sync::Lazy;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::errors::InternalError;
+use crate::game_mode::{self, GameMode, PlayerAction};
+use crate::gaming::{self, Card, CardAction, CardActionType, CardType, MatchData, MatchPlayer, MatchState, UserInfo};
+use crate::key_audit::check_admin_key;
+use crate::AppState;
+
+/// 生成牌组使用的随机数流，与动作位置区分
+const DECK_STREAM: u64 = u64::MAX;
+
+/// 对局种子
+pub type Seed = [u8; 32];
+
+/// 种子派生配置
+struct SeedConfig {
+    secret: Vec<u8>,
+    reveal: bool,
+}
+
+static SEED_CONFIG: Lazy<SeedConfig> = Lazy::new(|| {
+    let secret = match std::env::var("MATCH_SEED_SECRET").ok().filter(|v| !v.is_empty()) {
+        Some(secret) => secret.into_bytes(),
+        None => {
+            warn!("未设置MATCH_SEED_SECRET，使用随机生成的种子密钥，重启后无法重放之前的对局");
+            let mut secret = vec![0u8; 32];
+            rand::thread_rng().fill_bytes(&mut secret);
+            secret
+        }
+    };
+    let reveal = std::env::var("MATCH_SEED_REVEAL")
+        .ok()
+        .and_then(|v| v.trim().parse::<bool>().ok())
+        .unwrap_or(true);
+    SeedConfig { secret, reveal }
+});
+
+/// 由服务器密钥和对局ID派生对局种子
+fn derive_seed(secret: &[u8], match_id: &str) -> Seed {
+    let key = HmacKey::from_bytes(secret).expect("hmac key accepts any length");
+    hmac_sha3_256(&key, match_id.as_bytes()).digest
+}
+
+/// 种子的承诺，Hex编码
+pub fn seed_commitment(seed: &Seed) -> String {
+    Hex::encode(Blake2b256::digest(seed).digest)
+}
+
+/// 对局的种子承诺和初始座位
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplaySeed {
+    /// 种子的承诺 `Blake2b256(种子)`，Hex编码，开局时公开
+    pub commitment: String,
+    /// 对局结束后公开的种子，Hex编码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revealed: Option<String>,
+    /// 开局时按座位顺序排列的玩家
+    pub seating: Vec<UserInfo>,
+}
+
+impl ReplaySeed {
+    /// 为即将开始的对局记录种子承诺
+    pub fn new(match_id: &str, players: &[MatchPlayer]) -> Self {
+        Self {
+            commitment: seed_commitment(&derive_seed(&SEED_CONFIG.secret, match_id)),
+            revealed: None,
+            seating: players.iter().map(|p| p.user.clone()).collect(),
+        }
+    }
+
+    /**
+     * 取得对局种子
+     *
+     * 已公开时使用公开的种子，否则由服务器密钥派生；种子与承诺不一致时返回错误
+     *
+     * 参数:
+     * @param match_id - 对局ID
+     *
+     * 返回:
+     * 与承诺一致的种子
+     */
+    pub fn seed(&self, match_id: &str) -> Result<Seed> {
+        let seed = match &self.revealed {
+            Some(revealed) => Hex::decode(revealed)
+                .ok()
+                .and_then(|bytes| Seed::try_from(bytes).ok())
+                .ok_or_else(|| anyhow!("公开的种子格式无效"))?,
+            None => derive_seed(&SEED_CONFIG.secret, match_id),
+        };
+        if seed_commitment(&seed) != self.commitment {
+            bail!("种子与开局时的承诺不一致");
+        }
+        Ok(seed)
+    }
+}
+
+/**
+ * 对局结束时公开种子
+ *
+ * 配置关闭公开或对局没有种子时不做任何处理
+ *
+ * 参数:
+ * @param match_data - 已结束的对局数据
+ */
+pub fn reveal_seed(match_data: &mut MatchData) {
+    if !SEED_CONFIG.reveal {
+        return;
+    }
+    let match_id = match_data.id.clone();
+    if let Some(replay) = match_data.replay_seed.as_mut().filter(|r| r.revealed.is_none()) {
+        replay.revealed = Some(Hex::encode(derive_seed(&SEED_CONFIG.secret, &match_id)));
+    }
+}
+
+/// 由种子和随机数流编号派生的随机数生成器
+fn stream_rng(seed: &Seed, stream: u64) -> StdRng {
+    let mut hasher = Blake2b256::default();
+    hasher.update(seed);
+    hasher.update(stream.to_le_bytes());
+    StdRng::from_seed(hasher.finalize().digest)
+}
+
+/// 对局的种子，对局没有种子或种子与承诺不一致时为None
+pub fn match_seed(match_data: &MatchData) -> Option<Seed> {
+    match_data.replay_seed.as_ref().and_then(|r| r.seed(&match_data.id).ok())
+}
+
+/// 对局的随机数流，对局没有种子时使用系统随机数
+fn match_rng(match_data: &MatchData, stream: Option<u64>) -> StdRng {
+    match (match_seed(match_data), stream) {
+        (Some(seed), Some(stream)) => stream_rng(&seed, stream),
+        _ => StdRng::from_entropy(),
+    }
+}
+
+/// 生成牌组使用的随机数生成器
+pub fn deck_rng(match_data: &MatchData) -> StdRng {
+    match_rng(match_data, Some(DECK_STREAM))
+}
+
+/**
+ * 下一个动作使用的随机数生成器
+ *
+ * 随机数由种子和下一个动作在完整历史中的位置决定，用于抽牌前把弃牌洗回牌堆
+ *
+ * 参数:
+ * @param match_data - 对局数据
+ *
+ * 返回:
+ * 随机数生成器，对局没有种子时使用系统随机数
+ */
+pub fn next_action_rng(match_data: &MatchData) -> StdRng {
+    let seq = match_data.history_summary.archived_actions + match_data.action_history.len();
+    match_rng(match_data, Some(seq as u64))
+}
+
+/**
+ * 卡牌效果使用的随机数生成器
+ *
+ * 随机数由种子和出牌动作在完整历史中的位置决定，与效果实际执行的时间无关
+ *
+ * 参数:
+ * @param match_data - 对局数据
+ * @param card_id - 生效的卡牌ID
+ *
+ * 返回:
+ * 随机数生成器，对局没有种子或找不到出牌动作时使用系统随机数
+ */
+pub fn effect_rng(match_data: &MatchData, card_id: &str) -> StdRng {
+    let seq = match_data
+        .action_history
+        .iter()
+        .rposition(|a| a.action_type == CardActionType::Play && a.card_id.as_deref() == Some(card_id))
+        .map(|index| (match_data.history_summary.archived_actions + index) as u64);
+    match_rng(match_data, seq)
+}
+
+/// 重放中止的位置和原因
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayDivergence {
+    /// 动作在完整历史中的位置
+    pub seq: usize,
+    pub reason: String,
+}
+
+/// 重放结果与记录不一致的项
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayMismatch {
+    /// 比较项，如 `deck`、`hand:玩家ID`
+    pub field: String,
+    /// 服务器记录的值
+    pub recorded: String,
+    /// 重放得到的值
+    pub replayed: String,
+}
+
+/// 重放验证报告
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayReport {
+    pub match_id: String,
+    /// 重放结果与记录完全一致
+    pub verified: bool,
+    /// 动作历史中的动作数
+    pub actions: usize,
+    /// 成功重放的动作数
+    pub replayed: usize,
+    /// 重放中止时的分歧
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub divergence: Option<ReplayDivergence>,
+    /// 不一致的项
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mismatches: Vec<ReplayMismatch>,
+}
+
+impl Display for ReplayReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let result = if self.verified { "验证通过" } else { "验证失败" };
+        writeln!(f, "对局 {}: {}，重放动作 {}/{}", self.match_id, result, self.replayed, self.actions)?;
+        if let Some(divergence) = &self.divergence {
+            writeln!(f, "  第 {} 个动作出现分歧: {}", divergence.seq, divergence.reason)?;
+        }
+        for mismatch in &self.mismatches {
+            writeln!(f, "  {} 不一致", mismatch.field)?;
+            writeln!(f, "    记录: {}", mismatch.recorded)?;
+            writeln!(f, "    重放: {}", mismatch.replayed)?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * 重放对局并核对最终状态
+ *
+ * 参数:
+ * @param recorded - 服务器记录的对局数据，动作历史必须完整（已拼接归档）
+ *
+ * 返回:
+ * 验证报告；对局没有种子、历史不完整或模式未注册时返回错误
+ */
+pub fn replay_match(recorded: &MatchData) -> Result<ReplayReport> {
+    let replay_seed = recorded
+        .replay_seed
+        .as_ref()
+        .ok_or_else(|| anyhow!("对局没有记录随机种子，无法重放"))?;
+    let seed = replay_seed.seed(&recorded.id)?;
+    if recorded.history_summary.archived_actions > 0 {
+        bail!("动作历史不完整，缺少 {} 个已归档的动作", recorded.history_summary.archived_actions);
+    }
+    let mode = game_mode::global_game_modes()
+        .get(&recorded.mode)
+        .ok_or_else(|| anyhow!("不支持的游戏模式: {}", recorded.mode))?;
+    let mut state = initial_state(mode.as_ref(), recorded, replay_seed, &seed);
+
+    let history = &recorded.action_history;
+    let mut report = ReplayReport {
+        match_id: recorded.id.clone(),
+        verified: false,
+        actions: history.len(),
+        replayed: 0,
+        divergence: None,
+        mismatches: Vec::new(),
+    };
+    for seq in 0..history.len() {
+        if let Err(e) = apply_action(mode.as_ref(), &mut state, history, seq, &seed) {
+            report.divergence = Some(ReplayDivergence { seq, reason: e.to_string() });
+            break;
+        }
+        report.replayed += 1;
+    }
+    // 牌堆抽完结束游戏没有对应的动作，只有重放得到的牌堆确实无法补充时才认可
+    state.deck_exhausted = recorded.deck_exhausted && game_mode::deck_exhausted(mode.as_ref(), &state);
+
+    report.mismatches = compare(mode.as_ref(), recorded, &state);
+    report.verified = report.divergence.is_none() && report.mismatches.is_empty();
+    Ok(report)
+}
+
+/// 按开局流程重建初始状态
+fn initial_state(mode: &dyn GameMode, recorded: &MatchData, replay_seed: &ReplaySeed, seed: &Seed) -> MatchData {
+    let mut state = MatchData::new(
+        recorded.id.clone(),
+        recorded.match_type.clone(),
+        &replay_seed.seating,
+        recorded.created_at,
+    );
+    state.mode = recorded.mode.clone();
+    state.replay_seed = Some(replay_seed.clone());
+    state.deck = mode.build_deck(replay_seed.seating.len(), &mut stream_rng(seed, DECK_STREAM));
+    mode.deal(&mut state);
+    state.state = MatchState::InProgress;
+    state.turn_index = 0;
+    state.players[0].is_turn = true;
+    state
+}
+
+/**
+ * 重建发牌后的初始牌堆
+ *
+ * 参数:
+ * @param match_data - 已开始的对局数据
+ *
+ * 返回:
+ * 开局时的牌堆，对局没有种子或模式未注册时为None
+ */
+pub fn initial_deck(match_data: &MatchData) -> Option<Vec<Card>> {
+    let replay_seed = match_data.replay_seed.as_ref()?;
+    let seed = replay_seed.seed(&match_data.id).ok()?;
+    let mode = game_mode::global_game_modes().get(&match_data.mode)?;
+    Some(initial_state(mode.as_ref(), match_data, replay_seed, &seed).deck)
+}
+
+/**
+ * 重建开局时的所有卡牌
+ *
+ * 参数:
+ * @param match_data - 已开始的对局数据
+ *
+ * 返回:
+ * 发牌后的牌堆和各玩家的手牌，按牌堆、座位顺序排列；对局没有种子或模式未注册时为None
+ */
+pub fn initial_cards(match_data: &MatchData) -> Option<Vec<Card>> {
+    let replay_seed = match_data.replay_seed.as_ref()?;
+    let seed = replay_seed.seed(&match_data.id).ok()?;
+    let mode = game_mode::global_game_modes().get(&match_data.mode)?;
+    let state = initial_state(mode.as_ref(), match_data, replay_seed, &seed);
+    Some(state.deck.into_iter().chain(state.players.into_iter().flat_map(|p| p.hand)).collect())
+}
+
+/**
+ * 从开局快照按规则重放动作，重建对局数据
+ *
+ * 参数:
+ * @param start - 发牌后、第一个动作之前的对局数据
+ * @param actions - 完整的动作历史
+ *
+ * 返回:
+ * 重放后的对局数据，带完整动作历史；对局没有种子、模式未注册或动作无法重放时返回错误
+ */
+pub fn rebuild_state(start: &MatchData, actions: &[CardAction]) -> Result<MatchData> {
+    let seed = start
+        .replay_seed
+        .as_ref()
+        .ok_or_else(|| anyhow!("对局没有记录随机种子，无法重建"))?
+        .seed(&start.id)?;
+    let mode = game_mode::global_game_modes()
+        .get(&start.mode)
+        .ok_or_else(|| anyhow!("不支持的游戏模式: {}", start.mode))?;
+
+    let mut state = start.clone();
+    for seq in 0..actions.len() {
+        apply_action(mode.as_ref(), &mut state, actions, seq, &seed)
+            .map_err(|e| anyhow!("第 {} 个动作无法重放: {}", seq, e))?;
+    }
+    state.action_history = actions.to_vec();
+    state.history_summary = Default::default();
+    if let Some(last) = actions.last() {
+        state.updated_at = state.updated_at.max(last.created_at);
+    }
+    Ok(state)
+}
+
+/// 按规则执行第 `seq` 个动作，动作不合法时返回错误且不修改对局状态
+pub(crate) fn apply_action(mode: &dyn GameMode, state: &mut MatchData, history: &[CardAction], seq: usize, seed: &Seed) -> Result<()> {
+    let action = &history[seq];
+    let user_id = action.user_id.as_str();
+    match action.action_type {
+        CardActionType::Play => {
+            let card_id = action.card_id.as_deref().ok_or_else(|| anyhow!("出牌动作缺少卡牌ID"))?;
+            mode.validate_action(state, user_id, PlayerAction::Play { card_id })?;
+            let card = take_card(state, user_id, card_id)?;
+            state.discard_pile.push(card.clone());
+
+            let canceled = history[seq + 1..]
+                .iter()
+                .take_while(|a| a.action_type != CardActionType::Play)
+                .any(|a| a.action_type == CardActionType::Nope);
+            if !canceled {
+                apply_effect(state, user_id, &card, &mut stream_rng(seed, seq as u64))?;
+            }
+        }
+        CardActionType::Nope => {
+            let card_id = action.card_id.as_deref().ok_or_else(|| anyhow!("烦人卡动作缺少卡牌ID"))?;
+            mode.validate_action(state, user_id, PlayerAction::Play { card_id })?;
+            if !holds_card(state, user_id, card_id, CardType::Nope)? {
+                bail!("{} 不是烦人卡", card_id);
+            }
+            let card = take_card(state, user_id, card_id)?;
+            state.discard_pile.push(card);
+        }
+        CardActionType::Draw => {
+            // 牌堆抽完时与对局中相同，先按游戏模式的规则把弃牌洗回牌堆
+            if state.deck.is_empty() && state.players.iter().any(|p| p.user.id == user_id && p.is_turn) {
+                game_mode::refill_deck(mode, state, &mut stream_rng(seed, seq as u64));
+            }
+            mode.validate_action(state, user_id, PlayerAction::Draw)?;
+            let top = state.deck.last().ok_or_else(|| anyhow!("牌堆已空"))?;
+            if action.card_id.as_deref() != Some(top.id.as_str()) {
+                bail!("抽到 {}，记录为 {}", top.id, action.card_id.as_deref().unwrap_or("无"));
+            }
+            let player_index = player_index(state, user_id)?;
+            let card = state.deck.pop().expect("牌堆非空");
+            state.draw_count += 1;
+
+            if card.card_type != CardType::ExplodingKitten {
+                state.players[player_index].hand.push(card);
+                change_turn(state);
+            } else if history
+                .get(seq + 1)
+                .is_some_and(|next| next.action_type == CardActionType::Defuse && next.user_id == user_id)
+            {
+                // 爆炸猫放回牌堆顶部，拆除卡在下一个动作中处理
+                state.deck.push(card);
+            } else {
+                eliminate(state, player_index);
+            }
+        }
+        CardActionType::Defuse => {
+            let card_id = action.card_id.as_deref().ok_or_else(|| anyhow!("拆除动作缺少卡牌ID"))?;
+            // 只能紧接在同一玩家抽到爆炸猫之后，此时爆炸猫已放回牌堆顶部
+            let after_kitten = seq > 0
+                && history[seq - 1].action_type == CardActionType::Draw
+                && history[seq - 1].user_id == user_id
+                && state.deck.last().is_some_and(|c| {
+                    c.card_type == CardType::ExplodingKitten && history[seq - 1].card_id.as_deref() == Some(c.id.as_str())
+                });
+            if !after_kitten {
+                bail!("{} 没有抽到爆炸猫，不能使用拆除卡", user_id);
+            }
+            if !holds_card(state, user_id, card_id, CardType::Defuse)? {
+                bail!("{} 不是拆除卡", card_id);
+            }
+            let card = take_card(state, user_id, card_id)?;
+            state.discard_pile.push(card);
+            change_turn(state);
+        }
+        CardActionType::Eliminate => {
+            let player_index = player_index(state, user_id)?;
+            eliminate(state, player_index);
+        }
+        CardActionType::Discard => {
+            // 回合结束时弃牌，轮到下一位玩家的动作已在抽牌或出牌时执行
+            let card_id = action.card_id.as_deref().ok_or_else(|| anyhow!("弃牌动作缺少卡牌ID"))?;
+            let card = take_card(state, user_id, card_id)?;
+            state.discard_pile.push(card);
+        }
+    }
+    Ok(())
+}
+
+/// 执行卡牌效果，与MatchService的卡牌效果一致
+fn apply_effect(state: &mut MatchData, user_id: &str, card: &Card, rng: &mut StdRng) -> Result<()> {
+    match card.card_type {
+        CardType::Skip => change_turn(state),
+        CardType::Attack => {
+            state.draw_count = 2;
+            change_turn(state);
+        }
+        CardType::Shuffle => state.deck.shuffle(rng),
+        CardType::Favor => {
+            let player_index = player_index(state, user_id)?;
+            let targets = (0..state.players.len())
+                .filter(|i| *i != player_index && !state.players[*i].hand.is_empty())
+                .collect::<Vec<_>>();
+            if !targets.is_empty() {
+                let target_index = targets[rng.gen_range(0..targets.len())];
+                let target_hand = &mut state.players[target_index].hand;
+                let stolen = target_hand.remove(rng.gen_range(0..target_hand.len()));
+                state.players[player_index].hand.push(stolen);
+            }
+        }
+        CardType::AlterTheFuture => {
+            if state.deck.len() >= 3 {
+                let mut future = state.deck.split_off(state.deck.len() - 3);
+                future.reverse();
+                future.shuffle(rng);
+                state.deck.extend(future.into_iter().rev());
+            }
+        }
+        CardType::BuryCard => {
+            if let Some(buried) = state.deck.pop() {
+                let middle = state.deck.len() / 2;
+                state.deck.insert(middle, buried);
+            }
+        }
+        CardType::SpeedUpExplosion => {
+            if let Some(pos) = state.deck.iter().position(|c| c.card_type == CardType::ExplodingKitten) {
+                let kitten = state.deck.remove(pos);
+                let top_range = (state.deck.len() / 4).max(1);
+                state.deck.insert(rng.gen_range(0..top_range), kitten);
+            }
+        }
+        CardType::ImplodingKitten => {
+            let middle = state.deck.len() / 2;
+            state.deck.insert(
+                middle,
+                Card {
+                    id: format!("imploding-{}", card.id),
+                    card_type: CardType::ExplodingKitten,
+                    variant: Some("imploding".to_string()),
+                },
+            );
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn player_index(state: &MatchData, user_id: &str) -> Result<usize> {
+    state
+        .players
+        .iter()
+        .position(|p| p.user.id == user_id)
+        .ok_or_else(|| anyhow!("玩家 {} 不在游戏中", user_id))
+}
+
+/// 玩家手中是否有指定ID和类型的牌
+fn holds_card(state: &MatchData, user_id: &str, card_id: &str, card_type: CardType) -> Result<bool> {
+    let player_index = player_index(state, user_id)?;
+    Ok(state.players[player_index]
+        .hand
+        .iter()
+        .any(|c| c.id == card_id && c.card_type == card_type))
+}
+
+/// 从玩家手牌中取出指定的牌
+fn take_card(state: &mut MatchData, user_id: &str, card_id: &str) -> Result<Card> {
+    let player_index = player_index(state, user_id)?;
+    let hand = &mut state.players[player_index].hand;
+    let card_index = hand
+        .iter()
+        .position(|c| c.id == card_id)
+        .ok_or_else(|| anyhow!("玩家 {} 手中没有 {}", user_id, card_id))?;
+    Ok(hand.remove(card_index))
+}
+
+/// 轮到下一名玩家
+fn change_turn(state: &mut MatchData) {
+    if state.players.is_empty() {
+        return;
+    }
+    if let Some(player) = state.players.get_mut(state.turn_index) {
+        player.is_turn = false;
+    }
+    state.turn_index = (state.turn_index + 1) % state.players.len();
+    state.players[state.turn_index].is_turn = true;
+}
+
+/// 玩家出局，出局的是当前回合玩家时轮到下一位玩家
+fn eliminate(state: &mut MatchData, player_index: usize) {
+    let mut player = state.players.remove(player_index);
+    let had_turn = player.is_turn;
+    player.is_active = false;
+    player.is_turn = false;
+    state.out.push(player);
+    if state.players.is_empty() {
+        return;
+    }
+
+    if had_turn {
+        state.turn_index = (player_index + state.players.len() - 1) % state.players.len();
+        change_turn(state);
+    } else if player_index < state.turn_index {
+        state.turn_index -= 1;
+    }
+}
+
+fn card_ids(cards: &[Card]) -> String {
+    cards.iter().map(|c| c.id.as_str()).collect::<Vec<_>>().join(",")
+}
+
+fn sorted_card_ids(cards: &[Card]) -> String {
+    let mut ids = cards.iter().map(|c| c.id.as_str()).collect::<Vec<_>>();
+    ids.sort_unstable();
+    ids.join(",")
+}
+
+fn player_ids(players: &[MatchPlayer]) -> String {
+    players.iter().map(|p| p.user.id.as_str()).collect::<Vec<_>>().join(",")
+}
+
+/// 逐项比较记录的状态和重放得到的状态
+fn compare(mode: &dyn GameMode, recorded: &MatchData, replayed: &MatchData) -> Vec<ReplayMismatch> {
+    let mut mismatches = Vec::new();
+    let mut check = |field: String, recorded: String, replayed: String| {
+        if recorded != replayed {
+            mismatches.push(ReplayMismatch { field, recorded, replayed });
+        }
+    };
+
+    check("players".to_string(), player_ids(&recorded.players), player_ids(&replayed.players));
+    check("out".to_string(), player_ids(&recorded.out), player_ids(&replayed.out));
+    check("deck".to_string(), card_ids(&recorded.deck), card_ids(&replayed.deck));
+
+    // 弃牌堆可能被压缩，只比较保留的部分
+    let trimmed = recorded.history_summary.trimmed_discards.min(replayed.discard_pile.len());
+    check(
+        "discard_pile".to_string(),
+        card_ids(&recorded.discard_pile),
+        card_ids(&replayed.discard_pile[trimmed..]),
+    );
+
+    let replayed_players = replayed.players.iter().chain(replayed.out.iter()).collect::<Vec<_>>();
+    for player in recorded.players.iter().chain(recorded.out.iter()) {
+        let replayed_hand = replayed_players
+            .iter()
+            .find(|p| p.user.id == player.user.id)
+            .map(|p| sorted_card_ids(&p.hand))
+            .unwrap_or_default();
+        check(format!("hand:{}", player.user.id), sorted_card_ids(&player.hand), replayed_hand);
+    }
+
+    if recorded.state == MatchState::Completed {
+        let winner = recorded
+            .players
+            .iter()
+            .chain(recorded.out.iter())
+            .find(|p| p.is_winner)
+            .map(|p| p.user.id.clone())
+            .unwrap_or_default();
+        check("winner".to_string(), winner, mode.winner(replayed).unwrap_or_default());
+    } else {
+        check(
+            "turn_index".to_string(),
+            recorded.turn_index.to_string(),
+            replayed.turn_index.to_string(),
+        );
+    }
+
+    mismatches
+}
+
+/// 重放服务器中的对局
+pub async fn handle_verify_match(
+    headers: HeaderMap,
+    Path(match_id): Path<String>,
+) -> Result<Json<ReplayReport>, InternalError> {
+    check_admin_key(&headers)?;
+    let match_service = gaming::global_match_service().ok_or(InternalError::Failure)?;
+    let match_data = match_service
+        .get_match_with_history(&match_id)
+        .await
+        .ok_or(InternalError::MatchNotFound)?;
+
+    let report = replay_match(&match_data).map_err(|e| {
+        info!("无法重放对局 {}: {}", match_id, e);
+        InternalError::InvalidInput
+    })?;
+    if report.verified {
+        info!("对局 {} 重放验证通过", match_id);
+    } else {
+        warn!("对局 {} 重放验证失败: {:?}", match_id, report.divergence);
+    }
+    Ok(Json(report))
+}
+
+/// 注册重放验证路由
+pub fn register_replay_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router.route("/admin/matches/:match_id/verify", get(handle_verify_match))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gaming::MatchType;
+
+    fn seated_match(match_id: &str) -> MatchData {
+        let players = ["alice", "bob"]
+            .iter()
+            .map(|id| UserInfo {
+                id: id.to_string(),
+                name: id.to_string(),
+                rating: 1000,
+                avatar_url: None,
+                provisional: false,
+            })
+            .collect::<Vec<_>>();
+        let mut match_data = MatchData::new(match_id.to_string(), MatchType::Private, &players, 0);
+        match_data.replay_seed = Some(ReplaySeed {
+            commitment: seed_commitment(&derive_seed(&SEED_CONFIG.secret, match_id)),
+            revealed: None,
+            seating: players,
+        });
+        let mode = game_mode::mode_for(&match_data);
+        match_data.deck = mode.build_deck(2, &mut deck_rng(&match_data));
+        mode.deal(&mut match_data);
+        match_data.state = MatchState::InProgress;
+        match_data.players[0].is_turn = true;
+        match_data
+    }
+
+    fn record(action_type: CardActionType, user_id: &str, card: &Card) -> CardAction {
+        CardAction {
+            action_type,
+            user_id: user_id.to_string(),
+            card_id: Some(card.id.clone()),
+            card_type: Some(card.card_type.clone()),
+            is_canceled: false,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_replay_matches_recorded_draws() {
+        let mut recorded = seated_match("m1");
+        assert_eq!(card_ids(&recorded.deck), card_ids(&seated_match("m1").deck));
+
+        // 两名玩家各抽一张牌，只要不是爆炸猫就交给下一位
+        for user_id in ["alice", "bob"] {
+            let card = recorded.deck.last().unwrap().clone();
+            if card.card_type == CardType::ExplodingKitten {
+                break;
+            }
+            recorded.deck.pop();
+            recorded.action_history.push(record(CardActionType::Draw, user_id, &card));
+            let index = recorded.players.iter().position(|p| p.user.id == user_id).unwrap();
+            recorded.players[index].hand.push(card);
+            recorded.draw_count += 1;
+            change_turn(&mut recorded);
+        }
+
+        let report = replay_match(&recorded).unwrap();
+        assert!(report.verified, "{}", report);
+        assert_eq!(report.replayed, recorded.action_history.len());
+
+        // 公开种子后不依赖服务器密钥也能重放，伪造的种子与承诺不一致
+        reveal_seed(&mut recorded);
+        let revealed = recorded.replay_seed.as_ref().unwrap().seed("m1").unwrap();
+        assert!(replay_match(&recorded).unwrap().verified);
+        recorded.replay_seed.as_mut().unwrap().revealed = Some(Hex::encode([0u8; 32]));
+        assert!(replay_match(&recorded).is_err());
+        assert_eq!(revealed, derive_seed(&SEED_CONFIG.secret, "m1"));
+    }
+
+    #[test]
+    fn test_replay_reports_tampered_state() {
+        let mut recorded = seated_match("m2");
+        let card = recorded.deck.last().unwrap().clone();
+        // 记录中抽到的牌与牌堆顶部不一致
+        let fake = Card { id: "forged".to_string(), ..card };
+        recorded.action_history.push(record(CardActionType::Draw, "alice", &fake));
+        let report = replay_match(&recorded).unwrap();
+        assert!(!report.verified);
+        assert_eq!(report.divergence.as_ref().map(|d| d.seq), Some(0));
+
+        // 手牌被改动
+        let mut recorded = seated_match("m2");
+        recorded.players[1].hand.pop();
+        let report = replay_match(&recorded).unwrap();
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(report.mismatches[0].field, "hand:bob");
+
+        recorded.replay_seed = None;
+        assert!(replay_match(&recorded).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 对局计时器管理模块
+//!
+//! # 概述
+//! 回合超时和连锁等待原本各自启动独立的计时任务，到期时只比较游戏数据中记录的到期时间。
+//! 两个计时器在同一时刻到期时可能先后生效，同一名玩家被淘汰两次或同一个连锁被结算两次。
+//! 本模块统一管理每局游戏的所有计时任务：
+//! - 每种计时器（回合、连锁、目标选择）同时只有一个，重新设置时取消旧的计时任务
+//! - 计时器到期时必须先领取（`claim`），领取成功才执行超时处理
+//! - 每次领取都推进对局的状态版本，同一版本下设置的其他计时器随之失效，
+//!   因此一次状态转换只会有一个超时处理生效
+//!
+//! 连锁等待和目标选择期间回合计时暂停，结束后按剩余时间继续计时。
+//! 游戏数据中的到期时间仍然保留，用于暂停冻结和重启后的恢复。
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use tokio::task::AbortHandle;
+use tokio::time::{sleep, Duration};
+use tracing::debug;
+
+/// 计时器类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimerKind {
+    /// 回合超时
+    Turn,
+    /// 连锁等待
+    Chain,
+    /// 出牌玩家选择目标
+    Selection,
+}
+
+impl TimerKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimerKind::Turn => "turn",
+            TimerKind::Chain => "chain",
+            TimerKind::Selection => "selection",
+        }
+    }
+}
+
+/// 计时器凭证，到期时凭此领取超时处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerTicket {
+    token: u64,
+    transition: u64,
+}
+
+/// 已设置的计时器
+#[derive(Debug)]
+struct ArmedTimer {
+    token: u64,
+    deadline: u64,
+    handle: Option<AbortHandle>,
+}
+
+impl ArmedTimer {
+    fn abort(&self) {
+        if let Some(handle) = &self.handle {
+            handle.abort();
+        }
+    }
+}
+
+/// 单局游戏的计时器
+#[derive(Debug, Default)]
+struct MatchSchedule {
+    /// 状态版本，每次超时处理生效后加一
+    transition: u64,
+    timers: HashMap<TimerKind, ArmedTimer>,
+}
+
+/// 所有对局的计时器
+#[derive(Debug, Default)]
+pub struct MatchTimerManager {
+    next_token: AtomicU64,
+    matches: Mutex<HashMap<String, MatchSchedule>>,
+}
+
+impl MatchTimerManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * 设置计时器，取消同一局游戏中同类型的旧计时器
+     *
+     * 参数:
+     * @param match_id - 游戏ID
+     * @param kind - 计时器类型
+     * @param deadline - 到期时间（毫秒）
+     *
+     * 返回:
+     * 到期时领取超时处理所需的凭证
+     */
+    pub fn arm(&self, match_id: &str, kind: TimerKind, deadline: u64) -> TimerTicket {
+        let token = self.next_token.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut matches = self.matches.lock();
+        let schedule = matches.entry(match_id.to_string()).or_default();
+        let ticket = TimerTicket { token, transition: schedule.transition };
+        let previous = schedule.timers.insert(
+            kind,
+            ArmedTimer { token, deadline, handle: None },
+        );
+        if let Some(previous) = previous {
+            previous.abort();
+        }
+        ticket
+    }
+
+    /// 记录计时任务的句柄，用于取消时终止任务
+    ///
+    /// 计时器已被取消、替换或领取时不再记录，已取消或替换的任务到期后无法领取
+    fn attach(&self, match_id: &str, kind: TimerKind, ticket: TimerTicket, handle: AbortHandle) {
+        let mut matches = self.matches.lock();
+        if let Some(timer) = matches
+            .get_mut(match_id)
+            .and_then(|schedule| schedule.timers.get_mut(&kind))
+            .filter(|timer| timer.token == ticket.token)
+        {
+            timer.handle = Some(handle);
+        }
+    }
+
+    /**
+     * 启动计时任务，到期并领取成功后执行超时处理
+     *
+     * 参数:
+     * @param match_id - 游戏ID
+     * @param kind - 计时器类型
+     * @param deadline - 到期时间（毫秒）
+     * @param delay - 距离到期的时间（毫秒）
+     * @param action - 超时处理
+     */
+    pub fn schedule<F, Fut>(self: &Arc<Self>, match_id: &str, kind: TimerKind, deadline: u64, delay: u64, action: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let ticket = self.arm(match_id, kind, deadline);
+        let manager = self.clone();
+        let match_id_clone = match_id.to_string();
+        let handle = tokio::spawn(async move {
+            sleep(Duration::from_millis(delay)).await;
+            if !manager.claim(&match_id_clone, kind, ticket) {
+                debug!("游戏 {} 的{}计时器已失效，忽略超时处理", match_id_clone, kind.as_str());
+                return;
+            }
+            action().await;
+        });
+        self.attach(match_id, kind, ticket, handle.abort_handle());
+    }
+
+    /**
+     * 领取到期的计时器
+     *
+     * 计时器仍是当前设置的那个，且设置后对局没有发生过其他超时处理时才领取成功。
+     * 领取成功后计时器被移除，状态版本加一。
+     *
+     * 参数:
+     * @param match_id - 游戏ID
+     * @param kind - 计时器类型
+     * @param ticket - 设置计时器时得到的凭证
+     *
+     * 返回:
+     * 领取成功返回true，调用方应执行超时处理
+     */
+    pub fn claim(&self, match_id: &str, kind: TimerKind, ticket: TimerTicket) -> bool {
+        let mut matches = self.matches.lock();
+        let Some(schedule) = matches.get_mut(match_id) else {
+            return false;
+        };
+        let current = schedule
+            .timers
+            .get(&kind)
+            .map(|timer| timer.token == ticket.token)
+            .unwrap_or(false);
+        if !current || schedule.transition != ticket.transition {
+            return false;
+        }
+        schedule.timers.remove(&kind);
+        schedule.transition += 1;
+        true
+    }
+
+    /// 取消计时器，返回是否存在
+    pub fn cancel(&self, match_id: &str, kind: TimerKind) -> bool {
+        let mut matches = self.matches.lock();
+        let Some(timer) = matches
+            .get_mut(match_id)
+            .and_then(|schedule| schedule.timers.remove(&kind))
+        else {
+            return false;
+        };
+        timer.abort();
+        true
+    }
+
+    /// 计时器的到期时间
+    pub fn deadline(&self, match_id: &str, kind: TimerKind) -> Option<u64> {
+        self.matches
+            .lock()
+            .get(match_id)
+            .and_then(|schedule| schedule.timers.get(&kind))
+            .map(|timer| timer.deadline)
+    }
+
+    /// 游戏结束或删除时取消所有计时器
+    pub fn clear(&self, match_id: &str) {
+        if let Some(schedule) = self.matches.lock().remove(match_id) {
+            for timer in schedule.timers.values() {
+                timer.abort();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rearm_invalidates_previous_ticket() {
+        let manager = MatchTimerManager::new();
+        let first = manager.arm("m1", TimerKind::Turn, 100);
+        let second = manager.arm("m1", TimerKind::Turn, 200);
+        assert_eq!(manager.deadline("m1", TimerKind::Turn), Some(200));
+
+        assert!(!manager.claim("m1", TimerKind::Turn, first));
+        assert!(manager.claim("m1", TimerKind::Turn, second));
+        // 同一个计时器只能领取一次
+        assert!(!manager.claim("m1", TimerKind::Turn, second));
+
+        let ticket = manager.arm("m1", TimerKind::Chain, 300);
+        assert!(manager.cancel("m1", TimerKind::Chain));
+        assert!(!manager.claim("m1", TimerKind::Chain, ticket));
+    }
+
+    #[test]
+    fn test_one_timeout_per_transition() {
+        let manager = MatchTimerManager::new();
+        let turn = manager.arm("m1", TimerKind::Turn, 100);
+        let chain = manager.arm("m1", TimerKind::Chain, 100);
+        let other = manager.arm("m2", TimerKind::Turn, 100);
+
+        // 连锁先结算，同一状态下设置的回合计时器不再生效
+        assert!(manager.claim("m1", TimerKind::Chain, chain));
+        assert!(!manager.claim("m1", TimerKind::Turn, turn));
+        assert!(manager.claim("m2", TimerKind::Turn, other));
+
+        // 状态转换后重新设置的计时器正常生效
+        let turn = manager.arm("m1", TimerKind::Turn, 200);
+        assert!(manager.claim("m1", TimerKind::Turn, turn));
+
+        manager.arm("m1", TimerKind::Turn, 300);
+        manager.clear("m1");
+        assert_eq!(manager.deadline("m1", TimerKind::Turn), None);
+    }
+}
@@ -16,13 +16,15 @@
 use axum::{extract::Extension, http::StatusCode, routing::get, Router};
 use dashmap::DashMap;
 use prometheus::{
-    register_histogram_with_registry, register_int_counter_vec_with_registry,
-    register_int_counter_with_registry, Histogram, IntCounter, IntCounterVec, Registry, TextEncoder,
+    register_histogram_vec_with_registry, register_histogram_with_registry,
+    register_int_counter_vec_with_registry, register_int_counter_with_registry,
+    register_int_gauge_vec_with_registry, register_int_gauge_with_registry, Histogram, HistogramVec,
+    IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Registry, TextEncoder,
 };
 use std::net::SocketAddr;
 use std::net::{IpAddr, Ipv4Addr};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio;
 use uuid::Uuid;
 
@@ -274,6 +276,9 @@ pub struct Metrics {
     /// 接收的请求总数
     pub requests: IntCounterVec,
 
+    /// 按网络划分的请求总数
+    pub network_requests: IntCounterVec,
+
     /// 按类型划分的内部错误总数
     pub errors: IntCounterVec,
 
@@ -297,6 +302,36 @@ pub struct Metrics {
 
     /// 按ID数量划分的请求总数
     pub requests_per_number_of_ids: Histogram,
+
+    /// 每个全节点地址是否健康（1为健康）
+    pub sui_endpoint_healthy: IntGaugeVec,
+
+    /// 每个全节点地址的请求失败总数
+    pub sui_endpoint_failures: IntCounterVec,
+
+    /// 排队等待校验的密钥请求数
+    pub key_queue_depth: IntGauge,
+
+    /// 正在校验的密钥请求数
+    pub key_queue_in_flight: IntGauge,
+
+    /// 密钥请求在队列中的等待时间
+    pub key_queue_wait: Histogram,
+
+    /// 按原因划分的被拒绝或中止的密钥请求总数
+    pub key_queue_shed: IntCounterVec,
+
+    /// 对局动作各步骤的耗时
+    pub match_action_duration: HistogramVec,
+
+    /// 按动作划分的慢动作总数
+    pub match_slow_actions: IntCounterVec,
+
+    /// 后台任务是否在运行
+    pub background_task_up: IntGaugeVec,
+
+    /// 后台任务异常退出后的重启总数
+    pub background_task_restarts: IntCounterVec,
 }
 
 /// 定义指标组的枚举类型，替代字符串标识符
@@ -437,6 +472,14 @@ impl MetricsBuilder {
         )
         .map_err(|_| "Failed to register requests counter")?;
 
+        let network_requests = register_int_counter_vec_with_registry!(
+            "citadel_network_requests_total",
+            "Total number of requests received per Sui network",
+            &["network", "type"],
+            requests_registry
+        )
+        .map_err(|_| "Failed to register network requests counter")?;
+
         let errors = register_int_counter_vec_with_registry!(
             "internal_errors",
             "按类型划分的内部错误总数",
@@ -501,8 +544,88 @@ impl MetricsBuilder {
         )
         .unwrap();
 
+        let sui_endpoint_healthy = register_int_gauge_vec_with_registry!(
+            "sui_endpoint_healthy",
+            "全节点地址是否健康",
+            &["network", "url"],
+            &default_registry
+        )
+        .map_err(|_| "Failed to register endpoint health gauge")?;
+
+        let sui_endpoint_failures = register_int_counter_vec_with_registry!(
+            "sui_endpoint_failures_total",
+            "全节点地址的请求失败总数",
+            &["network", "url"],
+            &default_registry
+        )
+        .map_err(|_| "Failed to register endpoint failures counter")?;
+
+        let key_queue_depth = register_int_gauge_with_registry!(
+            "key_request_queue_depth",
+            "排队等待校验的密钥请求数",
+            &default_registry
+        )
+        .map_err(|_| "Failed to register key queue depth gauge")?;
+
+        let key_queue_in_flight = register_int_gauge_with_registry!(
+            "key_request_in_flight",
+            "正在校验的密钥请求数",
+            &default_registry
+        )
+        .map_err(|_| "Failed to register key queue in-flight gauge")?;
+
+        let key_queue_wait = register_histogram_with_registry!(
+            "key_request_queue_wait",
+            "密钥请求在队列中的等待时间",
+            buckets(0.0, 5000.0, 250.0),
+            &default_registry
+        )
+        .map_err(|_| "Failed to register key queue wait histogram")?;
+
+        let key_queue_shed = register_int_counter_vec_with_registry!(
+            "key_request_shed_total",
+            "按原因划分的被拒绝或中止的密钥请求总数",
+            &["reason"],
+            &default_registry
+        )
+        .map_err(|_| "Failed to register key queue shed counter")?;
+
+        let match_action_duration = register_histogram_vec_with_registry!(
+            "match_action_duration",
+            "对局动作各步骤的耗时",
+            &["action", "step"],
+            match_action_duration_buckets(),
+            &default_registry
+        )
+        .map_err(|_| "Failed to register match action duration histogram")?;
+
+        let match_slow_actions = register_int_counter_vec_with_registry!(
+            "match_slow_actions_total",
+            "按动作划分的慢动作总数",
+            &["action"],
+            &default_registry
+        )
+        .map_err(|_| "Failed to register match slow action counter")?;
+
+        let background_task_up = register_int_gauge_vec_with_registry!(
+            "background_task_up",
+            "后台任务是否在运行",
+            &["task"],
+            &default_registry
+        )
+        .map_err(|_| "Failed to register background task gauge")?;
+
+        let background_task_restarts = register_int_counter_vec_with_registry!(
+            "background_task_restarts_total",
+            "后台任务异常退出后的重启总数",
+            &["task"],
+            &default_registry
+        )
+        .map_err(|_| "Failed to register background task restarts counter")?;
+
         Ok(Metrics {
             requests,
+            network_requests,
             errors,
             checkpoint_timestamp_delay,
             get_checkpoint_timestamp_duration,
@@ -511,6 +634,16 @@ impl MetricsBuilder {
             check_policy_duration,
             fetch_pkg_ids_duration,
             requests_per_number_of_ids,
+            sui_endpoint_healthy,
+            sui_endpoint_failures,
+            key_queue_depth,
+            key_queue_in_flight,
+            key_queue_wait,
+            key_queue_shed,
+            match_action_duration,
+            match_slow_actions,
+            background_task_up,
+            background_task_restarts,
         })
     }
 }
@@ -538,6 +671,122 @@ impl Metrics {
         self.requests.with_label_values(&[request_type]).inc();
     }
 
+    /**
+     * 记录指定网络上的请求
+     * 
+     * 同时计入总请求数和按网络划分的请求数
+     * 
+     * 参数:
+     * @param network - 网络名称
+     * @param request_type - 请求类型标识符
+     */
+    pub fn observe_network_request(&self, network: &str, request_type: &str) {
+        self.observe_request(request_type);
+        self.network_requests.with_label_values(&[network, request_type]).inc();
+    }
+
+    /**
+     * 记录全节点地址的健康状态
+     * 
+     * 参数:
+     * @param network - 网络名称
+     * @param url - 全节点地址
+     * @param healthy - 是否健康
+     */
+    pub fn observe_endpoint_health(&self, network: &str, url: &str, healthy: bool) {
+        self.sui_endpoint_healthy
+            .with_label_values(&[network, url])
+            .set(healthy as i64);
+    }
+
+    /**
+     * 记录全节点地址的一次请求失败
+     * 
+     * 参数:
+     * @param network - 网络名称
+     * @param url - 全节点地址
+     */
+    pub fn observe_endpoint_failure(&self, network: &str, url: &str) {
+        self.sui_endpoint_failures.with_label_values(&[network, url]).inc();
+    }
+
+    /**
+     * 记录密钥请求队列的深度
+     * 
+     * 参数:
+     * @param pending - 排队等待的请求数
+     * @param in_flight - 正在校验的请求数
+     */
+    pub fn observe_key_queue_depth(&self, pending: usize, in_flight: usize) {
+        self.key_queue_depth.set(pending as i64);
+        self.key_queue_in_flight.set(in_flight as i64);
+    }
+
+    /**
+     * 记录密钥请求在队列中的等待时间（毫秒）
+     * 
+     * 参数:
+     * @param wait - 等待时间
+     */
+    pub fn observe_key_queue_wait(&self, wait: Duration) {
+        self.key_queue_wait.observe(wait.as_millis() as f64);
+    }
+
+    /**
+     * 记录一次被拒绝或中止的密钥请求
+     * 
+     * 参数:
+     * @param reason - 原因：`queue_full`、`deadline` 或 `timeout`
+     */
+    pub fn observe_key_queue_shed(&self, reason: &str) {
+        self.key_queue_shed.with_label_values(&[reason]).inc();
+    }
+
+    /**
+     * 记录对局动作一个步骤的耗时（毫秒）
+     * 
+     * 参数:
+     * @param action - 动作名称，如 `play_card`
+     * @param step - 步骤名称，`total` 为整个动作
+     * @param elapsed - 耗时
+     */
+    pub fn observe_match_action(&self, action: &str, step: &str, elapsed: Duration) {
+        self.match_action_duration
+            .with_label_values(&[action, step])
+            .observe(elapsed.as_secs_f64() * 1000.0);
+    }
+
+    /**
+     * 记录一次超过阈值的慢动作
+     * 
+     * 参数:
+     * @param action - 动作名称
+     */
+    pub fn observe_slow_match_action(&self, action: &str) {
+        self.match_slow_actions.with_label_values(&[action]).inc();
+    }
+
+    /**
+     * 记录后台任务是否在运行
+     * 
+     * 参数:
+     * @param task - 任务名称
+     * @param up - 是否在运行
+     */
+    pub fn observe_task_up(&self, task: &str, up: bool) {
+        self.background_task_up.with_label_values(&[task]).set(up as i64);
+    }
+
+    /**
+     * 记录后台任务的一次重启
+     * 
+     * 参数:
+     * @param task - 任务名称
+     */
+    pub fn observe_task_restart(&self, task: &str) {
+        self.background_task_restarts.with_label_values(&[task]).inc();
+    }
+
 }
 
 /**
@@ -655,3 +904,16 @@ fn default_external_call_duration_buckets() -> Vec<f64> {
 fn default_fast_call_duration_buckets() -> Vec<f64> {
     buckets(10.0, 100.0, 10.0)
 }
+
+/**
+ * 对局动作耗时桶
+ * 
+ * 缓存读写和广播通常在几毫秒内完成，慢的时候可能达到秒级，
+ * 因此使用从0.5ms到5000ms大致按倍数增长的桶
+ * 
+ * 返回:
+ * 适用于对局动作的桶值数组
+ */
+fn match_action_duration_buckets() -> Vec<f64> {
+    vec![0.5, 1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0]
+}
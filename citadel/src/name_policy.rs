@@ -0,0 +1,359 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Profile名称策略
+//!
+//! # 概述
+//! 创建Profile时提交的名称原先原样接受。本模块在创建和改名时校验名称，依次检查：
+//! - 长度：去掉首尾空白、合并连续空白后按字符计数
+//! - 字符集：只允许字母、数字、空格和 `_` `-` `.`，不能以分隔符开头或结尾，
+//!   也不能混用拉丁字母与西里尔/希腊字母（常见的仿冒手法）
+//! - 保留词：如 `admin`、`system`，名称去掉末尾数字后与保留词相同即拒绝
+//! - 敏感词：名称中包含敏感词即拒绝
+//!
+//! 保留词和敏感词在名称的"骨架"上匹配：全角字符转为半角，西里尔/希腊字母中与拉丁字母形似的转为拉丁字母，
+//! 常见的数字替代（`0`→`o`、`1`→`i`、`3`→`e` 等）还原为字母，转为小写并去掉分隔符，
+//! 因此 `Аdmin`（西里尔字母А）、`ＡＤＭＩＮ`、`4dm1n` 都会命中 `admin`。
+//!
+//! 校验通过后返回规范化的名称：全角字母数字转为半角，首尾空白去掉，连续空白合并为一个空格。
+//! 被拒绝时返回 `NameViolation`，`rule` 说明违反的规则，`message` 为可展示的说明。
+//!
+//! # 配置
+//! - `PROFILE_NAME_MIN_LENGTH` / `PROFILE_NAME_MAX_LENGTH`：名称的字符数范围，默认3到20
+//! - `PROFILE_NAME_RESERVED`：额外的保留词，逗号分隔
+//! - `PROFILE_NAME_PROFANITY_FILE`：额外的敏感词文件，每行一个，`#` 开头的行为注释
+
+use std::collections::HashSet;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+/// 默认的最短名称字符数
+const DEFAULT_MIN_LENGTH: usize = 3;
+/// 默认的最长名称字符数
+const DEFAULT_MAX_LENGTH: usize = 20;
+
+/// 默认保留词
+const DEFAULT_RESERVED: &[&str] = &[
+    "admin",
+    "administrator",
+    "moderator",
+    "system",
+    "server",
+    "support",
+    "official",
+    "staff",
+    "citadel",
+    "catastrophe",
+    "guest",
+    "null",
+    "undefined",
+];
+
+/// 默认敏感词
+const DEFAULT_PROFANITY: &[&str] = &[
+    "fuck", "shit", "bitch", "cunt", "asshole", "nigger", "faggot", "whore", "傻逼", "操你", "草泥马",
+];
+
+/// 名称中允许的分隔符
+const SEPARATORS: &[char] = &[' ', '_', '-', '.'];
+
+/// 违反的规则
+#[derive(Debug, Clone, Copy, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NameRule {
+    /// 长度不在允许范围内
+    Length,
+    /// 包含不允许的字符或混用字母表
+    Charset,
+    /// 与保留词相同
+    Reserved,
+    /// 包含敏感词
+    Profanity,
+}
+
+/// 名称被拒绝的原因
+#[derive(Debug, Clone, Serialize, ToSchema, PartialEq, Eq)]
+pub struct NameViolation {
+    /// 违反的规则
+    pub rule: NameRule,
+    /// 说明
+    pub message: String,
+}
+
+impl NameViolation {
+    fn new(rule: NameRule, message: impl Into<String>) -> Self {
+        Self { rule, message: message.into() }
+    }
+}
+
+impl std::fmt::Display for NameViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self.rule, self.message)
+    }
+}
+
+impl std::error::Error for NameViolation {}
+
+/// 名称策略配置
+#[derive(Debug, Clone)]
+pub struct NamePolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    /// 保留词的骨架
+    reserved: HashSet<String>,
+    /// 敏感词的骨架
+    profanity: Vec<String>,
+}
+
+impl Default for NamePolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_MIN_LENGTH, DEFAULT_MAX_LENGTH, DEFAULT_RESERVED, DEFAULT_PROFANITY)
+    }
+}
+
+impl NamePolicy {
+    pub fn new<R, P>(min_length: usize, max_length: usize, reserved: R, profanity: P) -> Self
+    where
+        R: IntoIterator,
+        R::Item: AsRef<str>,
+        P: IntoIterator,
+        P::Item: AsRef<str>,
+    {
+        Self {
+            min_length,
+            max_length,
+            reserved: reserved.into_iter().map(|w| skeleton(w.as_ref())).filter(|w| !w.is_empty()).collect(),
+            profanity: profanity.into_iter().map(|w| skeleton(w.as_ref())).filter(|w| !w.is_empty()).collect(),
+        }
+    }
+
+    /// 从环境变量读取配置，保留词和敏感词在默认列表上追加
+    pub fn from_env() -> Self {
+        let number = |key: &str, default: usize| {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(default)
+        };
+        let mut reserved: Vec<String> = DEFAULT_RESERVED.iter().map(|w| w.to_string()).collect();
+        if let Ok(extra) = std::env::var("PROFILE_NAME_RESERVED") {
+            reserved.extend(extra.split(',').map(|w| w.trim().to_string()));
+        }
+        let mut profanity: Vec<String> = DEFAULT_PROFANITY.iter().map(|w| w.to_string()).collect();
+        if let Ok(path) = std::env::var("PROFILE_NAME_PROFANITY_FILE") {
+            match std::fs::read_to_string(&path) {
+                Ok(content) => profanity.extend(
+                    content
+                        .lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                        .map(str::to_string),
+                ),
+                Err(e) => warn!("读取敏感词文件 {} 失败: {}", path, e),
+            }
+        }
+        Self::new(
+            number("PROFILE_NAME_MIN_LENGTH", DEFAULT_MIN_LENGTH),
+            number("PROFILE_NAME_MAX_LENGTH", DEFAULT_MAX_LENGTH),
+            reserved,
+            profanity,
+        )
+    }
+
+    /**
+     * 校验名称
+     *
+     * 参数:
+     * @param raw - 用户提交的名称
+     *
+     * 返回:
+     * 通过时返回规范化的名称，否则返回违反的第一条规则
+     */
+    pub fn check(&self, raw: &str) -> Result<String, NameViolation> {
+        let name = normalize_display(raw);
+
+        let length = name.chars().count();
+        if length < self.min_length || length > self.max_length {
+            return Err(NameViolation::new(
+                NameRule::Length,
+                format!("Name must be {} to {} characters long", self.min_length, self.max_length),
+            ));
+        }
+
+        if let Some(c) = name.chars().find(|c| !c.is_alphanumeric() && !SEPARATORS.contains(c)) {
+            return Err(NameViolation::new(
+                NameRule::Charset,
+                format!("Name contains a disallowed character: {:?}", c),
+            ));
+        }
+        if name.starts_with(SEPARATORS) || name.ends_with(SEPARATORS) {
+            return Err(NameViolation::new(
+                NameRule::Charset,
+                "Name cannot start or end with a separator",
+            ));
+        }
+        let latin = name.chars().any(|c| c.is_ascii_alphabetic());
+        if latin && name.chars().any(is_lookalike_script) {
+            return Err(NameViolation::new(
+                NameRule::Charset,
+                "Name cannot mix Latin letters with Cyrillic or Greek letters",
+            ));
+        }
+
+        // 末尾的数字在转换骨架之前去掉，避免被当作字母替代
+        let base = skeleton(name.trim_end_matches(|c: char| c.is_ascii_digit() || SEPARATORS.contains(&c)));
+        let skeleton = skeleton(&name);
+        if self.reserved.contains(&skeleton) || self.reserved.contains(&base) {
+            return Err(NameViolation::new(NameRule::Reserved, "Name is reserved"));
+        }
+
+        if self.profanity.iter().any(|word| skeleton.contains(word.as_str())) {
+            return Err(NameViolation::new(
+                NameRule::Profanity,
+                "Name contains inappropriate language",
+            ));
+        }
+
+        Ok(name)
+    }
+}
+
+/// 全角ASCII字符转为半角，全角空格转为普通空格
+fn fold_width(c: char) -> char {
+    match c {
+        '\u{3000}' => ' ',
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// 名称的展示形式：全角转半角，去掉首尾空白，连续空白合并为一个空格
+fn normalize_display(raw: &str) -> String {
+    let folded: String = raw.chars().map(fold_width).collect();
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 是否是西里尔或希腊字母
+fn is_lookalike_script(c: char) -> bool {
+    matches!(c, '\u{0370}'..='\u{03FF}' | '\u{0400}'..='\u{04FF}')
+}
+
+/// 与拉丁字母形似的字符
+fn fold_homoglyph(c: char) -> char {
+    match c {
+        // 西里尔字母
+        'а' | 'А' => 'a',
+        'в' | 'В' => 'b',
+        'с' | 'С' => 'c',
+        'е' | 'Е' | 'ё' | 'Ё' => 'e',
+        'н' | 'Н' => 'h',
+        'і' | 'І' | 'ї' | 'Ї' => 'i',
+        'ј' | 'Ј' => 'j',
+        'к' | 'К' => 'k',
+        'м' | 'М' => 'm',
+        'о' | 'О' => 'o',
+        'р' | 'Р' => 'p',
+        'ѕ' | 'Ѕ' => 's',
+        'т' | 'Т' => 't',
+        'у' | 'У' => 'y',
+        'х' | 'Х' => 'x',
+        // 希腊字母
+        'α' | 'Α' => 'a',
+        'β' | 'Β' => 'b',
+        'ε' | 'Ε' => 'e',
+        'η' | 'Η' => 'h',
+        'ι' | 'Ι' => 'i',
+        'κ' | 'Κ' => 'k',
+        'μ' | 'Μ' => 'm',
+        'ν' | 'Ν' => 'n',
+        'ο' | 'Ο' => 'o',
+        'ρ' | 'Ρ' => 'p',
+        'τ' | 'Τ' => 't',
+        'υ' | 'Υ' => 'y',
+        'χ' | 'Χ' => 'x',
+        'ζ' | 'Ζ' => 'z',
+        // 数字和符号替代
+        '0' => 'o',
+        '1' | '!' | '|' => 'i',
+        '3' => 'e',
+        '4' | '@' => 'a',
+        '5' | '$' => 's',
+        '7' => 't',
+        _ => c,
+    }
+}
+
+/**
+ * 计算名称的骨架，用于匹配保留词和敏感词
+ *
+ * 参数:
+ * @param name - 名称
+ *
+ * 返回:
+ * 全角转半角、形似字符和数字替代转为拉丁字母、转为小写并去掉分隔符后的字符串
+ */
+pub fn skeleton(name: &str) -> String {
+    name.chars()
+        .map(fold_width)
+        .filter(|c| !c.is_whitespace() && !SEPARATORS.contains(c))
+        .flat_map(char::to_lowercase)
+        .map(fold_homoglyph)
+        .collect()
+}
+
+static GLOBAL_NAME_POLICY: Lazy<NamePolicy> = Lazy::new(|| {
+    let policy = NamePolicy::from_env();
+    info!(
+        "Profile名称策略: 长度 {}-{}，保留词 {} 个，敏感词 {} 个",
+        policy.min_length,
+        policy.max_length,
+        policy.reserved.len(),
+        policy.profanity.len()
+    );
+    policy
+});
+
+/// 获取全局名称策略
+pub fn name_policy() -> &'static NamePolicy {
+    &GLOBAL_NAME_POLICY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(result: Result<String, NameViolation>) -> NameRule {
+        result.unwrap_err().rule
+    }
+
+    #[test]
+    fn test_skeleton() {
+        assert_eq!(skeleton("Аdmin"), "admin");
+        assert_eq!(skeleton("ＡＤＭＩＮ"), "admin");
+        assert_eq!(skeleton("4dm1n"), "admin");
+        assert_eq!(skeleton("s_h-i.t"), "shit");
+    }
+
+    #[test]
+    fn test_check_name() {
+        let policy = NamePolicy::default();
+
+        assert_eq!(policy.check("  Cat   Lover ").unwrap(), "Cat Lover");
+        assert_eq!(policy.check("Ｐｌａｙｅｒ１").unwrap(), "Player1");
+        assert_eq!(policy.check("猫咪玩家").unwrap(), "猫咪玩家");
+
+        assert_eq!(rule(policy.check("ab")), NameRule::Length);
+        assert_eq!(rule(policy.check(&"a".repeat(21))), NameRule::Length);
+        assert_eq!(rule(policy.check("cat<script>")), NameRule::Charset);
+        assert_eq!(rule(policy.check("_cat")), NameRule::Charset);
+        assert_eq!(rule(policy.check("Pаypal")), NameRule::Charset);
+        assert_eq!(rule(policy.check("Admin")), NameRule::Reserved);
+        assert_eq!(rule(policy.check("ＡＤＭＩＮ")), NameRule::Reserved);
+        assert_eq!(rule(policy.check("Guest-1234")), NameRule::Reserved);
+        assert_eq!(rule(policy.check("5h1t_happens")), NameRule::Profanity);
+        assert_eq!(rule(policy.check("我是傻逼")), NameRule::Profanity);
+    }
+}
@@ -0,0 +1,374 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 多网络支持模块
+//!
+//! # 概述
+//! 单个进程可以同时服务多个Sui网络（例如testnet和mainnet），每个网络拥有独立的全节点连接池和 `GameManager`。
+//! 主网络由 `NETWORK` 配置，其余网络由 `SUI_NETWORKS` 配置。请求按以下顺序选择网络：
+//! - 路径前缀 `/net/<name>/...`，前缀在路由前被移除，并转换为 `X-Sui-Network` 请求头
+//! - 请求头 `X-Sui-Network: <name>`
+//! - 两者都没有时使用主网络
+//!
+//! 游戏数据查询（档案、好友关系）按请求的网络路由，并按网络记录到 `citadel_network_requests_total` 指标。
+//! 密钥服务、检查点和gas价格更新依赖主密钥和密钥服务器对象，只在主网络上提供。
+//!
+//! # 配置
+//! - `SUI_NETWORKS`：主网络之外额外服务的网络，逗号分隔，例如 `mainnet,devnet`
+//! - `NODE_URL_<NAME>` / `GRAPHQL_URL_<NAME>` / `EXPLORER_URL_<NAME>`：覆盖该网络的地址，
+//!   devnet/testnet/mainnet 未设置时使用内置地址，其他名称必须设置前两项，节点地址可以用逗号分隔多个
+//! - `CITADEL_MANAGER_ADDRESS_<NAME>` / `CITADEL_FRIENDSHIP_ADDRESS_<NAME>`：该网络上的Citadel对象地址
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use axum::extract::{FromRequestParts, Request};
+use axum::http::request::Parts;
+use axum::http::{HeaderValue, StatusCode, Uri};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use sui_sdk::types::base_types::ObjectID;
+use tracing::{info, warn};
+
+use crate::errors::InternalError;
+use crate::node_pool::{PoolConfig, SuiClientPool};
+use crate::sdk::GameManager;
+use crate::types::Network;
+use crate::AppState;
+
+/// 选择网络的请求头
+pub const NETWORK_HEADER: &str = "x-sui-network";
+/// 选择网络的路径前缀
+pub const NETWORK_PATH_PREFIX: &str = "/net/";
+
+/// 额外网络的配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkSpec {
+    /// 网络名称（小写）
+    pub name: String,
+    /// 网络地址
+    pub network: Network,
+    /// 档案管理对象ID
+    pub manager_store_id: ObjectID,
+    /// 好友关系对象ID
+    pub friendship_store_id: ObjectID,
+}
+
+/// 单个网络的客户端和游戏数据
+pub struct NetworkContext {
+    /// 网络名称
+    pub name: String,
+    /// 网络地址
+    pub network: Network,
+    /// SUI全节点连接池
+    pub sui_pool: Arc<SuiClientPool>,
+    /// 游戏数据管理器
+    pub game_manager: Arc<GameManager>,
+}
+
+impl NetworkContext {
+    /**
+     * 连接额外网络
+     *
+     * 参数:
+     * @param spec - 网络配置
+     *
+     * 返回:
+     * 已连接的网络
+     */
+    pub async fn connect(spec: NetworkSpec) -> Result<Self> {
+        let node_url = spec.network.node_url();
+        let sui_pool = SuiClientPool::connect(&spec.name, &spec.network.node_urls(), PoolConfig::from_env())
+            .await
+            .with_context(|| format!("Sui client build failed with {:?}", node_url))?;
+        let game_manager = GameManager::new(
+            sui_pool.client(),
+            spec.network.clone(),
+            spec.manager_store_id,
+            spec.friendship_store_id,
+        )
+        .await?;
+        info!("网络 {} 已连接，节点: {}", spec.name, node_url);
+        Ok(Self {
+            name: spec.name,
+            network: spec.network,
+            sui_pool: Arc::new(sui_pool),
+            game_manager: Arc::new(game_manager),
+        })
+    }
+}
+
+/// 进程内服务的所有网络
+pub struct NetworkRegistry {
+    /// 主网络
+    default: Arc<NetworkContext>,
+    /// 额外网络，按名称索引
+    others: BTreeMap<String, Arc<NetworkContext>>,
+}
+
+impl NetworkRegistry {
+    /// 创建只包含主网络的注册表
+    pub fn new(default: NetworkContext) -> Self {
+        Self {
+            default: Arc::new(default),
+            others: BTreeMap::new(),
+        }
+    }
+
+    /// 添加额外网络
+    pub fn insert(&mut self, context: NetworkContext) {
+        self.others.insert(context.name.clone(), Arc::new(context));
+    }
+
+    /**
+     * 按 `SUI_NETWORKS` 连接额外网络
+     *
+     * 参数:
+     * @param default - 主网络
+     *
+     * 返回:
+     * 包含所有网络的注册表，任一额外网络配置无效或连接失败时返回错误
+     */
+    pub async fn from_env(default: NetworkContext) -> Result<Self> {
+        let list = std::env::var("SUI_NETWORKS").unwrap_or_default();
+        let specs = parse_network_specs(&list, &default.name, |key| {
+            std::env::var(key).ok().filter(|v| !v.is_empty())
+        })?;
+        let mut registry = Self::new(default);
+        for spec in specs {
+            let name = spec.name.clone();
+            let context = NetworkContext::connect(spec)
+                .await
+                .with_context(|| format!("Failed to connect network {}", name))?;
+            registry.insert(context);
+        }
+        info!("已启用网络: {:?}", registry.names());
+        Ok(registry)
+    }
+
+    /// 主网络
+    pub fn default_network(&self) -> &Arc<NetworkContext> {
+        &self.default
+    }
+
+    /// 额外网络
+    pub fn secondary(&self) -> impl Iterator<Item = &Arc<NetworkContext>> {
+        self.others.values()
+    }
+
+    /// 所有网络名称，主网络在前
+    pub fn names(&self) -> Vec<String> {
+        std::iter::once(self.default.name.clone())
+            .chain(self.others.keys().cloned())
+            .collect()
+    }
+
+    /**
+     * 按名称选择网络
+     *
+     * 参数:
+     * @param name - 请求指定的网络名称，为空时使用主网络
+     *
+     * 返回:
+     * 对应的网络，未配置时返回None
+     */
+    pub fn resolve(&self, name: Option<&str>) -> Option<Arc<NetworkContext>> {
+        let name = match name.map(str::trim).filter(|n| !n.is_empty()) {
+            None => return Some(self.default.clone()),
+            Some(name) => name.to_ascii_lowercase(),
+        };
+        if name == self.default.name {
+            return Some(self.default.clone());
+        }
+        self.others.get(&name).cloned()
+    }
+}
+
+/// 网络名称只允许小写字母、数字、`-` 和 `_`
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// 网络专属的环境变量名，例如 `NODE_URL_MAINNET`
+fn scoped_key(key: &str, name: &str) -> String {
+    format!("{}_{}", key, name.to_ascii_uppercase().replace('-', "_"))
+}
+
+/**
+ * 解析额外网络配置
+ *
+ * 参数:
+ * @param list - 逗号分隔的网络名称
+ * @param primary - 主网络名称，会被跳过
+ * @param env - 读取环境变量
+ *
+ * 返回:
+ * 额外网络配置
+ */
+pub fn parse_network_specs(
+    list: &str,
+    primary: &str,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<NetworkSpec>> {
+    let mut specs: Vec<NetworkSpec> = Vec::new();
+    for name in list.split(',').map(|n| n.trim().to_ascii_lowercase()) {
+        if name.is_empty() {
+            continue;
+        }
+        if !is_valid_name(&name) {
+            return Err(anyhow!("Invalid network name in SUI_NETWORKS: {}", name));
+        }
+        if name == primary || specs.iter().any(|spec| spec.name == name) {
+            warn!("SUI_NETWORKS 中的网络 {} 重复，已忽略", name);
+            continue;
+        }
+        let builtin = match name.as_str() {
+            "devnet" => Some(Network::Devnet),
+            "testnet" => Some(Network::Testnet),
+            "mainnet" => Some(Network::Mainnet),
+            _ => None,
+        };
+        let url = |key: &str, default: Option<String>| {
+            let scoped = scoped_key(key, &name);
+            env(&scoped).or(default).ok_or_else(|| anyhow!("{} must be set", scoped))
+        };
+        let network = Network::Custom {
+            node_url: url("NODE_URL", builtin.as_ref().map(Network::default_node_url))?,
+            graphql_url: url("GRAPHQL_URL", builtin.as_ref().map(Network::default_graphql_url))?,
+            explorer_url: env(&scoped_key("EXPLORER_URL", &name))
+                .or_else(|| builtin.as_ref().map(Network::default_explorer_base_url)),
+        };
+        let object_id = |key: &str| -> Result<ObjectID> {
+            let scoped = scoped_key(key, &name);
+            let value = env(&scoped).ok_or_else(|| anyhow!("{} must be set", scoped))?;
+            ObjectID::from_hex_literal(&value).map_err(|_| anyhow!("Invalid {}", scoped))
+        };
+        specs.push(NetworkSpec {
+            manager_store_id: object_id("CITADEL_MANAGER_ADDRESS")?,
+            friendship_store_id: object_id("CITADEL_FRIENDSHIP_ADDRESS")?,
+            name,
+            network,
+        });
+    }
+    Ok(specs)
+}
+
+/**
+ * 拆分路径中的网络前缀
+ *
+ * 参数:
+ * @param path - 请求路径
+ *
+ * 返回:
+ * 网络名称和去掉前缀后的路径，没有前缀时返回None
+ */
+pub fn split_network_prefix(path: &str) -> Option<(&str, String)> {
+    let rest = path.strip_prefix(NETWORK_PATH_PREFIX)?;
+    let (name, rest) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    if name.is_empty() {
+        return None;
+    }
+    Some((name, rest.to_string()))
+}
+
+/// 将 `/net/<name>/...` 前缀转换为 `X-Sui-Network` 请求头，需要在路由之前执行
+pub async fn network_prefix_middleware(mut request: Request, next: Next) -> Response {
+    let Some((name, path)) = split_network_prefix(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let name = name.to_ascii_lowercase();
+    let path_and_query = match request.uri().query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path,
+    };
+    let mut parts = request.uri().clone().into_parts();
+    let rewritten = path_and_query
+        .parse()
+        .ok()
+        .and_then(|pq| {
+            parts.path_and_query = Some(pq);
+            Uri::from_parts(parts).ok()
+        });
+    let (Some(uri), Ok(value)) = (rewritten, HeaderValue::from_str(&name)) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    *request.uri_mut() = uri;
+    request.headers_mut().insert(NETWORK_HEADER, value);
+    next.run(request).await
+}
+
+/// 按请求选择的网络，未配置的网络返回 `UnknownNetwork`
+pub struct SuiNetwork(pub Arc<NetworkContext>);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for SuiNetwork {
+    type Rejection = InternalError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        let name = parts
+            .headers
+            .get(NETWORK_HEADER)
+            .and_then(|value| value.to_str().ok());
+        state
+            .networks
+            .resolve(name)
+            .map(SuiNetwork)
+            .ok_or(InternalError::UnknownNetwork)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_split_network_prefix() {
+        assert_eq!(
+            split_network_prefix("/net/mainnet/profile/0x1"),
+            Some(("mainnet", "/profile/0x1".to_string()))
+        );
+        assert_eq!(split_network_prefix("/net/mainnet"), Some(("mainnet", "/".to_string())));
+        assert_eq!(split_network_prefix("/net//profile"), None);
+        assert_eq!(split_network_prefix("/network/mainnet"), None);
+        assert_eq!(split_network_prefix("/profile/me"), None);
+    }
+
+    #[test]
+    fn test_parse_network_specs() {
+        let env: HashMap<&str, &str> = HashMap::from([
+            ("CITADEL_MANAGER_ADDRESS_MAINNET", "0x1"),
+            ("CITADEL_FRIENDSHIP_ADDRESS_MAINNET", "0x2"),
+            ("NODE_URL_MAINNET", "https://rpc.example.com"),
+            ("CITADEL_MANAGER_ADDRESS_LOCAL_NET", "0x3"),
+            ("CITADEL_FRIENDSHIP_ADDRESS_LOCAL_NET", "0x4"),
+        ]);
+        let lookup = |key: &str| env.get(key).map(|v| v.to_string());
+
+        // 主网络和重复项被跳过，未设置的地址使用内置地址
+        let specs = parse_network_specs(" Mainnet, testnet,mainnet,", "testnet", lookup).unwrap();
+        assert_eq!(specs.len(), 1);
+        assert_eq!(specs[0].name, "mainnet");
+        assert_eq!(specs[0].manager_store_id, ObjectID::from_hex_literal("0x1").unwrap());
+        assert_eq!(specs[0].network.node_url(), "https://rpc.example.com");
+        assert_eq!(specs[0].network.graphql_url(), Network::Mainnet.default_graphql_url());
+        assert_eq!(
+            specs[0].network.explorer_base_url(),
+            Network::Mainnet.default_explorer_base_url()
+        );
+
+        // 自定义网络必须设置节点地址，所有网络都必须设置对象地址
+        assert!(parse_network_specs("local-net", "testnet", lookup).is_err());
+        assert!(parse_network_specs("devnet", "testnet", lookup).is_err());
+        assert!(parse_network_specs("main net", "testnet", lookup).is_err());
+        assert!(parse_network_specs("", "testnet", lookup).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,280 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 全节点健康状态与降级模式模块
+//!
+//! # 概述
+//! 全节点不可达时，定期更新任务只会记录警告，密钥请求则以各种令人困惑的错误失败。
+//! 本模块维护一个降级模式状态机，由定期更新任务的结果和检查点延迟驱动：
+//! - `healthy`：全节点正常
+//! - `degraded`：连续失败次数达到阈值，或最新检查点超过允许的延迟
+//! - `recovering`：降级后重新请求成功，连续成功次数达到阈值后恢复为 `healthy`，期间再次失败则回到 `degraded`
+//!
+//! 非 `healthy` 状态下，密钥请求返回可重试的 `Degraded` 错误（503，带 `Retry-After`），
+//! 最新检查点超过允许的延迟时返回 `StaleFullNode` 错误（同样为503，带 `Retry-After`），
+//! 纯游戏功能（WebSocket对局、匹配、聊天等）不依赖全节点，继续正常运行。
+//! 当前状态通过 `/readyz` 和 `/v1/service` 公开。
+//!
+//! # 配置
+//! - `NODE_FAILURE_THRESHOLD`：进入降级模式前允许的连续失败次数，默认3
+//! - `NODE_RECOVERY_THRESHOLD`：恢复正常前需要的连续成功次数，默认2
+//! - `ALLOWED_STALENESS_SECS`：密钥请求允许的最新检查点延迟（秒），默认120
+
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::errors::InternalError;
+
+/// 默认连续失败阈值
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+/// 默认连续成功阈值
+pub const DEFAULT_RECOVERY_THRESHOLD: u32 = 2;
+/// 默认允许的全节点数据过时时间
+/// 设置此持续时间时，注意Sui上的时间戳可能比当前时间稍晚，但不应超过一秒。
+pub const DEFAULT_ALLOWED_STALENESS: Duration = Duration::from_secs(120);
+
+/// 全节点健康状态
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeState {
+    /// 正常
+    #[default]
+    Healthy,
+    /// 降级
+    Degraded,
+    /// 恢复中
+    Recovering,
+}
+
+/// 全节点健康状态快照
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeStatus {
+    /// 当前状态
+    pub state: NodeState,
+    /// 进入当前状态的时间
+    pub since: u64,
+    /// 进入降级模式的原因
+    pub reason: Option<String>,
+    /// 连续失败次数
+    pub consecutive_failures: u32,
+}
+
+impl NodeStatus {
+    /// 是否处于降级模式（包括恢复中）
+    pub fn is_degraded(&self) -> bool {
+        self.state != NodeState::Healthy
+    }
+}
+
+/// 全节点健康状态机
+#[derive(Debug)]
+pub struct NodeHealth {
+    failure_threshold: u32,
+    recovery_threshold: u32,
+    allowed_staleness: Duration,
+    inner: Mutex<HealthInner>,
+}
+
+#[derive(Debug, Default)]
+struct HealthInner {
+    status: NodeStatus,
+    consecutive_successes: u32,
+}
+
+impl Default for NodeHealth {
+    fn default() -> Self {
+        Self::new(DEFAULT_FAILURE_THRESHOLD, DEFAULT_RECOVERY_THRESHOLD)
+    }
+}
+
+impl NodeHealth {
+    /// 创建新的健康状态机
+    pub fn new(failure_threshold: u32, recovery_threshold: u32) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            recovery_threshold: recovery_threshold.max(1),
+            allowed_staleness: DEFAULT_ALLOWED_STALENESS,
+            inner: Mutex::new(HealthInner::default()),
+        }
+    }
+
+    /// 设置允许的全节点数据过时时间
+    pub fn with_allowed_staleness(mut self, allowed_staleness: Duration) -> Self {
+        self.allowed_staleness = allowed_staleness;
+        self
+    }
+
+    /// 从环境变量读取阈值
+    pub fn from_env() -> Self {
+        let parse = |key: &str| std::env::var(key).ok().and_then(|v| v.trim().parse::<u32>().ok());
+        let allowed_staleness = std::env::var("ALLOWED_STALENESS_SECS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_ALLOWED_STALENESS);
+        Self::new(
+            parse("NODE_FAILURE_THRESHOLD").unwrap_or(DEFAULT_FAILURE_THRESHOLD),
+            parse("NODE_RECOVERY_THRESHOLD").unwrap_or(DEFAULT_RECOVERY_THRESHOLD),
+        )
+        .with_allowed_staleness(allowed_staleness)
+    }
+
+    /// 密钥请求允许的全节点数据过时时间
+    pub fn allowed_staleness(&self) -> Duration {
+        self.allowed_staleness
+    }
+
+    /**
+     * 检查最新检查点是否在允许的延迟内
+     *
+     * 参数:
+     * @param staleness_ms - 最新检查点的延迟（毫秒）
+     * @param now - 当前时间
+     *
+     * 返回:
+     * 未超过允许的延迟时返回Ok(())，否则进入降级模式并返回 `StaleFullNode`
+     */
+    pub fn ensure_fresh(&self, staleness_ms: i64, now: u64) -> Result<(), InternalError> {
+        if staleness_ms > self.allowed_staleness.as_millis() as i64 {
+            warn!("Full node is stale. Latest checkpoint is {} ms old.", staleness_ms);
+            self.record_stale(now, staleness_ms);
+            return Err(InternalError::StaleFullNode);
+        }
+        Ok(())
+    }
+
+    /// 当前状态快照
+    pub fn status(&self) -> NodeStatus {
+        self.inner.lock().status.clone()
+    }
+
+    /// 是否处于降级模式
+    pub fn is_degraded(&self) -> bool {
+        self.inner.lock().status.is_degraded()
+    }
+
+    /// 降级模式下返回可重试的错误
+    pub fn ensure_available(&self) -> Result<(), InternalError> {
+        if self.is_degraded() {
+            return Err(InternalError::Degraded);
+        }
+        Ok(())
+    }
+
+    /// 记录一次成功的全节点请求
+    pub fn record_success(&self, now: u64) {
+        let mut inner = self.inner.lock();
+        inner.status.consecutive_failures = 0;
+        match inner.status.state {
+            NodeState::Healthy => {}
+            NodeState::Degraded => {
+                inner.consecutive_successes = 1;
+                Self::transition(&mut inner, NodeState::Recovering, now, None);
+                if self.recovery_threshold <= 1 {
+                    Self::transition(&mut inner, NodeState::Healthy, now, None);
+                }
+            }
+            NodeState::Recovering => {
+                inner.consecutive_successes += 1;
+                if inner.consecutive_successes >= self.recovery_threshold {
+                    Self::transition(&mut inner, NodeState::Healthy, now, None);
+                }
+            }
+        }
+    }
+
+    /// 记录一次失败的全节点请求
+    pub fn record_failure(&self, now: u64, reason: impl Into<String>) {
+        let mut inner = self.inner.lock();
+        inner.consecutive_successes = 0;
+        inner.status.consecutive_failures += 1;
+        let should_degrade = match inner.status.state {
+            NodeState::Healthy => inner.status.consecutive_failures >= self.failure_threshold,
+            // 恢复期间再次失败立即回到降级模式
+            NodeState::Recovering => true,
+            NodeState::Degraded => false,
+        };
+        if should_degrade {
+            Self::transition(&mut inner, NodeState::Degraded, now, Some(reason.into()));
+        }
+    }
+
+    /// 检查点过旧时直接进入降级模式
+    pub fn record_stale(&self, now: u64, staleness_ms: i64) {
+        let mut inner = self.inner.lock();
+        inner.consecutive_successes = 0;
+        if inner.status.state != NodeState::Degraded {
+            let reason = format!("最新检查点已延迟 {} ms", staleness_ms);
+            Self::transition(&mut inner, NodeState::Degraded, now, Some(reason));
+        }
+    }
+
+    fn transition(inner: &mut HealthInner, state: NodeState, now: u64, reason: Option<String>) {
+        if inner.status.state == state {
+            return;
+        }
+        match state {
+            NodeState::Degraded => warn!("全节点不可用，进入降级模式: {}", reason.as_deref().unwrap_or("")),
+            NodeState::Recovering => info!("全节点请求恢复成功，等待确认"),
+            NodeState::Healthy => info!("全节点已恢复，退出降级模式"),
+        }
+        inner.status.state = state;
+        inner.status.since = now;
+        if state == NodeState::Healthy {
+            inner.status.reason = None;
+        } else if reason.is_some() {
+            inner.status.reason = reason;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_degrade_and_recover() {
+        let health = NodeHealth::new(3, 2);
+        health.record_failure(1, "timeout");
+        health.record_failure(2, "timeout");
+        assert!(!health.is_degraded());
+
+        health.record_failure(3, "timeout");
+        let status = health.status();
+        assert_eq!(status.state, NodeState::Degraded);
+        assert_eq!(status.since, 3);
+        assert_eq!(status.reason.as_deref(), Some("timeout"));
+        assert!(matches!(health.ensure_available(), Err(InternalError::Degraded)));
+
+        // 恢复期间再次失败回到降级模式
+        health.record_success(4);
+        assert_eq!(health.status().state, NodeState::Recovering);
+        health.record_failure(5, "refused");
+        assert_eq!(health.status().state, NodeState::Degraded);
+
+        health.record_success(6);
+        health.record_success(7);
+        let status = health.status();
+        assert_eq!(status.state, NodeState::Healthy);
+        assert_eq!(status.reason, None);
+        assert!(health.ensure_available().is_ok());
+    }
+
+    #[test]
+    fn test_stale_checkpoint() {
+        let health = NodeHealth::default().with_allowed_staleness(Duration::from_secs(60));
+        assert!(health.ensure_fresh(60_000, 9).is_ok());
+        assert!(!health.is_degraded());
+        assert!(matches!(health.ensure_fresh(60_001, 10), Err(InternalError::StaleFullNode)));
+        assert_eq!(health.status().state, NodeState::Degraded);
+        health.record_success(11);
+        health.record_success(12);
+        assert!(!health.is_degraded());
+    }
+}
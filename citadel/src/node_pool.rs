@@ -0,0 +1,411 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Sui全节点连接池与故障转移模块
+//!
+//! # 概述
+//! 每个网络原本只连接一个全节点，节点不可用时所有链上请求都会失败。本模块允许为每个网络配置多个全节点地址
+//! （按优先级排列），由 `SuiClientPool` 统一管理：
+//! - 通过 `call` 发起的请求失败时，依次在其他地址上重试，每个地址最多尝试一次
+//! - 同一地址连续失败 `NODE_FAILOVER_THRESHOLD` 次后标记为不健康，当前地址切换到优先级最高的健康地址
+//! - 后台任务定期检查所有地址，不健康的地址恢复后重新标记为健康，优先级更高的地址恢复后自动切回
+//! - 每个地址的健康状态和失败次数通过 `sui_endpoint_healthy`、`sui_endpoint_failures_total` 指标公开
+//!
+//! 启动时无法连接的地址会被跳过，所有地址都无法连接时启动失败。
+//!
+//! # 配置
+//! - `NODE_URL` / `NODE_URL_<NAME>`：全节点地址，多个地址用逗号分隔，排在前面的优先
+//! - `NODE_FAILOVER_THRESHOLD`：地址标记为不健康前允许的连续失败次数，默认2
+//! - `NODE_HEALTH_CHECK_INTERVAL_SECS`：后台健康检查的间隔，默认15秒
+
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::Serialize;
+use sui_sdk::error::SuiRpcResult;
+use sui_sdk::{SuiClient, SuiClientBuilder};
+use tracing::{info, warn};
+
+use crate::metrics::Metrics;
+use crate::tasks::TaskManager;
+
+/// 默认连续失败阈值
+pub const DEFAULT_FAILOVER_THRESHOLD: u32 = 2;
+/// 默认健康检查间隔
+pub const DEFAULT_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// 单个地址健康检查的超时时间
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 连接池配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolConfig {
+    /// 地址标记为不健康前允许的连续失败次数
+    pub failover_threshold: u32,
+    /// 后台健康检查的间隔
+    pub health_check_interval: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            failover_threshold: DEFAULT_FAILOVER_THRESHOLD,
+            health_check_interval: DEFAULT_HEALTH_CHECK_INTERVAL,
+        }
+    }
+}
+
+impl PoolConfig {
+    /// 从环境变量读取，未配置或无效的项使用默认值
+    pub fn from_env() -> Self {
+        let parse = |key: &str| {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .filter(|v| *v > 0)
+        };
+        Self {
+            failover_threshold: parse("NODE_FAILOVER_THRESHOLD")
+                .map(|v| v as u32)
+                .unwrap_or(DEFAULT_FAILOVER_THRESHOLD),
+            health_check_interval: parse("NODE_HEALTH_CHECK_INTERVAL_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_HEALTH_CHECK_INTERVAL),
+        }
+    }
+}
+
+/// 单个地址的健康状态
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointHealth {
+    /// 全节点地址
+    pub url: String,
+    /// 是否健康
+    pub healthy: bool,
+    /// 是否为当前使用的地址
+    pub active: bool,
+    /// 连续失败次数
+    pub consecutive_failures: u32,
+    /// 最近一次失败的原因
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct EndpointState {
+    unhealthy: bool,
+    consecutive_failures: u32,
+    last_error: Option<String>,
+}
+
+/// 地址健康状态与当前地址的选择，不涉及网络请求
+#[derive(Debug)]
+struct FailoverState {
+    threshold: u32,
+    active: usize,
+    endpoints: Vec<EndpointState>,
+}
+
+impl FailoverState {
+    fn new(count: usize, threshold: u32) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            active: 0,
+            endpoints: vec![EndpointState::default(); count],
+        }
+    }
+
+    /// 本次调用的尝试顺序：当前地址优先，其余按优先级排列
+    fn attempt_order(&self) -> Vec<usize> {
+        std::iter::once(self.active)
+            .chain((0..self.endpoints.len()).filter(|i| *i != self.active))
+            .collect()
+    }
+
+    /// 记录成功，返回地址是否从不健康恢复
+    fn record_success(&mut self, index: usize) -> bool {
+        let endpoint = &mut self.endpoints[index];
+        let recovered = endpoint.unhealthy;
+        *endpoint = EndpointState::default();
+        // 优先级更高的地址恢复后切回
+        if index < self.active || self.endpoints[self.active].unhealthy {
+            self.active = index;
+        }
+        recovered
+    }
+
+    /// 记录失败，返回地址是否刚被标记为不健康
+    fn record_failure(&mut self, index: usize, error: String) -> bool {
+        let endpoint = &mut self.endpoints[index];
+        endpoint.consecutive_failures += 1;
+        endpoint.last_error = Some(error);
+        if endpoint.unhealthy || endpoint.consecutive_failures < self.threshold {
+            return false;
+        }
+        endpoint.unhealthy = true;
+        if index == self.active {
+            if let Some(next) = self.endpoints.iter().position(|e| !e.unhealthy) {
+                self.active = next;
+            }
+        }
+        true
+    }
+}
+
+struct Endpoint {
+    url: String,
+    client: SuiClient,
+}
+
+/// 单个网络的全节点连接池
+pub struct SuiClientPool {
+    /// 网络名称，用于日志和指标标签
+    network: String,
+    endpoints: Vec<Endpoint>,
+    state: Mutex<FailoverState>,
+    config: PoolConfig,
+    metrics: OnceCell<Metrics>,
+}
+
+impl std::fmt::Debug for SuiClientPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SuiClientPool")
+            .field("network", &self.network)
+            .field("endpoints", &self.endpoints.iter().map(|e| &e.url).collect::<Vec<_>>())
+            .field("active", &self.state.lock().active)
+            .finish()
+    }
+}
+
+impl SuiClientPool {
+    /**
+     * 连接网络的所有全节点地址
+     *
+     * 参数:
+     * @param network - 网络名称
+     * @param urls - 按优先级排列的全节点地址
+     * @param config - 连接池配置
+     *
+     * 返回:
+     * 至少一个地址连接成功时返回连接池，否则返回错误
+     */
+    pub async fn connect(network: &str, urls: &[String], config: PoolConfig) -> Result<Self> {
+        let mut endpoints = Vec::with_capacity(urls.len());
+        for url in urls {
+            match SuiClientBuilder::default().build(url).await {
+                Ok(client) => endpoints.push(Endpoint { url: url.clone(), client }),
+                Err(e) => warn!("网络 {} 的全节点 {} 连接失败，已跳过: {}", network, url, e),
+            }
+        }
+        if endpoints.is_empty() {
+            return Err(anyhow!("网络 {} 的所有全节点都无法连接: {:?}", network, urls));
+        }
+        info!(
+            "网络 {} 已连接 {}/{} 个全节点，当前使用 {}",
+            network,
+            endpoints.len(),
+            urls.len(),
+            endpoints[0].url
+        );
+        Ok(Self::from_endpoints(network, endpoints, config))
+    }
+
+    /// 使用已连接的客户端创建只有一个地址的连接池
+    pub fn from_client(network: &str, url: &str, client: SuiClient) -> Self {
+        let endpoint = Endpoint { url: url.to_string(), client };
+        Self::from_endpoints(network, vec![endpoint], PoolConfig::default())
+    }
+
+    fn from_endpoints(network: &str, endpoints: Vec<Endpoint>, config: PoolConfig) -> Self {
+        Self {
+            network: network.to_string(),
+            state: Mutex::new(FailoverState::new(endpoints.len(), config.failover_threshold)),
+            endpoints,
+            config,
+            metrics: OnceCell::new(),
+        }
+    }
+
+    /// 关联指标，之后每个地址的健康状态和失败次数都会记录到指标中
+    pub fn attach_metrics(&self, metrics: Metrics) {
+        if self.metrics.set(metrics).is_ok() {
+            for endpoint in &self.endpoints {
+                self.observe_health(&endpoint.url, true);
+            }
+        }
+    }
+
+    /// 当前使用的客户端
+    pub fn client(&self) -> SuiClient {
+        self.endpoints[self.state.lock().active].client.clone()
+    }
+
+    /// 当前使用的地址
+    pub fn active_url(&self) -> &str {
+        &self.endpoints[self.state.lock().active].url
+    }
+
+    /// 所有地址的健康状态
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        let state = self.state.lock();
+        self.endpoints
+            .iter()
+            .zip(&state.endpoints)
+            .enumerate()
+            .map(|(i, (endpoint, s))| EndpointHealth {
+                url: endpoint.url.clone(),
+                healthy: !s.unhealthy,
+                active: i == state.active,
+                consecutive_failures: s.consecutive_failures,
+                last_error: s.last_error.clone(),
+            })
+            .collect()
+    }
+
+    /**
+     * 在全节点上执行请求，失败时在其他地址上重试
+     *
+     * 参数:
+     * @param f - 使用客户端发起请求的函数
+     *
+     * 返回:
+     * 第一个成功的结果，所有地址都失败时返回最后一个错误
+     */
+    pub async fn call<T, F, Fut>(&self, f: F) -> SuiRpcResult<T>
+    where
+        F: Fn(SuiClient) -> Fut,
+        Fut: Future<Output = SuiRpcResult<T>>,
+    {
+        let order = self.state.lock().attempt_order();
+        let mut last_error = None;
+        for index in order {
+            match f(self.endpoints[index].client.clone()).await {
+                Ok(value) => {
+                    self.record_success(index);
+                    return Ok(value);
+                }
+                Err(e) => {
+                    self.record_failure(index, e.to_string());
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.expect("pool has at least one endpoint"))
+    }
+
+    fn record_success(&self, index: usize) {
+        let recovered = self.state.lock().record_success(index);
+        if recovered {
+            info!("网络 {} 的全节点 {} 已恢复", self.network, self.endpoints[index].url);
+            self.observe_health(&self.endpoints[index].url, true);
+        }
+    }
+
+    fn record_failure(&self, index: usize, error: String) {
+        let url = &self.endpoints[index].url;
+        warn!("网络 {} 的全节点 {} 请求失败: {}", self.network, url, error);
+        if let Some(metrics) = self.metrics.get() {
+            metrics.observe_endpoint_failure(&self.network, url);
+        }
+        let (marked, active) = {
+            let mut state = self.state.lock();
+            let marked = state.record_failure(index, error);
+            (marked, state.active)
+        };
+        if marked {
+            warn!(
+                "网络 {} 的全节点 {} 标记为不健康，当前使用 {}",
+                self.network, url, self.endpoints[active].url
+            );
+            self.observe_health(url, false);
+        }
+    }
+
+    fn observe_health(&self, url: &str, healthy: bool) {
+        if let Some(metrics) = self.metrics.get() {
+            metrics.observe_endpoint_health(&self.network, url, healthy);
+        }
+    }
+
+    /// 检查一次所有地址
+    async fn check_all(&self) {
+        for (index, endpoint) in self.endpoints.iter().enumerate() {
+            let result = tokio::time::timeout(
+                HEALTH_CHECK_TIMEOUT,
+                endpoint.client.read_api().get_latest_checkpoint_sequence_number(),
+            )
+            .await;
+            match result {
+                Ok(Ok(_)) => self.record_success(index),
+                Ok(Err(e)) => self.record_failure(index, e.to_string()),
+                Err(_) => self.record_failure(index, "健康检查超时".to_string()),
+            }
+        }
+    }
+
+    /**
+     * 启动后台健康检查任务
+     *
+     * 只有一个地址时不启动
+     *
+     * 参数:
+     * @param pool - 连接池
+     * @param tasks - 后台任务管理器
+     */
+    pub fn spawn_health_checker(pool: std::sync::Arc<Self>, tasks: &TaskManager) {
+        if pool.endpoints.len() < 2 {
+            return;
+        }
+        let name = format!("node_pool:{}", pool.network);
+        tasks.spawn(name, move |token| async move {
+            let mut interval = tokio::time::interval(pool.config.health_check_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    _ = interval.tick() => {}
+                }
+                pool.check_all().await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failover_and_recovery() {
+        let mut state = FailoverState::new(3, 2);
+        assert_eq!(state.attempt_order(), vec![0, 1, 2]);
+
+        assert!(!state.record_failure(0, "timeout".to_string()));
+        assert_eq!(state.active, 0);
+        assert!(state.record_failure(0, "timeout".to_string()));
+        assert_eq!(state.active, 1);
+        assert_eq!(state.attempt_order(), vec![1, 0, 2]);
+
+        // 优先级更高的地址恢复后切回
+        assert!(!state.record_success(2));
+        assert_eq!(state.active, 1);
+        assert!(state.record_success(0));
+        assert_eq!(state.active, 0);
+        assert_eq!(state.endpoints[0].consecutive_failures, 0);
+    }
+
+    #[test]
+    fn test_all_unhealthy_keeps_active() {
+        let mut state = FailoverState::new(2, 1);
+        assert!(state.record_failure(0, "down".to_string()));
+        assert_eq!(state.active, 1);
+        assert!(state.record_failure(1, "down".to_string()));
+        assert_eq!(state.active, 1);
+
+        // 任一地址恢复后立即使用
+        state.record_success(0);
+        assert_eq!(state.active, 0);
+    }
+}
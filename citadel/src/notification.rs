@@ -0,0 +1,301 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 推送通知网关模块
+//!
+//! # 概述
+//! 用户离线时WebSocket事件无法送达，本模块将好友请求、游戏邀请等事件转为推送通知，
+//! 通过可插拔的推送渠道发送给用户：
+//! - `webhook`：将通知以JSON形式POST到配置的地址，由外部推送服务转发
+//! - `fcm`：通过Firebase Cloud Messaging推送到用户登记的设备
+//!
+//! 每个用户可以在通知偏好中关闭全部或部分类型的推送，并登记自己的设备令牌。
+//!
+//! # 配置
+//! - `NOTIFICATION_WEBHOOK_URL`：webhook推送地址，为空时不启用
+//! - `FCM_SERVER_KEY`：FCM服务器密钥，为空时不启用
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info};
+use utoipa::ToSchema;
+
+/// FCM推送接口地址
+const FCM_SEND_URL: &str = "https://fcm.googleapis.com/fcm/send";
+/// 每个用户最多登记的设备令牌数量
+pub const MAX_DEVICE_TOKENS: usize = 10;
+
+/// 通知类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// 收到好友请求
+    FriendRequest,
+    /// 收到游戏邀请
+    MatchInvite,
+}
+
+/// 推送通知内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PushNotification {
+    pub user_id: String,
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+    /// 客户端打开通知时使用的附加数据
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
+
+/// 用户通知偏好
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationPreferences {
+    /// 是否接收推送通知
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 是否接收好友请求通知
+    #[serde(default = "default_true")]
+    pub friend_requests: bool,
+    /// 是否接收游戏邀请通知
+    #[serde(default = "default_true")]
+    pub match_invites: bool,
+    /// 登记的设备令牌（FCM）
+    #[serde(default)]
+    pub device_tokens: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            friend_requests: true,
+            match_invites: true,
+            device_tokens: Vec::new(),
+        }
+    }
+}
+
+impl NotificationPreferences {
+    /// 是否接收指定类型的通知
+    pub fn allows(&self, kind: NotificationKind) -> bool {
+        self.enabled
+            && match kind {
+                NotificationKind::FriendRequest => self.friend_requests,
+                NotificationKind::MatchInvite => self.match_invites,
+            }
+    }
+
+    /// 去除空令牌和重复令牌，并限制数量
+    pub fn normalize(&mut self) {
+        let mut tokens = Vec::new();
+        for token in self.device_tokens.drain(..) {
+            let token = token.trim().to_string();
+            if !token.is_empty() && !tokens.contains(&token) {
+                tokens.push(token);
+            }
+        }
+        tokens.truncate(MAX_DEVICE_TOKENS);
+        self.device_tokens = tokens;
+    }
+}
+
+/// 推送渠道
+#[async_trait]
+pub trait NotificationProvider: Send + Sync {
+    /// 渠道名称
+    fn name(&self) -> &'static str;
+
+    /// 发送通知
+    async fn send(&self, preferences: &NotificationPreferences, notification: &PushNotification) -> Result<()>;
+}
+
+/// Webhook推送渠道
+pub struct WebhookProvider {
+    client: Client,
+    url: String,
+}
+
+impl WebhookProvider {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: Client::new(),
+            url,
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationProvider for WebhookProvider {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    async fn send(&self, preferences: &NotificationPreferences, notification: &PushNotification) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .json(&serde_json::json!({
+                "notification": notification,
+                "deviceTokens": preferences.device_tokens,
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// FCM推送渠道
+pub struct FcmProvider {
+    client: Client,
+    server_key: String,
+}
+
+impl FcmProvider {
+    pub fn new(server_key: String) -> Self {
+        Self {
+            client: Client::new(),
+            server_key,
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationProvider for FcmProvider {
+    fn name(&self) -> &'static str {
+        "fcm"
+    }
+
+    async fn send(&self, preferences: &NotificationPreferences, notification: &PushNotification) -> Result<()> {
+        for token in &preferences.device_tokens {
+            self.client
+                .post(FCM_SEND_URL)
+                .header("Authorization", format!("key={}", self.server_key))
+                .json(&serde_json::json!({
+                    "to": token,
+                    "notification": {
+                        "title": notification.title,
+                        "body": notification.body,
+                    },
+                    "data": {
+                        "kind": notification.kind,
+                        "payload": notification.data,
+                    },
+                }))
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+        Ok(())
+    }
+}
+
+/// 推送通知网关
+#[derive(Clone, Default)]
+pub struct NotificationGateway {
+    providers: Vec<Arc<dyn NotificationProvider>>,
+}
+
+impl NotificationGateway {
+    /// 创建不含任何渠道的网关
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 根据环境变量创建网关
+    pub fn from_env() -> Self {
+        let mut gateway = Self::new();
+        if let Some(url) = std::env::var("NOTIFICATION_WEBHOOK_URL").ok().filter(|v| !v.is_empty()) {
+            gateway.add_provider(Arc::new(WebhookProvider::new(url)));
+        }
+        if let Some(key) = std::env::var("FCM_SERVER_KEY").ok().filter(|v| !v.is_empty()) {
+            gateway.add_provider(Arc::new(FcmProvider::new(key)));
+        }
+        info!(
+            "推送通知渠道: {:?}",
+            gateway.providers.iter().map(|p| p.name()).collect::<Vec<_>>()
+        );
+        gateway
+    }
+
+    /// 添加推送渠道
+    pub fn add_provider(&mut self, provider: Arc<dyn NotificationProvider>) {
+        self.providers.push(provider);
+    }
+
+    /// 是否配置了推送渠道
+    pub fn is_enabled(&self) -> bool {
+        !self.providers.is_empty()
+    }
+
+    /**
+     * 按用户偏好发送推送通知
+     *
+     * 通知在后台任务中发送，不阻塞调用方
+     *
+     * 参数:
+     * @param preferences - 用户通知偏好
+     * @param notification - 通知内容
+     *
+     * 返回:
+     * 用户允许且配置了推送渠道时返回true
+     */
+    pub fn dispatch(&self, preferences: NotificationPreferences, notification: PushNotification) -> bool {
+        if !self.is_enabled() || !preferences.allows(notification.kind) {
+            debug!("跳过用户 {} 的 {:?} 推送通知", notification.user_id, notification.kind);
+            return false;
+        }
+
+        let providers = self.providers.clone();
+        tokio::spawn(async move {
+            for provider in providers {
+                if let Err(e) = provider.send(&preferences, &notification).await {
+                    error!(
+                        "通过 {} 向用户 {} 推送通知失败: {}",
+                        provider.name(),
+                        notification.user_id,
+                        e
+                    );
+                }
+            }
+        });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preferences_allow() {
+        let mut preferences = NotificationPreferences::default();
+        assert!(preferences.allows(NotificationKind::FriendRequest));
+        assert!(preferences.allows(NotificationKind::MatchInvite));
+
+        preferences.match_invites = false;
+        assert!(preferences.allows(NotificationKind::FriendRequest));
+        assert!(!preferences.allows(NotificationKind::MatchInvite));
+
+        preferences.enabled = false;
+        assert!(!preferences.allows(NotificationKind::FriendRequest));
+    }
+
+    #[test]
+    fn test_normalize_tokens() {
+        let mut preferences: NotificationPreferences =
+            serde_json::from_value(serde_json::json!({ "deviceTokens": [" a ", "", "a", "b"] })).unwrap();
+        assert!(preferences.enabled);
+        preferences.normalize();
+        assert_eq!(preferences.device_tokens, vec!["a".to_string(), "b".to_string()]);
+    }
+}
@@ -0,0 +1,380 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 外部转播工具的对局观察接口
+//!
+//! # 概述
+//! 直播叠加层、赛事转播等外部工具通过服务端推送事件（SSE）订阅指定对局的实时事件：
+//! - 连接时先推送一次对局的公开视图（`snapshot` 事件），之后推送对局房间中广播的 `match:*` 事件
+//! - 事件在 `ConnectionManager` 向房间广播时复制一份，观察者不加入房间，不占用观战名额，
+//!   对局的观战设置（禁止观战、只允许好友）也不影响观察者
+//! - 推送前清除事件数据中的手牌、牌堆、随机种子和客户端ID等字段，只保留公开信息
+//! - 观察者处理太慢导致事件积压时，跳过积压的事件并推送 `lagged` 事件，客户端可以重新连接获取快照
+//!
+//! 观察者使用单独的密钥认证，每个密钥限制新建连接的频率和同时连接的数量。
+//! 浏览器的 `EventSource` 不能设置请求头，密钥也可以放在 `key` 查询参数中。
+//!
+//! # 接口
+//! - `GET /observer/matches/:match_id/events`：订阅对局事件，需要 `X-Observer-Key` 请求头或 `key` 查询参数
+//!
+//! # 配置
+//! - `OBSERVER_API_KEYS`：逗号分隔的观察者密钥，不配置时关闭观察接口
+//! - `OBSERVER_CONNECT_LIMIT`：每个密钥每分钟允许新建的连接数，默认10，0表示不限流
+//! - `OBSERVER_MAX_STREAMS`：每个密钥同时连接的数量上限，默认5
+
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, Query},
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+    routing::get,
+    Router,
+};
+use futures_util::stream::{self, Stream};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+use crate::errors::InternalError;
+use crate::externals::current_epoch_time;
+use crate::key_audit::RateLimiter;
+use crate::AppState;
+
+/// 默认每个密钥每分钟允许新建的连接数
+const DEFAULT_CONNECT_LIMIT: u32 = 10;
+/// 默认每个密钥同时连接的数量上限
+const DEFAULT_MAX_STREAMS: usize = 5;
+/// 每场对局缓冲的事件数，超过后慢的观察者跳过积压的事件
+const CHANNEL_CAPACITY: usize = 256;
+/// SSE保活注释的间隔
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+/// 推送给观察者的事件前缀
+const OBSERVED_EVENT_PREFIX: &str = "match:";
+/// 推送前从事件数据中清除的字段
+const REDACTED_KEYS: &[&str] = &["hand", "cards", "deck", "seed", "salt", "clientId", "sessionVk"];
+
+/// 观察接口配置
+#[derive(Debug, Clone, Default)]
+pub struct ObserverConfig {
+    /// 允许的观察者密钥
+    pub api_keys: HashSet<String>,
+    /// 每个密钥每分钟允许新建的连接数，0表示不限流
+    pub connect_limit: u32,
+    /// 每个密钥同时连接的数量上限
+    pub max_streams: usize,
+}
+
+impl ObserverConfig {
+    /// 从环境变量读取配置
+    pub fn from_env() -> Self {
+        let parse = |key: &str| std::env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        Self {
+            api_keys: std::env::var("OBSERVER_API_KEYS")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(str::to_string)
+                .collect(),
+            connect_limit: parse("OBSERVER_CONNECT_LIMIT").map(|v| v as u32).unwrap_or(DEFAULT_CONNECT_LIMIT),
+            max_streams: parse("OBSERVER_MAX_STREAMS")
+                .filter(|v| *v > 0)
+                .map(|v| v as usize)
+                .unwrap_or(DEFAULT_MAX_STREAMS),
+        }
+    }
+
+    /// 是否开启观察接口
+    pub fn enabled(&self) -> bool {
+        !self.api_keys.is_empty()
+    }
+}
+
+/// 推送给观察者的事件
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObserverEvent {
+    pub event: String,
+    pub data: Value,
+}
+
+/// 清除事件数据中的非公开字段
+pub fn sanitize(data: &mut Value) {
+    match data {
+        Value::Object(map) => {
+            map.retain(|key, _| !REDACTED_KEYS.contains(&key.as_str()));
+            map.values_mut().for_each(sanitize);
+        }
+        Value::Array(items) => items.iter_mut().for_each(sanitize),
+        _ => {}
+    }
+}
+
+/// 观察者连接的占用，释放时减少密钥的连接数
+pub struct StreamPermit {
+    hub: &'static ObserverHub,
+    key: String,
+}
+
+impl Drop for StreamPermit {
+    fn drop(&mut self) {
+        let mut streams = self.hub.streams.lock();
+        if let Some(count) = streams.get_mut(&self.key) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                streams.remove(&self.key);
+            }
+        }
+    }
+}
+
+/// 观察者事件分发
+pub struct ObserverHub {
+    config: ObserverConfig,
+    /// 有观察者的对局的事件通道
+    channels: Mutex<HashMap<String, broadcast::Sender<ObserverEvent>>>,
+    /// 每个密钥当前的连接数
+    streams: Mutex<HashMap<String, usize>>,
+    connect_limiter: RateLimiter<String>,
+}
+
+impl ObserverHub {
+    pub fn new(config: ObserverConfig) -> Self {
+        let connect_limiter = RateLimiter::per_window(config.connect_limit, Duration::from_secs(60));
+        Self {
+            config,
+            channels: Mutex::new(HashMap::new()),
+            streams: Mutex::new(HashMap::new()),
+            connect_limiter,
+        }
+    }
+
+    pub fn config(&self) -> &ObserverConfig {
+        &self.config
+    }
+
+    /**
+     * 复制房间广播给对局的观察者，没有观察者时直接返回
+     *
+     * 参数:
+     * @param room_id - 房间ID，对局房间的ID就是对局ID
+     * @param event - 事件名称
+     * @param data - 事件数据
+     */
+    pub fn publish(&self, room_id: &str, event: &str, data: Option<&Value>) {
+        if !event.starts_with(OBSERVED_EVENT_PREFIX) {
+            return;
+        }
+        let mut channels = self.channels.lock();
+        let Some(sender) = channels.get(room_id) else {
+            return;
+        };
+        if sender.receiver_count() == 0 {
+            channels.remove(room_id);
+            return;
+        }
+        let mut data = data.cloned().unwrap_or(Value::Null);
+        sanitize(&mut data);
+        let _ = sender.send(ObserverEvent {
+            event: event.to_string(),
+            data,
+        });
+    }
+
+    /// 订阅对局的事件
+    pub fn subscribe(&self, match_id: &str) -> broadcast::Receiver<ObserverEvent> {
+        self.channels
+            .lock()
+            .entry(match_id.to_string())
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// 当前有观察者的对局数
+    pub fn observed_matches(&self) -> usize {
+        self.channels
+            .lock()
+            .values()
+            .filter(|sender| sender.receiver_count() > 0)
+            .count()
+    }
+
+    /**
+     * 认证观察者密钥并占用一个连接
+     *
+     * 参数:
+     * @param key - 观察者密钥
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 连接占用，连接断开时释放；密钥无效、新建连接过于频繁或连接数已满时返回错误
+     */
+    pub fn acquire(&'static self, key: Option<&str>, now: u64) -> Result<StreamPermit, InternalError> {
+        if !self.config.enabled() {
+            return Err(InternalError::Unauthorized);
+        }
+        let key = key.filter(|key| !key.is_empty()).ok_or(InternalError::MissingAuthToken)?;
+        if !self.config.api_keys.contains(key) {
+            return Err(InternalError::Unauthorized);
+        }
+        let key = key.to_string();
+        if !self.connect_limiter.try_acquire(&key, now) {
+            return Err(InternalError::RateLimited);
+        }
+        let mut streams = self.streams.lock();
+        let count = streams.entry(key.clone()).or_insert(0);
+        if *count >= self.config.max_streams {
+            return Err(InternalError::RateLimited);
+        }
+        *count += 1;
+        Ok(StreamPermit { hub: self, key })
+    }
+}
+
+// 全局观察者事件分发
+static GLOBAL_OBSERVER_HUB: Lazy<ObserverHub> = Lazy::new(|| {
+    let hub = ObserverHub::new(ObserverConfig::from_env());
+    if hub.config().enabled() {
+        info!("对局观察接口已开启，共 {} 个密钥", hub.config().api_keys.len());
+    }
+    hub
+});
+
+/// 获取全局观察者事件分发
+pub fn observer_hub() -> &'static ObserverHub {
+    &GLOBAL_OBSERVER_HUB
+}
+
+fn to_sse_event(event: &ObserverEvent) -> Event {
+    Event::default()
+        .event(event.event.as_str())
+        .json_data(&event.data)
+        .unwrap_or_else(|_| Event::default().event(event.event.as_str()))
+}
+
+/// 观察接口的查询参数
+#[derive(Debug, Deserialize)]
+pub struct ObserverQuery {
+    /// 观察者密钥，不能设置请求头的客户端使用
+    pub key: Option<String>,
+}
+
+/// 订阅对局事件
+pub async fn handle_observe_match(
+    headers: HeaderMap,
+    Path(match_id): Path<String>,
+    Query(query): Query<ObserverQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, InternalError> {
+    let hub = observer_hub();
+    let key = headers
+        .get("X-Observer-Key")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .or(query.key);
+    let permit = hub.acquire(key.as_deref(), current_epoch_time()).map_err(|e| {
+        warn!("拒绝观察对局 {}: {:?}", match_id, e);
+        e
+    })?;
+
+    let match_service = crate::gaming::global_match_service().ok_or(InternalError::Failure)?;
+    let match_data = match_service.get_match(&match_id).await.ok_or(InternalError::MatchNotFound)?;
+    // 先订阅再生成快照，快照之后的事件不会丢失
+    let receiver = hub.subscribe(&match_id);
+    let mut snapshot = match_service.match_view(&match_data, None).await.map_err(|_| InternalError::SerializationError)?;
+    sanitize(&mut snapshot);
+    let snapshot = ObserverEvent {
+        event: "snapshot".to_string(),
+        data: snapshot,
+    };
+    info!("外部观察者开始订阅对局 {}", match_id);
+
+    let events = stream::unfold(
+        (Some(snapshot), receiver, permit, match_id),
+        |(snapshot, mut receiver, permit, match_id)| async move {
+            if let Some(snapshot) = snapshot {
+                let event = to_sse_event(&snapshot);
+                return Some((Ok(event), (None, receiver, permit, match_id)));
+            }
+            let event = match receiver.recv().await {
+                Ok(event) => to_sse_event(&event),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    debug!("对局 {} 的观察者跳过 {} 个积压事件", match_id, skipped);
+                    Event::default().event("lagged").data(skipped.to_string())
+                }
+                // 通道关闭时结束推送
+                Err(broadcast::error::RecvError::Closed) => return None,
+            };
+            Some((Ok(event), (None, receiver, permit, match_id)))
+        },
+    );
+    Ok(Sse::new(events).keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL)))
+}
+
+/// 注册对局观察路由
+pub fn register_observer_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router.route("/observer/matches/:match_id/events", get(handle_observe_match))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn test_hub(connect_limit: u32, max_streams: usize) -> &'static ObserverHub {
+        let config = ObserverConfig {
+            api_keys: ["tv".to_string()].into_iter().collect(),
+            connect_limit,
+            max_streams,
+        };
+        Box::leak(Box::new(ObserverHub::new(config)))
+    }
+
+    #[test]
+    fn test_publish_sanitized_match_events() {
+        let hub = test_hub(0, 5);
+        // 没有观察者时不创建通道
+        hub.publish("m1", "match:draw_card", Some(&json!({})));
+        assert_eq!(hub.observed_matches(), 0);
+
+        let mut receiver = hub.subscribe("m1");
+        hub.publish("m1", "system:join", Some(&json!({"clientId": "c1"})));
+        hub.publish("m2", "match:draw_card", None);
+        hub.publish(
+            "m1",
+            "match:state",
+            Some(&json!({"players": [{"id": "a", "hand": ["x"], "handSize": 1}], "seed": "s"})),
+        );
+        let event = receiver.try_recv().unwrap();
+        assert_eq!(event.event, "match:state");
+        assert_eq!(event.data, json!({"players": [{"id": "a", "handSize": 1}]}));
+        assert!(receiver.try_recv().is_err());
+
+        drop(receiver);
+        hub.publish("m1", "match:state", None);
+        assert_eq!(hub.observed_matches(), 0);
+    }
+
+    #[test]
+    fn test_acquire_limits() {
+        let hub = test_hub(0, 2);
+        assert_eq!(hub.acquire(None, 0).err(), Some(InternalError::MissingAuthToken));
+        assert_eq!(hub.acquire(Some("other"), 0).err(), Some(InternalError::Unauthorized));
+
+        let first = hub.acquire(Some("tv"), 0).unwrap();
+        let _second = hub.acquire(Some("tv"), 0).unwrap();
+        assert_eq!(hub.acquire(Some("tv"), 0).err(), Some(InternalError::RateLimited));
+        drop(first);
+        assert!(hub.acquire(Some("tv"), 0).is_ok());
+
+        // 新建连接的频率限制
+        let hub = test_hub(1, 5);
+        let _permit = hub.acquire(Some("tv"), 0).unwrap();
+        assert_eq!(hub.acquire(Some("tv"), 1).err(), Some(InternalError::RateLimited));
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! OpenAPI文档模块
+//!
+//! # 概述
+//! 汇总各模块中通过 `#[utoipa::path]` 标注的REST接口，生成OpenAPI规范，
+//! 供前端根据机器可读的接口契约生成客户端代码：
+//! - `GET /docs/openapi.json`：OpenAPI JSON
+//! - `GET /docs`：Swagger UI
+//!
+//! 新增REST接口时，需要在处理函数上添加 `#[utoipa::path]` 标注，并加入下方 `paths` 列表。
+//! Sui和密码学相关的类型在文档中以字符串或对象表示，具体编码方式与对应的serde实现一致。
+
+use std::sync::Arc;
+
+use axum::Router;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::AppState;
+
+/// Swagger UI路径
+pub const DOCS_ROUTE: &str = "/docs";
+/// OpenAPI JSON路径
+pub const OPENAPI_ROUTE: &str = "/docs/openapi.json";
+
+/// Citadel REST接口文档
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Citadel API", description = "Catastrophe Genesis游戏服务器与Seal密钥服务器接口"),
+    paths(
+        crate::keys::handle_fetch_key,
+        crate::keys::handle_get_service,
+        crate::session_login::handle_session_token,
+        crate::session_login::handle_zklogin,
+        crate::session_login::handler_session_logout,
+        crate::session_login::handler_session_revoke_all,
+        crate::session_login::get_session_credentials,
+        crate::profile::get_my_profile,
+        crate::profile::get_my_stats,
+        crate::profile::get_user_profile,
+        crate::profile::get_user_stats,
+        crate::profile::get_profiles_batch,
+        crate::user_search::handle_search_users,
+        crate::passport::handle_get_relationships,
+        crate::passport::handle_list_friend_requests,
+        crate::account_link::handle_link_challenge,
+        crate::account_link::handle_link_wallet,
+        crate::account_link::handle_list_links,
+        crate::account_link::handle_unlink_wallet,
+        crate::profile_edit::handle_rename_profile,
+        crate::profile_edit::handle_reroll_avatar,
+        crate::profile::get_profile_match_stats,
+        crate::profile::get_profile_rating_history,
+        crate::catastrophe::handle_create_profile,
+        crate::catastrophe::handle_get_profile,
+        crate::catastrophe::handle_get_user_profile,
+        crate::catastrophe::handle_create_guest,
+        crate::catastrophe::handle_upgrade_guest,
+        crate::catastrophe::generate_avatar,
+        crate::catastrophe::handle_admin_send_friend_request,
+        crate::catastrophe::handle_get_relationship,
+        crate::timeline::handle_get_match_timeline,
+        crate::match_attestation::handle_get_match_attestation,
+        crate::calendar::handle_get_calendar,
+        crate::calendar::handle_register,
+        crate::cards::handle_get_cards,
+        crate::ws::ws_stats_doc,
+        crate::ws_schema::handle_ws_schema,
+    ),
+    tags(
+        (name = "keys", description = "Seal密钥服务器"),
+        (name = "auth", description = "会话登录与退出"),
+        (name = "profile", description = "用户档案与统计"),
+        (name = "catastrophe", description = "游戏档案与好友关系，/test前缀的接口仅用于测试"),
+        (name = "matches", description = "对局时间线、赛后复盘与结果存证"),
+        (name = "events", description = "定期活动日历与锦标赛报名"),
+        (name = "cards", description = "卡牌元数据与牌组预设"),
+        (name = "ws", description = "WebSocket服务状态与事件目录"),
+    )
+)]
+pub struct ApiDoc;
+
+/// 注册OpenAPI文档路由
+pub fn register_openapi_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router.merge(SwaggerUi::new(DOCS_ROUTE).url(OPENAPI_ROUTE, ApiDoc::openapi()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_paths() {
+        let doc = ApiDoc::openapi();
+        for path in [
+            "/v1/fetch_key",
+            "/auth/session_token",
+            "/profile/{profile_id}",
+            "/test/avatar",
+            "/ws/stats",
+            "/v1/matches/{match_id}/timeline",
+            "/v1/matches/{match_id}/attestation",
+        ] {
+            assert!(doc.paths.paths.contains_key(path), "缺少接口文档: {}", path);
+        }
+        let schemas = doc.components.expect("缺少schema定义").schemas;
+        assert!(schemas.contains_key("FetchKeyRequest"));
+        assert!(schemas.contains_key("ErrorResponse"));
+    }
+}
@@ -0,0 +1,337 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 慢客户端的发送队列与背压处理模块
+//!
+//! # 概述
+//! 每个连接的发送任务从有界通道中取出消息写入socket。客户端网络较慢时通道会被填满，
+//! 之前房间广播直接丢弃放不进通道的消息，客户端的对局状态因此与服务器不一致。
+//! 本模块为每个连接提供一个发送队列 `Outbox`：
+//! - 通道已满时消息进入溢出缓冲区，发送任务每发出一条消息就从缓冲区补充到通道，保持消息顺序
+//! - 状态类事件（`match:state`、`lobby:update`）在缓冲区中按房间合并，只保留最新的一条
+//! - 缓冲区也满时按配置的策略处理：
+//!   - `coalesce`：丢弃缓冲区中最早的消息
+//!   - `resync`：清空缓冲区，向客户端发送一次 `connection:resync`，客户端应重新请求所在对局的完整状态
+//!   - `disconnect`：关闭连接，客户端通过 `/ws/reconnect` 重连后重新同步
+//!
+//! 丢弃、合并、重新同步和断开的次数计入 `ConnectionStats`。
+//! 被合并的 `match:state` 增量会让客户端发现序号不连续，客户端按增量同步协议发送 `match:state_sync` 即可恢复。
+//!
+//! # 配置
+//! - `WS_OUTBOUND_BUFFER`：每个连接溢出缓冲区的最大消息数，默认256
+//! - `WS_OUTBOUND_POLICY`：缓冲区满时的策略，`coalesce`、`resync` 或 `disconnect`，默认 `resync`
+//!
+//! # 消息格式
+//! `connection:resync` 始终以JSON文本帧发送：
+//! ```json
+//! { "event": "connection:resync", "data": { "ok": false, "msg": "...", "payload": { "dropped": 120 } } }
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axum::extract::ws::Message;
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::i18n::{self, codes};
+use crate::ws::{WsMessage, WsResponse};
+
+/// 事件名称
+pub mod events {
+    /// 客户端需要重新同步状态
+    pub const RESYNC: &str = "connection:resync";
+}
+
+/// 缓冲区中可以合并的状态类事件
+const COALESCED_EVENTS: &[&str] = &["match:state", "lobby:update"];
+
+/// 获取消息的合并键，同一房间的同一状态事件只保留最新一条
+pub fn coalesce_key(room_id: &str, event: &str) -> Option<String> {
+    COALESCED_EVENTS
+        .contains(&event)
+        .then(|| format!("{}|{}", room_id, event))
+}
+
+/// 缓冲区满时的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// 丢弃最早的消息
+    Coalesce,
+    /// 清空缓冲区并通知客户端重新同步
+    #[default]
+    Resync,
+    /// 断开连接
+    Disconnect,
+}
+
+impl OverflowPolicy {
+    pub fn from_str(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "coalesce" => Some(Self::Coalesce),
+            "resync" => Some(Self::Resync),
+            "disconnect" => Some(Self::Disconnect),
+            _ => None,
+        }
+    }
+}
+
+/// 发送队列配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboundConfig {
+    pub max_buffered: usize,
+    pub policy: OverflowPolicy,
+}
+
+impl Default for OutboundConfig {
+    fn default() -> Self {
+        Self {
+            max_buffered: 256,
+            policy: OverflowPolicy::default(),
+        }
+    }
+}
+
+impl OutboundConfig {
+    /// 从环境变量读取，未配置或无效的项使用默认值
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_buffered: std::env::var("WS_OUTBOUND_BUFFER")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(defaults.max_buffered),
+            policy: std::env::var("WS_OUTBOUND_POLICY")
+                .ok()
+                .and_then(|v| OverflowPolicy::from_str(&v))
+                .unwrap_or(defaults.policy),
+        }
+    }
+}
+
+/// 所有连接共享的背压计数
+#[derive(Debug, Default)]
+pub struct OutboundCounters {
+    pub dropped: AtomicUsize,
+    pub coalesced: AtomicUsize,
+    pub resyncs: AtomicUsize,
+    pub disconnects: AtomicUsize,
+}
+
+/// 溢出缓冲区
+#[derive(Debug, Default)]
+struct OutboxState {
+    /// (合并键, 消息)
+    buffered: VecDeque<(Option<String>, Message)>,
+    /// 因缓冲区溢出而关闭
+    closed: bool,
+}
+
+/// 单个连接的发送队列
+#[derive(Debug)]
+pub struct Outbox {
+    client_id: String,
+    sender: mpsc::Sender<Message>,
+    config: OutboundConfig,
+    counters: Arc<OutboundCounters>,
+    state: Mutex<OutboxState>,
+}
+
+impl Outbox {
+    pub fn new(
+        client_id: &str,
+        sender: mpsc::Sender<Message>,
+        config: OutboundConfig,
+        counters: Arc<OutboundCounters>,
+    ) -> Self {
+        Self {
+            client_id: client_id.to_string(),
+            sender,
+            config,
+            counters,
+            state: Mutex::new(OutboxState::default()),
+        }
+    }
+
+    /**
+     * 将消息放入发送队列
+     *
+     * 缓冲区为空时直接写入通道，否则排在缓冲区末尾以保持顺序。
+     *
+     * 参数:
+     * @param message - 已编码的消息帧
+     * @param coalesce_key - 合并键，见 `coalesce_key`
+     *
+     * 返回:
+     * 消息已进入通道或缓冲区时返回true，连接已关闭或消息被丢弃时返回false
+     */
+    pub fn push(&self, message: Message, coalesce_key: Option<String>) -> bool {
+        let mut state = self.state.lock();
+        if state.closed || self.sender.is_closed() {
+            return false;
+        }
+        if state.buffered.is_empty() {
+            match self.sender.try_send(message) {
+                Ok(()) => return true,
+                Err(mpsc::error::TrySendError::Closed(_)) => return false,
+                Err(mpsc::error::TrySendError::Full(message)) => {
+                    state.buffered.push_back((coalesce_key, message));
+                    return true;
+                }
+            }
+        }
+
+        // 同一状态事件只保留最新的一条
+        if let Some(key) = &coalesce_key {
+            if let Some(slot) = state.buffered.iter_mut().find(|(k, _)| k.as_ref() == Some(key)) {
+                slot.1 = message;
+                self.counters.coalesced.fetch_add(1, Ordering::Relaxed);
+                return true;
+            }
+        }
+
+        if state.buffered.len() < self.config.max_buffered {
+            state.buffered.push_back((coalesce_key, message));
+            return true;
+        }
+        self.overflow(&mut state, message, coalesce_key)
+    }
+
+    /// 缓冲区已满时按策略处理
+    fn overflow(&self, state: &mut OutboxState, message: Message, coalesce_key: Option<String>) -> bool {
+        match self.config.policy {
+            OverflowPolicy::Coalesce => {
+                state.buffered.pop_front();
+                state.buffered.push_back((coalesce_key, message));
+                self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            OverflowPolicy::Resync => {
+                let dropped = state.buffered.len() + 1;
+                warn!("客户端 {} 发送队列溢出，丢弃 {} 条消息并要求重新同步", self.client_id, dropped);
+                state.buffered.clear();
+                self.counters.dropped.fetch_add(dropped, Ordering::Relaxed);
+                self.counters.resyncs.fetch_add(1, Ordering::Relaxed);
+                if let Some(notice) = resync_notice(dropped) {
+                    state.buffered.push_back((None, notice));
+                }
+                false
+            }
+            OverflowPolicy::Disconnect => {
+                let dropped = state.buffered.len() + 1;
+                warn!("客户端 {} 发送队列溢出，断开连接", self.client_id);
+                state.buffered.clear();
+                state.closed = true;
+                self.counters.dropped.fetch_add(dropped, Ordering::Relaxed);
+                self.counters.disconnects.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+        }
+    }
+
+    /// 发送任务每取出一条消息后调用，将缓冲区中的消息补充到通道
+    pub fn refill(&self) {
+        let mut state = self.state.lock();
+        while let Some((key, message)) = state.buffered.pop_front() {
+            match self.sender.try_send(message) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(message)) => {
+                    state.buffered.push_front((key, message));
+                    return;
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    state.buffered.clear();
+                    return;
+                }
+            }
+        }
+    }
+
+    /// 是否因缓冲区溢出需要断开连接
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().closed
+    }
+
+    /// 缓冲区中的消息数
+    pub fn buffered(&self) -> usize {
+        self.state.lock().buffered.len()
+    }
+}
+
+/// 构造重新同步通知
+fn resync_notice(dropped: usize) -> Option<Message> {
+    let response = WsResponse {
+        ok: false,
+        payload: Some(serde_json::json!({ "dropped": dropped })),
+        ..WsResponse::from_text(i18n::text(codes::CONNECTION_RESYNC, &[]))
+    };
+    let message = WsMessage {
+        event: events::RESYNC.to_string(),
+        data: serde_json::to_value(response).ok(),
+        msg_id: None,
+    };
+    serde_json::to_string(&message).ok().map(Message::Text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_outbox(capacity: usize, config: OutboundConfig) -> (Outbox, mpsc::Receiver<Message>, Arc<OutboundCounters>) {
+        let (tx, rx) = mpsc::channel(capacity);
+        let counters = Arc::new(OutboundCounters::default());
+        (Outbox::new("c1", tx, config, counters.clone()), rx, counters)
+    }
+
+    fn text(s: &str) -> Message {
+        Message::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_buffer_keeps_order_and_coalesces() {
+        let (outbox, mut rx, counters) = new_outbox(1, OutboundConfig::default());
+        assert!(outbox.push(text("a"), None));
+        assert!(outbox.push(text("s1"), coalesce_key("m1", "match:state")));
+        assert!(outbox.push(text("b"), None));
+        assert!(outbox.push(text("s2"), coalesce_key("m1", "match:state")));
+        assert_eq!(outbox.buffered(), 2);
+        assert_eq!(counters.coalesced.load(Ordering::Relaxed), 1);
+
+        let mut received = Vec::new();
+        while let Ok(message) = rx.try_recv() {
+            received.push(message);
+            outbox.refill();
+        }
+        assert_eq!(received, vec![text("a"), text("s2"), text("b")]);
+        assert!(coalesce_key("m1", "match:start").is_none());
+    }
+
+    #[test]
+    fn test_overflow_policies() {
+        let config = OutboundConfig { max_buffered: 1, policy: OverflowPolicy::Resync };
+        let (outbox, mut rx, counters) = new_outbox(1, config);
+        outbox.push(text("a"), None);
+        outbox.push(text("b"), None);
+        assert!(!outbox.push(text("c"), None));
+        assert_eq!(counters.dropped.load(Ordering::Relaxed), 2);
+        assert_eq!(counters.resyncs.load(Ordering::Relaxed), 1);
+        assert_eq!(rx.try_recv().unwrap(), text("a"));
+        outbox.refill();
+        let Message::Text(notice) = rx.try_recv().unwrap() else {
+            panic!("重新同步通知应为文本帧");
+        };
+        assert!(notice.contains(events::RESYNC));
+
+        let config = OutboundConfig { max_buffered: 1, policy: OverflowPolicy::Disconnect };
+        let (outbox, _rx, counters) = new_outbox(1, config);
+        outbox.push(text("a"), None);
+        outbox.push(text("b"), None);
+        assert!(!outbox.push(text("c"), None));
+        assert!(outbox.is_closed());
+        assert!(!outbox.push(text("d"), None));
+        assert_eq!(counters.disconnects.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,545 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 组队匹配模块
+//!
+//! # 概述
+//! 2~3名好友可以组成队伍一起匹配：
+//! 1. 队长发送 `party:create` 创建队伍
+//! 2. 队员发送 `party:invite` 邀请好友，被邀请者收到 `party:invited`
+//! 3. 被邀请者发送 `party:join` 加入队伍，队伍变化时所有队员收到 `party:updated`
+//! 4. 队长发送 `queue:join` 时整个队伍作为一个整体进入匹配队列，匹配时队伍成员一定进入同一局游戏，
+//!    剩余座位由单人玩家补齐
+//!
+//! 队伍成员变化（加入、离开）时，已在队列中的队伍会被移出队列，需要队长重新加入。
+//! 队伍只保存在内存中，服务重启后丢失。
+//!
+//! # 消息格式
+//! ```json
+//! // 客户端 -> 服务器
+//! { "event": "party:create" }
+//! { "event": "party:invite", "data": { "userId": "..." } }
+//! { "event": "party:join", "data": { "partyId": "..." } }
+//! { "event": "party:leave" }
+//! // 服务器 -> 客户端
+//! { "event": "party:updated", "data": { "ok": true, "payload": { "id": "...", "leaderId": "...", "members": [...], "invited": [...], "queued": false } } }
+//! { "event": "party:invited", "data": { "ok": true, "payload": { "partyId": "...", "inviter": {...} } } }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::gaming::UserInfo;
+use crate::i18n::{self, codes};
+use crate::ws::{ConnectionManager, WsMessage, WsResponse};
+
+/// 队伍人数上限
+pub const MAX_PARTY_SIZE: usize = 3;
+
+/// 组队相关事件
+pub mod events {
+    /// 创建队伍
+    pub const CREATE: &str = "party:create";
+    /// 邀请好友加入队伍
+    pub const INVITE: &str = "party:invite";
+    /// 加入队伍
+    pub const JOIN: &str = "party:join";
+    /// 离开队伍
+    pub const LEAVE: &str = "party:leave";
+    /// 队伍状态更新
+    pub const UPDATED: &str = "party:updated";
+    /// 收到组队邀请
+    pub const INVITED: &str = "party:invited";
+}
+
+/// `party:invite` 数据
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PartyInviteDto {
+    /// 被邀请的好友ID
+    pub user_id: String,
+}
+
+/// `party:join` 数据
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PartyJoinDto {
+    /// 队伍ID
+    pub party_id: String,
+}
+
+/// 队伍
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Party {
+    /// 队伍ID
+    pub id: String,
+    /// 队长ID
+    pub leader_id: String,
+    /// 队员，第一位为队长
+    #[schema(value_type = Vec<Object>)]
+    pub members: Vec<UserInfo>,
+    /// 已邀请但尚未加入的用户ID
+    pub invited: Vec<String>,
+    /// 是否在匹配队列中
+    pub queued: bool,
+    /// 创建时间
+    pub created_at: u64,
+}
+
+impl Party {
+    /// 是否包含该成员
+    pub fn has_member(&self, user_id: &str) -> bool {
+        self.members.iter().any(|m| m.id == user_id)
+    }
+
+    /// 成员ID列表
+    pub fn member_ids(&self) -> Vec<String> {
+        self.members.iter().map(|m| m.id.clone()).collect()
+    }
+}
+
+/// 匹配队列中的一项：单人玩家或整个队伍
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueEntry {
+    /// 队伍ID，单人玩家为None
+    pub party_id: Option<String>,
+    /// 玩家，队伍成员必须进入同一局游戏
+    pub members: Vec<UserInfo>,
+    /// 进入队列的时间
+    pub enqueued_at: u64,
+    /// 匹配地区提示，None表示可以和任何地区匹配
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+}
+
+impl QueueEntry {
+    /// 单人玩家
+    pub fn solo(user: UserInfo, now: u64) -> Self {
+        Self {
+            party_id: None,
+            members: vec![user],
+            enqueued_at: now,
+            region: None,
+        }
+    }
+
+    /// 整个队伍
+    pub fn party(party: &Party, now: u64) -> Self {
+        Self {
+            party_id: Some(party.id.clone()),
+            members: party.members.clone(),
+            enqueued_at: now,
+            region: None,
+        }
+    }
+
+    /// 设置匹配地区提示
+    pub fn with_region(mut self, region: Option<String>) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// 是否包含该玩家
+    pub fn contains(&self, user_id: &str) -> bool {
+        self.members.iter().any(|m| m.id == user_id)
+    }
+}
+
+/**
+ * 从匹配队列中选出一局游戏的玩家
+ *
+ * 按进入队列的顺序依次选取，队伍作为整体要么全部选中要么跳过，放不下的队伍留给下一局
+ *
+ * 参数:
+ * @param queue - 匹配队列
+ * @param max_players - 每局游戏的人数上限
+ *
+ * 返回:
+ * 选中的队列项下标，至少需要两项且两名玩家，否则返回None
+ */
+pub fn plan_match(queue: &[QueueEntry], max_players: usize) -> Option<Vec<usize>> {
+    let mut selected = Vec::new();
+    let mut seats = 0;
+    for (index, entry) in queue.iter().enumerate() {
+        if seats + entry.members.len() > max_players {
+            continue;
+        }
+        seats += entry.members.len();
+        selected.push(index);
+        if seats == max_players {
+            break;
+        }
+    }
+    // 队伍不能只和自己匹配
+    if selected.len() < 2 || seats < 2 {
+        return None;
+    }
+    Some(selected)
+}
+
+#[derive(Debug, Default)]
+struct PartyBook {
+    /// 队伍，按ID索引
+    parties: HashMap<String, Party>,
+    /// 用户所在的队伍
+    members: HashMap<String, String>,
+}
+
+/// 组队服务
+pub struct PartyService {
+    /// WebSocket连接管理器
+    connection_manager: Arc<ConnectionManager>,
+    book: Mutex<PartyBook>,
+}
+
+impl PartyService {
+    /// 创建新的组队服务
+    pub fn new(connection_manager: Arc<ConnectionManager>) -> Self {
+        Self {
+            connection_manager,
+            book: Mutex::new(PartyBook::default()),
+        }
+    }
+
+    fn now() -> u64 {
+        chrono::Utc::now().timestamp_millis() as u64
+    }
+
+    /// 用户所在的队伍
+    pub fn party_of(&self, user_id: &str) -> Option<Party> {
+        let book = self.book.lock();
+        book.members.get(user_id).and_then(|id| book.parties.get(id)).cloned()
+    }
+
+    /// 创建队伍
+    pub fn create(&self, leader: UserInfo) -> Result<Party> {
+        let mut book = self.book.lock();
+        if book.members.contains_key(&leader.id) {
+            return Err(anyhow!("已经在队伍中"));
+        }
+        let party = Party {
+            id: Uuid::new_v4().to_string(),
+            leader_id: leader.id.clone(),
+            members: vec![leader],
+            invited: Vec::new(),
+            queued: false,
+            created_at: Self::now(),
+        };
+        book.members.insert(party.leader_id.clone(), party.id.clone());
+        book.parties.insert(party.id.clone(), party.clone());
+        info!("玩家 {} 创建队伍 {}", party.leader_id, party.id);
+        Ok(party)
+    }
+
+    /**
+     * 邀请用户加入队伍，好友关系由调用方检查
+     *
+     * 参数:
+     * @param inviter_id - 邀请者ID，必须是队伍成员
+     * @param invitee_id - 被邀请者ID
+     *
+     * 返回:
+     * 更新后的队伍
+     */
+    pub fn invite(&self, inviter_id: &str, invitee_id: &str) -> Result<Party> {
+        let mut book = self.book.lock();
+        if book.members.contains_key(invitee_id) {
+            return Err(anyhow!("对方已经在队伍中"));
+        }
+        let party_id = book.members.get(inviter_id).cloned().ok_or_else(|| anyhow!("不在任何队伍中"))?;
+        let party = book.parties.get_mut(&party_id).ok_or_else(|| anyhow!("队伍不存在"))?;
+        if party.members.len() >= MAX_PARTY_SIZE {
+            return Err(anyhow!("队伍人数已满"));
+        }
+        if !party.invited.iter().any(|id| id == invitee_id) {
+            party.invited.push(invitee_id.to_string());
+        }
+        Ok(party.clone())
+    }
+
+    /**
+     * 接受邀请加入队伍
+     *
+     * 参数:
+     * @param user - 加入的玩家
+     * @param party_id - 队伍ID
+     *
+     * 返回:
+     * 更新后的队伍
+     */
+    pub fn join(&self, user: UserInfo, party_id: &str) -> Result<Party> {
+        let mut book = self.book.lock();
+        if book.members.contains_key(&user.id) {
+            return Err(anyhow!("已经在队伍中"));
+        }
+        let party = book.parties.get_mut(party_id).ok_or_else(|| anyhow!("队伍不存在"))?;
+        let Some(pos) = party.invited.iter().position(|id| *id == user.id) else {
+            return Err(anyhow!("没有收到该队伍的邀请"));
+        };
+        if party.members.len() >= MAX_PARTY_SIZE {
+            return Err(anyhow!("队伍人数已满"));
+        }
+        party.invited.remove(pos);
+        party.members.push(user.clone());
+        party.queued = false;
+        let party = party.clone();
+        book.members.insert(user.id.clone(), party.id.clone());
+        info!("玩家 {} 加入队伍 {}", user.id, party.id);
+        Ok(party)
+    }
+
+    /**
+     * 离开队伍，队长离开时由下一名队员接任，最后一名队员离开时解散队伍
+     *
+     * 参数:
+     * @param user_id - 离开的玩家
+     *
+     * 返回:
+     * 离开前的队伍和离开后剩余的队伍（已解散为None）
+     */
+    pub fn leave(&self, user_id: &str) -> Result<(Party, Option<Party>)> {
+        let mut book = self.book.lock();
+        let party_id = book.members.remove(user_id).ok_or_else(|| anyhow!("不在任何队伍中"))?;
+        let party = book.parties.get_mut(&party_id).ok_or_else(|| anyhow!("队伍不存在"))?;
+        let before = party.clone();
+        party.members.retain(|m| m.id != user_id);
+        party.queued = false;
+        if party.members.is_empty() {
+            book.parties.remove(&party_id);
+            info!("队伍 {} 已解散", party_id);
+            return Ok((before, None));
+        }
+        if party.leader_id == user_id {
+            party.leader_id = party.members[0].id.clone();
+        }
+        Ok((before, Some(party.clone())))
+    }
+
+    /// 设置队伍的排队状态
+    pub fn set_queued(&self, party_id: &str, queued: bool) {
+        if let Some(party) = self.book.lock().parties.get_mut(party_id) {
+            party.queued = queued;
+        }
+    }
+
+    /// 向队伍的所有成员发送队伍状态
+    pub async fn broadcast_party(&self, party: &Party) -> Result<()> {
+        let response = WsResponse {
+            ok: true,
+            payload: Some(serde_json::to_value(party)?),
+            ..WsResponse::from_text(i18n::text(codes::PARTY_UPDATED, &[]))
+        };
+        let data = Some(serde_json::to_value(response)?);
+        for member in &party.members {
+            self.connection_manager.send_to_client(&member.id, events::UPDATED, data.clone()).await?;
+        }
+        Ok(())
+    }
+
+    /// 向客户端发送组队操作失败的响应
+    async fn send_failure(&self, client_id: &str, reason: &str) -> Result<()> {
+        let response = WsResponse {
+            ok: false,
+            ..WsResponse::from_text(i18n::text(codes::PARTY_FAILED, &[("reason", reason.to_string())]))
+        };
+        self.connection_manager
+            .send_to_client(client_id, events::UPDATED, Some(serde_json::to_value(response)?))
+            .await?;
+        Ok(())
+    }
+
+    /// 队伍成员变化后，将已排队的队伍移出匹配队列
+    async fn dequeue(&self, party: &Party) {
+        if !party.queued {
+            return;
+        }
+        if let Some(match_service) = crate::gaming::global_match_service() {
+            match_service.dequeue_party(&party.id).await;
+        }
+    }
+
+    /// 邀请好友，只能邀请好友
+    async fn handle_invite(&self, user: &UserInfo, friend_id: &str) -> Result<Party> {
+        let passport_state = crate::ws::global_passport_state()
+            .ok_or_else(|| anyhow!("用户护照服务未初始化"))?;
+        let friends = passport_state.get_user_friends(&user.id).await?;
+        if !friends.iter().any(|id| id == friend_id) {
+            return Err(anyhow!("只能邀请好友"));
+        }
+        let party = self.invite(&user.id, friend_id)?;
+        let response = WsResponse {
+            ok: true,
+            payload: Some(serde_json::json!({
+                "partyId": party.id,
+                "inviter": user,
+            })),
+            ..WsResponse::from_text(i18n::text(codes::PARTY_INVITED, &[("user", user.name.clone())]))
+        };
+        passport_state
+            .send_event_to_user(friend_id, events::INVITED, Some(serde_json::to_value(response)?))
+            .await?;
+        Ok(party)
+    }
+
+    /// 处理组队事件，返回需要广播的队伍
+    async fn dispatch(&self, message: &WsMessage, user: UserInfo) -> Result<Option<Party>> {
+        let data = message.data.clone().unwrap_or_default();
+        match message.event.as_str() {
+            events::CREATE => Ok(Some(self.create(user)?)),
+            events::INVITE => {
+                let dto: PartyInviteDto = serde_json::from_value(data)?;
+                Ok(Some(self.handle_invite(&user, &dto.user_id).await?))
+            }
+            events::JOIN => {
+                let dto: PartyJoinDto = serde_json::from_value(data)?;
+                let before = self.party_by_id(&dto.party_id);
+                let party = self.join(user, &dto.party_id)?;
+                // 加入前的队伍如果在排队，需要移出队列
+                if let Some(before) = before {
+                    self.dequeue(&before).await;
+                }
+                Ok(Some(party))
+            }
+            events::LEAVE => {
+                let (before, after) = self.leave(&user.id)?;
+                self.dequeue(&before).await;
+                // 通知离开者已不在队伍中
+                self.connection_manager
+                    .send_to_client(&user.id, events::UPDATED, Some(serde_json::to_value(WsResponse {
+                        ok: true,
+                        payload: Some(serde_json::Value::Null),
+                        ..Default::default()
+                    })?))
+                    .await?;
+                Ok(after)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn party_by_id(&self, party_id: &str) -> Option<Party> {
+        self.book.lock().parties.get(party_id).cloned()
+    }
+}
+
+// 用于存储全局PartyService实例的静态变量
+static GLOBAL_PARTY_SERVICE: OnceCell<Arc<PartyService>> = OnceCell::new();
+
+/// 初始化组队服务并设置为全局实例
+pub fn init_party_service(connection_manager: Arc<ConnectionManager>) -> Arc<PartyService> {
+    let service = Arc::new(PartyService::new(connection_manager.clone()));
+    let _ = GLOBAL_PARTY_SERVICE.set(service.clone());
+    
+    let party_service = service.clone();
+    connection_manager.register_handler("party:*", move |event| {
+        let party_service = party_service.clone();
+        async move {
+            let user_info = event.user.map(|u| UserInfo::with_rating(u.id, u.name, u.avatar_url));
+            handle_ws_message(&event.client_id, event.message, &party_service, user_info).await
+        }
+    });
+    service
+}
+
+/// 获取全局组队服务
+pub fn global_party_service() -> Option<Arc<PartyService>> {
+    GLOBAL_PARTY_SERVICE.get().cloned()
+}
+
+/// 处理WebSocket消息
+pub async fn handle_ws_message(
+    client_id: &str,
+    message: WsMessage,
+    party_service: &PartyService,
+    user_info: Option<UserInfo>,
+) -> Result<bool> {
+    debug!("处理组队事件: {}", message.event);
+    if !matches!(message.event.as_str(), events::CREATE | events::INVITE | events::JOIN | events::LEAVE) {
+        return Ok(false);
+    }
+    let Some(user) = user_info else {
+        warn!("用户未认证，无法处理组队事件");
+        return Ok(false);
+    };
+    match party_service.dispatch(&message, user).await {
+        Ok(Some(party)) => party_service.broadcast_party(&party).await?,
+        Ok(None) => {}
+        Err(e) => {
+            info!("组队操作 {} 失败: {}", message.event, e);
+            party_service.send_failure(client_id, &e.to_string()).await?;
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: &str) -> UserInfo {
+        UserInfo {
+            id: id.to_string(),
+            name: id.to_string(),
+            rating: 1000,
+            avatar_url: None,
+            provisional: false,
+        }
+    }
+
+    fn entry(ids: &[&str]) -> QueueEntry {
+        QueueEntry {
+            party_id: (ids.len() > 1).then(|| ids.join("+")),
+            members: ids.iter().map(|id| user(id)).collect(),
+            enqueued_at: 0,
+            region: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_match() {
+        // 队伍不能只和自己匹配
+        assert_eq!(plan_match(&[entry(&["a", "b"])], 4), None);
+        assert_eq!(plan_match(&[entry(&["a"])], 4), None);
+
+        // 队伍作为整体，放不下时跳过，由后面的单人玩家补齐
+        let queue = vec![entry(&["a", "b"]), entry(&["c"]), entry(&["d", "e", "f"]), entry(&["g"])];
+        assert_eq!(plan_match(&queue, 4), Some(vec![0, 1, 3]));
+
+        let queue = vec![entry(&["a", "b", "c"]), entry(&["d", "e"]), entry(&["f"])];
+        assert_eq!(plan_match(&queue, 4), Some(vec![0, 2]));
+    }
+
+    #[test]
+    fn test_party_membership() {
+        let service = PartyService::new(Arc::new(ConnectionManager::new()));
+        let party = service.create(user("a")).unwrap();
+        assert!(service.create(user("a")).is_err());
+
+        // 未被邀请不能加入
+        assert!(service.join(user("b"), &party.id).is_err());
+        service.invite("a", "b").unwrap();
+        service.invite("a", "c").unwrap();
+        service.invite("a", "d").unwrap();
+        service.join(user("b"), &party.id).unwrap();
+        service.join(user("c"), &party.id).unwrap();
+        assert!(service.join(user("d"), &party.id).is_err());
+        assert_eq!(service.party_of("c").unwrap().member_ids(), vec!["a", "b", "c"]);
+
+        // 队长离开后由下一名队员接任，最后一人离开后解散
+        let (_, after) = service.leave("a").unwrap();
+        assert_eq!(after.unwrap().leader_id, "b");
+        service.leave("b").unwrap();
+        let (_, after) = service.leave("c").unwrap();
+        assert!(after.is_none());
+        assert!(service.party_of("c").is_none());
+    }
+}
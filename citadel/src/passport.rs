@@ -12,6 +12,10 @@
 //! 
 //! - **在线状态管理**: 实时监控和广播用户在线状态，基于缓存实现自动管理
 //! - **好友系统**: 完整的好友关系管理（添加、接受、拒绝、撤销、删除）
+//! - **关系批量查询**: 通过按用户建立的关系索引一次返回用户的全部关系，用于渲染好友页面
+//! - **好友请求收件箱**: 分页查询收到和发出的好友请求，并推送待处理数量（见 `inbox` 子模块）
+//! - **游戏邀请**: 推送好友发出的游戏邀请，接受后直接加入游戏房间
+//! - **离线推送**: 用户离线时通过推送通知网关发送好友请求和游戏邀请
 //! - **游戏查询**: 查询用户当前进行中的游戏
 //! - **用户补充信息**: 提供用户状态和活动信息的统一查询接口
 //! 
@@ -40,6 +44,12 @@
 //! socket.emit('user:accept-friend-request', {
 //!   user_id: '12345'
 //! });
+//!
+//! // 一次获取自己的全部好友关系（也可以通过 GET /v1/relationships 获取）
+//! socket.emit('user:get-relationships');
+//! socket.on('user:get-relationships-response', (res) => {
+//!   console.log('关系列表:', res.payload.relationships);
+//! });
 //! 
 //! // 监听用户上线事件
 //! socket.on('user:online', (data) => {
@@ -59,6 +69,11 @@
 //!     matchId: 'match-123'
 //!   }
 //! });
+//!
+//! // 收到好友的游戏邀请，接受后直接加入游戏房间
+//! socket.on('user:match-invite', (invite) => {
+//!   socket.emit('user:accept-match-invite', { invite_id: invite.id });
+//! });
 //! ```
 //!
 //! ## 架构说明
@@ -78,6 +93,7 @@
 //! 3. **活动追踪**: 跟踪用户是在大厅中、游戏中还是观战状态
 //! 4. **状态广播**: 当用户状态变化时，自动广播给相关用户
 //! 5. **游戏集成**: 与游戏系统集成，自动反映用户的游戏参与状态
+//! 6. **状态消息与丰富在线状态**: 支持自定义状态消息，对局中的剩余人数和回合由对局服务自动更新（见 `presence` 模块）
 //! 
 //! 这些功能使得游戏客户端能够轻松获取和展示用户的实时状态，为玩家提供更好的社交体验。
 
@@ -85,22 +101,38 @@ use std::sync::Arc;
 use std::collections::HashMap;
 
 use anyhow::Result;
+use async_trait::async_trait;
 use axum::{
-    extract::State,
+    extract::{Query, State},
     routing::{get, post},
+    Extension,
     Router,
     Json,
 };
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
+use tower_sessions::Session;
 use tracing::{debug, error, info, warn};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::account_link;
+use crate::errors::{ErrorResponse, InternalError};
+use crate::event_bus::{DomainEvent, EventSubscriber};
+use crate::session_login::{SessionUser, SESSION_USER_KEY};
 use crate::ws::{ConnectionManager, WsMessage, ClientId};
 use crate::game::{GameCache, GameCachePrefix, GameService};
+use crate::gaming::{MatchData, MatchInvite};
+use crate::i18n::codes;
+use crate::presence::{self, RichPresence};
+use crate::notification::{NotificationGateway, NotificationKind, NotificationPreferences, PushNotification};
 use crate::AppState;
 
+pub mod inbox;
+
+use inbox::{FriendRequestPage, ListFriendRequestsQuery};
+
 /// 用户状态枚举
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum UserStatus {
@@ -123,7 +155,7 @@ impl Default for UserStatus {
 }
 
 /// 好友关系状态枚举
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 pub enum RelationshipStatus {
     /// 无关系
     None,
@@ -167,7 +199,7 @@ pub struct UserInfo {
 }
 
 /// 好友关系结构
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Relationship {
     /// 关系ID
     pub id: String,
@@ -197,7 +229,7 @@ pub struct OngoingGame {
 }
 
 /// 用户活动类型
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum UserActivityType {
     /// 在大厅中
@@ -209,7 +241,7 @@ pub enum UserActivityType {
 }
 
 /// 用户活动信息
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, ToSchema)]
 pub struct UserActivity {
     /// 活动类型
     #[serde(rename = "type")]
@@ -223,7 +255,7 @@ pub struct UserActivity {
 }
 
 /// 用户状态字符串类型（用于前端兼容）
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum UserStatusString {
     /// 在线状态
@@ -251,10 +283,16 @@ pub struct UserSupplemental {
     pub status: UserStatusString,
     /// 用户活动信息（可为null）
     pub activity: Option<UserActivity>,
+    /// 自定义状态消息
+    #[serde(rename = "statusMessage", skip_serializing_if = "Option::is_none")]
+    pub status_message: Option<String>,
+    /// 丰富在线状态，由对局服务自动更新
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence: Option<RichPresence>,
 }
 
 /// 用户临时状态，与NestJS中的UserInterim对应
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, ToSchema)]
 pub struct UserInterim {
     /// 用户状态（可选）
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -262,10 +300,16 @@ pub struct UserInterim {
     /// 用户活动（可选）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub activity: Option<UserActivity>,
+    /// 自定义状态消息（可选），空字符串表示清除
+    #[serde(rename = "statusMessage", skip_serializing_if = "Option::is_none")]
+    pub status_message: Option<String>,
+    /// 丰富在线状态，只能由服务器设置
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub presence: Option<RichPresence>,
 }
 
 /// 获取用户补充信息的请求DTO
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct GetSupplementalDto {
     /// 用户ID列表
     pub ids: Vec<String>,
@@ -292,6 +336,12 @@ pub enum ServerEvent {
     FriendRequestRevoked,
     /// 被删除好友
     Unfriended,
+    /// 收到游戏邀请
+    MatchInvite,
+    /// 游戏邀请被拒绝
+    MatchInviteDeclined,
+    /// 待处理好友请求数量变化
+    FriendRequestCounts,
 }
 
 impl ServerEvent {
@@ -305,6 +355,9 @@ impl ServerEvent {
             Self::FriendRequestRejected => "user:friend-request-rejected",
             Self::FriendRequestRevoked => "user:friend-request-revoked",
             Self::Unfriended => "user:unfriended",
+            Self::MatchInvite => "user:match-invite",
+            Self::MatchInviteDeclined => "user:match-invite-declined",
+            Self::FriendRequestCounts => "user:friend-request-counts",
         }
     }
 }
@@ -330,6 +383,18 @@ pub enum ClientEvent {
     GetSupplemental,
     /// 设置用户临时状态
     SetInterim,
+    /// 接受游戏邀请
+    AcceptMatchInvite,
+    /// 拒绝游戏邀请
+    DeclineMatchInvite,
+    /// 获取通知偏好
+    GetNotificationPreferences,
+    /// 设置通知偏好
+    SetNotificationPreferences,
+    /// 获取自己的全部好友关系
+    GetRelationships,
+    /// 分页查询好友请求
+    ListFriendRequests,
 }
 
 impl ClientEvent {
@@ -345,6 +410,12 @@ impl ClientEvent {
             Self::Unblock => "user:unblock",
             Self::GetSupplemental => "user:get-supplemental",
             Self::SetInterim => "user:set-interim",
+            Self::AcceptMatchInvite => "user:accept-match-invite",
+            Self::DeclineMatchInvite => "user:decline-match-invite",
+            Self::GetNotificationPreferences => "user:get-notification-preferences",
+            Self::SetNotificationPreferences => "user:set-notification-preferences",
+            Self::GetRelationships => "user:get-relationships",
+            Self::ListFriendRequests => "user:list-friend-requests",
         }
     }
     
@@ -360,6 +431,12 @@ impl ClientEvent {
             "user:unblock" => Some(Self::Unblock),
             "user:get-supplemental" => Some(Self::GetSupplemental),
             "user:set-interim" => Some(Self::SetInterim),
+            "user:accept-match-invite" => Some(Self::AcceptMatchInvite),
+            "user:decline-match-invite" => Some(Self::DeclineMatchInvite),
+            "user:get-notification-preferences" => Some(Self::GetNotificationPreferences),
+            "user:set-notification-preferences" => Some(Self::SetNotificationPreferences),
+            "user:get-relationships" => Some(Self::GetRelationships),
+            "user:list-friend-requests" => Some(Self::ListFriendRequests),
             _ => None,
         }
     }
@@ -382,6 +459,18 @@ pub enum ResponseEvent {
     GetSupplementalResponse,
     /// 设置用户临时状态响应
     SetInterimResponse,
+    /// 接受游戏邀请响应
+    AcceptMatchInviteResponse,
+    /// 拒绝游戏邀请响应
+    DeclineMatchInviteResponse,
+    /// 获取通知偏好响应
+    GetNotificationPreferencesResponse,
+    /// 设置通知偏好响应
+    SetNotificationPreferencesResponse,
+    /// 获取全部好友关系响应
+    GetRelationshipsResponse,
+    /// 分页查询好友请求响应
+    ListFriendRequestsResponse,
 }
 
 impl ResponseEvent {
@@ -395,6 +484,12 @@ impl ResponseEvent {
             Self::UnfriendedResponse => "user:unfriended-response",
             Self::GetSupplementalResponse => "user:get-supplemental-response",
             Self::SetInterimResponse => "user:set-interim-response",
+            Self::AcceptMatchInviteResponse => "user:accept-match-invite-response",
+            Self::DeclineMatchInviteResponse => "user:decline-match-invite-response",
+            Self::GetNotificationPreferencesResponse => "user:get-notification-preferences-response",
+            Self::SetNotificationPreferencesResponse => "user:set-notification-preferences-response",
+            Self::GetRelationshipsResponse => "user:get-relationships-response",
+            Self::ListFriendRequestsResponse => "user:list-friend-requests-response",
         }
     }
 }
@@ -417,54 +512,66 @@ impl UserEvents {
 }
 
 /// 发送好友请求DTO
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SendFriendRequestDto {
     /// 目标用户ID
     pub user_id: String,
 }
 
 /// 撤销好友请求DTO
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct RevokeFriendRequestDto {
     /// 目标用户ID
     pub user_id: String,
 }
 
 /// 接受好友请求DTO
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct AcceptFriendRequestDto {
     /// 目标用户ID
     pub user_id: String,
 }
 
 /// 拒绝好友请求DTO
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct RejectFriendRequestDto {
     /// 目标用户ID
     pub user_id: String,
 }
 
 /// 删除好友DTO
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UnfriendDto {
     /// 目标用户ID
     pub user_id: String,
 }
 
+/// 接受/拒绝游戏邀请DTO
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MatchInviteDto {
+    /// 邀请ID
+    pub invite_id: String,
+}
+
 /// 封禁用户DTO
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct BlockUserDto {
     /// 目标用户ID
     pub user_id: String,
 }
 
 /// 解除封禁DTO
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UnblockUserDto {
     /// 目标用户ID
     pub user_id: String,
 }
 
+/// 用户关系索引的缓存键，保存与该用户存在关系的对方用户ID
+fn relationship_index_key(user_id: &str) -> String {
+    format!("{}:rels", user_id)
+}
+
 /// 用户护照模块状态
 pub struct PassportState {
     /// WebSocket连接管理器
@@ -475,6 +582,8 @@ pub struct PassportState {
     pub user_sessions: Arc<Mutex<HashMap<String, Vec<ClientId>>>>,
     /// 用户临时状态缓存（保存非持久化的状态信息）
     pub user_interim: Arc<Mutex<HashMap<String, UserInterim>>>,
+    /// 离线推送通知网关
+    pub notifications: Arc<NotificationGateway>,
 }
 
 impl PassportState {
@@ -485,6 +594,7 @@ impl PassportState {
             game_service: Arc::new(GameService::new()),
             user_sessions: Arc::new(Mutex::new(HashMap::new())),
             user_interim: Arc::new(Mutex::new(HashMap::new())),
+            notifications: Arc::new(NotificationGateway::from_env()),
         }
     }
     
@@ -510,7 +620,18 @@ impl PassportState {
                 
                 // 广播用户上线事件
                 self.broadcast_user_status(user_id, UserStatus::Online).await?;
+                
+                // 重启后恢复排队的玩家上线时通知仍在队列中
+                if let Some(match_service) = crate::gaming::global_match_service() {
+                    match_service.notify_queue_restored(user_id, client_id).await?;
+                }
+                
+                // 推送离线期间收到的私信
+                crate::chat::direct::deliver_pending(self, user_id, client_id).await?;
             }
+            
+            // 新会话需要待处理好友请求数量来显示角标
+            inbox::send_counts(self, user_id, client_id).await?;
         }
         
         Ok(())
@@ -570,10 +691,40 @@ impl PassportState {
         
         // 保存更新后的用户信息
         self.game_service.set(GameCachePrefix::USER, user_id, &user_info);
+        if let Some(search) = crate::user_search::global_user_search() {
+            search.index_user(&user_info.id, &user_info.username, user_info.avatar_url.clone());
+        }
         
         Ok(())
     }
-    
+
+    /// 更新用户名和头像，名称应已通过 `name_policy` 校验，为None的字段保持不变
+    pub async fn update_profile_info(&self, user_id: &str, username: Option<&str>, avatar_url: Option<&str>) -> Result<()> {
+        let now = Utc::now().timestamp_millis();
+        let mut user_info = self.game_service.get::<UserInfo>(GameCachePrefix::USER, user_id)
+            .unwrap_or_else(|| UserInfo {
+                id: user_id.to_string(),
+                username: format!("User-{}", user_id),
+                avatar_url: None,
+                status: UserStatus::Offline,
+                last_active: now,
+                created_at: now,
+            });
+        if let Some(username) = username {
+            user_info.username = username.to_string();
+        }
+        if let Some(avatar_url) = avatar_url {
+            user_info.avatar_url = Some(avatar_url.to_string());
+        }
+
+        self.game_service.set(GameCachePrefix::USER, user_id, &user_info);
+        if let Some(search) = crate::user_search::global_user_search() {
+            search.index_user(&user_info.id, &user_info.username, user_info.avatar_url.clone());
+        }
+
+        Ok(())
+    }
+
     /// 广播用户状态变化
     pub async fn broadcast_user_status(&self, user_id: &str, status: UserStatus) -> Result<()> {
         let event = match status {
@@ -701,6 +852,9 @@ impl PassportState {
         
         // 保存更新后的关系
         self.game_service.set(GameCachePrefix::USER, &format!("rel:{}", key), &updated_relationship);
+        self.index_relationship(first_id, second_id);
+        self.index_relationship(second_id, first_id);
+        inbox::on_relationship_changed(self, &updated_relationship).await?;
         
         Ok(updated_relationship)
     }
@@ -714,10 +868,71 @@ impl PassportState {
         };
         
         self.game_service.delete(GameCachePrefix::USER, &format!("rel:{}", key));
+        self.unindex_relationship(user_id1, user_id2);
+        self.unindex_relationship(user_id2, user_id1);
+        inbox::on_relationship_deleted(self, user_id1, user_id2).await?;
         
         Ok(())
     }
     
+    /**
+     * 一次获取用户的全部关系
+     *
+     * 通过按用户建立的关系索引查找，不需要逐对查询。状态为None的关系（已撤销的请求、已删除的好友）不返回，
+     * 缓存中已过期的关系同时从索引中移除
+     *
+     * 参数:
+     * @param user_id - 用户ID
+     *
+     * 返回:
+     * 按更新时间从新到旧排列的关系列表
+     */
+    pub async fn get_relationships_for_user(&self, user_id: &str) -> Vec<Relationship> {
+        let peers = self.relationship_peers(user_id);
+        let mut live = Vec::with_capacity(peers.len());
+        let mut relationships = Vec::new();
+        for peer_id in peers.iter() {
+            let Some(relationship) = self.get_relationship(user_id, peer_id).await else {
+                continue;
+            };
+            live.push(peer_id.clone());
+            if relationship.status != RelationshipStatus::None {
+                relationships.push(relationship);
+            }
+        }
+        if live.len() != peers.len() {
+            self.game_service.set(GameCachePrefix::USER, &relationship_index_key(user_id), &live);
+        }
+        relationships.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        relationships
+    }
+    
+    /// 与用户存在关系的对方用户ID
+    fn relationship_peers(&self, user_id: &str) -> Vec<String> {
+        self.game_service
+            .get::<Vec<String>>(GameCachePrefix::USER, &relationship_index_key(user_id))
+            .unwrap_or_default()
+    }
+    
+    /// 把对方用户加入关系索引
+    fn index_relationship(&self, user_id: &str, peer_id: &str) {
+        let mut peers = self.relationship_peers(user_id);
+        if !peers.iter().any(|id| id == peer_id) {
+            peers.push(peer_id.to_string());
+            self.game_service.set(GameCachePrefix::USER, &relationship_index_key(user_id), &peers);
+        }
+    }
+    
+    /// 从关系索引中移除对方用户
+    fn unindex_relationship(&self, user_id: &str, peer_id: &str) {
+        let mut peers = self.relationship_peers(user_id);
+        let before = peers.len();
+        peers.retain(|id| id != peer_id);
+        if peers.len() != before {
+            self.game_service.set(GameCachePrefix::USER, &relationship_index_key(user_id), &peers);
+        }
+    }
+    
     /// 获取用户的所有好友
     pub async fn get_user_friends(&self, user_id: &str) -> Result<Vec<String>> {
         if let Some(friends) = self.game_service.get::<Vec<String>>(GameCachePrefix::USER, &format!("{}:friends", user_id)) {
@@ -753,6 +968,40 @@ impl PassportState {
         Ok(())
     }
     
+    /**
+     * 把好友关系转移到另一个用户ID，用于游客升级为链上Profile
+     *
+     * 只转移已成为好友的关系，待处理的好友请求和封禁不转移
+     *
+     * 参数:
+     * @param from - 原用户ID
+     * @param to - 新用户ID
+     *
+     * 返回:
+     * 转移的好友数量
+     */
+    pub async fn transfer_friends(&self, from: &str, to: &str) -> Result<usize> {
+        let friends = self.get_user_friends(from).await?;
+        let mut moved = 0;
+        for friend_id in friends.iter().filter(|id| id.as_str() != to) {
+            let is_friend = self
+                .get_relationship(from, friend_id)
+                .await
+                .is_some_and(|rel| rel.status == RelationshipStatus::Friends);
+            self.delete_relationship(from, friend_id).await?;
+            self.remove_from_friends_list(friend_id, from).await?;
+            if !is_friend {
+                continue;
+            }
+            self.set_relationship(to, friend_id, RelationshipStatus::Friends).await?;
+            self.add_to_friends_list(to, friend_id).await?;
+            self.add_to_friends_list(friend_id, to).await?;
+            moved += 1;
+        }
+        self.game_service.delete(GameCachePrefix::USER, &format!("{}:friends", from));
+        Ok(moved)
+    }
+
     /// 向用户发送事件通知
     pub async fn send_event_to_user(&self, user_id: &str, event: &str, data: Option<serde_json::Value>) -> Result<bool> {
         // 获取用户的所有会话
@@ -770,13 +1019,151 @@ impl PassportState {
         Ok(sent)
     }
     
+    /// 获取用户的通知偏好
+    pub async fn get_notification_preferences(&self, user_id: &str) -> NotificationPreferences {
+        self.game_service
+            .get::<NotificationPreferences>(GameCachePrefix::USER, &format!("{}:notification_prefs", user_id))
+            .unwrap_or_default()
+    }
+    
+    /// 保存用户的通知偏好
+    pub async fn set_notification_preferences(&self, user_id: &str, mut preferences: NotificationPreferences) -> Result<NotificationPreferences> {
+        preferences.normalize();
+        if !self.game_service.set(GameCachePrefix::USER, &format!("{}:notification_prefs", user_id), &preferences) {
+            return Err(anyhow::anyhow!("保存通知偏好失败"));
+        }
+        Ok(preferences)
+    }
+    
+    /// 向用户发送事件，用户没有在线会话时按其偏好发送推送通知
+    async fn send_event_or_push(
+        &self,
+        user_id: &str,
+        event: &str,
+        data: serde_json::Value,
+        kind: NotificationKind,
+        title: String,
+        body: String,
+    ) -> Result<bool> {
+        if self.send_event_to_user(user_id, event, Some(data.clone())).await? {
+            return Ok(true);
+        }
+        
+        let preferences = self.get_notification_preferences(user_id).await;
+        let pushed = self.notifications.dispatch(preferences, PushNotification {
+            user_id: user_id.to_string(),
+            kind,
+            title,
+            body,
+            data,
+        });
+        if pushed {
+            debug!("用户 {} 离线，已发送 {} 推送通知", user_id, event);
+        }
+        Ok(false)
+    }
+    
+    /// 向被邀请的好友推送游戏邀请
+    pub async fn notify_match_invite(&self, invite: &MatchInvite) -> Result<bool> {
+        self.send_event_or_push(
+            &invite.invitee_id,
+            ServerEvent::MatchInvite.as_str(),
+            serde_json::to_value(invite)?,
+            NotificationKind::MatchInvite,
+            "游戏邀请".to_string(),
+            format!("{} 邀请你加入游戏", invite.inviter.name),
+        ).await
+    }
+    
+    /// 通知邀请者游戏邀请被拒绝
+    pub async fn notify_match_invite_declined(&self, invite: &MatchInvite) -> Result<bool> {
+        self.send_event_to_user(
+            &invite.inviter.id,
+            ServerEvent::MatchInviteDeclined.as_str(),
+            Some(serde_json::json!({
+                "inviteId": invite.id,
+                "matchId": invite.match_id,
+                "userId": invite.invitee_id
+            })),
+        ).await
+    }
+    
+    /// 处理接受游戏邀请
+    pub async fn handle_accept_match_invite(&self, user: &UserInfo, client_id: &str, invite_id: &str) -> Result<serde_json::Value> {
+        let Some(match_service) = crate::gaming::global_match_service() else {
+            return Ok(serde_json::json!({
+                "ok": false,
+                "msg": "游戏服务未初始化",
+                "code": codes::MATCH_SERVICE_UNAVAILABLE
+            }));
+        };
+        
+        let player = crate::gaming::UserInfo::with_rating(
+            user.id.clone(),
+            user.username.clone(),
+            user.avatar_url.clone(),
+        );
+        
+        match match_service.accept_invite(invite_id, &player, client_id).await {
+            Ok(match_data) => Ok(serde_json::json!({
+                "ok": true,
+                "payload": {
+                    "matchId": match_data.id
+                }
+            })),
+            Err(e) => Ok(serde_json::json!({
+                "ok": false,
+                "msg": e.to_string()
+            })),
+        }
+    }
+    
+    /// 处理拒绝游戏邀请
+    pub async fn handle_decline_match_invite(&self, user_id: &str, invite_id: &str) -> Result<serde_json::Value> {
+        let Some(match_service) = crate::gaming::global_match_service() else {
+            return Ok(serde_json::json!({
+                "ok": false,
+                "msg": "游戏服务未初始化",
+                "code": codes::MATCH_SERVICE_UNAVAILABLE
+            }));
+        };
+        
+        match match_service.decline_invite(invite_id, user_id).await {
+            Ok(invite) => Ok(serde_json::json!({
+                "ok": true,
+                "payload": {
+                    "matchId": invite.match_id
+                }
+            })),
+            Err(e) => Ok(serde_json::json!({
+                "ok": false,
+                "msg": e.to_string()
+            })),
+        }
+    }
+    
+    /// 通知接收方收到好友请求，离线时发送推送通知
+    async fn notify_friend_request(&self, sender_id: &str, receiver_id: &str) -> Result<bool> {
+        let sender_info = self.get_user_info(sender_id).await?;
+        let body = format!("{} 请求添加你为好友", sender_info.username);
+        self.send_event_or_push(
+            receiver_id,
+            ServerEvent::FriendRequestReceived.as_str(),
+            serde_json::json!({ "user": sender_info }),
+            NotificationKind::FriendRequest,
+            "好友请求".to_string(),
+            body,
+        ).await
+    }
+    
     /// 处理发送好友请求
     pub async fn handle_send_friend_request(&self, sender_id: &str, receiver_id: &str) -> Result<serde_json::Value> {
         // 检查用户是否存在
         if !self.user_exists(receiver_id).await {
             return Ok(serde_json::json!({
                 "ok": false,
-                "msg": "用户不存在"
+                "msg": "用户不存在",
+                "code": codes::USER_NOT_FOUND
             }));
         }
         
@@ -788,7 +1175,8 @@ impl PassportState {
             if rel.status == RelationshipStatus::Friends {
                 return Ok(serde_json::json!({
                     "ok": false,
-                    "msg": "你们已经是好友了"
+                    "msg": "你们已经是好友了",
+                    "code": codes::ALREADY_FRIENDS
                 }));
             }
             
@@ -801,7 +1189,8 @@ impl PassportState {
             if is_blocked {
                 return Ok(serde_json::json!({
                     "ok": false,
-                    "msg": "你已被该用户阻止"
+                    "msg": "你已被该用户阻止",
+                    "code": codes::BLOCKED_BY_USER
                 }));
             }
             
@@ -813,7 +1202,8 @@ impl PassportState {
             if already_sent {
                 return Ok(serde_json::json!({
                     "ok": false,
-                    "msg": "好友请求已经发送过了"
+                    "msg": "好友请求已经发送过了",
+                    "code": codes::FRIEND_REQUEST_ALREADY_SENT
                 }));
             }
             
@@ -837,12 +1227,7 @@ impl PassportState {
             let updated_rel = self.set_relationship(sender_id, receiver_id, new_status).await?;
             
             // 通知接收方
-            let sender_info = self.get_user_info(sender_id).await?;
-            self.send_event_to_user(
-                receiver_id, 
-                ServerEvent::FriendRequestReceived.as_str(), 
-                Some(serde_json::json!({ "user": sender_info }))
-            ).await?;
+            self.notify_friend_request(sender_id, receiver_id).await?;
             
             return Ok(serde_json::json!({
                 "ok": true,
@@ -859,12 +1244,7 @@ impl PassportState {
             let created_rel = self.set_relationship(sender_id, receiver_id, status).await?;
             
             // 通知接收方
-            let sender_info = self.get_user_info(sender_id).await?;
-            self.send_event_to_user(
-                receiver_id, 
-                ServerEvent::FriendRequestReceived.as_str(), 
-                Some(serde_json::json!({ "user": sender_info }))
-            ).await?;
+            self.notify_friend_request(sender_id, receiver_id).await?;
             
             return Ok(serde_json::json!({
                 "ok": true,
@@ -885,7 +1265,8 @@ impl PassportState {
             if !can_revoke {
                 return Ok(serde_json::json!({
                     "ok": false,
-                    "msg": "没有可撤销的好友请求"
+                    "msg": "没有可撤销的好友请求",
+                    "code": codes::NO_FRIEND_REQUEST_TO_REVOKE
                 }));
             }
             
@@ -907,7 +1288,8 @@ impl PassportState {
         } else {
             return Ok(serde_json::json!({
                 "ok": false,
-                "msg": "没有与该用户的关系"
+                "msg": "没有与该用户的关系",
+                "code": codes::NO_RELATIONSHIP
             }));
         }
     }
@@ -924,7 +1306,8 @@ impl PassportState {
             if !can_accept {
                 return Ok(serde_json::json!({
                     "ok": false,
-                    "msg": "没有待接受的好友请求"
+                    "msg": "没有待接受的好友请求",
+                    "code": codes::NO_FRIEND_REQUEST_TO_ACCEPT
                 }));
             }
             
@@ -935,6 +1318,9 @@ impl PassportState {
             self.add_to_friends_list(accepter_id, sender_id).await?;
             self.add_to_friends_list(sender_id, accepter_id).await?;
             
+            // 按配置把好友关系提交到链上
+            crate::friend_sync::on_friend_request_accepted(sender_id, accepter_id);
+            
             // 通知请求发送方
             let accepter_info = self.get_user_info(accepter_id).await?;
             self.send_event_to_user(
@@ -950,7 +1336,8 @@ impl PassportState {
         } else {
             return Ok(serde_json::json!({
                 "ok": false,
-                "msg": "没有与该用户的关系"
+                "msg": "没有与该用户的关系",
+                "code": codes::NO_RELATIONSHIP
             }));
         }
     }
@@ -967,7 +1354,8 @@ impl PassportState {
             if !can_reject {
                 return Ok(serde_json::json!({
                     "ok": false,
-                    "msg": "没有待拒绝的好友请求"
+                    "msg": "没有待拒绝的好友请求",
+                    "code": codes::NO_FRIEND_REQUEST_TO_REJECT
                 }));
             }
             
@@ -989,7 +1377,8 @@ impl PassportState {
         } else {
             return Ok(serde_json::json!({
                 "ok": false,
-                "msg": "没有与该用户的关系"
+                "msg": "没有与该用户的关系",
+                "code": codes::NO_RELATIONSHIP
             }));
         }
     }
@@ -1002,7 +1391,8 @@ impl PassportState {
             if rel.status != RelationshipStatus::Friends {
                 return Ok(serde_json::json!({
                     "ok": false,
-                    "msg": "你们不是好友关系"
+                    "msg": "你们不是好友关系",
+                    "code": codes::NOT_FRIENDS
                 }));
             }
             
@@ -1028,7 +1418,8 @@ impl PassportState {
         } else {
             return Ok(serde_json::json!({
                 "ok": false,
-                "msg": "没有与该用户的关系"
+                "msg": "没有与该用户的关系",
+                "code": codes::NO_RELATIONSHIP
             }));
         }
     }
@@ -1078,6 +1469,8 @@ impl PassportState {
         UserSupplemental {
             status: status_string,
             activity,
+            status_message: interim.status_message,
+            presence: interim.presence,
         }
     }
     
@@ -1089,6 +1482,12 @@ impl PassportState {
     
     /// 设置用户临时状态
     pub async fn set_interim(&self, user_id: &str, interim: UserInterim) -> Result<()> {
+        // 先校验状态消息，避免部分更新
+        let status_message = match &interim.status_message {
+            Some(raw) => Some(presence::sanitize(raw).map_err(|e| anyhow::anyhow!(e))?),
+            None => None,
+        };
+        
         let mut interim_map = self.user_interim.lock().await;
         
         // 获取或创建用户的临时状态
@@ -1109,6 +1508,11 @@ impl PassportState {
             current.status = Some(status.clone());
         }
         
+        // 更新状态消息（如果提供）
+        if let Some(message) = status_message {
+            current.status_message = message;
+        }
+        
         // 更新活动（如果提供）
         if let Some(activity) = &interim.activity {
             // 保存到临时状态
@@ -1132,6 +1536,42 @@ impl PassportState {
         Ok(())
     }
     
+    /**
+     * 按对局数据同步参与者的丰富在线状态
+     *
+     * 已离开对局的用户会被清除在线状态，仍在对局中的玩家和观众会更新为最新的剩余人数和回合
+     *
+     * 参数:
+     * @param match_data - 刚保存的对局数据
+     */
+    pub async fn sync_match_presence(&self, match_data: &MatchData) {
+        let now = Utc::now().timestamp_millis() as u64;
+        let mut interim_map = self.user_interim.lock().await;
+        Self::clear_presence_locked(&mut interim_map, &match_data.id);
+        let participants = match_data.players.iter()
+            .map(|p| &p.user.id)
+            .chain(match_data.spectators.iter().map(|s| &s.id));
+        for user_id in participants {
+            if let Some(presence) = RichPresence::for_match(match_data, user_id, now) {
+                interim_map.entry(user_id.clone()).or_default().presence = Some(presence);
+            }
+        }
+    }
+    
+    /// 清除所有用户在指定对局中的在线状态
+    pub async fn clear_match_presence(&self, match_id: &str) {
+        let mut interim_map = self.user_interim.lock().await;
+        Self::clear_presence_locked(&mut interim_map, match_id);
+    }
+    
+    fn clear_presence_locked(interim_map: &mut HashMap<String, UserInterim>, match_id: &str) {
+        for interim in interim_map.values_mut() {
+            if interim.presence.as_ref().is_some_and(|p| p.match_id == match_id) {
+                interim.presence = None;
+            }
+        }
+    }
+    
     /// 处理获取用户补充信息请求
     pub async fn handle_get_supplemental(&self, dto: GetSupplementalDto) -> Result<serde_json::Value> {
         let mut supplementals = HashMap::new();
@@ -1152,6 +1592,48 @@ impl PassportState {
     }
 }
 
+#[async_trait]
+impl EventSubscriber for PassportState {
+    fn name(&self) -> &'static str {
+        "passport_presence"
+    }
+
+    /// 对局保存或删除后同步参与者的丰富在线状态
+    async fn handle(&self, event: &DomainEvent) -> Result<()> {
+        match event {
+            DomainEvent::MatchSaved(match_data) => self.sync_match_presence(match_data).await,
+            DomainEvent::MatchRemoved { match_id } => self.clear_match_presence(match_id).await,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/**
+ * 注册用户护照的WebSocket事件处理器，处理所有 `user:` 事件
+ *
+ * 参数:
+ * @param connection_manager - 连接管理器
+ * @param passport_state - 用户护照状态
+ */
+pub fn register_ws_handlers(connection_manager: &ConnectionManager, passport_state: Arc<PassportState>) {
+    connection_manager.register_handler("user:*", move |event| {
+        let passport_state = passport_state.clone();
+        async move {
+            let now = Utc::now().timestamp_millis();
+            let user_info = event.user.map(|u| UserInfo {
+                id: u.id,
+                username: u.name,
+                avatar_url: u.avatar_url,
+                status: UserStatus::Online,
+                last_active: now,
+                created_at: now,
+            });
+            handle_ws_message(&event.client_id, event.message, &passport_state, user_info).await
+        }
+    });
+}
+
 /// 处理WebSocket消息
 pub async fn handle_ws_message(
     client_id: &str,
@@ -1167,8 +1649,10 @@ pub async fn handle_ws_message(
     // 处理不需要用户认证的事件
     if let Some(ClientEvent::GetSupplemental) = client_event {
         if let Some(data) = &message.data {
-            if let Ok(dto) = serde_json::from_value::<GetSupplementalDto>(data.clone()) {
+            if let Ok(mut dto) = serde_json::from_value::<GetSupplementalDto>(data.clone()) {
                 debug!("处理获取用户补充信息请求: {:?}", dto.ids);
+                // 已关联的钱包地址按所属用户查询
+                dto.ids = dto.ids.iter().map(|id| account_link::resolve_user_id(id)).collect();
                 let response = passport_state.handle_get_supplemental(dto).await?;
                 
                 // 发送响应
@@ -1199,7 +1683,7 @@ pub async fn handle_ws_message(
         Some(ClientEvent::SendFriendRequest) => {
             if let Some(data) = &message.data {
                 if let Ok(dto) = serde_json::from_value::<SendFriendRequestDto>(data.clone()) {
-                    let response = passport_state.handle_send_friend_request(&user.id, &dto.user_id).await?;
+                    let response = passport_state.handle_send_friend_request(&user.id, &account_link::resolve_user_id(&dto.user_id)).await?;
                     
                     // 发送响应
                     passport_state.connection_manager.send_to_client(
@@ -1215,7 +1699,7 @@ pub async fn handle_ws_message(
         Some(ClientEvent::RevokeFriendRequest) => {
             if let Some(data) = &message.data {
                 if let Ok(dto) = serde_json::from_value::<RevokeFriendRequestDto>(data.clone()) {
-                    let response = passport_state.handle_revoke_friend_request(&user.id, &dto.user_id).await?;
+                    let response = passport_state.handle_revoke_friend_request(&user.id, &account_link::resolve_user_id(&dto.user_id)).await?;
                     
                     // 发送响应
                     passport_state.connection_manager.send_to_client(
@@ -1231,7 +1715,7 @@ pub async fn handle_ws_message(
         Some(ClientEvent::AcceptFriendRequest) => {
             if let Some(data) = &message.data {
                 if let Ok(dto) = serde_json::from_value::<AcceptFriendRequestDto>(data.clone()) {
-                    let response = passport_state.handle_accept_friend_request(&user.id, &dto.user_id).await?;
+                    let response = passport_state.handle_accept_friend_request(&user.id, &account_link::resolve_user_id(&dto.user_id)).await?;
                     
                     // 发送响应
                     passport_state.connection_manager.send_to_client(
@@ -1247,7 +1731,7 @@ pub async fn handle_ws_message(
         Some(ClientEvent::RejectFriendRequest) => {
             if let Some(data) = &message.data {
                 if let Ok(dto) = serde_json::from_value::<RejectFriendRequestDto>(data.clone()) {
-                    let response = passport_state.handle_reject_friend_request(&user.id, &dto.user_id).await?;
+                    let response = passport_state.handle_reject_friend_request(&user.id, &account_link::resolve_user_id(&dto.user_id)).await?;
                     
                     // 发送响应
                     passport_state.connection_manager.send_to_client(
@@ -1263,7 +1747,7 @@ pub async fn handle_ws_message(
         Some(ClientEvent::Unfriend) => {
             if let Some(data) = &message.data {
                 if let Ok(dto) = serde_json::from_value::<UnfriendDto>(data.clone()) {
-                    let response = passport_state.handle_unfriend(&user.id, &dto.user_id).await?;
+                    let response = passport_state.handle_unfriend(&user.id, &account_link::resolve_user_id(&dto.user_id)).await?;
                     
                     // 发送响应
                     passport_state.connection_manager.send_to_client(
@@ -1301,7 +1785,9 @@ pub async fn handle_ws_message(
                             ResponseEvent::SetInterimResponse.as_str(),
                             Some(serde_json::json!({
                                 "ok": false,
-                                "msg": format!("设置临时状态失败: {}", e)
+                                "msg": format!("设置临时状态失败: {}", e),
+                                "code": codes::SET_INTERIM_FAILED,
+                                "params": { "error": e.to_string() }
                             })),
                         ).await?;
                     } else {
@@ -1324,6 +1810,114 @@ pub async fn handle_ws_message(
             }
             return Ok(false);
         },
+        Some(ClientEvent::AcceptMatchInvite) => {
+            if let Some(data) = &message.data {
+                if let Ok(dto) = serde_json::from_value::<MatchInviteDto>(data.clone()) {
+                    let response = passport_state.handle_accept_match_invite(&user, client_id, &dto.invite_id).await?;
+                    
+                    // 发送响应
+                    passport_state.connection_manager.send_to_client(
+                        client_id,
+                        ResponseEvent::AcceptMatchInviteResponse.as_str(),
+                        Some(response),
+                    ).await?;
+                    
+                    return Ok(true);
+                }
+            }
+        },
+        Some(ClientEvent::GetNotificationPreferences) => {
+            let preferences = passport_state.get_notification_preferences(&user.id).await;
+            
+            // 发送响应
+            passport_state.connection_manager.send_to_client(
+                client_id,
+                ResponseEvent::GetNotificationPreferencesResponse.as_str(),
+                Some(serde_json::json!({
+                    "ok": true,
+                    "payload": preferences
+                })),
+            ).await?;
+            
+            return Ok(true);
+        },
+        Some(ClientEvent::SetNotificationPreferences) => {
+            if let Some(data) = &message.data {
+                if let Ok(preferences) = serde_json::from_value::<NotificationPreferences>(data.clone()) {
+                    let response = match passport_state.set_notification_preferences(&user.id, preferences).await {
+                        Ok(preferences) => serde_json::json!({
+                            "ok": true,
+                            "payload": preferences
+                        }),
+                        Err(e) => serde_json::json!({
+                            "ok": false,
+                            "msg": e.to_string()
+                        }),
+                    };
+                    
+                    // 发送响应
+                    passport_state.connection_manager.send_to_client(
+                        client_id,
+                        ResponseEvent::SetNotificationPreferencesResponse.as_str(),
+                        Some(response),
+                    ).await?;
+                    
+                    return Ok(true);
+                }
+            }
+        },
+        Some(ClientEvent::GetRelationships) => {
+            let relationships = passport_state.get_relationships_for_user(&user.id).await;
+            
+            // 发送响应
+            passport_state.connection_manager.send_to_client(
+                client_id,
+                ResponseEvent::GetRelationshipsResponse.as_str(),
+                Some(serde_json::json!({
+                    "ok": true,
+                    "payload": {
+                        "relationships": relationships
+                    }
+                })),
+            ).await?;
+            
+            return Ok(true);
+        },
+        Some(ClientEvent::ListFriendRequests) => {
+            if let Some(data) = &message.data {
+                if let Ok(query) = serde_json::from_value::<ListFriendRequestsQuery>(data.clone()) {
+                    let page = inbox::list(passport_state, &user.id, &query);
+                    
+                    // 发送响应
+                    passport_state.connection_manager.send_to_client(
+                        client_id,
+                        ResponseEvent::ListFriendRequestsResponse.as_str(),
+                        Some(serde_json::json!({
+                            "ok": true,
+                            "payload": page
+                        })),
+                    ).await?;
+                    
+                    return Ok(true);
+                }
+            }
+        },
+        Some(ClientEvent::DeclineMatchInvite) => {
+            if let Some(data) = &message.data {
+                if let Ok(dto) = serde_json::from_value::<MatchInviteDto>(data.clone()) {
+                    let response = passport_state.handle_decline_match_invite(&user.id, &dto.invite_id).await?;
+                    
+                    // 发送响应
+                    passport_state.connection_manager.send_to_client(
+                        client_id,
+                        ResponseEvent::DeclineMatchInviteResponse.as_str(),
+                        Some(response),
+                    ).await?;
+                    
+                    return Ok(true);
+                }
+            }
+        },
         _ => return Ok(false), // 非用户相关事件
     }
     
@@ -1404,6 +1998,94 @@ pub async fn get_ongoing_games(
     Ok(games)
 }
 
+/// 关系列表响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RelationshipsResponse {
+    pub success: bool,
+    pub relationships: Vec<Relationship>,
+    pub error: Option<String>,
+}
+
+/// 获取当前用户的全部好友关系
+#[utoipa::path(
+    get,
+    path = "/v1/relationships",
+    tag = "profile",
+    responses(
+        (status = 200, description = "按更新时间从新到旧排列的关系列表", body = RelationshipsResponse),
+        (status = 403, description = "未登录", body = ErrorResponse),
+    )
+)]
+pub async fn handle_get_relationships(
+    State(app_state): State<Arc<AppState>>,
+    Extension(session): Extension<Session>,
+) -> Result<Json<RelationshipsResponse>, InternalError> {
+    app_state.metrics.observe_request("get_relationships");
+
+    let user = session
+        .get::<SessionUser>(SESSION_USER_KEY)
+        .await?
+        .ok_or(InternalError::Unauthorized)?;
+    let Some(profile) = user.profile else {
+        return Ok(Json(RelationshipsResponse {
+            success: true,
+            relationships: Vec::new(),
+            error: None,
+        }));
+    };
+    let Some(passport_state) = crate::ws::global_passport_state() else {
+        return Ok(Json(RelationshipsResponse {
+            success: false,
+            relationships: Vec::new(),
+            error: Some("用户护照模块未初始化".to_string()),
+        }));
+    };
+
+    let relationships = passport_state
+        .get_relationships_for_user(&profile.id.to_string())
+        .await;
+    Ok(Json(RelationshipsResponse {
+        success: true,
+        relationships,
+        error: None,
+    }))
+}
+
+/// 分页查询当前用户收到或发出的好友请求
+#[utoipa::path(
+    get,
+    path = "/v1/friend-requests",
+    tag = "profile",
+    params(ListFriendRequestsQuery),
+    responses(
+        (status = 200, description = "按发出时间从新到旧排列的好友请求和待处理数量", body = FriendRequestPage),
+        (status = 403, description = "未登录", body = ErrorResponse),
+        (status = 503, description = "用户护照模块未初始化", body = ErrorResponse),
+    )
+)]
+pub async fn handle_list_friend_requests(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<ListFriendRequestsQuery>,
+    Extension(session): Extension<Session>,
+) -> Result<Json<FriendRequestPage>, InternalError> {
+    app_state.metrics.observe_request("list_friend_requests");
+
+    let user = session
+        .get::<SessionUser>(SESSION_USER_KEY)
+        .await?
+        .ok_or(InternalError::Unauthorized)?;
+    let profile = user.profile.ok_or(InternalError::Unauthorized)?;
+    let passport_state = crate::ws::global_passport_state().ok_or(InternalError::Failure)?;
+    Ok(Json(inbox::list(&passport_state, &profile.id.to_string(), &query)))
+}
+
+/// 注册好友关系REST路由
+pub fn register_relationship_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router
+        .route("/v1/relationships", get(handle_get_relationships))
+        .route("/v1/friend-requests", get(handle_list_friend_requests))
+}
+
 /// 注册用户护照模块路由
 pub fn register_passport_routes(app: Router, connection_manager: Arc<ConnectionManager>) -> Router {
     let passport_state = Arc::new(PassportState::new(connection_manager));
@@ -1424,3 +2106,28 @@ pub fn integrate_passport_with_ws(app: Arc<AppState>, connection_manager: Arc<Co
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_relationships_for_user() {
+        let passport = PassportState::new(Arc::new(ConnectionManager::new()));
+        passport.set_relationship("b", "a", RelationshipStatus::Friends).await.unwrap();
+        passport.set_relationship("a", "c", RelationshipStatus::FriendRequest1To2).await.unwrap();
+        passport.set_relationship("d", "e", RelationshipStatus::Friends).await.unwrap();
+
+        let relationships = passport.get_relationships_for_user("a").await;
+        assert_eq!(relationships.len(), 2);
+        assert!(relationships.iter().all(|r| r.user1_id == "a" || r.user2_id == "a"));
+        assert_eq!(passport.get_relationships_for_user("c").await.len(), 1);
+
+        // 状态为None的关系不返回，删除的关系从索引中移除
+        passport.set_relationship("a", "c", RelationshipStatus::None).await.unwrap();
+        passport.delete_relationship("a", "b").await.unwrap();
+        assert!(passport.get_relationships_for_user("a").await.is_empty());
+        assert_eq!(passport.relationship_peers("a"), vec!["c".to_string()]);
+        assert!(passport.relationship_peers("b").is_empty());
+    }
+}
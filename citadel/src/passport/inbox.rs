@@ -0,0 +1,302 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 好友请求收件箱
+//!
+//! # 概述
+//! 每个用户维护一个待处理好友请求的索引，分为收到的请求和发出的请求，每次 `set_relationship`
+//! 和 `delete_relationship` 时同步更新：
+//! - 关系变为 `FriendRequest1To2` / `FriendRequest2To1` 时记录请求方和接收方，时间为发出请求的时间
+//! - 关系变为其他状态（接受、拒绝、撤销、删除、封禁）时移除对应的请求
+//!
+//! 客户端通过 `user:list-friend-requests` 或 `GET /v1/friend-requests` 分页查询，
+//! 按发出时间从新到旧排列，`before` 为上一页最后一条的时间。
+//! 待处理数量变化时向用户推送 `user:friend-request-counts`，用户新建会话时也会推送一次，
+//! 客户端据此显示角标。
+//!
+//! # 消息格式
+//! ```json
+//! // user:list-friend-requests
+//! { "direction": "incoming", "before": 1700000000000, "limit": 20 }
+//! // user:list-friend-requests-response
+//! { "ok": true, "payload": { "requests": [{ "user_id": "user-2", "created_at": 1700000000000 }],
+//!   "has_more": false, "counts": { "incoming": 3, "outgoing": 1 } } }
+//! // user:friend-request-counts
+//! { "incoming": 3, "outgoing": 1 }
+//! ```
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::{PassportState, Relationship, RelationshipStatus, ServerEvent};
+use crate::event_bus::{self, DomainEvent};
+use crate::game::GameCachePrefix;
+
+/// 每页默认条数
+pub const DEFAULT_LIMIT: usize = 20;
+/// 每页最多条数
+pub const MAX_LIMIT: usize = 100;
+
+/// 好友请求方向
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestDirection {
+    /// 收到的请求
+    Incoming,
+    /// 发出的请求
+    Outgoing,
+}
+
+/// 待处理的好友请求
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct PendingFriendRequest {
+    /// 对方用户ID
+    pub user_id: String,
+    /// 发出请求的时间（毫秒）
+    pub created_at: i64,
+}
+
+/// 待处理好友请求数量
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub struct FriendRequestCounts {
+    pub incoming: usize,
+    pub outgoing: usize,
+}
+
+/// 查询好友请求
+#[derive(Debug, Deserialize, ToSchema, IntoParams)]
+pub struct ListFriendRequestsQuery {
+    /// 请求方向
+    pub direction: RequestDirection,
+    /// 只返回早于该时间（毫秒）的请求，为空时从最新的请求开始
+    pub before: Option<i64>,
+    /// 返回条数，默认20，最多MAX_LIMIT
+    pub limit: Option<usize>,
+}
+
+/// 好友请求分页结果
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FriendRequestPage {
+    /// 按发出时间从新到旧排列的请求
+    pub requests: Vec<PendingFriendRequest>,
+    /// 是否还有更早的请求
+    pub has_more: bool,
+    /// 待处理数量
+    pub counts: FriendRequestCounts,
+}
+
+/// 单个用户的待处理好友请求索引，按发出时间从旧到新排列
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct FriendRequestIndex {
+    incoming: Vec<PendingFriendRequest>,
+    outgoing: Vec<PendingFriendRequest>,
+}
+
+impl FriendRequestIndex {
+    fn list(&self, direction: RequestDirection) -> &[PendingFriendRequest] {
+        match direction {
+            RequestDirection::Incoming => &self.incoming,
+            RequestDirection::Outgoing => &self.outgoing,
+        }
+    }
+
+    fn list_mut(&mut self, direction: RequestDirection) -> &mut Vec<PendingFriendRequest> {
+        match direction {
+            RequestDirection::Incoming => &mut self.incoming,
+            RequestDirection::Outgoing => &mut self.outgoing,
+        }
+    }
+
+    /// 待处理数量
+    pub fn counts(&self) -> FriendRequestCounts {
+        FriendRequestCounts {
+            incoming: self.incoming.len(),
+            outgoing: self.outgoing.len(),
+        }
+    }
+
+    /**
+     * 更新与某个用户之间的请求
+     *
+     * 参数:
+     * @param peer_id - 对方用户ID
+     * @param pending - 仍在等待处理的请求方向和发出时间，为None时移除请求
+     *
+     * 返回:
+     * 索引是否发生变化；同一方向的请求已存在时保留原来的发出时间
+     */
+    pub fn apply(&mut self, peer_id: &str, pending: Option<(RequestDirection, i64)>) -> bool {
+        if let Some((direction, _)) = pending {
+            if self.list(direction).iter().any(|r| r.user_id == peer_id) {
+                return false;
+            }
+        }
+
+        let before = self.incoming.len() + self.outgoing.len();
+        self.incoming.retain(|r| r.user_id != peer_id);
+        self.outgoing.retain(|r| r.user_id != peer_id);
+        let removed = before != self.incoming.len() + self.outgoing.len();
+
+        let Some((direction, created_at)) = pending else {
+            return removed;
+        };
+        let list = self.list_mut(direction);
+        let position = list.partition_point(|r| r.created_at <= created_at);
+        list.insert(
+            position,
+            PendingFriendRequest {
+                user_id: peer_id.to_string(),
+                created_at,
+            },
+        );
+        true
+    }
+
+    /// 取出早于 `before` 的最近 `limit` 条请求，按时间从新到旧排列，同时返回是否还有更早的请求
+    pub fn page(&self, direction: RequestDirection, before: Option<i64>, limit: usize) -> (Vec<PendingFriendRequest>, bool) {
+        let list = self.list(direction);
+        let end = match before {
+            Some(before) => list.partition_point(|r| r.created_at < before),
+            None => list.len(),
+        };
+        let start = end.saturating_sub(limit);
+        (list[start..end].iter().rev().cloned().collect(), start > 0)
+    }
+}
+
+fn index_key(user_id: &str) -> String {
+    format!("{}:friend-requests", user_id)
+}
+
+/// 读取用户的好友请求索引
+pub fn load(passport: &PassportState, user_id: &str) -> FriendRequestIndex {
+    passport
+        .game_service
+        .get::<FriendRequestIndex>(GameCachePrefix::USER, &index_key(user_id))
+        .unwrap_or_default()
+}
+
+/// 关系中待处理的请求，返回（请求方，接收方）
+fn pending_request(relationship: &Relationship) -> Option<(&str, &str)> {
+    match relationship.status {
+        RelationshipStatus::FriendRequest1To2 => Some((relationship.user1_id.as_str(), relationship.user2_id.as_str())),
+        RelationshipStatus::FriendRequest2To1 => Some((relationship.user2_id.as_str(), relationship.user1_id.as_str())),
+        _ => None,
+    }
+}
+
+/// 更新单个用户的索引，变化时推送新的数量
+async fn update(passport: &PassportState, user_id: &str, peer_id: &str, pending: Option<(RequestDirection, i64)>) -> Result<()> {
+    let mut index = load(passport, user_id);
+    if !index.apply(peer_id, pending) {
+        return Ok(());
+    }
+    passport.game_service.set(GameCachePrefix::USER, &index_key(user_id), &index);
+    push_counts(user_id, index.counts()).await;
+    Ok(())
+}
+
+/// 关系更新后同步双方的好友请求索引
+pub async fn on_relationship_changed(passport: &PassportState, relationship: &Relationship) -> Result<()> {
+    match pending_request(relationship) {
+        Some((sender, receiver)) => {
+            let at = relationship.updated_at;
+            update(passport, sender, receiver, Some((RequestDirection::Outgoing, at))).await?;
+            update(passport, receiver, sender, Some((RequestDirection::Incoming, at))).await
+        }
+        None => on_relationship_deleted(passport, &relationship.user1_id, &relationship.user2_id).await,
+    }
+}
+
+/// 关系删除后移除双方之间的好友请求
+pub async fn on_relationship_deleted(passport: &PassportState, user_id1: &str, user_id2: &str) -> Result<()> {
+    update(passport, user_id1, user_id2, None).await?;
+    update(passport, user_id2, user_id1, None).await
+}
+
+/// 发布待处理数量变化事件，由WebSocket出口推送给用户的所有会话
+async fn push_counts(user_id: &str, counts: FriendRequestCounts) {
+    event_bus::publish(DomainEvent::FriendRequestCountsChanged { user_id: user_id.to_string(), counts }).await;
+}
+
+/// 向新建的会话推送待处理数量
+pub async fn send_counts(passport: &PassportState, user_id: &str, client_id: &str) -> Result<()> {
+    let counts = load(passport, user_id).counts();
+    passport
+        .connection_manager
+        .send_to_client(client_id, ServerEvent::FriendRequestCounts.as_str(), Some(serde_json::to_value(counts)?))
+        .await?;
+    Ok(())
+}
+
+/// 分页查询用户的好友请求
+pub fn list(passport: &PassportState, user_id: &str, query: &ListFriendRequestsQuery) -> FriendRequestPage {
+    let index = load(passport, user_id);
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let (requests, has_more) = index.page(query.direction, query.before, limit);
+    FriendRequestPage {
+        requests,
+        has_more,
+        counts: index.counts(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::ws::ConnectionManager;
+
+    fn ids(requests: &[PendingFriendRequest]) -> Vec<&str> {
+        requests.iter().map(|r| r.user_id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_index_apply_and_page() {
+        let mut index = FriendRequestIndex::default();
+        assert!(index.apply("a", Some((RequestDirection::Incoming, 10))));
+        assert!(index.apply("b", Some((RequestDirection::Incoming, 30))));
+        assert!(index.apply("c", Some((RequestDirection::Incoming, 20))));
+        assert!(index.apply("d", Some((RequestDirection::Outgoing, 40))));
+        // 重复设置同一请求保留原来的时间
+        assert!(!index.apply("a", Some((RequestDirection::Incoming, 50))));
+        assert_eq!(index.counts(), FriendRequestCounts { incoming: 3, outgoing: 1 });
+
+        let (first, has_more) = index.page(RequestDirection::Incoming, None, 2);
+        assert_eq!(ids(&first), vec!["b", "c"]);
+        assert!(has_more);
+        let (second, has_more) = index.page(RequestDirection::Incoming, Some(first[1].created_at), 2);
+        assert_eq!(ids(&second), vec!["a"]);
+        assert!(!has_more);
+
+        // 方向改变或请求被处理
+        assert!(index.apply("d", Some((RequestDirection::Incoming, 60))));
+        assert!(index.apply("b", None));
+        assert!(!index.apply("b", None));
+        assert_eq!(index.counts(), FriendRequestCounts { incoming: 3, outgoing: 0 });
+    }
+
+    #[tokio::test]
+    async fn test_index_follows_relationship_changes() {
+        let passport = PassportState::new(Arc::new(ConnectionManager::new()));
+        passport.set_relationship("a", "b", RelationshipStatus::FriendRequest1To2).await.unwrap();
+        passport.set_relationship("b", "c", RelationshipStatus::FriendRequest2To1).await.unwrap();
+
+        assert_eq!(load(&passport, "b").counts(), FriendRequestCounts { incoming: 2, outgoing: 0 });
+        assert_eq!(load(&passport, "a").counts(), FriendRequestCounts { incoming: 0, outgoing: 1 });
+        let query = ListFriendRequestsQuery {
+            direction: RequestDirection::Outgoing,
+            before: None,
+            limit: None,
+        };
+        assert_eq!(ids(&list(&passport, "a", &query).requests), vec!["b"]);
+
+        passport.set_relationship("a", "b", RelationshipStatus::Friends).await.unwrap();
+        passport.delete_relationship("b", "c").await.unwrap();
+        assert_eq!(load(&passport, "b").counts(), FriendRequestCounts::default());
+        assert_eq!(load(&passport, "a").counts(), FriendRequestCounts::default());
+        assert_eq!(load(&passport, "c").counts(), FriendRequestCounts::default());
+    }
+}
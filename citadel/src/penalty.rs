@@ -0,0 +1,330 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 中途退出惩罚模块
+//!
+//! # 概述
+//! 对局进行中离开只会被淘汰，没有其他后果，频繁退出会破坏其他玩家的体验。
+//! 本模块记录每个玩家的退出率，并对短时间内多次退出的玩家施加临时的匹配禁令：
+//! - 每次中途退出都会记录到最近退出列表中，超过统计窗口的记录会被移除
+//! - 窗口内的退出次数超过免罚次数后，每多一次禁令时长翻倍，不超过最大时长
+//! - 完整打完一局会计入完成场次，用于计算退出率
+//!
+//! 禁令期间不能加入匹配队列，惩罚状态会在 `queue:status` 的响应中返回。
+//!
+//! # 配置
+//! - `PENALTY_WINDOW_SECS`：统计最近退出次数的窗口（秒），默认86400
+//! - `PENALTY_FREE_ABANDONS`：窗口内不受罚的退出次数，默认1
+//! - `PENALTY_BASE_BAN_SECS`：首次禁令时长（秒），默认300
+//! - `PENALTY_MAX_BAN_SECS`：最长禁令时长（秒），默认86400
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+use crate::game::{GameCachePrefix, GameService};
+use crate::gaming::MatchData;
+
+/// 默认统计窗口
+pub const DEFAULT_PENALTY_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+/// 默认免罚次数
+pub const DEFAULT_FREE_ABANDONS: u32 = 1;
+/// 默认首次禁令时长
+pub const DEFAULT_BASE_BAN: Duration = Duration::from_secs(5 * 60);
+/// 默认最长禁令时长
+pub const DEFAULT_MAX_BAN: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 惩罚配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PenaltyConfig {
+    /// 统计最近退出次数的窗口
+    pub window: Duration,
+    /// 窗口内不受罚的退出次数
+    pub free_abandons: u32,
+    /// 首次禁令时长
+    pub base_ban: Duration,
+    /// 最长禁令时长
+    pub max_ban: Duration,
+}
+
+impl Default for PenaltyConfig {
+    fn default() -> Self {
+        Self {
+            window: DEFAULT_PENALTY_WINDOW,
+            free_abandons: DEFAULT_FREE_ABANDONS,
+            base_ban: DEFAULT_BASE_BAN,
+            max_ban: DEFAULT_MAX_BAN,
+        }
+    }
+}
+
+impl PenaltyConfig {
+    /// 从环境变量读取惩罚配置，未设置的项使用默认值
+    pub fn from_env() -> Self {
+        let parse = |key: &str| std::env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        let defaults = Self::default();
+        let base_ban = parse("PENALTY_BASE_BAN_SECS").map(Duration::from_secs).unwrap_or(defaults.base_ban);
+        Self {
+            window: parse("PENALTY_WINDOW_SECS")
+                .filter(|v| *v > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.window),
+            free_abandons: parse("PENALTY_FREE_ABANDONS").map(|v| v as u32).unwrap_or(defaults.free_abandons),
+            base_ban,
+            max_ban: parse("PENALTY_MAX_BAN_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.max_ban)
+                .max(base_ban),
+        }
+    }
+
+    /// 窗口内第 `abandons` 次退出对应的禁令时长（毫秒），未超过免罚次数返回None
+    pub fn ban_for(&self, abandons: u32) -> Option<u64> {
+        let offenses = abandons.checked_sub(self.free_abandons).filter(|n| *n > 0)?;
+        let base = self.base_ban.as_millis() as u64;
+        let max = self.max_ban.as_millis() as u64;
+        let factor = 1u64.checked_shl(offenses - 1).unwrap_or(u64::MAX);
+        Some(base.saturating_mul(factor).min(max))
+    }
+}
+
+/// 玩家的退出记录
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PenaltyRecord {
+    /// 中途退出的总场次
+    pub abandoned: u32,
+    /// 完整打完的总场次
+    pub completed: u32,
+    /// 统计窗口内的退出时间
+    pub recent: VecDeque<u64>,
+    /// 禁令截止时间
+    pub banned_until: Option<u64>,
+}
+
+impl PenaltyRecord {
+    /// 退出率
+    pub fn abandon_rate(&self) -> f64 {
+        let total = self.abandoned + self.completed;
+        if total == 0 {
+            0.0
+        } else {
+            self.abandoned as f64 / total as f64
+        }
+    }
+
+    /// 移除超过统计窗口的退出记录
+    fn prune(&mut self, now: u64, config: &PenaltyConfig) {
+        let window = config.window.as_millis() as u64;
+        while self.recent.front().is_some_and(|at| now.saturating_sub(*at) > window) {
+            self.recent.pop_front();
+        }
+    }
+
+    /**
+     * 记录一次中途退出
+     *
+     * 参数:
+     * @param now - 当前时间
+     * @param config - 惩罚配置
+     *
+     * 返回:
+     * 本次施加的禁令时长（毫秒），未受罚返回None
+     */
+    pub fn record_abandon(&mut self, now: u64, config: &PenaltyConfig) -> Option<u64> {
+        self.prune(now, config);
+        self.recent.push_back(now);
+        self.abandoned += 1;
+        let ban = config.ban_for(self.recent.len() as u32)?;
+        // 禁令不会因为新的退出而缩短
+        let until = now + ban;
+        self.banned_until = Some(self.banned_until.map_or(until, |current| current.max(until)));
+        Some(ban)
+    }
+
+    /// 记录完整打完一局
+    pub fn record_completion(&mut self) {
+        self.completed += 1;
+    }
+
+    /// 剩余禁令时长（毫秒），没有生效的禁令返回None
+    pub fn ban_remaining(&self, now: u64) -> Option<u64> {
+        self.banned_until.filter(|until| *until > now).map(|until| until - now)
+    }
+
+    /// 生成惩罚状态
+    pub fn status(&self, now: u64, config: &PenaltyConfig) -> PenaltyStatus {
+        let mut record = self.clone();
+        record.prune(now, config);
+        let remaining = record.ban_remaining(now);
+        PenaltyStatus {
+            banned: remaining.is_some(),
+            banned_until: remaining.and(record.banned_until),
+            remaining_ms: remaining,
+            recent_abandons: record.recent.len() as u32,
+            abandon_rate: record.abandon_rate(),
+        }
+    }
+}
+
+/// 惩罚状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PenaltyStatus {
+    /// 是否处于匹配禁令中
+    pub banned: bool,
+    /// 禁令截止时间
+    pub banned_until: Option<u64>,
+    /// 剩余禁令时长（毫秒）
+    pub remaining_ms: Option<u64>,
+    /// 统计窗口内的退出次数
+    pub recent_abandons: u32,
+    /// 退出率
+    pub abandon_rate: f64,
+}
+
+/// 惩罚服务
+pub struct PenaltyService {
+    /// 游戏服务，处理缓存
+    game_service: Arc<GameService>,
+    /// 惩罚配置
+    config: PenaltyConfig,
+    /// 写锁，保证读-改-写的原子性
+    lock: parking_lot::Mutex<()>,
+}
+
+impl PenaltyService {
+    /// 创建新的惩罚服务
+    pub fn new(game_service: Arc<GameService>, config: PenaltyConfig) -> Self {
+        Self {
+            game_service,
+            config,
+            lock: parking_lot::Mutex::new(()),
+        }
+    }
+
+    fn now() -> u64 {
+        chrono::Utc::now().timestamp_millis() as u64
+    }
+
+    fn get_record(&self, user_id: &str) -> PenaltyRecord {
+        self.game_service.get(GameCachePrefix::PENALTY, user_id).unwrap_or_default()
+    }
+
+    fn save_record(&self, user_id: &str, record: &PenaltyRecord) {
+        if !self.game_service.set(GameCachePrefix::PENALTY, user_id, record) {
+            error!("保存玩家 {} 的退出记录失败", user_id);
+        }
+    }
+
+    /// 获取玩家的惩罚状态
+    pub fn status(&self, user_id: &str) -> PenaltyStatus {
+        self.get_record(user_id).status(Self::now(), &self.config)
+    }
+
+    /// 剩余禁令时长（毫秒），没有生效的禁令返回None
+    pub fn ban_remaining(&self, user_id: &str) -> Option<u64> {
+        self.get_record(user_id).ban_remaining(Self::now())
+    }
+
+    /**
+     * 记录玩家中途退出对局
+     *
+     * 参数:
+     * @param user_id - 玩家ID
+     *
+     * 返回:
+     * 本次施加的禁令时长（毫秒），未受罚返回None
+     */
+    pub fn record_abandon(&self, user_id: &str) -> Option<u64> {
+        let _guard = self.lock.lock();
+        let mut record = self.get_record(user_id);
+        let ban = record.record_abandon(Self::now(), &self.config);
+        self.save_record(user_id, &record);
+        if let Some(ban) = ban {
+            info!("玩家 {} 频繁退出对局，禁止匹配 {} 秒", user_id, ban / 1000);
+        }
+        ban
+    }
+
+    /// 为一局已结束游戏中没有中途退出的玩家记录完成场次
+    pub fn record_match(&self, match_data: &MatchData) {
+        let _guard = self.lock.lock();
+        for player in match_data.players.iter().chain(match_data.out.iter()) {
+            let user_id = &player.user.id;
+            if match_data.abandoned.contains(user_id) {
+                continue;
+            }
+            let mut record = self.get_record(user_id);
+            record.record_completion();
+            self.save_record(user_id, &record);
+        }
+    }
+}
+
+// 用于存储全局PenaltyService实例的静态变量
+static GLOBAL_PENALTY_SERVICE: OnceCell<Arc<PenaltyService>> = OnceCell::new();
+
+/// 初始化惩罚服务并设置为全局实例
+pub fn init_penalty_service(game_service: Arc<GameService>) -> Arc<PenaltyService> {
+    let service = Arc::new(PenaltyService::new(game_service, PenaltyConfig::from_env()));
+    let _ = GLOBAL_PENALTY_SERVICE.set(service.clone());
+    service
+}
+
+/// 获取全局惩罚服务
+pub fn global_penalty_service() -> Option<Arc<PenaltyService>> {
+    GLOBAL_PENALTY_SERVICE.get().cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: u64 = 60 * 1000;
+
+    #[test]
+    fn test_escalating_bans() {
+        let config = PenaltyConfig::default();
+        let mut record = PenaltyRecord::default();
+
+        // 第一次退出不受罚
+        assert_eq!(record.record_abandon(0, &config), None);
+        assert!(!record.status(0, &config).banned);
+
+        // 之后每次禁令时长翻倍
+        assert_eq!(record.record_abandon(MINUTE, &config), Some(5 * MINUTE));
+        assert_eq!(record.record_abandon(2 * MINUTE, &config), Some(10 * MINUTE));
+        assert_eq!(record.record_abandon(3 * MINUTE, &config), Some(20 * MINUTE));
+        let status = record.status(4 * MINUTE, &config);
+        assert!(status.banned);
+        assert_eq!(status.remaining_ms, Some(19 * MINUTE));
+        assert_eq!(status.recent_abandons, 4);
+
+        // 不超过最长禁令时长
+        assert_eq!(config.ban_for(40), Some(DEFAULT_MAX_BAN.as_millis() as u64));
+        assert_eq!(record.ban_remaining(23 * MINUTE + 1), None);
+    }
+
+    #[test]
+    fn test_window_and_rate() {
+        let config = PenaltyConfig::default();
+        let window = config.window.as_millis() as u64;
+        let mut record = PenaltyRecord::default();
+        record.record_abandon(0, &config);
+        record.record_completion();
+        record.record_completion();
+        record.record_completion();
+        assert_eq!(record.abandon_rate(), 0.25);
+
+        // 超过统计窗口的退出不再计入，再次退出不受罚
+        assert_eq!(record.record_abandon(window + 1, &config), None);
+        assert_eq!(record.recent.len(), 1);
+        assert_eq!(record.abandoned, 2);
+    }
+}
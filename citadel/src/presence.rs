@@ -0,0 +1,217 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 自定义状态消息与丰富在线状态模块
+//!
+//! # 概述
+//! `user:get-supplemental` 原本只返回在线状态和活动类型。本模块为其补充两类信息：
+//! - 状态消息：玩家通过 `user:set-interim` 设置的自由文本，校验长度并过滤不当词汇，发送空字符串清除
+//! - 丰富在线状态：由 `MatchService` 在每次保存对局时自动更新，包括对局ID、剩余玩家数和当前回合，
+//!   对局结束或玩家出局后自动清除，客户端据此展示“对局中，剩余3名玩家，第12回合”之类的信息
+//!
+//! 状态消息和在线状态只保存在内存中，服务重启后丢失。
+//! `idle` 子模块根据连接的最后操作时间自动将用户切换为空闲或离开状态。
+//!
+//! # 配置
+//! - `PROFANITY_WORDS`：额外的过滤词，逗号分隔
+//! - `PROFANITY_WORDS_PATH`：过滤词文件，每行一个
+//! - `IDLE_AFTER_SECS`、`AWAY_AFTER_SECS`、`IDLE_CHECK_INTERVAL_SECS`：见 `idle` 子模块
+//!
+//! # 消息格式
+//! ```json
+//! // 客户端 -> 服务器
+//! { "event": "user:set-interim", "data": { "statusMessage": "来一局吗" } }
+//! // user:get-supplemental 响应中的单个用户
+//! { "status": "online", "activity": { "type": "in-match" }, "statusMessage": "来一局吗",
+//!   "presence": { "state": "in-match", "matchId": "...", "playersLeft": 3, "turn": 12, "updatedAt": 0 } }
+//! ```
+
+pub mod idle;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::gaming::{MatchData, MatchState};
+
+/// 状态消息的最大长度（字符）
+pub const MAX_STATUS_MESSAGE_LEN: usize = 100;
+
+/// 内置的过滤词
+const DEFAULT_PROFANITY: &[&str] = &["fuck", "shit", "bitch", "cunt", "asshole", "傻逼", "操你"];
+
+/// 丰富在线状态的阶段
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum PresenceState {
+    /// 在等待中的对局里
+    Waiting,
+    /// 对局中
+    InMatch,
+    /// 对局已暂停
+    Paused,
+    /// 观战中
+    Spectating,
+}
+
+/// 丰富在线状态
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RichPresence {
+    /// 当前阶段
+    pub state: PresenceState,
+    /// 对局ID
+    pub match_id: String,
+    /// 剩余玩家数
+    pub players_left: usize,
+    /// 当前回合，从1开始
+    pub turn: usize,
+    /// 更新时间
+    pub updated_at: u64,
+}
+
+impl RichPresence {
+    /**
+     * 计算用户在对局中的在线状态
+     *
+     * 参数:
+     * @param match_data - 对局数据
+     * @param user_id - 用户ID
+     * @param now - 当前时间
+     *
+     * 返回:
+     * 用户仍在对局中（玩家或观众）时返回在线状态，已出局或对局已结束返回None
+     */
+    pub fn for_match(match_data: &MatchData, user_id: &str, now: u64) -> Option<Self> {
+        let is_player = match_data.players.iter().any(|p| p.user.id == user_id);
+        let state = match match_data.state {
+            MatchState::Completed => return None,
+            _ if !is_player && match_data.spectators.iter().any(|s| s.id == user_id) => PresenceState::Spectating,
+            _ if !is_player => return None,
+            MatchState::Waiting => PresenceState::Waiting,
+            MatchState::InProgress => PresenceState::InMatch,
+            MatchState::Paused => PresenceState::Paused,
+        };
+        Some(Self {
+            state,
+            match_id: match_data.id.clone(),
+            players_left: match_data.players.len(),
+            turn: match_data.turn_log.len() + 1,
+            updated_at: now,
+        })
+    }
+}
+
+/// 不当词汇过滤器
+#[derive(Debug, Clone)]
+pub struct ProfanityFilter {
+    /// 小写的过滤词
+    words: Vec<String>,
+}
+
+impl ProfanityFilter {
+    /// 使用指定的过滤词创建过滤器
+    pub fn new<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut words: Vec<String> = words
+            .into_iter()
+            .map(|w| w.as_ref().trim().to_ascii_lowercase())
+            .filter(|w| !w.is_empty())
+            .collect();
+        words.sort();
+        words.dedup();
+        Self { words }
+    }
+
+    /// 内置过滤词加上环境变量中配置的过滤词
+    pub fn from_env() -> Self {
+        let mut words: Vec<String> = DEFAULT_PROFANITY.iter().map(|w| w.to_string()).collect();
+        if let Ok(list) = std::env::var("PROFANITY_WORDS") {
+            words.extend(list.split(',').map(str::to_string));
+        }
+        if let Ok(path) = std::env::var("PROFANITY_WORDS_PATH") {
+            match std::fs::read_to_string(&path) {
+                Ok(content) => words.extend(content.lines().map(str::to_string)),
+                Err(e) => warn!("读取过滤词文件 {} 失败: {}", path, e),
+            }
+        }
+        Self::new(words)
+    }
+
+    /// 将文本中的过滤词替换为 `*`，匹配时忽略ASCII大小写
+    pub fn mask(&self, text: &str) -> String {
+        // ASCII小写化不改变字节位置，可以直接用于标记原文
+        let lower = text.to_ascii_lowercase();
+        let mut masked = vec![false; text.len()];
+        for word in &self.words {
+            for (start, _) in lower.match_indices(word.as_str()) {
+                masked[start..start + word.len()].iter_mut().for_each(|m| *m = true);
+            }
+        }
+        text.char_indices()
+            .map(|(index, c)| if masked[index] { '*' } else { c })
+            .collect()
+    }
+}
+
+/// 全局过滤器
+static PROFANITY_FILTER: Lazy<ProfanityFilter> = Lazy::new(ProfanityFilter::from_env);
+
+/**
+ * 校验并过滤状态消息
+ *
+ * 参数:
+ * @param raw - 客户端提交的状态消息
+ * @param filter - 不当词汇过滤器
+ *
+ * 返回:
+ * 过滤后的状态消息，空消息返回None表示清除，超长或包含控制字符时返回错误说明
+ */
+pub fn sanitize_status_message(raw: &str, filter: &ProfanityFilter) -> Result<Option<String>, String> {
+    let message = raw.trim();
+    if message.is_empty() {
+        return Ok(None);
+    }
+    if message.chars().count() > MAX_STATUS_MESSAGE_LEN {
+        return Err(format!("状态消息不能超过 {} 个字符", MAX_STATUS_MESSAGE_LEN));
+    }
+    if message.chars().any(char::is_control) {
+        return Err("状态消息不能包含控制字符".to_string());
+    }
+    Ok(Some(filter.mask(message)))
+}
+
+/// 使用全局过滤器校验状态消息
+pub fn sanitize(raw: &str) -> Result<Option<String>, String> {
+    sanitize_status_message(raw, &PROFANITY_FILTER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profanity_mask() {
+        let filter = ProfanityFilter::new(["shit", "坏词"]);
+        assert_eq!(filter.mask("oh SHIT, 坏词!"), "oh ****, **!");
+        assert_eq!(filter.mask("clean text"), "clean text");
+        // 重叠匹配也会被完整遮盖
+        let filter = ProfanityFilter::new(["ab", "bc"]);
+        assert_eq!(filter.mask("xabcx"), "x***x");
+    }
+
+    #[test]
+    fn test_sanitize_status_message() {
+        let filter = ProfanityFilter::new(["shit"]);
+        assert_eq!(sanitize_status_message("   ", &filter), Ok(None));
+        assert_eq!(sanitize_status_message(" hi ", &filter), Ok(Some("hi".to_string())));
+        assert_eq!(sanitize_status_message("shitty", &filter), Ok(Some("****ty".to_string())));
+        assert!(sanitize_status_message("a\nb", &filter).is_err());
+        assert!(sanitize_status_message(&"字".repeat(MAX_STATUS_MESSAGE_LEN + 1), &filter).is_err());
+        assert!(sanitize_status_message(&"字".repeat(MAX_STATUS_MESSAGE_LEN), &filter).is_ok());
+    }
+}
@@ -0,0 +1,227 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 空闲检测与自动离开状态
+//!
+//! # 概述
+//! 用户连接后状态一直保持在线，即使长时间没有任何操作。本模块记录每个连接最后一次收到客户端消息的时间
+//! （心跳不计入），后台任务定期检查每个在线用户的所有连接：
+//! - 超过 `IDLE_AFTER_SECS` 没有操作时状态变为空闲（Idle）
+//! - 超过 `AWAY_AFTER_SECS` 没有操作时状态变为离开（Away）
+//! - 空闲或离开的用户重新发送消息后立即恢复在线
+//!
+//! 状态变化通过 `status_updates` 房间广播，与上线、下线使用同一套状态广播。
+//! 游戏中和离线的用户不受影响。
+//!
+//! # 配置
+//! - `IDLE_AFTER_SECS`：进入空闲状态的时间，默认300秒，为0时关闭空闲检测
+//! - `AWAY_AFTER_SECS`：进入离开状态的时间，默认900秒
+//! - `IDLE_CHECK_INTERVAL_SECS`：检查间隔，默认30秒
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use tokio::time::{interval, Duration};
+use tracing::{debug, error};
+
+use crate::passport::{PassportState, UserStatus};
+use crate::ws::ClientId;
+
+/// 空闲检测配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdleConfig {
+    /// 进入空闲状态的时间（毫秒），为0时关闭空闲检测
+    pub idle_after_ms: u64,
+    /// 进入离开状态的时间（毫秒）
+    pub away_after_ms: u64,
+    /// 检查间隔
+    pub check_interval: Duration,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            idle_after_ms: 300_000,
+            away_after_ms: 900_000,
+            check_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+impl IdleConfig {
+    /// 从环境变量读取，未配置或无效的项使用默认值
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let secs = |key: &str| std::env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        let idle_after_ms = secs("IDLE_AFTER_SECS")
+            .map(|v| v * 1000)
+            .unwrap_or(defaults.idle_after_ms);
+        Self {
+            idle_after_ms,
+            // 离开不早于空闲
+            away_after_ms: secs("AWAY_AFTER_SECS")
+                .map(|v| v * 1000)
+                .unwrap_or(defaults.away_after_ms)
+                .max(idle_after_ms),
+            check_interval: secs("IDLE_CHECK_INTERVAL_SECS")
+                .filter(|v| *v > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.check_interval),
+        }
+    }
+
+    /// 是否启用空闲检测
+    pub fn enabled(&self) -> bool {
+        self.idle_after_ms > 0
+    }
+
+    /**
+     * 根据未操作的时间确定用户应处的状态
+     *
+     * 参数:
+     * @param current - 当前状态
+     * @param inactive_ms - 距离最后一次操作的时间（毫秒）
+     *
+     * 返回:
+     * 需要变更时返回新状态，游戏中、离线或状态不变时返回None
+     */
+    pub fn transition(&self, current: &UserStatus, inactive_ms: u64) -> Option<UserStatus> {
+        if !matches!(current, UserStatus::Online | UserStatus::Idle | UserStatus::Away) {
+            return None;
+        }
+        let target = if inactive_ms >= self.away_after_ms {
+            UserStatus::Away
+        } else if inactive_ms >= self.idle_after_ms {
+            UserStatus::Idle
+        } else {
+            UserStatus::Online
+        };
+        (target != *current).then_some(target)
+    }
+}
+
+/// 每个连接最后一次操作的时间
+#[derive(Debug, Default)]
+pub struct ActivityTracker {
+    idle_after_ms: u64,
+    last_activity: Mutex<HashMap<ClientId, u64>>,
+}
+
+impl ActivityTracker {
+    pub fn new(config: &IdleConfig) -> Self {
+        Self {
+            idle_after_ms: config.idle_after_ms,
+            last_activity: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 记录一次操作，返回此前是否已经达到空闲时间
+    pub fn touch(&self, client_id: &str, now: u64) -> bool {
+        let previous = self.last_activity.lock().insert(client_id.to_string(), now);
+        self.idle_after_ms > 0
+            && previous
+                .map(|previous| now.saturating_sub(previous) >= self.idle_after_ms)
+                .unwrap_or(false)
+    }
+
+    /// 连接断开时移除记录
+    pub fn remove(&self, client_id: &str) {
+        self.last_activity.lock().remove(client_id);
+    }
+
+    /// 多个连接中最近一次操作的时间
+    pub fn latest(&self, client_ids: &[ClientId]) -> Option<u64> {
+        let last_activity = self.last_activity.lock();
+        client_ids.iter().filter_map(|id| last_activity.get(id).copied()).max()
+    }
+}
+
+/// 空闲或离开的用户重新操作后恢复在线
+pub async fn mark_active(passport: &PassportState, user_id: &str) {
+    let status = passport.get_user_status(user_id).await;
+    if !matches!(status, UserStatus::Idle | UserStatus::Away) {
+        return;
+    }
+    if let Err(e) = update_and_broadcast(passport, user_id, UserStatus::Online).await {
+        error!("恢复用户 {} 在线状态失败: {}", user_id, e);
+    }
+}
+
+async fn update_and_broadcast(passport: &PassportState, user_id: &str, status: UserStatus) -> anyhow::Result<()> {
+    debug!("用户 {} 状态自动变更为 {:?}", user_id, status);
+    passport.update_user_status(user_id, status.clone()).await?;
+    passport.broadcast_user_status(user_id, status).await
+}
+
+/**
+ * 启动空闲检测任务
+ *
+ * 参数:
+ * @param passport - 用户护照状态
+ * @param config - 空闲检测配置
+ */
+pub fn spawn_idle_detector(passport: Arc<PassportState>, config: IdleConfig) {
+    if !config.enabled() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut ticker = interval(config.check_interval);
+        loop {
+            ticker.tick().await;
+            let now = chrono::Utc::now().timestamp_millis() as u64;
+            let tracker = passport.connection_manager.activity();
+            let sessions = passport.user_sessions.lock().await.clone();
+            for (user_id, client_ids) in sessions {
+                let Some(last_activity) = tracker.latest(&client_ids) else {
+                    continue;
+                };
+                let current = passport.get_user_status(&user_id).await;
+                let Some(target) = config.transition(&current, now.saturating_sub(last_activity)) else {
+                    continue;
+                };
+                if let Err(e) = update_and_broadcast(&passport, &user_id, target).await {
+                    error!("更新用户 {} 空闲状态失败: {}", user_id, e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> IdleConfig {
+        IdleConfig {
+            idle_after_ms: 1000,
+            away_after_ms: 5000,
+            check_interval: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn test_transition() {
+        let config = config();
+        assert_eq!(config.transition(&UserStatus::Online, 500), None);
+        assert_eq!(config.transition(&UserStatus::Online, 1000), Some(UserStatus::Idle));
+        assert_eq!(config.transition(&UserStatus::Idle, 5000), Some(UserStatus::Away));
+        assert_eq!(config.transition(&UserStatus::Away, 0), Some(UserStatus::Online));
+        // 游戏中和离线的用户不受影响
+        assert_eq!(config.transition(&UserStatus::InGame, 10_000), None);
+        assert_eq!(config.transition(&UserStatus::Offline, 10_000), None);
+    }
+
+    #[test]
+    fn test_activity_tracker() {
+        let tracker = ActivityTracker::new(&config());
+        assert!(!tracker.touch("c1", 0));
+        assert!(!tracker.touch("c1", 999));
+        assert!(tracker.touch("c1", 2000));
+        tracker.touch("c2", 3000);
+        assert_eq!(tracker.latest(&["c1".to_string(), "c2".to_string()]), Some(3000));
+
+        tracker.remove("c2");
+        assert_eq!(tracker.latest(&["c2".to_string()]), None);
+    }
+}
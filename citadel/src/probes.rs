@@ -0,0 +1,272 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 存活与就绪探针模块
+//!
+//! # 概述
+//! `/health` 需要认证且只做简单检查，不适合作为k8s探针。本模块提供两个无需认证的接口：
+//! - `GET /livez`：进程能够响应请求即返回200
+//! - `GET /readyz`：检查所有依赖，必需的检查全部通过返回200，否则返回503，响应体中列出每项检查的结果
+//!
+//! 就绪检查包括：
+//! - `sui_rpc`：Sui全节点RPC可达（非必需）
+//! - `checkpoint`：最新检查点时间戳未超过允许的延迟（非必需）
+//! - `node_health`：全节点降级模式状态（非必需）
+//! - `session_store`：会话存储后端可读写
+//! - `profile_updater`：Profile更新器至少成功完成过一轮更新
+//! - `background_tasks`：定期更新、匹配队列等后台任务都在运行，等待重启的任务视为未通过（非必需）
+//!
+//! 全节点相关的检查失败时服务进入降级模式：密钥请求暂不可用，但游戏功能不依赖全节点，
+//! 实例仍然就绪，响应中的 `degraded` 为true。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Serialize;
+use serde_json::json;
+use tracing::warn;
+
+use crate::externals::duration_since;
+use crate::session_store::global_session_store;
+use crate::tasks::{TaskHealth, TaskState};
+use crate::AppState;
+
+/// 单项依赖检查的超时时间
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Profile更新器成功完成的更新轮数
+static PROFILE_CYCLES: AtomicU64 = AtomicU64::new(0);
+
+/// 记录Profile更新器完成了一轮更新
+pub fn record_profile_cycle() {
+    PROFILE_CYCLES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Profile更新器成功完成的更新轮数
+pub fn profile_cycles() -> u64 {
+    PROFILE_CYCLES.load(Ordering::Relaxed)
+}
+
+/// 单项检查结果
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ProbeCheck {
+    pub name: &'static str,
+    pub ok: bool,
+    pub detail: String,
+    /// 是否为必需的检查，非必需的检查失败只会使服务进入降级模式
+    pub required: bool,
+}
+
+impl ProbeCheck {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            ok: true,
+            detail: detail.into(),
+            required: true,
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            ok: false,
+            detail: detail.into(),
+            required: true,
+        }
+    }
+
+    /// 标记为非必需的检查
+    fn optional(self) -> Self {
+        Self { required: false, ..self }
+    }
+}
+
+/// 就绪检查报告
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    /// 是否有非必需的检查失败，即服务处于降级模式
+    pub degraded: bool,
+    pub checks: Vec<ProbeCheck>,
+}
+
+impl ReadinessReport {
+    pub fn new(checks: Vec<ProbeCheck>) -> Self {
+        Self {
+            ready: checks.iter().filter(|c| c.required).all(|c| c.ok),
+            degraded: checks.iter().any(|c| !c.required && !c.ok),
+            checks,
+        }
+    }
+}
+
+impl IntoResponse for ReadinessReport {
+    fn into_response(self) -> Response {
+        let status = if self.ready {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        (status, Json(self)).into_response()
+    }
+}
+
+async fn check_sui_rpc(state: &AppState) -> ProbeCheck {
+    let name = "sui_rpc";
+    match tokio::time::timeout(CHECK_TIMEOUT, state.sui_pool.client().read_api().get_chain_identifier()).await {
+        Ok(Ok(chain_id)) => ProbeCheck::pass(name, format!("链ID: {}", chain_id)),
+        Ok(Err(e)) => ProbeCheck::fail(name, format!("RPC请求失败: {}", e)),
+        Err(_) => ProbeCheck::fail(name, "RPC请求超时"),
+    }
+}
+
+fn check_checkpoint(state: &AppState) -> ProbeCheck {
+    let name = "checkpoint";
+    let staleness = duration_since(*state.latest_checkpoint_timestamp_receiver.borrow());
+    let detail = format!("最新检查点延迟 {} ms", staleness);
+    if staleness > state.node_health.allowed_staleness().as_millis() as i64 {
+        ProbeCheck::fail(name, detail)
+    } else {
+        ProbeCheck::pass(name, detail)
+    }
+}
+
+async fn check_session_store() -> ProbeCheck {
+    let name = "session_store";
+    let Some(store) = global_session_store() else {
+        return ProbeCheck::fail(name, "会话存储尚未初始化");
+    };
+    let kind = if store.is_persistent() { "file" } else { "memory" };
+    match tokio::time::timeout(CHECK_TIMEOUT, store.check_health()).await {
+        Ok(Ok(())) => ProbeCheck::pass(name, kind),
+        Ok(Err(e)) => ProbeCheck::fail(name, format!("{}存储不可用: {}", kind, e)),
+        Err(_) => ProbeCheck::fail(name, format!("{}存储检查超时", kind)),
+    }
+}
+
+fn check_node_health(state: &AppState) -> ProbeCheck {
+    let name = "node_health";
+    let status = state.node_health.status();
+    let detail = match &status.reason {
+        Some(reason) => format!("{:?}: {}", status.state, reason),
+        None => format!("{:?}", status.state),
+    };
+    if status.is_degraded() {
+        ProbeCheck::fail(name, detail)
+    } else {
+        ProbeCheck::pass(name, detail)
+    }
+}
+
+fn check_profile_updater() -> ProbeCheck {
+    let name = "profile_updater";
+    match profile_cycles() {
+        0 => ProbeCheck::fail(name, "尚未完成首轮更新"),
+        cycles => ProbeCheck::pass(name, format!("已完成 {} 轮更新", cycles)),
+    }
+}
+
+fn check_background_tasks(health: &[TaskHealth]) -> ProbeCheck {
+    let name = "background_tasks";
+    let stopped = health
+        .iter()
+        .filter(|t| t.state != TaskState::Running)
+        .map(|t| format!("{}({:?})", t.name, t.state))
+        .collect::<Vec<_>>();
+    let restarts = health.iter().map(|t| t.restarts).sum::<u32>();
+    if stopped.is_empty() {
+        ProbeCheck::pass(name, format!("{} 个任务运行中，共重启 {} 次", health.len(), restarts))
+    } else {
+        ProbeCheck::fail(name, format!("已停止: {}", stopped.join(", ")))
+    }
+}
+
+/**
+ * 执行所有就绪检查
+ *
+ * 参数:
+ * @param state - 应用状态
+ *
+ * 返回:
+ * 就绪检查报告
+ */
+pub async fn check_readiness(state: &AppState) -> ReadinessReport {
+    let (sui_rpc, session_store) = tokio::join!(check_sui_rpc(state), check_session_store());
+    let report = ReadinessReport::new(vec![
+        sui_rpc.optional(),
+        check_checkpoint(state).optional(),
+        check_node_health(state).optional(),
+        session_store,
+        check_profile_updater(),
+        check_background_tasks(&state.tasks.health()).optional(),
+    ]);
+    if !report.ready || report.degraded {
+        let failed = report
+            .checks
+            .iter()
+            .filter(|c| !c.ok)
+            .map(|c| format!("{}: {}", c.name, c.detail))
+            .collect::<Vec<_>>();
+        if report.ready {
+            warn!("服务处于降级模式: {}", failed.join("; "));
+        } else {
+            warn!("就绪检查未通过: {}", failed.join("; "));
+        }
+    }
+    report
+}
+
+/// 存活探针
+pub async fn handle_livez() -> impl IntoResponse {
+    Json(json!({ "status": "ok" }))
+}
+
+/// 就绪探针
+pub async fn handle_readyz(State(state): State<Arc<AppState>>) -> ReadinessReport {
+    check_readiness(&state).await
+}
+
+/// 注册探针路由
+pub fn register_probe_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router
+        .route("/livez", get(handle_livez))
+        .route("/readyz", get(handle_readyz))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readiness_report() {
+        let report = ReadinessReport::new(vec![
+            ProbeCheck::pass("a", "ok"),
+            ProbeCheck::pass("b", "ok"),
+        ]);
+        assert!(report.ready);
+        assert_eq!(report.into_response().status(), StatusCode::OK);
+
+        let report = ReadinessReport::new(vec![
+            ProbeCheck::pass("a", "ok"),
+            ProbeCheck::fail("b", "down"),
+        ]);
+        assert!(!report.ready);
+        assert_eq!(report.into_response().status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        // 非必需的检查失败时仍然就绪，但处于降级模式
+        let report = ReadinessReport::new(vec![
+            ProbeCheck::pass("a", "ok"),
+            ProbeCheck::fail("b", "down").optional(),
+        ]);
+        assert!(report.ready);
+        assert!(report.degraded);
+        assert_eq!(report.into_response().status(), StatusCode::OK);
+    }
+}
@@ -4,16 +4,21 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tower_sessions::Session;
-use tracing::{info, error};
+use tracing::{debug, info, error};
+use utoipa::ToSchema;
 use anyhow::Result;
 
+use crate::account_link;
 use crate::AppState;
 use crate::session_login::{SessionUser, SESSION_USER_KEY};
-use crate::errors::InternalError;
+use crate::errors::{ErrorResponse, InternalError};
+use crate::networks::SuiNetwork;
 use crate::sdk::{Profile,ProfileWithRelationship};
+use crate::stats::{self, CardUsage, RatingHistoryEntry, FAVORITE_CARDS_LIMIT};
+use crate::tool::elo;
 
 /// 用户统计信息响应
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct UserStats {
     /// 胜场数
     pub won: u64,
@@ -25,18 +30,43 @@ pub struct UserStats {
     pub winrate: u64,
     /// 评分
     pub rating: u64,
+    /// 评分是否为临时评分（未完成定级赛）
+    pub provisional: bool,
 }
 
 /// 用户档案响应
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ProfileResponse {
     pub success: bool,
+    #[schema(value_type = Option<Object>)]
     pub profile: Option<ProfileWithRelationship>,
     pub error: Option<String>,
 }
 
+/// 单次批量查询的最大用户数
+pub const MAX_BATCH_PROFILES: usize = 50;
+
+/// 批量获取用户档案请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchProfilesRequest {
+    /// Profile对象ID列表，最多50个，重复的ID只返回一次
+    pub ids: Vec<String>,
+}
+
+/// 批量获取用户档案响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchProfilesResponse {
+    pub success: bool,
+    /// 找到的档案，按请求顺序排列
+    #[schema(value_type = Vec<Object>)]
+    pub profiles: Vec<Profile>,
+    /// 格式无效或不存在的ID
+    pub missing: Vec<String>,
+    pub error: Option<String>,
+}
+
 /// 用户统计信息响应
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct StatsResponse {
     pub success: bool,
     pub stats: Option<UserStats>,
@@ -44,12 +74,23 @@ pub struct StatsResponse {
 }
 
 /// 获取当前用户档案
+#[utoipa::path(
+    get,
+    path = "/profile/me",
+    tag = "profile",
+    responses(
+        (status = 200, description = "当前用户的档案", body = ProfileResponse),
+        (status = 403, description = "未登录或参数无效", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
 pub async fn get_my_profile(
     State(app_state): State<Arc<AppState>>,
+    SuiNetwork(network): SuiNetwork,
     Extension(session): Extension<Session>,
 ) -> Result<Json<ProfileResponse>, InternalError> {
     info!("收到获取当前用户档案请求");
+    app_state.metrics.observe_network_request(&network.name, "get_my_profile");
     
     // 从session获取用户信息
     let user = session.get::<SessionUser>(SESSION_USER_KEY).await?
@@ -57,7 +98,7 @@ pub async fn get_my_profile(
     
     if let Some(profile) = user.profile {
         // 获取带关系信息的Profile
-        match app_state.game_manager.get_profile_with_relationship(&profile.id, None).await {
+        match network.game_manager.get_profile_with_relationship(&profile.id, None).await {
             Ok(profile_with_relationship) => {
                 Ok(Json(ProfileResponse {
                     success: true,
@@ -84,13 +125,25 @@ pub async fn get_my_profile(
 }
 
 /// 获取指定用户档案
+#[utoipa::path(
+    get,
+    path = "/profile/{profile_id}",
+    tag = "profile",
+    params(("profile_id" = String, Path, description = "Profile对象ID")),
+    responses(
+        (status = 200, description = "指定用户的档案及与当前用户的关系", body = ProfileResponse),
+        (status = 403, description = "未登录或参数无效", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
 pub async fn get_user_profile(
     State(app_state): State<Arc<AppState>>,
+    SuiNetwork(network): SuiNetwork,
     Path(profile_id): Path<String>,
     Extension(session): Extension<Session>,
 ) -> Result<Json<ProfileResponse>, InternalError> {
     info!("收到获取用户档案请求: {}", profile_id);
+    app_state.metrics.observe_network_request(&network.name, "get_user_profile");
     
     // 将ProfileID转换为ObjectID
     let profile_obj_id = sui_types::base_types::ObjectID::from_hex_literal(&profile_id)
@@ -101,7 +154,7 @@ pub async fn get_user_profile(
     let current_user_profile = current_user.and_then(|u| u.profile);
     
     // 获取用户档案(带关系信息)
-    match app_state.game_manager.get_profile_with_relationship(
+    match network.game_manager.get_profile_with_relationship(
         &profile_obj_id,
         current_user_profile.as_ref().map(|p| &p.id)
     ).await {
@@ -123,7 +176,80 @@ pub async fn get_user_profile(
     }
 }
 
+/// 批量获取用户档案
+///
+/// 一次返回对局中所有玩家的档案，部分ID无效或不存在时仍返回其余档案，并在missing中列出这些ID
+#[utoipa::path(
+    post,
+    path = "/v1/profiles/batch",
+    tag = "profile",
+    request_body = BatchProfilesRequest,
+    responses(
+        (status = 200, description = "找到的档案和无效或不存在的ID", body = BatchProfilesResponse),
+        (status = 403, description = "ID数量超过上限", body = ErrorResponse),
+    )
+)]
+#[debug_handler]
+pub async fn get_profiles_batch(
+    State(app_state): State<Arc<AppState>>,
+    SuiNetwork(network): SuiNetwork,
+    Json(request): Json<BatchProfilesRequest>,
+) -> Result<Json<BatchProfilesResponse>, InternalError> {
+    info!("收到批量获取用户档案请求: {} 个ID", request.ids.len());
+    app_state.metrics.observe_network_request(&network.name, "get_profiles_batch");
+    
+    // 去重并保持请求顺序
+    let mut ids: Vec<String> = Vec::with_capacity(request.ids.len());
+    for id in request.ids {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    if ids.len() > MAX_BATCH_PROFILES {
+        return Err(InternalError::InvalidInput);
+    }
+    
+    // 格式无效的ID直接列入missing
+    let mut missing = Vec::new();
+    let mut object_ids = Vec::with_capacity(ids.len());
+    for id in ids {
+        match sui_types::base_types::ObjectID::from_hex_literal(&id) {
+            Ok(object_id) => object_ids.push((id, object_id)),
+            Err(_) => missing.push(id),
+        }
+    }
+    
+    let lookup: Vec<_> = object_ids.iter().map(|(_, object_id)| *object_id).collect();
+    let results = network.game_manager.get_profiles(&lookup).await;
+    let mut profiles = Vec::with_capacity(results.len());
+    for ((id, _), result) in object_ids.into_iter().zip(results) {
+        match result {
+            Ok(profile) => profiles.push(profile),
+            Err(e) => {
+                debug!("批量获取用户档案时未找到 {}: {}", id, e);
+                missing.push(id);
+            }
+        }
+    }
+    
+    Ok(Json(BatchProfilesResponse {
+        success: true,
+        profiles,
+        missing,
+        error: None,
+    }))
+}
+
 /// 获取当前用户统计信息
+#[utoipa::path(
+    get,
+    path = "/profile/me/stats",
+    tag = "profile",
+    responses(
+        (status = 200, description = "当前用户的统计信息", body = StatsResponse),
+        (status = 403, description = "未登录或参数无效", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
 pub async fn get_my_stats(
     State(app_state): State<Arc<AppState>>,
@@ -146,6 +272,7 @@ pub async fn get_my_stats(
                 0
             },
             rating: profile.rating,
+            provisional: elo::is_provisional(profile.played),
         };
         
         Ok(Json(StatsResponse {
@@ -163,19 +290,31 @@ pub async fn get_my_stats(
 }
 
 /// 获取指定用户统计信息
+#[utoipa::path(
+    get,
+    path = "/profile/{profile_id}/stats",
+    tag = "profile",
+    params(("profile_id" = String, Path, description = "Profile对象ID")),
+    responses(
+        (status = 200, description = "指定用户的统计信息", body = StatsResponse),
+        (status = 403, description = "未登录或参数无效", body = ErrorResponse),
+    )
+)]
 #[debug_handler]
 pub async fn get_user_stats(
     State(app_state): State<Arc<AppState>>,
+    SuiNetwork(network): SuiNetwork,
     Path(profile_id): Path<String>,
 ) -> Result<Json<StatsResponse>, InternalError> {
     info!("收到获取用户统计信息请求: {}", profile_id);
+    app_state.metrics.observe_network_request(&network.name, "get_user_stats");
     
     // 将ProfileID转换为ObjectID
     let profile_obj_id = sui_types::base_types::ObjectID::from_hex_literal(&profile_id)
         .map_err(|_| InternalError::InvalidInput)?;
     
     // 获取用户档案
-    match app_state.game_manager.get_profile(&profile_obj_id).await {
+    match network.game_manager.get_profile(&profile_obj_id).await {
         Ok(profile) => {
             let stats = UserStats {
                 won: profile.won,
@@ -187,6 +326,7 @@ pub async fn get_user_stats(
                     0
                 },
                 rating: profile.rating,
+                provisional: elo::is_provisional(profile.played),
             };
             
             Ok(Json(StatsResponse {
@@ -206,6 +346,125 @@ pub async fn get_user_stats(
     }
 }
 
+/// 对局统计信息
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileMatchStats {
+    /// 总场数
+    pub games_played: u64,
+    /// 胜场数
+    pub wins: u64,
+    /// 负场数
+    pub losses: u64,
+    /// 胜率
+    pub win_rate: u64,
+    /// 当前连胜（正数）或连败（负数）
+    pub current_streak: i64,
+    /// 最长连胜
+    pub best_win_streak: u64,
+    /// 常用卡牌
+    #[schema(value_type = Vec<Object>)]
+    pub favorite_cards: Vec<CardUsage>,
+    /// 各类卡牌的使用次数
+    #[schema(value_type = Vec<Object>)]
+    pub card_plays: Vec<CardUsage>,
+    /// 平均回合时间（毫秒）
+    pub average_turn_ms: u64,
+    /// 生存率（百分比）
+    pub survival_rate: u64,
+}
+
+/// 对局统计信息响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProfileMatchStatsResponse {
+    pub success: bool,
+    pub stats: Option<ProfileMatchStats>,
+    pub error: Option<String>,
+}
+
+/// 评分历史响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RatingHistoryResponse {
+    pub success: bool,
+    pub history: Option<Vec<RatingHistoryEntry>>,
+    pub error: Option<String>,
+}
+
+/// 获取指定用户的对局统计信息
+#[utoipa::path(
+    get,
+    path = "/v1/profiles/{profile_id}/stats",
+    tag = "profile",
+    params(("profile_id" = String, Path, description = "Profile对象ID")),
+    responses((status = 200, description = "指定用户的对局统计信息", body = ProfileMatchStatsResponse))
+)]
+#[debug_handler]
+pub async fn get_profile_match_stats(
+    State(app_state): State<Arc<AppState>>,
+    Path(profile_id): Path<String>,
+) -> Result<Json<ProfileMatchStatsResponse>, InternalError> {
+    info!("收到获取用户对局统计请求: {}", profile_id);
+    app_state.metrics.observe_request("get_profile_match_stats");
+    let profile_id = account_link::resolve_user_id(&profile_id);
+    
+    let Some(service) = stats::global_stats_service() else {
+        return Ok(Json(ProfileMatchStatsResponse {
+            success: false,
+            stats: None,
+            error: Some("统计服务未初始化".to_string()),
+        }));
+    };
+    
+    let player_stats = service.get_stats(&profile_id).unwrap_or_default();
+    Ok(Json(ProfileMatchStatsResponse {
+        success: true,
+        stats: Some(ProfileMatchStats {
+            games_played: player_stats.played,
+            wins: player_stats.won,
+            losses: player_stats.lost,
+            win_rate: player_stats.win_rate(),
+            current_streak: player_stats.current_streak,
+            best_win_streak: player_stats.best_win_streak,
+            favorite_cards: player_stats.favorite_cards(FAVORITE_CARDS_LIMIT),
+            card_plays: player_stats.favorite_cards(usize::MAX),
+            average_turn_ms: player_stats.average_turn_ms(),
+            survival_rate: player_stats.survival_rate(),
+        }),
+        error: None,
+    }))
+}
+
+/// 获取指定用户的评分历史
+#[utoipa::path(
+    get,
+    path = "/v1/profiles/{profile_id}/rating-history",
+    tag = "profile",
+    params(("profile_id" = String, Path, description = "Profile对象ID")),
+    responses((status = 200, description = "指定用户的评分历史", body = RatingHistoryResponse))
+)]
+#[debug_handler]
+pub async fn get_profile_rating_history(
+    State(app_state): State<Arc<AppState>>,
+    Path(profile_id): Path<String>,
+) -> Result<Json<RatingHistoryResponse>, InternalError> {
+    info!("收到获取用户评分历史请求: {}", profile_id);
+    app_state.metrics.observe_request("get_profile_rating_history");
+    let profile_id = account_link::resolve_user_id(&profile_id);
+    
+    match stats::global_stats_service() {
+        Some(service) => Ok(Json(RatingHistoryResponse {
+            success: true,
+            history: Some(service.get_rating_history(&profile_id)),
+            error: None,
+        })),
+        None => Ok(Json(RatingHistoryResponse {
+            success: false,
+            history: None,
+            error: Some("统计服务未初始化".to_string()),
+        })),
+    }
+}
+
 /// 注册Profile路由
 pub fn register_profile_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
     router
@@ -213,4 +472,7 @@ pub fn register_profile_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppS
         .route("/profile/me/stats", get(get_my_stats))
         .route("/profile/:profile_id", get(get_user_profile))
         .route("/profile/:profile_id/stats", get(get_user_stats))
+        .route("/v1/profiles/batch", post(get_profiles_batch))
+        .route("/v1/profiles/:profile_id/stats", get(get_profile_match_stats))
+        .route("/v1/profiles/:profile_id/rating-history", get(get_profile_rating_history))
 } 
\ No newline at end of file
@@ -0,0 +1,508 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Profile改名与重新生成头像
+//!
+//! # 概述
+//! Profile创建后名称和头像原先无法修改。本模块提供两个接口，都只能修改当前登录用户自己的Profile：
+//! - 改名：名称按 `name_policy` 校验，与当前名称相同时拒绝，两次改名之间有冷却时间。
+//!   名称通过合约的 `rename_profile_for_passport` 写入链上Profile的动态字段
+//! - 重新生成头像：使用请求中的种子或随机种子生成头像，通过合约的 `modify_profile_for_passport` 写入链上Profile，
+//!   同时更新 `GameManager` 的Profile缓存。每次修改都需要服务端钱包支付gas，同样有冷却时间
+//!
+//! 链上交易成功后，新的名称和头像同步到使用 `UserInfo` 的各处：
+//! - 护照模块的用户信息缓存和用户搜索索引
+//! - 活跃对局中的玩家和观众、匹配队列中的玩家
+//! - WebSocket消息中的用户信息，聊天消息和组队使用的名称来自这里
+//! - 当前会话中的Profile
+//!
+//! 名称和修改时间保存在游戏缓存中，不过期。创建Profile时提交的名称也记录在这里，但不触发冷却。
+//!
+//! # 接口
+//! - `POST /profile/me/name`：修改名称
+//! - `POST /profile/me/avatar`：重新生成头像
+//!
+//! # 配置
+//! - `PROFILE_RENAME_COOLDOWN_SECS`：两次改名的最小间隔（秒），默认7天
+//! - `PROFILE_AVATAR_COOLDOWN_SECS`：两次修改头像的最小间隔（秒），默认60
+
+use std::sync::Arc;
+
+use axum::{extract::State, routing::post, Extension, Json, Router};
+use fastcrypto::encoding::{Base64, Encoding};
+use once_cell::sync::OnceCell;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sui_sdk::types::base_types::ObjectID;
+use tower_sessions::Session;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::avatars::make_avatar;
+use crate::errors::{ErrorResponse, InternalError};
+use crate::externals::current_epoch_time;
+use crate::game::{GameCachePrefix, GameService};
+use crate::name_policy::{name_policy, NameViolation};
+use crate::sdk::executor;
+use crate::session_login::{SessionUser, SESSION_USER_KEY};
+use crate::AppState;
+
+/// 默认两次改名的最小间隔（秒）
+pub const DEFAULT_RENAME_COOLDOWN_SECS: u64 = 7 * 24 * 3600;
+/// 默认两次修改头像的最小间隔（秒）
+pub const DEFAULT_AVATAR_COOLDOWN_SECS: u64 = 60;
+/// 头像种子的最大长度
+pub const MAX_AVATAR_SEED_LEN: usize = 64;
+
+/// Profile修改配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileEditConfig {
+    /// 两次改名的最小间隔（毫秒）
+    pub rename_cooldown_ms: u64,
+    /// 两次修改头像的最小间隔（毫秒）
+    pub avatar_cooldown_ms: u64,
+}
+
+impl Default for ProfileEditConfig {
+    fn default() -> Self {
+        Self {
+            rename_cooldown_ms: DEFAULT_RENAME_COOLDOWN_SECS * 1000,
+            avatar_cooldown_ms: DEFAULT_AVATAR_COOLDOWN_SECS * 1000,
+        }
+    }
+}
+
+impl ProfileEditConfig {
+    /// 从环境变量读取配置，未设置的项使用默认值
+    pub fn from_env() -> Self {
+        let parse = |key: &str| std::env::var(key).ok().and_then(|v| v.trim().parse::<u64>().ok());
+        let defaults = Self::default();
+        Self {
+            rename_cooldown_ms: parse("PROFILE_RENAME_COOLDOWN_SECS")
+                .map(|v| v * 1000)
+                .unwrap_or(defaults.rename_cooldown_ms),
+            avatar_cooldown_ms: parse("PROFILE_AVATAR_COOLDOWN_SECS")
+                .map(|v| v * 1000)
+                .unwrap_or(defaults.avatar_cooldown_ms),
+        }
+    }
+}
+
+/// Profile的名称和修改记录
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileRecord {
+    /// 当前名称
+    #[serde(default)]
+    pub name: Option<String>,
+    /// 上次改名时间（毫秒）
+    #[serde(default)]
+    pub renamed_at: Option<u64>,
+    /// 当前头像的种子
+    #[serde(default)]
+    pub avatar_seed: Option<String>,
+    /// 上次修改头像时间（毫秒）
+    #[serde(default)]
+    pub avatar_changed_at: Option<u64>,
+}
+
+/// 改名被拒绝的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameError {
+    /// 名称违反名称策略
+    Invalid(NameViolation),
+    /// 与当前名称相同
+    Unchanged,
+    /// 冷却中，可以再次改名的时间（毫秒）
+    Cooldown(u64),
+}
+
+impl std::fmt::Display for RenameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid(violation) => write!(f, "{}", violation.message),
+            Self::Unchanged => write!(f, "Name is unchanged"),
+            Self::Cooldown(_) => write!(f, "Name was changed recently, please try again later"),
+        }
+    }
+}
+
+impl std::error::Error for RenameError {}
+
+/// Profile修改服务
+pub struct ProfileEditService {
+    game_service: Arc<GameService>,
+    config: ProfileEditConfig,
+}
+
+impl ProfileEditService {
+    /// 创建Profile修改服务
+    pub fn new(game_service: Arc<GameService>, config: ProfileEditConfig) -> Self {
+        Self { game_service, config }
+    }
+
+    /// 用户的名称和修改记录
+    pub fn record(&self, user_id: &str) -> ProfileRecord {
+        self.game_service
+            .get(GameCachePrefix::PROFILE, user_id)
+            .unwrap_or_default()
+    }
+
+    /// 用户当前的名称
+    pub fn display_name(&self, user_id: &str) -> Option<String> {
+        self.record(user_id).name
+    }
+
+    fn save(&self, user_id: &str, record: &ProfileRecord) {
+        if !self.game_service.set(GameCachePrefix::PROFILE, user_id, record) {
+            warn!("保存Profile {} 的修改记录失败", user_id);
+        }
+    }
+
+    /**
+     * 检查能否改名
+     *
+     * 参数:
+     * @param user_id - 用户ID
+     * @param raw - 用户提交的名称
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 可以改名时返回规范化的名称，否则返回拒绝原因
+     */
+    pub fn check_rename(&self, user_id: &str, raw: &str, now: u64) -> Result<String, RenameError> {
+        let name = name_policy().check(raw).map_err(RenameError::Invalid)?;
+        let record = self.record(user_id);
+        if record.name.as_deref() == Some(name.as_str()) {
+            return Err(RenameError::Unchanged);
+        }
+        if let Some(renamed_at) = record.renamed_at {
+            let available_at = renamed_at + self.config.rename_cooldown_ms;
+            if now < available_at {
+                return Err(RenameError::Cooldown(available_at));
+            }
+        }
+        Ok(name)
+    }
+
+    /**
+     * 记录名称
+     *
+     * 参数:
+     * @param user_id - 用户ID
+     * @param name - 已校验的名称
+     * @param renamed_at - 改名时间（毫秒），创建Profile时为None，不触发冷却
+     */
+    pub fn record_name(&self, user_id: &str, name: &str, renamed_at: Option<u64>) {
+        let mut record = self.record(user_id);
+        record.name = Some(name.to_string());
+        if renamed_at.is_some() {
+            record.renamed_at = renamed_at;
+        }
+        self.save(user_id, &record);
+    }
+
+    /// 检查能否修改头像，冷却中时返回可以再次修改的时间（毫秒）
+    pub fn check_avatar(&self, user_id: &str, now: u64) -> Result<(), u64> {
+        match self.record(user_id).avatar_changed_at {
+            Some(changed_at) if now < changed_at + self.config.avatar_cooldown_ms => {
+                Err(changed_at + self.config.avatar_cooldown_ms)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 记录新的头像种子
+    pub fn record_avatar(&self, user_id: &str, seed: &str, now: u64) {
+        let mut record = self.record(user_id);
+        record.avatar_seed = Some(seed.to_string());
+        record.avatar_changed_at = Some(now);
+        self.save(user_id, &record);
+    }
+}
+
+// 用于存储全局ProfileEditService实例的静态变量
+static GLOBAL_PROFILE_EDIT_SERVICE: OnceCell<Arc<ProfileEditService>> = OnceCell::new();
+
+/// 初始化Profile修改服务并设置为全局实例
+pub fn init_profile_edit_service(game_service: Arc<GameService>) -> Arc<ProfileEditService> {
+    let service = Arc::new(ProfileEditService::new(game_service, ProfileEditConfig::from_env()));
+    let _ = GLOBAL_PROFILE_EDIT_SERVICE.set(service.clone());
+    service
+}
+
+/// 获取全局Profile修改服务
+pub fn global_profile_edit_service() -> Option<Arc<ProfileEditService>> {
+    GLOBAL_PROFILE_EDIT_SERVICE.get().cloned()
+}
+
+/// 用户的名称，服务未初始化或未设置名称时返回None
+pub fn display_name(user_id: &str) -> Option<String> {
+    global_profile_edit_service().and_then(|service| service.display_name(user_id))
+}
+
+/// 由种子生成头像数据
+pub fn avatar_data(seed: &str) -> String {
+    format!("data:image/svg+xml;base64,{}", Base64::encode(make_avatar(seed).as_bytes()))
+}
+
+/**
+ * 把新的名称和头像同步到护照缓存、活跃对局和匹配队列
+ *
+ * 参数:
+ * @param user_id - 用户ID
+ * @param name - 新名称，None表示不变
+ * @param avatar_url - 新头像，None表示不变
+ */
+pub async fn propagate(user_id: &str, name: Option<&str>, avatar_url: Option<&str>) {
+    if let Some(passport) = crate::ws::global_passport_state() {
+        if let Err(e) = passport.update_profile_info(user_id, name, avatar_url).await {
+            warn!("更新用户 {} 的护照信息失败: {}", user_id, e);
+        }
+    }
+    if let Some(match_service) = crate::gaming::global_match_service() {
+        let updated = match_service.refresh_user_info(user_id, name, avatar_url).await;
+        if updated > 0 {
+            info!("已更新用户 {} 所在的 {} 个对局", user_id, updated);
+        }
+    }
+}
+
+/// 修改名称请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RenameProfileRequest {
+    /// 新名称
+    pub name: String,
+}
+
+/// 修改名称响应
+#[derive(Debug, Default, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameProfileResponse {
+    pub success: bool,
+    /// 规范化后的名称
+    pub name: Option<String>,
+    /// 交易摘要
+    pub digest: Option<String>,
+    /// 冷却中时可以再次改名的时间（毫秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_at: Option<u64>,
+    /// 名称违反的规则
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub violation: Option<NameViolation>,
+    pub error: Option<String>,
+}
+
+/// 重新生成头像请求
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct RerollAvatarRequest {
+    /// 头像种子，为空时随机生成
+    #[serde(default)]
+    pub seed: Option<String>,
+}
+
+/// 重新生成头像响应
+#[derive(Debug, Default, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RerollAvatarResponse {
+    pub success: bool,
+    /// 使用的种子
+    pub seed: Option<String>,
+    /// 新的头像数据
+    pub avatar: Option<String>,
+    /// 交易摘要
+    pub digest: Option<String>,
+    /// 冷却中时可以再次修改的时间（毫秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_at: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// 从会话中获取当前用户和Profile ID
+async fn session_user(session: &Session) -> Result<(SessionUser, ObjectID), InternalError> {
+    let user = session
+        .get::<SessionUser>(SESSION_USER_KEY)
+        .await?
+        .ok_or(InternalError::Unauthorized)?;
+    let profile_id = user.profile.as_ref().ok_or(InternalError::Unauthorized)?.id;
+    Ok((user, profile_id))
+}
+
+/// Profile所属的护照ID，缓存中没有时刷新一次Profile映射
+async fn passport_of(app_state: &AppState, profile_id: &ObjectID) -> Option<ObjectID> {
+    if let Some(passport_id) = app_state.game_manager.profile_passport_map().await.get(profile_id) {
+        return Some(*passport_id);
+    }
+    if let Err(e) = app_state.game_manager.update_all_profiles().await {
+        warn!("刷新Profile映射失败: {}", e);
+    }
+    app_state.game_manager.profile_passport_map().await.get(profile_id).copied()
+}
+
+/// 修改当前用户的名称
+#[utoipa::path(
+    post,
+    path = "/profile/me/name",
+    tag = "profile",
+    request_body = RenameProfileRequest,
+    responses(
+        (status = 200, description = "改名结果，被拒绝时说明违反的规则或冷却结束时间", body = RenameProfileResponse),
+        (status = 403, description = "未登录或没有档案", body = ErrorResponse),
+    )
+)]
+pub async fn handle_rename_profile(
+    State(app_state): State<Arc<AppState>>,
+    Extension(session): Extension<Session>,
+    Json(request): Json<RenameProfileRequest>,
+) -> Result<Json<RenameProfileResponse>, InternalError> {
+    app_state.metrics.observe_request("rename_profile");
+    let (_, profile_id) = session_user(&session).await?;
+    let user_id = profile_id.to_string();
+    let service = global_profile_edit_service().ok_or(InternalError::Failure)?;
+
+    let now = current_epoch_time();
+    let name = match service.check_rename(&user_id, &request.name, now) {
+        Ok(name) => name,
+        Err(e) => {
+            let mut response = RenameProfileResponse { error: Some(e.to_string()), ..Default::default() };
+            match e {
+                RenameError::Invalid(violation) => response.violation = Some(violation),
+                RenameError::Cooldown(retry_at) => response.retry_at = Some(retry_at),
+                RenameError::Unchanged => {}
+            }
+            return Ok(Json(response));
+        }
+    };
+    let passport_id = passport_of(&app_state, &profile_id).await.ok_or(InternalError::Failure)?;
+
+    match executor::rename_profile_for_passport(&app_state, &passport_id, &profile_id, &name).await {
+        Ok(response) => {
+            service.record_name(&user_id, &name, Some(now));
+            propagate(&user_id, Some(&name), None).await;
+            info!("档案 {} 改名为 {}", user_id, name);
+            Ok(Json(RenameProfileResponse {
+                success: true,
+                name: Some(name),
+                digest: Some(response.digest.to_string()),
+                ..Default::default()
+            }))
+        }
+        Err(e) => {
+            warn!("档案 {} 改名失败: {:?}", user_id, e);
+            Ok(Json(RenameProfileResponse { error: Some(e.to_string()), ..Default::default() }))
+        }
+    }
+}
+
+/// 重新生成当前用户的头像
+#[utoipa::path(
+    post,
+    path = "/profile/me/avatar",
+    tag = "profile",
+    request_body = RerollAvatarRequest,
+    responses(
+        (status = 200, description = "新的头像，冷却中时返回冷却结束时间", body = RerollAvatarResponse),
+        (status = 403, description = "未登录、没有档案或种子无效", body = ErrorResponse),
+    )
+)]
+pub async fn handle_reroll_avatar(
+    State(app_state): State<Arc<AppState>>,
+    Extension(session): Extension<Session>,
+    Json(request): Json<RerollAvatarRequest>,
+) -> Result<Json<RerollAvatarResponse>, InternalError> {
+    app_state.metrics.observe_request("reroll_avatar");
+    let (mut user, profile_id) = session_user(&session).await?;
+    let user_id = profile_id.to_string();
+    let service = global_profile_edit_service().ok_or(InternalError::Failure)?;
+
+    let seed = match request.seed.as_deref().map(str::trim) {
+        Some(seed) if seed.is_empty() || seed.len() > MAX_AVATAR_SEED_LEN => return Err(InternalError::InvalidInput),
+        Some(seed) => seed.to_string(),
+        None => format!("{:016x}", rand::thread_rng().gen::<u64>()),
+    };
+
+    let now = current_epoch_time();
+    if let Err(retry_at) = service.check_avatar(&user_id, now) {
+        return Ok(Json(RerollAvatarResponse {
+            retry_at: Some(retry_at),
+            error: Some("Avatar was changed recently, please try again later".to_string()),
+            ..Default::default()
+        }));
+    }
+    let passport_id = passport_of(&app_state, &profile_id).await.ok_or(InternalError::Failure)?;
+
+    let avatar = avatar_data(&seed);
+    match executor::modify_profile_for_passport(&app_state, &passport_id, &profile_id, &avatar).await {
+        Ok(response) => {
+            service.record_avatar(&user_id, &seed, now);
+            propagate(&user_id, None, Some(&avatar)).await;
+            if let Some(profile) = user.profile.as_mut() {
+                profile.avatar = avatar.clone();
+            }
+            session.insert(SESSION_USER_KEY, user).await?;
+            info!("档案 {} 重新生成头像，种子 {}", user_id, seed);
+            Ok(Json(RerollAvatarResponse {
+                success: true,
+                seed: Some(seed),
+                avatar: Some(avatar),
+                digest: Some(response.digest.to_string()),
+                ..Default::default()
+            }))
+        }
+        Err(e) => {
+            warn!("档案 {} 修改头像失败: {:?}", user_id, e);
+            Ok(Json(RerollAvatarResponse { error: Some(e.to_string()), ..Default::default() }))
+        }
+    }
+}
+
+/// 注册Profile修改路由
+pub fn register_profile_edit_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router
+        .route("/profile/me/name", post(handle_rename_profile))
+        .route("/profile/me/avatar", post(handle_reroll_avatar))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::name_policy::NameRule;
+
+    const USER: &str = "0x00000000000000000000000000000000000000000000000000000000000000aa";
+
+    fn service() -> ProfileEditService {
+        ProfileEditService::new(
+            Arc::new(GameService::new()),
+            ProfileEditConfig { rename_cooldown_ms: 1000, avatar_cooldown_ms: 100 },
+        )
+    }
+
+    #[test]
+    fn test_rename_cooldown() {
+        let service = service();
+
+        // 创建时的名称不触发冷却
+        service.record_name(USER, "Cat Lover", None);
+        assert_eq!(service.check_rename(USER, " Cat  Lover", 0), Err(RenameError::Unchanged));
+        let name = service.check_rename(USER, "Dog Lover", 0).unwrap();
+        service.record_name(USER, &name, Some(10));
+        assert_eq!(service.display_name(USER).as_deref(), Some("Dog Lover"));
+
+        assert_eq!(service.check_rename(USER, "Bird Lover", 500), Err(RenameError::Cooldown(1010)));
+        assert!(service.check_rename(USER, "Bird Lover", 1010).is_ok());
+        match service.check_rename(USER, "admin", 1010) {
+            Err(RenameError::Invalid(violation)) => assert_eq!(violation.rule, NameRule::Reserved),
+            other => panic!("应违反名称策略: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_avatar_cooldown() {
+        let service = service();
+        assert_eq!(service.check_avatar(USER, 0), Ok(()));
+        service.record_avatar(USER, "seed", 50);
+        assert_eq!(service.check_avatar(USER, 100), Err(150));
+        assert_eq!(service.check_avatar(USER, 150), Ok(()));
+        assert_eq!(service.record(USER).avatar_seed.as_deref(), Some("seed"));
+    }
+}
@@ -0,0 +1,409 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Profile创建的限流与防女巫保护
+//!
+//! # 概述
+//! 创建Profile的接口由服务端钱包代付gas，可以被反复调用批量铸造Profile。本模块在交易提交之前依次检查：
+//! - 按客户端IP和护照地址的限流，超出限制返回429
+//! - 可选的工作量证明：`Blake2b256(passport_id || pow_nonce)` 的前导零比特数不少于配置的难度
+//! - 可选的验证码：将 `captcha_token` 提交到验证码服务的校验地址（兼容hCaptcha、Turnstile、reCAPTCHA的siteverify接口）
+//! - 重复检测：每个护照只能有一个Profile，已有Profile或正在创建中的护照返回409
+//!
+//! 创建游客（见 `guest` 模块）不写链上数据，但同样会占用缓存，按客户端IP单独限流并校验验证码。
+//!
+//! # 配置
+//! - `PROFILE_CREATE_IP_LIMIT`：每个IP每小时允许的创建次数，默认10，0表示不限流
+//! - `PROFILE_CREATE_ADDRESS_LIMIT`：每个护照地址每小时允许的创建次数，默认3，0表示不限流
+//! - `GUEST_CREATE_IP_LIMIT`：每个IP每小时允许创建的游客数，默认5，0表示不限流
+//! - `PROFILE_POW_DIFFICULTY`：工作量证明的前导零比特数，默认0（关闭）
+//! - `PROFILE_CAPTCHA_VERIFY_URL` / `PROFILE_CAPTCHA_SECRET`：验证码校验地址和密钥，都配置时启用验证码
+//! - `TRUST_PROXY_HEADERS`：是否从 `X-Forwarded-For` / `X-Real-IP` 请求头读取客户端IP，默认否，
+//!   只应在服务部署于可信反向代理之后时开启
+
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use axum::http::{HeaderMap, StatusCode};
+use dashmap::DashSet;
+use fastcrypto::hash::{Blake2b256, HashFunction};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use sui_types::base_types::ObjectID;
+use tracing::{info, warn};
+
+use crate::externals::current_epoch_time;
+use crate::key_audit::RateLimiter;
+use crate::sdk::manager::GameManager;
+
+/// 限流的时间窗口
+const RATE_WINDOW: Duration = Duration::from_secs(3600);
+/// 默认每个IP每小时的创建次数
+const DEFAULT_IP_LIMIT: u32 = 10;
+/// 默认每个护照地址每小时的创建次数
+const DEFAULT_ADDRESS_LIMIT: u32 = 3;
+/// 默认每个IP每小时创建的游客数
+const DEFAULT_GUEST_IP_LIMIT: u32 = 5;
+/// 验证码校验请求的超时时间
+const CAPTCHA_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 防女巫保护配置
+#[derive(Debug, Clone, Default)]
+pub struct ProfileGuardConfig {
+    pub ip_limit: u32,
+    pub address_limit: u32,
+    pub guest_ip_limit: u32,
+    /// 工作量证明的前导零比特数，0表示关闭
+    pub pow_difficulty: u32,
+    pub captcha_verify_url: Option<String>,
+    pub captcha_secret: Option<String>,
+    pub trust_proxy_headers: bool,
+}
+
+impl ProfileGuardConfig {
+    /// 从环境变量读取配置
+    pub fn from_env() -> Self {
+        let number = |key: &str, default: u32| {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(default)
+        };
+        let text = |key: &str| std::env::var(key).ok().filter(|v| !v.trim().is_empty());
+        Self {
+            ip_limit: number("PROFILE_CREATE_IP_LIMIT", DEFAULT_IP_LIMIT),
+            address_limit: number("PROFILE_CREATE_ADDRESS_LIMIT", DEFAULT_ADDRESS_LIMIT),
+            guest_ip_limit: number("GUEST_CREATE_IP_LIMIT", DEFAULT_GUEST_IP_LIMIT),
+            pow_difficulty: number("PROFILE_POW_DIFFICULTY", 0),
+            captcha_verify_url: text("PROFILE_CAPTCHA_VERIFY_URL"),
+            captcha_secret: text("PROFILE_CAPTCHA_SECRET"),
+            trust_proxy_headers: std::env::var("TRUST_PROXY_HEADERS")
+                .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// 创建Profile被拒绝的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileGuardError {
+    /// 护照ID格式无效
+    InvalidPassport,
+    /// 超出限流
+    RateLimited,
+    /// 缺少或无效的工作量证明
+    InvalidProofOfWork,
+    /// 缺少或无效的验证码
+    CaptchaFailed,
+    /// 护照已有Profile或正在创建中
+    DuplicateProfile,
+}
+
+impl ProfileGuardError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::InvalidPassport => StatusCode::BAD_REQUEST,
+            Self::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Self::InvalidProofOfWork | Self::CaptchaFailed => StatusCode::FORBIDDEN,
+            Self::DuplicateProfile => StatusCode::CONFLICT,
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::InvalidPassport => "Invalid passport id",
+            Self::RateLimited => "Too many profile creation requests, please try again later",
+            Self::InvalidProofOfWork => "Missing or invalid proof of work",
+            Self::CaptchaFailed => "Captcha verification failed",
+            Self::DuplicateProfile => "Passport already has a profile",
+        }
+    }
+}
+
+/// 验证码服务的响应
+#[derive(Debug, Deserialize)]
+struct CaptchaResponse {
+    success: bool,
+}
+
+/// Profile创建保护
+pub struct ProfileGuard {
+    config: ProfileGuardConfig,
+    ip_limiter: RateLimiter<IpAddr>,
+    address_limiter: RateLimiter<ObjectID>,
+    guest_limiter: RateLimiter<IpAddr>,
+    /// 正在创建Profile的护照
+    pending: DashSet<ObjectID>,
+    http: reqwest::Client,
+}
+
+/// 正在创建中的护照，释放时移除标记
+pub struct PendingProfile<'a> {
+    guard: &'a ProfileGuard,
+    passport_id: ObjectID,
+}
+
+impl PendingProfile<'_> {
+    pub fn passport_id(&self) -> ObjectID {
+        self.passport_id
+    }
+}
+
+impl Drop for PendingProfile<'_> {
+    fn drop(&mut self) {
+        self.guard.pending.remove(&self.passport_id);
+    }
+}
+
+static GLOBAL_PROFILE_GUARD: Lazy<ProfileGuard> = Lazy::new(|| {
+    let guard = ProfileGuard::new(ProfileGuardConfig::from_env());
+    info!(
+        "Profile创建保护: IP限制 {}/小时，地址限制 {}/小时，工作量证明难度 {}，验证码 {}",
+        guard.config.ip_limit,
+        guard.config.address_limit,
+        guard.config.pow_difficulty,
+        guard.captcha_enabled()
+    );
+    guard
+});
+
+/// 获取全局Profile创建保护
+pub fn profile_guard() -> &'static ProfileGuard {
+    &GLOBAL_PROFILE_GUARD
+}
+
+/// 启动定期清理空闲限流桶的后台任务
+pub fn spawn_pruner() {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(RATE_WINDOW);
+        loop {
+            ticker.tick().await;
+            let now = current_epoch_time();
+            profile_guard().ip_limiter.prune(now);
+            profile_guard().address_limiter.prune(now);
+            profile_guard().guest_limiter.prune(now);
+        }
+    });
+}
+
+/**
+ * 检查工作量证明
+ *
+ * 参数:
+ * @param passport_id - 护照ID
+ * @param nonce - 客户端找到的随机数
+ * @param difficulty - 要求的前导零比特数
+ *
+ * 返回:
+ * `Blake2b256(passport_id || nonce)` 的前导零比特数是否不少于难度
+ */
+pub fn verify_proof_of_work(passport_id: &ObjectID, nonce: &str, difficulty: u32) -> bool {
+    let mut hasher = Blake2b256::default();
+    hasher.update(passport_id.as_ref());
+    hasher.update(nonce.as_bytes());
+    let digest = hasher.finalize().digest;
+    leading_zero_bits(&digest) >= difficulty
+}
+
+fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in bytes {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+impl ProfileGuard {
+    pub fn new(config: ProfileGuardConfig) -> Self {
+        Self {
+            ip_limiter: RateLimiter::per_window(config.ip_limit, RATE_WINDOW),
+            address_limiter: RateLimiter::per_window(config.address_limit, RATE_WINDOW),
+            guest_limiter: RateLimiter::per_window(config.guest_ip_limit, RATE_WINDOW),
+            pending: DashSet::new(),
+            http: reqwest::Client::builder()
+                .timeout(CAPTCHA_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            config,
+        }
+    }
+
+    fn captcha_enabled(&self) -> bool {
+        self.config.captcha_verify_url.is_some() && self.config.captcha_secret.is_some()
+    }
+
+    /**
+     * 确定客户端IP
+     *
+     * 参数:
+     * @param headers - 请求头
+     * @param peer - TCP连接的对端地址
+     *
+     * 返回:
+     * 信任代理请求头时优先使用请求头中的地址，否则使用对端地址
+     */
+    pub fn client_ip(&self, headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<IpAddr> {
+        if self.config.trust_proxy_headers {
+            let forwarded = headers
+                .get("x-forwarded-for")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next())
+                .or_else(|| headers.get("x-real-ip").and_then(|v| v.to_str().ok()))
+                .and_then(|v| v.trim().parse().ok());
+            if forwarded.is_some() {
+                return forwarded;
+            }
+        }
+        peer.map(|addr| addr.ip())
+    }
+
+    /**
+     * 在提交创建Profile的交易之前执行所有检查
+     *
+     * 参数:
+     * @param game_manager - 用于查询护照是否已有Profile
+     * @param passport_id - 护照ID
+     * @param client_ip - 客户端IP
+     * @param pow_nonce - 工作量证明随机数
+     * @param captcha_token - 验证码令牌
+     *
+     * 返回:
+     * 检查通过时返回创建中标记，交易完成前同一护照的其他请求会被拒绝
+     */
+    pub async fn check(
+        &self,
+        game_manager: &GameManager,
+        passport_id: &str,
+        client_ip: Option<IpAddr>,
+        pow_nonce: Option<&str>,
+        captcha_token: Option<&str>,
+    ) -> Result<PendingProfile<'_>, ProfileGuardError> {
+        let passport_id = ObjectID::from_hex_literal(passport_id).map_err(|_| ProfileGuardError::InvalidPassport)?;
+
+        // 工作量证明和验证码放在限流之前，无效请求不消耗限额
+        if self.config.pow_difficulty > 0
+            && !pow_nonce.is_some_and(|nonce| verify_proof_of_work(&passport_id, nonce, self.config.pow_difficulty))
+        {
+            return Err(ProfileGuardError::InvalidProofOfWork);
+        }
+        if self.captcha_enabled() {
+            let token = captcha_token.ok_or(ProfileGuardError::CaptchaFailed)?;
+            self.verify_captcha(token, client_ip).await?;
+        }
+
+        let now = current_epoch_time();
+        if let Some(ip) = client_ip {
+            if !self.ip_limiter.try_acquire(&ip, now) {
+                warn!("IP {} 创建Profile过于频繁", ip);
+                return Err(ProfileGuardError::RateLimited);
+            }
+        }
+        if !self.address_limiter.try_acquire(&passport_id, now) {
+            warn!("护照 {} 创建Profile过于频繁", passport_id);
+            return Err(ProfileGuardError::RateLimited);
+        }
+
+        if !self.pending.insert(passport_id) {
+            return Err(ProfileGuardError::DuplicateProfile);
+        }
+        let pending = PendingProfile { guard: self, passport_id };
+        if game_manager.get_profile_id_by_passport(&passport_id).await.is_ok() {
+            return Err(ProfileGuardError::DuplicateProfile);
+        }
+        Ok(pending)
+    }
+
+    /**
+     * 在创建游客之前检查验证码和IP限流
+     *
+     * 参数:
+     * @param client_ip - 客户端IP
+     * @param captcha_token - 验证码令牌
+     */
+    pub async fn check_guest(&self, client_ip: Option<IpAddr>, captcha_token: Option<&str>) -> Result<(), ProfileGuardError> {
+        if self.captcha_enabled() {
+            let token = captcha_token.ok_or(ProfileGuardError::CaptchaFailed)?;
+            self.verify_captcha(token, client_ip).await?;
+        }
+        if let Some(ip) = client_ip {
+            if !self.guest_limiter.try_acquire(&ip, current_epoch_time()) {
+                warn!("IP {} 创建游客过于频繁", ip);
+                return Err(ProfileGuardError::RateLimited);
+            }
+        }
+        Ok(())
+    }
+
+    async fn verify_captcha(&self, token: &str, client_ip: Option<IpAddr>) -> Result<(), ProfileGuardError> {
+        let (Some(url), Some(secret)) = (&self.config.captcha_verify_url, &self.config.captcha_secret) else {
+            return Ok(());
+        };
+        let mut form = vec![("secret", secret.clone()), ("response", token.to_string())];
+        if let Some(ip) = client_ip {
+            form.push(("remoteip", ip.to_string()));
+        }
+        let response = self
+            .http
+            .post(url)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| {
+                warn!("验证码校验请求失败: {}", e);
+                ProfileGuardError::CaptchaFailed
+            })?
+            .json::<CaptchaResponse>()
+            .await
+            .map_err(|e| {
+                warn!("验证码校验响应解析失败: {}", e);
+                ProfileGuardError::CaptchaFailed
+            })?;
+        if response.success {
+            Ok(())
+        } else {
+            Err(ProfileGuardError::CaptchaFailed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proof_of_work() {
+        assert_eq!(leading_zero_bits(&[0, 0, 0x10, 0xff]), 19);
+        assert_eq!(leading_zero_bits(&[0xff]), 0);
+
+        let passport_id = ObjectID::random();
+        assert!(verify_proof_of_work(&passport_id, "anything", 0));
+        let nonce = (0u64..)
+            .map(|n| n.to_string())
+            .find(|nonce| verify_proof_of_work(&passport_id, nonce, 8))
+            .unwrap();
+        let mut hasher = Blake2b256::default();
+        hasher.update(passport_id.as_ref());
+        hasher.update(nonce.as_bytes());
+        assert_eq!(hasher.finalize().digest[0], 0);
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.7, 10.0.0.1".parse().unwrap());
+        let peer: SocketAddr = "10.0.0.1:4000".parse().unwrap();
+
+        let guard = ProfileGuard::new(ProfileGuardConfig::default());
+        assert_eq!(guard.client_ip(&headers, Some(peer)), Some(peer.ip()));
+
+        let guard = ProfileGuard::new(ProfileGuardConfig {
+            trust_proxy_headers: true,
+            ..Default::default()
+        });
+        assert_eq!(guard.client_ip(&headers, Some(peer)), Some("203.0.113.7".parse().unwrap()));
+        assert_eq!(guard.client_ip(&HeaderMap::new(), None), None);
+    }
+}
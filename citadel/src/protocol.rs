@@ -0,0 +1,351 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! WebSocket协议版本与能力协商模块
+//!
+//! # 概述
+//! 客户端连接后发送 `hello` 消息声明自己的协议版本和支持的功能，
+//! 服务器校验版本兼容性并返回双方共同支持的能力列表。
+//! 新增的服务端事件需要挂在某个能力下，只有声明了该能力的客户端才会收到，
+//! 从而在不破坏旧客户端的前提下演进事件格式。
+//!
+//! 未发送 `hello` 的客户端按协议版本1处理，不具备任何可选能力。
+//!
+//! 客户端还可以在 `encodings` 中按优先级列出支持的帧编码（`json`、`msgpack`），
+//! 服务器选择第一个支持的编码。选择 `msgpack` 后，服务器下发的消息使用二进制帧，
+//! 客户端也可以用二进制帧发送MessagePack编码的消息。`hello:ack` 始终以JSON文本帧发送。
+//!
+//! 服务端生成的提示消息按连接的语言渲染。语言取自 `hello` 的 `locale` 字段，
+//! 未提供时沿用连接请求的 `Accept-Language` 头，默认为中文。
+//!
+//! # 消息格式
+//! ```json
+//! // 客户端 -> 服务器
+//! { "event": "hello", "data": { "version": 2, "features": ["achievements"], "encodings": ["msgpack", "json"], "locale": "en" } }
+//! // 服务器 -> 客户端
+//! { "event": "hello:ack", "data": { "ok": true, "payload": { "version": 2, "capabilities": ["achievements"], "encoding": "msgpack", "locale": "en" } } }
+//! // 版本不兼容
+//! { "event": "protocol:error", "data": { "ok": false, "msg": "...", "payload": { "code": "unsupported_version", ... } } }
+//! ```
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use axum::extract::ws::Message;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::i18n::Locale;
+use crate::ws::WsMessage;
+
+/// 当前服务器协议版本
+pub const PROTOCOL_VERSION: u32 = 2;
+/// 服务器仍兼容的最低协议版本
+pub const MIN_SUPPORTED_PROTOCOL_VERSION: u32 = 1;
+/// 未握手客户端的默认协议版本
+pub const LEGACY_PROTOCOL_VERSION: u32 = 1;
+
+/// 协议相关事件
+pub mod events {
+    /// 客户端握手
+    pub const HELLO: &str = "hello";
+    /// 握手成功
+    pub const HELLO_ACK: &str = "hello:ack";
+    /// 协议错误
+    pub const PROTOCOL_ERROR: &str = "protocol:error";
+}
+
+/// 服务器支持的可选能力
+pub mod capabilities {
+    /// 成就解锁通知
+    pub const ACHIEVEMENTS: &str = "achievements";
+    /// 游戏币与押注对局
+    pub const ECONOMY: &str = "economy";
+    /// 对局状态增量同步
+    pub const MATCH_DELTA: &str = "match_delta";
+    /// 关键事件送达确认
+    pub const ACK: &str = "ack";
+    /// 发给单个玩家的手牌等私密消息使用IBE加密
+    pub const SEALED_HANDS: &str = "sealed_hands";
+}
+
+/// 服务器支持的全部能力
+pub const SERVER_CAPABILITIES: &[&str] = &[
+    capabilities::ACHIEVEMENTS,
+    capabilities::ECONOMY,
+    capabilities::MATCH_DELTA,
+    capabilities::ACK,
+    capabilities::SEALED_HANDS,
+];
+
+/// 需要特定能力才会下发的事件前缀
+const GATED_EVENTS: &[(&str, &str)] = &[
+    ("achievement:", capabilities::ACHIEVEMENTS),
+    ("match:create", capabilities::ECONOMY),
+    ("match:state", capabilities::MATCH_DELTA),
+];
+
+/// 获取事件所需的能力，None表示所有客户端都可接收
+pub fn required_capability(event: &str) -> Option<&'static str> {
+    GATED_EVENTS
+        .iter()
+        .find(|(prefix, _)| event.starts_with(prefix))
+        .map(|(_, capability)| *capability)
+}
+
+/// 帧编码格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WireEncoding {
+    /// JSON文本帧
+    #[default]
+    Json,
+    /// MessagePack二进制帧
+    Msgpack,
+}
+
+impl WireEncoding {
+    /// 从字符串解析编码格式
+    pub fn from_str(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "json" => Some(WireEncoding::Json),
+            "msgpack" | "messagepack" => Some(WireEncoding::Msgpack),
+            _ => None,
+        }
+    }
+
+    /// 将消息编码为WebSocket帧
+    pub fn encode(&self, message: &WsMessage) -> Result<Message> {
+        match self {
+            WireEncoding::Json => Ok(Message::Text(serde_json::to_string(message)?)),
+            WireEncoding::Msgpack => Ok(Message::Binary(rmp_serde::to_vec_named(message)?)),
+        }
+    }
+
+    /// 从二进制帧解码消息
+    pub fn decode_binary(&self, data: &[u8]) -> Result<WsMessage> {
+        match self {
+            WireEncoding::Msgpack => Ok(rmp_serde::from_slice(data)?),
+            WireEncoding::Json => Ok(serde_json::from_slice(data)?),
+        }
+    }
+}
+
+/// 客户端握手请求
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HelloRequest {
+    /// 客户端协议版本
+    pub version: u32,
+    /// 客户端支持的功能
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// 客户端支持的帧编码（按优先级排序）
+    #[serde(default)]
+    pub encodings: Vec<String>,
+    /// 客户端语言，如 `en`、`zh-CN`
+    #[serde(default)]
+    pub locale: Option<String>,
+}
+
+/// 协议错误码
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtocolErrorCode {
+    /// 不支持的协议版本
+    UnsupportedVersion,
+    /// 握手消息格式错误
+    InvalidHello,
+}
+
+/// 结构化的协议错误
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolError {
+    pub code: ProtocolErrorCode,
+    pub message: String,
+    pub min_version: u32,
+    pub max_version: u32,
+}
+
+impl ProtocolError {
+    fn new(code: ProtocolErrorCode, message: String) -> Self {
+        Self {
+            code,
+            message,
+            min_version: MIN_SUPPORTED_PROTOCOL_VERSION,
+            max_version: PROTOCOL_VERSION,
+        }
+    }
+}
+
+/// 单个连接协商后的协议信息
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClientProtocol {
+    /// 协商后的协议版本
+    pub version: u32,
+    /// 协商后的能力集合
+    pub capabilities: BTreeSet<String>,
+    /// 协商后的帧编码
+    #[serde(default)]
+    pub encoding: WireEncoding,
+    /// 服务端消息使用的语言
+    #[serde(default)]
+    pub locale: Locale,
+}
+
+impl Default for ClientProtocol {
+    fn default() -> Self {
+        Self {
+            version: LEGACY_PROTOCOL_VERSION,
+            capabilities: BTreeSet::new(),
+            encoding: WireEncoding::Json,
+            locale: Locale::default(),
+        }
+    }
+}
+
+impl ClientProtocol {
+    /// 是否支持指定能力
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.contains(capability)
+    }
+
+    /// 是否可以接收指定事件
+    pub fn accepts_event(&self, event: &str) -> bool {
+        required_capability(event)
+            .map(|capability| self.supports(capability))
+            .unwrap_or(true)
+    }
+}
+
+/// 解析握手消息
+pub fn parse_hello(data: Option<&serde_json::Value>) -> Result<HelloRequest, ProtocolError> {
+    let data = data.ok_or_else(|| {
+        ProtocolError::new(ProtocolErrorCode::InvalidHello, "握手消息缺少数据".to_string())
+    })?;
+    serde_json::from_value(data.clone()).map_err(|e| {
+        ProtocolError::new(ProtocolErrorCode::InvalidHello, format!("握手消息格式错误: {}", e))
+    })
+}
+
+/**
+ * 协商协议版本和能力
+ *
+ * 参数:
+ * @param hello - 客户端握手请求
+ *
+ * 返回:
+ * 成功时返回协商后的协议信息，版本不兼容时返回结构化错误
+ */
+pub fn negotiate(hello: &HelloRequest) -> Result<ClientProtocol, ProtocolError> {
+    if hello.version < MIN_SUPPORTED_PROTOCOL_VERSION || hello.version > PROTOCOL_VERSION {
+        return Err(ProtocolError::new(
+            ProtocolErrorCode::UnsupportedVersion,
+            format!(
+                "不支持的协议版本 {}，服务器支持 {} - {}",
+                hello.version, MIN_SUPPORTED_PROTOCOL_VERSION, PROTOCOL_VERSION
+            ),
+        ));
+    }
+
+    let capabilities = hello
+        .features
+        .iter()
+        .filter(|feature| SERVER_CAPABILITIES.contains(&feature.as_str()))
+        .cloned()
+        .collect();
+
+    // 选择客户端列出的第一个可识别的编码
+    let encoding = hello
+        .encodings
+        .iter()
+        .find_map(|e| WireEncoding::from_str(e))
+        .unwrap_or_default();
+
+    let locale = hello
+        .locale
+        .as_deref()
+        .and_then(Locale::from_tag)
+        .unwrap_or_default();
+
+    Ok(ClientProtocol {
+        version: hello.version,
+        capabilities,
+        encoding,
+        locale,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_capabilities() {
+        let hello = HelloRequest {
+            version: PROTOCOL_VERSION,
+            features: vec!["achievements".to_string(), "unknown".to_string()],
+            encodings: vec![],
+            locale: None,
+        };
+        let protocol = negotiate(&hello).unwrap();
+        assert_eq!(protocol.version, PROTOCOL_VERSION);
+        assert!(protocol.supports(capabilities::ACHIEVEMENTS));
+        assert!(!protocol.supports("unknown"));
+        assert!(protocol.accepts_event("achievement:unlocked"));
+        assert!(!protocol.accepts_event("match:create"));
+        assert!(protocol.accepts_event("match:start"));
+    }
+
+    #[test]
+    fn test_reject_unsupported_version() {
+        let hello = HelloRequest {
+            version: PROTOCOL_VERSION + 1,
+            features: vec![],
+            encodings: vec![],
+            locale: None,
+        };
+        let err = negotiate(&hello).unwrap_err();
+        assert_eq!(err.code, ProtocolErrorCode::UnsupportedVersion);
+        assert_eq!(err.max_version, PROTOCOL_VERSION);
+
+        let err = parse_hello(Some(&serde_json::json!({ "features": [] }))).unwrap_err();
+        assert_eq!(err.code, ProtocolErrorCode::InvalidHello);
+    }
+
+    #[test]
+    fn test_msgpack_roundtrip() {
+        let hello = HelloRequest {
+            version: PROTOCOL_VERSION,
+            features: vec![],
+            encodings: vec!["cbor".to_string(), "msgpack".to_string(), "json".to_string()],
+            locale: Some("en-US".to_string()),
+        };
+        let protocol = negotiate(&hello).unwrap();
+        assert_eq!(protocol.encoding, WireEncoding::Msgpack);
+        assert_eq!(protocol.locale, Locale::En);
+
+        let message = WsMessage {
+            event: "match:start".to_string(),
+            data: Some(serde_json::json!({ "matchId": "m1", "players": [1, 2, 3] })),
+            msg_id: None,
+        };
+        let frame = protocol.encoding.encode(&message).unwrap();
+        let Message::Binary(bytes) = frame else {
+            panic!("msgpack应编码为二进制帧");
+        };
+        let decoded = protocol.encoding.decode_binary(&bytes).unwrap();
+        assert_eq!(decoded.event, message.event);
+        assert_eq!(decoded.data, message.data);
+    }
+
+    #[test]
+    fn test_legacy_client_is_gated() {
+        let protocol = ClientProtocol::default();
+        assert_eq!(protocol.version, LEGACY_PROTOCOL_VERSION);
+        assert_eq!(protocol.encoding, WireEncoding::Json);
+        assert!(!protocol.accepts_event("achievement:unlocked"));
+        assert!(!protocol.accepts_event("match:state"));
+        assert!(protocol.accepts_event("chat:message"));
+    }
+}
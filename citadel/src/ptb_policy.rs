@@ -0,0 +1,463 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! PTB访问策略模块
+//!
+//! # 概述
+//! `ValidPtb` 只检查PTB的基本结构（都是 `seal_approve*` 调用、使用同一个包等），
+//! 本模块在此之上提供可配置的策略，声明允许调用的包、模块、函数以及参数约束。
+//! 策略文件在启动时加载，之后定期检查文件修改时间并自动重新加载，
+//! 运维人员修改策略后无需重新编译或重启服务。
+//!
+//! PTB中的每个MoveCall都必须匹配至少一条规则；策略中没有规则时不做额外限制。
+//! 重新加载失败时保留之前的策略。
+//!
+//! # 配置
+//! - `PTB_POLICY_PATH`：策略文件路径，支持 `.toml` 和 `.json`，为空时不启用策略
+//! - `PTB_POLICY_RELOAD_SECS`：检查策略文件变化的间隔（秒），默认30
+//!
+//! # 策略示例
+//! ```toml
+//! max_commands = 4
+//!
+//! [[rules]]
+//! package = "0x1234..."          # "*" 表示任意包
+//! modules = ["citadel"]          # 为空表示任意模块
+//! functions = ["seal_approve*"]  # 支持结尾的 * 通配
+//!
+//! [[rules.arguments]]
+//! index = 0
+//! kind = "pure"
+//! max_len = 64
+//!
+//! [[rules.arguments]]
+//! index = 1
+//! kind = "shared_object"
+//! mutable = false
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context, Result};
+use fastcrypto::encoding::{Encoding, Hex};
+use once_cell::sync::OnceCell;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use sui_sdk::types::transaction::{Argument, CallArg, Command, ObjectArg, ProgrammableTransaction};
+use sui_types::base_types::ObjectID;
+use sui_types::transaction::ProgrammableMoveCall;
+use tracing::{debug, error, info};
+
+use crate::errors::InternalError;
+
+/// 默认的策略文件检查间隔
+const DEFAULT_RELOAD_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 参数类型约束
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ArgumentKind {
+    /// 任意参数
+    #[default]
+    Any,
+    /// 纯值输入
+    Pure,
+    /// 任意对象输入
+    Object,
+    /// 不可变或自有对象
+    ImmOrOwnedObject,
+    /// 共享对象
+    SharedObject,
+}
+
+/// 单个参数的约束
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ArgumentConstraint {
+    /// 参数在MoveCall中的位置
+    pub index: usize,
+    #[serde(default)]
+    pub kind: ArgumentKind,
+    /// 纯值的最小字节长度
+    #[serde(default)]
+    pub min_len: Option<usize>,
+    /// 纯值的最大字节长度
+    #[serde(default)]
+    pub max_len: Option<usize>,
+    /// 纯值按 `vector<u8>` 解码后必须以此前缀开头（Hex编码）
+    #[serde(default)]
+    pub id_prefix: Option<String>,
+    /// 共享对象是否允许可变引用
+    #[serde(default)]
+    pub mutable: Option<bool>,
+}
+
+/// 一条允许规则
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PolicyRule {
+    /// 包ID，"*" 表示任意包
+    pub package: String,
+    /// 允许的模块，为空表示任意模块
+    #[serde(default)]
+    pub modules: Vec<String>,
+    /// 允许的函数，支持结尾的 * 通配，为空表示任意 `seal_approve*` 函数
+    #[serde(default)]
+    pub functions: Vec<String>,
+    /// 参数约束
+    #[serde(default)]
+    pub arguments: Vec<ArgumentConstraint>,
+}
+
+/// PTB访问策略
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PtbPolicy {
+    /// PTB中最多允许的命令数量
+    #[serde(default)]
+    pub max_commands: Option<usize>,
+    /// 允许规则
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+/// 名称是否匹配模式，模式结尾的 * 匹配任意后缀
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => value.starts_with(prefix),
+        None => pattern == value,
+    }
+}
+
+impl ArgumentConstraint {
+    /// 检查参数是否满足约束
+    fn check(&self, ptb: &ProgrammableTransaction, cmd: &ProgrammableMoveCall) -> Result<(), String> {
+        let Some(argument) = cmd.arguments.get(self.index) else {
+            return Err(format!("缺少第 {} 个参数", self.index));
+        };
+        if self.kind == ArgumentKind::Any {
+            return Ok(());
+        }
+        let Argument::Input(input) = argument else {
+            return Err(format!("第 {} 个参数必须是交易输入", self.index));
+        };
+        let Some(input) = ptb.inputs.get(*input as usize) else {
+            return Err(format!("第 {} 个参数引用了不存在的输入", self.index));
+        };
+
+        match (self.kind, input) {
+            (ArgumentKind::Pure, CallArg::Pure(bytes)) => self.check_pure(bytes),
+            (ArgumentKind::Object, CallArg::Object(_)) => Ok(()),
+            (ArgumentKind::ImmOrOwnedObject, CallArg::Object(ObjectArg::ImmOrOwnedObject(_))) => Ok(()),
+            (ArgumentKind::SharedObject, CallArg::Object(ObjectArg::SharedObject { mutable, .. })) => {
+                match self.mutable {
+                    Some(allowed) if !allowed && *mutable => {
+                        Err(format!("第 {} 个参数不允许可变引用共享对象", self.index))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            _ => Err(format!("第 {} 个参数类型不是 {:?}", self.index, self.kind)),
+        }
+    }
+
+    fn check_pure(&self, bytes: &[u8]) -> Result<(), String> {
+        if self.min_len.map(|min| bytes.len() < min).unwrap_or(false)
+            || self.max_len.map(|max| bytes.len() > max).unwrap_or(false)
+        {
+            return Err(format!("第 {} 个参数长度 {} 超出限制", self.index, bytes.len()));
+        }
+        if let Some(prefix) = &self.id_prefix {
+            let prefix = Hex::decode(prefix).map_err(|e| format!("无效的id_prefix: {}", e))?;
+            let id: Vec<u8> =
+                bcs::from_bytes(bytes).map_err(|_| format!("第 {} 个参数不是vector<u8>", self.index))?;
+            if !id.starts_with(&prefix) {
+                return Err(format!("第 {} 个参数的ID前缀不匹配", self.index));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PolicyRule {
+    /// 检查MoveCall是否匹配此规则
+    fn check(&self, ptb: &ProgrammableTransaction, cmd: &ProgrammableMoveCall) -> Result<(), String> {
+        if self.package != "*" {
+            let package = ObjectID::from_hex_literal(&self.package)
+                .map_err(|e| format!("规则中的包ID无效: {}", e))?;
+            if package != cmd.package {
+                return Err(format!("包 {} 不匹配", cmd.package));
+            }
+        }
+        let module = cmd.module.as_str();
+        if !self.modules.is_empty() && !self.modules.iter().any(|m| matches_pattern(m, module)) {
+            return Err(format!("模块 {} 不在允许列表中", module));
+        }
+        let function = cmd.function.as_str();
+        if !self.functions.is_empty() && !self.functions.iter().any(|f| matches_pattern(f, function)) {
+            return Err(format!("函数 {} 不在允许列表中", function));
+        }
+        self.arguments.iter().try_for_each(|constraint| constraint.check(ptb, cmd))
+    }
+}
+
+impl PtbPolicy {
+    /// 从文件内容解析策略，格式由扩展名决定
+    pub fn parse(path: &Path, content: &str) -> Result<Self> {
+        let policy: PtbPolicy = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(content)?,
+            Some("json") => serde_json::from_str(content)?,
+            _ => bail!("不支持的策略文件格式: {}，请使用.toml或.json", path.display()),
+        };
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// 检查策略本身是否有效
+    fn validate(&self) -> Result<()> {
+        for rule in &self.rules {
+            if rule.package != "*" {
+                ObjectID::from_hex_literal(&rule.package)
+                    .with_context(|| format!("规则中的包ID无效: {}", rule.package))?;
+            }
+            for constraint in &rule.arguments {
+                if let Some(prefix) = &constraint.id_prefix {
+                    Hex::decode(prefix).map_err(|e| anyhow::anyhow!("无效的id_prefix {}: {}", prefix, e))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * 检查PTB是否符合策略
+     *
+     * 参数:
+     * @param ptb - 已通过ValidPtb结构检查的可编程交易块
+     *
+     * 返回:
+     * 符合策略时返回Ok(())，否则返回拒绝原因
+     */
+    pub fn check(&self, ptb: &ProgrammableTransaction) -> Result<(), String> {
+        if let Some(max) = self.max_commands {
+            if ptb.commands.len() > max {
+                return Err(format!("命令数量 {} 超过上限 {}", ptb.commands.len(), max));
+            }
+        }
+        if self.rules.is_empty() {
+            return Ok(());
+        }
+        for cmd in &ptb.commands {
+            let Command::MoveCall(cmd) = cmd else {
+                return Err("只允许MoveCall命令".to_string());
+            };
+            let mut reasons = Vec::new();
+            let matched = self.rules.iter().any(|rule| match rule.check(ptb, cmd) {
+                Ok(()) => true,
+                Err(reason) => {
+                    reasons.push(reason);
+                    false
+                }
+            });
+            if !matched {
+                return Err(format!(
+                    "{}::{}::{} 未匹配任何规则: {}",
+                    cmd.package,
+                    cmd.module,
+                    cmd.function,
+                    reasons.join("; ")
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 可热加载的策略引擎
+pub struct PolicyEngine {
+    path: Option<PathBuf>,
+    policy: RwLock<Arc<PtbPolicy>>,
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl PolicyEngine {
+    /// 创建使用固定策略的引擎
+    pub fn new(policy: PtbPolicy) -> Self {
+        Self {
+            path: None,
+            policy: RwLock::new(Arc::new(policy)),
+            modified: Mutex::new(None),
+        }
+    }
+
+    /// 从策略文件创建引擎
+    pub fn from_path(path: PathBuf) -> Result<Self> {
+        let engine = Self {
+            path: Some(path),
+            policy: RwLock::new(Arc::new(PtbPolicy::default())),
+            modified: Mutex::new(None),
+        };
+        engine.reload()?;
+        Ok(engine)
+    }
+
+    /// 当前生效的策略
+    pub fn current(&self) -> Arc<PtbPolicy> {
+        self.policy.read().clone()
+    }
+
+    /**
+     * 在策略文件变化时重新加载
+     *
+     * 返回:
+     * 重新加载了策略时返回true，文件未变化时返回false
+     */
+    pub fn reload(&self) -> Result<bool> {
+        let Some(path) = &self.path else {
+            return Ok(false);
+        };
+        let modified = std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .with_context(|| format!("读取策略文件失败: {}", path.display()))?;
+        if *self.modified.lock() == Some(modified) {
+            return Ok(false);
+        }
+
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("读取策略文件失败: {}", path.display()))?;
+        let policy = PtbPolicy::parse(path, &content)
+            .with_context(|| format!("解析策略文件失败: {}", path.display()))?;
+        info!("加载PTB策略 {}: {} 条规则", path.display(), policy.rules.len());
+        *self.policy.write() = Arc::new(policy);
+        *self.modified.lock() = Some(modified);
+        Ok(true)
+    }
+
+    /// 启动定期检查策略文件的后台任务
+    pub fn spawn_reloader(self: &Arc<Self>, interval: Option<Duration>) {
+        if self.path.is_none() {
+            return;
+        }
+        let engine = self.clone();
+        let interval = interval.unwrap_or(DEFAULT_RELOAD_INTERVAL);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = engine.reload() {
+                    error!("重新加载PTB策略失败，继续使用之前的策略: {:#}", e);
+                }
+            }
+        });
+    }
+
+    /// 检查PTB是否符合当前策略
+    pub fn check(&self, ptb: &ProgrammableTransaction, req_id: Option<&str>) -> Result<(), InternalError> {
+        self.current().check(ptb).map_err(|reason| {
+            debug!("PTB不符合策略: {} (req_id: {:?})", reason, req_id);
+            InternalError::InvalidPTB
+        })
+    }
+}
+
+static GLOBAL_POLICY_ENGINE: OnceCell<Arc<PolicyEngine>> = OnceCell::new();
+
+/// 获取全局策略引擎，未启用策略时返回None
+pub fn global_policy_engine() -> Option<Arc<PolicyEngine>> {
+    GLOBAL_POLICY_ENGINE.get().cloned()
+}
+
+/// 根据环境变量初始化全局策略引擎并启动热加载
+pub fn init_policy_engine() -> Result<Option<Arc<PolicyEngine>>> {
+    let Some(path) = std::env::var("PTB_POLICY_PATH").ok().filter(|v| !v.is_empty()) else {
+        info!("未配置PTB_POLICY_PATH，不启用PTB策略");
+        return Ok(None);
+    };
+    let interval = std::env::var("PTB_POLICY_RELOAD_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(Duration::from_secs);
+
+    let engine = GLOBAL_POLICY_ENGINE
+        .get_or_try_init(|| PolicyEngine::from_path(PathBuf::from(path)).map(Arc::new))?
+        .clone();
+    engine.spawn_reloader(interval);
+    Ok(Some(engine))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sui_types::programmable_transaction_builder::ProgrammableTransactionBuilder;
+    use sui_types::Identifier;
+
+    fn build_ptb(pkgid: ObjectID, module: &str, function: &str, id: Vec<u8>) -> ProgrammableTransaction {
+        let mut builder = ProgrammableTransactionBuilder::new();
+        let id_caller = builder.pure(id).unwrap();
+        builder.programmable_move_call(
+            pkgid,
+            Identifier::new(module).unwrap(),
+            Identifier::new(function).unwrap(),
+            vec![],
+            vec![id_caller],
+        );
+        builder.finish()
+    }
+
+    #[test]
+    fn test_policy_rules() {
+        let pkgid = ObjectID::random();
+        let content = format!(
+            r#"
+            max_commands = 2
+
+            [[rules]]
+            package = "{}"
+            modules = ["citadel"]
+            functions = ["seal_approve*"]
+
+            [[rules.arguments]]
+            index = 0
+            kind = "pure"
+            id_prefix = "0102"
+            "#,
+            pkgid
+        );
+        let policy = PtbPolicy::parse(Path::new("policy.toml"), &content).unwrap();
+
+        assert!(policy.check(&build_ptb(pkgid, "citadel", "seal_approve_x", vec![1, 2, 3])).is_ok());
+        assert!(policy.check(&build_ptb(pkgid, "other", "seal_approve_x", vec![1, 2, 3])).is_err());
+        assert!(policy.check(&build_ptb(pkgid, "citadel", "seal_approve_x", vec![9, 2, 3])).is_err());
+        assert!(policy
+            .check(&build_ptb(ObjectID::random(), "citadel", "seal_approve_x", vec![1, 2]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_empty_policy_allows_all() {
+        let policy = PtbPolicy::parse(Path::new("policy.json"), "{}").unwrap();
+        assert!(policy.check(&build_ptb(ObjectID::random(), "m", "seal_approve", vec![1])).is_ok());
+        assert!(PtbPolicy::parse(Path::new("policy.yaml"), "{}").is_err());
+        assert!(PtbPolicy::parse(Path::new("policy.json"), r#"{"rules":[{"package":"bad"}]}"#).is_err());
+    }
+
+    #[test]
+    fn test_hot_reload() {
+        let path = std::env::temp_dir().join(format!("ptb_policy_{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"max_commands": 1}"#).unwrap();
+        let engine = PolicyEngine::from_path(path.clone()).unwrap();
+        assert_eq!(engine.current().max_commands, Some(1));
+        assert!(!engine.reload().unwrap());
+
+        // 无效的策略不会替换当前策略
+        std::thread::sleep(Duration::from_millis(20));
+        std::fs::write(&path, "not json").unwrap();
+        assert!(engine.reload().is_err());
+        assert_eq!(engine.current().max_commands, Some(1));
+
+        std::thread::sleep(Duration::from_millis(20));
+        std::fs::write(&path, r#"{"max_commands": 3}"#).unwrap();
+        assert!(engine.reload().unwrap());
+        assert_eq!(engine.current().max_commands, Some(3));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 评分修正
+//!
+//! # 概述
+//! 对局结束时先按ELO计算每名玩家的基础评分变化，再按 `RatingConfig` 依次应用修正：
+//! - 连胜奖励：获胜后连胜达到一定场数时额外加分，每多一场加分增加，有上限
+//! - 逆转奖励：连败达到一定场数后获胜时额外加分
+//! - 首个出局减免：人数足够的对局中第一个出局的玩家按比例减少扣分
+//! - 单局上限：修正后的评分变化不超过上限
+//!
+//! 每项生效的修正记录在评分变化和评分历史中，便于客户端展示和排查。
+//! 修正后的评分同样不低于ELO评分下限。
+//!
+//! # 配置
+//! - `RATING_STREAK_MIN`：开始获得连胜奖励的连胜场数（含本局），默认3
+//! - `RATING_STREAK_BONUS`：每场连胜的奖励分，默认2
+//! - `RATING_STREAK_BONUS_MAX`：连胜奖励上限，默认10
+//! - `RATING_COMEBACK_MIN_LOSSES`：获得逆转奖励所需的连败场数，默认3
+//! - `RATING_COMEBACK_BONUS`：逆转奖励分，默认5
+//! - `RATING_FIRST_OUT_MIN_PLAYERS`：首个出局减免适用的最少人数，默认4
+//! - `RATING_FIRST_OUT_LOSS_FACTOR`：首个出局玩家的扣分比例，默认0.5
+//! - `RATING_MAX_DELTA`：单局评分变化上限，默认60，0表示不限制
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::tool::elo::RATING_FLOOR;
+
+/// 评分修正类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RatingModifierKind {
+    /// 连胜奖励
+    WinStreak,
+    /// 连败后获胜的逆转奖励
+    Comeback,
+    /// 首个出局的扣分减免
+    FirstOut,
+    /// 单局评分变化上限
+    Cap,
+}
+
+/// 一项生效的评分修正
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RatingModifier {
+    pub kind: RatingModifierKind,
+    /// 该项修正对评分变化的影响
+    pub delta: i32,
+}
+
+/// 计算修正所需的玩家对局信息
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RatingContext {
+    pub won: bool,
+    /// 本局之前的连胜（正数）或连败（负数）场数
+    pub streak_before: i64,
+    /// 是否为本局第一个出局的玩家
+    pub first_out: bool,
+    /// 本局玩家人数
+    pub players: usize,
+}
+
+/// 评分修正配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct RatingConfig {
+    pub streak_min: i64,
+    pub streak_bonus: i32,
+    pub streak_bonus_max: i32,
+    pub comeback_min_losses: i64,
+    pub comeback_bonus: i32,
+    pub first_out_min_players: usize,
+    pub first_out_loss_factor: f64,
+    /// 为0时不限制
+    pub max_delta: i32,
+}
+
+impl Default for RatingConfig {
+    fn default() -> Self {
+        Self {
+            streak_min: 3,
+            streak_bonus: 2,
+            streak_bonus_max: 10,
+            comeback_min_losses: 3,
+            comeback_bonus: 5,
+            first_out_min_players: 4,
+            first_out_loss_factor: 0.5,
+            max_delta: 60,
+        }
+    }
+}
+
+impl RatingConfig {
+    /// 从环境变量读取配置，未设置的项使用默认值
+    pub fn from_env() -> Self {
+        fn parse<T: std::str::FromStr>(key: &str, default: T) -> T {
+            std::env::var(key).ok().and_then(|v| v.trim().parse().ok()).unwrap_or(default)
+        }
+        let defaults = Self::default();
+        Self {
+            streak_min: parse("RATING_STREAK_MIN", defaults.streak_min),
+            streak_bonus: parse("RATING_STREAK_BONUS", defaults.streak_bonus),
+            streak_bonus_max: parse("RATING_STREAK_BONUS_MAX", defaults.streak_bonus_max),
+            comeback_min_losses: parse("RATING_COMEBACK_MIN_LOSSES", defaults.comeback_min_losses),
+            comeback_bonus: parse("RATING_COMEBACK_BONUS", defaults.comeback_bonus),
+            first_out_min_players: parse("RATING_FIRST_OUT_MIN_PLAYERS", defaults.first_out_min_players),
+            first_out_loss_factor: parse("RATING_FIRST_OUT_LOSS_FACTOR", defaults.first_out_loss_factor)
+                .clamp(0.0, 1.0),
+            max_delta: parse("RATING_MAX_DELTA", defaults.max_delta),
+        }
+    }
+
+    /**
+     * 对基础评分变化应用修正
+     *
+     * 参数:
+     * @param rating_before - 本局之前的评分
+     * @param base_after - 按ELO计算的新评分
+     * @param context - 玩家的对局信息
+     *
+     * 返回:
+     * 修正后的新评分和生效的修正
+     */
+    pub fn adjust(&self, rating_before: i32, base_after: i32, context: &RatingContext) -> (i32, Vec<RatingModifier>) {
+        let mut delta = base_after - rating_before;
+        let mut modifiers = Vec::new();
+        let mut apply = |delta: &mut i32, kind: RatingModifierKind, change: i32| {
+            if change != 0 {
+                *delta += change;
+                modifiers.push(RatingModifier { kind, delta: change });
+            }
+        };
+
+        if context.won {
+            // 本局获胜后的连胜场数
+            let streak = context.streak_before.max(0) + 1;
+            if streak >= self.streak_min {
+                let bonus = (self.streak_bonus * (streak - self.streak_min + 1) as i32).min(self.streak_bonus_max);
+                apply(&mut delta, RatingModifierKind::WinStreak, bonus.max(0));
+            }
+            if -context.streak_before >= self.comeback_min_losses {
+                apply(&mut delta, RatingModifierKind::Comeback, self.comeback_bonus.max(0));
+            }
+        } else if context.first_out && context.players >= self.first_out_min_players && delta < 0 {
+            let reduced = (delta as f64 * self.first_out_loss_factor).round() as i32;
+            apply(&mut delta, RatingModifierKind::FirstOut, reduced - delta);
+        }
+
+        if self.max_delta > 0 {
+            let capped = delta.clamp(-self.max_delta, self.max_delta);
+            apply(&mut delta, RatingModifierKind::Cap, capped - delta);
+        }
+
+        // 与ELO计算一致，已低于下限的评分不会继续降低
+        let rating_after = (rating_before + delta).max(RATING_FLOOR.min(rating_before));
+        (rating_after, modifiers)
+    }
+}
+
+static RATING_CONFIG: Lazy<RatingConfig> = Lazy::new(RatingConfig::from_env);
+
+/// 获取全局评分修正配置
+pub fn rating_config() -> &'static RatingConfig {
+    &RATING_CONFIG
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adjust() {
+        let config = RatingConfig::default();
+
+        // 第二场连胜没有奖励，第四场连胜奖励4分
+        let won = |streak_before| RatingContext { won: true, streak_before, players: 2, ..Default::default() };
+        assert_eq!(config.adjust(1000, 1020, &won(1)), (1020, vec![]));
+        assert_eq!(
+            config.adjust(1000, 1020, &won(3)).1,
+            vec![RatingModifier { kind: RatingModifierKind::WinStreak, delta: 4 }]
+        );
+        assert_eq!(config.adjust(1000, 1020, &won(20)).0, 1030);
+
+        // 连败后获胜
+        let (rating, modifiers) = config.adjust(1000, 1020, &won(-4));
+        assert_eq!(rating, 1025);
+        assert_eq!(modifiers[0].kind, RatingModifierKind::Comeback);
+
+        // 四人局第一个出局扣分减半，两人局不减免
+        let first_out = |players| RatingContext { first_out: true, players, ..Default::default() };
+        assert_eq!(config.adjust(1000, 970, &first_out(4)).0, 985);
+        assert_eq!(config.adjust(1000, 970, &first_out(2)).0, 970);
+
+        // 单局上限
+        let (rating, modifiers) = config.adjust(1000, 1100, &won(0));
+        assert_eq!(rating, 1060);
+        assert_eq!(modifiers, vec![RatingModifier { kind: RatingModifierKind::Cap, delta: -40 }]);
+    }
+}
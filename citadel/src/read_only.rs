@@ -0,0 +1,113 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 只读镜像模式
+//!
+//! # 概述
+//! 以 `server --read-only` 启动时，实例只提供读取接口，可以低成本地在多个地区部署只读镜像：
+//! - GET/HEAD/OPTIONS请求正常处理，Profile、对局历史、时间线、头像等都可以读取
+//! - 不修改共享状态的POST接口仍然可用，包括密钥派生 `/v1/fetch_key`、批量查询Profile，
+//!   以及只写入本实例会话存储的登录接口，见 `READ_ONLY_POSTS`
+//! - 其他修改状态的请求（创建Profile、游客升级、结算、成就铸造、交易提交、管理操作等）返回403 `ReadOnly`
+//! - 不接受WebSocket连接，对局动作只能在主服务上进行
+//! - 好友同步不会把好友关系提交到链上
+//!
+//! `/v1/service` 的 `read_only` 字段告知客户端当前实例是否为只读镜像。
+//!
+//! # 配置
+//! - `READ_ONLY`：为true时与 `--read-only` 参数效果相同
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use axum::extract::Request;
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use tracing::{debug, info};
+
+use crate::errors::InternalError;
+
+/// 只读模式下仍然可用的POST接口
+pub const READ_ONLY_POSTS: &[&str] = &[
+    "/v1/fetch_key",
+    "/v1/profiles/batch",
+    "/test/get_profile",
+    "/test/get_relationship",
+    "/process_data",
+    "/auth/session_token",
+    "/auth/zklogin",
+    "/auth/session_logout",
+];
+
+/// 只读模式下不可用的WebSocket接口
+const WS_PATHS: &[&str] = &["/ws", "/ws/reconnect"];
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/**
+ * 初始化只读模式
+ *
+ * 参数:
+ * @param flag - 命令行是否指定了 `--read-only`
+ *
+ * 返回:
+ * 是否启用只读模式，命令行参数和 `READ_ONLY` 环境变量任一开启即启用
+ */
+pub fn init_read_only(flag: bool) -> bool {
+    let from_env = std::env::var("READ_ONLY")
+        .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false);
+    let enabled = flag || from_env;
+    READ_ONLY.store(enabled, Ordering::SeqCst);
+    if enabled {
+        info!("只读镜像模式：修改状态的接口和WebSocket连接已禁用");
+    }
+    enabled
+}
+
+/// 当前实例是否为只读镜像
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::SeqCst)
+}
+
+/// 只读模式下是否允许该请求
+pub fn is_allowed(method: &Method, path: &str) -> bool {
+    if WS_PATHS.contains(&path) {
+        return false;
+    }
+    match *method {
+        Method::GET | Method::HEAD | Method::OPTIONS => true,
+        Method::POST => READ_ONLY_POSTS.contains(&path),
+        _ => false,
+    }
+}
+
+/// 只读模式下拒绝修改状态的请求
+pub async fn read_only_middleware(request: Request, next: Next) -> Response {
+    if is_read_only() && !is_allowed(request.method(), request.uri().path()) {
+        debug!("只读模式拒绝请求: {} {}", request.method(), request.uri().path());
+        return InternalError::ReadOnly.into_response();
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_allowed() {
+        assert!(is_allowed(&Method::GET, "/profile/0x1"));
+        assert!(is_allowed(&Method::GET, "/v1/matches/m1/timeline"));
+        assert!(is_allowed(&Method::OPTIONS, "/v1/fetch_key"));
+        assert!(is_allowed(&Method::POST, "/v1/fetch_key"));
+        assert!(is_allowed(&Method::POST, "/v1/profiles/batch"));
+
+        assert!(!is_allowed(&Method::POST, "/test/create_profile"));
+        assert!(!is_allowed(&Method::POST, "/user/guest/upgrade"));
+        assert!(!is_allowed(&Method::POST, "/economy/settle"));
+        assert!(!is_allowed(&Method::DELETE, "/v1/passport/links/0x1"));
+        assert!(!is_allowed(&Method::GET, "/ws"));
+        assert!(!is_allowed(&Method::GET, "/ws/reconnect"));
+    }
+}
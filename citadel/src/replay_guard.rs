@@ -0,0 +1,288 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 密钥请求重放保护模块
+//!
+//! # 概述
+//! `/v1/fetch_key` 的请求签名只覆盖PTB和ElGamal密钥，截获的请求在会话证书过期前可以被原样重放。
+//! 本模块为请求增加新鲜度校验：
+//! - 客户端在请求中携带 `timestamp`（毫秒）和 `nonce`，并用会话密钥对包含这两个字段的请求格式签名
+//!   （见 `signed_message::signed_request_with_freshness`）
+//! - 时间戳与服务器时间相差超过新鲜度窗口的请求被拒绝
+//! - 已使用的nonce按会话密钥记录在短期缓存中，窗口内重复出现的nonce被拒绝
+//!
+//! 新鲜度窗口和是否强制要求通过 `GET /v1/service` 的 `freshness` 字段告知客户端。
+//! 默认强制要求，不携带新鲜度字段的请求被拒绝。迁移期间可以显式关闭，
+//! 此时旧版客户端仍按原有格式校验签名，但请求可以被重放。
+//!
+//! # 配置
+//! - `REPLAY_WINDOW_SECS`：时间戳允许的偏差（秒），默认60
+//! - `REPLAY_PROTECTION_REQUIRED`：设为false或0时接受不携带新鲜度字段的请求，仅用于旧版客户端迁移，默认true
+//! - `REPLAY_NONCE_CACHE_MAX`：nonce缓存的最大条目数，默认100000
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::errors::InternalError;
+
+/// 默认新鲜度窗口（秒）
+const DEFAULT_WINDOW_SECS: u64 = 60;
+/// 默认nonce缓存的最大条目数
+const DEFAULT_NONCE_CACHE_MAX: usize = 100_000;
+/// nonce的最大长度
+pub const MAX_NONCE_LEN: usize = 64;
+
+/// 客户端构造请求所需的新鲜度要求
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub struct FreshnessPolicy {
+    /// 时间戳允许的偏差（秒）
+    pub window_secs: u64,
+    /// 是否必须携带时间戳和nonce
+    pub required: bool,
+    /// nonce的最大长度
+    pub max_nonce_len: usize,
+}
+
+/// 重放保护配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayConfig {
+    pub window_ms: u64,
+    pub required: bool,
+    pub max_entries: usize,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            window_ms: DEFAULT_WINDOW_SECS * 1000,
+            required: true,
+            max_entries: DEFAULT_NONCE_CACHE_MAX,
+        }
+    }
+}
+
+impl ReplayConfig {
+    /// 从环境变量读取配置
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            window_ms: std::env::var("REPLAY_WINDOW_SECS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|v| *v > 0)
+                .map_or(defaults.window_ms, |secs| secs * 1000),
+            required: parse_required(std::env::var("REPLAY_PROTECTION_REQUIRED").ok().as_deref(), defaults.required),
+            max_entries: std::env::var("REPLAY_NONCE_CACHE_MAX")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(defaults.max_entries),
+        }
+    }
+}
+
+/// 解析 `REPLAY_PROTECTION_REQUIRED`，只有显式设为false或0时才关闭强制要求
+fn parse_required(value: Option<&str>, default: bool) -> bool {
+    match value.map(str::trim) {
+        Some("false") | Some("0") => false,
+        Some("true") | Some("1") => true,
+        _ => default,
+    }
+}
+
+/// 请求携带的新鲜度字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Freshness<'a> {
+    pub timestamp: u64,
+    pub nonce: &'a str,
+}
+
+impl<'a> Freshness<'a> {
+    /// 从可选字段构造，两个字段必须同时提供
+    pub fn from_parts(timestamp: Option<u64>, nonce: Option<&'a str>) -> Result<Option<Self>, InternalError> {
+        match (timestamp, nonce) {
+            (Some(timestamp), Some(nonce)) => Ok(Some(Self { timestamp, nonce })),
+            (None, None) => Ok(None),
+            _ => Err(InternalError::StaleRequest),
+        }
+    }
+}
+
+/// 重放保护
+#[derive(Debug)]
+pub struct ReplayGuard {
+    config: ReplayConfig,
+    /// (会话密钥, nonce) -> 过期时间
+    seen: Mutex<HashMap<(String, String), u64>>,
+}
+
+impl ReplayGuard {
+    pub fn new(config: ReplayConfig) -> Self {
+        Self {
+            config,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 对外公布的新鲜度要求
+    pub fn policy(&self) -> FreshnessPolicy {
+        FreshnessPolicy {
+            window_secs: self.config.window_ms / 1000,
+            required: self.config.required,
+            max_nonce_len: MAX_NONCE_LEN,
+        }
+    }
+
+    /**
+     * 签名校验前检查新鲜度字段
+     *
+     * 参数:
+     * @param freshness - 请求携带的新鲜度字段
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 强制要求时缺少字段、时间戳超出窗口或nonce格式无效时返回错误
+     */
+    pub fn check(&self, freshness: Option<Freshness>, now: u64) -> Result<(), InternalError> {
+        let Some(freshness) = freshness else {
+            return if self.config.required { Err(InternalError::StaleRequest) } else { Ok(()) };
+        };
+        if freshness.nonce.is_empty() || freshness.nonce.len() > MAX_NONCE_LEN {
+            return Err(InternalError::InvalidInput);
+        }
+        if freshness.timestamp.abs_diff(now) > self.config.window_ms {
+            return Err(InternalError::StaleRequest);
+        }
+        Ok(())
+    }
+
+    /**
+     * 签名校验通过后记录nonce
+     *
+     * nonce的保留时间覆盖时间戳的整个有效范围，过期前重复使用的nonce被拒绝。
+     *
+     * 参数:
+     * @param session_key - 会话密钥的编码
+     * @param freshness - 请求携带的新鲜度字段
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * nonce已被使用时返回ReplayedRequest，缓存已满时返回RateLimited
+     */
+    pub fn consume(&self, session_key: &str, freshness: Freshness, now: u64) -> Result<(), InternalError> {
+        let mut seen = self.seen.lock();
+        let key = (session_key.to_string(), freshness.nonce.to_string());
+        if seen.get(&key).is_some_and(|expires_at| *expires_at > now) {
+            warn!("拒绝重放的密钥请求: session_key={}", session_key);
+            return Err(InternalError::ReplayedRequest);
+        }
+        if seen.len() >= self.config.max_entries {
+            seen.retain(|_, expires_at| *expires_at > now);
+            if seen.len() >= self.config.max_entries {
+                return Err(InternalError::RateLimited);
+            }
+        }
+        seen.insert(key, freshness.timestamp + self.config.window_ms);
+        Ok(())
+    }
+
+    /// 清理过期的nonce
+    pub fn prune(&self, now: u64) {
+        self.seen.lock().retain(|_, expires_at| *expires_at > now);
+    }
+
+    /// 启动定期清理的后台任务
+    pub fn spawn_pruner(self: &Arc<Self>) {
+        let guard = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(std::time::Duration::from_millis(guard.config.window_ms));
+            loop {
+                ticker.tick().await;
+                guard.prune(crate::externals::current_epoch_time());
+            }
+        });
+    }
+}
+
+static GLOBAL_REPLAY_GUARD: OnceCell<Arc<ReplayGuard>> = OnceCell::new();
+
+/// 初始化全局重放保护
+pub fn init_replay_guard() -> Arc<ReplayGuard> {
+    GLOBAL_REPLAY_GUARD
+        .get_or_init(|| {
+            let config = ReplayConfig::from_env();
+            info!("密钥请求重放保护: 窗口 {} 毫秒, 强制要求 {}", config.window_ms, config.required);
+            if !config.required {
+                warn!("REPLAY_PROTECTION_REQUIRED已关闭，不携带新鲜度字段的密钥请求可以被重放");
+            }
+            let guard = Arc::new(ReplayGuard::new(config));
+            guard.spawn_pruner();
+            guard
+        })
+        .clone()
+}
+
+/// 获取全局重放保护
+pub fn global_replay_guard() -> Option<Arc<ReplayGuard>> {
+    GLOBAL_REPLAY_GUARD.get().cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fresh(timestamp: u64, nonce: &str) -> Option<Freshness<'_>> {
+        Some(Freshness { timestamp, nonce })
+    }
+
+    #[test]
+    fn test_check_window_and_required() {
+        let guard = ReplayGuard::new(ReplayConfig { required: false, ..Default::default() });
+        let now = 1_000_000;
+        assert_eq!(guard.check(None, now), Ok(()));
+        assert_eq!(guard.check(fresh(now - 60_000, "n"), now), Ok(()));
+        assert_eq!(guard.check(fresh(now + 60_001, "n"), now), Err(InternalError::StaleRequest));
+        assert_eq!(guard.check(fresh(now, ""), now), Err(InternalError::InvalidInput));
+
+        let guard = ReplayGuard::new(ReplayConfig { required: true, ..Default::default() });
+        assert_eq!(guard.check(None, now), Err(InternalError::StaleRequest));
+        assert_eq!(Freshness::from_parts(Some(now), None), Err(InternalError::StaleRequest));
+    }
+
+    #[test]
+    fn test_required_by_default() {
+        // 默认拒绝不携带新鲜度字段的请求
+        let guard = ReplayGuard::new(ReplayConfig::default());
+        assert!(guard.policy().required);
+        assert_eq!(guard.check(None, 1_000_000), Err(InternalError::StaleRequest));
+
+        // 只有显式关闭才接受旧版请求，空值或无效值保持默认
+        assert!(parse_required(None, true));
+        assert!(parse_required(Some(""), true));
+        assert!(parse_required(Some("no"), true));
+        assert!(!parse_required(Some("false"), true));
+        assert!(!parse_required(Some("0"), true));
+    }
+
+    #[test]
+    fn test_consume_rejects_replay() {
+        let guard = ReplayGuard::new(ReplayConfig { max_entries: 2, ..Default::default() });
+        let now = 1_000_000;
+        let first = Freshness { timestamp: now, nonce: "a" };
+        assert_eq!(guard.consume("vk", first, now), Ok(()));
+        assert_eq!(guard.consume("vk", first, now + 1), Err(InternalError::ReplayedRequest));
+        // 不同会话密钥的nonce互不影响
+        assert_eq!(guard.consume("other", first, now), Ok(()));
+        // 缓存已满且没有过期条目
+        assert_eq!(guard.consume("vk", Freshness { timestamp: now, nonce: "b" }, now), Err(InternalError::RateLimited));
+        // 过期后nonce被清理
+        let later = now + 60_001;
+        assert_eq!(guard.consume("vk", Freshness { timestamp: later, nonce: "a" }, later), Ok(()));
+    }
+}
@@ -46,7 +46,7 @@ pub async fn create_profile_for_passport(
     let passport_id = ObjectID::from_hex_literal(&passport_id).context("无效的护照ID格式")?;
 
     // 使用AppState中的SUI客户端
-    let sui_client = &app_state.sui_client;
+    let sui_client = &app_state.sui_pool.client();
 
     // 从环境变量获取密钥对并创建密钥库
     let sk = env::var("WALLET_SK").context("未设置WALLET_SK环境变量")?;
@@ -146,7 +146,7 @@ pub async fn admin_send_friend_request(
     let package_id = ObjectID::from_hex_literal(&package_id_str).context("无效的包ID格式")?;
 
     // 使用AppState中的SUI客户端
-    let sui_client = &app_state.sui_client;
+    let sui_client = &app_state.sui_pool.client();
 
     // 从环境变量获取密钥对并创建密钥库
     let sk = env::var("WALLET_SK").context("未设置WALLET_SK环境变量")?;
@@ -215,3 +215,464 @@ pub async fn admin_send_friend_request(
 
     Ok(response)
 }
+
+/// 管理员接受好友请求
+///
+/// 调用Citadel合约中的accept_friend_request_for_profile函数，将待确认的好友请求更新为已接受
+///
+/// 参数:
+/// @param app_state - 应用状态，包含网络配置和SUI客户端
+/// @param requester_profile_id - 请求发送者的Profile ID
+/// @param receiver_profile_id - 请求接收者的Profile ID
+///
+/// 返回:
+/// 交易执行结果
+pub async fn admin_accept_friend_request(
+    app_state: &Arc<crate::AppState>,
+    requester_profile_id: &ObjectID,
+    receiver_profile_id: &ObjectID,
+) -> Result<SuiTransactionBlockResponse> {
+    let package_id_str = app_state.citadel_package_id();
+    tracing::debug!("使用Citadel包ID: {}", package_id_str);
+
+    // 解析包ID
+    let package_id = ObjectID::from_hex_literal(&package_id_str).context("无效的包ID格式")?;
+
+    // 使用AppState中的SUI客户端
+    let sui_client = &app_state.sui_pool.client();
+
+    // 从环境变量获取密钥对并创建密钥库
+    let sk = env::var("WALLET_SK").context("未设置WALLET_SK环境变量")?;
+    let (keystore, _, sender) = txb::create_keystore_from_sk(&sk, Some("EnvKeyPair".to_string()))?;
+    let admin_cap_id = ObjectID::from_hex_literal(&app_state.config["CITADEL_ADMINCAP_ADDRESS"])
+        .context("无效的admin_cap_id格式")?;
+    let friendship_store_id = ObjectID::from_hex_literal(&app_state.config["CITADEL_FRIENDSHIP_ADDRESS"])
+        .context("无效的friendship_store_id格式")?;
+
+    info!("管理员开始接受好友请求 Profile {} -> {}", requester_profile_id, receiver_profile_id);
+
+    let args = vec![
+        SuiJsonValue::from_object_id(friendship_store_id),
+        SuiJsonValue::from_object_id(*requester_profile_id),
+        SuiJsonValue::from_object_id(*receiver_profile_id),
+        SuiJsonValue::from_object_id(admin_cap_id),
+        SuiJsonValue::from_object_id(ObjectID::from_hex_literal("0x6").unwrap()),
+    ];
+
+    let tx_data = sui_client
+        .transaction_builder()
+        .move_call(
+            sender,
+            package_id,
+            "citadel",
+            "accept_friend_request_for_profile",
+            vec![],  // 类型参数为空
+            args,
+            None,  // gas object参数
+            crate::types::GAS_BUDGET,
+            None,  // gas price参数
+        )
+        .await
+        .context("构建Move调用交易失败")?;
+
+    // 执行交易
+    let response = txb::execute_transaction(sui_client, tx_data, &keystore, &sender)
+        .await
+        .context("执行交易失败")?;
+
+    let digest = app_state.network.explorer_tx_url(&response.digest.to_string());
+    info!("Successfully executed transaction: {}", &digest);
+
+    if !response.status_ok().unwrap_or(false) {
+        anyhow::bail!("Transaction execution failed: {:?}, transaction: {}", response.effects.as_ref().unwrap(), digest);
+    }
+
+    Ok(response)
+}
+
+/// 为Profile铸造链上成就对象
+///
+/// 调用Citadel合约中的mint_achievement函数，成就对象会转移给对应的Profile
+///
+/// 参数:
+/// @param app_state - 应用状态，包含网络配置和SUI客户端
+/// @param profile_id - Profile ID
+/// @param achievement_id - 成就ID
+/// @param name - 成就名称
+///
+/// 返回:
+/// 交易执行结果
+pub async fn mint_achievement(
+    app_state: &Arc<crate::AppState>,
+    profile_id: &ObjectID,
+    achievement_id: &str,
+    name: &str,
+) -> Result<SuiTransactionBlockResponse> {
+    let package_id_str = app_state.citadel_package_id();
+    tracing::debug!("使用Citadel包ID: {}", package_id_str);
+
+    // 解析包ID
+    let package_id = ObjectID::from_hex_literal(&package_id_str).context("无效的包ID格式")?;
+
+    // 使用AppState中的SUI客户端
+    let sui_client = &app_state.sui_pool.client();
+
+    // 从环境变量获取密钥对并创建密钥库
+    let sk = env::var("WALLET_SK").context("未设置WALLET_SK环境变量")?;
+    let (keystore, _, sender) = txb::create_keystore_from_sk(&sk, Some("EnvKeyPair".to_string()))?;
+    let admin_cap_id = ObjectID::from_hex_literal(&app_state.config["CITADEL_ADMINCAP_ADDRESS"])
+        .context("无效的admin_cap_id格式")?;
+
+    info!("开始为Profile {} 铸造成就: {}", profile_id, achievement_id);
+
+    let args = vec![
+        SuiJsonValue::from_object_id(*profile_id),
+        SuiJsonValue::new(Value::String(achievement_id.to_string()))?,
+        SuiJsonValue::new(Value::String(name.to_string()))?,
+        SuiJsonValue::from_object_id(admin_cap_id),
+        SuiJsonValue::from_object_id(ObjectID::from_hex_literal("0x6").unwrap()),
+    ];
+
+    let tx_data = sui_client
+        .transaction_builder()
+        .move_call(
+            sender,
+            package_id,
+            "citadel",
+            "mint_achievement",
+            vec![],  // 类型参数为空
+            args,
+            None,  // gas object参数
+            crate::types::GAS_BUDGET,
+            None,  // gas price参数
+        )
+        .await
+        .context("构建Move调用交易失败")?;
+
+    // 执行交易
+    let response = txb::execute_transaction(sui_client, tx_data, &keystore, &sender)
+        .await
+        .context("执行交易失败")?;
+
+    let digest = app_state.network.explorer_tx_url(&response.digest.to_string());
+    info!("Successfully executed transaction: {}", &digest);
+
+    if !response.status_ok().unwrap_or(false) {
+        anyhow::bail!("Transaction execution failed: {:?}, transaction: {}", response.effects.as_ref().unwrap(), digest);
+    }
+
+    Ok(response)
+}
+
+/// 将游戏币余额结算到链上Profile
+///
+/// 调用Citadel合约中的settle_profile_balance函数，覆盖Profile上记录的余额
+///
+/// 参数:
+/// @param app_state - 应用状态，包含网络配置和SUI客户端
+/// @param profile_id - Profile ID
+/// @param balance - 本地缓存中的当前余额
+///
+/// 返回:
+/// 交易执行结果
+pub async fn settle_profile_balance(
+    app_state: &Arc<crate::AppState>,
+    profile_id: &ObjectID,
+    balance: u64,
+) -> Result<SuiTransactionBlockResponse> {
+    let package_id_str = app_state.citadel_package_id();
+    tracing::debug!("使用Citadel包ID: {}", package_id_str);
+
+    // 解析包ID
+    let package_id = ObjectID::from_hex_literal(&package_id_str).context("无效的包ID格式")?;
+
+    // 使用AppState中的SUI客户端
+    let sui_client = &app_state.sui_pool.client();
+
+    // 从环境变量获取密钥对并创建密钥库
+    let sk = env::var("WALLET_SK").context("未设置WALLET_SK环境变量")?;
+    let (keystore, _, sender) = txb::create_keystore_from_sk(&sk, Some("EnvKeyPair".to_string()))?;
+    let admin_cap_id = ObjectID::from_hex_literal(&app_state.config["CITADEL_ADMINCAP_ADDRESS"])
+        .context("无效的admin_cap_id格式")?;
+
+    info!("开始为Profile {} 结算余额: {}", profile_id, balance);
+
+    let args = vec![
+        SuiJsonValue::from_object_id(*profile_id),
+        SuiJsonValue::new(Value::String(balance.to_string()))?,
+        SuiJsonValue::from_object_id(admin_cap_id),
+        SuiJsonValue::from_object_id(ObjectID::from_hex_literal("0x6").unwrap()),
+    ];
+
+    let tx_data = sui_client
+        .transaction_builder()
+        .move_call(
+            sender,
+            package_id,
+            "citadel",
+            "settle_profile_balance",
+            vec![],  // 类型参数为空
+            args,
+            None,  // gas object参数
+            crate::types::GAS_BUDGET,
+            None,  // gas price参数
+        )
+        .await
+        .context("构建Move调用交易失败")?;
+
+    // 执行交易
+    let response = txb::execute_transaction(sui_client, tx_data, &keystore, &sender)
+        .await
+        .context("执行交易失败")?;
+
+    let digest = app_state.network.explorer_tx_url(&response.digest.to_string());
+    info!("Successfully executed transaction: {}", &digest);
+
+    if !response.status_ok().unwrap_or(false) {
+        anyhow::bail!("Transaction execution failed: {:?}, transaction: {}", response.effects.as_ref().unwrap(), digest);
+    }
+
+    Ok(response)
+}
+
+/// 将服务器签名的对局结果存证到链上
+///
+/// 调用Citadel合约中的attest_match_result函数，合约校验签名后创建冻结的存证对象
+///
+/// 参数:
+/// @param app_state - 应用状态，包含网络配置和SUI客户端
+/// @param attestation - 对局结果摘要
+/// @param message - 签名的BCS编码消息
+/// @param signature - Ed25519签名
+/// @param public_key - 签名使用的公钥
+///
+/// 返回:
+/// 交易执行结果
+pub async fn attest_match_result(
+    app_state: &Arc<crate::AppState>,
+    attestation: &crate::match_attestation::MatchAttestation,
+    message: &[u8],
+    signature: &[u8],
+    public_key: &[u8],
+) -> Result<SuiTransactionBlockResponse> {
+    let package_id_str = app_state.citadel_package_id();
+    tracing::debug!("使用Citadel包ID: {}", package_id_str);
+
+    // 解析包ID
+    let package_id = ObjectID::from_hex_literal(&package_id_str).context("无效的包ID格式")?;
+
+    // 使用AppState中的SUI客户端
+    let sui_client = &app_state.sui_pool.client();
+
+    // 从环境变量获取密钥对并创建密钥库
+    let sk = env::var("WALLET_SK").context("未设置WALLET_SK环境变量")?;
+    let (keystore, _, sender) = txb::create_keystore_from_sk(&sk, Some("EnvKeyPair".to_string()))?;
+    let admin_cap_id = ObjectID::from_hex_literal(&app_state.config["CITADEL_ADMINCAP_ADDRESS"])
+        .context("无效的admin_cap_id格式")?;
+
+    info!("开始存证对局结果: {}", attestation.match_id);
+
+    let args = vec![
+        SuiJsonValue::new(Value::String(attestation.match_id.clone()))?,
+        SuiJsonValue::new(serde_json::json!(attestation.players))?,
+        SuiJsonValue::new(Value::String(attestation.winner_id.clone()))?,
+        SuiJsonValue::new(serde_json::json!(attestation.history_hash))?,
+        SuiJsonValue::new(serde_json::json!(message))?,
+        SuiJsonValue::new(serde_json::json!(signature))?,
+        SuiJsonValue::new(serde_json::json!(public_key))?,
+        SuiJsonValue::from_object_id(admin_cap_id),
+        SuiJsonValue::from_object_id(ObjectID::from_hex_literal("0x6").unwrap()),
+    ];
+
+    let tx_data = sui_client
+        .transaction_builder()
+        .move_call(
+            sender,
+            package_id,
+            "citadel",
+            "attest_match_result",
+            vec![],  // 类型参数为空
+            args,
+            None,  // gas object参数
+            crate::types::GAS_BUDGET,
+            None,  // gas price参数
+        )
+        .await
+        .context("构建Move调用交易失败")?;
+
+    // 执行交易
+    let response = txb::execute_transaction(sui_client, tx_data, &keystore, &sender)
+        .await
+        .context("执行交易失败")?;
+
+    let digest = app_state.network.explorer_tx_url(&response.digest.to_string());
+    info!("Successfully executed transaction: {}", &digest);
+
+    if !response.status_ok().unwrap_or(false) {
+        anyhow::bail!("Transaction execution failed: {:?}, transaction: {}", response.effects.as_ref().unwrap(), digest);
+    }
+
+    Ok(response)
+}
+
+/// 管理员修改Profile的头像
+///
+/// 调用Citadel合约中的modify_profile_for_passport函数，成功后更新GameManager中的Profile缓存
+///
+/// 参数:
+/// @param app_state - 应用状态，包含网络配置和SUI客户端
+/// @param passport_id - Profile所属的护照ID
+/// @param profile_id - Profile ID
+/// @param avatar - 新的头像数据
+///
+/// 返回:
+/// 交易执行结果
+pub async fn modify_profile_for_passport(
+    app_state: &Arc<crate::AppState>,
+    passport_id: &ObjectID,
+    profile_id: &ObjectID,
+    avatar: &str,
+) -> Result<SuiTransactionBlockResponse> {
+    let package_id_str = app_state.citadel_package_id();
+    tracing::debug!("使用Citadel包ID: {}", package_id_str);
+
+    // 解析包ID
+    let package_id = ObjectID::from_hex_literal(&package_id_str).context("无效的包ID格式")?;
+
+    // 使用AppState中的SUI客户端
+    let sui_client = &app_state.sui_pool.client();
+
+    // 从环境变量获取密钥对并创建密钥库
+    let sk = env::var("WALLET_SK").context("未设置WALLET_SK环境变量")?;
+    let (keystore, _, sender) = txb::create_keystore_from_sk(&sk, Some("EnvKeyPair".to_string()))?;
+    let admin_cap_id = ObjectID::from_hex_literal(&app_state.config["CITADEL_ADMINCAP_ADDRESS"])
+        .context("无效的admin_cap_id格式")?;
+    let manager_store_id = ObjectID::from_hex_literal(&app_state.config["CITADEL_MANAGER_ADDRESS"])
+        .context("无效的manager_store_id格式")?;
+
+    info!("开始修改Profile {} 的头像", profile_id);
+
+    let args = vec![
+        SuiJsonValue::from_object_id(manager_store_id),
+        SuiJsonValue::from_object_id(*profile_id),
+        SuiJsonValue::from_object_id(*passport_id),
+        SuiJsonValue::new(Value::String(avatar.to_string()))?,
+        SuiJsonValue::from_object_id(admin_cap_id),
+    ];
+
+    let tx_data = sui_client
+        .transaction_builder()
+        .move_call(
+            sender,
+            package_id,
+            "citadel",
+            "modify_profile_for_passport",
+            vec![],  // 类型参数为空
+            args,
+            None,  // gas object参数
+            crate::types::GAS_BUDGET,
+            None,  // gas price参数
+        )
+        .await
+        .context("构建Move调用交易失败")?;
+
+    // 执行交易
+    let response = txb::execute_transaction(sui_client, tx_data, &keystore, &sender)
+        .await
+        .context("执行交易失败")?;
+
+    let digest = app_state.network.explorer_tx_url(&response.digest.to_string());
+    info!("Successfully executed transaction: {}", &digest);
+
+    if !response.status_ok().unwrap_or(false) {
+        anyhow::bail!("Transaction execution failed: {:?}, transaction: {}", response.effects.as_ref().unwrap(), digest);
+    }
+
+    // 更新缓存中的头像
+    match app_state.game_manager.get_profile(profile_id).await {
+        Ok(mut profile) => {
+            profile.avatar = avatar.to_string();
+            app_state.game_manager.update_profile_cache(profile).await;
+        }
+        Err(e) => warn!("头像已修改，但读取Profile {} 失败，缓存未更新: {}", profile_id, e),
+    }
+
+    Ok(response)
+}
+
+/// 管理员修改Profile的名称
+///
+/// 调用Citadel合约中的rename_profile_for_passport函数，名称保存在Profile的动态字段中
+///
+/// 参数:
+/// @param app_state - 应用状态，包含网络配置和SUI客户端
+/// @param passport_id - Profile所属的护照ID
+/// @param profile_id - Profile ID
+/// @param name - 已通过名称策略校验的名称
+///
+/// 返回:
+/// 交易执行结果
+pub async fn rename_profile_for_passport(
+    app_state: &Arc<crate::AppState>,
+    passport_id: &ObjectID,
+    profile_id: &ObjectID,
+    name: &str,
+) -> Result<SuiTransactionBlockResponse> {
+    let package_id_str = app_state.citadel_package_id();
+    tracing::debug!("使用Citadel包ID: {}", package_id_str);
+
+    // 解析包ID
+    let package_id = ObjectID::from_hex_literal(&package_id_str).context("无效的包ID格式")?;
+
+    // 使用AppState中的SUI客户端
+    let sui_client = &app_state.sui_pool.client();
+
+    // 从环境变量获取密钥对并创建密钥库
+    let sk = env::var("WALLET_SK").context("未设置WALLET_SK环境变量")?;
+    let (keystore, _, sender) = txb::create_keystore_from_sk(&sk, Some("EnvKeyPair".to_string()))?;
+    let admin_cap_id = ObjectID::from_hex_literal(&app_state.config["CITADEL_ADMINCAP_ADDRESS"])
+        .context("无效的admin_cap_id格式")?;
+    let manager_store_id = ObjectID::from_hex_literal(&app_state.config["CITADEL_MANAGER_ADDRESS"])
+        .context("无效的manager_store_id格式")?;
+
+    info!("开始修改Profile {} 的名称: {}", profile_id, name);
+
+    let args = vec![
+        SuiJsonValue::from_object_id(manager_store_id),
+        SuiJsonValue::from_object_id(*profile_id),
+        SuiJsonValue::from_object_id(*passport_id),
+        SuiJsonValue::new(Value::String(name.to_string()))?,
+        SuiJsonValue::from_object_id(admin_cap_id),
+        SuiJsonValue::from_object_id(ObjectID::from_hex_literal("0x6").unwrap()),
+    ];
+
+    let tx_data = sui_client
+        .transaction_builder()
+        .move_call(
+            sender,
+            package_id,
+            "citadel",
+            "rename_profile_for_passport",
+            vec![],  // 类型参数为空
+            args,
+            None,  // gas object参数
+            crate::types::GAS_BUDGET,
+            None,  // gas price参数
+        )
+        .await
+        .context("构建Move调用交易失败")?;
+
+    // 执行交易
+    let response = txb::execute_transaction(sui_client, tx_data, &keystore, &sender)
+        .await
+        .context("执行交易失败")?;
+
+    let digest = app_state.network.explorer_tx_url(&response.digest.to_string());
+    info!("Successfully executed transaction: {}", &digest);
+
+    if !response.status_ok().unwrap_or(false) {
+        anyhow::bail!("Transaction execution failed: {:?}, transaction: {}", response.effects.as_ref().unwrap(), digest);
+    }
+
+    Ok(response)
+}
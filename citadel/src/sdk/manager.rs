@@ -8,8 +8,8 @@ use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 use std::sync::atomic::{AtomicU64, Ordering};
 
-use super::query::{query_all_table_content, query_object_content};
 use crate::cache::{Cache, CACHE_SIZE, CACHE_TTL};
+use crate::sui_api::SuiApi;
 use crate::types::Network;
 
 /// 好友关系状态
@@ -56,14 +56,16 @@ pub struct ProfileWithRelationship {
 /// 3. 排行榜数据
 /// 等等
 pub struct GameManager {
-    /// SUI客户端
-    client: sui_sdk::SuiClient,
+    /// 全节点接口，测试时可以替换为模拟实现
+    client: Arc<dyn SuiApi>,
     /// 网络
     network: Network,
     /// Profile缓存
     profile_cache: Arc<RwLock<Cache<ObjectID, Profile>>>,
     /// 好友关系缓存
     relationship_cache: Arc<RwLock<Cache<(ObjectID, ObjectID), Relationship>>>,
+    /// 最近一次全量更新得到的所有好友关系，每对用户一条
+    relationships: Arc<RwLock<Vec<Relationship>>>,
     /// PassportID到ProfileID的映射
     passport_profile_map: Arc<RwLock<HashMap<ObjectID, ObjectID>>>,
     /// Profile表格ID
@@ -78,12 +80,13 @@ pub struct GameManager {
 
 impl GameManager {
     /// 创建新的游戏数据管理器
-    pub async fn new(client: sui_sdk::SuiClient, network: Network, manager_store_id: ObjectID,friendship_store_id: ObjectID) -> Result<Self> {
+    pub async fn new(client: impl SuiApi + 'static, network: Network, manager_store_id: ObjectID,friendship_store_id: ObjectID) -> Result<Self> {
+        let client: Arc<dyn SuiApi> = Arc::new(client);
         let profile_table_id = match network {
             #[cfg(test)]
             Network::TestCluster => ObjectID::ZERO, // 在测试环境中使用一个固定的ID
             _ => {
-                let store = query_object_content(&network, &manager_store_id).await?;
+                let store = client.object_content(&network, &manager_store_id).await?;
                 // 获取profiles表格ID
                 let profile_table_id = store.content["profiles"]["id"]
                     .as_str()
@@ -96,7 +99,7 @@ impl GameManager {
             #[cfg(test)]
             Network::TestCluster => ObjectID::ZERO, // 在测试环境中使用一个固定的ID
             _ => {
-                let store = query_object_content(&network, &friendship_store_id).await?;
+                let store = client.object_content(&network, &friendship_store_id).await?;
                 // 获取profiles表格ID
                 let friendship_table_id = store.content["relations"]["id"]
                     .as_str()
@@ -116,6 +119,7 @@ impl GameManager {
             network,
             profile_cache: Arc::new(RwLock::new(Cache::new(CACHE_TTL, CACHE_SIZE))),
             relationship_cache: Arc::new(RwLock::new(Cache::new(CACHE_TTL, CACHE_SIZE))),
+            relationships: Arc::new(RwLock::new(Vec::new())),
             passport_profile_map: Arc::new(RwLock::new(HashMap::new())),
             profile_table_id,
             friendship_table_id,
@@ -203,8 +207,21 @@ impl GameManager {
                     return Ok(profile);
                 }
 
-                // 查询Profile对象
-                let data = query_object_content(&self.network, profile_id).await?;
+                // 查询Profile对象，链上查询失败时回退到持久化存储中的最近一次结果
+                let data = match self.client.object_content(&self.network, profile_id).await {
+                    Ok(data) => data,
+                    Err(e) => {
+                        let Some(profile) = crate::storage::load_profile(profile_id).await else {
+                            return Err(e);
+                        };
+                        warn!("查询Profile {} 失败，使用存储中的数据: {}", profile_id, e);
+                        self.profile_cache
+                            .write()
+                            .await
+                            .insert(*profile_id, profile.clone());
+                        return Ok(profile);
+                    }
+                };
 
                 // 解析数据
                 let profile = Profile {
@@ -236,16 +253,22 @@ impl GameManager {
                     .write()
                     .await
                     .insert(*profile_id, profile.clone());
+                crate::storage::persist_profile(&profile).await;
 
                 Ok(profile)
             }
         }
     }
 
+    /// 批量获取Profile信息，缓存未命中的并发查询链上数据，结果与输入顺序一致
+    pub async fn get_profiles(&self, profile_ids: &[ObjectID]) -> Vec<Result<Profile>> {
+        futures::future::join_all(profile_ids.iter().map(|id| self.get_profile(id))).await
+    }
+
     /// 更新所有Profile信息
     pub async fn update_all_profiles(&self) -> Result<()> {
         // 查询表格获取所有映射
-        let fields = query_all_table_content(&self.network, &self.profile_table_id, None).await?;
+        let fields = self.client.table_content(&self.network, &self.profile_table_id).await?;
         info!("update_all_profiles fields: {:?}", fields.len());
         // 更新映射
         let mut map = self.passport_profile_map.write().await;
@@ -261,7 +284,7 @@ impl GameManager {
             map.insert(passport, profile);
 
             // 更新Profile信息
-            if let Ok(data) = query_object_content(&self.network, &profile).await {
+            if let Ok(data) = self.client.object_content(&self.network, &profile).await {
                 let profile_data = Profile {
                     id: profile,
                     avatar: data.content["avatar"]
@@ -285,6 +308,7 @@ impl GameManager {
                         .and_then(|s| s.parse().ok())
                         .unwrap_or_default(),
                 };
+                crate::storage::persist_profile(&profile_data).await;
                 profile_cache.insert(profile, profile_data);
             }
         }
@@ -307,8 +331,19 @@ impl GameManager {
         map.insert(passport_id, profile_id);
     }
 
+    /// ProfileID到PassportID的映射
+    pub async fn profile_passport_map(&self) -> HashMap<ObjectID, ObjectID> {
+        self.passport_profile_map
+            .read()
+            .await
+            .iter()
+            .map(|(passport, profile)| (*profile, *passport))
+            .collect()
+    }
+
     /// 更新Profile缓存
     pub async fn update_profile_cache(&self, profile: Profile) {
+        crate::storage::persist_profile(&profile).await;
         let mut cache = self.profile_cache.write().await;
         cache.insert(profile.id, profile);
     }
@@ -353,6 +388,11 @@ impl GameManager {
     }
 
 
+    /// 最近一次全量更新得到的所有好友关系
+    pub async fn all_relationships(&self) -> Vec<Relationship> {
+        self.relationships.read().await.clone()
+    }
+
     /// 获取关系缓存大小
     pub async fn get_relationship_cache_size(&self) -> u64 {
         self.relationship_cache.read().await.len() as u64
@@ -363,7 +403,7 @@ impl GameManager {
         info!("开始更新所有好友关系缓存");
         
         // 查询所有好友关系
-        let fields = query_all_table_content(&self.network, &self.friendship_table_id, None).await?;
+        let fields = self.client.table_content(&self.network, &self.friendship_table_id).await?;
         info!("获取到 {} 个关系记录", fields.len());
         
         // 获取缓存写锁
@@ -371,6 +411,7 @@ impl GameManager {
         
         // 清空现有缓存
         cache.clear();
+        let mut relationships = Vec::with_capacity(fields.len());
         
         // 更新缓存
         for field in fields {
@@ -406,8 +447,10 @@ impl GameManager {
             
             // 双向缓存关系
             cache.insert((initiator, receiver), relationship.clone());
-            cache.insert((receiver, initiator), relationship);
+            cache.insert((receiver, initiator), relationship.clone());
+            relationships.push(relationship);
         }
+        *self.relationships.write().await = relationships;
         
         // 更新完成后更新时间戳
         self.last_relationship_update.store(
@@ -1,6 +1,11 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
 use serde_json::Value;
-use sui_types::base_types::{ObjectID};
+use sui_sdk::rpc_types::SuiObjectDataOptions;
+use sui_sdk::SuiClient;
+use sui_types::base_types::{ObjectID, SequenceNumber};
+use sui_types::TypeTag;
 use reqwest::Client;
 use tracing::{info, warn};
 use crate::types::Network;
@@ -31,6 +36,54 @@ impl GraphQLClient {
             .await
             .context("Failed to parse GraphQL response")
     }
+
+    /**
+     * 执行带变量的GraphQL查询并解析为指定类型
+     *
+     * 参数:
+     * @param query - GraphQL查询语句
+     * @param variables - 查询变量
+     *
+     * 返回:
+     * 响应中的data字段，请求失败、响应包含errors或没有data时返回错误
+     */
+    pub async fn execute<T: DeserializeOwned>(&self, query: &str, variables: Value) -> Result<T> {
+        let response: GraphQLResponse<T> = self
+            .client
+            .post(&self.url)
+            .json(&serde_json::json!({ "query": query, "variables": variables }))
+            .send()
+            .await
+            .context("Failed to execute GraphQL query")?
+            .error_for_status()
+            .context("GraphQL service returned error status")?
+            .json()
+            .await
+            .context("Failed to parse GraphQL response")?;
+        response.into_data()
+    }
+}
+
+/// GraphQL响应
+#[derive(Debug, Deserialize)]
+struct GraphQLResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQLError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQLError {
+    message: String,
+}
+
+impl<T> GraphQLResponse<T> {
+    fn into_data(self) -> Result<T> {
+        if let Some(error) = self.errors.first() {
+            return Err(anyhow!("GraphQL query failed: {}", error.message));
+        }
+        self.data.context("GraphQL response has no data")
+    }
 }
 
 /// 对象数据结构
@@ -242,4 +295,469 @@ pub async fn query_all_table_content(
     }
 
     Ok(all_fields)
-}
\ No newline at end of file
+}
+
+/// 历史版本对象查询
+const OBJECT_AT_VERSION_QUERY: &str = r#"
+    query GetObjectAtVersion($address: SuiAddress!, $version: UInt53) {
+        object(address: $address, version: $version) {
+            version
+            asMoveObject {
+                contents {
+                    type { repr }
+                    json
+                }
+            }
+        }
+    }
+"#;
+
+/// 动态字段分页查询
+const DYNAMIC_FIELDS_QUERY: &str = r#"
+    query GetDynamicFields($address: SuiAddress!, $first: Int, $after: String) {
+        owner(address: $address) {
+            dynamicFields(first: $first, after: $after) {
+                pageInfo {
+                    hasNextPage
+                    endCursor
+                }
+                nodes {
+                    name {
+                        type { repr }
+                        json
+                    }
+                    value {
+                        ... on MoveValue {
+                            json
+                        }
+                        ... on MoveObject {
+                            contents {
+                                type { repr }
+                                json
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+"#;
+
+#[derive(Debug, Deserialize)]
+struct GqlTypeRepr {
+    repr: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GqlMoveValue {
+    #[serde(rename = "type")]
+    type_: GqlTypeRepr,
+    json: Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlObject {
+    version: u64,
+    as_move_object: Option<GqlMoveObject>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GqlMoveObject {
+    contents: Option<GqlMoveValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ObjectAtVersionData {
+    object: Option<GqlObject>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlPageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+/// 动态字段的值：MoveValue只有json，MoveObject（动态对象字段）的内容在contents中
+#[derive(Debug, Deserialize)]
+struct GqlDynamicFieldValue {
+    json: Option<Value>,
+    contents: Option<GqlMoveValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GqlDynamicField {
+    name: Option<GqlMoveValue>,
+    value: Option<GqlDynamicFieldValue>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlDynamicFieldConnection {
+    page_info: GqlPageInfo,
+    nodes: Vec<GqlDynamicField>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GqlOwner {
+    dynamic_fields: GqlDynamicFieldConnection,
+}
+
+#[derive(Debug, Deserialize)]
+struct DynamicFieldsData {
+    owner: Option<GqlOwner>,
+}
+
+/// 对象某个历史版本的数据
+#[derive(Debug, Clone)]
+pub struct ObjectVersionData {
+    pub object_id: ObjectID,
+    pub version: u64,
+    /// Move类型
+    pub type_: Option<String>,
+    /// 对象字段的JSON表示
+    pub content: Value,
+}
+
+/// 动态字段
+#[derive(Debug, Clone)]
+pub struct DynamicField {
+    /// 字段名的Move类型
+    pub name_type: String,
+    pub name: Value,
+    pub value: Value,
+}
+
+/// 动态字段分页游标
+///
+/// GraphQL和JSON-RPC的游标格式不同，游标只能用于产生它的查询方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DynamicFieldCursor {
+    GraphQL(String),
+    JsonRpc(ObjectID),
+}
+
+/// 动态字段查询结果
+#[derive(Debug, Clone)]
+pub struct DynamicFieldPage {
+    pub fields: Vec<DynamicField>,
+    pub has_next_page: bool,
+    pub next_cursor: Option<DynamicFieldCursor>,
+}
+
+/// 动态字段过滤条件
+///
+/// 过滤在取回每页数据后进行，因此一页返回的字段数可能少于页面大小
+#[derive(Debug, Clone, Default)]
+pub struct DynamicFieldFilter {
+    name_type: Option<TypeTag>,
+}
+
+impl DynamicFieldFilter {
+    /// 只保留字段名为指定Move类型的字段，如 `0x1::string::String`
+    pub fn name_type(name_type: &str) -> Result<Self> {
+        let name_type = sui_types::parse_sui_type_tag(name_type)
+            .with_context(|| format!("Invalid type tag: {}", name_type))?;
+        Ok(Self { name_type: Some(name_type) })
+    }
+
+    /// 字段名类型是否满足条件，地址长短写法视为相同
+    pub fn matches(&self, name_type: &str) -> bool {
+        match &self.name_type {
+            None => true,
+            Some(expected) => sui_types::parse_sui_type_tag(name_type)
+                .map(|t| t == *expected)
+                .unwrap_or(false),
+        }
+    }
+}
+
+impl GqlDynamicField {
+    fn into_field(self) -> Option<DynamicField> {
+        let name = self.name?;
+        let value = self
+            .value
+            .and_then(|v| v.json.or(v.contents.map(|c| c.json)))
+            .unwrap_or(Value::Null);
+        Some(DynamicField {
+            name_type: name.type_.repr,
+            name: name.json,
+            value,
+        })
+    }
+}
+
+impl DynamicFieldsData {
+    fn into_page(self, parent: &ObjectID, filter: &DynamicFieldFilter) -> Result<DynamicFieldPage> {
+        let connection = self
+            .owner
+            .with_context(|| format!("Object {} not found", parent))?
+            .dynamic_fields;
+        let fields = connection
+            .nodes
+            .into_iter()
+            .filter_map(GqlDynamicField::into_field)
+            .filter(|field| filter.matches(&field.name_type))
+            .collect();
+        Ok(DynamicFieldPage {
+            fields,
+            has_next_page: connection.page_info.has_next_page,
+            next_cursor: connection.page_info.end_cursor.map(DynamicFieldCursor::GraphQL),
+        })
+    }
+}
+
+/**
+ * 查询对象的历史版本
+ *
+ * 优先使用GraphQL，GraphQL不可用时通过JSON-RPC查询
+ *
+ * 参数:
+ * @param network - 网络配置
+ * @param sui_client - 备用的JSON-RPC客户端
+ * @param object_id - 对象ID
+ * @param version - 对象版本
+ *
+ * 返回:
+ * 该版本的对象数据
+ */
+pub async fn query_object_at_version(
+    network: &Network,
+    sui_client: &SuiClient,
+    object_id: &ObjectID,
+    version: u64,
+) -> Result<ObjectVersionData> {
+    match graphql_object_at_version(network, object_id, version).await {
+        Ok(data) => Ok(data),
+        Err(e) => {
+            warn!("GraphQL查询对象 {} 版本 {} 失败，改用JSON-RPC: {:#}", object_id, version, e);
+            rpc_object_at_version(sui_client, object_id, version).await
+        }
+    }
+}
+
+async fn graphql_object_at_version(network: &Network, object_id: &ObjectID, version: u64) -> Result<ObjectVersionData> {
+    let variables = serde_json::json!({ "address": object_id.to_string(), "version": version });
+    let data: ObjectAtVersionData = GraphQLClient::new(network)
+        .execute(OBJECT_AT_VERSION_QUERY, variables)
+        .await?;
+    let object = data
+        .object
+        .with_context(|| format!("Object {} version {} not found", object_id, version))?;
+    let contents = object.as_move_object.and_then(|o| o.contents);
+    Ok(ObjectVersionData {
+        object_id: *object_id,
+        version: object.version,
+        type_: contents.as_ref().map(|c| c.type_.repr.clone()),
+        content: contents.map(|c| c.json).unwrap_or(Value::Null),
+    })
+}
+
+async fn rpc_object_at_version(sui_client: &SuiClient, object_id: &ObjectID, version: u64) -> Result<ObjectVersionData> {
+    let data = sui_client
+        .read_api()
+        .try_get_parsed_past_object(
+            *object_id,
+            SequenceNumber::from_u64(version),
+            SuiObjectDataOptions::new().with_type().with_content(),
+        )
+        .await
+        .context("Failed to query past object")?
+        .into_object()
+        .with_context(|| format!("Object {} version {} not available", object_id, version))?;
+    let content = data
+        .content
+        .as_ref()
+        .and_then(|c| c.try_as_move())
+        .map(|o| o.fields.clone().to_json_value())
+        .unwrap_or(Value::Null);
+    Ok(ObjectVersionData {
+        object_id: *object_id,
+        version: data.version.value(),
+        type_: data.type_.map(|t| t.to_string()),
+        content,
+    })
+}
+
+/**
+ * 分页查询对象的动态字段
+ *
+ * 没有游标时优先使用GraphQL，GraphQL不可用时通过JSON-RPC查询。
+ * 带游标时使用产生该游标的查询方式。
+ *
+ * 参数:
+ * @param network - 网络配置
+ * @param sui_client - 备用的JSON-RPC客户端
+ * @param parent - 父对象ID
+ * @param filter - 过滤条件
+ * @param cursor - 上一页返回的游标
+ * @param page_size - 页面大小，默认50
+ *
+ * 返回:
+ * 当前页满足条件的动态字段
+ */
+pub async fn query_dynamic_fields(
+    network: &Network,
+    sui_client: &SuiClient,
+    parent: &ObjectID,
+    filter: &DynamicFieldFilter,
+    cursor: Option<DynamicFieldCursor>,
+    page_size: Option<u32>,
+) -> Result<DynamicFieldPage> {
+    let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+    match cursor {
+        Some(DynamicFieldCursor::GraphQL(cursor)) => {
+            graphql_dynamic_fields(network, parent, filter, Some(cursor), page_size).await
+        }
+        Some(DynamicFieldCursor::JsonRpc(cursor)) => {
+            rpc_dynamic_fields(sui_client, parent, filter, Some(cursor), page_size).await
+        }
+        None => match graphql_dynamic_fields(network, parent, filter, None, page_size).await {
+            Ok(page) => Ok(page),
+            Err(e) => {
+                warn!("GraphQL查询对象 {} 的动态字段失败，改用JSON-RPC: {:#}", parent, e);
+                rpc_dynamic_fields(sui_client, parent, filter, None, page_size).await
+            }
+        },
+    }
+}
+
+async fn graphql_dynamic_fields(
+    network: &Network,
+    parent: &ObjectID,
+    filter: &DynamicFieldFilter,
+    cursor: Option<String>,
+    page_size: u32,
+) -> Result<DynamicFieldPage> {
+    let variables = serde_json::json!({
+        "address": parent.to_string(),
+        "first": page_size,
+        "after": cursor,
+    });
+    let data: DynamicFieldsData = GraphQLClient::new(network)
+        .execute(DYNAMIC_FIELDS_QUERY, variables)
+        .await?;
+    data.into_page(parent, filter)
+}
+
+async fn rpc_dynamic_fields(
+    sui_client: &SuiClient,
+    parent: &ObjectID,
+    filter: &DynamicFieldFilter,
+    cursor: Option<ObjectID>,
+    page_size: u32,
+) -> Result<DynamicFieldPage> {
+    let page = sui_client
+        .read_api()
+        .get_dynamic_fields(*parent, cursor, Some(page_size as usize))
+        .await
+        .context("Failed to query dynamic fields")?;
+
+    let mut fields = Vec::new();
+    for info in page.data {
+        let name_type = info.name.type_.to_canonical_string(true);
+        if !filter.matches(&name_type) {
+            continue;
+        }
+        // JSON-RPC的分页结果不含字段值，需要逐个读取Field对象
+        let value = sui_client
+            .read_api()
+            .get_dynamic_field_object(*parent, info.name.clone())
+            .await
+            .context("Failed to query dynamic field object")?
+            .data
+            .and_then(|d| d.content)
+            .and_then(|c| c.try_as_move().map(|o| o.fields.clone().to_json_value()))
+            .map(|mut field| field["value"].take())
+            .unwrap_or(Value::Null);
+        fields.push(DynamicField {
+            name_type,
+            name: info.name.value,
+            value,
+        });
+    }
+
+    Ok(DynamicFieldPage {
+        fields,
+        has_next_page: page.has_next_page,
+        next_cursor: page.next_cursor.map(DynamicFieldCursor::JsonRpc),
+    })
+}
+
+/// 查询对象满足条件的所有动态字段
+pub async fn query_all_dynamic_fields(
+    network: &Network,
+    sui_client: &SuiClient,
+    parent: &ObjectID,
+    filter: &DynamicFieldFilter,
+    page_size: Option<u32>,
+) -> Result<Vec<DynamicField>> {
+    let mut all_fields = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = query_dynamic_fields(network, sui_client, parent, filter, cursor, page_size).await?;
+        all_fields.extend(page.fields);
+
+        if !page.has_next_page {
+            break;
+        }
+        cursor = page.next_cursor;
+    }
+
+    Ok(all_fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dynamic_fields_response() {
+        let response: GraphQLResponse<DynamicFieldsData> = serde_json::from_value(serde_json::json!({
+            "data": {
+                "owner": {
+                    "dynamicFields": {
+                        "pageInfo": { "hasNextPage": true, "endCursor": "abc" },
+                        "nodes": [
+                            {
+                                "name": { "type": { "repr": "0x0000000000000000000000000000000000000000000000000000000000000001::string::String" }, "json": "alice" },
+                                "value": { "json": { "rating": "1000" } }
+                            },
+                            {
+                                "name": { "type": { "repr": "u64" }, "json": "7" },
+                                "value": { "contents": { "type": { "repr": "0x2::object::ID" }, "json": { "id": "0x7" } } }
+                            }
+                        ]
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let parent = ObjectID::ZERO;
+        let page = response
+            .into_data()
+            .unwrap()
+            .into_page(&parent, &DynamicFieldFilter::default())
+            .unwrap();
+        assert_eq!(page.fields.len(), 2);
+        assert_eq!(page.fields[1].value["id"], "0x7");
+        assert_eq!(page.next_cursor, Some(DynamicFieldCursor::GraphQL("abc".to_string())));
+
+        let errors: GraphQLResponse<DynamicFieldsData> =
+            serde_json::from_value(serde_json::json!({ "errors": [{ "message": "boom" }] })).unwrap();
+        assert!(errors.into_data().is_err());
+    }
+
+    #[test]
+    fn test_dynamic_field_filter() {
+        let filter = DynamicFieldFilter::name_type("0x1::string::String").unwrap();
+        assert!(filter.matches("0x0000000000000000000000000000000000000000000000000000000000000001::string::String"));
+        assert!(!filter.matches("u64"));
+        assert!(!filter.matches("not a type"));
+        assert!(DynamicFieldFilter::default().matches("u64"));
+        assert!(DynamicFieldFilter::name_type("::bad").is_err());
+    }
+}
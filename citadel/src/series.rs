@@ -0,0 +1,226 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 多局制系列赛（三局两胜、五局三胜）
+//!
+//! # 概述
+//! 创建私人对局时指定 `bestOf`（3或5），该对局成为系列赛的第一局。每局结束时 `MatchService`
+//! 记录比分，没有玩家达到获胜局数时用相同的玩家、游戏模式和观战设置创建下一局，
+//! 玩家加入新对局并开始后继续比赛。
+//!
+//! - 座位在第一局开始时确定，之后每局轮换一个座位，先手玩家依次交替
+//! - 中间各局只记录比分，不更新评分、统计、评分历史和胜利成就；
+//!   系列赛结束时按最后一局（即系列赛胜利者赢下的一局）统一结算
+//! - 系列赛不支持押注
+//!
+//! 系列赛数据保存在游戏缓存中，各局的游戏数据通过 `seriesId` 关联。
+//!
+//! # 消息格式
+//! - `series:round_end`：一局结束，payload包含seriesId、bestOf、round、matchId、winnerId、
+//!   scores（玩家ID到已赢局数）和nextMatchId（下一局的游戏ID）
+//! - `series:end`：系列赛结束，payload包含seriesId、bestOf、winnerId、scores、rounds和ratingChanges
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::game::{GameCachePrefix, GameService};
+use crate::gaming::{MatchPlayer, UserInfo};
+
+/// 系列赛事件
+pub mod events {
+    pub const ROUND_END: &str = "series:round_end";
+    pub const END: &str = "series:end";
+}
+
+/// 支持的局数
+pub const SUPPORTED_BEST_OF: [u32; 2] = [3, 5];
+
+/// 系列赛状态
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SeriesState {
+    InProgress,
+    Completed,
+}
+
+/// 已结束的一局
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeriesRound {
+    pub match_id: String,
+    pub winner_id: String,
+    pub ended_at: u64,
+}
+
+/// 系列赛数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeriesData {
+    pub id: String,
+    pub best_of: u32,
+    /// 第一局开始时的座位顺序，之后每局轮换
+    pub players: Vec<UserInfo>,
+    /// 玩家ID到已赢局数
+    pub scores: HashMap<String, u32>,
+    pub rounds: Vec<SeriesRound>,
+    /// 当前一局的游戏ID
+    pub current_match_id: String,
+    pub state: SeriesState,
+    pub winner_id: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl SeriesData {
+    /**
+     * 创建系列赛
+     *
+     * 参数:
+     * @param id - 系列赛ID
+     * @param best_of - 局数，只支持3和5
+     * @param first_match_id - 第一局的游戏ID
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 局数不支持时返回错误
+     */
+    pub fn new(id: String, best_of: u32, first_match_id: String, now: u64) -> Result<Self> {
+        if !SUPPORTED_BEST_OF.contains(&best_of) {
+            return Err(anyhow!("不支持的系列赛局数: {}", best_of));
+        }
+        Ok(SeriesData {
+            id,
+            best_of,
+            players: Vec::new(),
+            scores: HashMap::new(),
+            rounds: Vec::new(),
+            current_match_id: first_match_id,
+            state: SeriesState::InProgress,
+            winner_id: None,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    /// 获胜需要赢下的局数
+    pub fn wins_needed(&self) -> u32 {
+        self.best_of / 2 + 1
+    }
+
+    /// 当前是第几局，从1开始
+    pub fn round(&self) -> usize {
+        self.rounds.len() + 1
+    }
+
+    /**
+     * 按当前一局的座位排列玩家
+     *
+     * 第一局开始时记录座位顺序；之后每局把座位向左轮换一位，先手玩家依次交替。
+     * 不在系列赛座位中的玩家排在最后。
+     *
+     * 参数:
+     * @param players - 当前一局的玩家
+     */
+    pub fn seat(&mut self, players: &mut [MatchPlayer]) {
+        if self.players.is_empty() {
+            self.players = players.iter().map(|p| p.user.clone()).collect();
+            return;
+        }
+        let count = self.players.len();
+        let offset = self.rounds.len() % count;
+        let seat_of = |user_id: &str| {
+            self.players
+                .iter()
+                .position(|p| p.id == user_id)
+                .map(|index| (index + count - offset) % count)
+                .unwrap_or(usize::MAX)
+        };
+        players.sort_by_key(|p| seat_of(&p.user.id));
+    }
+
+    /**
+     * 记录一局的胜利者
+     *
+     * 参数:
+     * @param match_id - 结束的游戏ID
+     * @param winner_id - 胜利者ID
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 系列赛是否因此结束
+     */
+    pub fn record_round(&mut self, match_id: &str, winner_id: &str, now: u64) -> bool {
+        let wins = self.scores.entry(winner_id.to_string()).or_insert(0);
+        *wins += 1;
+        let finished = *wins >= self.wins_needed();
+        self.rounds.push(SeriesRound {
+            match_id: match_id.to_string(),
+            winner_id: winner_id.to_string(),
+            ended_at: now,
+        });
+        if finished {
+            self.state = SeriesState::Completed;
+            self.winner_id = Some(winner_id.to_string());
+        }
+        self.updated_at = now;
+        finished
+    }
+}
+
+/// 读取系列赛数据
+pub fn load(game_service: &GameService, series_id: &str) -> Option<SeriesData> {
+    game_service.get(GameCachePrefix::SERIES, series_id)
+}
+
+/// 保存系列赛数据
+pub fn save(game_service: &GameService, series: &SeriesData) -> bool {
+    game_service.set(GameCachePrefix::SERIES, &series.id, series)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(id: &str) -> MatchPlayer {
+        MatchPlayer {
+            user: UserInfo {
+                id: id.to_string(),
+                name: id.to_string(),
+                rating: 1000,
+                avatar_url: None,
+                provisional: false,
+            },
+            hand: Vec::new(),
+            is_active: true,
+            is_winner: false,
+            is_turn: false,
+        }
+    }
+
+    fn ids(players: &[MatchPlayer]) -> Vec<&str> {
+        players.iter().map(|p| p.user.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_best_of_three() {
+        assert!(SeriesData::new("s".into(), 4, "m1".into(), 0).is_err());
+        let mut series = SeriesData::new("s".into(), 3, "m1".into(), 0).unwrap();
+        assert_eq!(series.wins_needed(), 2);
+
+        // 第一局记录座位，之后先手玩家交替
+        let mut players = vec![player("alice"), player("bob")];
+        series.seat(&mut players);
+        assert!(!series.record_round("m1", "alice", 1));
+        let mut players = vec![player("alice"), player("bob")];
+        series.seat(&mut players);
+        assert_eq!(ids(&players), vec!["bob", "alice"]);
+
+        assert!(!series.record_round("m2", "bob", 2));
+        assert_eq!(series.round(), 3);
+        assert!(series.record_round("m3", "alice", 3));
+        assert_eq!(series.state, SeriesState::Completed);
+        assert_eq!(series.winner_id.as_deref(), Some("alice"));
+        assert_eq!(series.scores["bob"], 1);
+    }
+}
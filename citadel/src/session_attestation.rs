@@ -0,0 +1,307 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! WebSocket会话的飞地证明绑定模块
+//!
+//! # 概述
+//! `/get_attestation` 只证明飞地持有某个临时公钥，无法说明某条WebSocket连接另一端就是该飞地实例。
+//! 本模块把证明文档绑定到具体的会话上：
+//! 1. 客户端发送 `session:attest`，携带自己生成的随机挑战 `nonce`
+//! 2. 服务器请求NSM生成证明文档：`public_key` 为临时公钥，`nonce` 为客户端挑战，
+//!    `user_data` 为 `SessionBinding`（会话ID、公钥、挑战、签发时间）的BCS字节，
+//!    并通过 `session:attested` 返回证明文档和绑定信息
+//! 3. 此后服务器发给该会话的每条消息都包装为 `session:signed`：
+//!    `frame` 为原消息的JSON字符串，`seq` 从1开始严格递增，`signature` 为临时私钥的签名
+//!
+//! 客户端验证证明文档的证书链、`public_key`、`nonce` 和 `user_data` 后，即可用其中的公钥验证后续消息，
+//! 并通过会话ID和递增的 `seq` 拒绝来自其他会话的消息和重放的消息。
+//! 签名方式与 `common::to_signed_response` 一致：对 `IntentMessage` 的BCS字节做Ed25519签名，签名以Hex编码。
+//!
+//! 绑定只在当前连接内有效，重连后需要重新请求证明。
+//!
+//! # 消息格式
+//! ```json
+//! // 客户端 -> 服务器
+//! { "event": "session:attest", "data": { "nonce": "<hex, 16~64字节>" } }
+//! // 服务器 -> 客户端
+//! { "event": "session:attested", "data": { "ok": true, "payload": { "attestation": "<hex>", "binding": { "sessionId": "...", "publicKey": "<hex>", "nonce": "<hex>", "issuedAt": 0 } } } }
+//! { "event": "session:signed", "data": { "frame": "{\"event\":...}", "seq": 1, "timestampMs": 0, "signature": "<hex>" } }
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519PublicKey, Ed25519Signature};
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::traits::{KeyPair, Signer, ToFromBytes, VerifyingKey};
+use nsm_api::api::{Request as NsmRequest, Response as NsmResponse};
+use nsm_api::driver;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+use utoipa::ToSchema;
+
+use crate::common::{IntentMessage, IntentScope};
+use crate::externals::current_epoch_time;
+use crate::ws::WsMessage;
+use crate::AppState;
+
+/// 挑战的最小字节数
+pub const MIN_NONCE_BYTES: usize = 16;
+/// 挑战的最大字节数
+pub const MAX_NONCE_BYTES: usize = 64;
+
+/// 会话证明相关事件
+pub mod events {
+    /// 客户端请求会话证明
+    pub const ATTEST: &str = "session:attest";
+    /// 会话证明结果
+    pub const ATTESTED: &str = "session:attested";
+    /// 已证明会话收到的签名消息
+    pub const SIGNED: &str = "session:signed";
+}
+
+/// `session:attest` 数据
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AttestRequest {
+    /// 客户端生成的随机挑战（Hex编码）
+    pub nonce: String,
+}
+
+/// 写入证明文档user_data的会话绑定信息
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionBinding {
+    /// 会话ID（WebSocket客户端ID）
+    pub session_id: String,
+    /// 临时公钥（Hex编码）
+    pub public_key: String,
+    /// 客户端挑战（Hex编码）
+    pub nonce: String,
+    /// 签发时间
+    pub issued_at: u64,
+}
+
+/// 被签名的消息帧
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SignedFrame {
+    session_id: String,
+    seq: u64,
+    frame: String,
+}
+
+/// `session:signed` 数据
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedFrameData {
+    /// 原消息的JSON字符串
+    pub frame: String,
+    /// 会话内严格递增的序号
+    pub seq: u64,
+    /// 签名时间
+    pub timestamp_ms: u64,
+    /// 签名（Hex编码）
+    pub signature: String,
+}
+
+/// 已证明的会话
+#[derive(Debug)]
+pub struct AttestedSession {
+    binding: SessionBinding,
+    seq: AtomicU64,
+}
+
+impl AttestedSession {
+    pub fn new(binding: SessionBinding) -> Self {
+        Self {
+            binding,
+            seq: AtomicU64::new(0),
+        }
+    }
+
+    /// 会话绑定信息
+    pub fn binding(&self) -> &SessionBinding {
+        &self.binding
+    }
+
+    /**
+     * 将消息包装为签名消息
+     *
+     * 参数:
+     * @param kp - 临时密钥对
+     * @param message - 已按客户端语言渲染的消息
+     *
+     * 返回:
+     * `session:signed` 消息
+     */
+    pub fn seal(&self, kp: &Ed25519KeyPair, message: &WsMessage) -> Result<WsMessage> {
+        let seq = self.seq.fetch_add(1, Ordering::SeqCst) + 1;
+        let timestamp_ms = current_epoch_time();
+        let frame = serde_json::to_string(message)?;
+        let signed = IntentMessage::new(
+            SignedFrame {
+                session_id: self.binding.session_id.clone(),
+                seq,
+                frame: frame.clone(),
+            },
+            timestamp_ms,
+            IntentScope::SessionFrame,
+        );
+        let sig: Ed25519Signature = kp.sign(&bcs::to_bytes(&signed)?);
+        Ok(WsMessage {
+            event: events::SIGNED.to_string(),
+            data: Some(serde_json::to_value(SignedFrameData {
+                frame,
+                seq,
+                timestamp_ms,
+                signature: Hex::encode(sig),
+            })?),
+            // 外层同样带上消息ID，客户端不解包也能确认
+            msg_id: message.msg_id,
+        })
+    }
+}
+
+/**
+ * 验证签名消息，供客户端实现参考和测试使用
+ *
+ * 参数:
+ * @param pk - 证明文档中的临时公钥
+ * @param session_id - 会话ID
+ * @param data - `session:signed` 数据
+ *
+ * 返回:
+ * 签名是否有效
+ */
+pub fn verify_frame(pk: &Ed25519PublicKey, session_id: &str, data: &SignedFrameData) -> bool {
+    let signed = IntentMessage::new(
+        SignedFrame {
+            session_id: session_id.to_string(),
+            seq: data.seq,
+            frame: data.frame.clone(),
+        },
+        data.timestamp_ms,
+        IntentScope::SessionFrame,
+    );
+    let Ok(bytes) = bcs::to_bytes(&signed) else {
+        return false;
+    };
+    let Ok(sig) = Hex::decode(&data.signature) else {
+        return false;
+    };
+    let Ok(sig) = Ed25519Signature::from_bytes(&sig) else {
+        return false;
+    };
+    pk.verify(&bytes, &sig).is_ok()
+}
+
+/// 解析并校验客户端挑战
+pub fn parse_nonce(data: Option<&serde_json::Value>) -> Result<Vec<u8>> {
+    let request: AttestRequest = data
+        .cloned()
+        .map(serde_json::from_value)
+        .transpose()?
+        .ok_or_else(|| anyhow!("缺少挑战nonce"))?;
+    let nonce = Hex::decode(&request.nonce).map_err(|_| anyhow!("挑战必须是Hex编码"))?;
+    if !(MIN_NONCE_BYTES..=MAX_NONCE_BYTES).contains(&nonce.len()) {
+        return Err(anyhow!("挑战长度必须在 {} 到 {} 字节之间", MIN_NONCE_BYTES, MAX_NONCE_BYTES));
+    }
+    Ok(nonce)
+}
+
+/// 请求NSM生成绑定会话的证明文档
+fn attestation_document(public_key: &[u8], user_data: Vec<u8>, nonce: Vec<u8>) -> Result<Vec<u8>> {
+    let fd = driver::nsm_init();
+    let request = NsmRequest::Attestation {
+        user_data: Some(ByteBuf::from(user_data)),
+        nonce: Some(ByteBuf::from(nonce)),
+        public_key: Some(ByteBuf::from(public_key.to_vec())),
+    };
+    let response = driver::nsm_process_request(fd, request);
+    driver::nsm_exit(fd);
+    match response {
+        NsmResponse::Attestation { document } => Ok(document),
+        other => Err(anyhow!("NSM证明请求失败: {:?}", other)),
+    }
+}
+
+/// 全局飞地状态，持有临时密钥对
+static GLOBAL_ENCLAVE_STATE: OnceCell<Arc<AppState>> = OnceCell::new();
+
+/// 初始化会话证明，需要在WebSocket服务启动前调用
+pub fn init_session_attestation(state: Arc<AppState>) {
+    let _ = GLOBAL_ENCLAVE_STATE.set(state);
+}
+
+/// 获取临时密钥对，未初始化时返回None
+pub fn enclave_keypair() -> Option<&'static Ed25519KeyPair> {
+    GLOBAL_ENCLAVE_STATE.get().map(|state| &state.eph_kp)
+}
+
+/**
+ * 为会话生成绑定的证明文档
+ *
+ * 参数:
+ * @param session_id - 会话ID
+ * @param data - `session:attest` 数据
+ *
+ * 返回:
+ * 证明文档和已证明的会话
+ */
+pub fn attest_session(session_id: &str, data: Option<&serde_json::Value>) -> Result<(Vec<u8>, AttestedSession)> {
+    let kp = enclave_keypair().ok_or_else(|| anyhow!("会话证明尚未初始化"))?;
+    let nonce = parse_nonce(data)?;
+    let public_key = kp.public().as_bytes().to_vec();
+    let binding = SessionBinding {
+        session_id: session_id.to_string(),
+        public_key: Hex::encode(&public_key),
+        nonce: Hex::encode(&nonce),
+        issued_at: current_epoch_time(),
+    };
+    let document = attestation_document(&public_key, bcs::to_bytes(&binding)?, nonce)?;
+    Ok((document, AttestedSession::new(binding)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_nonce() {
+        assert!(parse_nonce(None).is_err());
+        assert!(parse_nonce(Some(&json!({ "nonce": "zz" }))).is_err());
+        assert!(parse_nonce(Some(&json!({ "nonce": Hex::encode([1u8; 8]) }))).is_err());
+        assert!(parse_nonce(Some(&json!({ "nonce": Hex::encode([1u8; 65]) }))).is_err());
+        assert_eq!(parse_nonce(Some(&json!({ "nonce": Hex::encode([1u8; 32]) }))).unwrap(), vec![1u8; 32]);
+    }
+
+    #[test]
+    fn test_seal_and_verify_frame() {
+        let kp = AppState::generate_keypair(Some(11));
+        let session = AttestedSession::new(SessionBinding {
+            session_id: "s1".to_string(),
+            public_key: Hex::encode(kp.public().as_bytes()),
+            nonce: Hex::encode([0u8; 16]),
+            issued_at: 1,
+        });
+        let message = WsMessage {
+            event: "room_joined".to_string(),
+            data: Some(json!({ "ok": true })),
+            msg_id: None,
+        };
+
+        let first: SignedFrameData = serde_json::from_value(session.seal(&kp, &message).unwrap().data.unwrap()).unwrap();
+        let second: SignedFrameData = serde_json::from_value(session.seal(&kp, &message).unwrap().data.unwrap()).unwrap();
+        assert_eq!((first.seq, second.seq), (1, 2));
+        assert!(verify_frame(kp.public(), "s1", &first));
+        assert_eq!(serde_json::from_str::<WsMessage>(&first.frame).unwrap().event, "room_joined");
+
+        // 其他会话或被篡改的消息无法通过验证
+        assert!(!verify_frame(kp.public(), "s2", &first));
+        let mut tampered = second.clone();
+        tampered.seq = 3;
+        assert!(!verify_frame(kp.public(), "s1", &tampered));
+    }
+}
@@ -19,6 +19,7 @@ use axum::{extract::State, http::HeaderMap, Json};
 use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use once_cell::sync::Lazy;
 
 use crypto::elgamal::{encrypt};
 use crypto::ibe;
@@ -27,19 +28,21 @@ use fastcrypto::encoding::{Base64, Encoding};
 use fastcrypto::traits::Signer;
 use rand::thread_rng;
 use std::sync::Arc;
-use std::time::Duration;
 
-use sui_sdk::rpc_types::SuiTransactionBlockEffectsAPI;
+use sui_sdk::rpc_types::{SuiObjectDataFilter, SuiObjectResponseQuery, SuiTransactionBlockEffectsAPI};
 use sui_sdk::types::base_types::{ObjectID, SuiAddress};
+use sui_sdk::types::parse_sui_struct_tag;
 use sui_sdk::types::signature::GenericSignature;
 use sui_sdk::types::transaction::{Command, Argument, CallArg, ProgrammableTransaction, TransactionKind};
 use sui_sdk::verify_personal_message_signature::verify_personal_message_signature;
 use tap::TapFallible;
 use tracing::{debug, info, warn,error};
 
-use crate::errors::InternalError;
-use crate::externals::{current_epoch_time, fetch_first_and_last_pkg_id};
-use crate::keys::{check_request, Certificate};
+use crate::account_link;
+use crate::errors::{ErrorResponse, InternalError};
+use crate::externals::{current_epoch_time, fetch_first_and_last_pkg_id, get_current_epoch};
+use crate::keys::{certificate_time_is_valid, check_request, Certificate};
+use crate::signed_message::zklogin_session_message;
 use crate::metrics::call_with_duration;
 use crate::metrics::Metrics;
 use crate::types::{ElGamalPublicKey, ElgamalVerificationKey, ElgamalEncryption, MasterKeyPOP, GAS_BUDGET};
@@ -57,6 +60,7 @@ use crate::sdk::create_profile_for_passport;
 use crate::sdk::Profile;  // 从sdk模块直接导入Profile类型
 use hex;
 use tower_sessions::{Session, Expiry};
+use utoipa::ToSchema;
 use uuid::Uuid;
 use axum::extract::Extension;
 
@@ -97,11 +101,13 @@ pub struct AuthenticatedUser {
  * 
  * 存储在 session 中的用户数据
  */
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct SessionUser {
+    #[schema(value_type = String)]
     pub user_address: SuiAddress,  // 用户地址
     pub session_vk: String,        // 会话验证密钥
     pub exp: u64,                  // 过期时间
+    #[schema(value_type = Option<Object>)]
     pub profile: Option<Profile>, // 用户档案
 }
 
@@ -223,39 +229,46 @@ pub fn verify_auth_token(app_state: &Arc<AppState>, token: &str) -> Result<Authe
     })
 } 
 
-/// 允许的全节点数据过时时间
-/// 设置此持续时间时，注意Sui上的时间戳可能比当前时间稍晚，但不应超过一秒。
-const ALLOWED_STALENESS: Duration = Duration::from_secs(120);
-
 /**
  * 获取密钥请求结构
  *
  * 客户端发送此请求以获取解密密钥
  * 包含签名的请求数据和验证信息
  */
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct SessionTokenRequest {
     // 以下字段必须签名，以防止他人代表用户发送请求并能够获取密钥
     ptb: String, // 必须遵循特定结构，参见ValidPtb
     // 我们不想仅依靠HTTPS来限制对此用户的响应，因为在多个服务的情况下，
     // 一个服务可以对另一个服务进行重放攻击以获取其他服务的密钥。
+    #[schema(value_type = String)]
     enc_key: ElGamalPublicKey,                    // ElGamal加密公钥
+    #[schema(value_type = String)]
     enc_verification_key: ElgamalVerificationKey, // ElGamal验证密钥
+    #[schema(value_type = String)]
     request_signature: Ed25519Signature,          // 请求签名
     certificate: Certificate,                     // 用户会话证书
 }
 
+impl SessionTokenRequest {
+    /// 用户会话证书
+    pub fn certificate(&self) -> &Certificate {
+        &self.certificate
+    }
+}
+
 
 /**
  * 会话令牌响应结构
  *
  * 服务器返回的授权令牌，包含加密的证书信息
  */
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct SessionTokenResponse {
     pub auth_token: String, // JWT格式的授权令牌
     pub expires_at: u64,    // 令牌过期时间（Unix时间戳，毫秒）
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
     pub profile: Option<Profile>,
 }
 
@@ -314,13 +327,141 @@ fn create_session_token_response(
     }
 }
 
-/// 处理获取密钥的核心逻辑
-async fn handle_session_token_core(
+/// 钱包已关联到其他档案时，返回关联的档案
+async fn linked_session_profile(app_state: &Arc<AppState>, user: SuiAddress) -> Option<Profile> {
+    let linked_profile_id = account_link::global_account_link_service()
+        .and_then(|service| service.linked_user(&user.to_string()))
+        .and_then(|id| ObjectID::from_hex_literal(&id).ok());
+    match linked_profile_id {
+        Some(profile_id) => match app_state.game_manager.get_profile(&profile_id).await {
+            Ok(profile) => {
+                info!("钱包 {} 已关联到档案 {:?}", user, profile_id);
+                Some(profile)
+            },
+            Err(e) => {
+                error!("获取关联档案数据失败: {:?}", e);
+                None
+            }
+        },
+        None => None,
+    }
+}
+
+/**
+ * 查找登录用户的档案，护照还没有档案时为其创建
+ *
+ * 已关联到其他档案的钱包直接使用关联的档案
+ *
+ * 参数:
+ * @param app_state - 应用状态
+ * @param user - 登录的钱包地址
+ * @param passport_id - 护照ID
+ *
+ * 返回:
+ * 用户档案，查找和创建都失败时为None；护照ID格式无效时返回错误
+ */
+pub(crate) async fn resolve_session_profile(
+    app_state: &Arc<AppState>,
+    user: SuiAddress,
+    passport_id: &str,
+) -> Result<Option<Profile>, InternalError> {
+    let svg = make_avatar(passport_id);
+    let svg_base64 = Base64::encode(svg.as_bytes());
+    let avatar_data = format!("data:image/svg+xml;base64,{}", svg_base64);
+    info!("头像生成完成");
+
+    // 已关联到其他档案的钱包直接使用关联的档案，不再按护照查找或创建
+    let profile = match linked_session_profile(app_state, user).await {
+        Some(profile) => Some(profile),
+        None => match ObjectID::from_hex_literal(passport_id) {
+            Ok(passport_obj_id) => {
+                info!("护照ID转换为ObjectID成功: {:?}", passport_obj_id);
+                match app_state.game_manager.get_profile_id_by_passport(&passport_obj_id).await {
+                    Ok(profile_id) => {
+                        info!("找到现有档案ID: {:?}", profile_id);
+                        match app_state.game_manager.get_profile(&profile_id).await {
+                            Ok(profile) => {
+                                info!("成功获取现有档案: {:?}", profile);
+                                Some(profile)
+                            },
+                            Err(e) => {
+                                error!("获取档案数据失败: {:?}", e);
+                                None
+                            }
+                        }
+                    },
+                    Err(_) => {
+                        info!("未找到现有档案，开始创建新档案...");
+                        match create_profile_for_passport(
+                            app_state,
+                            passport_id,
+                            &avatar_data,
+                        ).await {
+                            Ok(_) => {
+                                info!("新档案创建成功");
+                                match ObjectID::from_hex_literal(passport_id) {
+                                    Ok(passport_obj_id) => {
+                                        info!("重新获取新创建的档案...");
+                                        match app_state.game_manager.get_profile_id_by_passport(&passport_obj_id).await {
+                                            Ok(profile_id) => {
+                                                info!("获取到新档案ID: {:?}", profile_id);
+                                                match app_state.game_manager.get_profile(&profile_id).await {
+                                                    Ok(profile) => {
+                                                        info!("成功获取新创建的档案: {:?}", profile);
+                                                        Some(profile)
+                                                    },
+                                                    Err(e) => {
+                                                        error!("获取新档案数据失败: {:?}", e);
+                                                        None
+                                                    }
+                                                }
+                                            },
+                                            Err(e) => {
+                                                error!("获取新档案ID失败: {:?}", e);
+                                                None
+                                            }
+                                        }
+                                    },
+                                    Err(e) => {
+                                        error!("护照ID格式无效: {:?}", e);
+                                        None
+                                    }
+                                }
+                            },
+                            Err(e) => {
+                                error!("创建档案失败: {:?}", e);
+                                None
+                            }
+                        }
+                    }
+                }
+            },
+            Err(e) => {
+                error!("护照ID格式无效: {:?}", e);
+                return Err(InternalError::InvalidPTB);
+            }
+        },
+    };
+
+    Ok(profile)
+}
+
+/**
+ * 验证钱包登录请求并提取护照ID
+ *
+ * 参数:
+ * @param app_state - 应用状态
+ * @param headers - 请求头
+ * @param payload - 登录请求
+ *
+ * 返回:
+ * 请求中的护照ID
+ */
+pub(crate) async fn verify_session_request(
     app_state: &Arc<AppState>,
     headers: &HeaderMap,
     payload: &SessionTokenRequest,
-    session: &Session,
-) -> Result<SessionTokenResponse, InternalError> {
+) -> Result<String, InternalError> {
     let req_id = headers
         .get("Request-Id")
         .map(|v| v.to_str().unwrap_or_default());
@@ -333,7 +474,7 @@ async fn handle_session_token_core(
     
     app_state.metrics.observe_request("session_token");
     info!("检查全节点状态...");
-    app_state.check_full_node_is_fresh(ALLOWED_STALENESS)?;
+    app_state.check_full_node_is_fresh()?;
     
     let valid_function = format!("{}::{}::{}",&app_state.config["CITADEL_PACKAGE"],"citadel","seal_approve_verify_nexus_passport");
     info!("验证函数名称: {}", valid_function);
@@ -386,91 +527,56 @@ async fn handle_session_token_core(
     let bytes = valid_ptb.inner_ids().first().unwrap().to_vec();
     let passport_id = format!("0x{}", hex::encode(&bytes));
     info!("提取护照ID: {}", passport_id);
+    Ok(passport_id)
+}
 
-    let svg = make_avatar(&passport_id);
-    let svg_base64 = Base64::encode(svg.as_bytes());
-    let avatar_data = format!("data:image/svg+xml;base64,{}", svg_base64);
-    info!("头像生成完成");
+/// 处理获取密钥的核心逻辑
+async fn handle_session_token_core(
+    app_state: &Arc<AppState>,
+    headers: &HeaderMap,
+    payload: &SessionTokenRequest,
+    session: &Session,
+) -> Result<SessionTokenResponse, InternalError> {
+    let passport_id = verify_session_request(app_state, headers, payload).await?;
+    let profile = resolve_session_profile(app_state, payload.certificate.user, &passport_id).await?;
+    let response = start_session(app_state, &payload.certificate, profile, session).await?;
 
-    let profile = match ObjectID::from_hex_literal(&passport_id) {
-        Ok(passport_obj_id) => {
-            info!("护照ID转换为ObjectID成功: {:?}", passport_obj_id);
-            match app_state.game_manager.get_profile_id_by_passport(&passport_obj_id).await {
-                Ok(profile_id) => {
-                    info!("找到现有档案ID: {:?}", profile_id);
-                    match app_state.game_manager.get_profile(&profile_id).await {
-                        Ok(profile) => {
-                            info!("成功获取现有档案: {:?}", profile);
-                            Some(profile)
-                        },
-                        Err(e) => {
-                            error!("获取档案数据失败: {:?}", e);
-                            None
-                        }
-                    }
-                },
-                Err(_) => {
-                    info!("未找到现有档案，开始创建新档案...");
-                    match create_profile_for_passport(
-                        app_state,
-                        &passport_id,
-                        &avatar_data,
-                    ).await {
-                        Ok(_) => {
-                            info!("新档案创建成功");
-                            match ObjectID::from_hex_literal(&passport_id) {
-                                Ok(passport_obj_id) => {
-                                    info!("重新获取新创建的档案...");
-                                    match app_state.game_manager.get_profile_id_by_passport(&passport_obj_id).await {
-                                        Ok(profile_id) => {
-                                            info!("获取到新档案ID: {:?}", profile_id);
-                                            match app_state.game_manager.get_profile(&profile_id).await {
-                                                Ok(profile) => {
-                                                    info!("成功获取新创建的档案: {:?}", profile);
-                                                    Some(profile)
-                                                },
-                                                Err(e) => {
-                                                    error!("获取新档案数据失败: {:?}", e);
-                                                    None
-                                                }
-                                            }
-                                        },
-                                        Err(e) => {
-                                            error!("获取新档案ID失败: {:?}", e);
-                                            None
-                                        }
-                                    }
-                                },
-                                Err(e) => {
-                                    error!("护照ID格式无效: {:?}", e);
-                                    None
-                                }
-                            }
-                        },
-                        Err(e) => {
-                            error!("创建档案失败: {:?}", e);
-                            None
-                        }
-                    }
-                }
-            }
-        },
-        Err(e) => {
-            error!("护照ID格式无效: {:?}", e);
-            return Err(InternalError::InvalidPTB);
-        }
-    };
+    info!("会话令牌处理完成");
+    Ok(response)
+}
+
+/**
+ * 签发授权令牌并写入会话
+ *
+ * 参数:
+ * @param app_state - 应用状态
+ * @param certificate - 已验证的用户证书
+ * @param profile - 用户档案
+ * @param session - 当前会话
+ *
+ * 返回:
+ * 包含授权令牌和档案的响应
+ */
+pub(crate) async fn start_session(
+    app_state: &Arc<AppState>,
+    certificate: &Certificate,
+    profile: Option<Profile>,
+    session: &Session,
+) -> Result<SessionTokenResponse, InternalError> {
+    // 被封禁的地址或用户不能建立会话
+    let profile_id = profile.as_ref().map(|p| p.id.to_string());
+    crate::ban::check_login(&certificate.user, profile_id.as_deref())?;
 
     let mut response = create_session_token_response(
         app_state,
-        &payload.certificate,
+        certificate,
         profile.clone(),
     );
 
     // 设置 session
     let session_user = SessionUser {
-        user_address: payload.certificate.user,
-        session_vk: Base64::encode(payload.certificate.session_vk.clone()),
+        user_address: certificate.user,
+        session_vk: Base64::encode(certificate.session_vk.clone()),
         exp: response.expires_at / 1000, // 转换为秒
         profile: profile.clone(),
     };
@@ -479,12 +585,21 @@ async fn handle_session_token_core(
     if let Some(profile_data) = profile {
         response.profile = Some(profile_data);
     }
-
-    info!("会话令牌处理完成");
     Ok(response)
 }
 
 /// 原始的session token处理函数
+#[utoipa::path(
+    post,
+    path = "/auth/session_token",
+    tag = "auth",
+    request_body = SessionTokenRequest,
+    responses(
+        (status = 200, description = "登录成功，返回授权令牌并写入会话", body = SessionTokenResponse),
+        (status = 403, description = "签名或证书校验失败，或地址、用户已被封禁", body = ErrorResponse),
+        (status = 503, description = "全节点不可用或数据过时（可按Retry-After重试）", body = ErrorResponse),
+    )
+)]
 #[axum::debug_handler]
 pub async fn handle_session_token(
     State(app_state): State<Arc<AppState>>,
@@ -500,8 +615,172 @@ pub async fn handle_session_token(
 
 
 
+/// 默认允许的zkLogin身份提供方
+pub const DEFAULT_ZKLOGIN_ISSUERS: [&str; 2] = ["https://accounts.google.com", "https://id.twitch.tv/oauth2"];
+
+/**
+ * zkLogin登录配置
+ *
+ * 字段:
+ * @field allowed_issuers - 允许的OpenID身份提供方（JWT的iss）
+ * @field passport_type - 护照对象的完整类型，配置后按zkLogin地址拥有的护照查找档案
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZkLoginConfig {
+    pub allowed_issuers: Vec<String>,
+    pub passport_type: Option<String>,
+}
+
+impl ZkLoginConfig {
+    /// 从环境变量读取配置，ZKLOGIN_ALLOWED_ISSUERS以逗号分隔
+    pub fn from_env() -> Self {
+        let allowed_issuers = std::env::var("ZKLOGIN_ALLOWED_ISSUERS")
+            .ok()
+            .map(|v| {
+                v.split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| DEFAULT_ZKLOGIN_ISSUERS.iter().map(|s| s.to_string()).collect());
+        let passport_type = std::env::var("ZKLOGIN_PASSPORT_TYPE")
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        Self { allowed_issuers, passport_type }
+    }
+
+    /// 是否允许该身份提供方
+    pub fn allows_issuer(&self, iss: &str) -> bool {
+        self.allowed_issuers.iter().any(|allowed| allowed == iss)
+    }
+}
+
+static ZKLOGIN_CONFIG: Lazy<ZkLoginConfig> = Lazy::new(ZkLoginConfig::from_env);
+
+/**
+ * zkLogin登录请求结构
+ *
+ * 证书中的地址为zkLogin地址，签名为zkLogin签名，
+ * 签名的消息见 `signed_message::zklogin_session_message`
+ */
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ZkLoginRequest {
+    certificate: Certificate, // 用户会话证书
+}
+
+/// 查找地址拥有的护照对象
+async fn find_owned_passport(app_state: &AppState, owner: SuiAddress, passport_type: &str) -> Option<ObjectID> {
+    let struct_tag = parse_sui_struct_tag(passport_type)
+        .tap_err(|e| warn!("护照类型配置无效: {:?}", e))
+        .ok()?;
+    let query = SuiObjectResponseQuery::new_with_filter(SuiObjectDataFilter::StructType(struct_tag));
+    let page = app_state
+        .sui_pool
+        .client()
+        .read_api()
+        .get_owned_objects(owner, Some(query), None, Some(1))
+        .await
+        .tap_err(|e| warn!("查询地址 {} 的护照失败: {:?}", owner, e))
+        .ok()?;
+    page.data.first().and_then(|object| object.object_id().ok())
+}
+
+/// 处理zkLogin登录的核心逻辑
+async fn handle_zklogin_core(
+    app_state: &Arc<AppState>,
+    payload: &ZkLoginRequest,
+    session: &Session,
+) -> Result<SessionTokenResponse, InternalError> {
+    app_state.metrics.observe_request("zklogin");
+    app_state.check_full_node_is_fresh()?;
+
+    let cert = &payload.certificate;
+    let GenericSignature::ZkLoginAuthenticator(authenticator) = &cert.signature else {
+        debug!("zkLogin登录请求的签名不是zkLogin签名: {:?}", cert.user);
+        return Err(InternalError::InvalidSignature);
+    };
+    if !certificate_time_is_valid(cert) {
+        return Err(InternalError::InvalidCertificate);
+    }
+
+    let config = &*ZKLOGIN_CONFIG;
+    let iss = authenticator.get_iss();
+    if !config.allows_issuer(iss) {
+        warn!("拒绝未允许的zkLogin身份提供方: {}", iss);
+        return Err(InternalError::Unauthorized);
+    }
+
+    // 临时密钥只在max_epoch之前有效
+    let epoch = get_current_epoch(app_state.sui_pool.client())
+        .await
+        .map_err(|e| {
+            warn!("获取当前纪元失败: {:?}", e);
+            InternalError::Failure
+        })?;
+    if authenticator.get_max_epoch() < epoch {
+        debug!("zkLogin临时密钥已过期: max_epoch {}, 当前纪元 {}", authenticator.get_max_epoch(), epoch);
+        return Err(InternalError::InvalidCertificate);
+    }
+
+    // 验证zkLogin签名，同时校验证明与地址一致
+    let msg = zklogin_session_message(&cert.user, &cert.session_vk, cert.creation_time, cert.ttl_min);
+    verify_personal_message_signature(
+        cert.signature.clone(),
+        msg.as_bytes(),
+        cert.user,
+        Some(app_state.sui_pool.client()),
+    )
+    .await
+    .map_err(|e| {
+        debug!("zkLogin签名验证失败: {:?}", e);
+        InternalError::InvalidSignature
+    })?;
+    info!("zkLogin签名验证通过: {} ({})", cert.user, iss);
+
+    // 按地址拥有的护照查找或创建档案，没有护照时只能使用关联的档案
+    let passport_id = match &config.passport_type {
+        Some(passport_type) => find_owned_passport(app_state, cert.user, passport_type).await,
+        None => None,
+    };
+    let profile = match passport_id {
+        Some(passport_id) => resolve_session_profile(app_state, cert.user, &passport_id.to_string()).await?,
+        None => linked_session_profile(app_state, cert.user).await,
+    };
+    if profile.is_none() {
+        info!("zkLogin地址 {} 没有护照档案", cert.user);
+    }
+
+    start_session(app_state, cert, profile, session).await
+}
+
+/// zkLogin登录
+#[utoipa::path(
+    post,
+    path = "/auth/zklogin",
+    tag = "auth",
+    request_body = ZkLoginRequest,
+    responses(
+        (status = 200, description = "登录成功，返回授权令牌并写入会话", body = SessionTokenResponse),
+        (status = 403, description = "签名、证书或身份提供方校验失败，临时密钥已过期，或地址、用户已被封禁", body = ErrorResponse),
+        (status = 503, description = "全节点不可用或数据过时（可按Retry-After重试）", body = ErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+pub async fn handle_zklogin(
+    State(app_state): State<Arc<AppState>>,
+    Extension(session): Extension<Session>,
+    Json(payload): Json<ZkLoginRequest>,
+) -> Result<Json<SessionTokenResponse>, InternalError> {
+    handle_zklogin_core(&app_state, &payload, &session)
+        .await
+        .map(Json)
+        .tap_err(|e| app_state.metrics.observe_error(e.as_str()))
+}
+
 /// 获取用户Profile响应结构
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct AuthCredentialsResponse {
     pub success: bool,
     pub credentials: Option<SessionUser>,
@@ -513,6 +792,15 @@ pub struct AuthCredentialsResponse {
  * 
  * 在被 session 中间件保护的路由中使用
  */
+#[utoipa::path(
+    get,
+    path = "/auth/credentials",
+    tag = "auth",
+    responses(
+        (status = 200, description = "当前会话中的用户信息", body = AuthCredentialsResponse),
+        (status = 403, description = "未登录", body = ErrorResponse),
+    )
+)]
 #[axum::debug_handler]
 pub async fn get_session_credentials(
     State(app_state): State<Arc<AppState>>,
@@ -541,13 +829,19 @@ pub async fn get_session_credentials(
 }
 
 /// 退出登录响应结构
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct LogoutResponse {
     pub success: bool,
     pub message: String,
 }
 
 /// 退出登录接口
+#[utoipa::path(
+    post,
+    path = "/auth/session_logout",
+    tag = "auth",
+    responses((status = 200, description = "已退出登录", body = LogoutResponse))
+)]
 #[axum::debug_handler]
 pub async fn handler_session_logout(
     Extension(session): Extension<Session>,
@@ -563,10 +857,56 @@ pub async fn handler_session_logout(
     }))
 } 
 
+/// 撤销会话响应结构
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RevokeSessionsResponse {
+    pub success: bool,
+    pub revoked: usize,
+    pub message: String,
+}
+
+/**
+ * 撤销当前用户的所有会话（退出所有设备）
+ *
+ * 包括当前会话在内，该用户地址下的所有会话都会从会话存储中删除
+ */
+#[utoipa::path(
+    post,
+    path = "/auth/session_revoke_all",
+    tag = "auth",
+    responses(
+        (status = 200, description = "已撤销该用户的所有会话", body = RevokeSessionsResponse),
+        (status = 403, description = "未登录", body = ErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+pub async fn handler_session_revoke_all(
+    State(app_state): State<Arc<AppState>>,
+    Extension(session): Extension<Session>,
+) -> Result<Json<RevokeSessionsResponse>, InternalError> {
+    app_state.metrics.observe_request("session_revoke_all");
+
+    let user = session.get::<SessionUser>(SESSION_USER_KEY).await?
+        .ok_or(InternalError::Unauthorized)?;
+    let store = crate::session_store::global_session_store().ok_or(InternalError::Failure)?;
+
+    let revoked = store.revoke_user_sessions(&user.user_address.to_string()).await;
+    // 当前会话也需要清空，避免本次请求结束时被重新写回
+    session.flush().await?;
+
+    Ok(Json(RevokeSessionsResponse {
+        success: true,
+        revoked,
+        message: "已退出所有设备".to_string(),
+    }))
+}
+
 /// 注册认证路由
 pub fn register_auth_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
     router
         .route("/auth/session_token", post(handle_session_token))
+        .route("/auth/zklogin", post(handle_zklogin))
         .route("/auth/session_logout", post(handler_session_logout))
+        .route("/auth/session_revoke_all", post(handler_session_revoke_all))
         .route("/auth/credentials", get(get_session_credentials))
 }
\ No newline at end of file
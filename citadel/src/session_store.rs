@@ -0,0 +1,341 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 会话存储模块
+//!
+//! # 概述
+//! tower_sessions自带的MemoryStore只在进程内存中保存会话，重启后所有用户都会被登出，
+//! 多个实例之间也无法共享会话。本模块提供可配置的会话存储：
+//! - `memory`：仅内存保存（默认，与之前的行为一致）
+//! - `file`：每个会话以JSON文件保存在指定目录下，重启后自动恢复，
+//!   多个实例挂载同一目录即可共享会话
+//!
+//! 两种模式都支持按用户地址撤销会话，供认证模块实现“退出所有设备”。
+//!
+//! # 配置
+//! - `SESSION_STORE`：`memory` 或 `file`，默认 `memory`
+//! - `SESSION_STORE_PATH`：`file` 模式下的存储目录，默认 `./data/sessions`
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use once_cell::sync::OnceCell;
+use time::OffsetDateTime;
+use tower_sessions::session::{Id, Record};
+use tower_sessions::session_store::{self, ExpiredDeletion, SessionStore};
+use tracing::{debug, error, info, warn};
+
+/// 默认的会话存储目录
+pub const DEFAULT_SESSION_STORE_PATH: &str = "./data/sessions";
+/// 过期会话清理间隔
+pub const EXPIRED_SESSION_CLEANUP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// 会话存储类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionStoreKind {
+    /// 仅内存
+    Memory,
+    /// 文件持久化
+    File(PathBuf),
+}
+
+impl SessionStoreKind {
+    /// 从环境变量读取会话存储配置
+    pub fn from_env() -> Self {
+        let kind = std::env::var("SESSION_STORE").unwrap_or_default();
+        match kind.trim().to_lowercase().as_str() {
+            "file" => {
+                let path = std::env::var("SESSION_STORE_PATH")
+                    .ok()
+                    .filter(|p| !p.is_empty())
+                    .unwrap_or_else(|| DEFAULT_SESSION_STORE_PATH.to_string());
+                SessionStoreKind::File(PathBuf::from(path))
+            }
+            "" | "memory" => SessionStoreKind::Memory,
+            other => {
+                warn!("未知的会话存储类型: {}，使用内存存储", other);
+                SessionStoreKind::Memory
+            }
+        }
+    }
+}
+
+/// Citadel会话存储
+///
+/// 内存中始终保留一份会话副本；配置了目录时，会话同时写入磁盘，
+/// 加载时以磁盘数据为准，以便多个实例共享同一目录。
+#[derive(Debug, Clone)]
+pub struct CitadelSessionStore {
+    /// 持久化目录，None表示仅内存
+    dir: Option<PathBuf>,
+    /// 内存中的会话副本
+    records: Arc<DashMap<Id, Record>>,
+}
+
+impl CitadelSessionStore {
+    /// 创建仅内存的会话存储
+    pub fn memory() -> Self {
+        Self {
+            dir: None,
+            records: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// 打开文件会话存储，加载目录中所有未过期的会话
+    pub async fn open(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        tokio::fs::create_dir_all(&dir).await?;
+
+        let store = Self {
+            dir: Some(dir.clone()),
+            records: Arc::new(DashMap::new()),
+        };
+
+        let now = OffsetDateTime::now_utc();
+        let mut loaded = 0;
+        for record in store.read_all_from_disk().await? {
+            if record.expiry_date > now {
+                store.records.insert(record.id, record);
+                loaded += 1;
+            } else {
+                store.remove_file(&record.id).await;
+            }
+        }
+        info!("从 {:?} 加载了 {} 个会话", dir, loaded);
+        Ok(store)
+    }
+
+    /// 根据配置创建会话存储
+    pub async fn from_kind(kind: &SessionStoreKind) -> anyhow::Result<Self> {
+        match kind {
+            SessionStoreKind::Memory => Ok(Self::memory()),
+            SessionStoreKind::File(dir) => Self::open(dir).await,
+        }
+    }
+
+    /// 是否持久化到磁盘
+    pub fn is_persistent(&self) -> bool {
+        self.dir.is_some()
+    }
+
+    /// 检查存储后端是否可用，文件模式下在存储目录中写入并删除一个探测文件
+    pub async fn check_health(&self) -> anyhow::Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let probe = dir.join(".readyz_probe");
+        tokio::fs::write(&probe, b"ok").await?;
+        tokio::fs::remove_file(&probe).await?;
+        Ok(())
+    }
+
+    fn record_path(dir: &Path, id: &Id) -> PathBuf {
+        dir.join(format!("{}.json", id))
+    }
+
+    async fn read_record(path: &Path) -> Option<Record> {
+        let bytes = tokio::fs::read(path).await.ok()?;
+        match serde_json::from_slice::<Record>(&bytes) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                warn!("解析会话文件 {:?} 失败: {}", path, e);
+                None
+            }
+        }
+    }
+
+    async fn read_all_from_disk(&self) -> anyhow::Result<Vec<Record>> {
+        let Some(dir) = &self.dir else {
+            return Ok(Vec::new());
+        };
+        let mut records = Vec::new();
+        let mut entries = tokio::fs::read_dir(dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            if let Some(record) = Self::read_record(&path).await {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
+    async fn write_file(&self, record: &Record) -> session_store::Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let json = serde_json::to_vec(record)
+            .map_err(|e| session_store::Error::Encode(e.to_string()))?;
+        // 先写临时文件再重命名，避免读取到写了一半的文件
+        let path = Self::record_path(dir, &record.id);
+        let tmp_path = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp_path, json)
+            .await
+            .map_err(|e| session_store::Error::Backend(e.to_string()))?;
+        tokio::fs::rename(&tmp_path, &path)
+            .await
+            .map_err(|e| session_store::Error::Backend(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn remove_file(&self, id: &Id) {
+        if let Some(dir) = &self.dir {
+            let path = Self::record_path(dir, id);
+            if let Err(e) = tokio::fs::remove_file(&path).await {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    error!("删除会话文件 {:?} 失败: {}", path, e);
+                }
+            }
+        }
+    }
+
+    /// 获取所有会话（持久化模式下从磁盘读取）
+    async fn all_records(&self) -> Vec<Record> {
+        if self.dir.is_some() {
+            match self.read_all_from_disk().await {
+                Ok(records) => return records,
+                Err(e) => error!("读取会话目录失败: {}", e),
+            }
+        }
+        self.records.iter().map(|r| r.value().clone()).collect()
+    }
+
+    /**
+     * 撤销满足条件的所有会话
+     *
+     * 参数:
+     * @param predicate - 判断会话是否需要撤销
+     *
+     * 返回:
+     * 被撤销的会话数量
+     */
+    pub async fn revoke_where<F>(&self, predicate: F) -> usize
+    where
+        F: Fn(&Record) -> bool,
+    {
+        let mut revoked = 0;
+        for record in self.all_records().await {
+            if predicate(&record) {
+                self.records.remove(&record.id);
+                self.remove_file(&record.id).await;
+                revoked += 1;
+            }
+        }
+        revoked
+    }
+
+    /// 撤销指定用户地址的所有会话
+    pub async fn revoke_user_sessions(&self, user_address: &str) -> usize {
+        let revoked = self
+            .revoke_where(|record| {
+                record
+                    .data
+                    .get(crate::session_login::SESSION_USER_KEY)
+                    .and_then(|user| user.get("user_address"))
+                    .and_then(|addr| addr.as_str())
+                    .map(|addr| addr == user_address)
+                    .unwrap_or(false)
+            })
+            .await;
+        info!("撤销了用户 {} 的 {} 个会话", user_address, revoked);
+        revoked
+    }
+
+    /// 启动过期会话定期清理任务
+    pub fn spawn_expired_cleanup(&self, interval: Option<Duration>) {
+        let store = self.clone();
+        let interval = interval.unwrap_or(EXPIRED_SESSION_CLEANUP_INTERVAL);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = store.delete_expired().await {
+                    error!("清理过期会话失败: {}", e);
+                }
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl SessionStore for CitadelSessionStore {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        // 避免会话ID冲突
+        while self.records.contains_key(&record.id)
+            || self
+                .dir
+                .as_ref()
+                .map(|dir| Self::record_path(dir, &record.id).exists())
+                .unwrap_or(false)
+        {
+            record.id = Id::default();
+        }
+        self.save(record).await
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        self.write_file(record).await?;
+        self.records.insert(record.id, record.clone());
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        let record = match &self.dir {
+            // 持久化模式以磁盘为准，其他实例写入或撤销的会话也能生效
+            Some(dir) => {
+                let record = Self::read_record(&Self::record_path(dir, session_id)).await;
+                match &record {
+                    Some(record) => {
+                        self.records.insert(record.id, record.clone());
+                    }
+                    None => {
+                        self.records.remove(session_id);
+                    }
+                }
+                record
+            }
+            None => self.records.get(session_id).map(|r| r.value().clone()),
+        };
+        Ok(record.filter(|r| r.expiry_date > OffsetDateTime::now_utc()))
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        self.records.remove(session_id);
+        self.remove_file(session_id).await;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExpiredDeletion for CitadelSessionStore {
+    async fn delete_expired(&self) -> session_store::Result<()> {
+        let now = OffsetDateTime::now_utc();
+        let removed = self.revoke_where(|record| record.expiry_date <= now).await;
+        if removed > 0 {
+            debug!("清理了 {} 个过期会话", removed);
+        }
+        Ok(())
+    }
+}
+
+// 用于存储全局会话存储实例的静态变量
+static GLOBAL_SESSION_STORE: OnceCell<CitadelSessionStore> = OnceCell::new();
+
+/// 根据环境变量初始化会话存储并设置为全局实例
+pub async fn init_session_store() -> anyhow::Result<CitadelSessionStore> {
+    let kind = SessionStoreKind::from_env();
+    info!("会话存储类型: {:?}", kind);
+    let store = CitadelSessionStore::from_kind(&kind).await?;
+    let _ = GLOBAL_SESSION_STORE.set(store.clone());
+    Ok(store)
+}
+
+/// 获取全局会话存储
+pub fn global_session_store() -> Option<&'static CitadelSessionStore> {
+    GLOBAL_SESSION_STORE.get()
+}
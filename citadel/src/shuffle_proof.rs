@@ -0,0 +1,155 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 洗牌公平性证明
+//!
+//! # 概述
+//! 开局发牌后，服务器对牌堆顺序做出承诺，随 `match:start` 下发给所有客户端；对局结束时公开
+//! 开局牌堆的顺序和本局的盐值，随 `match:end` 下发。客户端可以核对公开的顺序与开局时的承诺一致，
+//! 证明服务器没有在对局中途更换牌堆，再结合公开的对局种子（见 `match_replay`）重放洗牌等效果。
+//!
+//! 承诺的计算方式：`Blake2b256(盐值 || 卡牌ID按牌堆从底到顶以逗号连接)`，结果和盐值都是Hex编码。
+//! 牌堆顶部是最后一张，抽牌时从顶部取。
+//!
+//! 盐值由对局种子派生，不保存在对局数据中，对局进行中无法通过承诺穷举牌堆顺序。
+//! 开局牌堆由种子和座位重建，也不需要额外保存。
+
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::hash::{Blake2b256, HashFunction};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::gaming::{Card, MatchData};
+use crate::match_replay::{self, Seed};
+
+/// 对局的洗牌承诺和结束后公开的数据
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShuffleProof {
+    /// 开局牌堆顺序的承诺，Hex编码
+    pub commitment: String,
+    /// 对局结束后公开的盐值，Hex编码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    /// 对局结束后公开的开局牌堆顺序（卡牌ID，从底到顶）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<Vec<String>>,
+}
+
+impl ShuffleProof {
+    /// 已公开的盐值和顺序是否与承诺一致，尚未公开时返回false
+    pub fn verify(&self) -> bool {
+        let (Some(salt), Some(order)) = (&self.salt, &self.order) else {
+            return false;
+        };
+        match Hex::decode(salt) {
+            Ok(salt) => compute_commitment(&salt, order) == self.commitment,
+            Err(_) => false,
+        }
+    }
+}
+
+/// 由对局种子派生盐值
+fn derive_salt(seed: &Seed) -> [u8; 32] {
+    let mut hasher = Blake2b256::default();
+    hasher.update(b"shuffle-salt:");
+    hasher.update(seed);
+    hasher.finalize().digest
+}
+
+/**
+ * 计算牌堆顺序的承诺
+ *
+ * 参数:
+ * @param salt - 盐值
+ * @param order - 卡牌ID，从底到顶
+ *
+ * 返回:
+ * Hex编码的承诺
+ */
+pub fn compute_commitment<S: AsRef<str>>(salt: &[u8], order: &[S]) -> String {
+    let order = order.iter().map(|id| id.as_ref()).collect::<Vec<_>>().join(",");
+    let mut hasher = Blake2b256::default();
+    hasher.update(salt);
+    hasher.update(order.as_bytes());
+    Hex::encode(hasher.finalize().digest)
+}
+
+fn card_order(deck: &[Card]) -> Vec<String> {
+    deck.iter().map(|c| c.id.clone()).collect()
+}
+
+/**
+ * 开局发牌后对牌堆顺序做出承诺
+ *
+ * 参数:
+ * @param match_data - 已发牌的对局数据，对局没有种子时不做承诺
+ */
+pub fn commit(match_data: &mut MatchData) {
+    let Some(seed) = match_replay::match_seed(match_data) else {
+        return;
+    };
+    match_data.shuffle_proof = Some(ShuffleProof {
+        commitment: compute_commitment(&derive_salt(&seed), &card_order(&match_data.deck)),
+        salt: None,
+        order: None,
+    });
+}
+
+/**
+ * 对局结束时公开开局牌堆顺序和盐值
+ *
+ * 重建的牌堆与承诺不一致说明服务器状态有误，仍然公开以便排查
+ *
+ * 参数:
+ * @param match_data - 已结束的对局数据
+ */
+pub fn reveal(match_data: &mut MatchData) {
+    if match_data.shuffle_proof.as_ref().map_or(true, |p| p.salt.is_some()) {
+        return;
+    }
+    let (Some(seed), Some(deck)) = (match_replay::match_seed(match_data), match_replay::initial_deck(match_data)) else {
+        error!("对局 {} 无法重建开局牌堆，洗牌承诺未公开", match_data.id);
+        return;
+    };
+    let match_id = match_data.id.clone();
+    if let Some(proof) = match_data.shuffle_proof.as_mut() {
+        proof.salt = Some(Hex::encode(derive_salt(&seed)));
+        proof.order = Some(card_order(&deck));
+        if !proof.verify() {
+            error!("对局 {} 的开局牌堆与洗牌承诺不一致", match_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_revealed_order() {
+        let salt = [7u8; 32];
+        let order = vec!["defuse-0".to_string(), "exploding-0".to_string(), "1-2".to_string()];
+        let mut proof = ShuffleProof {
+            commitment: compute_commitment(&salt, &order),
+            salt: None,
+            order: None,
+        };
+        assert!(!proof.verify());
+
+        proof.salt = Some(Hex::encode(salt));
+        proof.order = Some(order.clone());
+        assert!(proof.verify());
+
+        // 交换顺序后与承诺不一致
+        proof.order = Some(vec![order[1].clone(), order[0].clone(), order[2].clone()]);
+        assert!(!proof.verify());
+    }
+
+    #[test]
+    fn test_commitment_depends_on_salt() {
+        let order = ["a", "b"];
+        assert_ne!(compute_commitment(&[1u8; 32], &order), compute_commitment(&[2u8; 32], &order));
+        assert_eq!(compute_commitment(&[1u8; 32], &order), compute_commitment(&[1u8; 32], &order));
+    }
+}
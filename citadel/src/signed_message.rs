@@ -6,7 +6,9 @@
  * 
  * 本模块负责生成用于签名的消息格式，包括：
  * 1. 用户证书签名消息 - 用户授权会话密钥时显示的消息
- * 2. 密钥请求签名格式 - 用于保护请求数据完整性的序列化格式
+ * 2. zkLogin登录签名消息 - zkLogin用户登录时授权会话密钥的消息
+ * 3. 密钥请求签名格式 - 用于保护请求数据完整性的序列化格式
+ * 4. 带新鲜度字段的密钥请求签名格式 - 额外覆盖时间戳和nonce，用于防止重放
  * 
  * 这些签名机制确保只有授权用户能够获取密钥，并防止请求被篡改。
  */
@@ -15,7 +17,7 @@
  use chrono::{DateTime, Utc};
  use fastcrypto::ed25519::Ed25519PublicKey;
  use serde::{Deserialize, Serialize};
- use sui_types::base_types::ObjectID;
+ use sui_types::base_types::{ObjectID, SuiAddress};
  use sui_types::transaction::ProgrammableTransaction;
  use tracing::debug;
  
@@ -52,6 +54,39 @@
      res
  }
  
+ /**
+  * 生成zkLogin登录签名消息
+  * 
+  * zkLogin用户登录时，用临时密钥对该消息进行个人消息签名，授权会话密钥。
+  * 消息包含登录地址、授权时长、创建时间和会话公钥。
+  * 
+  * 参数:
+  * @param user - zkLogin地址
+  * @param vk - 会话验证密钥(Ed25519公钥)
+  * @param creation_time - 创建时间戳(毫秒)
+  * @param ttl_min - 生存时间(分钟)
+  * 
+  * 返回:
+  * 格式化的签名消息字符串
+  */
+ pub fn zklogin_session_message(
+     user: &SuiAddress,
+     vk: &Ed25519PublicKey,
+     creation_time: u64,
+     ttl_min: u16,
+ ) -> String {
+     let res = format!(
+         "Sign in to Catastrophe Genesis as {} for {} mins from {}, session key {}",
+         user,
+         ttl_min,
+         DateTime::<Utc>::from_timestamp((creation_time / 1000) as i64, 0)
+             .expect("tested that in the future"),
+         vk,
+     );
+     debug!("zkLogin session message: {}", res.clone());
+     res
+ }
+ 
  /**
   * 请求格式结构
   * 
@@ -96,16 +131,65 @@
      bcs::to_bytes(&req).expect("should serialize")
  }
  
+ /**
+  * 带新鲜度字段的请求格式结构
+  * 
+  * 在RequestFormat的基础上增加时间戳和nonce，签名覆盖这两个字段后，
+  * 截获的请求无法修改它们以绕过重放保护。
+  * 
+  * 字段:
+  * @field timestamp - 请求时间戳(毫秒)
+  * @field nonce - 客户端生成的一次性随机串
+  */
+ #[derive(Serialize, Deserialize)]
+ struct FreshRequestFormat {
+     ptb: Vec<u8>,
+     enc_key: Vec<u8>,
+     enc_verification_key: Vec<u8>,
+     timestamp: u64,
+     nonce: String,
+ }
+ 
+ /**
+  * 生成带新鲜度字段的请求签名数据
+  * 
+  * 参数:
+  * @param ptb - 可编程交易块
+  * @param enc_key - ElGamal加密公钥
+  * @param enc_verification_key - ElGamal验证密钥
+  * @param timestamp - 请求时间戳(毫秒)
+  * @param nonce - 一次性随机串
+  * 
+  * 返回:
+  * 序列化后的请求数据字节数组
+  */
+ pub fn signed_request_with_freshness(
+     ptb: &ProgrammableTransaction,
+     enc_key: &ElGamalPublicKey,
+     enc_verification_key: &ElgamalVerificationKey,
+     timestamp: u64,
+     nonce: &str,
+ ) -> Vec<u8> {
+     let req = FreshRequestFormat {
+         ptb: bcs::to_bytes(&ptb).expect("should serialize"),
+         enc_key: bcs::to_bytes(&enc_key).expect("should serialize"),
+         enc_verification_key: bcs::to_bytes(&enc_verification_key).expect("should serialize"),
+         timestamp,
+         nonce: nonce.to_string(),
+     };
+     bcs::to_bytes(&req).expect("should serialize")
+ }
+ 
  #[cfg(test)]
  mod tests {
-     use crate::signed_message::{signed_message, signed_request};
+     use crate::signed_message::{signed_message, signed_request, signed_request_with_freshness, zklogin_session_message};
      use crypto::elgamal::genkey;
      use fastcrypto::ed25519::Ed25519KeyPair;
      use fastcrypto::traits::KeyPair;
      use rand::rngs::StdRng;
      use rand::SeedableRng;
      use std::str::FromStr;
-     use sui_types::base_types::ObjectID;
+     use sui_types::base_types::{ObjectID, SuiAddress};
      use sui_types::crypto::deterministic_random_account_key;
      use sui_types::programmable_transaction_builder::ProgrammableTransactionBuilder;
      use sui_types::Identifier;
@@ -131,6 +215,22 @@
          assert_eq!(result, expected_output);
      }
  
+     /**
+      * 测试zkLogin登录消息格式回归测试
+      */
+     #[test]
+     fn test_zklogin_session_message_regression() {
+         let user =
+             SuiAddress::from_str("0xd92bc457b42d48924087ea3f22d35fd2fe9afdf5bdfe38cc51c0f14f3282f6d5")
+                 .unwrap();
+         let (_, kp): (_, Ed25519KeyPair) = deterministic_random_account_key();
+ 
+         let expected_output = "Sign in to Catastrophe Genesis as 0xd92bc457b42d48924087ea3f22d35fd2fe9afdf5bdfe38cc51c0f14f3282f6d5 for 10 mins from 1970-01-19 18:42:28 UTC, session key DX2rNYyNrapO+gBJp1sHQ2VVsQo2ghm7aA9wVxNJ13U=";
+ 
+         let result = zklogin_session_message(&user, kp.public(), 1622548800, 10);
+         assert_eq!(result, expected_output);
+     }
+ 
      /**
       * 测试请求签名数据回归测试
       * 
@@ -158,6 +258,13 @@
  
          let result = signed_request(&ptb, &eg_keys.1, &eg_keys.2);
          assert_eq!(hex::encode(result), expected_output);
+ 
+         // 带新鲜度字段的格式以原格式为前缀，末尾追加时间戳和nonce
+         let fresh = signed_request_with_freshness(&ptb, &eg_keys.1, &eg_keys.2, 1, "ab");
+         let mut expected = hex::decode(expected_output).unwrap();
+         expected.extend_from_slice(&1u64.to_le_bytes());
+         expected.extend_from_slice(&[2, b'a', b'b']);
+         assert_eq!(fresh, expected);
      }
  }
  
\ No newline at end of file
@@ -0,0 +1,442 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 玩家统计模块
+//!
+//! # 概述
+//! 本模块在每局游戏结束时由MatchService调用，统计玩家的对局数据
+//! （场次、胜率、各类卡牌使用次数、连胜/连败、平均回合时间、生存率）并记录ELO评分变化历史，
+//! 供Profile REST接口查询。
+//!
+//! 统计数据是增量更新的：每局结束时只根据本局的MatchData（动作历史、回合记录、出局顺序）
+//! 计算出PlayerMatchSummary并累加到缓存中的PlayerStats，不需要重新扫描历史对局。
+//! 生存率是玩家出局前撑过的对手数占所有对手数的比例，获胜视为撑过所有对手。
+//!
+//! # 使用示例
+//! ```ignore
+//! let stats = stats::init_stats_service(game_service);
+//! stats.record_match(&match_data, &rating_changes);
+//! let history = stats.get_rating_history(&user_id);
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::ToSchema;
+
+use crate::game::{GameCachePrefix, GameService};
+use crate::gaming::{CardActionType, CardType, MatchData};
+use crate::rating::RatingModifier;
+
+/// 每个玩家保留的最大评分历史条数
+pub const MAX_RATING_HISTORY: usize = 200;
+/// 统计中返回的常用卡牌数量
+pub const FAVORITE_CARDS_LIMIT: usize = 3;
+
+/// 单个玩家在一局游戏中的评分变化
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RatingChange {
+    pub user_id: String,
+    pub rating_before: i32,
+    pub rating_after: i32,
+    /// 生效的评分修正（连胜、逆转、首个出局减免、单局上限）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifiers: Vec<RatingModifier>,
+}
+
+/// 评分历史记录
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RatingHistoryEntry {
+    pub match_id: String,
+    pub rating_before: i32,
+    pub rating_after: i32,
+    pub delta: i32,
+    pub won: bool,
+    pub timestamp: u64,
+    /// 生效的评分修正
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifiers: Vec<RatingModifier>,
+}
+
+/// 卡牌使用次数
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardUsage {
+    pub card_type: CardType,
+    pub count: u64,
+}
+
+/// 玩家统计数据（缓存中保存的结构）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerStats {
+    pub played: u64,
+    pub won: u64,
+    pub lost: u64,
+    /// 当前连胜（正数）或连败（负数）场数
+    pub current_streak: i64,
+    /// 最长连胜
+    pub best_win_streak: u64,
+    /// 各类卡牌的使用次数
+    #[serde(default)]
+    pub card_plays: HashMap<CardType, u64>,
+    /// 最近一局的时间
+    #[serde(default)]
+    pub last_played_at: Option<u64>,
+    /// 已完成的回合数
+    #[serde(default)]
+    pub turns_taken: u64,
+    /// 回合总时长（毫秒）
+    #[serde(default)]
+    pub total_turn_ms: u64,
+    /// 出局前撑过的对手数之和
+    #[serde(default)]
+    pub opponents_outlasted: u64,
+    /// 对手数之和
+    #[serde(default)]
+    pub opponents_faced: u64,
+}
+
+/// 单个玩家在一局游戏中的统计，由已结束的MatchData计算
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerMatchSummary {
+    pub won: bool,
+    /// 实际生效的出牌
+    pub cards: Vec<CardType>,
+    pub turns: u64,
+    pub turn_ms: u64,
+    /// 出局前撑过的对手数
+    pub opponents_outlasted: u64,
+    pub opponents: u64,
+}
+
+impl PlayerMatchSummary {
+    /**
+     * 计算玩家在一局游戏中的统计
+     *
+     * 参数:
+     * @param match_data - 已结束的游戏数据
+     * @param user_id - 玩家ID
+     *
+     * 返回:
+     * 玩家不在本局中时返回None
+     */
+    pub fn from_match(match_data: &MatchData, user_id: &str) -> Option<Self> {
+        let total = (match_data.players.len() + match_data.out.len()) as u64;
+        // 出局列表按出局顺序排列，仍在游戏中的玩家撑过了所有对手
+        let (won, opponents_outlasted) = match match_data.players.iter().find(|p| p.user.id == user_id) {
+            Some(player) => (player.is_winner, total.saturating_sub(1)),
+            None => {
+                let index = match_data.out.iter().position(|p| p.user.id == user_id)?;
+                (match_data.out[index].is_winner, index as u64)
+            }
+        };
+
+        let cards = match_data
+            .action_history
+            .iter()
+            .filter(|a| {
+                a.user_id == user_id
+                    && !a.is_canceled
+                    && matches!(a.action_type, CardActionType::Play | CardActionType::Nope)
+            })
+            .filter_map(|a| a.card_type.clone())
+            .collect();
+
+        let turns = match_data.turn_log.iter().filter(|t| t.user_id == user_id);
+        let (turns, turn_ms) = turns.fold((0, 0), |(count, ms), t| (count + 1, ms + t.duration_ms));
+
+        Some(Self {
+            won,
+            cards,
+            turns,
+            turn_ms,
+            opponents_outlasted,
+            opponents: total.saturating_sub(1),
+        })
+    }
+}
+
+impl PlayerStats {
+    /// 记录一局游戏结果
+    pub fn record_result(&mut self, won: bool, now: u64) {
+        self.played += 1;
+        if won {
+            self.won += 1;
+            self.current_streak = if self.current_streak > 0 { self.current_streak + 1 } else { 1 };
+            self.best_win_streak = self.best_win_streak.max(self.current_streak as u64);
+        } else {
+            self.lost += 1;
+            self.current_streak = if self.current_streak < 0 { self.current_streak - 1 } else { -1 };
+        }
+        self.last_played_at = Some(now);
+    }
+
+    /// 记录一次卡牌使用
+    pub fn record_card(&mut self, card_type: CardType) {
+        *self.card_plays.entry(card_type).or_insert(0) += 1;
+    }
+
+    /// 累加一局游戏的统计
+    pub fn apply_match(&mut self, summary: &PlayerMatchSummary, now: u64) {
+        self.record_result(summary.won, now);
+        for card_type in &summary.cards {
+            self.record_card(card_type.clone());
+        }
+        self.turns_taken += summary.turns;
+        self.total_turn_ms += summary.turn_ms;
+        self.opponents_outlasted += summary.opponents_outlasted;
+        self.opponents_faced += summary.opponents;
+    }
+
+    /// 平均回合时间（毫秒）
+    pub fn average_turn_ms(&self) -> u64 {
+        if self.turns_taken == 0 {
+            return 0;
+        }
+        self.total_turn_ms / self.turns_taken
+    }
+
+    /// 生存率（百分比）
+    pub fn survival_rate(&self) -> u64 {
+        if self.opponents_faced == 0 {
+            return 0;
+        }
+        self.opponents_outlasted * 100 / self.opponents_faced
+    }
+
+    /// 胜率（百分比）
+    pub fn win_rate(&self) -> u64 {
+        if self.played == 0 {
+            return 0;
+        }
+        self.won * 100 / self.played
+    }
+
+    /// 使用次数最多的卡牌
+    pub fn favorite_cards(&self, limit: usize) -> Vec<CardUsage> {
+        let mut cards = self
+            .card_plays
+            .iter()
+            .map(|(card_type, count)| CardUsage {
+                card_type: card_type.clone(),
+                count: *count,
+            })
+            .collect::<Vec<_>>();
+        cards.sort_by(|a, b| b.count.cmp(&a.count));
+        cards.truncate(limit);
+        cards
+    }
+}
+
+/// 统计服务
+pub struct StatsService {
+    /// 游戏服务，处理缓存
+    game_service: Arc<GameService>,
+    /// 写锁，保证读-改-写的原子性
+    lock: parking_lot::Mutex<()>,
+}
+
+impl StatsService {
+    /// 创建新的统计服务
+    pub fn new(game_service: Arc<GameService>) -> Self {
+        Self {
+            game_service,
+            lock: parking_lot::Mutex::new(()),
+        }
+    }
+
+    /// 获取玩家统计数据
+    pub fn get_stats(&self, user_id: &str) -> Option<PlayerStats> {
+        self.game_service.get(GameCachePrefix::STATS, user_id)
+    }
+
+    /// 获取玩家评分历史（按时间顺序）
+    pub fn get_rating_history(&self, user_id: &str) -> Vec<RatingHistoryEntry> {
+        self.game_service
+            .get::<VecDeque<RatingHistoryEntry>>(GameCachePrefix::RATING_HISTORY, user_id)
+            .map(|history| history.into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    /**
+     * 记录一局已结束游戏的统计数据
+     *
+     * 参数:
+     * @param match_data - 已结束的游戏数据
+     * @param rating_changes - 本局所有玩家的评分变化
+     */
+    pub fn record_match(&self, match_data: &MatchData, rating_changes: &[RatingChange]) {
+        let _guard = self.lock.lock();
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+
+        for player in match_data.players.iter().chain(match_data.out.iter()) {
+            let user_id = &player.user.id;
+            let Some(summary) = PlayerMatchSummary::from_match(match_data, user_id) else {
+                continue;
+            };
+            let mut stats = self.get_stats(user_id).unwrap_or_default();
+            stats.apply_match(&summary, now);
+
+            if !self.game_service.set(GameCachePrefix::STATS, user_id, &stats) {
+                error!("保存玩家 {} 的统计数据失败", user_id);
+            }
+
+            if let Some(change) = rating_changes.iter().find(|c| &c.user_id == user_id) {
+                let mut history = self
+                    .game_service
+                    .get::<VecDeque<RatingHistoryEntry>>(GameCachePrefix::RATING_HISTORY, user_id)
+                    .unwrap_or_default();
+                history.push_back(RatingHistoryEntry {
+                    match_id: match_data.id.clone(),
+                    rating_before: change.rating_before,
+                    rating_after: change.rating_after,
+                    delta: change.rating_after - change.rating_before,
+                    won: player.is_winner,
+                    timestamp: now,
+                    modifiers: change.modifiers.clone(),
+                });
+                while history.len() > MAX_RATING_HISTORY {
+                    history.pop_front();
+                }
+                if !self.game_service.set(GameCachePrefix::RATING_HISTORY, user_id, &history) {
+                    error!("保存玩家 {} 的评分历史失败", user_id);
+                }
+            }
+        }
+    }
+
+    /**
+     * 把统计数据和评分历史转移到另一个用户ID，用于游客升级为链上Profile
+     *
+     * 参数:
+     * @param from - 原用户ID
+     * @param to - 新用户ID，已有统计数据或评分历史时不覆盖
+     *
+     * 返回:
+     * 是否转移了数据
+     */
+    pub fn transfer(&self, from: &str, to: &str) -> bool {
+        let _guard = self.lock.lock();
+        let history_of = |user_id: &str| {
+            self.game_service
+                .get::<VecDeque<RatingHistoryEntry>>(GameCachePrefix::RATING_HISTORY, user_id)
+        };
+        if self.get_stats(to).is_some() || history_of(to).is_some() {
+            error!("用户 {} 已有统计数据，不转移 {} 的数据", to, from);
+            return false;
+        }
+
+        let mut moved = false;
+        if let Some(stats) = self.get_stats(from) {
+            if self.game_service.set(GameCachePrefix::STATS, to, &stats) {
+                self.game_service.delete(GameCachePrefix::STATS, from);
+                moved = true;
+            } else {
+                error!("转移玩家 {} 的统计数据失败", from);
+            }
+        }
+        if let Some(history) = history_of(from) {
+            if self.game_service.set(GameCachePrefix::RATING_HISTORY, to, &history) {
+                self.game_service.delete(GameCachePrefix::RATING_HISTORY, from);
+                moved = true;
+            } else {
+                error!("转移玩家 {} 的评分历史失败", from);
+            }
+        }
+        moved
+    }
+}
+
+// 用于存储全局StatsService实例的静态变量
+static GLOBAL_STATS_SERVICE: OnceCell<Arc<StatsService>> = OnceCell::new();
+
+/// 初始化统计服务并设置为全局实例
+pub fn init_stats_service(game_service: Arc<GameService>) -> Arc<StatsService> {
+    let service = Arc::new(StatsService::new(game_service));
+    let _ = GLOBAL_STATS_SERVICE.set(service.clone());
+    service
+}
+
+/// 获取全局统计服务
+pub fn global_stats_service() -> Option<Arc<StatsService>> {
+    GLOBAL_STATS_SERVICE.get().cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streaks() {
+        let mut stats = PlayerStats::default();
+        stats.record_result(true, 1);
+        stats.record_result(true, 2);
+        stats.record_result(true, 3);
+        assert_eq!(stats.current_streak, 3);
+        assert_eq!(stats.best_win_streak, 3);
+
+        stats.record_result(false, 4);
+        stats.record_result(false, 5);
+        assert_eq!(stats.current_streak, -2);
+        assert_eq!(stats.best_win_streak, 3);
+
+        stats.record_result(true, 6);
+        assert_eq!(stats.current_streak, 1);
+        assert_eq!(stats.played, 6);
+        assert_eq!(stats.win_rate(), 66);
+    }
+
+    #[test]
+    fn test_favorite_cards() {
+        let mut stats = PlayerStats::default();
+        for _ in 0..3 {
+            stats.record_card(CardType::Nope);
+        }
+        stats.record_card(CardType::Skip);
+        for _ in 0..2 {
+            stats.record_card(CardType::Attack);
+        }
+        let favorites = stats.favorite_cards(2);
+        assert_eq!(favorites.len(), 2);
+        assert_eq!(favorites[0].card_type, CardType::Nope);
+        assert_eq!(favorites[1].card_type, CardType::Attack);
+    }
+
+    #[test]
+    fn test_apply_match_aggregates() {
+        let mut stats = PlayerStats::default();
+        stats.apply_match(
+            &PlayerMatchSummary {
+                won: false,
+                cards: vec![CardType::Skip, CardType::Nope],
+                turns: 3,
+                turn_ms: 30_000,
+                opponents_outlasted: 1,
+                opponents: 3,
+            },
+            1,
+        );
+        stats.apply_match(
+            &PlayerMatchSummary {
+                won: true,
+                cards: vec![CardType::Skip],
+                turns: 1,
+                turn_ms: 2_000,
+                opponents_outlasted: 1,
+                opponents: 1,
+            },
+            2,
+        );
+        assert_eq!(stats.played, 2);
+        assert_eq!(stats.card_plays[&CardType::Skip], 2);
+        assert_eq!(stats.average_turn_ms(), 8_000);
+        assert_eq!(stats.survival_rate(), 50);
+        assert_eq!(PlayerStats::default().average_turn_ms(), 0);
+    }
+}
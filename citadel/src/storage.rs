@@ -0,0 +1,258 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 可插拔的持久化存储
+//!
+//! # 概述
+//! Profile缓存、对局历史、密钥审计日志和服务端设置原先只保存在进程内存中，重启后全部丢失。
+//! 本模块定义统一的 `Storage` 接口，由配置选择具体实现：
+//! - `memory`：仅内存保存（默认，与之前的行为一致）
+//! - `sqlite`：通过sqlx写入SQLite数据库，重启后可恢复
+//!
+//! 存储只作为缓存的后备，读写失败时记录日志，不影响正常流程：
+//! - Profile：链上查询成功后写入，链上查询失败时回退到存储中的最近一次结果
+//! - 对局历史：对局结束时写入带完整动作历史的对局数据，缓存过期后仍可查询
+//! - 审计日志：每条记录追加写入，启动时恢复最近的记录到内存
+//! - 对局事件日志：进行中对局的开局快照和动作按位置追加写入，缓存丢失时用于重建对局，见 `match_log`
+//! - 设置：简单的键值对
+//! - 钱包：游戏币余额以设置键 `wallet:<用户ID>` 写入，写入失败时余额变动失败，见 `economy`
+//!
+//! 新增后端时在 `storage/` 下添加模块实现 `Storage`，并在 `StorageKind` 和 `open` 中注册。
+//!
+//! # 配置
+//! - `STORAGE_BACKEND`：`memory` 或 `sqlite`，默认 `memory`
+//! - `STORAGE_SQLITE_URL`：`sqlite` 模式下的数据库地址，默认 `sqlite://./data/citadel.db`
+
+pub mod memory;
+pub mod sqlite;
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use once_cell::sync::OnceCell;
+use sui_types::base_types::ObjectID;
+use tracing::{error, info, warn};
+
+use crate::gaming::MatchData;
+use crate::key_audit::AuditRecord;
+use crate::match_log::MatchLogEntry;
+use crate::sdk::manager::Profile;
+
+/// 默认的SQLite数据库地址
+pub const DEFAULT_SQLITE_URL: &str = "sqlite://./data/citadel.db";
+
+/// 存储后端类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageKind {
+    /// 仅内存
+    Memory,
+    /// SQLite数据库
+    Sqlite(String),
+}
+
+impl StorageKind {
+    /// 从环境变量读取存储配置
+    pub fn from_env() -> Self {
+        let kind = std::env::var("STORAGE_BACKEND").unwrap_or_default();
+        match kind.trim().to_lowercase().as_str() {
+            "sqlite" => {
+                let url = std::env::var("STORAGE_SQLITE_URL")
+                    .ok()
+                    .filter(|u| !u.is_empty())
+                    .unwrap_or_else(|| DEFAULT_SQLITE_URL.to_string());
+                StorageKind::Sqlite(url)
+            }
+            "" | "memory" => StorageKind::Memory,
+            other => {
+                warn!("未知的存储类型: {}，使用内存存储", other);
+                StorageKind::Memory
+            }
+        }
+    }
+}
+
+/// 持久化存储接口
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// 后端名称，用于日志
+    fn kind(&self) -> &'static str;
+
+    /// 读取Profile
+    async fn get_profile(&self, profile_id: &ObjectID) -> Result<Option<Profile>>;
+
+    /// 写入Profile，已存在时覆盖
+    async fn put_profile(&self, profile: &Profile) -> Result<()>;
+
+    /// 写入对局历史，已存在时覆盖
+    async fn put_match(&self, match_data: &MatchData) -> Result<()>;
+
+    /// 读取对局历史
+    async fn get_match(&self, match_id: &str) -> Result<Option<MatchData>>;
+
+    /// 追加对局事件日志，同一位置已存在时忽略
+    async fn append_match_log(&self, match_id: &str, seq: u64, entry: &MatchLogEntry) -> Result<()>;
+
+    /// 读取对局事件日志，按位置排列
+    async fn match_log(&self, match_id: &str) -> Result<Vec<MatchLogEntry>>;
+
+    /// 删除对局事件日志
+    async fn delete_match_log(&self, match_id: &str) -> Result<()>;
+
+    /// 追加一条审计记录
+    async fn append_audit(&self, record: &AuditRecord) -> Result<()>;
+
+    /// 读取最近的审计记录，按时间从旧到新排列
+    async fn recent_audit(&self, limit: usize) -> Result<Vec<AuditRecord>>;
+
+    /// 读取设置
+    async fn get_setting(&self, key: &str) -> Result<Option<String>>;
+
+    /// 写入设置，已存在时覆盖
+    async fn put_setting(&self, key: &str, value: &str) -> Result<()>;
+}
+
+/// 按配置打开存储后端
+pub async fn open(kind: &StorageKind) -> Result<Arc<dyn Storage>> {
+    Ok(match kind {
+        StorageKind::Memory => Arc::new(memory::MemoryStorage::default()),
+        StorageKind::Sqlite(url) => Arc::new(sqlite::SqliteStorage::connect(url).await?),
+    })
+}
+
+// 用于存储全局存储实例的静态变量
+static GLOBAL_STORAGE: OnceCell<Arc<dyn Storage>> = OnceCell::new();
+
+/// 根据环境变量初始化存储并设置为全局实例
+pub async fn init_storage() -> Result<Arc<dyn Storage>> {
+    if let Some(storage) = GLOBAL_STORAGE.get() {
+        return Ok(storage.clone());
+    }
+    let kind = StorageKind::from_env();
+    info!("持久化存储类型: {:?}", kind);
+    let storage = open(&kind).await?;
+    let _ = GLOBAL_STORAGE.set(storage.clone());
+    Ok(storage)
+}
+
+/// 获取全局存储
+pub fn global_storage() -> Option<Arc<dyn Storage>> {
+    GLOBAL_STORAGE.get().cloned()
+}
+
+/// 写入Profile，未初始化存储或写入失败时只记录日志
+pub async fn persist_profile(profile: &Profile) {
+    if let Some(storage) = global_storage() {
+        if let Err(e) = storage.put_profile(profile).await {
+            error!("保存Profile {} 失败: {}", profile.id, e);
+        }
+    }
+}
+
+/// 读取存储中的Profile，未初始化存储或读取失败时返回None
+pub async fn load_profile(profile_id: &ObjectID) -> Option<Profile> {
+    let storage = global_storage()?;
+    storage.get_profile(profile_id).await.unwrap_or_else(|e| {
+        error!("读取Profile {} 失败: {}", profile_id, e);
+        None
+    })
+}
+
+/// 写入对局历史，未初始化存储或写入失败时只记录日志
+pub async fn persist_match(match_data: &MatchData) {
+    if let Some(storage) = global_storage() {
+        if let Err(e) = storage.put_match(match_data).await {
+            error!("保存对局历史 {} 失败: {}", match_data.id, e);
+        }
+    }
+}
+
+/// 读取存储中的对局历史，未初始化存储或读取失败时返回None
+pub async fn load_match(match_id: &str) -> Option<MatchData> {
+    let storage = global_storage()?;
+    storage.get_match(match_id).await.unwrap_or_else(|e| {
+        error!("读取对局历史 {} 失败: {}", match_id, e);
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gaming::CardAction;
+    use crate::key_audit::AuditDecision;
+
+    fn profile(rating: u64) -> Profile {
+        Profile {
+            id: ObjectID::ZERO,
+            avatar: "avatar".to_string(),
+            rating,
+            played: 1,
+            won: 1,
+            lost: 0,
+        }
+    }
+
+    fn audit(timestamp: u64) -> AuditRecord {
+        AuditRecord {
+            timestamp,
+            req_id: None,
+            requester: "0x1".to_string(),
+            key_ids: vec![],
+            ptb_digest: String::new(),
+            decision: AuditDecision::Allowed,
+            error: None,
+            latency_ms: 0,
+        }
+    }
+
+    async fn exercise(storage: Arc<dyn Storage>) {
+        assert!(storage.get_profile(&ObjectID::ZERO).await.unwrap().is_none());
+        storage.put_profile(&profile(1000)).await.unwrap();
+        storage.put_profile(&profile(1200)).await.unwrap();
+        assert_eq!(storage.get_profile(&ObjectID::ZERO).await.unwrap().unwrap().rating, 1200);
+
+        for timestamp in 1..=5 {
+            storage.append_audit(&audit(timestamp)).await.unwrap();
+        }
+        let recent = storage.recent_audit(3).await.unwrap();
+        assert_eq!(recent.iter().map(|r| r.timestamp).collect::<Vec<_>>(), vec![3, 4, 5]);
+
+        assert!(storage.get_setting("motd").await.unwrap().is_none());
+        storage.put_setting("motd", "hello").await.unwrap();
+        storage.put_setting("motd", "bye").await.unwrap();
+        assert_eq!(storage.get_setting("motd").await.unwrap().as_deref(), Some("bye"));
+
+        assert!(storage.get_match("missing").await.unwrap().is_none());
+
+        let action = |user_id: &str| MatchLogEntry::Action(CardAction::eliminate(user_id));
+        storage.append_match_log("m1", 2, &action("b")).await.unwrap();
+        storage.append_match_log("m1", 1, &action("a")).await.unwrap();
+        storage.append_match_log("m1", 1, &action("c")).await.unwrap();
+        let users = storage
+            .match_log("m1")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| match entry {
+                MatchLogEntry::Action(action) => action.user_id,
+                MatchLogEntry::Started(_) => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(users, vec!["a", "b"]);
+        storage.delete_match_log("m1").await.unwrap();
+        assert!(storage.match_log("m1").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage() {
+        exercise(open(&StorageKind::Memory).await.unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_storage() {
+        let storage = open(&StorageKind::Sqlite("sqlite::memory:".to_string())).await.unwrap();
+        assert_eq!(storage.kind(), "sqlite");
+        exercise(storage).await;
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 仅内存的存储后端，进程退出后数据丢失
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use sui_types::base_types::ObjectID;
+
+use super::Storage;
+use crate::gaming::MatchData;
+use crate::key_audit::AuditRecord;
+use crate::match_log::MatchLogEntry;
+use crate::sdk::manager::Profile;
+
+/// 内存中最多保留的审计记录数
+pub const MAX_AUDIT_RECORDS: usize = 10_000;
+
+/// 内存存储
+#[derive(Default)]
+pub struct MemoryStorage {
+    profiles: Mutex<HashMap<ObjectID, Profile>>,
+    matches: Mutex<HashMap<String, MatchData>>,
+    match_logs: Mutex<HashMap<String, BTreeMap<u64, MatchLogEntry>>>,
+    audit: Mutex<VecDeque<AuditRecord>>,
+    settings: Mutex<HashMap<String, String>>,
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    fn kind(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn get_profile(&self, profile_id: &ObjectID) -> Result<Option<Profile>> {
+        Ok(self.profiles.lock().get(profile_id).cloned())
+    }
+
+    async fn put_profile(&self, profile: &Profile) -> Result<()> {
+        self.profiles.lock().insert(profile.id, profile.clone());
+        Ok(())
+    }
+
+    async fn put_match(&self, match_data: &MatchData) -> Result<()> {
+        self.matches.lock().insert(match_data.id.clone(), match_data.clone());
+        Ok(())
+    }
+
+    async fn get_match(&self, match_id: &str) -> Result<Option<MatchData>> {
+        Ok(self.matches.lock().get(match_id).cloned())
+    }
+
+    async fn append_match_log(&self, match_id: &str, seq: u64, entry: &MatchLogEntry) -> Result<()> {
+        self.match_logs
+            .lock()
+            .entry(match_id.to_string())
+            .or_default()
+            .entry(seq)
+            .or_insert_with(|| entry.clone());
+        Ok(())
+    }
+
+    async fn match_log(&self, match_id: &str) -> Result<Vec<MatchLogEntry>> {
+        Ok(self
+            .match_logs
+            .lock()
+            .get(match_id)
+            .map(|log| log.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn delete_match_log(&self, match_id: &str) -> Result<()> {
+        self.match_logs.lock().remove(match_id);
+        Ok(())
+    }
+
+    async fn append_audit(&self, record: &AuditRecord) -> Result<()> {
+        let mut audit = self.audit.lock();
+        if audit.len() >= MAX_AUDIT_RECORDS {
+            audit.pop_front();
+        }
+        audit.push_back(record.clone());
+        Ok(())
+    }
+
+    async fn recent_audit(&self, limit: usize) -> Result<Vec<AuditRecord>> {
+        let audit = self.audit.lock();
+        let skip = audit.len().saturating_sub(limit);
+        Ok(audit.iter().skip(skip).cloned().collect())
+    }
+
+    async fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.settings.lock().get(key).cloned())
+    }
+
+    async fn put_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.settings.lock().insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! SQLite存储后端
+//!
+//! 每类数据一张表，内容以JSON保存，连接时自动建表。
+//! 数据库文件不存在时自动创建，所在目录需要预先存在或可创建。
+
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use sui_types::base_types::ObjectID;
+
+use super::Storage;
+use crate::gaming::MatchData;
+use crate::key_audit::AuditRecord;
+use crate::match_log::MatchLogEntry;
+use crate::sdk::manager::Profile;
+
+/// 建表语句
+const SCHEMA: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS profiles (id TEXT PRIMARY KEY, data TEXT NOT NULL)",
+    "CREATE TABLE IF NOT EXISTS match_history (id TEXT PRIMARY KEY, data TEXT NOT NULL, updated_at INTEGER NOT NULL)",
+    "CREATE TABLE IF NOT EXISTS match_log (match_id TEXT NOT NULL, seq INTEGER NOT NULL, data TEXT NOT NULL, PRIMARY KEY (match_id, seq))",
+    "CREATE TABLE IF NOT EXISTS audit_log (seq INTEGER PRIMARY KEY AUTOINCREMENT, data TEXT NOT NULL)",
+    "CREATE TABLE IF NOT EXISTS settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+];
+
+/// SQLite存储
+pub struct SqliteStorage {
+    pool: SqlitePool,
+}
+
+impl SqliteStorage {
+    /**
+     * 连接数据库并建表
+     *
+     * 参数:
+     * @param url - 数据库地址，如 `sqlite://./data/citadel.db` 或 `sqlite::memory:`
+     *
+     * 返回:
+     * SQLite存储
+     */
+    pub async fn connect(url: &str) -> Result<Self> {
+        let options = SqliteConnectOptions::from_str(url)
+            .with_context(|| format!("无效的SQLite地址: {}", url))?
+            .create_if_missing(true);
+        if let Some(dir) = Path::new(options.get_filename()).parent() {
+            if !dir.as_os_str().is_empty() {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+        }
+        // 内存数据库每个连接各自独立，只能使用单个连接
+        let pool = SqlitePoolOptions::new()
+            .max_connections(if url.contains(":memory:") { 1 } else { 4 })
+            .connect_with(options)
+            .await
+            .with_context(|| format!("连接SQLite数据库失败: {}", url))?;
+        for statement in SCHEMA {
+            sqlx::query(statement).execute(&pool).await?;
+        }
+        Ok(Self { pool })
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, sql: &str, key: &str) -> Result<Option<T>> {
+        let row = sqlx::query(sql).bind(key).fetch_optional(&self.pool).await?;
+        match row {
+            Some(row) => Ok(Some(serde_json::from_str(row.try_get::<&str, _>(0)?)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    fn kind(&self) -> &'static str {
+        "sqlite"
+    }
+
+    async fn get_profile(&self, profile_id: &ObjectID) -> Result<Option<Profile>> {
+        self.get_json("SELECT data FROM profiles WHERE id = ?", &profile_id.to_string())
+            .await
+    }
+
+    async fn put_profile(&self, profile: &Profile) -> Result<()> {
+        sqlx::query("INSERT INTO profiles (id, data) VALUES (?, ?) ON CONFLICT(id) DO UPDATE SET data = excluded.data")
+            .bind(profile.id.to_string())
+            .bind(serde_json::to_string(profile)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn put_match(&self, match_data: &MatchData) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO match_history (id, data, updated_at) VALUES (?, ?, ?) \
+             ON CONFLICT(id) DO UPDATE SET data = excluded.data, updated_at = excluded.updated_at",
+        )
+        .bind(&match_data.id)
+        .bind(serde_json::to_string(match_data)?)
+        .bind(match_data.updated_at as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_match(&self, match_id: &str) -> Result<Option<MatchData>> {
+        self.get_json("SELECT data FROM match_history WHERE id = ?", match_id)
+            .await
+    }
+
+    async fn append_match_log(&self, match_id: &str, seq: u64, entry: &MatchLogEntry) -> Result<()> {
+        sqlx::query("INSERT OR IGNORE INTO match_log (match_id, seq, data) VALUES (?, ?, ?)")
+            .bind(match_id)
+            .bind(seq as i64)
+            .bind(serde_json::to_string(entry)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn match_log(&self, match_id: &str) -> Result<Vec<MatchLogEntry>> {
+        let rows = sqlx::query("SELECT data FROM match_log WHERE match_id = ? ORDER BY seq")
+            .bind(match_id)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter()
+            .map(|row| Ok(serde_json::from_str(row.try_get::<&str, _>(0)?)?))
+            .collect()
+    }
+
+    async fn delete_match_log(&self, match_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM match_log WHERE match_id = ?")
+            .bind(match_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn append_audit(&self, record: &AuditRecord) -> Result<()> {
+        sqlx::query("INSERT INTO audit_log (data) VALUES (?)")
+            .bind(serde_json::to_string(record)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn recent_audit(&self, limit: usize) -> Result<Vec<AuditRecord>> {
+        let rows = sqlx::query("SELECT data FROM audit_log ORDER BY seq DESC LIMIT ?")
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .await?;
+        let mut records = rows
+            .iter()
+            .map(|row| Ok(serde_json::from_str(row.try_get::<&str, _>(0)?)?))
+            .collect::<Result<Vec<AuditRecord>>>()?;
+        records.reverse();
+        Ok(records)
+    }
+
+    async fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let row = sqlx::query("SELECT value FROM settings WHERE key = ?")
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| row.try_get::<String, _>(0)).transpose()?)
+    }
+
+    async fn put_setting(&self, key: &str, value: &str) -> Result<()> {
+        sqlx::query("INSERT INTO settings (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value")
+            .bind(key)
+            .bind(value)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
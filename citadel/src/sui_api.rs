@@ -0,0 +1,266 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 全节点接口抽象
+//!
+//! # 概述
+//! `externals`、`txb` 和 `sdk::GameManager` 只通过 `SuiApi` 访问全节点，
+//! 生产环境使用 `SuiClient` 的实现，测试使用 `MockSuiApi`，不需要运行中的全节点。
+//!
+//! `SuiApi` 覆盖服务端用到的调用：
+//! - 最新检查点的时间戳和纪元、参考Gas价格（JSON-RPC）
+//! - 对象内容和表格内容（GraphQL，地址由 `Network` 决定，与 `sdk::query` 一致）
+//! - 已签名交易的执行
+//!
+//! # 接口
+//! - `MockSuiApi`：所有数据都由测试预先设置，执行的交易按顺序记录
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde_json::Value;
+use sui_sdk::error::{Error as SuiRpcError, SuiRpcResult};
+use sui_sdk::rpc_types::{CheckpointId, SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions};
+use sui_sdk::SuiClient;
+use sui_types::base_types::ObjectID;
+use sui_types::quorum_driver_types::ExecuteTransactionRequestType;
+use sui_types::transaction::Transaction;
+
+use crate::sdk::query::{query_all_table_content, query_object_content, ObjectData, TableField};
+use crate::types::Network;
+
+/// 全节点接口
+#[async_trait]
+pub trait SuiApi: Send + Sync {
+    /// 最新检查点的时间戳（毫秒）
+    async fn latest_checkpoint_timestamp(&self) -> SuiRpcResult<u64>;
+
+    /// 最新检查点所在的纪元
+    async fn current_epoch(&self) -> SuiRpcResult<u64>;
+
+    /// 参考Gas价格
+    async fn reference_gas_price(&self) -> SuiRpcResult<u64>;
+
+    /// 查询对象内容
+    async fn object_content(&self, network: &Network, object_id: &ObjectID) -> Result<ObjectData>;
+
+    /// 查询表格的所有字段
+    async fn table_content(&self, network: &Network, table_id: &ObjectID) -> Result<Vec<TableField>>;
+
+    /// 执行已签名的交易，等待本地执行完成
+    async fn execute_transaction(&self, transaction: Transaction) -> Result<SuiTransactionBlockResponse>;
+}
+
+#[async_trait]
+impl SuiApi for SuiClient {
+    async fn latest_checkpoint_timestamp(&self) -> SuiRpcResult<u64> {
+        let sequence_number = self.read_api().get_latest_checkpoint_sequence_number().await?;
+        let checkpoint = self
+            .read_api()
+            .get_checkpoint(CheckpointId::SequenceNumber(sequence_number))
+            .await?;
+        Ok(checkpoint.timestamp_ms)
+    }
+
+    async fn current_epoch(&self) -> SuiRpcResult<u64> {
+        let sequence_number = self.read_api().get_latest_checkpoint_sequence_number().await?;
+        let checkpoint = self
+            .read_api()
+            .get_checkpoint(CheckpointId::SequenceNumber(sequence_number))
+            .await?;
+        Ok(checkpoint.epoch)
+    }
+
+    async fn reference_gas_price(&self) -> SuiRpcResult<u64> {
+        self.read_api().get_reference_gas_price().await
+    }
+
+    async fn object_content(&self, network: &Network, object_id: &ObjectID) -> Result<ObjectData> {
+        query_object_content(network, object_id).await
+    }
+
+    async fn table_content(&self, network: &Network, table_id: &ObjectID) -> Result<Vec<TableField>> {
+        query_all_table_content(network, table_id, None).await
+    }
+
+    async fn execute_transaction(&self, transaction: Transaction) -> Result<SuiTransactionBlockResponse> {
+        let response = self
+            .quorum_driver_api()
+            .execute_transaction_block(
+                transaction,
+                SuiTransactionBlockResponseOptions::new()
+                    .with_effects()
+                    .with_input()
+                    .with_events()
+                    .with_object_changes()
+                    .with_balance_changes(),
+                Some(ExecuteTransactionRequestType::WaitForLocalExecution),
+            )
+            .await?;
+        Ok(response)
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    checkpoint_timestamp: AtomicU64,
+    epoch: AtomicU64,
+    gas_price: AtomicU64,
+    /// 为true时所有调用返回错误，用于模拟全节点不可用
+    unavailable: AtomicBool,
+    objects: Mutex<HashMap<ObjectID, Value>>,
+    tables: Mutex<HashMap<ObjectID, Vec<TableField>>>,
+    transactions: Mutex<Vec<Transaction>>,
+}
+
+/// 测试用的全节点，克隆的实例共享同一份数据
+#[derive(Clone, Default)]
+pub struct MockSuiApi {
+    state: Arc<MockState>,
+}
+
+impl MockSuiApi {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置最新检查点的时间戳和纪元
+    pub fn set_checkpoint(&self, timestamp_ms: u64, epoch: u64) {
+        self.state.checkpoint_timestamp.store(timestamp_ms, Ordering::Relaxed);
+        self.state.epoch.store(epoch, Ordering::Relaxed);
+    }
+
+    /// 设置参考Gas价格
+    pub fn set_gas_price(&self, gas_price: u64) {
+        self.state.gas_price.store(gas_price, Ordering::Relaxed);
+    }
+
+    /// 设置全节点是否可用
+    pub fn set_unavailable(&self, unavailable: bool) {
+        self.state.unavailable.store(unavailable, Ordering::Relaxed);
+    }
+
+    /// 设置对象内容，格式与GraphQL返回的 `contents.json` 相同
+    pub fn insert_object(&self, object_id: ObjectID, content: Value) {
+        self.state.objects.lock().insert(object_id, content);
+    }
+
+    /// 向表格追加一个字段
+    pub fn insert_table_field(&self, table_id: ObjectID, name: &str, value: &str) {
+        self.state.tables.lock().entry(table_id).or_default().push(TableField {
+            name: name.to_string(),
+            value: value.to_string(),
+        });
+    }
+
+    /// 已执行的交易
+    pub fn transactions(&self) -> Vec<Transaction> {
+        self.state.transactions.lock().clone()
+    }
+
+    fn check_available(&self) -> SuiRpcResult<()> {
+        if self.state.unavailable.load(Ordering::Relaxed) {
+            return Err(SuiRpcError::DataError("mock full node unavailable".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SuiApi for MockSuiApi {
+    async fn latest_checkpoint_timestamp(&self) -> SuiRpcResult<u64> {
+        self.check_available()?;
+        Ok(self.state.checkpoint_timestamp.load(Ordering::Relaxed))
+    }
+
+    async fn current_epoch(&self) -> SuiRpcResult<u64> {
+        self.check_available()?;
+        Ok(self.state.epoch.load(Ordering::Relaxed))
+    }
+
+    async fn reference_gas_price(&self) -> SuiRpcResult<u64> {
+        self.check_available()?;
+        Ok(self.state.gas_price.load(Ordering::Relaxed))
+    }
+
+    async fn object_content(&self, _network: &Network, object_id: &ObjectID) -> Result<ObjectData> {
+        self.check_available()?;
+        let content = self
+            .state
+            .objects
+            .lock()
+            .get(object_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Object {} not found", object_id))?;
+        Ok(ObjectData {
+            address: *object_id,
+            content,
+        })
+    }
+
+    async fn table_content(&self, _network: &Network, table_id: &ObjectID) -> Result<Vec<TableField>> {
+        self.check_available()?;
+        Ok(self.state.tables.lock().get(table_id).cloned().unwrap_or_default())
+    }
+
+    async fn execute_transaction(&self, transaction: Transaction) -> Result<SuiTransactionBlockResponse> {
+        self.check_available()?;
+        let response = SuiTransactionBlockResponse::new(*transaction.digest());
+        self.state.transactions.lock().push(transaction);
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::externals::{get_latest_checkpoint_timestamp, get_reference_gas_price};
+    use crate::sdk::GameManager;
+
+    #[tokio::test]
+    async fn test_mock_externals() {
+        let api = MockSuiApi::new();
+        api.set_checkpoint(1_700_000_000_000, 42);
+        api.set_gas_price(750);
+        assert_eq!(get_latest_checkpoint_timestamp(api.clone()).await.unwrap(), 1_700_000_000_000);
+        assert_eq!(get_reference_gas_price(api.clone()).await.unwrap(), 750);
+        assert_eq!(api.current_epoch().await.unwrap(), 42);
+
+        api.set_unavailable(true);
+        assert!(get_latest_checkpoint_timestamp(api.clone()).await.is_err());
+        assert!(api.object_content(&Network::Testnet, &ObjectID::ZERO).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_game_manager_with_mock() {
+        let manager_store = ObjectID::from_single_byte(1);
+        let friendship_store = ObjectID::from_single_byte(2);
+        let profile_table = ObjectID::from_single_byte(3);
+        let passport = ObjectID::from_single_byte(4);
+        let profile = ObjectID::from_single_byte(5);
+
+        let api = MockSuiApi::new();
+        api.insert_object(manager_store, serde_json::json!({ "profiles": { "id": profile_table.to_string() } }));
+        api.insert_object(friendship_store, serde_json::json!({ "relations": { "id": ObjectID::from_single_byte(6).to_string() } }));
+        api.insert_object(
+            profile,
+            serde_json::json!({ "avatar": "cat", "rating": "1200", "played": "3", "won": "2", "lost": "1" }),
+        );
+        api.insert_table_field(profile_table, &passport.to_string(), &profile.to_string());
+
+        let manager = GameManager::new(api.clone(), Network::Testnet, manager_store, friendship_store)
+            .await
+            .unwrap();
+        let loaded = manager.get_profile(&profile).await.unwrap();
+        assert_eq!(loaded.avatar, "cat");
+        assert_eq!(loaded.rating, 1200);
+        assert_eq!(loaded.won, 2);
+
+        manager.update_all_profiles().await.unwrap();
+        assert_eq!(manager.get_profile_id_by_passport(&passport).await.unwrap(), profile);
+    }
+}
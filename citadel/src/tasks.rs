@@ -0,0 +1,470 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 后台任务管理模块
+//!
+//! # 概述
+//! 检查点时间戳、gas价格、包ID、Profile和好友关系等定期更新任务由 `TaskManager` 统一启动和管理：
+//! - 每个任务收到一个 `CancellationToken`，关闭服务时取消令牌，任务在当前一轮结束后退出
+//! - 关闭时等待所有任务退出，超过时限的任务直接中止
+//! - 记录每个任务的运行状态，任务异常退出（panic）时就绪探针会报告降级
+//!
+//! 任务的watch通道没有接收者时，任务记录日志后正常退出，不会导致进程panic。
+//!
+//! # 监督与重启
+//! 每个任务都运行在监督者之下，panic时立即记录日志和panic信息，而不是在关闭服务时才发现。
+//! 通过 `supervise` 启动的任务按 `RestartPolicy` 在退避后重新启动：
+//! - 退避时间从 `initial_backoff` 开始每次翻倍，不超过 `max_backoff`
+//! - 任务连续运行超过 `reset_after` 后退避和重启计数重新开始
+//! - 连续重启超过 `max_restarts` 次后放弃，任务状态为 `panicked`
+//! - 等待重启期间任务状态为 `restarting`，关闭服务时不再重启
+//!
+//! 匹配队列、关键事件重发等没有 `AppState` 的子系统通过 `supervise_global` 使用全局的任务管理器。
+//!
+//! # 指标
+//! - `background_task_up`：任务是否在运行
+//! - `background_task_restarts_total`：任务的重启次数
+
+use std::any::Any;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::task::{AbortHandle, JoinHandle};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::metrics::Metrics;
+
+/// 关闭服务时等待后台任务退出的默认时间
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+static GLOBAL_TASK_MANAGER: OnceCell<Arc<TaskManager>> = OnceCell::new();
+
+/// 设置全局任务管理器
+pub fn set_global_task_manager(tasks: Arc<TaskManager>) {
+    let _ = GLOBAL_TASK_MANAGER.set(tasks);
+}
+
+/// 获取全局任务管理器
+pub fn global_task_manager() -> Option<Arc<TaskManager>> {
+    GLOBAL_TASK_MANAGER.get().cloned()
+}
+
+/**
+ * 使用全局任务管理器启动受监督的任务
+ *
+ * 全局任务管理器未设置时（如测试中）任务仍然受监督，但不会出现在健康状态中，也不会在关闭服务时取消。
+ *
+ * 参数:
+ * @param name - 任务名称
+ * @param policy - 重启策略
+ * @param task - 每次启动时调用，接收取消令牌并返回任务future
+ */
+pub fn supervise_global<F, Fut>(name: impl Into<String>, policy: RestartPolicy, task: F)
+where
+    F: FnMut(CancellationToken) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    match global_task_manager() {
+        Some(tasks) => tasks.supervise(name, policy, task),
+        None => {
+            let supervisor = Supervisor {
+                name: name.into(),
+                policy,
+                status: Arc::new(TaskStatus::default()),
+                metrics: Arc::new(OnceCell::new()),
+                token: CancellationToken::new(),
+            };
+            tokio::spawn(supervisor.run(task));
+        }
+    }
+}
+
+/// 任务异常退出后的重启策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// 连续重启的最多次数，None表示不限制
+    pub max_restarts: Option<u32>,
+    /// 第一次重启前的等待时间，之后每次翻倍
+    pub initial_backoff: Duration,
+    /// 最长的等待时间
+    pub max_backoff: Duration,
+    /// 任务连续运行超过该时间后，退避和重启计数重新开始
+    pub reset_after: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: None,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            reset_after: Duration::from_secs(300),
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// 不重启，异常退出后任务保持 `panicked` 状态
+    pub fn never() -> Self {
+        Self {
+            max_restarts: Some(0),
+            ..Self::default()
+        }
+    }
+
+    /// 第 `attempt` 次连续重启（从0开始）前的等待时间
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+}
+
+/// 后台任务的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    /// 运行中
+    Running,
+    /// 已正常退出
+    Stopped,
+    /// 异常退出，等待重启
+    Restarting,
+    /// 异常退出
+    Panicked,
+}
+
+/// 单个后台任务的健康状态
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaskHealth {
+    pub name: String,
+    pub state: TaskState,
+    /// 异常退出后的重启次数
+    pub restarts: u32,
+    /// 最近一次panic的信息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_panic: Option<String>,
+}
+
+/// 监督者和任务管理器共享的任务状态
+#[derive(Default)]
+struct TaskStatus {
+    /// 任务函数正常返回或关闭服务时设置
+    exited: AtomicBool,
+    /// 等待重启时设置
+    restarting: AtomicBool,
+    restarts: AtomicU32,
+    last_panic: Mutex<Option<String>>,
+}
+
+struct ManagedTask {
+    name: String,
+    status: Arc<TaskStatus>,
+    handle: JoinHandle<()>,
+}
+
+impl ManagedTask {
+    fn state(&self) -> TaskState {
+        if !self.handle.is_finished() {
+            if self.status.restarting.load(Ordering::Acquire) {
+                TaskState::Restarting
+            } else {
+                TaskState::Running
+            }
+        } else if self.status.exited.load(Ordering::Acquire) {
+            TaskState::Stopped
+        } else {
+            TaskState::Panicked
+        }
+    }
+}
+
+/// 任务被取消（包括监督者被中止）时中止正在运行的任务
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// 运行任务并在panic后按重启策略重新启动
+struct Supervisor {
+    name: String,
+    policy: RestartPolicy,
+    status: Arc<TaskStatus>,
+    metrics: Arc<OnceCell<Metrics>>,
+    token: CancellationToken,
+}
+
+impl Supervisor {
+    fn observe_up(&self, up: bool) {
+        if let Some(metrics) = self.metrics.get() {
+            metrics.observe_task_up(&self.name, up);
+        }
+    }
+
+    async fn run<F, Fut>(self, mut task: F)
+    where
+        F: FnMut(CancellationToken) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut attempt = 0;
+        loop {
+            self.observe_up(true);
+            let started = Instant::now();
+            let mut handle = tokio::spawn(task(self.token.clone()));
+            let _guard = AbortOnDrop(handle.abort_handle());
+            let result = (&mut handle).await;
+            self.observe_up(false);
+
+            let message = match result {
+                Ok(()) => {
+                    self.status.exited.store(true, Ordering::Release);
+                    return;
+                }
+                Err(e) if e.is_panic() => panic_message(e.into_panic()),
+                Err(_) => "任务被中止".to_string(),
+            };
+            error!("后台任务 {} 异常退出: {}", self.name, message);
+            *self.status.last_panic.lock() = Some(message);
+
+            if self.token.is_cancelled() {
+                return;
+            }
+            if started.elapsed() >= self.policy.reset_after {
+                attempt = 0;
+            }
+            if self.policy.max_restarts.is_some_and(|max| attempt >= max) {
+                if self.policy.max_restarts != Some(0) {
+                    error!("后台任务 {} 连续重启 {} 次后仍然异常退出，不再重启", self.name, attempt);
+                }
+                return;
+            }
+
+            let backoff = self.policy.backoff(attempt);
+            attempt += 1;
+            warn!("后台任务 {} 将在 {}ms 后重启", self.name, backoff.as_millis());
+            self.status.restarting.store(true, Ordering::Release);
+            tokio::select! {
+                _ = self.token.cancelled() => {
+                    self.status.exited.store(true, Ordering::Release);
+                    return;
+                }
+                _ = tokio::time::sleep(backoff) => {}
+            }
+            self.status.restarting.store(false, Ordering::Release);
+            self.status.restarts.fetch_add(1, Ordering::Relaxed);
+            if let Some(metrics) = self.metrics.get() {
+                metrics.observe_task_restart(&self.name);
+            }
+        }
+    }
+}
+
+/// panic的信息
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "未知的panic".to_string()
+    }
+}
+
+/// 后台任务管理器
+#[derive(Default)]
+pub struct TaskManager {
+    token: CancellationToken,
+    tasks: Mutex<Vec<ManagedTask>>,
+    metrics: Arc<OnceCell<Metrics>>,
+}
+
+impl std::fmt::Debug for TaskManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TaskManager")
+            .field("cancelled", &self.token.is_cancelled())
+            .field("tasks", &format!("<{} tasks>", self.tasks.lock().len()))
+            .finish()
+    }
+}
+
+impl TaskManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 关联指标，之后每个任务的运行状态和重启次数都会记录到指标中
+    pub fn attach_metrics(&self, metrics: Metrics) {
+        if self.metrics.set(metrics.clone()).is_ok() {
+            for task in self.tasks.lock().iter() {
+                metrics.observe_task_up(&task.name, task.state() == TaskState::Running);
+            }
+        }
+    }
+
+    /**
+     * 启动一个受管理的后台任务，异常退出后不重启
+     *
+     * 参数:
+     * @param name - 任务名称（用于日志和健康状态）
+     * @param task - 接收取消令牌并返回任务future的函数，令牌取消后任务应尽快返回
+     */
+    pub fn spawn<F, Fut>(&self, name: impl Into<String>, task: F)
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let token = self.token.child_token();
+        let mut future = Some(task(token.clone()));
+        self.start(name.into(), RestartPolicy::never(), token, move |_| {
+            future.take().expect("不重启的任务只运行一次")
+        });
+    }
+
+    /**
+     * 启动一个受监督的后台任务，异常退出后按重启策略重新启动
+     *
+     * 参数:
+     * @param name - 任务名称（用于日志、健康状态和指标）
+     * @param policy - 重启策略
+     * @param task - 每次启动时调用，接收取消令牌并返回任务future，令牌取消后任务应尽快返回
+     */
+    pub fn supervise<F, Fut>(&self, name: impl Into<String>, policy: RestartPolicy, task: F)
+    where
+        F: FnMut(CancellationToken) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.start(name.into(), policy, self.token.child_token(), task);
+    }
+
+    fn start<F, Fut>(&self, name: String, policy: RestartPolicy, token: CancellationToken, task: F)
+    where
+        F: FnMut(CancellationToken) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let status = Arc::new(TaskStatus::default());
+        let supervisor = Supervisor {
+            name: name.clone(),
+            policy,
+            status: status.clone(),
+            metrics: self.metrics.clone(),
+            token,
+        };
+        let handle = tokio::spawn(supervisor.run(task));
+        self.tasks.lock().push(ManagedTask { name, status, handle });
+    }
+
+    /// 是否已开始关闭
+    pub fn is_shutting_down(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// 所有任务的健康状态
+    pub fn health(&self) -> Vec<TaskHealth> {
+        self.tasks
+            .lock()
+            .iter()
+            .map(|task| TaskHealth {
+                name: task.name.clone(),
+                state: task.state(),
+                restarts: task.status.restarts.load(Ordering::Relaxed),
+                last_panic: task.status.last_panic.lock().clone(),
+            })
+            .collect()
+    }
+
+    /**
+     * 取消所有任务并等待退出
+     *
+     * 参数:
+     * @param timeout - 等待所有任务退出的总时间，超时后中止剩余的任务
+     */
+    pub async fn shutdown(&self, timeout: Duration) {
+        self.token.cancel();
+        let tasks = std::mem::take(&mut *self.tasks.lock());
+        info!("正在停止 {} 个后台任务", tasks.len());
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        for mut task in tasks {
+            match tokio::time::timeout_at(deadline, &mut task.handle).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) if e.is_panic() => warn!("后台任务 {} 异常退出", task.name),
+                Ok(Err(_)) => {}
+                Err(_) => {
+                    warn!("后台任务 {} 未能在时限内退出，直接中止", task.name);
+                    task.handle.abort();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shutdown_cancels_tasks() {
+        let manager = TaskManager::new();
+        manager.spawn("loop", |token| async move {
+            token.cancelled().await;
+        });
+        manager.spawn("stuck", |_| async move {
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+        });
+        assert!(manager.health().iter().all(|t| t.state == TaskState::Running));
+
+        manager.shutdown(Duration::from_millis(100)).await;
+        assert!(manager.is_shutting_down());
+        assert!(manager.health().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_panics() {
+        let manager = TaskManager::new();
+        manager.spawn("ok", |_| async {});
+        manager.spawn("panic", |_| async { panic!("boom") });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let health = manager.health();
+        assert_eq!(health[0].state, TaskState::Stopped);
+        assert_eq!(health[1].state, TaskState::Panicked);
+        assert_eq!(health[1].last_panic.as_deref(), Some("boom"));
+    }
+
+    #[tokio::test]
+    async fn test_supervise_restarts_with_backoff() {
+        let policy = RestartPolicy {
+            max_restarts: Some(2),
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(15),
+            reset_after: Duration::from_secs(60),
+        };
+        assert_eq!(policy.backoff(0), Duration::from_millis(10));
+        assert_eq!(policy.backoff(3), Duration::from_millis(15));
+
+        let manager = TaskManager::new();
+        let runs = Arc::new(AtomicU32::new(0));
+        let task_runs = runs.clone();
+        manager.supervise("flaky", policy, move |_| {
+            let run = task_runs.fetch_add(1, Ordering::SeqCst);
+            async move { panic!("run {}", run) }
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // 第一次运行加两次重启后放弃
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        let health = manager.health();
+        assert_eq!(health[0].state, TaskState::Panicked);
+        assert_eq!(health[0].restarts, 2);
+        assert_eq!(health[0].last_panic.as_deref(), Some("run 2"));
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 集成测试工具
+//!
+//! # 概述
+//! 在进程内启动WebSocket和对局服务，用模拟客户端走真实的WebSocket协议，
+//! 不需要Sui全节点、测试集群或密钥服务器配置，可以在CI中运行多人对局的端到端测试。
+//!
+//! - `TestServer`：进程内只启动一次的测试服务，监听随机端口，运行在独立的线程和运行时上，
+//!   各个 `#[tokio::test]` 共享同一个服务
+//! - `client::TestClient`：可编程的WebSocket客户端，按事件名等待消息，跳过的消息留待后续读取
+//! - `scenario::Scenario`：场景运行器，用固定的测试数据创建对局，并让客户端轮流行动直到对局结束
+//! - `rules::RulesHarness`：不经过WebSocket，直接按游戏规则执行随机动作并检查不变量，用于属性测试和模糊测试
+//!
+//! WebSocket和对局服务不依赖 `AppState`，测试服务只注册这部分路由，会话使用内存存储。
+//! 依赖链上数据的REST接口（Profile、好友、密钥服务）仍需使用 `tests` 模块的测试集群。
+//! 模拟客户端的ID同时作为用户ID，每个场景使用独立的ID前缀，互不干扰。
+//!
+//! # 接口
+//! - `test_server()`：获取共享的测试服务
+//!
+//! # 配置
+//! 通过 `test-support` feature 启用，crate自身的测试总是可用。
+//! 测试服务启动前会设置固定的 `MATCH_SEED_SECRET`，对局种子只取决于对局ID。
+
+pub mod client;
+pub mod rules;
+pub mod scenario;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::Router;
+use once_cell::sync::Lazy;
+use tower_sessions::SessionManagerLayer;
+
+use crate::session_store::{CitadelSessionStore, SessionStoreKind};
+
+/// 测试服务使用的对局种子密钥
+pub const TEST_SEED_SECRET: &str = "citadel-test-support";
+/// 等待消息的默认超时
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 进程内的测试服务
+#[derive(Debug, Clone, Copy)]
+pub struct TestServer {
+    addr: SocketAddr,
+}
+
+impl TestServer {
+    /// 监听地址
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// WebSocket连接地址，为指定的客户端ID签发恢复令牌，以便测试按ID找到对应的用户
+    pub fn ws_url(&self, client_id: &str) -> String {
+        let issued = crate::ws_resume::resume_tokens().issue(client_id, client_id, crate::externals::current_epoch_time());
+        format!("ws://{}/ws/reconnect?resume_token={}", self.addr, issued.token)
+    }
+
+    /// 启动测试服务，服务运行在独立的线程上，不随单个测试的运行时结束
+    fn start() -> Self {
+        std::env::set_var("MATCH_SEED_SECRET", TEST_SEED_SECRET);
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::Builder::new()
+            .name("citadel-test-server".to_string())
+            .spawn(move || {
+                let runtime = tokio::runtime::Builder::new_multi_thread()
+                    .enable_all()
+                    .build()
+                    .expect("创建测试服务运行时失败");
+                runtime.block_on(async move {
+                    let store = CitadelSessionStore::from_kind(&SessionStoreKind::Memory)
+                        .await
+                        .expect("创建会话存储失败");
+                    let app = crate::ws::register_ws_routes(Router::new())
+                        .layer(SessionManagerLayer::new(store).with_secure(false));
+                    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+                        .await
+                        .expect("绑定测试端口失败");
+                    tx.send(listener.local_addr().expect("读取测试端口失败"))
+                        .expect("发送测试端口失败");
+                    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+                        .await
+                        .expect("测试服务异常退出");
+                });
+            })
+            .expect("启动测试服务线程失败");
+        let addr = rx.recv().expect("测试服务启动失败");
+        Self { addr }
+    }
+}
+
+static TEST_SERVER: Lazy<TestServer> = Lazy::new(TestServer::start);
+
+/// 获取共享的测试服务，第一次调用时启动
+pub fn test_server() -> TestServer {
+    *TEST_SERVER
+}
@@ -0,0 +1,149 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 可编程的WebSocket测试客户端
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+use super::{TestServer, DEFAULT_TIMEOUT};
+use crate::ws::{WsMessage, WsResponse};
+
+/// 模拟客户端
+pub struct TestClient {
+    id: String,
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    /// 等待其他事件时跳过的消息，按收到的顺序保留
+    backlog: VecDeque<WsMessage>,
+    timeout: Duration,
+}
+
+impl TestClient {
+    /**
+     * 连接测试服务
+     *
+     * 参数:
+     * @param server - 测试服务
+     * @param client_id - 客户端ID，同时作为用户ID
+     *
+     * 返回:
+     * 已连接的客户端
+     */
+    pub async fn connect(server: &TestServer, client_id: &str) -> Result<Self> {
+        let (stream, _) = connect_async(server.ws_url(client_id))
+            .await
+            .with_context(|| format!("客户端 {} 连接失败", client_id))?;
+        Ok(Self {
+            id: client_id.to_string(),
+            stream,
+            backlog: VecDeque::new(),
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// 设置等待消息的超时
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 客户端ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// 发送事件
+    pub async fn send(&mut self, event: &str, data: Value) -> Result<()> {
+        let message = WsMessage {
+            event: event.to_string(),
+            data: Some(data),
+            msg_id: None,
+        };
+        self.stream
+            .send(Message::Text(serde_json::to_string(&message)?))
+            .await
+            .with_context(|| format!("客户端 {} 发送 {} 失败", self.id, event))
+    }
+
+    /// 读取下一条消息，先返回等待其他事件时跳过的消息
+    pub async fn recv(&mut self) -> Result<WsMessage> {
+        if let Some(message) = self.backlog.pop_front() {
+            return Ok(message);
+        }
+        self.read().await
+    }
+
+    /// 从连接读取下一条事件消息，Ping由底层自动回复
+    async fn read(&mut self) -> Result<WsMessage> {
+        loop {
+            let frame = tokio::time::timeout(self.timeout, self.stream.next())
+                .await
+                .map_err(|_| anyhow!("客户端 {} 等待消息超时", self.id))?
+                .ok_or_else(|| anyhow!("客户端 {} 的连接已关闭", self.id))??;
+            match frame {
+                Message::Text(text) => return Ok(serde_json::from_str(&text)?),
+                Message::Binary(bytes) => return Ok(serde_json::from_slice(&bytes)?),
+                Message::Close(frame) => return Err(anyhow!("客户端 {} 的连接被关闭: {:?}", self.id, frame)),
+                _ => continue,
+            }
+        }
+    }
+
+    /**
+     * 等待指定事件
+     *
+     * 之前跳过的消息优先匹配，其间收到的其他事件保留在队列中
+     *
+     * 参数:
+     * @param event - 事件名称
+     *
+     * 返回:
+     * 第一条匹配的消息
+     */
+    pub async fn expect(&mut self, event: &str) -> Result<WsMessage> {
+        if let Some(index) = self.backlog.iter().position(|m| m.event == event) {
+            return Ok(self.backlog.remove(index).expect("index in range"));
+        }
+        loop {
+            let message = self
+                .read()
+                .await
+                .with_context(|| format!("等待事件 {}", event))?;
+            if message.event == event {
+                return Ok(message);
+            }
+            self.backlog.push_back(message);
+        }
+    }
+
+    /// 等待指定事件并解析为响应
+    pub async fn expect_response(&mut self, event: &str) -> Result<WsResponse> {
+        let message = self.expect(event).await?;
+        let data = message.data.ok_or_else(|| anyhow!("事件 {} 没有数据", event))?;
+        Ok(serde_json::from_value(data)?)
+    }
+
+    /// 发送事件并等待回复
+    pub async fn request(&mut self, event: &str, data: Value, reply: &str) -> Result<WsResponse> {
+        self.send(event, data).await?;
+        self.expect_response(reply).await
+    }
+
+    /// 丢弃已跳过的消息
+    pub fn clear(&mut self) {
+        self.backlog.clear();
+    }
+
+    /// 关闭连接
+    pub async fn close(mut self) -> Result<()> {
+        self.stream.close(None).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,346 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 游戏规则的随机动作测试工具
+//!
+//! 使用重放验证的规则引擎（`match_replay`，卡牌效果与MatchService一致）逐个执行随机动作，
+//! 每一步之后检查不变量，供属性测试（`tests::rules`）和 `fuzz/` 下的cargo-fuzz目标使用：
+//! - 卡牌守恒：牌堆、所有玩家（包括出局玩家）的手牌和弃牌堆中的卡牌与开局时一致，没有重复或丢失
+//! - 回合合法：对局进行中只有 `turn_index` 指向的玩家处于回合中，出局玩家不在回合中
+//! - 被拒绝的动作不修改对局状态
+//! - 终止：从任意状态开始只抽牌，抽牌次数不超过牌堆张数加拆除卡张数
+//!
+//! 动作中的座位和卡牌都是序号，按当前状态取模，任意字节序列都能解码为一串动作。
+//! 与重放不同，出牌效果在出牌时立即结算，之后打出的烦人卡只进入弃牌堆；
+//! 抽到爆炸猫时手中有拆除卡则自动使用。
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::{bail, ensure, Result};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::game_mode::{self, GameMode};
+use crate::gaming::{Card, CardAction, CardActionType, CardType, MatchData, MatchState, MatchType, UserInfo, INITIAL_RATING};
+use crate::match_replay::{self, Seed};
+
+/// 每个动作编码占用的字节数
+pub const ACTION_BYTES: usize = 3;
+
+/// 随机动作，座位和卡牌为序号
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleAction {
+    /// 抽牌
+    Draw { seat: u8 },
+    /// 出牌
+    Play { seat: u8, card: u8 },
+    /// 打出烦人卡，手中有烦人卡时在烦人卡中选择
+    Nope { seat: u8, card: u8 },
+    /// 单独使用拆除卡，总是不合法
+    Defuse { seat: u8, card: u8 },
+    /// 离开对局
+    Leave { seat: u8 },
+}
+
+impl RuleAction {
+    /// 由3个字节解码，抽牌和出牌的比重较高，离开的比重最低
+    pub fn decode(bytes: [u8; ACTION_BYTES]) -> Self {
+        let [kind, seat, card] = bytes;
+        match kind % 16 {
+            0..=5 => RuleAction::Draw { seat },
+            6..=11 => RuleAction::Play { seat, card },
+            12 | 13 => RuleAction::Nope { seat, card },
+            14 => RuleAction::Defuse { seat, card },
+            _ => RuleAction::Leave { seat },
+        }
+    }
+
+    /// 把字节序列解码为动作序列，末尾不足一个动作的字节被忽略
+    pub fn decode_all(data: &[u8]) -> Vec<Self> {
+        data.chunks_exact(ACTION_BYTES)
+            .map(|chunk| Self::decode([chunk[0], chunk[1], chunk[2]]))
+            .collect()
+    }
+}
+
+/// 随机动作执行器
+pub struct RulesHarness {
+    mode: Arc<dyn GameMode>,
+    state: MatchData,
+    history: Vec<CardAction>,
+    seed: Seed,
+    /// 发牌后所有卡牌的ID
+    initial_cards: HashSet<String>,
+    accepted: usize,
+    rejected: usize,
+}
+
+impl RulesHarness {
+    /**
+     * 创建已发牌的对局
+     *
+     * 参数:
+     * @param players - 玩家数，超出默认游戏模式的范围时取最近的合法值
+     * @param seed - 随机种子，决定牌组顺序和卡牌效果
+     *
+     * 返回:
+     * 第一名玩家先行动的对局
+     */
+    pub fn new(players: usize, seed: u64) -> Self {
+        let mode = game_mode::global_game_modes()
+            .get(game_mode::DEFAULT_MODE)
+            .expect("默认游戏模式未注册");
+        let (min_players, max_players) = mode.player_range();
+        let users = (0..players.clamp(min_players, max_players))
+            .map(|i| UserInfo {
+                id: format!("player-{}", i),
+                name: format!("player-{}", i),
+                rating: INITIAL_RATING,
+                avatar_url: None,
+                provisional: false,
+            })
+            .collect::<Vec<_>>();
+
+        let mut state = MatchData::new("rules".to_string(), MatchType::Private, &users, 0);
+        state.mode = mode.id().to_string();
+        state.deck = mode.build_deck(users.len(), &mut StdRng::seed_from_u64(seed));
+        mode.deal(&mut state);
+        state.state = MatchState::InProgress;
+        state.players[0].is_turn = true;
+
+        let mut match_seed = Seed::default();
+        match_seed[..8].copy_from_slice(&seed.to_le_bytes());
+        let initial_cards = all_cards(&state).map(|c| c.id.clone()).collect();
+        Self {
+            mode,
+            state,
+            history: Vec::new(),
+            seed: match_seed,
+            initial_cards,
+            accepted: 0,
+            rejected: 0,
+        }
+    }
+
+    /**
+     * 创建对局，依次执行动作后只抽牌直到对局结束
+     *
+     * 参数:
+     * @param players - 玩家数
+     * @param seed - 随机种子
+     * @param actions - 随机动作
+     *
+     * 返回:
+     * 执行完毕的执行器；任一不变量不成立时返回错误
+     */
+    pub fn run(players: usize, seed: u64, actions: &[RuleAction]) -> Result<Self> {
+        let mut harness = Self::new(players, seed);
+        harness.check_invariants()?;
+        for action in actions {
+            harness.step(*action)?;
+        }
+        harness.finish_by_drawing()?;
+        Ok(harness)
+    }
+
+    /// 当前对局状态
+    pub fn state(&self) -> &MatchData {
+        &self.state
+    }
+
+    /// 被接受的动作，包括自动使用的拆除卡
+    pub fn history(&self) -> &[CardAction] {
+        &self.history
+    }
+
+    /// 被接受的随机动作数
+    pub fn accepted(&self) -> usize {
+        self.accepted
+    }
+
+    /// 被拒绝的随机动作数
+    pub fn rejected(&self) -> usize {
+        self.rejected
+    }
+
+    /// 对局是否已分出胜负
+    pub fn is_finished(&self) -> bool {
+        self.state.state == MatchState::Completed
+    }
+
+    /**
+     * 执行一个随机动作
+     *
+     * 参数:
+     * @param action - 随机动作，对局结束后的动作总是被拒绝
+     *
+     * 返回:
+     * 动作被接受时为true，被拒绝时为false；不变量不成立时返回错误
+     */
+    pub fn step(&mut self, action: RuleAction) -> Result<bool> {
+        if self.is_finished() {
+            self.rejected += 1;
+            return Ok(false);
+        }
+
+        let before = serde_json::to_value(&self.state)?;
+        let actions = self.resolve(action);
+        let start = self.history.len();
+        self.history.extend(actions);
+        for seq in start..self.history.len() {
+            let result = match_replay::apply_action(self.mode.as_ref(), &mut self.state, &self.history, seq, &self.seed);
+            match result {
+                Ok(()) => {}
+                Err(_) if seq == start => {
+                    // 动作不合法时对局状态保持不变
+                    ensure!(
+                        serde_json::to_value(&self.state)? == before,
+                        "被拒绝的动作 {:?} 修改了对局状态",
+                        action
+                    );
+                    self.history.truncate(start);
+                    self.rejected += 1;
+                    return Ok(false);
+                }
+                Err(e) => bail!("动作 {:?} 自动使用拆除卡失败: {}", action, e),
+            }
+        }
+
+        self.accepted += 1;
+        if self.mode.winner(&self.state).is_some() {
+            self.state.state = MatchState::Completed;
+        }
+        self.check_invariants()?;
+        Ok(true)
+    }
+
+    /**
+     * 由当前回合玩家一直抽牌直到对局结束
+     *
+     * 每次抽牌要么让牌堆减少一张，要么消耗一张拆除卡，因此次数不超过牌堆张数加上牌堆和手牌中的拆除卡张数
+     *
+     * 返回:
+     * 对局结束或牌堆抽完时的抽牌次数；超过上限仍未结束或回合玩家不能抽牌时返回错误
+     */
+    pub fn finish_by_drawing(&mut self) -> Result<usize> {
+        let defuses = self
+            .state
+            .deck
+            .iter()
+            .chain(self.state.players.iter().flat_map(|p| p.hand.iter()))
+            .filter(|c| c.card_type == CardType::Defuse)
+            .count();
+        let limit = self.state.deck.len() + defuses;
+
+        let mut draws = 0;
+        while !self.is_finished() && !self.state.deck.is_empty() {
+            ensure!(draws < limit, "抽牌 {} 次后对局仍未结束", limit);
+            let seat = self.state.turn_index as u8;
+            ensure!(self.step(RuleAction::Draw { seat })?, "回合中的玩家不能抽牌");
+            draws += 1;
+        }
+        Ok(draws)
+    }
+
+    /// 检查卡牌守恒和回合合法
+    pub fn check_invariants(&self) -> Result<()> {
+        let mut seen = HashSet::new();
+        for card in all_cards(&self.state) {
+            ensure!(seen.insert(card.id.as_str()), "卡牌 {} 重复出现", card.id);
+            // 内爆猫效果会向牌堆中加入新卡牌
+            ensure!(
+                self.initial_cards.contains(&card.id) || card.id.starts_with("imploding-"),
+                "卡牌 {} 不是开局时的卡牌",
+                card.id
+            );
+        }
+        if let Some(missing) = self.initial_cards.iter().find(|id| !seen.contains(id.as_str())) {
+            bail!("卡牌 {} 丢失", missing);
+        }
+
+        for player in &self.state.out {
+            ensure!(!player.is_active && !player.is_turn, "出局玩家 {} 仍在回合中", player.user.id);
+        }
+        if self.state.state == MatchState::InProgress {
+            let players = &self.state.players;
+            let in_turn = players
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| p.is_turn)
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+            ensure!(
+                self.state.turn_index < players.len() && in_turn == [self.state.turn_index],
+                "回合中的玩家为 {:?}，回合序号为 {}",
+                in_turn,
+                self.state.turn_index
+            );
+        }
+        Ok(())
+    }
+
+    /// 把随机动作转换为动作记录，抽到爆炸猫且手中有拆除卡时附加拆除动作
+    fn resolve(&self, action: RuleAction) -> Vec<CardAction> {
+        let players = &self.state.players;
+        let player = |seat: u8| &players[seat as usize % players.len()];
+        let pick = |cards: Vec<&Card>, index: u8| {
+            (!cards.is_empty()).then(|| cards[index as usize % cards.len()].clone())
+        };
+
+        match action {
+            RuleAction::Draw { seat } => {
+                let player = player(seat);
+                let top = self.state.deck.last();
+                let mut actions = vec![record(CardActionType::Draw, &player.user.id, top)];
+                if top.is_some_and(|c| c.card_type == CardType::ExplodingKitten) {
+                    if let Some(defuse) = player.hand.iter().find(|c| c.card_type == CardType::Defuse) {
+                        actions.push(record(CardActionType::Defuse, &player.user.id, Some(defuse)));
+                    }
+                }
+                actions
+            }
+            RuleAction::Play { seat, card } => {
+                let player = player(seat);
+                let card = pick(player.hand.iter().collect(), card);
+                vec![record(CardActionType::Play, &player.user.id, card.as_ref())]
+            }
+            RuleAction::Nope { seat, card } => {
+                let player = player(seat);
+                let nopes = player.hand.iter().filter(|c| c.card_type == CardType::Nope).collect::<Vec<_>>();
+                let card = if nopes.is_empty() {
+                    pick(player.hand.iter().collect(), card)
+                } else {
+                    pick(nopes, card)
+                };
+                vec![record(CardActionType::Nope, &player.user.id, card.as_ref())]
+            }
+            RuleAction::Defuse { seat, card } => {
+                let player = player(seat);
+                let card = pick(player.hand.iter().collect(), card);
+                vec![record(CardActionType::Defuse, &player.user.id, card.as_ref())]
+            }
+            RuleAction::Leave { seat } => vec![record(CardActionType::Eliminate, &player(seat).user.id, None)],
+        }
+    }
+}
+
+fn record(action_type: CardActionType, user_id: &str, card: Option<&Card>) -> CardAction {
+    CardAction {
+        action_type,
+        user_id: user_id.to_string(),
+        card_id: card.map(|c| c.id.clone()),
+        card_type: card.map(|c| c.card_type.clone()),
+        is_canceled: false,
+        created_at: 0,
+    }
+}
+
+/// 牌堆、弃牌堆和所有玩家手牌中的卡牌
+fn all_cards(state: &MatchData) -> impl Iterator<Item = &Card> {
+    state
+        .deck
+        .iter()
+        .chain(state.discard_pile.iter())
+        .chain(state.players.iter().chain(state.out.iter()).flat_map(|p| p.hand.iter()))
+}
@@ -0,0 +1,214 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 多人对局的场景运行器
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::json;
+use uuid::Uuid;
+
+use super::client::TestClient;
+use super::{test_server, DEFAULT_TIMEOUT};
+use crate::gaming::{self as match_game, events, MatchData, MatchState, MatchType, UserInfo};
+
+/// 轮询对局状态的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// 场景结束时的对局结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScenarioOutcome {
+    pub match_id: String,
+    /// 胜利者的玩家名称
+    pub winner: Option<String>,
+    /// 执行的回合数
+    pub turns: usize,
+}
+
+/// 一组模拟玩家
+pub struct Scenario {
+    /// 本场景的客户端ID前缀
+    prefix: String,
+    clients: HashMap<String, TestClient>,
+}
+
+impl Scenario {
+    /**
+     * 为每个玩家连接一个客户端
+     *
+     * 参数:
+     * @param players - 玩家名称，客户端ID为 `场景前缀-名称`
+     *
+     * 返回:
+     * 所有客户端都已连接的场景
+     */
+    pub async fn connect(players: &[&str]) -> Result<Self> {
+        let server = test_server();
+        let prefix = Uuid::new_v4().simple().to_string();
+        let mut clients = HashMap::new();
+        for name in players {
+            let client = TestClient::connect(&server, &format!("{}-{}", prefix, name)).await?;
+            clients.insert(name.to_string(), client);
+        }
+        Ok(Self { prefix, clients })
+    }
+
+    /// 玩家的客户端
+    pub fn client(&mut self, name: &str) -> &mut TestClient {
+        self.clients
+            .get_mut(name)
+            .unwrap_or_else(|| panic!("场景中没有玩家 {}", name))
+    }
+
+    /// 玩家的用户ID
+    pub fn user_id(&self, name: &str) -> String {
+        format!("{}-{}", self.prefix, name)
+    }
+
+    /// 按用户ID找到玩家名称
+    fn player_name(&self, user_id: &str) -> Option<String> {
+        user_id
+            .strip_prefix(&self.prefix)
+            .and_then(|rest| rest.strip_prefix('-'))
+            .filter(|name| self.clients.contains_key(*name))
+            .map(|name| name.to_string())
+    }
+
+    /**
+     * 创建对局并开始游戏
+     *
+     * 对局数据直接由对局服务创建，玩家按顺序入座，第一个玩家先行动；
+     * 之后各客户端通过WebSocket加入对局房间，由第一个玩家发送开始事件
+     *
+     * 参数:
+     * @param players - 入座的玩家名称
+     *
+     * 返回:
+     * 对局ID
+     */
+    pub async fn start_match(&mut self, players: &[&str]) -> Result<String> {
+        let match_service = match_game::global_match_service().context("对局服务未初始化")?;
+        let users = players
+            .iter()
+            .map(|name| UserInfo::with_rating(self.user_id(name), name.to_string(), None))
+            .collect();
+        let match_data = match_service.create_match(MatchType::Private, users).await?;
+        let match_id = match_data.id;
+
+        for name in players {
+            self.client(name)
+                .send("join_room", json!({ "roomId": match_id }))
+                .await?;
+            self.client(name).expect("room_joined").await?;
+        }
+
+        let host = players.first().ok_or_else(|| anyhow!("对局至少需要一名玩家"))?;
+        self.client(host)
+            .send(events::match_events::START, json!({ "matchId": match_id }))
+            .await?;
+        for name in players {
+            let response = self.client(name).expect_response(events::match_events::START).await?;
+            if !response.ok {
+                return Err(anyhow!("玩家 {} 收到开始失败: {:?}", name, response.msg));
+            }
+        }
+        Ok(match_id)
+    }
+
+    /**
+     * 让当前回合的玩家不断抽牌，直到对局结束
+     *
+     * 每回合读取服务端的权威对局数据确定行动的玩家，抽牌后等待回合切换或对局结束
+     *
+     * 参数:
+     * @param match_id - 对局ID
+     * @param max_turns - 最多执行的回合数，超过时返回错误
+     *
+     * 返回:
+     * 对局结果
+     */
+    pub async fn play_until_finished(&mut self, match_id: &str, max_turns: usize) -> Result<ScenarioOutcome> {
+        for turns in 0..=max_turns {
+            let match_data = current_match(match_id).await?;
+            if match_data.state == MatchState::Completed {
+                let winner = match_data
+                    .players
+                    .iter()
+                    .find(|p| p.is_winner)
+                    .and_then(|p| self.player_name(&p.user.id));
+                return Ok(ScenarioOutcome {
+                    match_id: match_id.to_string(),
+                    winner,
+                    turns,
+                });
+            }
+            if turns == max_turns {
+                break;
+            }
+
+            let current = match_data
+                .players
+                .iter()
+                .find(|p| p.is_turn)
+                .ok_or_else(|| anyhow!("对局 {} 没有当前回合的玩家", match_id))?;
+            let user_id = current.user.id.clone();
+            let name = self
+                .player_name(&user_id)
+                .ok_or_else(|| anyhow!("当前玩家 {} 不属于本场景", user_id))?;
+            let history_len = action_count(&match_data);
+
+            self.client(&name)
+                .send(events::match_events::DRAW_CARD, json!({ "matchId": match_id }))
+                .await?;
+            wait_for_match(match_id, |m| {
+                m.state == MatchState::Completed
+                    || (action_count(m) > history_len && !m.players.iter().any(|p| p.is_turn && p.user.id == user_id))
+            })
+            .await
+            .with_context(|| format!("等待玩家 {} 抽牌后切换回合", name))?;
+        }
+        Err(anyhow!("对局 {} 在 {} 回合内没有结束", match_id, max_turns))
+    }
+
+    /// 关闭所有客户端
+    pub async fn close(self) -> Result<()> {
+        for (_, client) in self.clients {
+            client.close().await?;
+        }
+        Ok(())
+    }
+}
+
+/// 包括已归档动作在内的动作总数
+fn action_count(match_data: &MatchData) -> usize {
+    match_data.action_history.len() + match_data.history_summary.archived_actions
+}
+
+/// 读取服务端的对局数据
+async fn current_match(match_id: &str) -> Result<MatchData> {
+    let match_service = match_game::global_match_service().context("对局服务未初始化")?;
+    match_service
+        .get_match(match_id)
+        .await
+        .ok_or_else(|| anyhow!("对局 {} 不存在", match_id))
+}
+
+/// 轮询对局数据，直到满足条件或超时
+pub async fn wait_for_match<F>(match_id: &str, condition: F) -> Result<MatchData>
+where
+    F: Fn(&MatchData) -> bool,
+{
+    let deadline = Instant::now() + DEFAULT_TIMEOUT;
+    loop {
+        let match_data = current_match(match_id).await?;
+        if condition(&match_data) {
+            return Ok(match_data);
+        }
+        if Instant::now() >= deadline {
+            return Err(anyhow!("等待对局 {} 状态变化超时", match_id));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
@@ -14,9 +14,13 @@
  * - tle: 时间限制执行模式测试
  * - whitelist: 白名单访问控制模式测试
  * - server: 服务器后台功能和更新机制测试
+ * - scenarios: 模拟客户端的多人对局场景测试，不需要测试集群
+ * - rules: 随机动作序列的游戏规则属性测试，不需要测试集群
  */
 use crate::externals::{add_latest, add_package};
 use crate::metrics::{start_basic_prometheus_server, METRICS_HOST_PORT};
+use crate::networks::{NetworkContext, NetworkRegistry};
+use crate::node_pool::SuiClientPool;
 use crate::sdk::GameManager;
 use crate::types::Network;
 use crate::{create_metrics, AppState};
@@ -43,6 +47,8 @@ use tokio::sync::watch::channel;
 mod e2e;
 mod externals;
 mod pd;
+mod rules;
+mod scenarios;
 mod server;
 mod tle;
 mod whitelist;
@@ -115,19 +121,30 @@ impl SealTestCluster {
             let rpc_url = cluster.rpc_url().to_string();
             
             // 创建 GameManager
-            let game_manager = GameManager::new(
+            let game_manager = Arc::new(GameManager::new(
                 cluster.sui_client().clone(),
                 Network::TestCluster,
                 ObjectID::ZERO,
                 ObjectID::ZERO,
-            ).await.unwrap();
+            ).await.unwrap());
+            let sui_pool = Arc::new(SuiClientPool::from_client(
+                Network::TestCluster.name(),
+                &rpc_url,
+                cluster.sui_client().clone(),
+            ));
+            let networks = NetworkRegistry::new(NetworkContext {
+                name: Network::TestCluster.name().to_string(),
+                network: Network::TestCluster,
+                sui_pool: sui_pool.clone(),
+                game_manager: game_manager.clone(),
+            });
             
             let server = SealKeyServer {
                 server: AppState {
                     eph_kp: AppState::generate_keypair(Some(42)),
                     config: std::collections::HashMap::new(),
                     metrics: create_metrics!(&server_registry_service),
-                    sui_client: cluster.sui_client().clone(),
+                    sui_pool,
                     network: Network::TestCluster,
                     master_key,
                     key_server_object_id: ObjectID::ZERO,
@@ -135,7 +152,10 @@ impl SealTestCluster {
                     latest_checkpoint_timestamp_receiver: channel(0).1,
                     reference_gas_price: channel(0).1,
                     citadel_package_id_receiver: channel(String::new()).1,
-                    game_manager: Arc::new(game_manager),
+                    game_manager,
+                    node_health: Default::default(),
+                    networks: Arc::new(networks),
+                    tasks: Default::default(),
                 },
                 public_key,
             };
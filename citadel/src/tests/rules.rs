@@ -0,0 +1,71 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+/**
+ * 游戏规则属性测试模块
+ *
+ * 使用 `test_support::rules` 按游戏规则执行随机生成的动作序列，验证卡牌守恒、回合合法、
+ * 被拒绝的动作不修改状态以及对局一定结束。失败时proptest会缩小到最短的动作序列。
+ * 同样的检查也可以通过 `fuzz/` 下的cargo-fuzz目标长时间运行。
+ */
+use proptest::prelude::*;
+
+use crate::test_support::rules::{RuleAction, RulesHarness, ACTION_BYTES};
+
+fn rule_action() -> impl Strategy<Value = RuleAction> {
+    any::<[u8; ACTION_BYTES]>().prop_map(RuleAction::decode)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    /// 任意动作序列之后所有不变量成立，只抽牌时对局在上限内结束
+    #[test]
+    fn test_random_actions_keep_invariants(
+        players in 2usize..=4,
+        seed in any::<u64>(),
+        actions in prop::collection::vec(rule_action(), 0..200),
+    ) {
+        let harness = RulesHarness::run(players, seed, &actions);
+        prop_assert!(harness.is_ok(), "{:?}", harness.err());
+    }
+
+    /// 相同的种子和动作序列得到相同的结果
+    #[test]
+    fn test_random_actions_are_deterministic(
+        seed in any::<u64>(),
+        actions in prop::collection::vec(rule_action(), 0..100),
+    ) {
+        let first = RulesHarness::run(3, seed, &actions).unwrap();
+        let second = RulesHarness::run(3, seed, &actions).unwrap();
+        prop_assert_eq!(
+            serde_json::to_value(first.state()).unwrap(),
+            serde_json::to_value(second.state()).unwrap()
+        );
+        prop_assert_eq!(first.accepted(), second.accepted());
+    }
+}
+
+/// 测试单独使用拆除卡和不在回合中的抽牌被拒绝
+#[test]
+fn test_out_of_turn_actions_rejected() {
+    let mut harness = RulesHarness::new(2, 7);
+    for card in 0..8 {
+        assert!(!harness.step(RuleAction::Defuse { seat: 0, card }).unwrap());
+    }
+    assert!(!harness.step(RuleAction::Draw { seat: 1 }).unwrap());
+    assert!(harness.step(RuleAction::Draw { seat: 0 }).unwrap());
+    assert_eq!(harness.accepted(), 1);
+    assert_eq!(harness.rejected(), 9);
+}
+
+/// 测试玩家离开后剩下的玩家获胜，之后的动作都被拒绝
+#[test]
+fn test_leave_finishes_match() {
+    let mut harness = RulesHarness::new(2, 1);
+    assert!(harness.step(RuleAction::Leave { seat: 0 }).unwrap());
+    assert!(harness.is_finished());
+    assert_eq!(harness.state().players[0].user.id, "player-1");
+    assert!(!harness.step(RuleAction::Draw { seat: 0 }).unwrap());
+    assert_eq!(harness.finish_by_drawing().unwrap(), 0);
+}
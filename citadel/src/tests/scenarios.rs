@@ -0,0 +1,53 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+/**
+ * 多人对局场景测试模块
+ *
+ * 使用 `test_support` 的进程内测试服务和模拟客户端，通过真实的WebSocket协议
+ * 验证对局的开始、回合切换和结束，不需要Sui测试集群。
+ */
+use crate::gaming::{global_match_service, MatchState};
+use crate::test_support::scenario::Scenario;
+
+/// 测试对局开始后所有玩家收到开始事件，第一个玩家先行动
+#[tokio::test]
+async fn test_start_match() {
+    let mut scenario = Scenario::connect(&["alice", "bob"]).await.unwrap();
+    let match_id = scenario.start_match(&["alice", "bob"]).await.unwrap();
+
+    let match_data = global_match_service().unwrap().get_match(&match_id).await.unwrap();
+    assert_eq!(match_data.state, MatchState::InProgress);
+    assert_eq!(match_data.players.len(), 2);
+    let current = match_data.players.iter().find(|p| p.is_turn).unwrap();
+    assert_eq!(current.user.id, scenario.user_id("alice"));
+
+    scenario.close().await.unwrap();
+}
+
+/// 测试玩家轮流抽牌直到对局结束，胜利者是场景中的玩家
+#[tokio::test]
+async fn test_play_until_finished() {
+    let mut scenario = Scenario::connect(&["alice", "bob", "carol"]).await.unwrap();
+    let match_id = scenario.start_match(&["alice", "bob", "carol"]).await.unwrap();
+
+    let outcome = scenario.play_until_finished(&match_id, 500).await.unwrap();
+    assert!(outcome.turns > 0);
+    let winner = outcome.winner.unwrap();
+    assert!(["alice", "bob", "carol"].contains(&winner.as_str()));
+
+    // 胜利事件广播给房间中的所有玩家
+    for name in ["alice", "bob", "carol"] {
+        let victory = scenario
+            .client(name)
+            .expect_response("match:victory")
+            .await
+            .unwrap();
+        assert!(victory.ok);
+    }
+
+    let match_data = global_match_service().unwrap().get_match(&match_id).await.unwrap();
+    assert_eq!(match_data.state, MatchState::Completed);
+
+    scenario.close().await.unwrap();
+}
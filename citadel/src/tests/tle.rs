@@ -3,7 +3,11 @@
 
 use crate::errors::InternalError;
 use crate::externals::current_epoch_time;
-use crate::keys::check_request;
+use crate::event_signing::verify_signed_event;
+use crate::keys::{
+    check_request, create_response, keys_digest, request_digest, sign_key_response, KeyResponseBinding,
+};
+use crate::signed_message::signed_request;
 /**
  * 时间限制执行(Time-Limited Execution, TLE)测试模块
  *
@@ -296,6 +300,59 @@ async fn test_tle_signed_request() {
     // 等等...
 }
 
+/**
+ * 测试密钥响应的请求绑定
+ *
+ * 此测试验证服务器对响应的签名：
+ * 1. 绑定可以用服务器的临时公钥验证
+ * 2. 篡改请求摘要后验证失败
+ */
+#[traced_test]
+#[tokio::test]
+async fn test_key_response_binding() {
+    let mut tc = SealTestCluster::new(1, 1).await;
+    let (package_id, _) = tc.publish("patterns").await;
+
+    let ptb = tle_create_ptb(package_id, 1);
+    let (_, pk, vk) = elgamal::genkey(&mut thread_rng());
+    let (cert, req_sig) = sign(
+        &package_id,
+        &ptb,
+        &pk,
+        &vk,
+        &tc.users[0].keypair,
+        current_epoch_time(),
+        1,
+    );
+    let result = check_request(
+        tc.server(),
+        &ptb_to_base64(&ptb),
+        &pk,
+        &vk,
+        &req_sig,
+        &cert,
+        1000,
+        None,
+        None,
+    )
+    .await;
+    assert!(result.is_ok());
+
+    let server = tc.server();
+    let response = create_response(server, &result.unwrap(), &pk);
+    let binding = KeyResponseBinding {
+        service_id: server.key_server_object_id,
+        request_digest: request_digest(&signed_request(&ptb, &pk, &vk), &req_sig),
+        keys_digest: keys_digest(&response.decryption_keys),
+        checkpoint_timestamp: current_epoch_time(),
+    };
+    let signed = sign_key_response(&server.eph_kp, binding, current_epoch_time());
+    assert!(verify_signed_event(server.eph_kp.public(), &signed));
+    let mut tampered = signed;
+    tampered.response.data.request_digest = request_digest(b"other request", &req_sig);
+    assert!(!verify_signed_event(server.eph_kp.public(), &tampered));
+}
+
 /**
  * 生成TLE ID
  *
@@ -0,0 +1,127 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 回合时间储备
+//!
+//! # 概述
+//! 每名玩家在一局游戏中拥有一份时间储备（默认90秒）。每回合先使用基础回合时间
+//! （`queue_constants::inactivity::COMMON`），超出部分才从储备中扣除，
+//! 因此回合超时时间为基础时间加上剩余储备。回合计时器到期说明储备已经耗尽，此时才淘汰玩家。
+//!
+//! 回合结束时按计时器的剩余时间结算：剩余时间不少于储备说明基础时间内完成，储备不变；
+//! 否则储备减少为剩余时间。连锁等待和暂停期间回合计时冻结，不消耗储备。
+//!
+//! 回合变更事件（`match:turn_change`）的载荷中带有 `turnTime`（基础回合时间）和
+//! `timeBank`（当前玩家的剩余储备），单位都是毫秒。
+//!
+//! # 配置
+//! - `TURN_TIME_BANK_MS`：每名玩家每局的时间储备（毫秒），默认 90000
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+/// 默认的时间储备（毫秒）
+pub const DEFAULT_TIME_BANK_MS: u64 = 90 * 1000;
+
+static TIME_BANK_MS: Lazy<u64> = Lazy::new(|| {
+    std::env::var("TURN_TIME_BANK_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_TIME_BANK_MS)
+});
+
+/// 每名玩家每局的时间储备（毫秒）
+pub fn initial_bank() -> u64 {
+    *TIME_BANK_MS
+}
+
+/// 一局游戏中所有玩家的剩余时间储备
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimeBanks {
+    /// 玩家ID到剩余储备（毫秒）的映射，没有记录的玩家使用完整储备
+    banks: HashMap<String, u64>,
+    /// 本局的完整储备（毫秒）
+    #[serde(default = "initial_bank")]
+    initial: u64,
+}
+
+impl TimeBanks {
+    /**
+     * 为开局玩家分配时间储备
+     *
+     * 参数:
+     * @param user_ids - 玩家ID
+     * @param initial - 每名玩家的储备（毫秒）
+     *
+     * 返回:
+     * 时间储备
+     */
+    pub fn new<'a>(user_ids: impl IntoIterator<Item = &'a str>, initial: u64) -> Self {
+        TimeBanks {
+            banks: user_ids.into_iter().map(|id| (id.to_string(), initial)).collect(),
+            initial,
+        }
+    }
+
+    /// 玩家的剩余储备（毫秒）
+    pub fn remaining(&self, user_id: &str) -> u64 {
+        self.banks.get(user_id).copied().unwrap_or(self.initial)
+    }
+
+    /**
+     * 玩家本回合的超时时间
+     *
+     * 参数:
+     * @param user_id - 玩家ID
+     * @param turn_time - 基础回合时间（毫秒）
+     *
+     * 返回:
+     * 基础回合时间加剩余储备（毫秒）
+     */
+    pub fn turn_timeout(&self, user_id: &str, turn_time: u64) -> u64 {
+        turn_time + self.remaining(user_id)
+    }
+
+    /**
+     * 回合结束时结算储备
+     *
+     * 储备是回合超时时间的最后一段，剩余时间少于储备时，储备减少为剩余时间。
+     *
+     * 参数:
+     * @param user_id - 玩家ID
+     * @param remaining_ms - 回合计时器的剩余时间（毫秒）
+     *
+     * 返回:
+     * 结算后的剩余储备（毫秒）
+     */
+    pub fn settle(&mut self, user_id: &str, remaining_ms: u64) -> u64 {
+        let bank = self.remaining(user_id).min(remaining_ms);
+        self.banks.insert(user_id.to_string(), bank);
+        bank
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settle() {
+        let mut banks = TimeBanks::new(["a", "b"], 90_000);
+        assert_eq!(banks.turn_timeout("a", 30_000), 120_000);
+
+        // 基础时间内完成，储备不变
+        assert_eq!(banks.settle("a", 100_000), 90_000);
+        // 超出基础时间20秒
+        assert_eq!(banks.settle("a", 70_000), 70_000);
+        assert_eq!(banks.turn_timeout("a", 30_000), 100_000);
+        // 储备耗尽
+        assert_eq!(banks.settle("b", 0), 0);
+        assert_eq!(banks.turn_timeout("b", 30_000), 30_000);
+
+        // 中途加入的玩家使用完整储备
+        assert_eq!(banks.remaining("c"), 90_000);
+    }
+}
@@ -0,0 +1,397 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 对局时间线模块
+//!
+//! # 概述
+//! 将已结束对局的 `action_history` 和 `turn_log` 整理为按时间排序的时间线，供前端渲染赛后复盘界面：
+//! - 回合边界：每个已结束回合的开始时间、玩家和时长
+//! - 玩家动作：出牌、烦人卡、抽牌、拆除、出局，每个动作标注所在回合
+//! - 连锁结算：每次出牌的连锁最终生效还是被烦人卡取消
+//!
+//! 时间线按请求者的视角裁剪：出牌和烦人卡是公开信息，抽牌和拆除只有动作玩家本人可以看到卡牌，
+//! 最终手牌也只返回请求者本人的，其他玩家只返回手牌数量。未登录的请求按观众视角返回。
+//!
+//! 进行中的对局不提供时间线，避免泄露对局信息。时间线包括已归档的动作（见 `match_history`），
+//! 对局数据和归档按缓存过期时间清理，过期后无法再查询。
+//!
+//! # 接口
+//! - `GET /v1/matches/:match_id/timeline`
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    routing::get,
+    Extension, Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use tracing::info;
+use utoipa::ToSchema;
+
+use crate::errors::{ErrorResponse, InternalError};
+use crate::gaming::{self, Card, CardAction, CardActionType, CardType, MatchData, MatchState, TurnRecord, UserInfo};
+use crate::session_login::{SessionUser, SESSION_USER_KEY};
+use crate::AppState;
+
+/// 连锁结算结果
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainOutcome {
+    /// 等待结束后生效
+    Resolved,
+    /// 被烦人卡取消
+    Canceled,
+}
+
+/// 时间线条目
+#[derive(Debug, Clone, Serialize, PartialEq, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TimelineEntry {
+    /// 回合开始
+    #[serde(rename_all = "camelCase")]
+    TurnStart {
+        /// 回合序号，从1开始
+        turn: usize,
+        user_id: String,
+        at: u64,
+        /// 回合时长（毫秒），不包括暂停的时间
+        duration_ms: u64,
+    },
+    /// 玩家动作
+    #[serde(rename_all = "camelCase")]
+    Action {
+        /// 动作序号，对应 `action_history` 中的位置
+        seq: usize,
+        /// 所在回合，第一个回合开始前为0
+        turn: usize,
+        user_id: String,
+        #[schema(value_type = String)]
+        action: CardActionType,
+        /// 卡牌类型，对请求者不可见时为空
+        #[schema(value_type = Option<String>)]
+        card_type: Option<CardType>,
+        /// 卡牌ID，对请求者不可见时为空
+        card_id: Option<String>,
+        /// 卡牌是否对请求者隐藏
+        redacted: bool,
+        at: u64,
+    },
+    /// 连锁结算
+    #[serde(rename_all = "camelCase")]
+    ChainResolved {
+        /// 发起连锁的动作序号
+        seq: usize,
+        turn: usize,
+        outcome: ChainOutcome,
+        /// 打出烦人卡的玩家
+        canceled_by: Option<String>,
+        at: u64,
+    },
+}
+
+impl TimelineEntry {
+    /// 条目时间
+    pub fn at(&self) -> u64 {
+        match self {
+            TimelineEntry::TurnStart { at, .. }
+            | TimelineEntry::Action { at, .. }
+            | TimelineEntry::ChainResolved { at, .. } => *at,
+        }
+    }
+
+    /// 同一时间的条目顺序：回合开始、动作、连锁结算
+    fn rank(&self) -> u8 {
+        match self {
+            TimelineEntry::TurnStart { .. } => 0,
+            TimelineEntry::Action { .. } => 1,
+            TimelineEntry::ChainResolved { .. } => 2,
+        }
+    }
+}
+
+/// 时间线中的玩家
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelinePlayer {
+    #[schema(value_type = Object)]
+    pub user: UserInfo,
+    pub is_winner: bool,
+    /// 出局顺序，从1开始，未出局为空
+    pub eliminated_order: Option<usize>,
+    /// 最终手牌数量
+    pub hand_size: usize,
+    /// 最终手牌，只返回请求者本人的
+    #[schema(value_type = Option<Vec<Object>>)]
+    pub final_hand: Option<Vec<Card>>,
+}
+
+/// 对局时间线
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchTimeline {
+    pub match_id: String,
+    /// 时间线视角的用户ID，观众视角为空
+    pub perspective: Option<String>,
+    pub created_at: u64,
+    pub ended_at: u64,
+    pub players: Vec<TimelinePlayer>,
+    pub entries: Vec<TimelineEntry>,
+}
+
+/// 时间线响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MatchTimelineResponse {
+    pub success: bool,
+    pub timeline: Option<MatchTimeline>,
+    pub error: Option<String>,
+}
+
+/// 请求者是否可以看到动作的卡牌
+fn is_visible(action: &CardAction, viewer: Option<&str>) -> bool {
+    match action.action_type {
+        CardActionType::Play | CardActionType::Nope | CardActionType::Eliminate | CardActionType::Discard => true,
+        CardActionType::Draw | CardActionType::Defuse => viewer == Some(action.user_id.as_str()),
+    }
+}
+
+/**
+ * 整理动作历史和回合记录为时间线条目
+ *
+ * 出牌进入连锁，下一次出牌之前的烦人卡会取消该连锁；没有被取消的连锁在等待时间结束后生效。
+ *
+ * 参数:
+ * @param history - 动作历史
+ * @param turns - 已结束的回合
+ * @param chain_wait_time - 连锁等待时间（毫秒）
+ * @param viewer - 请求者ID，观众视角为None
+ *
+ * 返回:
+ * 按时间排序的时间线条目
+ */
+pub fn build_entries(
+    history: &[CardAction],
+    turns: &[TurnRecord],
+    chain_wait_time: u64,
+    viewer: Option<&str>,
+) -> Vec<TimelineEntry> {
+    let turn_at = |at: u64| turns.iter().take_while(|t| t.started_at <= at).count();
+    let mut entries: Vec<TimelineEntry> = turns
+        .iter()
+        .enumerate()
+        .map(|(index, t)| TimelineEntry::TurnStart {
+            turn: index + 1,
+            user_id: t.user_id.clone(),
+            at: t.started_at,
+            duration_ms: t.duration_ms,
+        })
+        .collect();
+
+    for (seq, action) in history.iter().enumerate() {
+        let visible = is_visible(action, viewer);
+        entries.push(TimelineEntry::Action {
+            seq,
+            turn: turn_at(action.created_at),
+            user_id: action.user_id.clone(),
+            action: action.action_type.clone(),
+            card_type: action.card_type.clone().filter(|_| visible),
+            card_id: action.card_id.clone().filter(|_| visible),
+            redacted: !visible,
+            at: action.created_at,
+        });
+
+        if action.action_type != CardActionType::Play {
+            continue;
+        }
+        let nope = history[seq + 1..]
+            .iter()
+            .take_while(|a| a.action_type != CardActionType::Play)
+            .find(|a| a.action_type == CardActionType::Nope);
+        let (outcome, canceled_by, at) = match nope {
+            Some(nope) => (ChainOutcome::Canceled, Some(nope.user_id.clone()), nope.created_at),
+            None => (ChainOutcome::Resolved, None, action.created_at + chain_wait_time),
+        };
+        entries.push(TimelineEntry::ChainResolved {
+            seq,
+            turn: turn_at(action.created_at),
+            outcome,
+            canceled_by,
+            at,
+        });
+    }
+
+    entries.sort_by_key(|e| (e.at(), e.rank()));
+    entries
+}
+
+/**
+ * 生成对局时间线
+ *
+ * 参数:
+ * @param match_data - 已结束的对局
+ * @param viewer - 请求者ID，不是本局玩家时按观众视角处理
+ *
+ * 返回:
+ * 按请求者视角裁剪后的时间线
+ */
+pub fn build_timeline(match_data: &MatchData, viewer: Option<&str>) -> MatchTimeline {
+    let viewer = viewer.filter(|id| {
+        match_data.players.iter().chain(match_data.out.iter()).any(|p| p.user.id == *id)
+    });
+
+    // 出局列表按出局顺序排列
+    let players = match_data.players.iter().map(|p| (p, None))
+        .chain(match_data.out.iter().enumerate().map(|(i, p)| (p, Some(i + 1))))
+        .map(|(p, eliminated_order)| TimelinePlayer {
+            user: p.user.clone(),
+            is_winner: p.is_winner,
+            eliminated_order,
+            hand_size: p.hand.len(),
+            final_hand: (viewer == Some(p.user.id.as_str())).then(|| p.hand.clone()),
+        })
+        .collect();
+
+    MatchTimeline {
+        match_id: match_data.id.clone(),
+        perspective: viewer.map(str::to_string),
+        created_at: match_data.created_at,
+        ended_at: match_data.updated_at,
+        players,
+        entries: build_entries(&match_data.action_history, &match_data.turn_log, match_data.chain_wait_time, viewer),
+    }
+}
+
+/// 获取已结束对局的时间线
+#[utoipa::path(
+    get,
+    path = "/v1/matches/{match_id}/timeline",
+    tag = "matches",
+    params(("match_id" = String, Path, description = "对局ID")),
+    responses(
+        (status = 200, description = "按请求者视角裁剪的对局时间线", body = MatchTimelineResponse),
+        (status = 404, description = "对局不存在或已过期", body = ErrorResponse),
+        (status = 409, description = "对局尚未结束", body = ErrorResponse),
+    )
+)]
+pub async fn handle_get_match_timeline(
+    State(app_state): State<Arc<AppState>>,
+    Path(match_id): Path<String>,
+    Extension(session): Extension<Session>,
+) -> Result<Json<MatchTimelineResponse>, InternalError> {
+    info!("收到获取对局时间线请求: {}", match_id);
+    app_state.metrics.observe_request("get_match_timeline");
+
+    let Some(match_service) = gaming::global_match_service() else {
+        return Ok(Json(MatchTimelineResponse {
+            success: false,
+            timeline: None,
+            error: Some("游戏服务未初始化".to_string()),
+        }));
+    };
+
+    let match_data = match_service.get_match_with_history(&match_id).await.ok_or(InternalError::MatchNotFound)?;
+    if match_data.state != MatchState::Completed {
+        return Err(InternalError::MatchInProgress);
+    }
+
+    // 未登录按观众视角返回
+    let viewer = session
+        .get::<SessionUser>(SESSION_USER_KEY)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|u| u.profile)
+        .map(|p| p.id.to_string());
+
+    Ok(Json(MatchTimelineResponse {
+        success: true,
+        timeline: Some(build_timeline(&match_data, viewer.as_deref())),
+        error: None,
+    }))
+}
+
+/// 注册对局时间线路由
+pub fn register_timeline_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router.route("/v1/matches/:match_id/timeline", get(handle_get_match_timeline))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(action_type: CardActionType, user_id: &str, card_type: CardType, created_at: u64) -> CardAction {
+        CardAction {
+            action_type,
+            user_id: user_id.to_string(),
+            card_id: Some(format!("{:?}-{}", card_type, created_at)),
+            card_type: Some(card_type),
+            is_canceled: false,
+            created_at,
+        }
+    }
+
+    fn turn(user_id: &str, started_at: u64, duration_ms: u64) -> TurnRecord {
+        TurnRecord {
+            user_id: user_id.to_string(),
+            started_at,
+            duration_ms,
+        }
+    }
+
+    #[test]
+    fn test_chain_resolution_and_turns() {
+        let history = vec![
+            action(CardActionType::Play, "a", CardType::Skip, 1_000),
+            action(CardActionType::Nope, "b", CardType::Nope, 1_500),
+            action(CardActionType::Play, "a", CardType::Attack, 2_000),
+        ];
+        let turns = vec![turn("a", 500, 5_000), turn("b", 5_500, 1_000)];
+        let entries = build_entries(&history, &turns, 3_000, None);
+
+        let kinds: Vec<(u64, u8)> = entries.iter().map(|e| (e.at(), e.rank())).collect();
+        assert_eq!(kinds, vec![(500, 0), (1_000, 1), (1_500, 1), (1_500, 2), (2_000, 1), (5_000, 2), (5_500, 0)]);
+
+        let chains: Vec<_> = entries
+            .iter()
+            .filter_map(|e| match e {
+                TimelineEntry::ChainResolved { seq, turn, outcome, canceled_by, .. } => {
+                    Some((*seq, *turn, *outcome, canceled_by.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            chains,
+            vec![
+                (0, 1, ChainOutcome::Canceled, Some("b".to_string())),
+                (2, 1, ChainOutcome::Resolved, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redaction_by_perspective() {
+        let history = vec![
+            action(CardActionType::Draw, "a", CardType::Defuse, 1_000),
+            action(CardActionType::Play, "b", CardType::Shuffle, 2_000),
+        ];
+        let card_of = |entries: &[TimelineEntry], seq: usize| {
+            entries.iter().find_map(|e| match e {
+                TimelineEntry::Action { seq: s, card_type, redacted, .. } if *s == seq => {
+                    Some((card_type.clone(), *redacted))
+                }
+                _ => None,
+            })
+        };
+
+        let own = build_entries(&history, &[], 3_000, Some("a"));
+        assert_eq!(card_of(&own, 0), Some((Some(CardType::Defuse), false)));
+
+        for viewer in [Some("b"), None] {
+            let other = build_entries(&history, &[], 3_000, viewer);
+            assert_eq!(card_of(&other, 0), Some((None, true)));
+            assert_eq!(card_of(&other, 1), Some((Some(CardType::Shuffle), false)));
+        }
+    }
+}
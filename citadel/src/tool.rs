@@ -43,6 +43,18 @@ pub fn split_into_chunks<T: Clone>(array: &[T], chunk_size: usize) -> Vec<Vec<T>
 
 /// ELO评分系统模块，提供评分计算功能
 pub mod elo {
+    /// 定级赛场数，完成前评分为临时评分
+    pub const PLACEMENT_GAMES: u64 = 10;
+    /// 达到该场数后视为老玩家，评分变化减小
+    pub const VETERAN_GAMES: u64 = 100;
+    /// 评分下限
+    pub const RATING_FLOOR: i32 = 100;
+
+    /// 评分是否仍为临时评分（未完成定级赛）
+    pub fn is_provisional(games_played: u64) -> bool {
+        games_played < PLACEMENT_GAMES
+    }
+
     /// 定义比赛结果的枚举
     #[derive(Debug, Clone, Copy, PartialEq)]
     pub enum MatchOutcome {
@@ -61,6 +73,16 @@ pub mod elo {
         
         /// 计算胜利调整系数
         fn victory_adjustment(&self, shift: f64, opponents_count: usize) -> f64;
+        
+        /// 根据已完成的场数获取K因子，默认与场数无关
+        fn k_factor_for(&self, _games_played: u64) -> f64 {
+            self.k_factor()
+        }
+        
+        /// 获取评分下限
+        fn rating_floor(&self) -> i32 {
+            RATING_FLOOR
+        }
     }
 
     /// 默认的ELO配置实现
@@ -96,6 +118,87 @@ pub mod elo {
         }
     }
 
+    /// 按场数调整K因子的规则
+    #[derive(Debug, Clone)]
+    pub struct KFactorSchedule {
+        /// 基础K因子
+        pub base: f64,
+        /// 定级赛场数
+        pub placement_games: u64,
+        /// 定级赛期间的K因子倍数，使新玩家更快收敛到真实水平
+        pub placement_multiplier: f64,
+        /// 老玩家场数
+        pub veteran_games: u64,
+        /// 老玩家的K因子倍数，使评分更稳定
+        pub veteran_multiplier: f64,
+    }
+
+    impl KFactorSchedule {
+        /// 使用推荐值创建K因子规则
+        pub fn default() -> Self {
+            Self {
+                base: 70.0,
+                placement_games: PLACEMENT_GAMES,
+                placement_multiplier: 2.0,
+                veteran_games: VETERAN_GAMES,
+                veteran_multiplier: 0.5,
+            }
+        }
+        
+        /// 根据已完成的场数获取K因子
+        pub fn k_for(&self, games_played: u64) -> f64 {
+            if games_played < self.placement_games {
+                self.base * self.placement_multiplier
+            } else if games_played >= self.veteran_games {
+                self.base * self.veteran_multiplier
+            } else {
+                self.base
+            }
+        }
+    }
+
+    /// 按场数调整K因子并带评分下限的ELO配置
+    #[derive(Debug, Clone)]
+    pub struct ScaledEloConfig {
+        perf: f64,
+        schedule: KFactorSchedule,
+        floor: i32,
+    }
+
+    impl ScaledEloConfig {
+        /// 创建新的ELO配置
+        pub fn new(perf: f64, schedule: KFactorSchedule, floor: i32) -> Self {
+            Self { perf, schedule, floor }
+        }
+        
+        /// 使用推荐值创建ELO配置
+        pub fn default() -> Self {
+            Self::new(400.0, KFactorSchedule::default(), RATING_FLOOR)
+        }
+    }
+
+    impl EloConfig for ScaledEloConfig {
+        fn performance_constant(&self) -> f64 {
+            self.perf
+        }
+        
+        fn k_factor(&self) -> f64 {
+            self.schedule.base
+        }
+        
+        fn victory_adjustment(&self, shift: f64, opponents_count: usize) -> f64 {
+            shift * opponents_count as f64
+        }
+        
+        fn k_factor_for(&self, games_played: u64) -> f64 {
+            self.schedule.k_for(games_played)
+        }
+        
+        fn rating_floor(&self) -> i32 {
+            self.floor
+        }
+    }
+
     /// ELO计算器
     #[derive(Debug, Clone)]
     pub struct EloCalculator<C: EloConfig> {
@@ -125,6 +228,26 @@ pub mod elo {
         
         /// 计算新的评分
         pub fn calculate_new_rating(&self, rating: i32, opponents: &[i32], outcome: MatchOutcome) -> i32 {
+            self.apply(rating, self.config.k_factor(), opponents, outcome)
+        }
+        
+        /**
+         * 根据玩家已完成的场数计算新的评分
+         *
+         * 参数:
+         * @param rating - 当前评分
+         * @param games_played - 本局之前已完成的场数
+         * @param opponents - 对手评分
+         * @param outcome - 比赛结果
+         *
+         * 返回:
+         * 新的评分，不低于评分下限
+         */
+        pub fn calculate_new_rating_for(&self, rating: i32, games_played: u64, opponents: &[i32], outcome: MatchOutcome) -> i32 {
+            self.apply(rating, self.config.k_factor_for(games_played), opponents, outcome)
+        }
+        
+        fn apply(&self, rating: i32, k_factor: f64, opponents: &[i32], outcome: MatchOutcome) -> i32 {
             if opponents.is_empty() {
                 return rating;
             }
@@ -139,14 +262,16 @@ pub mod elo {
             };
             
             let score_diff = actual_score - expected;
-            let mut shift = self.config.k_factor() * score_diff;
+            let mut shift = k_factor * score_diff;
             
             // 胜利时的额外调整
             if outcome == MatchOutcome::Victory {
                 shift = self.config.victory_adjustment(shift, opponents.len());
             }
             
-            (rating as f64 + shift).round() as i32
+            // 失败不会使评分低于下限，已低于下限的评分不会因失败继续降低
+            let new_rating = (rating as f64 + shift).round() as i32;
+            new_rating.max(self.config.rating_floor().min(rating))
         }
         
         /// 如果获胜，计算新的评分
@@ -174,6 +299,16 @@ pub mod elo {
     pub fn if_lost(rating: i32, opponents: &[i32]) -> i32 {
         create_default_calculator().if_lost(rating, opponents)
     }
+    
+    /// 便捷函数，创建按场数调整K因子的ELO计算器
+    pub fn create_scaled_calculator() -> EloCalculator<ScaledEloConfig> {
+        EloCalculator::new(ScaledEloConfig::default())
+    }
+    
+    /// 使用按场数调整K因子的计算器计算新评分
+    pub fn rating_after(rating: i32, games_played: u64, opponents: &[i32], outcome: MatchOutcome) -> i32 {
+        create_scaled_calculator().calculate_new_rating_for(rating, games_played, opponents, outcome)
+    }
 }
 
 #[cfg(test)]
@@ -255,4 +390,37 @@ mod tests {
         assert!(won_rating > player_rating);
         assert!(lost_rating < player_rating);
     }
+    
+    #[test]
+    fn test_elo_k_factor_scaling() {
+        use super::elo::{self, KFactorSchedule, MatchOutcome};
+        
+        let schedule = KFactorSchedule::default();
+        assert_eq!(schedule.k_for(0), 140.0);
+        assert_eq!(schedule.k_for(elo::PLACEMENT_GAMES), 70.0);
+        assert_eq!(schedule.k_for(elo::VETERAN_GAMES), 35.0);
+        
+        assert!(elo::is_provisional(0));
+        assert!(!elo::is_provisional(elo::PLACEMENT_GAMES));
+        
+        // 定级赛期间评分变化更大，老玩家评分变化更小
+        let opponents = [1000];
+        let placement = elo::rating_after(1000, 0, &opponents, MatchOutcome::Victory) - 1000;
+        let regular = elo::rating_after(1000, 50, &opponents, MatchOutcome::Victory) - 1000;
+        let veteran = elo::rating_after(1000, 200, &opponents, MatchOutcome::Victory) - 1000;
+        assert!(placement > regular && regular > veteran && veteran > 0);
+    }
+    
+    #[test]
+    fn test_elo_rating_floor() {
+        use super::elo::{self, MatchOutcome};
+        
+        // 失败不会使评分低于下限
+        let rating = elo::rating_after(elo::RATING_FLOOR + 5, 0, &[2000], MatchOutcome::Defeat);
+        assert_eq!(rating, elo::RATING_FLOOR);
+        
+        // 已低于下限的评分不会继续降低
+        let rating = elo::rating_after(50, 0, &[2000], MatchOutcome::Defeat);
+        assert_eq!(rating, 50);
+    }
 }
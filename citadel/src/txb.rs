@@ -12,13 +12,13 @@ use fastcrypto::encoding::Base64;
 use fastcrypto::encoding::Encoding;
 use shared_crypto::intent::{Intent, IntentMessage};
 use sui_keys::keystore::{AccountKeystore, InMemKeystore};
-use sui_sdk::rpc_types::SuiTransactionBlockResponseOptions;
-use sui_sdk::SuiClient;
 use sui_types::{
     base_types::SuiAddress, crypto::Signature, crypto::SuiKeyPair, transaction::Transaction,
     transaction::TransactionData,
 };
 
+use crate::sui_api::SuiApi;
+
 /**
  * 从私钥字符串创建内存密钥库
  *
@@ -64,14 +64,14 @@ pub fn create_keystore_from_sk(
  * 此函数接收事务数据、密钥库和发送者地址，签名并执行事务，
  * 返回事务执行的响应结果。
  *
- * @param client - Sui客户端
+ * @param client - 全节点接口，通常为Sui客户端
  * @param tx_data - 事务数据
  * @param keystore - 密钥库
  * @param sender - 发送者地址
  * @return 返回事务执行响应
  */
 pub async fn execute_transaction(
-    client: &SuiClient,
+    client: &(impl SuiApi + ?Sized),
     tx_data: TransactionData,
     keystore: &impl AccountKeystore,
     sender: &SuiAddress,
@@ -80,20 +80,9 @@ pub async fn execute_transaction(
     let sig = keystore
         .sign_secure(sender, &tx_data, Intent::sui_transaction())
         .context("KeyStore Sign Failed")?;
-    let response = client
-    .quorum_driver_api()
-    .execute_transaction_block(
-        Transaction::from_data(tx_data,vec![sig]),
-        SuiTransactionBlockResponseOptions::new()
-            .with_effects()
-            .with_input()
-            .with_events()
-            .with_object_changes()
-            .with_balance_changes(),
-        Some(sui_types::quorum_driver_types::ExecuteTransactionRequestType::WaitForLocalExecution),
-    )
-    .await?;
-    Ok(response)
+    client
+        .execute_transaction(Transaction::from_data(tx_data, vec![sig]))
+        .await
 }
 
 /**
@@ -102,30 +91,19 @@ pub async fn execute_transaction(
  * 此函数接收事务数据和密钥对，直接签名并执行事务，
  * 无需创建中间的密钥库。
  *
- * @param client - Sui客户端
+ * @param client - 全节点接口，通常为Sui客户端
  * @param tx_data - 事务数据
  * @param keypair - Sui密钥对
  * @return 返回事务执行响应
  */
 pub async fn execute_transaction_with_keypair(
-    client: &SuiClient,
+    client: &(impl SuiApi + ?Sized),
     tx_data: TransactionData,
     keypair: &SuiKeyPair,
 ) -> Result<sui_sdk::rpc_types::SuiTransactionBlockResponse> {
     let intent_msg = IntentMessage::new(Intent::sui_transaction(), tx_data.clone());
     let sig = Signature::new_secure(&intent_msg, keypair);
-    let response = client
-    .quorum_driver_api()
-    .execute_transaction_block(
-        Transaction::from_data(tx_data,vec![sig]),
-        SuiTransactionBlockResponseOptions::new()
-            .with_effects()
-            .with_input()
-            .with_events()
-            .with_object_changes()
-            .with_balance_changes(),
-        Some(sui_types::quorum_driver_types::ExecuteTransactionRequestType::WaitForLocalExecution),
-    )
-    .await?;
-    Ok(response)
+    client
+        .execute_transaction(Transaction::from_data(tx_data, vec![sig]))
+        .await
 }
@@ -58,6 +58,23 @@ pub enum Network {
 }
 
 impl Network {
+    /**
+     * 获取网络名称
+     *
+     * 返回:
+     * 小写的网络名称，用于日志、指标标签和请求路由
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            Network::Devnet => "devnet",
+            Network::Testnet => "testnet",
+            Network::Mainnet => "mainnet",
+            Network::Custom { .. } => "custom",
+            #[cfg(test)]
+            Network::TestCluster => "testcluster",
+        }
+    }
+
     /**
      * 获取当前网络的节点URL
      *
@@ -65,10 +82,39 @@ impl Network {
      * 对应网络环境的全节点URL
      */
     pub fn node_url(&self) -> String {
-        // 优先使用环境变量中的配置
-        if let Ok(url) = std::env::var("NODE_URL") {
-            return url;
+        // 优先使用环境变量中的配置，自定义网络已经携带了自己的地址
+        if !matches!(self, Network::Custom { .. }) {
+            if let Ok(url) = std::env::var("NODE_URL") {
+                return url;
+            }
         }
+        self.default_node_url()
+    }
+
+    /**
+     * 获取当前网络的所有全节点URL
+     *
+     * 节点URL中可以用逗号分隔多个地址，排在前面的优先使用
+     *
+     * 返回:
+     * 按优先级排列的全节点URL
+     */
+    pub fn node_urls(&self) -> Vec<String> {
+        self.node_url()
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /**
+     * 获取当前网络内置的节点URL，不读取环境变量
+     *
+     * 返回:
+     * 对应网络环境的全节点URL
+     */
+    pub fn default_node_url(&self) -> String {
         match self {
             Network::Devnet => "https://fullnode.devnet.sui.io:443".into(),
             Network::Testnet => "https://fullnode.testnet.sui.io:443".into(),
@@ -88,11 +134,22 @@ impl Network {
      * 对应网络环境的GraphQL端点URL
      */
     pub fn graphql_url(&self) -> String {
-        // 优先使用环境变量中的配置
-        if let Ok(url) = std::env::var("GRAPHQL_URL") {
-            return url;
+        // 优先使用环境变量中的配置，自定义网络已经携带了自己的地址
+        if !matches!(self, Network::Custom { .. }) {
+            if let Ok(url) = std::env::var("GRAPHQL_URL") {
+                return url;
+            }
         }
+        self.default_graphql_url()
+    }
 
+    /**
+     * 获取当前网络内置的GraphQL URL，不读取环境变量
+     *
+     * 返回:
+     * 对应网络环境的GraphQL端点URL
+     */
+    pub fn default_graphql_url(&self) -> String {
         match self {
             Network::Devnet => "https://sui-devnet.mystenlabs.com/graphql".into(),
             Network::Testnet => "https://sui-testnet.mystenlabs.com/graphql".into(),
@@ -112,11 +169,22 @@ impl Network {
      * 包含网络名称的浏览器基本URL
      */
     pub fn explorer_base_url(&self) -> String {
-        // 优先使用环境变量中的配置
-        if let Ok(url) = std::env::var("EXPLORER_URL") {
-            return url;
+        // 优先使用环境变量中的配置，自定义网络已经携带了自己的地址
+        if !matches!(self, Network::Custom { .. }) {
+            if let Ok(url) = std::env::var("EXPLORER_URL") {
+                return url;
+            }
         }
+        self.default_explorer_base_url()
+    }
 
+    /**
+     * 获取内置的浏览器基本URL，不读取环境变量
+     *
+     * 返回:
+     * 包含网络名称的浏览器基本URL
+     */
+    pub fn default_explorer_base_url(&self) -> String {
         match self {
             Network::Devnet => "https://suiscan.xyz/devnet".into(),
             Network::Testnet => "https://suiscan.xyz/testnet".into(),
@@ -0,0 +1,433 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! 用户名搜索模块
+//!
+//! # 概述
+//! 链上Profile不包含用户名，用户名来自Passport缓存的用户信息和对局中的玩家信息。
+//! 本模块维护一个按小写用户名排序的内存索引，提供前缀查询：
+//! - Passport保存用户信息时、对局结束时写入索引，用户改名后旧名称自动失效
+//! - 对局结束时记录同局玩家之间的最近互动时间
+//! - 搜索结果依次按是否为好友、最近互动时间、用户名长度和用户名排序
+//! - 接口 `GET /v1/users/search?q=...` 需要登录，按请求者地址限流
+//!
+//! # 配置
+//! - `USER_SEARCH_RATE_LIMIT`：每个地址每分钟允许的搜索次数，默认30，0表示不限流
+//! - `USER_SEARCH_BURST`：允许的突发搜索次数，默认10
+//! - `USER_SEARCH_MAX_USERS`：索引保留的最大用户数，默认100000
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use once_cell::sync::OnceCell;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use tracing::{info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::errors::{ErrorResponse, InternalError};
+use crate::externals::current_epoch_time;
+use crate::key_audit::RateLimiter;
+use crate::session_login::{SessionUser, SESSION_USER_KEY};
+use crate::AppState;
+
+/// 查询字符串的最小长度（字符）
+pub const MIN_QUERY_LEN: usize = 2;
+/// 查询字符串的最大长度（字符）
+pub const MAX_QUERY_LEN: usize = 32;
+/// 默认返回的结果数
+const DEFAULT_LIMIT: usize = 10;
+/// 单次返回的最大结果数
+pub const MAX_LIMIT: usize = 50;
+/// 参与排序的最大候选数
+const MAX_CANDIDATES: usize = 500;
+/// 每个用户保留的最近互动数
+const MAX_INTERACTIONS_PER_USER: usize = 100;
+/// 默认每分钟搜索次数
+const DEFAULT_RATE_PER_MINUTE: u32 = 30;
+/// 默认突发搜索次数
+const DEFAULT_BURST: u32 = 10;
+/// 默认索引保留的最大用户数
+const DEFAULT_MAX_USERS: usize = 100_000;
+
+/// 索引中的用户
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IndexedUser {
+    username: String,
+    avatar_url: Option<String>,
+}
+
+/// 搜索结果中的用户
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSearchHit {
+    /// 用户ID
+    pub id: String,
+    /// 用户名
+    pub username: String,
+    /// 头像URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+    /// 是否为请求者的好友
+    pub is_friend: bool,
+    /// 与请求者最近一次同局的时间（毫秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_interaction: Option<u64>,
+}
+
+/// 用户名前缀索引
+#[derive(Debug, Default)]
+struct PrefixIndex {
+    /// (小写用户名, 用户ID)，按字典序排列
+    names: BTreeMap<(String, String), ()>,
+    /// 用户ID -> 用户信息
+    users: HashMap<String, IndexedUser>,
+    /// 用户ID -> (对方用户ID -> 最近互动时间)
+    interactions: HashMap<String, HashMap<String, u64>>,
+}
+
+impl PrefixIndex {
+    fn upsert(&mut self, id: &str, username: &str, avatar_url: Option<String>) {
+        let entry = IndexedUser {
+            username: username.to_string(),
+            avatar_url,
+        };
+        if let Some(previous) = self.users.insert(id.to_string(), entry) {
+            if previous.username != username {
+                self.names.remove(&(previous.username.to_lowercase(), id.to_string()));
+            }
+        }
+        self.names.insert((username.to_lowercase(), id.to_string()), ());
+    }
+
+    fn remove(&mut self, id: &str) {
+        if let Some(previous) = self.users.remove(id) {
+            self.names.remove(&(previous.username.to_lowercase(), id.to_string()));
+        }
+        self.interactions.remove(id);
+    }
+
+    fn record_interaction(&mut self, user_id: &str, other_id: &str, at: u64) {
+        let recent = self.interactions.entry(user_id.to_string()).or_default();
+        let last = recent.entry(other_id.to_string()).or_insert(at);
+        *last = (*last).max(at);
+        if recent.len() > MAX_INTERACTIONS_PER_USER {
+            // 丢弃最早的互动
+            if let Some(oldest) = recent.iter().min_by_key(|(_, at)| **at).map(|(id, _)| id.clone()) {
+                recent.remove(&oldest);
+            }
+        }
+    }
+
+    /**
+     * 按前缀查询并排序
+     *
+     * 参数:
+     * @param prefix - 小写的查询前缀
+     * @param requester - 请求者用户ID，不出现在结果中
+     * @param friends - 请求者的好友ID
+     * @param limit - 返回的最大结果数
+     *
+     * 返回:
+     * 好友优先，其次按最近互动时间倒序，再按用户名长度和用户名排序
+     */
+    fn search(&self, prefix: &str, requester: Option<&str>, friends: &HashSet<String>, limit: usize) -> Vec<UserSearchHit> {
+        let recent = requester.and_then(|id| self.interactions.get(id));
+        let mut hits: Vec<UserSearchHit> = self
+            .names
+            .range((prefix.to_string(), String::new())..)
+            .map(|((name, id), _)| (name, id))
+            .take_while(|(name, _)| name.starts_with(prefix))
+            .filter(|(_, id)| Some(id.as_str()) != requester)
+            .take(MAX_CANDIDATES)
+            .filter_map(|(_, id)| {
+                let user = self.users.get(id)?;
+                Some(UserSearchHit {
+                    id: id.clone(),
+                    username: user.username.clone(),
+                    avatar_url: user.avatar_url.clone(),
+                    is_friend: friends.contains(id),
+                    last_interaction: recent.and_then(|r| r.get(id).copied()),
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.is_friend
+                .cmp(&a.is_friend)
+                .then_with(|| b.last_interaction.cmp(&a.last_interaction))
+                .then_with(|| a.username.chars().count().cmp(&b.username.chars().count()))
+                .then_with(|| a.username.to_lowercase().cmp(&b.username.to_lowercase()))
+        });
+        hits.truncate(limit);
+        hits
+    }
+}
+
+/// 用户名搜索服务
+pub struct UserSearchService {
+    index: RwLock<PrefixIndex>,
+    limiter: RateLimiter,
+    max_users: usize,
+}
+
+impl UserSearchService {
+    pub fn new(limiter: RateLimiter, max_users: usize) -> Self {
+        Self {
+            index: RwLock::new(PrefixIndex::default()),
+            limiter,
+            max_users,
+        }
+    }
+
+    /// 根据环境变量创建
+    pub fn from_env() -> Self {
+        let read = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u32>().ok());
+        let limiter = RateLimiter::new(
+            read("USER_SEARCH_RATE_LIMIT").unwrap_or(DEFAULT_RATE_PER_MINUTE),
+            read("USER_SEARCH_BURST").unwrap_or(DEFAULT_BURST),
+        );
+        let max_users = std::env::var("USER_SEARCH_MAX_USERS")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_MAX_USERS);
+        Self::new(limiter, max_users)
+    }
+
+    /// 写入或更新用户名，索引已满时忽略新用户
+    pub fn index_user(&self, id: &str, username: &str, avatar_url: Option<String>) {
+        if id.is_empty() || username.is_empty() {
+            return;
+        }
+        let mut index = self.index.write();
+        if !index.users.contains_key(id) && index.users.len() >= self.max_users {
+            warn!("用户名索引已满，忽略用户: {}", id);
+            return;
+        }
+        index.upsert(id, username, avatar_url);
+    }
+
+    /// 从索引中移除用户
+    pub fn remove_user(&self, id: &str) {
+        self.index.write().remove(id);
+    }
+
+    /**
+     * 记录对局中玩家的用户名和互动
+     *
+     * 参数:
+     * @param players - 同局玩家 (用户ID, 用户名, 头像URL)
+     * @param at - 对局结束时间（毫秒）
+     */
+    pub fn record_match(&self, players: &[(String, String, Option<String>)], at: u64) {
+        for (id, username, avatar_url) in players {
+            self.index_user(id, username, avatar_url.clone());
+        }
+        let mut index = self.index.write();
+        for (a, _, _) in players {
+            for (b, _, _) in players {
+                if a != b {
+                    index.record_interaction(a, b, at);
+                }
+            }
+        }
+    }
+
+    /// 检查请求者是否超出限流
+    pub fn try_acquire(&self, requester: &sui_types::base_types::SuiAddress, now: u64) -> bool {
+        self.limiter.try_acquire(requester, now)
+    }
+
+    /**
+     * 按用户名前缀搜索
+     *
+     * 参数:
+     * @param query - 查询字符串，不区分大小写
+     * @param requester - 请求者用户ID
+     * @param friends - 请求者的好友ID
+     * @param limit - 返回的最大结果数
+     *
+     * 返回:
+     * 查询字符串过短或过长时返回InvalidInput
+     */
+    pub fn search(
+        &self,
+        query: &str,
+        requester: Option<&str>,
+        friends: &HashSet<String>,
+        limit: usize,
+    ) -> Result<Vec<UserSearchHit>, InternalError> {
+        let prefix = query.trim().to_lowercase();
+        let len = prefix.chars().count();
+        if !(MIN_QUERY_LEN..=MAX_QUERY_LEN).contains(&len) {
+            return Err(InternalError::InvalidInput);
+        }
+        Ok(self.index.read().search(&prefix, requester, friends, limit.clamp(1, MAX_LIMIT)))
+    }
+
+    /// 清理空闲的限流桶
+    pub fn prune(&self, now: u64) {
+        self.limiter.prune(now);
+    }
+}
+
+static GLOBAL_USER_SEARCH: OnceCell<Arc<UserSearchService>> = OnceCell::new();
+
+/// 初始化全局用户名搜索服务
+pub fn init_user_search() -> Arc<UserSearchService> {
+    GLOBAL_USER_SEARCH
+        .get_or_init(|| {
+            let service = Arc::new(UserSearchService::from_env());
+            info!("用户名搜索服务已初始化，最多索引 {} 个用户", service.max_users);
+            service
+        })
+        .clone()
+}
+
+/// 获取全局用户名搜索服务
+pub fn global_user_search() -> Option<Arc<UserSearchService>> {
+    GLOBAL_USER_SEARCH.get().cloned()
+}
+
+/// 搜索参数
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct UserSearchQuery {
+    /// 用户名前缀，2到32个字符，不区分大小写
+    pub q: String,
+    /// 返回的最大结果数，默认10，最多50
+    pub limit: Option<usize>,
+}
+
+/// 搜索响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserSearchResponse {
+    pub success: bool,
+    pub users: Vec<UserSearchHit>,
+    pub error: Option<String>,
+}
+
+/// 按用户名前缀搜索用户
+#[utoipa::path(
+    get,
+    path = "/v1/users/search",
+    tag = "profile",
+    params(UserSearchQuery),
+    responses(
+        (status = 200, description = "按好友关系和最近互动排序的用户", body = UserSearchResponse),
+        (status = 403, description = "未登录或查询字符串无效", body = ErrorResponse),
+        (status = 429, description = "搜索过于频繁", body = ErrorResponse),
+    )
+)]
+pub async fn handle_search_users(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<UserSearchQuery>,
+    Extension(session): Extension<Session>,
+) -> Result<Json<UserSearchResponse>, InternalError> {
+    app_state.metrics.observe_request("search_users");
+
+    let user = session
+        .get::<SessionUser>(SESSION_USER_KEY)
+        .await?
+        .ok_or(InternalError::Unauthorized)?;
+
+    let Some(service) = global_user_search() else {
+        return Ok(Json(UserSearchResponse {
+            success: false,
+            users: Vec::new(),
+            error: Some("用户搜索服务未初始化".to_string()),
+        }));
+    };
+
+    if !service.try_acquire(&user.user_address, current_epoch_time()) {
+        warn!("用户搜索超出限流: {}", user.user_address);
+        return Err(InternalError::RateLimited);
+    }
+
+    let requester = user.profile.map(|p| p.id.to_string());
+    let friends: HashSet<String> = match (&requester, crate::ws::global_passport_state()) {
+        (Some(id), Some(passport_state)) => passport_state
+            .get_user_friends(id)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .collect(),
+        _ => HashSet::new(),
+    };
+
+    let users = service.search(
+        &query.q,
+        requester.as_deref(),
+        &friends,
+        query.limit.unwrap_or(DEFAULT_LIMIT),
+    )?;
+    Ok(Json(UserSearchResponse {
+        success: true,
+        users,
+        error: None,
+    }))
+}
+
+/// 注册用户搜索路由
+pub fn register_user_search_routes(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router.route("/v1/users/search", get(handle_search_users))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> UserSearchService {
+        UserSearchService::new(RateLimiter::new(0, 1), 3)
+    }
+
+    fn ids(hits: &[UserSearchHit]) -> Vec<&str> {
+        hits.iter().map(|h| h.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_prefix_lookup_and_rename() {
+        let service = service();
+        service.index_user("1", "Alice", None);
+        service.index_user("2", "alicia", None);
+        service.index_user("3", "Bob", None);
+        let none = HashSet::new();
+
+        assert_eq!(ids(&service.search("ALI", None, &none, 10).unwrap()), vec!["1", "2"]);
+        assert_eq!(service.search("a", None, &none, 10), Err(InternalError::InvalidInput));
+
+        // 改名后旧名称不再命中
+        service.index_user("3", "Alfred", None);
+        assert!(service.search("bo", None, &none, 10).unwrap().is_empty());
+        assert_eq!(service.search("alf", None, &none, 10).unwrap()[0].username, "Alfred");
+
+        // 索引已满时忽略新用户
+        service.index_user("4", "Alina", None);
+        assert_eq!(service.search("al", None, &none, 10).unwrap().len(), 3);
+        service.remove_user("1");
+        service.index_user("4", "Alina", None);
+        assert_eq!(ids(&service.search("alin", None, &none, 10).unwrap()), vec!["4"]);
+    }
+
+    #[test]
+    fn test_ranking_by_friendship_and_interaction() {
+        let service = UserSearchService::new(RateLimiter::new(0, 1), 100);
+        for (id, name) in [("me", "sam"), ("a", "sandy"), ("b", "sara"), ("c", "sarah"), ("d", "sal")] {
+            service.index_user(id, name, None);
+        }
+        let player = |id: &str, name: &str| (id.to_string(), name.to_string(), None);
+        service.record_match(&[player("me", "sam"), player("c", "sarah")], 1_000);
+        service.record_match(&[player("me", "sam"), player("b", "sara")], 2_000);
+        let friends: HashSet<String> = ["a".to_string()].into();
+
+        let hits = service.search("sa", Some("me"), &friends, 10).unwrap();
+        // 好友优先，其次最近互动，其余按用户名长度
+        assert_eq!(ids(&hits), vec!["a", "b", "c", "d"]);
+        assert!(hits[0].is_friend);
+        assert_eq!(hits[1].last_interaction, Some(2_000));
+        assert_eq!(ids(&service.search("sa", Some("me"), &friends, 2).unwrap()), vec!["a", "b"]);
+    }
+}
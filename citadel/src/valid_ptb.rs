@@ -12,6 +12,8 @@
  * 2. 只调用seal_approve开头的函数
  * 3. 使用正确的包ID
  * 4. 包含有效的密钥ID
+ * 
+ * 运维人员可配置的包、模块、函数和参数限制由ptb_policy模块在此基础上检查。
  */
 
  use crate::errors::InternalError;
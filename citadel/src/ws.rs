@@ -8,6 +8,7 @@
 
 use std::{
     collections::{HashMap, HashSet},
+    net::{IpAddr, SocketAddr},
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc,
@@ -20,7 +21,7 @@ use async_trait::async_trait;
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
+        ConnectInfo, Path, State,
     },
     response::IntoResponse,
     routing::get,
@@ -32,21 +33,48 @@ use tokio::{
     sync::{mpsc, Mutex},
     time::sleep,
 };
-use tracing::{debug, error, info, warn};
+use sui_types::base_types::SuiAddress;
+use tower_sessions::Session;
+use tracing::{debug, error, info, info_span, warn, Instrument};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::AppState;
 use crate::chat::{self, UserInfo};
+use crate::delivery::{self, AckConfig, AckRequest, DeliveryTracker};
+use crate::errors::{ErrorResponse, InternalError};
+use crate::event_bus;
+use crate::externals::current_epoch_time;
+use crate::key_audit::check_admin_key;
 use crate::passport::{self, PassportState};
+use crate::presence::{self, idle::{ActivityTracker, IdleConfig}};
 use crate::gaming as match_game;
+use crate::hand_seal;
+use crate::game::CacheMetricsSnapshot;
+use crate::i18n::{self, codes, Locale, LocalizedText};
+use crate::latency::{self, LatencyConfig, LatencyTracker};
+use crate::outbound::{self, OutboundConfig, OutboundCounters, Outbox};
+use crate::protocol::{self, ClientProtocol, WireEncoding};
+use crate::session_attestation::{self, AttestedSession};
+use crate::session_login::{SessionUser, SESSION_USER_KEY};
+use crate::tasks::{self, RestartPolicy};
+use crate::ws_acl::{JoinAccess, JoinPolicy, RoomAcls, RoomDenial, RoomOwner};
+use crate::ws_guard::{self, PayloadLimits, PayloadViolation};
+use crate::ws_handlers::{self, WsHandlers};
+use crate::ws_resume::{self, ResumeError, ResumeTokens};
+use crate::ws_sessions::{self, SessionInfo, SessionRegistry, UserSessions};
+use crate::ws_traffic::{TrafficSnapshot, TrafficStats};
 
 /// 客户端连接标识
 pub type ClientId = String;
 /// 房间标识
 pub type RoomId = String;
 
+/// 所有在线客户端所在的系统房间，用于全服广播
+pub const ALL_CLIENTS_ROOM: &str = "system:all";
+
 /// 连接状态统计
-#[derive(Debug, Default, Clone, Serialize)]
+#[derive(Debug, Default, Clone, Serialize, ToSchema)]
 pub struct ConnectionStats {
     /// 当前活跃连接数
     pub active_connections: usize,
@@ -58,15 +86,57 @@ pub struct ConnectionStats {
     pub messages_sent: usize,
     /// 消息接收总数
     pub messages_received: usize,
+    /// 关键事件重发次数
+    pub messages_redelivered: usize,
+    /// 重试用完或超出上限仍未确认的关键事件数
+    pub deliveries_failed: usize,
+    /// 因发送队列溢出丢弃的消息数
+    pub messages_dropped: usize,
+    /// 发送队列中被合并的状态消息数
+    pub messages_coalesced: usize,
+    /// 要求慢客户端重新同步的次数
+    pub forced_resyncs: usize,
+    /// 因发送队列溢出断开的连接数
+    pub slow_client_disconnects: usize,
+    /// 超过恢复窗口未重连而被清除房间记录的客户端数
+    pub stale_clients_reaped: usize,
+    /// 按事件和房间的流量统计
+    pub traffic: TrafficSnapshot,
+}
+
+/// `/ws/stats` 响应
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WsStatsResponse {
+    pub stats: ConnectionStats,
+    /// 房间ID到房间内客户端数量的映射
+    pub rooms: HashMap<String, usize>,
+    /// 游戏数据缓存统计
+    pub cache: CacheMetricsSnapshot,
 }
 
+/// `/ws/stats` 的OpenAPI描述，实际处理逻辑在 `register_ws_routes` 的闭包中
+///
+/// 需要在 `X-Admin-Key` 请求头中提供管理密钥
+#[utoipa::path(
+    get,
+    path = "/ws/stats",
+    tag = "ws",
+    params(("X-Admin-Key" = String, Header, description = "管理密钥")),
+    responses(
+        (status = 200, description = "WebSocket连接、房间与流量统计", body = WsStatsResponse),
+        (status = 401, description = "缺少管理密钥", body = ErrorResponse),
+        (status = 403, description = "管理密钥无效或未配置", body = ErrorResponse)
+    )
+)]
+pub async fn ws_stats_doc() {}
+
 /// 房间定义
 #[derive(Debug)]
 struct Room {
     /// 房间ID
     id: RoomId,
-    /// 客户端和其消息发送器映射
-    clients: HashMap<ClientId, mpsc::Sender<Message>>,
+    /// 客户端和其发送队列映射
+    clients: HashMap<ClientId, Arc<Outbox>>,
 }
 
 impl Room {
@@ -79,8 +149,8 @@ impl Room {
     }
 
     /// 添加客户端到房间
-    fn join(&mut self, client_id: ClientId, sender: mpsc::Sender<Message>) {
-        self.clients.insert(client_id, sender);
+    fn join(&mut self, client_id: ClientId, outbox: Arc<Outbox>) {
+        self.clients.insert(client_id, outbox);
     }
 
     /// 从房间中移除客户端
@@ -88,12 +158,18 @@ impl Room {
         self.clients.remove(client_id);
     }
 
-    /// 向房间内所有客户端广播消息
-    fn broadcast(&self, message: Message) -> usize {
+    /// 向房间内客户端广播消息，由闭包为每个客户端生成消息，返回None则跳过该客户端
+    fn broadcast_with<F>(&self, event: &str, message_for: F) -> usize
+    where
+        F: Fn(&str) -> Option<Message>,
+    {
+        let coalesce_key = outbound::coalesce_key(&self.id, event);
         let mut sent_count = 0;
-        for (_, sender) in &self.clients {
-            if sender.try_send(message.clone()).is_ok() {
-                sent_count += 1;
+        for (client_id, outbox) in &self.clients {
+            if let Some(message) = message_for(client_id) {
+                if outbox.push(message, coalesce_key.clone()) {
+                    sent_count += 1;
+                }
             }
         }
         sent_count
@@ -101,9 +177,12 @@ impl Room {
 
     /// 向特定客户端发送消息
     fn send_to(&self, client_id: &str, message: Message) -> Result<()> {
-        if let Some(sender) = self.clients.get(client_id) {
-            sender.try_send(message)?;
-            Ok(())
+        if let Some(outbox) = self.clients.get(client_id) {
+            if outbox.push(message, None) {
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("客户端发送队列已关闭或已溢出"))
+            }
         } else {
             Err(anyhow::anyhow!("客户端不在房间中"))
         }
@@ -120,12 +199,18 @@ impl Room {
 struct Rooms {
     /// 房间映射
     rooms: Arc<Mutex<HashMap<RoomId, Room>>>,
+    /// 房间权限
+    acls: Arc<RoomAcls>,
 }
 
 impl Default for Rooms {
     fn default() -> Self {
+        let acls = RoomAcls::default();
+        // 系统房间只能由服务端加入
+        let _ = acls.reserve_prefix("system:", RoomOwner::System, JoinPolicy::Server);
         Self {
             rooms: Arc::new(Mutex::new(HashMap::new())),
+            acls: Arc::new(acls),
         }
     }
 }
@@ -150,11 +235,19 @@ impl Rooms {
         }
     }
 
-    /// 客户端加入房间
-    async fn join(&self, room_id: &str, client_id: ClientId, sender: mpsc::Sender<Message>) {
+    /// 客户端加入房间，按房间权限检查加入来源，WebSocket连接的用户ID即客户端ID
+    async fn join(&self, room_id: &str, client_id: ClientId, outbox: Arc<Outbox>, access: JoinAccess) -> Result<(), RoomDenial> {
+        self.acls.check_join(room_id, &client_id, access)?;
         let mut rooms = self.rooms.lock().await;
         let room = rooms.entry(room_id.to_string()).or_insert_with(|| Room::new(room_id));
-        room.join(client_id, sender);
+        room.join(client_id, outbox);
+        Ok(())
+    }
+
+    /// 获取客户端在房间中的发送队列
+    async fn outbox_of(&self, room_id: &str, client_id: &str) -> Option<Arc<Outbox>> {
+        let rooms = self.rooms.lock().await;
+        rooms.get(room_id).and_then(|room| room.clients.get(client_id).cloned())
     }
 
     /// 客户端离开房间
@@ -170,11 +263,20 @@ impl Rooms {
         }
     }
 
-    /// 向房间广播消息
-    async fn broadcast(&self, room_id: &str, message: Message) -> usize {
+    /// 向房间广播消息，由闭包为每个客户端生成消息，无权接收广播的客户端被跳过
+    async fn broadcast_with<F>(&self, room_id: &str, event: &str, message_for: F) -> usize
+    where
+        F: Fn(&str) -> Option<Message>,
+    {
         let rooms = self.rooms.lock().await;
         if let Some(room) = rooms.get(room_id) {
-            room.broadcast(message)
+            room.broadcast_with(event, |client_id| {
+                if self.acls.may_receive(room_id, client_id) {
+                    message_for(client_id)
+                } else {
+                    None
+                }
+            })
         } else {
             0
         }
@@ -212,6 +314,32 @@ pub struct ConnectionManager {
     rooms: Arc<Rooms>,
     /// 断开连接处理器
     disconnect_handlers: Arc<Mutex<HashMap<String, Box<dyn Fn() + Send + Sync + 'static>>>>,
+    /// 客户端协商后的协议信息
+    client_protocols: Arc<Mutex<HashMap<ClientId, ClientProtocol>>>,
+    /// 已绑定飞地证明的会话，发给这些会话的消息会被签名
+    attested_sessions: Arc<Mutex<HashMap<ClientId, Arc<AttestedSession>>>>,
+    /// 连接时登录会话的钱包地址，用于派生私密消息的加密身份
+    client_wallets: Arc<Mutex<HashMap<ClientId, SuiAddress>>>,
+    /// 客户端消息的大小限制
+    payload_limits: Arc<PayloadLimits>,
+    /// 关键事件的待确认消息
+    delivery: Arc<DeliveryTracker>,
+    /// 发送队列配置
+    outbound_config: OutboundConfig,
+    /// 发送队列的背压计数
+    outbound_counters: Arc<OutboundCounters>,
+    /// 每个连接最后一次操作的时间，用于空闲检测
+    activity: Arc<ActivityTracker>,
+    /// 每个连接的往返延迟
+    latency: Arc<LatencyTracker>,
+    /// 按事件和房间的流量统计
+    traffic: Arc<TrafficStats>,
+    /// 在线连接的建立时间和客户端IP
+    sessions: Arc<SessionRegistry>,
+    /// 重连使用的恢复令牌
+    resume_tokens: Arc<ResumeTokens>,
+    /// 各模块注册的事件处理器
+    handlers: Arc<WsHandlers>,
 }
 
 impl std::fmt::Debug for ConnectionManager {
@@ -227,7 +355,7 @@ impl std::fmt::Debug for ConnectionManager {
 }
 
 /// WebSocket响应格式
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 pub struct WsResponse {
     /// 操作是否成功
     pub ok: bool,
@@ -236,7 +364,28 @@ pub struct WsResponse {
     pub msg: Option<String>,
     /// 可选的负载数据
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
     pub payload: Option<serde_json::Value>,
+    /// 消息代码，发送时按客户端语言重新渲染msg
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// 消息参数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub params: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+impl WsResponse {
+    /// 创建带消息代码的成功响应，msg为默认语言的文本
+    pub fn from_text(text: LocalizedText) -> Self {
+        Self {
+            ok: true,
+            msg: Some(text.render(Locale::default())),
+            payload: None,
+            code: Some(text.code.to_string()),
+            params: if text.params.is_empty() { None } else { Some(text.params) },
+        }
+    }
 }
 
 /// WebSocket消息类型
@@ -248,6 +397,9 @@ pub struct WsMessage {
     /// 消息数据
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<serde_json::Value>,
+    /// 需要确认的消息ID，仅发给已协商 `ack` 能力的客户端的关键事件带有该字段
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub msg_id: Option<u64>,
 }
 
 impl ConnectionManager {
@@ -259,12 +411,62 @@ impl ConnectionManager {
             stats: Arc::new(Mutex::new(ConnectionStats::default())),
             rooms: Arc::new(Rooms::default()),
             disconnect_handlers: Arc::new(Mutex::new(HashMap::new())),
+            client_protocols: Arc::new(Mutex::new(HashMap::new())),
+            attested_sessions: Arc::new(Mutex::new(HashMap::new())),
+            client_wallets: Arc::new(Mutex::new(HashMap::new())),
+            payload_limits: Arc::new(PayloadLimits::from_env()),
+            delivery: Arc::new(DeliveryTracker::new(AckConfig::from_env())),
+            outbound_config: OutboundConfig::from_env(),
+            outbound_counters: Arc::new(OutboundCounters::default()),
+            activity: Arc::new(ActivityTracker::new(&IdleConfig::from_env())),
+            latency: Arc::new(LatencyTracker::new(LatencyConfig::from_env())),
+            traffic: Arc::new(TrafficStats::new()),
+            sessions: Arc::new(SessionRegistry::default()),
+            resume_tokens: ws_resume::resume_tokens(),
+            handlers: Arc::new(WsHandlers::default()),
         }
     }
 
+    /// 获取连接活动记录
+    pub fn activity(&self) -> &ActivityTracker {
+        &self.activity
+    }
+
+    /// 获取连接延迟记录
+    pub fn latency(&self) -> &LatencyTracker {
+        &self.latency
+    }
+
+    /**
+     * 注册WebSocket事件处理器，模块在初始化时通过它接入消息分发
+     *
+     * 参数:
+     * @param pattern - 完整的事件名，或以 `*` 结尾的前缀，如 `chat:*`
+     * @param handler - 处理函数，返回是否已处理
+     */
+    pub fn register_handler<F, Fut>(&self, pattern: &str, handler: F)
+    where
+        F: Fn(ws_handlers::WsEvent) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<bool>> + Send + 'static,
+    {
+        self.handlers.register(pattern, handler);
+    }
+
+    /// 获取房间权限，模块通过它声明自己拥有的房间
+    pub fn room_acls(&self) -> &RoomAcls {
+        &self.rooms.acls
+    }
+
     /// 获取连接统计
     pub async fn get_stats(&self) -> ConnectionStats {
-        self.stats.lock().await.clone()
+        let mut stats = self.stats.lock().await.clone();
+        let counters = &self.outbound_counters;
+        stats.messages_dropped = counters.dropped.load(Ordering::Relaxed);
+        stats.messages_coalesced = counters.coalesced.load(Ordering::Relaxed);
+        stats.forced_resyncs = counters.resyncs.load(Ordering::Relaxed);
+        stats.slow_client_disconnects = counters.disconnects.load(Ordering::Relaxed);
+        stats.traffic = self.traffic.snapshot();
+        stats
     }
 
     /// 处理新的WebSocket连接
@@ -272,12 +474,35 @@ impl ConnectionManager {
         &self, 
         socket: WebSocket,
         client_id: Option<String>,
+        locale: Option<Locale>,
+        client_ip: Option<IpAddr>,
+        wallet: Option<SuiAddress>,
     ) -> Result<()> {
         // 生成客户端ID或使用提供的ID (用于重连)
         let client_id = client_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+        
+        // 记录登录钱包，没有登录的连接不能协商加密的私密消息
+        if let Some(wallet) = wallet {
+            self.client_wallets.lock().await.insert(client_id.clone(), wallet);
+        }
+        
+        // 按客户端IP查找匹配地区
+        crate::geo::geo_service().bind(&client_id, client_ip);
+        
+        // 握手前按Accept-Language确定语言
+        if let Some(locale) = locale {
+            self.client_protocols.lock().await.insert(
+                client_id.clone(),
+                ClientProtocol {
+                    locale,
+                    ..Default::default()
+                },
+            );
+        }
         let connection_id = self.connection_counter.fetch_add(1, Ordering::SeqCst);
         
         info!("New WebSocket connection: id={}, connection_id={}", client_id, connection_id);
+        let kicked = self.sessions.register(&client_id, connection_id, client_ip, current_epoch_time());
         
         // 更新统计
         {
@@ -289,6 +514,16 @@ impl ConnectionManager {
         // 创建消息通道
         let (mut sender, mut receiver) = socket.split();
         let (tx, mut rx) = mpsc::channel::<Message>(100);
+        let outbox = Arc::new(Outbox::new(
+            &client_id,
+            tx.clone(),
+            self.outbound_config.clone(),
+            self.outbound_counters.clone(),
+        ));
+        
+        // 加入全服广播房间，不记录到client_rooms中，重连时由新连接重新加入
+        let _ = self.rooms.join(ALL_CLIENTS_ROOM, client_id.clone(), outbox.clone(), JoinAccess::Server).await;
+        self.activity.touch(&client_id, chrono::Utc::now().timestamp_millis() as u64);
 
         // 提前克隆client_id供任务使用
         let client_id_for_send = client_id.clone();
@@ -301,20 +536,48 @@ impl ConnectionManager {
                     error!("发送消息错误: {}", e);
                     break;
                 }
+                // 通道有空位后补充溢出缓冲区中的消息
+                outbox.refill();
+                if outbox.is_closed() {
+                    warn!("客户端 {} 发送队列溢出，关闭连接", client_id_for_send);
+                    let _ = sender
+                        .send(Message::Close(Some(axum::extract::ws::CloseFrame {
+                            code: axum::extract::ws::close_code::AGAIN,
+                            reason: "outbound queue overflow".into(),
+                        })))
+                        .await;
+                    break;
+                }
             }
             debug!("发送任务结束: client_id={}", client_id_for_send);
         });
 
-        // 设置心跳检测
+        // 签发恢复令牌，客户端断开后凭令牌重连
+        self.send_resume_token(&client_id, &tx).await?;
+        
+        // 设置心跳检测，Ping帧带有发送时间，收到Pong后计算往返延迟
+        // 心跳时顺便在令牌过半有效期后签发新令牌
         let heartbeat_tx = tx.clone();
+        let ping_interval = self.latency.config().ping_interval;
+        let manager = self.clone();
         let heartbeat_task = tokio::spawn(async move {
+            let refresh_interval_ms = manager.resume_tokens.config().refresh_interval_ms();
+            let mut next_refresh = chrono::Utc::now().timestamp_millis() as u64 + refresh_interval_ms;
             loop {
-                sleep(Duration::from_secs(30)).await;
+                sleep(ping_interval).await;
                 debug!("发送心跳ping到客户端: {}", client_id_for_heartbeat);
-                if heartbeat_tx.send(Message::Ping(vec![])).await.is_err() {
+                let now = chrono::Utc::now().timestamp_millis() as u64;
+                let payload = latency::ping_payload(now);
+                if heartbeat_tx.send(Message::Ping(payload)).await.is_err() {
                     error!("心跳发送失败，客户端可能已断开连接: {}", client_id_for_heartbeat);
                     break;
                 }
+                if now >= next_refresh {
+                    if let Err(e) = manager.send_resume_token(&client_id_for_heartbeat, &heartbeat_tx).await {
+                        warn!("发送恢复令牌失败: {}", e);
+                    }
+                    next_refresh = now + refresh_interval_ms;
+                }
             }
         });
         
@@ -333,8 +596,24 @@ impl ConnectionManager {
             None
         };
 
-        // 处理从客户端接收的消息
-        while let Some(result) = receiver.next().await {
+        // 处理从客户端接收的消息，管理员强制断开时发送关闭帧后结束
+        loop {
+            let result = tokio::select! {
+                _ = kicked.notified() => {
+                    info!("管理员强制断开连接: id={}", client_id);
+                    let _ = tx.send(Message::Close(Some(axum::extract::ws::CloseFrame {
+                        code: axum::extract::ws::close_code::POLICY,
+                        reason: ws_sessions::ADMIN_CLOSE_REASON.into(),
+                    }))).await;
+                    // 等待发送任务把关闭帧发出
+                    sleep(Duration::from_millis(200)).await;
+                    break;
+                }
+                result = receiver.next() => result,
+            };
+            let Some(result) = result else {
+                break;
+            };
             match result {
                 Ok(message) => {
                     self.handle_message(&client_id, message, &tx).await?;
@@ -364,6 +643,16 @@ impl ConnectionManager {
         
         // 执行断开连接处理器
         self.execute_disconnect_handlers(&client_id).await;
+        self.rooms.leave(ALL_CLIENTS_ROOM, &client_id).await;
+        
+        // 协议和会话证明需要在每次连接时重新协商
+        self.client_protocols.lock().await.remove(&client_id);
+        self.attested_sessions.lock().await.remove(&client_id);
+        self.client_wallets.lock().await.remove(&client_id);
+        self.activity.remove(&client_id);
+        self.latency.remove(&client_id);
+        self.sessions.unregister(&client_id, connection_id);
+        crate::geo::geo_service().unbind(&client_id);
         
         // 清理资源
         send_task.abort();
@@ -376,7 +665,7 @@ impl ConnectionManager {
         }
 
         // 保留客户端的房间信息以便重连
-        // (不立即清除client_rooms中的记录，便于重连)
+        // (不立即清除client_rooms中的记录，便于重连，超过恢复窗口后由清理任务清除)
 
         Ok(())
     }
@@ -390,116 +679,38 @@ impl ConnectionManager {
     ) -> Result<()> {
         match message {
             Message::Text(text) => {
+                // 超大的帧不解析也不记录内容
+                if let Err(violation) = self.payload_limits.check_frame(text.len()) {
+                    return self.reject_payload(client_id, violation, tx).await;
+                }
                 debug!("接收到文本消息: {}", text);
                 
                 // 尝试解析为WsMessage
-                if let Ok(ws_msg) = serde_json::from_str::<WsMessage>(&text) {
-                    debug!("处理事件: {} 来自客户端: {}", ws_msg.event, client_id);
-                    
-                    // 创建一个模拟用户（真实系统中应该从认证信息获取）
-                    let user_info = Some(UserInfo {
-                        id: client_id.to_string(),
-                        name: format!("User-{}", client_id.split('-').next().unwrap_or("unknown")),
-                        avatar_url: None,
-                    });
-                    
-                    // 创建Passport用户信息
-                    let passport_user_info = user_info.clone().map(|u| passport::UserInfo {
-                        id: u.id,
-                        username: u.name,
-                        avatar_url: u.avatar_url,
-                        status: passport::UserStatus::Online,
-                        last_active: chrono::Utc::now().timestamp_millis(),
-                        created_at: chrono::Utc::now().timestamp_millis(),
-                    });
-                    
-                    // 首先尝试处理用户护照相关事件
-                    if ws_msg.event.starts_with("user:") {
-                        // 获取全局PassportState实例
-                        if let Some(passport_state) = GLOBAL_PASSPORT_STATE.get() {
-                            // 特殊处理不需要身份验证的事件，如获取用户补充信息
-                            if let Some(passport::ClientEvent::GetSupplemental) = passport::ClientEvent::from_str(&ws_msg.event) {
-                                if let Ok(handled) = passport::handle_ws_message(
-                                    client_id, 
-                                    ws_msg.clone(), 
-                                    passport_state, 
-                                    None // 不需要用户信息
-                                ).await {
-                                    if handled {
-                                        return Ok(());
-                                    }
-                                }
-                            } else if let Ok(handled) = passport::handle_ws_message(
-                                client_id, 
-                                ws_msg.clone(), 
-                                passport_state, 
-                                passport_user_info
-                            ).await {
-                                if handled {
-                                    // 消息已由用户护照模块处理
-                                    return Ok(());
-                                }
-                            }
-                        }
-                    }
-                    
-                    // 其次尝试处理聊天相关事件
-                    if ws_msg.event.starts_with("chat:") {
-                        if let Ok(handled) = chat::handle_ws_message(
-                            client_id, 
-                            ws_msg.clone(), 
-                            self, 
-                            user_info
-                        ).await {
-                            if handled {
-                                // 消息已由聊天模块处理
-                                return Ok(());
-                            }
-                        }
-                    }
-                    
-                    // 如果不是特定模块的事件或模块未处理，则继续处理其他事件
-                    match ws_msg.event.as_str() {
-                        "join_room" => {
-                            if let Some(data) = ws_msg.data {
-                                if let Some(room_id) = data.get("roomId").and_then(|v| v.as_str()) {
-                                    self.handle_join_room(client_id, room_id, tx).await?;
-                                }
-                            }
-                        }
-                        "leave_room" => {
-                            if let Some(data) = ws_msg.data {
-                                if let Some(room_id) = data.get("roomId").and_then(|v| v.as_str()) {
-                                    self.handle_leave_room(client_id, room_id, tx).await?;
-                                }
-                            }
-                        }
-                        "reconnect" => {
-                            if let Some(data) = ws_msg.data {
-                                if let Some(old_client_id) = data.get("clientId").and_then(|v| v.as_str()) {
-                                    self.handle_reconnect(client_id, old_client_id, tx).await?;
-                                }
-                            }
-                        }
-                        _ => {
-                            // 其他自定义事件处理
-                            debug!("未处理的事件类型: {}", ws_msg.event);
-                        }
-                    }
-                } else {
-                    debug!("无法解析消息为WsMessage: {}", text);
+                match serde_json::from_str::<WsMessage>(&text) {
+                    Ok(ws_msg) => self.handle_checked_event(client_id, ws_msg, tx).await?,
+                    Err(_) => debug!("无法解析消息为WsMessage: {}", text),
                 }
             }
             Message::Binary(data) => {
+                if let Err(violation) = self.payload_limits.check_frame(data.len()) {
+                    return self.reject_payload(client_id, violation, tx).await;
+                }
                 debug!("接收到二进制消息: {} 字节", data.len());
-                // 注意：我们主要处理文本消息，二进制消息仅用于特殊情况
+                // 二进制帧按MessagePack解码，未协商编码的客户端同样允许发送
+                match protocol::WireEncoding::Msgpack.decode_binary(&data) {
+                    Ok(ws_msg) => self.handle_checked_event(client_id, ws_msg, tx).await?,
+                    Err(e) => debug!("无法解码二进制消息: {}", e),
+                }
             }
             Message::Ping(data) => {
                 debug!("接收到Ping");
                 let _ = tx.send(Message::Pong(data)).await;
             }
-            Message::Pong(_) => {
-                debug!("接收到Pong");
+            Message::Pong(data) => {
+                let now = chrono::Utc::now().timestamp_millis() as u64;
+                if let Some(rtt) = self.latency.record_pong(client_id, &data, now) {
+                    debug!("接收到Pong, client_id={}, rtt={}ms", client_id, rtt);
+                }
             }
             Message::Close(frame) => {
                 info!("接收到关闭消息: {:?}", frame);
@@ -509,6 +720,331 @@ impl ConnectionManager {
         Ok(())
     }
 
+    /// 校验已解码的消息，通过后再分发给各模块
+    async fn handle_checked_event(
+        &self,
+        client_id: &str,
+        ws_msg: WsMessage,
+        tx: &mpsc::Sender<Message>,
+    ) -> Result<()> {
+        match self.payload_limits.check_message(&ws_msg) {
+            Ok(()) => {
+                self.traffic.record_received(&ws_msg.event);
+                self.handle_ws_event(client_id, ws_msg, tx).await
+            }
+            Err(violation) => self.reject_payload(client_id, violation, tx).await,
+        }
+    }
+    
+    /// 拒绝不符合大小限制的消息，向客户端返回结构化的违规信息
+    async fn reject_payload(
+        &self,
+        client_id: &str,
+        violation: PayloadViolation,
+        tx: &mpsc::Sender<Message>,
+    ) -> Result<()> {
+        warn!("拒绝客户端 {} 的消息: {:?}", client_id, violation);
+        let response = WsResponse {
+            ok: false,
+            payload: Some(serde_json::to_value(&violation)?),
+            ..WsResponse::from_text(violation.text())
+        };
+        let response_msg = WsMessage {
+            event: ws_guard::events::PAYLOAD_REJECTED.to_string(),
+            data: Some(serde_json::to_value(response)?),
+            msg_id: None,
+        };
+        let client_protocol = self.get_client_protocol(client_id).await;
+        let response_msg = localize_message(&response_msg, client_protocol.locale);
+        let _ = tx.send(client_protocol.encoding.encode(&response_msg)?).await;
+        Ok(())
+    }
+    
+    /// 处理已解码的WebSocket事件
+    async fn handle_ws_event(
+        &self,
+        client_id: &str,
+        ws_msg: WsMessage,
+        tx: &mpsc::Sender<Message>,
+    ) -> Result<()> {
+        debug!("处理事件: {} 来自客户端: {}", ws_msg.event, client_id);
+        
+        // 协议握手
+        if ws_msg.event == protocol::events::HELLO {
+            return self.handle_hello(client_id, ws_msg.data.as_ref(), tx).await;
+        }
+        
+        // 会话证明
+        if ws_msg.event == session_attestation::events::ATTEST {
+            return self.handle_attest(client_id, ws_msg.data.as_ref(), tx).await;
+        }
+        
+        // 关键事件确认
+        if ws_msg.event == delivery::events::ACK {
+            self.handle_ack(client_id, ws_msg.data.as_ref());
+            return Ok(());
+        }
+        
+        // 记录用户操作，空闲或离开的用户恢复在线
+        if self.activity.touch(client_id, chrono::Utc::now().timestamp_millis() as u64) {
+            if let Some(passport_state) = GLOBAL_PASSPORT_STATE.get() {
+                presence::idle::mark_active(passport_state, client_id).await;
+            }
+        }
+        
+        // 创建一个模拟用户（真实系统中应该从认证信息获取），用户设置过名称时使用设置的名称
+        let user_info = Some(UserInfo {
+            id: client_id.to_string(),
+            name: crate::profile_edit::display_name(client_id)
+                .unwrap_or_else(|| format!("User-{}", client_id.split('-').next().unwrap_or("unknown"))),
+            avatar_url: None,
+        });
+        
+        // 交给各模块注册的处理器，模块未处理时继续处理内置事件
+        let event = ws_handlers::WsEvent {
+            client_id: client_id.to_string(),
+            message: ws_msg.clone(),
+            user: user_info,
+            connection_manager: self.clone(),
+        };
+        // 日志中关联用户和对局
+        let span = info_span!(
+            "ws_event",
+            event = %ws_msg.event,
+            user_id = client_id,
+            match_id = tracing::field::Empty
+        );
+        if let Some(match_id) = ws_msg.data.as_ref().and_then(|d| d.get("matchId")).and_then(|v| v.as_str()) {
+            span.record("match_id", match_id);
+        }
+        match self.handlers.dispatch(event).instrument(span).await {
+            Ok(true) => return Ok(()),
+            Ok(false) => {}
+            Err(e) => {
+                warn!("处理客户端 {} 的事件 {} 失败: {}", client_id, ws_msg.event, e);
+                return Ok(());
+            }
+        }
+        
+        // 如果不是特定模块的事件或模块未处理，则继续处理其他事件
+        match ws_msg.event.as_str() {
+            "join_room" => {
+                if let Some(data) = ws_msg.data {
+                    if let Some(room_id) = data.get("roomId").and_then(|v| v.as_str()) {
+                        self.handle_join_room(client_id, room_id, tx).await?;
+                    }
+                }
+            }
+            "leave_room" => {
+                if let Some(data) = ws_msg.data {
+                    if let Some(room_id) = data.get("roomId").and_then(|v| v.as_str()) {
+                        self.handle_leave_room(client_id, room_id, tx).await?;
+                    }
+                }
+            }
+            "reconnect" => {
+                if let Some(data) = ws_msg.data {
+                    if let Some(resume_token) = data.get("resumeToken").and_then(|v| v.as_str()) {
+                        self.handle_reconnect(client_id, resume_token, tx).await?;
+                    }
+                }
+            }
+            _ => {
+                // 其他自定义事件处理
+                debug!("未处理的事件类型: {}", ws_msg.event);
+            }
+        }
+        
+        Ok(())
+    }
+
+    /// 处理协议握手
+    async fn handle_hello(
+        &self,
+        client_id: &str,
+        data: Option<&serde_json::Value>,
+        tx: &mpsc::Sender<Message>,
+    ) -> Result<()> {
+        // hello未指定语言时沿用连接时确定的语言
+        let previous_locale = self.get_client_protocol(client_id).await.locale;
+        let can_seal = hand_seal::can_seal(self.client_wallet(client_id).await.as_ref());
+        let result = protocol::parse_hello(data).and_then(|hello| {
+            let mut client_protocol = protocol::negotiate(&hello)?;
+            if hello.locale.as_deref().and_then(Locale::from_tag).is_none() {
+                client_protocol.locale = previous_locale;
+            }
+            // 没有登录钱包时无法派生加密身份，不授予加密能力，客户端从hello_ack中得知
+            if !can_seal && client_protocol.capabilities.remove(protocol::capabilities::SEALED_HANDS) {
+                info!("客户端 {} 没有登录钱包或未启用加密，拒绝 sealed_hands 能力", client_id);
+            }
+            Ok(client_protocol)
+        });
+        
+        match result {
+            Ok(client_protocol) => {
+                info!(
+                    "客户端 {} 协议协商成功: version={}, capabilities={:?}",
+                    client_id, client_protocol.version, client_protocol.capabilities
+                );
+                
+                let response = WsResponse {
+                    ok: true,
+                    msg: None,
+                    payload: Some(serde_json::json!({
+                        "version": client_protocol.version,
+                        "serverVersion": protocol::PROTOCOL_VERSION,
+                        "capabilities": client_protocol.capabilities,
+                        "encoding": client_protocol.encoding,
+                        "locale": client_protocol.locale,
+                    })),
+                    ..Default::default()
+                };
+                
+                self.client_protocols
+                    .lock()
+                    .await
+                    .insert(client_id.to_string(), client_protocol);
+                
+                let response_msg = WsMessage {
+                    event: protocol::events::HELLO_ACK.to_string(),
+                    data: Some(serde_json::to_value(response)?),
+                    msg_id: None,
+                };
+                let _ = tx.send(Message::Text(serde_json::to_string(&response_msg)?)).await;
+            }
+            Err(protocol_error) => {
+                warn!("客户端 {} 协议协商失败: {}", client_id, protocol_error.message);
+                
+                let response = WsResponse {
+                    ok: false,
+                    msg: Some(protocol_error.message.clone()),
+                    payload: Some(serde_json::to_value(&protocol_error)?),
+                    ..Default::default()
+                };
+                let response_msg = WsMessage {
+                    event: protocol::events::PROTOCOL_ERROR.to_string(),
+                    data: Some(serde_json::to_value(response)?),
+                    msg_id: None,
+                };
+                let _ = tx.send(Message::Text(serde_json::to_string(&response_msg)?)).await;
+                
+                // 版本不兼容时关闭连接
+                if protocol_error.code == protocol::ProtocolErrorCode::UnsupportedVersion {
+                    let _ = tx
+                        .send(Message::Close(Some(axum::extract::ws::CloseFrame {
+                            code: axum::extract::ws::close_code::PROTOCOL,
+                            reason: "unsupported protocol version".into(),
+                        })))
+                        .await;
+                }
+            }
+        }
+        
+        Ok(())
+    }
+
+    /**
+     * 处理会话证明请求
+     *
+     * 生成绑定当前会话的证明文档，成功后发给该会话的消息都会被签名
+     *
+     * 参数:
+     * @param client_id - 客户端ID
+     * @param data - 包含客户端挑战的请求数据
+     * @param tx - 客户端消息发送器
+     */
+    async fn handle_attest(
+        &self,
+        client_id: &str,
+        data: Option<&serde_json::Value>,
+        tx: &mpsc::Sender<Message>,
+    ) -> Result<()> {
+        let response = match session_attestation::attest_session(client_id, data) {
+            Ok((document, session)) => {
+                info!("客户端 {} 已绑定飞地证明", client_id);
+                let response = WsResponse {
+                    ok: true,
+                    payload: Some(serde_json::json!({
+                        "attestation": hex::encode(document),
+                        "binding": session.binding(),
+                    })),
+                    ..WsResponse::from_text(i18n::text(codes::SESSION_ATTESTED, &[]))
+                };
+                // 证明结果本身不签名，之后的消息才签名
+                let response_msg = WsMessage {
+                    event: session_attestation::events::ATTESTED.to_string(),
+                    data: Some(serde_json::to_value(response)?),
+                    msg_id: None,
+                };
+                self.send_direct(client_id, tx, &response_msg).await?;
+                self.attested_sessions
+                    .lock()
+                    .await
+                    .insert(client_id.to_string(), Arc::new(session));
+                return Ok(());
+            }
+            Err(e) => {
+                warn!("客户端 {} 会话证明失败: {}", client_id, e);
+                WsResponse {
+                    ok: false,
+                    payload: None,
+                    ..WsResponse::from_text(i18n::text(codes::SESSION_ATTEST_FAILED, &[("reason", e.to_string())]))
+                }
+            }
+        };
+        let response_msg = WsMessage {
+            event: session_attestation::events::ATTESTED.to_string(),
+            data: Some(serde_json::to_value(response)?),
+            msg_id: None,
+        };
+        self.send_direct(client_id, tx, &response_msg).await
+    }
+
+    /// 获取客户端的会话证明，未证明的客户端返回None
+    /// 处理客户端对关键事件的确认
+    fn handle_ack(&self, client_id: &str, data: Option<&serde_json::Value>) {
+        match data.cloned().map(serde_json::from_value::<AckRequest>) {
+            Some(Ok(request)) => {
+                let acked = self.delivery.ack(client_id, &request.msg_ids);
+                debug!("客户端 {} 确认了 {} 条消息", client_id, acked);
+            }
+            _ => debug!("无法解析客户端 {} 的确认消息", client_id),
+        }
+    }
+    
+    async fn attested_session(&self, client_id: &str) -> Option<Arc<AttestedSession>> {
+        self.attested_sessions.lock().await.get(client_id).cloned()
+    }
+
+    /// 获取连接的登录钱包地址，未登录时返回None
+    pub async fn client_wallet(&self, client_id: &str) -> Option<SuiAddress> {
+        self.client_wallets.lock().await.get(client_id).copied()
+    }
+
+    /// 按客户端协商的编码直接发送消息
+    async fn send_direct(
+        &self,
+        client_id: &str,
+        tx: &mpsc::Sender<Message>,
+        ws_message: &WsMessage,
+    ) -> Result<()> {
+        let client_protocol = self.get_client_protocol(client_id).await;
+        let ws_message = localize_message(ws_message, client_protocol.locale);
+        let ws_message = seal_message(self.attested_session(client_id).await.as_deref(), ws_message)?;
+        let _ = tx.send(client_protocol.encoding.encode(&ws_message)?).await;
+        Ok(())
+    }
+
+    /// 获取客户端协商后的协议信息，未握手的客户端返回默认协议
+    pub async fn get_client_protocol(&self, client_id: &str) -> ClientProtocol {
+        self.client_protocols
+            .lock()
+            .await
+            .get(client_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
     /// 处理加入房间请求
     async fn handle_join_room(
         &self,
@@ -518,8 +1054,24 @@ impl ConnectionManager {
     ) -> Result<()> {
         info!("客户端加入房间: client_id={}, room_id={}", client_id, room_id);
         
-        // 将客户端添加到房间
-        self.rooms.join(room_id, client_id.to_string(), tx.clone()).await;
+        // 将客户端添加到房间，复用连接的发送队列
+        let Some(outbox) = self.rooms.outbox_of(ALL_CLIENTS_ROOM, client_id).await else {
+            warn!("客户端 {} 的连接不存在，无法加入房间 {}", client_id, room_id);
+            return Ok(());
+        };
+        if let Err(denial) = self.rooms.join(room_id, client_id.to_string(), outbox, JoinAccess::Client).await {
+            warn!("拒绝客户端 {} 加入房间 {}: {:?}", client_id, room_id, denial);
+            let response = WsResponse {
+                ok: false,
+                ..WsResponse::from_text(i18n::text(denial.code(), &[("room", room_id.to_string())]))
+            };
+            let response_msg = WsMessage {
+                event: "room_joined".to_string(),
+                data: Some(serde_json::to_value(response)?),
+                msg_id: None,
+            };
+            return self.send_direct(client_id, tx, &response_msg).await;
+        }
         
         // 更新客户端->房间映射
         let mut client_rooms = self.client_rooms.lock().await;
@@ -531,17 +1083,17 @@ impl ConnectionManager {
         // 发送确认消息
         let response = WsResponse {
             ok: true,
-            msg: Some(format!("已加入房间: {}", room_id)),
             payload: None,
+            ..WsResponse::from_text(i18n::text(codes::ROOM_JOINED, &[("room", room_id.to_string())]))
         };
         
         let response_msg = WsMessage {
             event: "room_joined".to_string(),
             data: Some(serde_json::to_value(response)?),
+            msg_id: None,
         };
         
-        let msg_json = serde_json::to_string(&response_msg)?;
-        let _ = tx.send(Message::Text(msg_json)).await;
+        self.send_direct(client_id, tx, &response_msg).await?;
         
         Ok(())
     }
@@ -567,39 +1119,180 @@ impl ConnectionManager {
         // 发送确认消息
         let response = WsResponse {
             ok: true,
-            msg: Some(format!("已离开房间: {}", room_id)),
             payload: None,
+            ..WsResponse::from_text(i18n::text(codes::ROOM_LEFT, &[("room", room_id.to_string())]))
         };
         
         let response_msg = WsMessage {
             event: "room_left".to_string(),
             data: Some(serde_json::to_value(response)?),
+            msg_id: None,
         };
         
-        let msg_json = serde_json::to_string(&response_msg)?;
-        let _ = tx.send(Message::Text(msg_json)).await;
+        self.send_direct(client_id, tx, &response_msg).await?;
         
         Ok(())
     }
 
-    /// 处理重连请求
+    /**
+     * 为连接签发恢复令牌并发送给客户端
+     *
+     * 参数:
+     * @param client_id - 客户端ID，WebSocket连接的用户ID即客户端ID
+     * @param tx - 客户端消息发送器
+     */
+    async fn send_resume_token(&self, client_id: &str, tx: &mpsc::Sender<Message>) -> Result<()> {
+        let issued = self.resume_tokens.issue(client_id, client_id, current_epoch_time());
+        let response = WsResponse {
+            ok: true,
+            msg: None,
+            payload: Some(serde_json::json!({
+                "clientId": client_id,
+                "resumeToken": issued.token,
+                "expiresAt": issued.expires_at,
+            })),
+            ..Default::default()
+        };
+        let response_msg = WsMessage {
+            event: ws_resume::RESUME_TOKEN_EVENT.to_string(),
+            data: Some(serde_json::to_value(response)?),
+            msg_id: None,
+        };
+        self.send_direct(client_id, tx, &response_msg).await
+    }
+
+    /**
+     * 校验恢复令牌，令牌已过期时清除旧客户端的房间成员资格
+     *
+     * 参数:
+     * @param resume_token - 客户端出示的令牌
+     *
+     * 返回:
+     * 令牌内容，或校验失败的原因
+     */
+    pub async fn verify_resume_token(&self, resume_token: &str) -> Result<ws_resume::ResumeClaims, ResumeError> {
+        let result = self.resume_tokens.verify(resume_token, current_epoch_time());
+        if let Err(ResumeError::Expired(claims)) = &result {
+            self.expire_client_rooms(&claims.client_id).await;
+        }
+        result
+    }
+
+    /// 清除断开的客户端保留的房间成员资格，客户端仍然在线时不处理
+    async fn expire_client_rooms(&self, client_id: &str) -> bool {
+        if self.sessions.get(client_id).is_some() {
+            return false;
+        }
+        self.resume_tokens.revoke(client_id);
+        let rooms = self.client_rooms.lock().await.remove(client_id).unwrap_or_default();
+        for room_id in &rooms {
+            self.rooms.leave(room_id, client_id).await;
+        }
+        if !rooms.is_empty() {
+            info!("客户端 {} 的恢复令牌已过期，清除 {} 个房间的成员资格", client_id, rooms.len());
+        }
+        true
+    }
+
+    /**
+     * 清除超过恢复窗口仍未重连的客户端保留的房间记录
+     *
+     * 断开连接时保留房间记录以便重连，客户端不在线且没有未过期的恢复令牌时不可能再恢复，
+     * 其房间记录和房间成员资格被清除，过期的签发记录也一并移除。
+     *
+     * 参数:
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 被清除的客户端数
+     */
+    pub async fn reap_stale_clients(&self, now: u64) -> usize {
+        let stale = self
+            .client_rooms
+            .lock()
+            .await
+            .keys()
+            .filter(|client_id| self.sessions.get(client_id).is_none() && !self.resume_tokens.is_resumable(client_id, now))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut reaped = 0;
+        for client_id in &stale {
+            if self.expire_client_rooms(client_id).await {
+                reaped += 1;
+            }
+        }
+        self.resume_tokens.prune(now);
+        if reaped > 0 {
+            self.stats.lock().await.stale_clients_reaped += reaped;
+            debug!("清除了 {} 个超过恢复窗口的客户端的房间记录", reaped);
+        }
+        reaped
+    }
+
+    /// 启动定期清理过期房间记录的任务
+    pub fn spawn_stale_client_reaper(&self) {
+        let manager = self.clone();
+        tasks::supervise_global("ws_stale_client_reaper", RestartPolicy::default(), move |token| {
+            let manager = manager.clone();
+            async move {
+                let mut ticker = tokio::time::interval(manager.resume_tokens.config().reap_interval);
+                loop {
+                    tokio::select! {
+                        _ = token.cancelled() => break,
+                        _ = ticker.tick() => {}
+                    }
+                    manager.reap_stale_clients(current_epoch_time()).await;
+                }
+            }
+        });
+    }
+
+    /// 处理重连请求，凭恢复令牌把旧客户端的房间迁移到当前连接
     async fn handle_reconnect(
         &self,
         client_id: &str,
-        old_client_id: &str,
+        resume_token: &str,
         tx: &mpsc::Sender<Message>,
     ) -> Result<()> {
+        let old_client_id = match self.verify_resume_token(resume_token).await {
+            Ok(claims) if claims.client_id != client_id => claims.client_id,
+            result => {
+                let code = match result {
+                    Err(ResumeError::Expired(_)) => codes::RESUME_TOKEN_EXPIRED,
+                    _ => codes::RESUME_TOKEN_INVALID,
+                };
+                warn!("客户端 {} 的重连请求被拒绝: {:?}", client_id, result);
+                let response = WsResponse {
+                    ok: false,
+                    ..WsResponse::from_text(i18n::text(code, &[]))
+                };
+                let response_msg = WsMessage {
+                    event: "reconnect_failed".to_string(),
+                    data: Some(serde_json::to_value(response)?),
+                    msg_id: None,
+                };
+                return self.send_direct(client_id, tx, &response_msg).await;
+            }
+        };
+        let old_client_id = old_client_id.as_str();
+        self.resume_tokens.revoke(old_client_id);
         info!("处理重连请求: old_id={}, new_id={}", old_client_id, client_id);
         
-        // 恢复房间成员资格
+        // 恢复房间成员资格，无权恢复的房间不再迁移
         let mut rejoined_rooms = Vec::new();
+        let mut denied_rooms = HashSet::new();
         
-        {
+        if let Some(outbox) = self.rooms.outbox_of(ALL_CLIENTS_ROOM, client_id).await {
             let client_rooms = self.client_rooms.lock().await;
             if let Some(rooms) = client_rooms.get(old_client_id) {
                 for room_id in rooms {
-                    self.rooms.join(room_id, client_id.to_string(), tx.clone()).await;
-                    rejoined_rooms.push(room_id.clone());
+                    match self.rooms.join(room_id, client_id.to_string(), outbox.clone(), JoinAccess::Rejoin).await {
+                        Ok(()) => rejoined_rooms.push(room_id.clone()),
+                        Err(denial) => {
+                            debug!("客户端 {} 不能恢复房间 {}: {:?}", client_id, room_id, denial);
+                            denied_rooms.insert(room_id.clone());
+                        }
+                    }
                 }
             }
         }
@@ -607,7 +1300,8 @@ impl ConnectionManager {
         // 更新客户端->房间映射，为新ID创建映射并迁移所有房间
         {
             let mut client_rooms = self.client_rooms.lock().await;
-            if let Some(rooms) = client_rooms.remove(old_client_id) {
+            if let Some(mut rooms) = client_rooms.remove(old_client_id) {
+                rooms.retain(|room_id| !denied_rooms.contains(room_id));
                 client_rooms.insert(client_id.to_string(), rooms);
             }
         }
@@ -616,19 +1310,19 @@ impl ConnectionManager {
         if !rejoined_rooms.is_empty() {
             let response = WsResponse {
                 ok: true,
-                msg: Some("重连成功".to_string()),
                 payload: Some(serde_json::json!({
                     "rejoined_rooms": rejoined_rooms
                 })),
+                ..WsResponse::from_text(i18n::text(codes::RECONNECTED, &[]))
             };
             
             let response_msg = WsMessage {
                 event: "reconnect_success".to_string(),
                 data: Some(serde_json::to_value(response)?),
+                msg_id: None,
             };
             
-            let msg_json = serde_json::to_string(&response_msg)?;
-            let _ = tx.send(Message::Text(msg_json)).await;
+            self.send_direct(client_id, tx, &response_msg).await?;
             
             // 更新统计
             let mut stats = self.stats.lock().await;
@@ -637,17 +1331,22 @@ impl ConnectionManager {
             // 没有找到以前的房间
             let response = WsResponse {
                 ok: true,
-                msg: Some("重连成功，但没有找到以前的房间".to_string()),
                 payload: None,
+                ..WsResponse::from_text(i18n::text(codes::RECONNECTED_NO_ROOMS, &[]))
             };
             
             let response_msg = WsMessage {
                 event: "reconnect_success".to_string(),
                 data: Some(serde_json::to_value(response)?),
+                msg_id: None,
             };
             
-            let msg_json = serde_json::to_string(&response_msg)?;
-            let _ = tx.send(Message::Text(msg_json)).await;
+            self.send_direct(client_id, tx, &response_msg).await?;
+        }
+        
+        // 重启后恢复排队的玩家重连时通知仍在队列中
+        if let Some(match_service) = crate::gaming::global_match_service() {
+            match_service.notify_queue_restored(old_client_id, client_id).await?;
         }
         
         Ok(())
@@ -716,17 +1415,157 @@ impl ConnectionManager {
         }
     }
 
+    /// 向所有在线客户端广播消息
+    pub async fn broadcast_to_all(&self, event: &str, data: Option<serde_json::Value>) -> Result<usize> {
+        self.broadcast_variants(ALL_CLIENTS_ROOM, event, data, None, &[]).await
+    }
+
     /// 向特定房间广播消息
     pub async fn broadcast_to_room(&self, room_id: &str, event: &str, data: Option<serde_json::Value>) -> Result<usize> {
+        self.broadcast_variants(room_id, event, data, None, &[]).await
+    }
+
+    /**
+     * 向房间广播消息，跳过指定的客户端，用于这些客户端已经单独收到各自版本的消息
+     *
+     * 参数:
+     * @param room_id - 房间ID
+     * @param event - 事件名称
+     * @param data - 消息数据
+     * @param except - 跳过的客户端ID
+     *
+     * 返回:
+     * 收到消息的客户端数量
+     */
+    pub async fn broadcast_to_room_except(
+        &self,
+        room_id: &str,
+        event: &str,
+        data: Option<serde_json::Value>,
+        except: &[String],
+    ) -> Result<usize> {
+        self.broadcast_variants(room_id, event, data, None, except).await
+    }
+
+    /**
+     * 向房间广播消息，已协商指定能力的客户端收到精简版本
+     *
+     * 参数:
+     * @param room_id - 房间ID
+     * @param event - 事件名称
+     * @param data - 发给其他客户端的完整数据
+     * @param capability - 接收精简数据所需的能力
+     * @param lite_data - 发给已协商能力客户端的精简数据
+     *
+     * 返回:
+     * 收到消息的客户端数量
+     */
+    pub async fn broadcast_to_room_with_lite(
+        &self,
+        room_id: &str,
+        event: &str,
+        data: Option<serde_json::Value>,
+        capability: &str,
+        lite_data: Option<serde_json::Value>,
+    ) -> Result<usize> {
+        self.broadcast_variants(room_id, event, data, Some((capability, lite_data)), &[]).await
+    }
+
+    async fn broadcast_variants(
+        &self,
+        room_id: &str,
+        event: &str,
+        data: Option<serde_json::Value>,
+        lite: Option<(&str, Option<serde_json::Value>)>,
+        except: &[String],
+    ) -> Result<usize> {
+        let started = std::time::Instant::now();
         let ws_message = WsMessage {
             event: event.to_string(),
             data,
+            msg_id: None,
         };
+        // 对局事件同时推送给外部观察者
+        crate::observer::observer_hub().publish(room_id, event, ws_message.data.as_ref());
+        let lite = lite.map(|(capability, data)| {
+            (
+                capability,
+                WsMessage {
+                    event: event.to_string(),
+                    data,
+                    msg_id: None,
+                },
+            )
+        });
         
-        let message_json = serde_json::to_string(&ws_message)?;
-        let axum_message = Message::Text(message_json);
+        // 默认语言的JSON帧总是需要，其他帧在第一个需要的客户端出现时才编码
+        // 帧按 [语言][编码] 缓存
+        let json_message = WireEncoding::Json.encode(&ws_message)?;
+        let frames: [[once_cell::sync::OnceCell<Option<Message>>; 2]; 2] = Default::default();
+        let lite_frames: [[once_cell::sync::OnceCell<Option<Message>>; 2]; 2] = Default::default();
+        let _ = frames[Locale::default().index()][0].set(Some(json_message));
+        let capability = protocol::required_capability(event);
+        let critical = delivery::is_critical(event);
+        let now = current_epoch_time();
+        let evicted = AtomicUsize::new(0);
         
-        let count = self.rooms.broadcast(room_id, axum_message).await;
+        let protocols = self.client_protocols.lock().await;
+        let attested = self.attested_sessions.lock().await;
+        let count = self
+            .rooms
+            .broadcast_with(room_id, event, |client_id| {
+                if except.iter().any(|id| id == client_id) {
+                    return None;
+                }
+                let client_protocol = protocols.get(client_id);
+                let supports = |capability: &str| client_protocol.map(|p| p.supports(capability)).unwrap_or(false);
+                // 需要特定能力的事件只发给已协商该能力的客户端
+                if let Some(capability) = capability {
+                    if !supports(capability) {
+                        return None;
+                    }
+                }
+                let encoding = client_protocol.map(|p| p.encoding).unwrap_or_default();
+                let locale = client_protocol.map(|p| p.locale).unwrap_or_default();
+                let index = if encoding == WireEncoding::Json { 0 } else { 1 };
+                let selected = match &lite {
+                    Some((lite_capability, lite_message)) if supports(lite_capability) => lite_message,
+                    _ => &ws_message,
+                };
+                // 已协商确认能力的客户端收到关键事件时，每条消息单独分配msgId，不使用缓存的帧
+                if critical && supports(protocol::capabilities::ACK) {
+                    let (message, dropped) = self.delivery.track(client_id, localize_message(selected, locale), now);
+                    evicted.fetch_add(dropped, Ordering::Relaxed);
+                    return seal_message(attested.get(client_id).map(|s| s.as_ref()), message)
+                        .and_then(|message| encoding.encode(&message))
+                        .ok();
+                }
+                // 已证明的会话每条消息单独签名，不使用缓存的帧
+                if let Some(session) = attested.get(client_id) {
+                    return seal_message(Some(session.as_ref()), localize_message(selected, locale))
+                        .and_then(|message| encoding.encode(&message))
+                        .ok();
+                }
+                // 已协商对应能力的客户端收到精简版本
+                if let Some((lite_capability, lite_message)) = &lite {
+                    if supports(lite_capability) {
+                        return lite_frames[locale.index()][index]
+                            .get_or_init(|| encoding.encode(&localize_message(lite_message, locale)).ok())
+                            .clone();
+                    }
+                }
+                frames[locale.index()][index]
+                    .get_or_init(|| encoding.encode(&localize_message(&ws_message, locale)).ok())
+                    .clone()
+            })
+            .await;
+        drop(attested);
+        drop(protocols);
+        let evicted = evicted.into_inner();
+        if evicted > 0 {
+            self.stats.lock().await.deliveries_failed += evicted;
+        }
+        self.traffic.record_broadcast(room_id, event, count, started.elapsed());
         if count > 0 {
             // 更新消息计数
             let mut stats = self.stats.lock().await;
@@ -742,13 +1581,32 @@ impl ConnectionManager {
 
     /// 向特定客户端发送消息
     pub async fn send_to_client(&self, client_id: &str, event: &str, data: Option<serde_json::Value>) -> Result<bool> {
+        // 客户端未协商该事件所需的能力时不发送
+        let client_protocol = self.get_client_protocol(client_id).await;
+        if !client_protocol.accepts_event(event) {
+            debug!("客户端 {} 不支持事件 {}，跳过发送", client_id, event);
+            return Ok(false);
+        }
+        
         let ws_message = WsMessage {
             event: event.to_string(),
             data,
+            msg_id: None,
         };
         
-        let message_json = serde_json::to_string(&ws_message)?;
-        let axum_message = Message::Text(message_json);
+        let ws_message = localize_message(&ws_message, client_protocol.locale);
+        // 关键事件分配msgId，发送失败时由重发任务继续投递
+        let ws_message = if client_protocol.supports(protocol::capabilities::ACK) && delivery::is_critical(event) {
+            let (ws_message, evicted) = self.delivery.track(client_id, ws_message, current_epoch_time());
+            if evicted > 0 {
+                self.stats.lock().await.deliveries_failed += evicted;
+            }
+            ws_message
+        } else {
+            ws_message
+        };
+        let ws_message = seal_message(self.attested_session(client_id).await.as_deref(), ws_message)?;
+        let axum_message = client_protocol.encoding.encode(&ws_message)?;
         
         // 遍历客户端所在的所有房间，寻找客户端
         let client_rooms = self.client_rooms.lock().await;
@@ -758,6 +1616,7 @@ impl ConnectionManager {
                     // 更新消息计数
                     let mut stats = self.stats.lock().await;
                     stats.messages_sent += 1;
+                    self.traffic.record_sent(event);
                     
                     info!("Sent event {} to client {}", event, client_id);
                     return Ok(true);
@@ -769,6 +1628,86 @@ impl ConnectionManager {
         Ok(false)
     }
     
+    /**
+     * 由服务端将已连接的客户端加入房间
+     *
+     * 客户端必须已经在某个房间中，以便复用其消息发送器
+     *
+     * 参数:
+     * @param client_id - 客户端ID
+     * @param room_id - 目标房间ID
+     *
+     * 返回:
+     * 成功加入返回true，找不到客户端连接返回false
+     */
+    pub async fn join_room(&self, client_id: &str, room_id: &str) -> Result<bool> {
+        let mut client_rooms = self.client_rooms.lock().await;
+        let Some(rooms) = client_rooms.get_mut(client_id) else {
+            return Ok(false);
+        };
+        if rooms.contains(room_id) {
+            return Ok(true);
+        }
+        
+        let mut outbox = None;
+        for current_room in rooms.iter() {
+            outbox = self.rooms.outbox_of(current_room, client_id).await;
+            if outbox.is_some() {
+                break;
+            }
+        }
+        let Some(outbox) = outbox else {
+            return Ok(false);
+        };
+        
+        info!("客户端加入房间: client_id={}, room_id={}", client_id, room_id);
+        let _ = self.rooms.join(room_id, client_id.to_string(), outbox, JoinAccess::Server).await;
+        rooms.insert(room_id.to_string());
+        Ok(true)
+    }
+    
+    /// 启动关键事件的重发任务
+    pub fn spawn_redelivery(&self) {
+        let manager = self.clone();
+        tasks::supervise_global("ws_redelivery", RestartPolicy::default(), move |token| {
+            let manager = manager.clone();
+            async move {
+                let mut ticker = tokio::time::interval(Duration::from_millis(manager.delivery.tick_interval_ms()));
+                loop {
+                    tokio::select! {
+                        _ = token.cancelled() => break,
+                        _ = ticker.tick() => {}
+                    }
+                    manager.redeliver(current_epoch_time()).await;
+                }
+            }
+        });
+    }
+    
+    /// 重发超时未确认的关键事件，客户端不在线时等待下一轮
+    async fn redeliver(&self, now: u64) {
+        let batch = self.delivery.due(now);
+        let mut resent = 0;
+        for (client_id, message) in batch.resend {
+            let encoding = self.get_client_protocol(&client_id).await.encoding;
+            let frame = seal_message(self.attested_session(&client_id).await.as_deref(), message)
+                .and_then(|message| encoding.encode(&message));
+            match frame {
+                Ok(frame) => {
+                    if self.rooms.send_to_client(ALL_CLIENTS_ROOM, &client_id, frame).await.is_ok() {
+                        resent += 1;
+                    }
+                }
+                Err(e) => error!("重发消息编码失败: {}", e),
+            }
+        }
+        if resent > 0 || batch.failed > 0 {
+            let mut stats = self.stats.lock().await;
+            stats.messages_redelivered += resent;
+            stats.deliveries_failed += batch.failed;
+        }
+    }
+    
     /// 获取特定房间内的客户端数量
     pub async fn get_room_size(&self, room_id: &str) -> usize {
         self.rooms.get_room_size(room_id).await
@@ -788,27 +1727,200 @@ impl ConnectionManager {
             false
         }
     }
+
+    /// 检查客户端能否在房间中发言，即在房间中且有权接收房间广播，用于由客户端触发的房间广播
+    pub async fn can_speak_in(&self, client_id: &str, room_id: &str) -> bool {
+        self.is_client_in_room(client_id, room_id).await && self.rooms.acls.may_receive(room_id, client_id)
+    }
+
+    /// 在线连接的会话信息，连接不存在时返回None
+    pub async fn session_info(&self, client_id: &str) -> Option<SessionInfo> {
+        let (connected_at, ip) = self.sessions.get(client_id)?;
+        let mut rooms: Vec<String> = self
+            .client_rooms
+            .lock()
+            .await
+            .get(client_id)
+            .map(|rooms| rooms.iter().cloned().collect())
+            .unwrap_or_default();
+        rooms.sort();
+        Some(SessionInfo {
+            client_id: client_id.to_string(),
+            connected_at,
+            ip,
+            rooms,
+            last_activity: self.activity.latest(&[client_id.to_string()]),
+            rtt_ms: self.latency.rtt(client_id),
+        })
+    }
+
+    /// 强制断开连接，连接不存在时返回false
+    pub fn disconnect_client(&self, client_id: &str) -> bool {
+        self.sessions.kick(client_id)
+    }
+}
+
+/**
+ * 按用户列出活跃会话
+ *
+ * 参数:
+ * @param connection_manager - 连接管理器
+ * @param passport - 用户护照状态，提供用户到客户端的映射
+ * @param user_id - 指定用户，为None时列出所有用户
+ *
+ * 返回:
+ * 有活跃会话的用户，按用户ID排序
+ */
+pub async fn list_user_sessions(
+    connection_manager: &ConnectionManager,
+    passport: &PassportState,
+    user_id: Option<&str>,
+) -> Vec<UserSessions> {
+    let user_sessions: Vec<(String, Vec<ClientId>)> = match user_id {
+        Some(user_id) => vec![(user_id.to_string(), passport.get_user_sessions(user_id).await)],
+        None => passport
+            .user_sessions
+            .lock()
+            .await
+            .iter()
+            .map(|(user_id, clients)| (user_id.clone(), clients.clone()))
+            .collect(),
+    };
+
+    let mut users = Vec::new();
+    for (user_id, client_ids) in user_sessions {
+        let mut sessions = Vec::new();
+        for client_id in &client_ids {
+            if let Some(info) = connection_manager.session_info(client_id).await {
+                sessions.push(info);
+            }
+        }
+        if !sessions.is_empty() {
+            sessions.sort_by_key(|s| s.connected_at);
+            users.push(UserSessions { user_id, sessions });
+        }
+    }
+    users.sort_by(|a, b| a.user_id.cmp(&b.user_id));
+    users
+}
+
+/// 按客户端语言重新渲染消息中的提示文本
+fn localize_message(message: &WsMessage, locale: Locale) -> WsMessage {
+    let mut message = message.clone();
+    if locale != Locale::default() {
+        if let Some(data) = message.data.as_mut() {
+            i18n::localize_value(data, locale);
+        }
+    }
+    message
+}
+
+/// 已证明的会话收到签名包装后的消息，其他会话收到原消息
+fn seal_message(session: Option<&AttestedSession>, message: WsMessage) -> Result<WsMessage> {
+    match (session, session_attestation::enclave_keypair()) {
+        (Some(session), Some(kp)) => session.seal(kp, &message),
+        _ => Ok(message),
+    }
 }
 
 // 用于存储全局PassportState实例的静态变量
 static GLOBAL_PASSPORT_STATE: once_cell::sync::OnceCell<Arc<PassportState>> = once_cell::sync::OnceCell::new();
 
+/// 获取全局PassportState实例
+pub fn global_passport_state() -> Option<Arc<PassportState>> {
+    GLOBAL_PASSPORT_STATE.get().cloned()
+}
+
+/// 读取连接请求的登录会话的钱包地址，未登录或读取失败时返回None
+async fn session_wallet(session: &Session) -> Option<SuiAddress> {
+    session
+        .get::<SessionUser>(SESSION_USER_KEY)
+        .await
+        .ok()
+        .flatten()
+        .map(|user| user.user_address)
+}
+
+/// 从请求头中解析客户端语言
+fn accept_language(headers: &axum::http::HeaderMap) -> Option<Locale> {
+    headers
+        .get(axum::http::header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Locale::from_accept_language)
+}
+
 /// 注册WebSocket路由
 pub fn register_ws_routes(app: Router) -> Router {
     // 创建连接管理器
     let connection_manager = Arc::new(ConnectionManager::new());
     
+    // 启动关键事件的重发任务
+    connection_manager.spawn_redelivery();
+    
+    // 启动过期房间记录的清理任务
+    connection_manager.spawn_stale_client_reaper();
+    
     // 创建用户护照状态
     let passport_state = Arc::new(PassportState::new(connection_manager.clone()));
+    passport_state.game_service.spawn_sweeper();
     
     // 设置全局PassportState实例
-    let _ = GLOBAL_PASSPORT_STATE.set(passport_state);
+    let _ = GLOBAL_PASSPORT_STATE.set(passport_state.clone());
+    passport::register_ws_handlers(&connection_manager, passport_state.clone());
+    
+    // 初始化事件总线，注册在线状态订阅者和WebSocket出口
+    event_bus::init_event_bus(passport_state.clone());
     
-    // 创建游戏服务
+    // 启动空闲检测，长时间没有操作的用户自动变为空闲或离开
+    presence::idle::spawn_idle_detector(passport_state.clone(), IdleConfig::from_env());
+    
+    // 创建游戏服务，并启动过期缓存的后台清理
     let game_service = Arc::new(crate::game::GameService::new());
+    game_service.spawn_sweeper();
+    let game_service_for_stats = game_service.clone();
+    
+    // 单实例部署时，从快照恢复游戏数据并定期写入快照
+    if let Some(config) = crate::game_snapshot::SnapshotConfig::from_env() {
+        crate::game_snapshot::enable_snapshots(game_service.clone(), "match", &config);
+        crate::game_snapshot::enable_snapshots(passport_state.game_service.clone(), "passport", &config);
+    }
+    
+    // 初始化成就服务
+    let achievement_service = crate::achievement::init_achievement_service(game_service.clone(), connection_manager.clone());
+    
+    // 初始化经济服务
+    let economy_service = crate::economy::init_economy_service(game_service.clone());
+    
+    // 初始化玩家统计服务
+    let stats_service = crate::stats::init_stats_service(game_service.clone());
+    
+    // 初始化中途退出惩罚服务
+    let penalty_service = crate::penalty::init_penalty_service(game_service.clone());
+    
+    // 初始化账户关联服务
+    crate::account_link::init_account_link_service(game_service.clone());
+    
+    // 初始化Profile修改服务
+    crate::profile_edit::init_profile_edit_service(game_service.clone());
+    
+    // 初始化游客服务
+    crate::guest::init_guest_service(game_service.clone());
+    
+    // 初始化组队服务
+    crate::party::init_party_service(connection_manager.clone());
     
     // 初始化匹配服务
-    let match_service = match_game::init_match_service(game_service, connection_manager.clone());
+    let match_service = match_game::init_match_service(
+        game_service,
+        connection_manager.clone(),
+        achievement_service,
+        economy_service,
+        stats_service,
+        penalty_service,
+    );
+    
+    // 初始化活动日历，启动锦标赛调度
+    crate::calendar::init_calendar_service(connection_manager.clone());
     
     // 添加聊天模块路由
     let app = chat::register_chat_routes(app, connection_manager.clone());
@@ -816,16 +1928,30 @@ pub fn register_ws_routes(app: Router) -> Router {
     // 添加用户护照模块路由
     let app = passport::register_passport_routes(app, connection_manager.clone());
     
+    // 添加系统公告路由
+    let app = crate::announcement::register_announcement_routes(app, connection_manager.clone());
+    
+    // 添加实例间内部接口
+    let app = crate::cluster::register_cluster_routes(app, connection_manager.clone());
+    
     // 创建WebSocket处理闭包
     let connection_manager_for_handler = connection_manager.clone();
-    let handle_ws = move |ws: WebSocketUpgrade| {
+    let handle_ws = move |ws: WebSocketUpgrade, headers: axum::http::HeaderMap, session: Session, peer: Option<ConnectInfo<SocketAddr>>| {
         let connection_manager = connection_manager_for_handler.clone();
         async move {
             info!("WebSocket连接请求");
+            let locale = accept_language(&headers);
+            let client_ip = crate::profile_guard::profile_guard().client_ip(&headers, peer.map(|ConnectInfo(addr)| addr));
+            // 被封禁的用户或地址不能建立连接
+            if let Err(e) = crate::ban::check_ws_connect(&session, None).await {
+                warn!("拒绝WebSocket连接: {}", e);
+                return InternalError::from(e).into_response();
+            }
+            let wallet = session_wallet(&session).await;
             // 升级连接
             ws.on_upgrade(move |socket| async move {
                 // 处理WebSocket连接
-                if let Err(e) = connection_manager.handle_socket(socket, None).await {
+                if let Err(e) = connection_manager.handle_socket(socket, None, locale, client_ip, wallet).await {
                     error!("WebSocket处理错误: {}", e);
                 }
             })
@@ -837,36 +1963,103 @@ pub fn register_ws_routes(app: Router) -> Router {
     let connection_manager_for_stats = connection_manager.clone();
     
     // 创建WebSocket重连处理闭包
-    let handle_ws_reconnect = move |ws: WebSocketUpgrade, params: axum::extract::Query<HashMap<String, String>>| {
+    let handle_ws_reconnect = move |ws: WebSocketUpgrade, headers: axum::http::HeaderMap, session: Session, peer: Option<ConnectInfo<SocketAddr>>, params: axum::extract::Query<HashMap<String, String>>| {
         let connection_manager = connection_manager_for_reconnect.clone();
         async move {
-            let client_id = params.get("client_id").cloned();
+            let locale = accept_language(&headers);
+            let client_ip = crate::profile_guard::profile_guard().client_ip(&headers, peer.map(|ConnectInfo(addr)| addr));
+            
+            // 凭恢复令牌确定要恢复的客户端ID，令牌只能使用一次
+            let Some(resume_token) = params.get("resume_token") else {
+                return InternalError::MissingAuthToken.into_response();
+            };
+            let claims = match connection_manager.verify_resume_token(resume_token).await {
+                Ok(claims) => claims,
+                Err(e) => {
+                    warn!("拒绝WebSocket重连: {}", e);
+                    return match e {
+                        ResumeError::Expired(_) => InternalError::ExpiredToken,
+                        _ => InternalError::InvalidToken,
+                    }
+                    .into_response();
+                }
+            };
+            let client_id = Some(claims.client_id.clone());
             
             info!("WebSocket重连请求, client_id: {:?}", client_id);
             
+            // 令牌绑定的用户ID检查封禁
+            if let Err(e) = crate::ban::check_ws_connect(&session, Some(claims.user_id.as_str())).await {
+                warn!("拒绝WebSocket重连: {}", e);
+                return InternalError::from(e).into_response();
+            }
+            
+            connection_manager.resume_tokens.revoke(&claims.client_id);
+            let wallet = session_wallet(&session).await;
+            
             // 升级连接
             ws.on_upgrade(move |socket| async move {
-                // 处理WebSocket连接（使用提供的客户端ID进行重连）
-                if let Err(e) = connection_manager.handle_socket(socket, client_id).await {
+                // 处理WebSocket连接（使用令牌中的客户端ID进行重连）
+                if let Err(e) = connection_manager.handle_socket(socket, client_id, locale, client_ip, wallet).await {
                     error!("WebSocket重连处理错误: {}", e);
                 }
             })
         }
     };
     
-    // 创建WebSocket状态处理闭包
-    let handle_ws_stats = move || {
+    // 创建WebSocket状态处理闭包，统计信息只对管理员开放
+    let handle_ws_stats = move |headers: axum::http::HeaderMap| {
         let connection_manager = connection_manager_for_stats.clone();
+        let cache = game_service_for_stats.cache_metrics();
         async move {
+            check_admin_key(&headers)?;
             let stats = connection_manager.get_stats().await;
-            let rooms_info = connection_manager.get_rooms_info().await;
+            let rooms = connection_manager.get_rooms_info().await;
             
-            let response = serde_json::json!({
-                "stats": stats,
-                "rooms": rooms_info
-            });
-            
-            axum::Json(response)
+            Ok::<_, InternalError>(axum::Json(WsStatsResponse { stats, rooms, cache }))
+        }
+    };
+    
+    // 创建会话列表和强制断开的处理闭包，只对管理员开放
+    let connection_manager_for_sessions = connection_manager.clone();
+    let passport_for_sessions = passport_state.clone();
+    let handle_list_sessions = move |headers: axum::http::HeaderMap| {
+        let connection_manager = connection_manager_for_sessions.clone();
+        let passport = passport_for_sessions.clone();
+        async move {
+            check_admin_key(&headers)?;
+            let users = list_user_sessions(&connection_manager, &passport, None).await;
+            Ok::<_, InternalError>(axum::Json(ws_sessions::SessionListResponse { users }))
+        }
+    };
+    let connection_manager_for_user_sessions = connection_manager.clone();
+    let passport_for_user_sessions = passport_state.clone();
+    let handle_user_sessions = move |headers: axum::http::HeaderMap, Path(user_id): Path<String>| {
+        let connection_manager = connection_manager_for_user_sessions.clone();
+        let passport = passport_for_user_sessions.clone();
+        async move {
+            check_admin_key(&headers)?;
+            let users = list_user_sessions(&connection_manager, &passport, Some(&user_id)).await;
+            Ok::<_, InternalError>(axum::Json(ws_sessions::SessionListResponse { users }))
+        }
+    };
+    let connection_manager_for_disconnect = connection_manager.clone();
+    let passport_for_disconnect = passport_state.clone();
+    let handle_disconnect_session = move |headers: axum::http::HeaderMap, Path((user_id, client_id)): Path<(String, String)>| {
+        let connection_manager = connection_manager_for_disconnect.clone();
+        let passport = passport_for_disconnect.clone();
+        async move {
+            check_admin_key(&headers)?;
+            // 只断开属于该用户的会话，避免误断其他用户
+            let error = if !passport.get_user_sessions(&user_id).await.contains(&client_id) {
+                Some("会话不属于该用户".to_string())
+            } else if !connection_manager.disconnect_client(&client_id) {
+                Some("会话已断开".to_string())
+            } else {
+                info!("管理员断开用户 {} 的会话 {}", user_id, client_id);
+                None
+            };
+            Ok::<_, InternalError>(axum::Json(ws_sessions::DisconnectResponse { success: error.is_none(), error }))
         }
     };
     
@@ -874,4 +2067,8 @@ pub fn register_ws_routes(app: Router) -> Router {
     app.route("/ws", get(handle_ws))
        .route("/ws/reconnect", get(handle_ws_reconnect))
        .route("/ws/stats", get(handle_ws_stats))
+       .route("/ws/sessions", get(handle_list_sessions))
+       .route("/ws/sessions/:user_id", get(handle_user_sessions))
+       .route("/ws/sessions/:user_id/:client_id", axum::routing::delete(handle_disconnect_session))
+       .route("/ws/schema", get(crate::ws_schema::handle_ws_schema))
 }
@@ -0,0 +1,284 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! WebSocket房间权限模块
+//!
+//! # 概述
+//! 房间原先只是名称，任何客户端都可以通过 `join_room` 加入任意房间，收到房间内的所有广播。
+//! 本模块为房间记录权限，由 `ConnectionManager` 在客户端加入房间和广播时执行：
+//! - 所有者：声明房间的模块，只有所有者可以修改成员或释放房间
+//! - 成员：允许接收房间广播的用户ID，WebSocket连接的用户ID即客户端ID
+//! - 加入策略：
+//!   - `open`：任何客户端都可以加入，未声明的房间按此处理
+//!   - `members`：只有成员可以加入，广播只发给成员，被移出成员的客户端即使仍在房间中也收不到广播
+//!   - `server`：客户端不能自行加入，只能由所有者模块在服务端加入
+//!
+//! 服务端调用 `ConnectionManager::join_room` 由模块自行负责校验，不检查加入策略。
+//! 断线重连恢复房间时，`server` 房间视为恢复所有者模块原先的加入，`members` 房间仍要求新连接是成员。
+//!
+//! 当前的所有者：
+//! - 系统：`system:` 前缀的房间，如全服广播房间
+//! - 对局：以对局ID为名的房间，成员为玩家和观众，保存对局时同步，删除对局时释放
+//! - 聊天：`chat:` 前缀的房间，只能通过 `chat:join-chat` 加入，只有房间内的客户端可以发言
+//!
+//! 客户端加入被拒绝时收到 `ok` 为false的 `room_joined` 事件，`code` 说明原因。
+
+use std::collections::{HashMap, HashSet};
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::codes;
+
+/// 房间所有者
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomOwner {
+    System,
+    Match,
+    Chat,
+}
+
+/// 加入策略
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JoinPolicy {
+    /// 任何客户端都可以加入
+    Open,
+    /// 只有成员可以加入和接收广播
+    Members,
+    /// 只能由所有者模块在服务端加入
+    Server,
+}
+
+/// 加入房间的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinAccess {
+    /// 客户端发送 `join_room`
+    Client,
+    /// 断线重连恢复原有房间
+    Rejoin,
+    /// 模块在服务端加入
+    Server,
+}
+
+/// 加入被拒绝的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoomDenial {
+    /// 不是房间成员
+    NotMember,
+    /// 房间只能由服务端加入
+    ServerManaged,
+}
+
+impl RoomDenial {
+    /// 对应的消息代码
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotMember => codes::ROOM_NOT_MEMBER,
+            Self::ServerManaged => codes::ROOM_SERVER_MANAGED,
+        }
+    }
+}
+
+/// 修改房间权限失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoomAclError {
+    /// 房间属于其他模块
+    OwnedBy(RoomOwner),
+    /// 房间未声明
+    Undeclared,
+}
+
+impl std::fmt::Display for RoomAclError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OwnedBy(owner) => write!(f, "房间属于其他模块: {:?}", owner),
+            Self::Undeclared => write!(f, "房间未声明"),
+        }
+    }
+}
+
+impl std::error::Error for RoomAclError {}
+
+/// 单个房间的权限
+#[derive(Debug, Clone)]
+pub struct RoomAcl {
+    pub owner: RoomOwner,
+    pub policy: JoinPolicy,
+    pub members: HashSet<String>,
+}
+
+impl RoomAcl {
+    fn new(owner: RoomOwner, policy: JoinPolicy) -> Self {
+        Self { owner, policy, members: HashSet::new() }
+    }
+
+    fn check_join(&self, user_id: &str, access: JoinAccess) -> Result<(), RoomDenial> {
+        match (self.policy, access) {
+            (_, JoinAccess::Server) | (JoinPolicy::Open, _) => Ok(()),
+            (JoinPolicy::Server, JoinAccess::Client) => Err(RoomDenial::ServerManaged),
+            (JoinPolicy::Server, JoinAccess::Rejoin) => Ok(()),
+            (JoinPolicy::Members, _) if self.members.contains(user_id) => Ok(()),
+            (JoinPolicy::Members, _) => Err(RoomDenial::NotMember),
+        }
+    }
+
+    fn may_receive(&self, user_id: &str) -> bool {
+        self.policy != JoinPolicy::Members || self.members.contains(user_id)
+    }
+}
+
+/// 所有房间的权限
+#[derive(Debug, Default)]
+pub struct RoomAcls {
+    /// 按房间ID声明的权限
+    rooms: RwLock<HashMap<String, RoomAcl>>,
+    /// 按前缀保留的权限，前缀下的房间都按此处理
+    prefixes: RwLock<Vec<(String, RoomAcl)>>,
+}
+
+impl RoomAcls {
+    /// 保留房间前缀，前缀下未单独声明的房间都属于该所有者
+    pub fn reserve_prefix(&self, prefix: &str, owner: RoomOwner, policy: JoinPolicy) -> Result<(), RoomAclError> {
+        let mut prefixes = self.prefixes.write();
+        if let Some((_, acl)) = prefixes.iter().find(|(p, _)| p == prefix) {
+            return if acl.owner == owner { Ok(()) } else { Err(RoomAclError::OwnedBy(acl.owner)) };
+        }
+        prefixes.push((prefix.to_string(), RoomAcl::new(owner, policy)));
+        Ok(())
+    }
+
+    /**
+     * 声明房间，已由同一所有者声明时更新加入策略并保留成员
+     *
+     * 参数:
+     * @param room_id - 房间ID
+     * @param owner - 所有者
+     * @param policy - 加入策略
+     *
+     * 返回:
+     * 房间或其前缀属于其他所有者时返回错误
+     */
+    pub fn declare(&self, room_id: &str, owner: RoomOwner, policy: JoinPolicy) -> Result<(), RoomAclError> {
+        if let Some(acl) = self.prefix_acl(room_id) {
+            if acl.owner != owner {
+                return Err(RoomAclError::OwnedBy(acl.owner));
+            }
+        }
+        let mut rooms = self.rooms.write();
+        match rooms.get_mut(room_id) {
+            Some(acl) if acl.owner != owner => Err(RoomAclError::OwnedBy(acl.owner)),
+            Some(acl) => {
+                acl.policy = policy;
+                Ok(())
+            }
+            None => {
+                rooms.insert(room_id.to_string(), RoomAcl::new(owner, policy));
+                Ok(())
+            }
+        }
+    }
+
+    /// 替换房间的全部成员
+    pub fn set_members<I>(&self, room_id: &str, owner: RoomOwner, members: I) -> Result<(), RoomAclError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut rooms = self.rooms.write();
+        let acl = rooms.get_mut(room_id).ok_or(RoomAclError::Undeclared)?;
+        if acl.owner != owner {
+            return Err(RoomAclError::OwnedBy(acl.owner));
+        }
+        acl.members = members.into_iter().collect();
+        Ok(())
+    }
+
+    /// 释放房间，之后按前缀或未声明处理
+    pub fn release(&self, room_id: &str, owner: RoomOwner) -> Result<(), RoomAclError> {
+        let mut rooms = self.rooms.write();
+        match rooms.get(room_id) {
+            None => Ok(()),
+            Some(acl) if acl.owner != owner => Err(RoomAclError::OwnedBy(acl.owner)),
+            Some(_) => {
+                rooms.remove(room_id);
+                Ok(())
+            }
+        }
+    }
+
+    /// 检查用户能否加入房间
+    pub fn check_join(&self, room_id: &str, user_id: &str, access: JoinAccess) -> Result<(), RoomDenial> {
+        match self.rooms.read().get(room_id) {
+            Some(acl) => acl.check_join(user_id, access),
+            None => self.prefix_acl(room_id).map_or(Ok(()), |acl| acl.check_join(user_id, access)),
+        }
+    }
+
+    /// 房间内的用户能否接收广播
+    pub fn may_receive(&self, room_id: &str, user_id: &str) -> bool {
+        match self.rooms.read().get(room_id) {
+            Some(acl) => acl.may_receive(user_id),
+            None => self.prefix_acl(room_id).map_or(true, |acl| acl.may_receive(user_id)),
+        }
+    }
+
+    /// 房间的权限，未声明时返回前缀保留的权限
+    pub fn get(&self, room_id: &str) -> Option<RoomAcl> {
+        self.rooms.read().get(room_id).cloned().or_else(|| self.prefix_acl(room_id))
+    }
+
+    /// 已声明的房间数量
+    pub fn len(&self) -> usize {
+        self.rooms.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn prefix_acl(&self, room_id: &str) -> Option<RoomAcl> {
+        self.prefixes
+            .read()
+            .iter()
+            .find(|(prefix, _)| room_id.starts_with(prefix.as_str()))
+            .map(|(_, acl)| acl.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_room_acls() {
+        let acls = RoomAcls::default();
+        acls.reserve_prefix("chat:", RoomOwner::Chat, JoinPolicy::Server).unwrap();
+        acls.declare("m1", RoomOwner::Match, JoinPolicy::Members).unwrap();
+        acls.set_members("m1", RoomOwner::Match, ["alice".to_string()]).unwrap();
+
+        // 未声明的房间任何人都可以加入
+        assert_eq!(acls.check_join("lobby", "eve", JoinAccess::Client), Ok(()));
+        assert!(acls.may_receive("lobby", "eve"));
+
+        // 对局房间只有成员可以加入和接收广播
+        assert_eq!(acls.check_join("m1", "alice", JoinAccess::Client), Ok(()));
+        assert_eq!(acls.check_join("m1", "eve", JoinAccess::Client), Err(RoomDenial::NotMember));
+        assert_eq!(acls.check_join("m1", "eve", JoinAccess::Rejoin), Err(RoomDenial::NotMember));
+        assert_eq!(acls.check_join("m1", "eve", JoinAccess::Server), Ok(()));
+        assert!(!acls.may_receive("m1", "eve"));
+
+        // 聊天室只能由服务端加入
+        assert_eq!(acls.check_join("chat:general", "alice", JoinAccess::Client), Err(RoomDenial::ServerManaged));
+        assert_eq!(acls.check_join("chat:general", "alice", JoinAccess::Rejoin), Ok(()));
+
+        // 其他模块不能修改或声明不属于自己的房间
+        assert_eq!(acls.set_members("m1", RoomOwner::Chat, []), Err(RoomAclError::OwnedBy(RoomOwner::Match)));
+        assert_eq!(acls.declare("chat:m1", RoomOwner::Match, JoinPolicy::Open), Err(RoomAclError::OwnedBy(RoomOwner::Chat)));
+        assert_eq!(acls.release("m1", RoomOwner::Chat), Err(RoomAclError::OwnedBy(RoomOwner::Match)));
+
+        acls.release("m1", RoomOwner::Match).unwrap();
+        assert_eq!(acls.check_join("m1", "eve", JoinAccess::Client), Ok(()));
+        assert!(acls.is_empty());
+    }
+}
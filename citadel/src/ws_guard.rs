@@ -0,0 +1,295 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! WebSocket负载校验模块
+//!
+//! # 概述
+//! `ConnectionManager` 在分发任何事件之前使用本模块校验客户端消息，避免超大或恶意构造的负载
+//! 被原样转发给房间内的其他成员：
+//! - 帧大小：文本帧和二进制帧在解码前检查字节数
+//! - JSON嵌套深度：解码后检查 `data` 的嵌套层数
+//! - 字符串长度：按字段名对用户提供的字段限制字符数，名称、聊天消息和状态消息各有上限，
+//!   ID类字段和其他字符串使用通用上限
+//!
+//! 校验失败时不再处理该消息，向客户端发送 `payload:rejected` 事件，payload为结构化的违规信息。
+//!
+//! # 配置
+//! - `WS_MAX_FRAME_BYTES`：单帧最大字节数，默认65536
+//! - `WS_MAX_JSON_DEPTH`：`data` 最大嵌套深度，默认16
+//! - `WS_MAX_STRING_LEN`：字符串字段的通用上限（字符），默认4096
+//! - `WS_MAX_NAME_LEN`：名称字段上限，默认32
+//! - `WS_MAX_CHAT_LEN`：聊天消息上限，默认1000
+//!
+//! # 消息格式
+//! ```json
+//! { "event": "payload:rejected", "data": { "ok": false, "msg": "...",
+//!   "payload": { "reason": "field_too_long", "event": "chat:send-message", "field": "text", "size": 1200, "limit": 1000 } } }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::i18n::{self, codes, LocalizedText};
+use crate::presence::MAX_STATUS_MESSAGE_LEN;
+use crate::ws::WsMessage;
+
+/// 事件名称
+pub mod events {
+    /// 消息被拒绝
+    pub const PAYLOAD_REJECTED: &str = "payload:rejected";
+}
+
+/// 事件名称的最大长度
+pub const MAX_EVENT_NAME_LEN: usize = 64;
+/// ID类字段的最大长度
+pub const MAX_ID_LEN: usize = 128;
+
+/// 名称类字段
+const NAME_FIELDS: &[&str] = &["name", "username", "displayName"];
+/// 聊天消息字段
+const CHAT_FIELDS: &[&str] = &["text", "message"];
+/// 状态消息字段
+const STATUS_FIELDS: &[&str] = &["statusMessage"];
+
+/// 违规原因
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationReason {
+    /// 帧超过大小上限
+    FrameTooLarge,
+    /// JSON嵌套过深
+    TooDeep,
+    /// 事件名称过长
+    EventTooLong,
+    /// 字符串字段过长
+    FieldTooLong,
+}
+
+/// 结构化的违规信息
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PayloadViolation {
+    pub reason: ViolationReason,
+    /// 被拒绝的事件，帧解码前被拒绝时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    /// 超长的字段路径，如 `data.players[0].name`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// 实际大小（字节、层数或字符数）
+    pub size: usize,
+    /// 上限
+    pub limit: usize,
+}
+
+impl PayloadViolation {
+    fn new(reason: ViolationReason, size: usize, limit: usize) -> Self {
+        Self { reason, event: None, field: None, size, limit }
+    }
+
+    /// 本地化的提示文本
+    pub fn text(&self) -> LocalizedText {
+        let size = self.size.to_string();
+        let limit = self.limit.to_string();
+        match self.reason {
+            ViolationReason::FrameTooLarge => i18n::text(codes::PAYLOAD_TOO_LARGE, &[("size", size), ("limit", limit)]),
+            ViolationReason::TooDeep => i18n::text(codes::PAYLOAD_TOO_DEEP, &[("limit", limit)]),
+            ViolationReason::EventTooLong | ViolationReason::FieldTooLong => i18n::text(
+                codes::PAYLOAD_FIELD_TOO_LONG,
+                &[("field", self.field.clone().unwrap_or_else(|| "event".to_string())), ("limit", limit)],
+            ),
+        }
+    }
+}
+
+/// 负载限制
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayloadLimits {
+    pub max_frame_bytes: usize,
+    pub max_depth: usize,
+    pub max_string_len: usize,
+    pub max_name_len: usize,
+    pub max_chat_len: usize,
+}
+
+impl Default for PayloadLimits {
+    fn default() -> Self {
+        Self {
+            max_frame_bytes: 64 * 1024,
+            max_depth: 16,
+            max_string_len: 4096,
+            max_name_len: 32,
+            max_chat_len: 1000,
+        }
+    }
+}
+
+impl PayloadLimits {
+    /// 从环境变量读取，未配置或无效的项使用默认值
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let read = |name: &str, default: usize| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(default)
+        };
+        Self {
+            max_frame_bytes: read("WS_MAX_FRAME_BYTES", defaults.max_frame_bytes),
+            max_depth: read("WS_MAX_JSON_DEPTH", defaults.max_depth),
+            max_string_len: read("WS_MAX_STRING_LEN", defaults.max_string_len),
+            max_name_len: read("WS_MAX_NAME_LEN", defaults.max_name_len),
+            max_chat_len: read("WS_MAX_CHAT_LEN", defaults.max_chat_len),
+        }
+    }
+
+    /// 检查帧大小
+    pub fn check_frame(&self, len: usize) -> Result<(), PayloadViolation> {
+        if len > self.max_frame_bytes {
+            return Err(PayloadViolation::new(ViolationReason::FrameTooLarge, len, self.max_frame_bytes));
+        }
+        Ok(())
+    }
+
+    /// 字段的长度上限
+    fn field_limit(&self, key: &str) -> usize {
+        if NAME_FIELDS.contains(&key) {
+            self.max_name_len
+        } else if CHAT_FIELDS.contains(&key) {
+            self.max_chat_len
+        } else if STATUS_FIELDS.contains(&key) {
+            MAX_STATUS_MESSAGE_LEN
+        } else if key == "id" || key.ends_with("Id") {
+            MAX_ID_LEN
+        } else {
+            self.max_string_len
+        }
+    }
+
+    /**
+     * 检查已解码的消息
+     *
+     * 参数:
+     * @param message - 客户端消息
+     *
+     * 返回:
+     * 通过返回Ok，否则返回第一处违规
+     */
+    pub fn check_message(&self, message: &WsMessage) -> Result<(), PayloadViolation> {
+        let result = self.check_event_name(&message.event).and_then(|_| match &message.data {
+            Some(data) => self.check_value(data, "data", None, 1),
+            None => Ok(()),
+        });
+        result.map_err(|violation| PayloadViolation {
+            event: Some(message.event.chars().take(MAX_EVENT_NAME_LEN).collect()),
+            ..violation
+        })
+    }
+
+    fn check_event_name(&self, event: &str) -> Result<(), PayloadViolation> {
+        let len = event.chars().count();
+        if len > MAX_EVENT_NAME_LEN {
+            return Err(PayloadViolation::new(ViolationReason::EventTooLong, len, MAX_EVENT_NAME_LEN));
+        }
+        Ok(())
+    }
+
+    /// 递归检查嵌套深度和字符串长度，key为所在对象的字段名
+    fn check_value(&self, value: &Value, path: &str, key: Option<&str>, depth: usize) -> Result<(), PayloadViolation> {
+        match value {
+            Value::String(s) => {
+                let limit = key.map_or(self.max_string_len, |k| self.field_limit(k));
+                let len = s.chars().count();
+                if len > limit {
+                    return Err(PayloadViolation {
+                        field: Some(path.to_string()),
+                        ..PayloadViolation::new(ViolationReason::FieldTooLong, len, limit)
+                    });
+                }
+                Ok(())
+            }
+            Value::Array(items) => {
+                self.check_depth(depth)?;
+                items
+                    .iter()
+                    .enumerate()
+                    .try_for_each(|(i, item)| self.check_value(item, &format!("{}[{}]", path, i), key, depth + 1))
+            }
+            Value::Object(map) => {
+                self.check_depth(depth)?;
+                map.iter().try_for_each(|(k, v)| {
+                    if k.chars().count() > MAX_EVENT_NAME_LEN {
+                        return Err(PayloadViolation {
+                            field: Some(path.to_string()),
+                            ..PayloadViolation::new(ViolationReason::FieldTooLong, k.chars().count(), MAX_EVENT_NAME_LEN)
+                        });
+                    }
+                    self.check_value(v, &format!("{}.{}", path, k), Some(k), depth + 1)
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_depth(&self, depth: usize) -> Result<(), PayloadViolation> {
+        if depth > self.max_depth {
+            return Err(PayloadViolation::new(ViolationReason::TooDeep, depth, self.max_depth));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(event: &str, data: Value) -> WsMessage {
+        WsMessage {
+            event: event.to_string(),
+            data: Some(data),
+            msg_id: None,
+        }
+    }
+
+    #[test]
+    fn test_field_limits() {
+        let limits = PayloadLimits::default();
+        assert!(limits.check_message(&message("chat:send-message", json!({ "chatId": "c1", "text": "hi" }))).is_ok());
+
+        let text = "字".repeat(limits.max_chat_len + 1);
+        let violation = limits
+            .check_message(&message("chat:send-message", json!({ "chatId": "c1", "text": text })))
+            .unwrap_err();
+        assert_eq!(violation.reason, ViolationReason::FieldTooLong);
+        assert_eq!(violation.field.as_deref(), Some("data.text"));
+        assert_eq!(violation.event.as_deref(), Some("chat:send-message"));
+        assert_eq!((violation.size, violation.limit), (limits.max_chat_len + 1, limits.max_chat_len));
+
+        // 数组中的名称使用所在字段的上限
+        let name = "a".repeat(limits.max_name_len + 1);
+        let violation = limits.check_message(&message("x", json!({ "players": [{ "name": "ok" }, { "name": name }] }))).unwrap_err();
+        assert_eq!(violation.field.as_deref(), Some("data.players[1].name"));
+
+        let violation = limits.check_message(&message("x", json!({ "matchId": "m".repeat(MAX_ID_LEN + 1) }))).unwrap_err();
+        assert_eq!(violation.limit, MAX_ID_LEN);
+        assert_eq!(
+            limits.check_message(&message(&"e".repeat(MAX_EVENT_NAME_LEN + 1), json!({}))).unwrap_err().reason,
+            ViolationReason::EventTooLong
+        );
+    }
+
+    #[test]
+    fn test_depth_and_frame() {
+        let limits = PayloadLimits { max_depth: 3, ..Default::default() };
+        assert!(limits.check_message(&message("x", json!({ "a": { "b": [1] } }))).is_ok());
+        let violation = limits.check_message(&message("x", json!({ "a": { "b": [[1]] } }))).unwrap_err();
+        assert_eq!(violation.reason, ViolationReason::TooDeep);
+        assert_eq!(violation.limit, 3);
+
+        assert!(limits.check_frame(limits.max_frame_bytes).is_ok());
+        assert_eq!(limits.check_frame(limits.max_frame_bytes + 1).unwrap_err().reason, ViolationReason::FrameTooLarge);
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! WebSocket事件处理器注册
+//!
+//! # 概述
+//! 用户护照、聊天、组队和对局等模块各自处理一组WebSocket事件。模块在初始化时通过
+//! `ConnectionManager::register_handler` 注册处理器，`ConnectionManager` 收到事件后按事件名分发，
+//! 新模块接入时不需要修改 `ws.rs` 的消息处理逻辑。
+//!
+//! 事件模式有两种：
+//! - 完整的事件名，如 `match:create`
+//! - 以 `*` 结尾的前缀，如 `chat:*` 匹配所有以 `chat:` 开头的事件
+//!
+//! 分发时先查找完整事件名，再按前缀从长到短依次尝试。处理器返回 `Ok(true)` 表示已处理；
+//! 返回 `Ok(false)` 时继续尝试下一个匹配的处理器，都没有处理时交给连接管理器的内置事件（房间、重连等）。
+//! 处理器返回错误时记录日志，不再继续分发。
+//!
+//! 协议握手、会话证明和事件确认在分发前由连接管理器处理，不能被注册的处理器覆盖。
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use parking_lot::RwLock;
+
+use crate::chat::UserInfo;
+use crate::ws::{ConnectionManager, WsMessage};
+
+/// 分发给处理器的事件
+#[derive(Debug, Clone)]
+pub struct WsEvent {
+    /// 发送事件的客户端ID
+    pub client_id: String,
+    pub message: WsMessage,
+    /// 连接对应的用户，未认证时为None
+    pub user: Option<UserInfo>,
+    pub connection_manager: ConnectionManager,
+}
+
+type BoxedHandler = Arc<dyn Fn(WsEvent) -> BoxFuture<'static, Result<bool>> + Send + Sync>;
+
+/// 已注册的处理器
+#[derive(Default)]
+pub struct WsHandlers {
+    events: RwLock<HashMap<String, Vec<BoxedHandler>>>,
+    /// 按前缀长度从长到短排列
+    prefixes: RwLock<Vec<(String, BoxedHandler)>>,
+}
+
+impl std::fmt::Debug for WsHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WsHandlers")
+            .field("events", &self.events.read().keys().collect::<Vec<_>>())
+            .field("prefixes", &self.prefixes.read().iter().map(|(p, _)| p).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl WsHandlers {
+    /**
+     * 注册处理器
+     *
+     * 参数:
+     * @param pattern - 完整的事件名，或以 `*` 结尾的前缀
+     * @param handler - 处理函数，返回是否已处理
+     */
+    pub fn register<F, Fut>(&self, pattern: &str, handler: F)
+    where
+        F: Fn(WsEvent) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<bool>> + Send + 'static,
+    {
+        let handler: BoxedHandler = Arc::new(move |event| Box::pin(handler(event)));
+        match pattern.strip_suffix('*') {
+            Some(prefix) => {
+                let mut prefixes = self.prefixes.write();
+                prefixes.push((prefix.to_string(), handler));
+                prefixes.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+            }
+            None => self.events.write().entry(pattern.to_string()).or_default().push(handler),
+        }
+    }
+
+    /// 按分发顺序匹配事件的处理器
+    fn matching(&self, event: &str) -> Vec<BoxedHandler> {
+        let mut handlers = self.events.read().get(event).cloned().unwrap_or_default();
+        handlers.extend(
+            self.prefixes
+                .read()
+                .iter()
+                .filter(|(prefix, _)| event.starts_with(prefix.as_str()))
+                .map(|(_, handler)| handler.clone()),
+        );
+        handlers
+    }
+
+    /**
+     * 把事件依次交给匹配的处理器
+     *
+     * 参数:
+     * @param event - 事件
+     *
+     * 返回:
+     * 是否有处理器处理了事件，处理器返回错误时返回该错误
+     */
+    pub async fn dispatch(&self, event: WsEvent) -> Result<bool> {
+        for handler in self.matching(&event.message.event) {
+            if handler(event.clone()).await? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(name: &str) -> WsEvent {
+        WsEvent {
+            client_id: "c1".to_string(),
+            message: WsMessage {
+                event: name.to_string(),
+                data: None,
+                msg_id: None,
+            },
+            user: None,
+            connection_manager: ConnectionManager::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_order() {
+        let handlers = WsHandlers::default();
+        let seen = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        for (pattern, handled) in [("chat:*", true), ("chat:send", false), ("chat:admin:*", true)] {
+            let seen = seen.clone();
+            handlers.register(pattern, move |_| {
+                seen.lock().push(pattern);
+                async move { Ok(handled) }
+            });
+        }
+
+        // 完整事件名未处理时交给前缀处理器
+        assert!(handlers.dispatch(event("chat:send")).await.unwrap());
+        assert_eq!(*seen.lock(), vec!["chat:send", "chat:*"]);
+
+        // 较长的前缀优先
+        seen.lock().clear();
+        assert!(handlers.dispatch(event("chat:admin:mute")).await.unwrap());
+        assert_eq!(*seen.lock(), vec!["chat:admin:*"]);
+
+        assert!(!handlers.dispatch(event("match:create")).await.unwrap());
+    }
+}
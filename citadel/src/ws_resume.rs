@@ -0,0 +1,350 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! WebSocket连接恢复令牌
+//!
+//! # 概述
+//! 重连以前直接使用旧的客户端ID，知道客户端ID的任何人都可以接管其房间，断开的客户端也永远可以恢复。
+//! 现在每个连接建立时服务器签发一个恢复令牌，重连时必须出示令牌：
+//! - 令牌绑定客户端ID、用户ID和过期时间，使用服务器密钥签名（HMAC-SHA3-256），客户端无法伪造或修改
+//! - 连接期间服务器每隔半个有效期签发新令牌，客户端保存最新的令牌，断开后至少还有半个有效期可以恢复
+//! - 每个令牌带有递增的序号，令牌使用后，该客户端此前签发的所有令牌失效，
+//!   恢复后的连接会收到序号更大的新令牌
+//! - 出示已过期的令牌时，旧客户端的房间成员资格被清除，不能再恢复
+//!
+//! 断开后没有重连的客户端不会出示令牌，`ConnectionManager` 的清理任务定期检查保留的房间记录：
+//! 客户端不在线且最新令牌已过期（即超过恢复窗口）时，清除其房间成员资格和签发记录。
+//!
+//! 令牌格式为 `{claims}.{signature}`，两部分都是Hex编码，claims是JSON。
+//! 签发记录只保存在内存中，服务器重启后所有令牌失效，与房间数据一致。
+//!
+//! # 配置
+//! - `WS_RESUME_SECRET`：签名密钥，为空时启动时随机生成
+//! - `WS_RESUME_TTL_SECS`：令牌有效期（秒），默认600
+//! - `WS_RESUME_REAP_INTERVAL_SECS`：清理过期房间记录的间隔（秒），默认60
+//!
+//! # 消息格式
+//! - `resume_token`（服务器发给客户端）：payload包含clientId、resumeToken和expiresAt
+//! - `reconnect`（客户端）：`{resumeToken}`，把令牌对应的旧客户端的房间迁移到当前连接
+//! - `GET /ws/reconnect?resume_token=...`：使用令牌对应的客户端ID建立连接
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::hmac::{hmac_sha3_256, HmacKey};
+use fastcrypto::traits::ToFromBytes;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::common::constant_time_eq;
+use crate::ws::ClientId;
+
+/// 服务器发送恢复令牌的事件
+pub const RESUME_TOKEN_EVENT: &str = "resume_token";
+/// 默认令牌有效期（秒）
+pub const DEFAULT_RESUME_TTL_SECS: u64 = 600;
+/// 默认清理间隔（秒）
+pub const DEFAULT_REAP_INTERVAL_SECS: u64 = 60;
+
+static RESUME_TOKENS: Lazy<Arc<ResumeTokens>> = Lazy::new(|| Arc::new(ResumeTokens::new(ResumeConfig::from_env())));
+
+/// 全局的恢复令牌签发器，进程内的测试服务也通过它为指定的客户端ID签发令牌
+pub fn resume_tokens() -> Arc<ResumeTokens> {
+    RESUME_TOKENS.clone()
+}
+
+/// 恢复令牌配置
+#[derive(Clone)]
+pub struct ResumeConfig {
+    secret: Vec<u8>,
+    /// 令牌有效期（毫秒）
+    pub ttl_ms: u64,
+    /// 清理过期房间记录的间隔
+    pub reap_interval: Duration,
+}
+
+impl std::fmt::Debug for ResumeConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResumeConfig")
+            .field("ttl_ms", &self.ttl_ms)
+            .field("reap_interval", &self.reap_interval)
+            .finish()
+    }
+}
+
+impl ResumeConfig {
+    pub fn new(secret: Vec<u8>, ttl_ms: u64) -> Self {
+        Self {
+            secret,
+            ttl_ms,
+            reap_interval: Duration::from_secs(DEFAULT_REAP_INTERVAL_SECS),
+        }
+    }
+
+    /// 从环境变量读取，未配置密钥时随机生成
+    pub fn from_env() -> Self {
+        let secret = std::env::var("WS_RESUME_SECRET")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .map(|v| v.into_bytes())
+            .unwrap_or_else(|| rand::thread_rng().gen::<[u8; 32]>().to_vec());
+        let parse = |key: &str| {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .filter(|v| *v > 0)
+        };
+        let ttl_secs = parse("WS_RESUME_TTL_SECS").unwrap_or(DEFAULT_RESUME_TTL_SECS);
+        Self {
+            reap_interval: Duration::from_secs(parse("WS_RESUME_REAP_INTERVAL_SECS").unwrap_or(DEFAULT_REAP_INTERVAL_SECS)),
+            ..Self::new(secret, ttl_secs * 1000)
+        }
+    }
+
+    /// 连接期间重新签发令牌的间隔（毫秒）
+    pub fn refresh_interval_ms(&self) -> u64 {
+        self.ttl_ms / 2
+    }
+
+    fn sign(&self, claims: &[u8]) -> Vec<u8> {
+        let key = HmacKey::from_bytes(&self.secret).expect("hmac key accepts any length");
+        hmac_sha3_256(&key, claims).digest.to_vec()
+    }
+}
+
+/// 令牌的内容
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResumeClaims {
+    pub client_id: ClientId,
+    pub user_id: String,
+    /// 签发序号，在服务器进程内唯一
+    pub generation: u64,
+    /// 过期时间（毫秒）
+    pub expires_at: u64,
+}
+
+/// 签发的令牌
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResumeToken {
+    pub token: String,
+    pub expires_at: u64,
+}
+
+/// 令牌校验失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResumeError {
+    /// 格式错误或签名不匹配
+    Invalid,
+    /// 已使用，或服务器重启后没有签发记录
+    Revoked,
+    /// 签名有效但已过期，附带令牌内容以便清理旧客户端
+    Expired(ResumeClaims),
+}
+
+impl std::fmt::Display for ResumeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResumeError::Invalid => write!(f, "恢复令牌无效"),
+            ResumeError::Revoked => write!(f, "恢复令牌已失效"),
+            ResumeError::Expired(claims) => write!(f, "客户端 {} 的恢复令牌已过期", claims.client_id),
+        }
+    }
+}
+
+/// 客户端的签发记录
+#[derive(Debug, Clone, Copy)]
+struct Issued {
+    /// 仍然有效的最小序号，令牌使用后提升到已签发的最大序号之后
+    valid_from: u64,
+    /// 最新签发的序号
+    latest: u64,
+    /// 最新签发的令牌的过期时间（毫秒）
+    expires_at: u64,
+}
+
+impl Issued {
+    /// 是否还有未使用的令牌
+    fn has_valid(&self) -> bool {
+        self.latest >= self.valid_from
+    }
+}
+
+/// 恢复令牌的签发和校验
+#[derive(Debug)]
+pub struct ResumeTokens {
+    config: ResumeConfig,
+    /// 下一个签发序号
+    next_generation: AtomicU64,
+    /// 客户端ID到签发记录，令牌使用后保留记录直到最新令牌过期，用于拒绝已使用的令牌
+    issued: Mutex<HashMap<ClientId, Issued>>,
+}
+
+impl ResumeTokens {
+    pub fn new(config: ResumeConfig) -> Self {
+        Self {
+            config,
+            next_generation: AtomicU64::new(1),
+            issued: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &ResumeConfig {
+        &self.config
+    }
+
+    /**
+     * 为连接签发令牌
+     *
+     * 参数:
+     * @param client_id - 客户端ID
+     * @param user_id - 用户ID
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 令牌和过期时间
+     */
+    pub fn issue(&self, client_id: &str, user_id: &str, now: u64) -> ResumeToken {
+        let claims = ResumeClaims {
+            client_id: client_id.to_string(),
+            user_id: user_id.to_string(),
+            generation: self.next_generation.fetch_add(1, Ordering::Relaxed),
+            expires_at: now + self.config.ttl_ms,
+        };
+        let bytes = serde_json::to_vec(&claims).expect("claims serialize");
+        let token = format!("{}.{}", Hex::encode(&bytes), Hex::encode(self.config.sign(&bytes)));
+        let mut issued = self.issued.lock();
+        let record = issued.entry(claims.client_id).or_insert(Issued {
+            valid_from: claims.generation,
+            latest: claims.generation,
+            expires_at: claims.expires_at,
+        });
+        record.latest = record.latest.max(claims.generation);
+        record.expires_at = record.expires_at.max(claims.expires_at);
+        ResumeToken {
+            token,
+            expires_at: claims.expires_at,
+        }
+    }
+
+    /**
+     * 校验令牌
+     *
+     * 同一客户端签发过的令牌在各自的过期时间前都有效，直到其中一个被使用。
+     * 使用后此前签发的令牌全部失效，即使之后又为同一客户端签发了新令牌。
+     *
+     * 参数:
+     * @param token - 客户端出示的令牌
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 令牌内容，或校验失败的原因
+     */
+    pub fn verify(&self, token: &str, now: u64) -> Result<ResumeClaims, ResumeError> {
+        let (claims, signature) = token.split_once('.').ok_or(ResumeError::Invalid)?;
+        let claims = Hex::decode(claims).map_err(|_| ResumeError::Invalid)?;
+        let signature = Hex::decode(signature).map_err(|_| ResumeError::Invalid)?;
+        if !constant_time_eq(&self.config.sign(&claims), &signature) {
+            return Err(ResumeError::Invalid);
+        }
+        let claims: ResumeClaims = serde_json::from_slice(&claims).map_err(|_| ResumeError::Invalid)?;
+        if now > claims.expires_at {
+            return Err(ResumeError::Expired(claims));
+        }
+        match self.issued.lock().get(&claims.client_id) {
+            Some(record) if (record.valid_from..=record.latest).contains(&claims.generation) => Ok(claims),
+            _ => Err(ResumeError::Revoked),
+        }
+    }
+
+    /// 令牌已使用或客户端已清理，之前签发的令牌全部失效
+    pub fn revoke(&self, client_id: &str) {
+        if let Some(record) = self.issued.lock().get_mut(client_id) {
+            record.valid_from = record.latest + 1;
+        }
+    }
+
+    /// 客户端是否还在恢复窗口内，即有未使用且未过期的令牌
+    pub fn is_resumable(&self, client_id: &str, now: u64) -> bool {
+        self.issued
+            .lock()
+            .get(client_id)
+            .is_some_and(|record| record.has_valid() && now <= record.expires_at)
+    }
+
+    /**
+     * 移除已过期的签发记录
+     *
+     * 参数:
+     * @param now - 当前时间（毫秒）
+     *
+     * 返回:
+     * 移除的记录数
+     */
+    pub fn prune(&self, now: u64) -> usize {
+        let mut issued = self.issued.lock();
+        let before = issued.len();
+        issued.retain(|_, record| now <= record.expires_at);
+        before - issued.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_token_lifecycle() {
+        let tokens = ResumeTokens::new(ResumeConfig::new(b"secret".to_vec(), 1000));
+        let first = tokens.issue("c1", "u1", 0);
+        let second = tokens.issue("c1", "u1", 500);
+        assert_eq!(second.expires_at, 1500);
+
+        // 较早签发的令牌在过期前仍然有效
+        let claims = tokens.verify(&first.token, 800).unwrap();
+        assert_eq!(claims.user_id, "u1");
+        assert!(matches!(tokens.verify(&first.token, 1200), Err(ResumeError::Expired(c)) if c.client_id == "c1"));
+
+        // 篡改内容或使用其他密钥签名的令牌无效
+        let other = ResumeTokens::new(ResumeConfig::new(b"other".to_vec(), 1000));
+        assert_eq!(tokens.verify(&other.issue("c1", "u1", 0).token, 0), Err(ResumeError::Invalid));
+        assert_eq!(tokens.verify("abc", 0), Err(ResumeError::Invalid));
+
+        // 使用后失效
+        tokens.revoke("c1");
+        assert_eq!(tokens.verify(&second.token, 600), Err(ResumeError::Revoked));
+        assert!(!tokens.is_resumable("c1", 600));
+    }
+
+    #[test]
+    fn test_used_token_rejected_after_reissue() {
+        let tokens = ResumeTokens::new(ResumeConfig::new(b"secret".to_vec(), 1000));
+        let used = tokens.issue("c1", "u1", 0);
+        assert!(tokens.verify(&used.token, 100).is_ok());
+        tokens.revoke("c1");
+
+        // 恢复后的连接使用同一客户端ID收到新令牌，已使用的令牌不能再次使用
+        let fresh = tokens.issue("c1", "u1", 200);
+        assert_eq!(tokens.verify(&used.token, 300), Err(ResumeError::Revoked));
+        assert!(tokens.verify(&fresh.token, 300).is_ok());
+        assert!(tokens.is_resumable("c1", 300));
+    }
+
+    #[test]
+    fn test_resume_window() {
+        let tokens = ResumeTokens::new(ResumeConfig::new(b"secret".to_vec(), 1000));
+        tokens.issue("c1", "u1", 0);
+        tokens.issue("c2", "u2", 800);
+        assert!(tokens.is_resumable("c1", 1000));
+        assert!(!tokens.is_resumable("c1", 1001));
+        assert!(!tokens.is_resumable("c3", 0));
+
+        assert_eq!(tokens.prune(1200), 1);
+        assert!(tokens.is_resumable("c2", 1200));
+    }
+}
@@ -0,0 +1,559 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! WebSocket事件目录模块
+//!
+//! # 概述
+//! WebSocket事件名和负载格式分散在协议、护照、聊天、对局和成就等模块中。
+//! 本模块集中登记所有事件，并通过 `GET /ws/schema` 以JSON Schema形式提供，
+//! 供前端生成TypeScript客户端代码。
+//!
+//! 每个事件包含：
+//! - `event`：事件名称
+//! - `direction`：`client` 表示客户端发送，`server` 表示服务器下发；同名事件可能两个方向都有
+//! - `capability`：需要在 `hello` 中声明的能力，为空表示所有客户端都会收到
+//! - `schema`：消息 `data` 字段的JSON Schema
+//!
+//! 带名称的类型定义放在 `components.schemas` 下，事件中通过 `$ref` 引用。
+//! 负载类型通过 `utoipa::ToSchema` 派生；对局等负载结构较大的服务器事件只描述 `WsResponse` 外层格式，
+//! 具体内容见事件说明。
+//!
+//! 新增事件时需要在 `build_catalog` 中登记。
+
+use std::collections::BTreeMap;
+
+use axum::Json;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use utoipa::ToSchema;
+
+use crate::achievement;
+use crate::announcement;
+use crate::chat::direct::{DirectHistoryRequest, SendDirectMessageRequest};
+use crate::chat::indicators::{ReadRequest, TypingRequest};
+use crate::chat::{ChatEvents, ChatMessage, JoinChatRequest, SendMessageRequest};
+use crate::delivery::{self, AckRequest};
+use crate::gaming::events::match_events;
+use crate::notification::NotificationPreferences;
+use crate::outbound;
+use crate::party::{self, PartyInviteDto, PartyJoinDto};
+use crate::passport::inbox::{FriendRequestCounts, ListFriendRequestsQuery};
+use crate::passport::{
+    AcceptFriendRequestDto, BlockUserDto, ClientEvent, GetSupplementalDto, MatchInviteDto,
+    RejectFriendRequestDto, ResponseEvent, RevokeFriendRequestDto, SendFriendRequestDto,
+    ServerEvent, UnblockUserDto, UnfriendDto, UserInterim,
+};
+use crate::protocol::{self, HelloRequest, PROTOCOL_VERSION};
+use crate::series;
+use crate::session_attestation::{self, AttestRequest, SignedFrameData};
+use crate::ws::WsResponse;
+use crate::ws_guard;
+use crate::ws_resume;
+
+/// JSON Schema方言
+const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// 事件方向
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum EventDirection {
+    /// 客户端发送
+    Client,
+    /// 服务器下发
+    Server,
+}
+
+/// 单个事件的描述
+#[derive(Debug, Clone, Serialize)]
+pub struct WsEventSchema {
+    pub event: &'static str,
+    pub direction: EventDirection,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capability: Option<&'static str>,
+    pub description: &'static str,
+    /// `data` 字段的JSON Schema，None表示不需要数据
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
+}
+
+/// 只包含对局ID的数据
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchIdData {
+    pub match_id: String,
+}
+
+/// `queue:join` 数据，可以省略
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueJoinData {
+    /// 客户端声明的匹配地区，例如 `eu`，不填时按连接IP查找
+    pub region: Option<String>,
+}
+
+/// `match:pause` 数据
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PauseMatchData {
+    pub match_id: String,
+    /// 是否同意暂停，默认true；没有进行中的暂停投票时发起投票
+    pub accept: Option<bool>,
+}
+
+/// `match:vote_skip` 数据
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VoteSkipData {
+    pub match_id: String,
+    /// 当前回合玩家ID，与服务端不一致时拒绝投票
+    pub user_id: Option<String>,
+    /// 是否同意跳过，默认true
+    pub accept: Option<bool>,
+}
+
+/// `match:select_target` 数据
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectTargetData {
+    pub match_id: String,
+    /// 服务端下发的等待选择动作ID
+    pub pending_id: String,
+    /// 选择的玩家ID，必须在候选列表中
+    pub target_id: String,
+}
+
+/// `match:discard` 数据
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscardData {
+    pub match_id: String,
+    /// 服务端下发的等待弃牌动作ID
+    pub pending_id: String,
+    /// 弃掉的卡牌ID，必须恰好是需要弃掉的张数
+    pub card_ids: Vec<String>,
+}
+
+/// `match:create` 数据
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMatchData {
+    /// 押注金额，默认0
+    pub wager: Option<u64>,
+    /// 观战权限
+    pub spectator_policy: Option<String>,
+    /// 观战人数上限
+    pub max_spectators: Option<u64>,
+    /// 游戏模式ID，默认exploding_kittens
+    pub mode: Option<String>,
+    /// 系列赛局数（3或5），不填时为单局，系列赛不支持押注
+    pub best_of: Option<u32>,
+    /// 手牌上限，0表示不限制，不填时使用服务器默认值
+    pub max_hand_size: Option<u64>,
+}
+
+/// `match:invite` 数据
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteMatchData {
+    pub match_id: String,
+    /// 被邀请的好友ID
+    pub user_id: String,
+}
+
+/// `match:play_card` 数据
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayCardData {
+    pub match_id: String,
+    pub card_id: String,
+}
+
+/// `match:can_play` 数据
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CanPlayData {
+    pub match_id: String,
+    /// 卡牌ID，不填时检查所有手牌
+    pub card_id: Option<String>,
+}
+
+/// `join_room` / `leave_room` 数据
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomData {
+    pub room_id: String,
+}
+
+/// `reconnect` 数据
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconnectData {
+    /// 断线前最近收到的恢复令牌
+    pub resume_token: String,
+}
+
+/// 用户上线/下线广播数据
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserStatusData {
+    pub user_id: String,
+}
+
+/// 房间内客户端加入/离开的系统广播数据
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SystemRoomData {
+    pub client_id: String,
+}
+
+/// 新聊天消息广播数据
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChatNewMessageData {
+    pub message: ChatMessage,
+}
+
+/// `chat:typing` 转发数据
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChatTypingData {
+    pub user_id: String,
+    /// 聊天室ID，私信时为空
+    pub chat_id: Option<String>,
+    pub typing: bool,
+}
+
+/// `chat:read` 转发数据
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChatReadData {
+    pub user_id: String,
+    /// 聊天室ID，私信时为空
+    pub chat_id: Option<String>,
+    pub message_id: String,
+    pub read_at: i64,
+}
+
+/// `chat:new-dm` 数据
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChatNewDirectMessageData {
+    pub message: ChatMessage,
+    /// 是否为离线期间收到的消息
+    pub offline: bool,
+}
+
+/// 事件目录
+#[derive(Debug, Default)]
+pub struct WsEventRegistry {
+    events: Vec<WsEventSchema>,
+    schemas: BTreeMap<String, Value>,
+}
+
+impl WsEventRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记类型及其引用的类型，返回指向该类型的 `$ref`
+    fn schema_ref<T: ToSchema>(&mut self) -> Value {
+        let mut nested = Vec::new();
+        T::schemas(&mut nested);
+        for (name, schema) in nested {
+            self.schemas
+                .entry(name)
+                .or_insert_with(|| serde_json::to_value(schema).unwrap_or_default());
+        }
+        let name = T::name().into_owned();
+        self.schemas
+            .entry(name.clone())
+            .or_insert_with(|| serde_json::to_value(T::schema()).unwrap_or_default());
+        json!({ "$ref": format!("#/components/schemas/{}", name) })
+    }
+
+    fn push(
+        &mut self,
+        event: &'static str,
+        direction: EventDirection,
+        description: &'static str,
+        schema: Option<Value>,
+    ) -> &mut Self {
+        let capability = match direction {
+            EventDirection::Client => None,
+            EventDirection::Server => protocol::required_capability(event),
+        };
+        self.events.push(WsEventSchema {
+            event,
+            direction,
+            capability,
+            description,
+            schema,
+        });
+        self
+    }
+
+    /// 登记客户端事件，数据格式为T
+    pub fn client<T: ToSchema>(&mut self, event: &'static str, description: &'static str) -> &mut Self {
+        let schema = self.schema_ref::<T>();
+        self.push(event, EventDirection::Client, description, Some(schema))
+    }
+
+    /// 登记不需要数据的客户端事件
+    pub fn client_without_data(&mut self, event: &'static str, description: &'static str) -> &mut Self {
+        self.push(event, EventDirection::Client, description, None)
+    }
+
+    /// 登记服务器事件，数据格式为T
+    pub fn server<T: ToSchema>(&mut self, event: &'static str, description: &'static str) -> &mut Self {
+        let schema = self.schema_ref::<T>();
+        self.push(event, EventDirection::Server, description, Some(schema))
+    }
+
+    /// 登记数据格式不固定的服务器事件
+    pub fn server_untyped(&mut self, event: &'static str, description: &'static str) -> &mut Self {
+        self.push(event, EventDirection::Server, description, Some(json!({ "type": "object" })))
+    }
+
+    /// 所有已登记的事件
+    pub fn events(&self) -> &[WsEventSchema] {
+        &self.events
+    }
+
+    /// 生成完整的事件目录文档
+    pub fn to_json(&self) -> Value {
+        json!({
+            "jsonSchemaDialect": JSON_SCHEMA_DIALECT,
+            "protocolVersion": PROTOCOL_VERSION,
+            "envelope": {
+                "type": "object",
+                "description": "所有WebSocket消息的外层格式",
+                "properties": {
+                    "event": { "type": "string" },
+                    "data": {}
+                },
+                "required": ["event"]
+            },
+            "events": self.events,
+            "components": { "schemas": self.schemas },
+        })
+    }
+}
+
+/// 登记所有WebSocket事件
+pub fn build_catalog() -> WsEventRegistry {
+    let mut registry = WsEventRegistry::new();
+
+    // 协议握手
+    registry
+        .client::<HelloRequest>(protocol::events::HELLO, "声明协议版本、功能、帧编码和语言")
+        .server::<WsResponse>(protocol::events::HELLO_ACK, "握手成功，payload包含协商后的版本、能力、编码和语言")
+        .server::<WsResponse>(protocol::events::PROTOCOL_ERROR, "握手失败，payload.code为错误码")
+        .server::<WsResponse>(ws_guard::events::PAYLOAD_REJECTED, "消息超过大小限制被拒绝，payload包含reason、field、size和limit")
+        .client::<AckRequest>(delivery::events::ACK, "确认已处理带有msgId的关键事件，需要协商ack能力")
+        .server::<WsResponse>(outbound::events::RESYNC, "网络过慢导致消息被丢弃，客户端应重新请求所在对局的完整状态，payload包含dropped");
+
+    // 会话证明
+    registry
+        .client::<AttestRequest>(session_attestation::events::ATTEST, "发送随机挑战，请求绑定当前会话的飞地证明")
+        .server::<WsResponse>(session_attestation::events::ATTESTED, "会话证明结果，payload包含证明文档和会话绑定信息")
+        .server::<SignedFrameData>(session_attestation::events::SIGNED, "已证明会话收到的签名消息，frame为原消息的JSON字符串");
+
+    // 房间与重连
+    registry
+        .client::<RoomData>("join_room", "加入房间")
+        .client::<RoomData>("leave_room", "离开房间")
+        .client::<ReconnectData>("reconnect", "凭断线前收到的恢复令牌恢复房间")
+        .server::<WsResponse>("room_joined", "已加入房间")
+        .server::<WsResponse>("room_left", "已离开房间")
+        .server::<WsResponse>("reconnect_success", "已恢复断线前加入的房间")
+        .server::<WsResponse>("reconnect_failed", "恢复令牌无效、已使用或已过期，过期时旧客户端的房间已被清除")
+        .server::<WsResponse>(ws_resume::RESUME_TOKEN_EVENT, "恢复令牌，payload包含clientId、resumeToken和expiresAt，连接期间定期更新")
+        .server::<SystemRoomData>("system:join", "有客户端加入对局房间")
+        .server::<SystemRoomData>("system:leave", "有客户端离开对局房间")
+        .server::<WsResponse>(announcement::ANNOUNCEMENT_EVENT, "系统公告，msg为公告内容，payload包含id、level、roomId、sequence和total");
+
+    // 用户护照
+    registry
+        .client::<SendFriendRequestDto>(ClientEvent::SendFriendRequest.as_str(), "发送好友请求")
+        .client::<RevokeFriendRequestDto>(ClientEvent::RevokeFriendRequest.as_str(), "撤销好友请求")
+        .client::<AcceptFriendRequestDto>(ClientEvent::AcceptFriendRequest.as_str(), "接受好友请求")
+        .client::<RejectFriendRequestDto>(ClientEvent::RejectFriendRequest.as_str(), "拒绝好友请求")
+        .client::<UnfriendDto>(ClientEvent::Unfriend.as_str(), "删除好友")
+        .client::<BlockUserDto>(ClientEvent::Block.as_str(), "封禁用户")
+        .client::<UnblockUserDto>(ClientEvent::Unblock.as_str(), "解除封禁")
+        .client::<GetSupplementalDto>(ClientEvent::GetSupplemental.as_str(), "批量获取用户补充信息")
+        .client::<UserInterim>(ClientEvent::SetInterim.as_str(), "设置临时状态、当前活动和自定义状态消息")
+        .client::<MatchInviteDto>(ClientEvent::AcceptMatchInvite.as_str(), "接受游戏邀请")
+        .client::<MatchInviteDto>(ClientEvent::DeclineMatchInvite.as_str(), "拒绝游戏邀请")
+        .client_without_data(ClientEvent::GetNotificationPreferences.as_str(), "获取通知偏好")
+        .client::<NotificationPreferences>(ClientEvent::SetNotificationPreferences.as_str(), "设置通知偏好")
+        .client_without_data(ClientEvent::GetRelationships.as_str(), "一次获取自己的全部好友关系")
+        .client::<ListFriendRequestsQuery>(ClientEvent::ListFriendRequests.as_str(), "分页查询收到或发出的好友请求")
+        .server::<FriendRequestCounts>(ServerEvent::FriendRequestCounts.as_str(), "待处理好友请求数量，数量变化和新建会话时推送")
+        .server::<UserStatusData>(ServerEvent::Online.as_str(), "好友上线")
+        .server::<UserStatusData>(ServerEvent::Offline.as_str(), "好友下线");
+    for event in [
+        ServerEvent::FriendRequestReceived,
+        ServerEvent::FriendRequestAccepted,
+        ServerEvent::FriendRequestRejected,
+        ServerEvent::FriendRequestRevoked,
+        ServerEvent::Unfriended,
+        ServerEvent::MatchInvite,
+        ServerEvent::MatchInviteDeclined,
+    ] {
+        registry.server_untyped(event.as_str(), "好友关系或游戏邀请通知");
+    }
+    for event in [
+        ResponseEvent::FriendRequestSent,
+        ResponseEvent::FriendRequestRevokedResponse,
+        ResponseEvent::FriendRequestAcceptedResponse,
+        ResponseEvent::FriendRequestRejectedResponse,
+        ResponseEvent::UnfriendedResponse,
+        ResponseEvent::GetSupplementalResponse,
+        ResponseEvent::SetInterimResponse,
+        ResponseEvent::AcceptMatchInviteResponse,
+        ResponseEvent::DeclineMatchInviteResponse,
+        ResponseEvent::GetNotificationPreferencesResponse,
+        ResponseEvent::SetNotificationPreferencesResponse,
+        ResponseEvent::GetRelationshipsResponse,
+        ResponseEvent::ListFriendRequestsResponse,
+    ] {
+        registry.server::<WsResponse>(event.as_str(), "对应客户端请求的处理结果");
+    }
+
+    // 聊天
+    registry
+        .client::<JoinChatRequest>(ChatEvents::JOIN_CHAT, "加入聊天室")
+        .client::<SendMessageRequest>(ChatEvents::SEND_MESSAGE, "发送聊天消息")
+        .server::<WsResponse>("chat:joined", "已加入聊天室")
+        .server::<WsResponse>("chat:message-sent", "消息已发送")
+        .server::<ChatNewMessageData>(ChatEvents::NEW_MESSAGE, "聊天室新消息")
+        .client::<SendDirectMessageRequest>(ChatEvents::SEND_DM, "给好友发送私信，双方必须是好友且没有互相阻止")
+        .client::<DirectHistoryRequest>(ChatEvents::DM_HISTORY, "分页查询与某个好友的私信历史，并清零该对话的未读数")
+        .client_without_data(ChatEvents::DM_UNREAD, "查询各对话的未读私信数")
+        .server::<WsResponse>(ChatEvents::DM_SENT, "私信发送结果，成功时包含消息")
+        .server::<WsResponse>(ChatEvents::DM_HISTORY, "私信历史，payload包含messages和has_more")
+        .server::<WsResponse>(ChatEvents::DM_UNREAD, "各对话的未读私信数，键为对方用户ID")
+        .server::<ChatNewDirectMessageData>(ChatEvents::NEW_DM, "收到私信，offline为true表示离线期间收到的消息")
+        .client::<TypingRequest>(ChatEvents::TYPING, "通知聊天室或私信对象正在输入，每个用户每秒最多转发一次")
+        .client::<ReadRequest>(ChatEvents::READ, "通知聊天室或私信对象已读到某条消息")
+        .server::<ChatTypingData>(ChatEvents::TYPING, "其他成员正在输入或停止输入")
+        .server::<ChatReadData>(ChatEvents::READ, "其他成员的已读回执");
+
+    // 组队
+    registry
+        .client_without_data(party::events::CREATE, "创建队伍，创建者成为队长")
+        .client::<PartyInviteDto>(party::events::INVITE, "邀请好友加入队伍")
+        .client::<PartyJoinDto>(party::events::JOIN, "接受邀请加入队伍")
+        .client_without_data(party::events::LEAVE, "离开队伍，队长离开时由下一名成员接任")
+        .server::<WsResponse>(party::events::UPDATED, "队伍成员或排队状态变化，payload为队伍信息；操作失败时ok为false")
+        .server::<WsResponse>(party::events::INVITED, "收到组队邀请，payload包含partyId和邀请者inviter");
+
+    // 对局与匹配队列
+    registry
+        .client::<CreateMatchData>(match_events::CREATE, "创建私人对局")
+        .client::<InviteMatchData>(match_events::INVITE, "邀请好友加入对局")
+        .client::<MatchIdData>(match_events::JOIN, "加入对局")
+        .client::<MatchIdData>(match_events::LEAVE, "离开对局")
+        .client::<MatchIdData>(match_events::STATE_SYNC, "请求完整的对局状态快照")
+        .client::<MatchIdData>(match_events::START, "开始对局")
+        .client::<MatchIdData>(match_events::DRAW_CARD, "抽牌")
+        .client::<PlayCardData>(match_events::PLAY_CARD, "出牌")
+        .client::<CanPlayData>(match_events::CAN_PLAY, "查询卡牌当前能否打出，与出牌使用相同的校验，不修改对局状态")
+        .client::<PauseMatchData>(match_events::PAUSE, "发起暂停或对暂停请求投票，暂停期间的抽牌和出牌会排队等待恢复")
+        .client::<MatchIdData>(match_events::RESUME, "恢复暂停的对局")
+        .client::<VoteSkipData>(match_events::VOTE_SKIP, "投票跳过长时间未操作的当前回合玩家")
+        .client::<SelectTargetData>(match_events::SELECT_TARGET, "出牌玩家从候选列表中选择卡牌效果的目标")
+        .client::<DiscardData>(match_events::DISCARD, "手牌超过上限的玩家选择弃掉的牌")
+        .client::<MatchIdData>(match_events::JOIN_SPECTATORS, "进入观战")
+        .client::<MatchIdData>(match_events::LEAVE_SPECTATORS, "退出观战")
+        .client::<QueueJoinData>("queue:join", "加入匹配队列，优先与同地区的玩家匹配，等待超过回退时间后跨地区匹配")
+        .client_without_data("queue:leave", "离开匹配队列")
+        .client_without_data("queue:status", "查询匹配队列状态")
+        .server::<WsResponse>("queue:status", "匹配队列状态，payload包含isEnqueued、enqueuedAt、中途退出惩罚状态penalty和封禁状态ban，重启后恢复排队的玩家restored为true；在队伍中时由队长带领全队加入")
+        .server::<WsResponse>("match:chain_start", "连锁开始，payload包含action和waitTime")
+        .server::<WsResponse>("match:chain_end", "连锁结束")
+        .server::<WsResponse>(match_events::PAUSE, "暂停状态变化，payload.status为requested、voting、rejected、expired、paused或action_queued")
+        .server::<WsResponse>(match_events::RESUME, "对局已恢复，payload包含resumedBy和auto")
+        .server::<WsResponse>(match_events::CAN_PLAY, "出牌校验结果，payload包含matchId和results，每项包含cardId、legal和不能打出时的reason")
+        .server::<WsResponse>(match_events::VOTE_SKIP, "跳过投票进度，payload包含targetId、votes、required和passed")
+        .server::<WsResponse>(match_events::SELECT_TARGET, "请求出牌玩家选择目标，payload包含pendingId、kind、candidates和deadline，超时后随机选择")
+        .server::<WsResponse>(match_events::DECK_EXHAUSTED, "牌堆抽完，payload包含rule（reshuffle或end_game）、reshuffled（洗回的张数）和deckCount；无法补充时随后结束游戏")
+        .server::<WsResponse>(match_events::DISCARD, "回合结束时手牌超过上限：发给该玩家的请求包含pendingId、count、maxHandSize和deadline，超时后随机弃牌；弃牌后广播userId、cards和auto")
+        .server::<WsResponse>(series::events::ROUND_END, "系列赛一局结束，payload包含seriesId、bestOf、round、matchId、winnerId、scores和下一局的nextMatchId")
+        .server::<WsResponse>(series::events::END, "系列赛结束，payload包含seriesId、bestOf、winnerId、scores、rounds和ratingChanges");
+    for event in [
+        match_events::CREATE,
+        match_events::INVITE,
+        match_events::JOIN,
+        match_events::LEAVE,
+        match_events::START,
+        match_events::END,
+        match_events::DRAW_CARD,
+        match_events::PLAY_CARD,
+        match_events::TURN_CHANGE,
+        match_events::DEFUSE,
+        match_events::DEFEAT,
+        match_events::VICTORY,
+        match_events::ALTER_FUTURE,
+        match_events::SPEED_UP_EXPLOSION,
+        match_events::BURY_CARD,
+        match_events::SHARE_FUTURE,
+        match_events::INSERT_IMPLODING_KITTEN,
+        match_events::JOIN_SPECTATORS,
+        match_events::LEAVE_SPECTATORS,
+        match_events::STATE,
+    ] {
+        registry.server::<WsResponse>(event, "对局事件，payload为对局数据");
+    }
+
+    // 成就
+    registry.server::<WsResponse>(achievement::events::UNLOCKED, "成就解锁，payload包含achievement和unlockedAt");
+
+    registry
+}
+
+/// 事件目录文档，启动后只生成一次
+static EVENT_CATALOG: Lazy<Value> = Lazy::new(|| build_catalog().to_json());
+
+/// 获取WebSocket事件目录
+#[utoipa::path(
+    get,
+    path = "/ws/schema",
+    tag = "ws",
+    responses((status = 200, description = "WebSocket事件目录，每个事件附带data字段的JSON Schema", body = Object))
+)]
+pub async fn handle_ws_schema() -> Json<Value> {
+    Json(EVENT_CATALOG.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_catalog_events_unique() {
+        let registry = build_catalog();
+        let mut seen = HashSet::new();
+        for event in registry.events() {
+            assert!(seen.insert((event.event, event.direction)), "重复登记的事件: {}", event.event);
+        }
+        assert!(seen.contains(&(protocol::events::HELLO, EventDirection::Client)));
+        assert!(seen.contains(&(match_events::PLAY_CARD, EventDirection::Server)));
+    }
+
+    #[test]
+    fn test_catalog_refs_resolve() {
+        let catalog = build_catalog().to_json();
+        let schemas = catalog["components"]["schemas"].as_object().unwrap();
+        let text = serde_json::to_string(&catalog).unwrap();
+        for reference in text.split("\"#/components/schemas/").skip(1) {
+            let name = reference.split('"').next().unwrap();
+            assert!(schemas.contains_key(name), "未定义的类型: {}", name);
+        }
+        let capability = catalog["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|e| e["event"] == match_events::STATE && e["direction"] == "server")
+            .map(|e| e["capability"].clone());
+        assert_eq!(capability, Some(json!(protocol::capabilities::MATCH_DELTA)));
+    }
+}
@@ -0,0 +1,174 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! WebSocket会话列表与强制断开
+//!
+//! # 概述
+//! `/ws/stats` 只有汇总数字，无法查看某个用户当前有哪些连接。本模块记录每个在线连接的建立时间和客户端IP，
+//! 结合 `PassportState.user_sessions` 的用户到客户端映射，以及 `ConnectionManager` 中的房间、最后操作时间和往返延迟，
+//! 按用户列出活跃会话，并允许管理员强制断开指定会话。
+//!
+//! 强制断开时服务端向客户端发送关闭帧（`1008`，原因 `disconnected by admin`）并结束连接，
+//! 之后按正常断线处理：用户离线、断开处理器执行，房间记录保留以便重连。
+//! 需要阻止重连时应同时使用 `ban` 模块封禁用户。
+//!
+//! # 接口
+//! 都需要在 `X-Admin-Key` 请求头中提供管理密钥：
+//! - `GET /ws/sessions`：按用户列出所有活跃会话
+//! - `GET /ws/sessions/:user_id`：列出指定用户的活跃会话
+//! - `DELETE /ws/sessions/:user_id/:client_id`：强制断开指定会话
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::Notify;
+use utoipa::ToSchema;
+
+/// 强制断开时发送的关闭原因
+pub const ADMIN_CLOSE_REASON: &str = "disconnected by admin";
+
+/// 单个连接的记录
+#[derive(Debug)]
+struct SessionEntry {
+    /// 连接序号，同一客户端ID重连时用于区分新旧连接
+    connection_id: usize,
+    connected_at: u64,
+    ip: Option<IpAddr>,
+    /// 通知连接的接收循环结束
+    kick: Arc<Notify>,
+}
+
+/// 在线连接的登记表
+#[derive(Debug, Default)]
+pub struct SessionRegistry {
+    sessions: Mutex<HashMap<String, SessionEntry>>,
+}
+
+impl SessionRegistry {
+    /**
+     * 登记新连接
+     *
+     * 参数:
+     * @param client_id - 客户端ID
+     * @param connection_id - 连接序号
+     * @param ip - 客户端IP
+     * @param now - 连接时间（毫秒）
+     *
+     * 返回:
+     * 强制断开时收到通知的句柄
+     */
+    pub fn register(&self, client_id: &str, connection_id: usize, ip: Option<IpAddr>, now: u64) -> Arc<Notify> {
+        let kick = Arc::new(Notify::new());
+        self.sessions.lock().insert(
+            client_id.to_string(),
+            SessionEntry {
+                connection_id,
+                connected_at: now,
+                ip,
+                kick: kick.clone(),
+            },
+        );
+        kick
+    }
+
+    /// 连接关闭时移除记录，已被同一客户端ID的新连接替换时保留
+    pub fn unregister(&self, client_id: &str, connection_id: usize) {
+        let mut sessions = self.sessions.lock();
+        if sessions.get(client_id).map(|s| s.connection_id) == Some(connection_id) {
+            sessions.remove(client_id);
+        }
+    }
+
+    /// 连接时间和客户端IP，连接不存在时返回None
+    pub fn get(&self, client_id: &str) -> Option<(u64, Option<IpAddr>)> {
+        self.sessions.lock().get(client_id).map(|s| (s.connected_at, s.ip))
+    }
+
+    /// 通知连接结束，连接不存在时返回false
+    pub fn kick(&self, client_id: &str) -> bool {
+        match self.sessions.lock().get(client_id) {
+            Some(session) => {
+                session.kick.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 在线连接数
+    pub fn len(&self) -> usize {
+        self.sessions.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// 单个会话的信息
+#[derive(Debug, Clone, Serialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub client_id: String,
+    /// 连接时间（毫秒）
+    pub connected_at: u64,
+    /// 客户端IP
+    #[schema(value_type = Option<String>)]
+    pub ip: Option<IpAddr>,
+    /// 所在房间，不包括全服广播房间
+    pub rooms: Vec<String>,
+    /// 最后一次操作时间（毫秒）
+    pub last_activity: Option<u64>,
+    /// 最近一次往返延迟（毫秒）
+    pub rtt_ms: Option<u64>,
+}
+
+/// 用户的活跃会话
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSessions {
+    pub user_id: String,
+    pub sessions: Vec<SessionInfo>,
+}
+
+/// 会话列表响应
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SessionListResponse {
+    pub users: Vec<UserSessions>,
+}
+
+/// 强制断开响应
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DisconnectResponse {
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_registry() {
+        let registry = SessionRegistry::default();
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let old = registry.register("c1", 1, Some(ip), 100);
+        assert_eq!(registry.get("c1"), Some((100, Some(ip))));
+
+        // 同一客户端ID重连后，旧连接关闭不影响新连接的记录
+        let new = registry.register("c1", 2, None, 200);
+        registry.unregister("c1", 1);
+        assert_eq!(registry.get("c1"), Some((200, None)));
+
+        assert!(registry.kick("c1"));
+        new.notified().await;
+        assert!(!registry.kick("c2"));
+        drop(old);
+
+        registry.unregister("c1", 2);
+        assert!(registry.is_empty());
+    }
+}
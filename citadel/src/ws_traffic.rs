@@ -0,0 +1,233 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! WebSocket流量分类统计
+//!
+//! # 概述
+//! `ConnectionStats` 只有消息总数，看不出哪些事件和房间占用了流量。本模块按事件名称和房间分别统计：
+//! - 每个事件发送和接收的消息数
+//! - 每个房间的广播次数、送达的消息数，以及广播耗时的p50/p95
+//! - 所有广播耗时的p50/p95
+//!
+//! 耗时百分位基于最近 `LATENCY_SAMPLES` 次广播计算。事件名称来自客户端，房间随对局不断创建，
+//! 为避免统计无限增长，分别最多记录 `MAX_TRACKED_EVENTS` 个事件和 `MAX_TRACKED_ROOMS` 个房间，
+//! 超出的计入 `OTHER_KEY`。统计结果通过 `/ws/stats`（需要管理密钥）查看。
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// 最多记录的事件数量
+pub const MAX_TRACKED_EVENTS: usize = 256;
+/// 最多记录的房间数量
+pub const MAX_TRACKED_ROOMS: usize = 512;
+/// 计算耗时百分位时保留的样本数量
+pub const LATENCY_SAMPLES: usize = 256;
+/// 超出记录上限的事件和房间使用的统计键
+pub const OTHER_KEY: &str = "other";
+
+/// 最近若干次耗时（微秒）
+#[derive(Debug, Default)]
+struct LatencyWindow {
+    samples: VecDeque<u64>,
+}
+
+impl LatencyWindow {
+    fn record(&mut self, elapsed: Duration) {
+        if self.samples.len() == LATENCY_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(elapsed.as_micros() as u64);
+    }
+
+    /// 第p百分位（0~100）的耗时，单位毫秒，没有样本时为0
+    fn percentile_ms(&self, p: usize) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        let mut sorted: Vec<u64> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let index = (sorted.len() * p).div_ceil(100).saturating_sub(1);
+        sorted[index.min(sorted.len() - 1)] as f64 / 1000.0
+    }
+
+    fn snapshot(&self) -> LatencySnapshot {
+        LatencySnapshot {
+            p50_ms: self.percentile_ms(50),
+            p95_ms: self.percentile_ms(95),
+            samples: self.samples.len(),
+        }
+    }
+}
+
+/// 广播耗时
+#[derive(Debug, Default, Clone, PartialEq, Serialize, ToSchema)]
+pub struct LatencySnapshot {
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    /// 参与计算的样本数
+    pub samples: usize,
+}
+
+/// 单个事件的流量
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct EventTraffic {
+    /// 发送的消息数（广播按收到的客户端计数）
+    pub sent: u64,
+    /// 从客户端接收的消息数
+    pub received: u64,
+}
+
+/// 单个房间的流量
+#[derive(Debug, Default, Clone, PartialEq, Serialize, ToSchema)]
+pub struct RoomTraffic {
+    /// 广播次数
+    pub broadcasts: u64,
+    /// 广播送达的消息数
+    pub messages_sent: u64,
+    /// 广播耗时
+    pub latency: LatencySnapshot,
+}
+
+/// 流量分类统计结果
+#[derive(Debug, Default, Clone, PartialEq, Serialize, ToSchema)]
+pub struct TrafficSnapshot {
+    /// 按事件名称统计
+    pub events: BTreeMap<String, EventTraffic>,
+    /// 按房间统计
+    pub rooms: BTreeMap<String, RoomTraffic>,
+    /// 所有广播的耗时
+    pub broadcast_latency: LatencySnapshot,
+}
+
+#[derive(Debug, Default)]
+struct RoomState {
+    broadcasts: u64,
+    messages_sent: u64,
+    latency: LatencyWindow,
+}
+
+#[derive(Debug, Default)]
+struct TrafficState {
+    events: HashMap<String, EventTraffic>,
+    rooms: HashMap<String, RoomState>,
+    latency: LatencyWindow,
+}
+
+/// 取得统计条目，超出数量上限的新键计入 `OTHER_KEY`
+fn entry<'a, V: Default>(map: &'a mut HashMap<String, V>, key: &str, limit: usize) -> &'a mut V {
+    let key = if map.contains_key(key) || map.len() < limit {
+        key
+    } else {
+        OTHER_KEY
+    };
+    map.entry(key.to_string()).or_default()
+}
+
+/// 按事件和房间的流量统计
+#[derive(Debug, Default)]
+pub struct TrafficStats {
+    state: Mutex<TrafficState>,
+}
+
+impl TrafficStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录从客户端接收的消息
+    pub fn record_received(&self, event: &str) {
+        let mut state = self.state.lock();
+        entry(&mut state.events, event, MAX_TRACKED_EVENTS).received += 1;
+    }
+
+    /// 记录发送给单个客户端的消息
+    pub fn record_sent(&self, event: &str) {
+        let mut state = self.state.lock();
+        entry(&mut state.events, event, MAX_TRACKED_EVENTS).sent += 1;
+    }
+
+    /**
+     * 记录一次房间广播
+     *
+     * 参数:
+     * @param room_id - 房间ID
+     * @param event - 事件名称
+     * @param recipients - 收到消息的客户端数量
+     * @param elapsed - 广播耗时
+     */
+    pub fn record_broadcast(&self, room_id: &str, event: &str, recipients: usize, elapsed: Duration) {
+        let mut state = self.state.lock();
+        entry(&mut state.events, event, MAX_TRACKED_EVENTS).sent += recipients as u64;
+        let room = entry(&mut state.rooms, room_id, MAX_TRACKED_ROOMS);
+        room.broadcasts += 1;
+        room.messages_sent += recipients as u64;
+        room.latency.record(elapsed);
+        state.latency.record(elapsed);
+    }
+
+    /// 当前的统计结果
+    pub fn snapshot(&self) -> TrafficSnapshot {
+        let state = self.state.lock();
+        TrafficSnapshot {
+            events: state.events.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            rooms: state
+                .rooms
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        k.clone(),
+                        RoomTraffic {
+                            broadcasts: v.broadcasts,
+                            messages_sent: v.messages_sent,
+                            latency: v.latency.snapshot(),
+                        },
+                    )
+                })
+                .collect(),
+            broadcast_latency: state.latency.snapshot(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakdown_and_percentiles() {
+        let stats = TrafficStats::new();
+        stats.record_received("game:play");
+        stats.record_sent("game:state");
+        for ms in 1..=100 {
+            stats.record_broadcast("room-1", "game:state", 2, Duration::from_millis(ms));
+        }
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.events["game:play"].received, 1);
+        assert_eq!(snapshot.events["game:state"].sent, 201);
+        let room = &snapshot.rooms["room-1"];
+        assert_eq!(room.broadcasts, 100);
+        assert_eq!(room.messages_sent, 200);
+        assert_eq!(room.latency.p50_ms, 50.0);
+        assert_eq!(room.latency.p95_ms, 95.0);
+        assert_eq!(snapshot.broadcast_latency.samples, 100);
+    }
+
+    #[test]
+    fn test_tracked_keys_are_capped() {
+        let stats = TrafficStats::new();
+        for i in 0..MAX_TRACKED_EVENTS + 10 {
+            stats.record_received(&format!("event-{}", i));
+        }
+        stats.record_received("event-0");
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.events.len(), MAX_TRACKED_EVENTS + 1);
+        assert_eq!(snapshot.events[OTHER_KEY].received, 10);
+        assert_eq!(snapshot.events["event-0"].received, 2);
+    }
+}